@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{Method, RequestBuilder};
+use serde_json::Value;
+
+/// Client for the REST API of a Spacedrive server.
+pub struct Client {
+	http: reqwest::Client,
+	base_url: String,
+	token: String,
+}
+
+impl Client {
+	pub fn new(url: &str, token: String) -> Self {
+		Self {
+			http: reqwest::Client::new(),
+			base_url: format!("{}/api/v1", url.trim_end_matches('/')),
+			token,
+		}
+	}
+
+	pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+		self.http
+			.request(method, format!("{}{path}", self.base_url))
+			.bearer_auth(&self.token)
+	}
+
+	pub async fn send(&self, request: RequestBuilder) -> Result<Value> {
+		let response = request
+			.send()
+			.await
+			.context("failed to reach the Spacedrive server")?;
+
+		let status = response.status();
+		let body = response
+			.json::<Value>()
+			.await
+			.context("invalid response from the Spacedrive server")?;
+
+		if !status.is_success() {
+			bail!(
+				"request failed with status {status}: {}",
+				body.get("error")
+					.and_then(Value::as_str)
+					.unwrap_or("unknown error")
+			);
+		}
+
+		Ok(body)
+	}
+
+	pub async fn get(&self, path: &str) -> Result<Value> {
+		self.send(self.request(Method::GET, path)).await
+	}
+
+	pub async fn post(&self, path: &str, body: Option<Value>) -> Result<Value> {
+		let request = self.request(Method::POST, path);
+
+		self.send(match body {
+			Some(body) => request.json(&body),
+			None => request,
+		})
+		.await
+	}
+
+	pub async fn delete(&self, path: &str) -> Result<Value> {
+		self.send(self.request(Method::DELETE, path)).await
+	}
+}
+
+/// The data of every item of a normalised result, as returned by list and search endpoints.
+pub fn nodes(value: &Value) -> impl Iterator<Item = &Value> {
+	value
+		.get("nodes")
+		.and_then(Value::as_array)
+		.into_iter()
+		.flatten()
+}
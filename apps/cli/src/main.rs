@@ -1,85 +1,325 @@
-use anyhow::Result;
-// use clap::Parser;
-// use indoc::printdoc;
-// use sd_crypto::header::file::FileHeader;
-// use std::path::PathBuf;
-// use tokio::fs::File;
-
-// #[derive(Parser)]
-// struct Args {
-// 	#[arg(help = "the file path to get details for")]
-// 	path: PathBuf,
-// }
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+
+use self::client::{nodes, Client};
+
+mod client;
+
+/// Drives a Spacedrive server from the command line, through its REST API.
+#[derive(Parser)]
+#[command(name = "sd-cli", version)]
+struct Args {
+	/// URL of the Spacedrive server
+	#[arg(long, env = "SD_URL", default_value = "http://localhost:8080")]
+	url: String,
+	/// One of the tokens set in the `SD_API_TOKENS` of the server
+	#[arg(long, env = "SD_API_TOKEN", hide_env_values = true)]
+	token: String,
+	/// Library to act on, only required if the server has more than one
+	#[arg(long, short, env = "SD_LIBRARY")]
+	library: Option<String>,
+	/// Print the JSON responses of the server as they are
+	#[arg(long, global = true)]
+	json: bool,
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// List the libraries of the server
+	Libraries,
+	/// Manage the locations of the library
+	#[command(subcommand)]
+	Locations(LocationsCommand),
+	/// Search the library for files by name
+	Search {
+		query: String,
+		#[arg(long, default_value_t = 50)]
+		take: u8,
+	},
+	/// Manage the jobs of the library
+	#[command(subcommand)]
+	Jobs(JobsCommand),
+	/// Check the sync of the library
+	#[command(subcommand)]
+	Sync(SyncCommand),
+}
+
+#[derive(Subcommand)]
+enum LocationsCommand {
+	List,
+	/// Add a directory of the server as a location, and start indexing it
+	Add {
+		path: PathBuf,
+		/// Id of an indexer rule to apply, can be repeated
+		#[arg(long = "indexer-rule")]
+		indexer_rules_ids: Vec<i32>,
+	},
+	Remove {
+		id: i32,
+	},
+	Rescan {
+		id: i32,
+		/// Identify the files of the location again
+		#[arg(long)]
+		reidentify: bool,
+	},
+}
+
+#[derive(Subcommand)]
+enum JobsCommand {
+	List,
+	/// Start a job on a location
+	Start {
+		job: JobKind,
+		location_id: i32,
+		/// Sub path of the location to run the job on
+		#[arg(long, default_value = "")]
+		path: String,
+		/// Generate thumbnails or labels that already exist again
+		#[arg(long)]
+		regenerate: bool,
+	},
+	Pause {
+		id: String,
+	},
+	Resume {
+		id: String,
+	},
+	Cancel {
+		id: String,
+	},
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum JobKind {
+	Thumbnails,
+	Labels,
+	Identify,
+	Validate,
+}
+
+impl JobKind {
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Thumbnails => "thumbnails",
+			Self::Labels => "labels",
+			Self::Identify => "identify",
+			Self::Validate => "validate",
+		}
+	}
+}
+
+#[derive(Subcommand)]
+enum SyncCommand {
+	Status,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-	// let args = Args::parse();
+	let args = Args::parse();
+	let client = Client::new(&args.url, args.token);
 
-	// let mut reader = File::open(args.path).await.context("unable to open file")?;
-	// let (header, aad) = FileHeader::from_reader(&mut reader).await?;
-	// print_crypto_details(&header, &aad);
+	if let Command::Libraries = args.command {
+		let libraries = client.get("/libraries").await?;
 
-	Ok(())
+		if args.json {
+			return print_json(&libraries);
+		}
+
+		for library in nodes(&libraries) {
+			println!(
+				"{}\t{}",
+				str_field(library, "/uuid"),
+				str_field(library, "/config/name")
+			);
+		}
+
+		return Ok(());
+	}
+
+	let library = format!(
+		"/libraries/{}",
+		match args.library {
+			Some(library) => library,
+			None => default_library(&client).await?,
+		}
+	);
+
+	let (output, print): (_, fn(&Value)) = match args.command {
+		Command::Libraries => unreachable!("handled above"),
+
+		Command::Locations(LocationsCommand::List) => (
+			client.get(&format!("{library}/locations")).await?,
+			|locations| {
+				for location in nodes(locations) {
+					println!(
+						"{}\t{}\t{}",
+						location["id"],
+						str_field(location, "/name"),
+						str_field(location, "/path")
+					);
+				}
+			},
+		),
+		Command::Locations(LocationsCommand::Add {
+			path,
+			indexer_rules_ids,
+		}) => (
+			client
+				.post(
+					&format!("{library}/locations"),
+					Some(json!({
+						"path": path,
+						"dry_run": false,
+						"indexer_rules_ids": indexer_rules_ids,
+					})),
+				)
+				.await?,
+			|id| match id.as_i64() {
+				Some(id) => println!("Added location {id}, indexing started"),
+				None => println!("Location already exists"),
+			},
+		),
+		Command::Locations(LocationsCommand::Remove { id }) => (
+			client.delete(&format!("{library}/locations/{id}")).await?,
+			|_| println!("Location removed"),
+		),
+		Command::Locations(LocationsCommand::Rescan { id, reidentify }) => (
+			client
+				.post(
+					&format!("{library}/locations/{id}/rescan?reidentify_objects={reidentify}"),
+					None,
+				)
+				.await?,
+			|_| println!("Rescan started"),
+		),
+
+		Command::Search { query, take } => (
+			client
+				.post(
+					&format!("{library}/search/paths"),
+					Some(json!({
+						"take": take,
+						"filters": [{ "filePath": { "name": { "contains": query } } }],
+					})),
+				)
+				.await?,
+			|results| {
+				for result in nodes(results) {
+					let item = &result["item"];
+					let extension = str_field(item, "/extension");
+
+					println!(
+						"{}\t{}{}{}{extension}",
+						item["id"],
+						str_field(item, "/materialized_path"),
+						str_field(item, "/name"),
+						if extension.is_empty() { "" } else { "." },
+					);
+				}
+			},
+		),
+
+		Command::Jobs(JobsCommand::List) => {
+			(client.get(&format!("{library}/jobs")).await?, |groups| {
+				for job in groups
+					.as_array()
+					.into_iter()
+					.flatten()
+					.flat_map(|group| group["jobs"].as_array().into_iter().flatten())
+				{
+					println!(
+						"{}\t{}\t{}\t{}/{}",
+						str_field(job, "/id"),
+						str_field(job, "/name"),
+						str_field(job, "/status"),
+						job["completed_task_count"],
+						job["task_count"]
+					);
+				}
+			})
+		}
+		Command::Jobs(JobsCommand::Start {
+			job,
+			location_id,
+			path,
+			regenerate,
+		}) => {
+			let mut request = client.request(
+				reqwest::Method::POST,
+				&format!("{library}/locations/{location_id}/jobs/{}", job.as_str()),
+			);
+			request = request.query(&[("path", path.as_str())]);
+			if regenerate {
+				request = request.query(&[("regenerate", "true")]);
+			}
+
+			(client.send(request).await?, |_| println!("Job started"))
+		}
+		Command::Jobs(JobsCommand::Pause { id }) => (
+			client
+				.post(&format!("{library}/jobs/{id}/pause"), None)
+				.await?,
+			|_| println!("Job paused"),
+		),
+		Command::Jobs(JobsCommand::Resume { id }) => (
+			client
+				.post(&format!("{library}/jobs/{id}/resume"), None)
+				.await?,
+			|_| println!("Job resumed"),
+		),
+		Command::Jobs(JobsCommand::Cancel { id }) => (
+			client.delete(&format!("{library}/jobs/{id}")).await?,
+			|_| println!("Job canceled"),
+		),
+
+		Command::Sync(SyncCommand::Status) => {
+			let mut status = client.get(&format!("{library}/sync")).await?;
+			status["enabled"] = client.get(&format!("{library}/sync/enabled")).await?;
+
+			(status, |status| {
+				for (name, value) in status.as_object().into_iter().flatten() {
+					println!("{name}: {value}");
+				}
+			})
+		}
+	};
+
+	if args.json {
+		print_json(&output)
+	} else {
+		print(&output);
+		Ok(())
+	}
+}
+
+/// The only library of the server, as most headless nodes have a single one.
+async fn default_library(client: &Client) -> Result<String> {
+	let libraries = client.get("/libraries").await?;
+	let mut ids = nodes(&libraries).map(|library| str_field(library, "/uuid"));
+
+	match (ids.next(), ids.next()) {
+		(Some(id), None) => Ok(id.to_string()),
+		(None, _) => bail!("the server has no library"),
+		(Some(_), Some(_)) => {
+			bail!("the server has multiple libraries, pick one with `--library`")
+		}
+	}
 }
 
-// fn print_crypto_details(header: &FileHeader, aad: &[u8]) {
-// 	printdoc! {"
-//         Header version: {version}
-//         Encryption algorithm: {algorithm}
-//         AAD (hex): {hex}
-//     ",
-// 		version = header.version,
-// 		algorithm = header.algorithm,
-// 		hex = hex::encode(aad)
-// 	};
-
-// 	header.keyslots.iter().enumerate().for_each(|(i, k)| {
-// 		printdoc! {"
-//             Keyslot {index}:
-//               Version: {version}
-//               Algorithm: {algorithm}
-//               Hashing algorithm: {hashing_algorithm}
-//               Salt (hex): {salt}
-//               Master Key (hex, encrypted): {master}
-//               Master key nonce (hex): {nonce}
-//         ",
-// 			index = i + i,
-// 			version = k.version,
-// 			algorithm = k.algorithm,
-// 			hashing_algorithm = k.hashing_algorithm,
-// 			salt = hex::encode(&*k.salt),
-// 			master = hex::encode(&*k.master_key),
-// 			nonce = hex::encode(k.nonce)
-// 		};
-// 	});
-
-// 	header.metadata.iter().for_each(|m| {
-// 		printdoc! {"
-//             Metadata:
-//               Version: {version}
-//               Algorithm: {algorithm}
-//               Encrypted size: {size}
-//               Nonce (hex): {nonce}
-//         ",
-// 			version = m.version,
-// 			algorithm = m.algorithm,
-// 			size = m.metadata.len(),
-// 			nonce = hex::encode(m.metadata_nonce)
-// 		}
-// 	});
-
-// 	header.preview_media.iter().for_each(|p| {
-// 		printdoc! {"
-//             Preview Media:
-//               Version: {version}
-//               Algorithm: {algorithm}
-//               Encrypted size: {size}
-//               Nonce (hex): {nonce}
-//         ",
-// 			version = p.version,
-// 			algorithm = p.algorithm,
-// 			size = p.media.len(),
-// 			nonce = hex::encode(p.media_nonce)
-// 		};
-// 	});
-// }
+fn str_field<'a>(value: &'a Value, pointer: &str) -> &'a str {
+	value
+		.pointer(pointer)
+		.and_then(Value::as_str)
+		.unwrap_or_default()
+}
+
+fn print_json(value: &Value) -> Result<()> {
+	println!("{}", serde_json::to_string_pretty(value)?);
+	Ok(())
+}
@@ -21,7 +21,7 @@ type ExplorerProps = {
 	items: ExplorerItem[] | null;
 	/** Function to fetch next page of items. */
 	loadMore: () => void;
-	query: UseInfiniteQueryResult<SearchData<ExplorerItem>>;
+	query: UseInfiniteQueryResult<SearchData<ExplorerItem, unknown>>;
 	count?: number;
 };
 
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, env, net::SocketAddr, path::Path};
+use std::{collections::HashMap, env, net::SocketAddr, path::Path, sync::Arc};
 
 use axum::{
 	extract::{FromRequestParts, State},
@@ -9,7 +9,7 @@ use axum::{
 	routing::get,
 	TypedHeader,
 };
-use sd_core::{custom_uri, Node};
+use sd_core::{custom_uri, rest_api, Node};
 use secstr::SecStr;
 use tracing::{info, warn};
 
@@ -22,6 +22,7 @@ static ASSETS_DIR: include_dir::Dir<'static> =
 #[derive(Clone)]
 pub struct AppState {
 	auth: HashMap<String, SecStr>,
+	node: Arc<Node>,
 }
 
 async fn basic_auth<B>(
@@ -29,6 +30,16 @@ async fn basic_auth<B>(
 	request: Request<B>,
 	next: Next<B>,
 ) -> Response {
+	// API tokens are an alternative to the credentials, limited to their scopes and rate limit
+	if let Some(token) = rest_api::bearer_token(&request) {
+		let scope = rest_api::gateway_scope(&request);
+
+		return match rest_api::authorize(&state.node, token, scope).await {
+			Ok(()) => next.run(request).await,
+			Err(response) => response,
+		};
+	}
+
 	let (mut parts, body) = request.into_parts();
 	let Ok(TypedHeader(Authorization(hdr))) =
 		TypedHeader::<Authorization<Basic>>::from_request_parts(&mut parts, &()).await
@@ -138,8 +149,6 @@ async fn main() {
 		}
 	}
 
-	let state = AppState { auth };
-
 	let (node, router) = match Node::new(
 		data_dir,
 		sd_core::Env {
@@ -160,11 +169,59 @@ async fn main() {
 	};
 	let signal = utils::axum_shutdown_signal(node.clone());
 
+	let state = AppState {
+		auth,
+		node: node.clone(),
+	};
+
+	// The libraries stay mounted until the session is dropped, when the server stops
+	#[cfg(all(feature = "fuse", unix))]
+	let _fuse_session = env::var("SD_FUSE_MOUNT").ok().and_then(|mountpoint| {
+		sd_core::virtual_fs::fuse::mount(node.clone(), &mountpoint)
+			.map_err(|e| warn!("Failed to mount the libraries at '{mountpoint}': {e:#?}"))
+			.ok()
+	});
+
+	// The REST API has its own auth so it's not behind the basic auth, it accepts the API tokens of the node
+	// and the ones in `SD_API_TOKENS`
+	let rest_api = rest_api::router(
+		node.clone(),
+		router.clone(),
+		env::var("SD_API_TOKENS")
+			.unwrap_or_default()
+			.split(',')
+			.filter(|token| !token.is_empty())
+			.map(ToString::to_string)
+			.collect::<Vec<_>>(),
+	);
+
+	// WebDAV clients sign in with the same credentials as the web app, through the basic auth
+	let webdav = env::var("SD_WEBDAV")
+		.is_ok_and(|webdav| webdav == "enabled")
+		.then(|| sd_core::webdav::router(node.clone()));
+
 	let app = axum::Router::new()
 		.route("/health", get(|| async { "OK" }))
+		.route(
+			"/metrics",
+			get({
+				let node = node.clone();
+				move || async move {
+					(
+						[(http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+						sd_core::metrics::render(&node).await,
+					)
+				}
+			}),
+		)
 		.nest("/spacedrive", custom_uri::router(node.clone()))
 		.nest("/rspc", router.endpoint(move || node.clone()).axum());
 
+	let app = match webdav {
+		Some(webdav) => app.nest("/webdav", webdav),
+		None => app,
+	};
+
 	#[cfg(feature = "assets")]
 	let app = app
 		.route(
@@ -237,8 +294,13 @@ async fn main() {
 	#[cfg(not(feature = "assets"))]
 	let app = app
 		.route("/", get(|| async { "Spacedrive Server!" }))
-		.fallback(|| async { "404 Not Found: We're past the event horizon..." })
-		.layer(middleware::from_fn_with_state(state, basic_auth));
+		.fallback(|| async { "404 Not Found: We're past the event horizon..." });
+
+	// Everything but the REST API is behind the basic auth, with or without the web app, including
+	// the WebDAV server and the metrics
+	let app = app
+		.layer(middleware::from_fn_with_state(state, basic_auth))
+		.nest("/api/v1", rest_api);
 
 	let mut addr = "[::]:8080".parse::<SocketAddr>().unwrap(); // This listens on IPv6 and IPv4
 	addr.set_port(port);
@@ -12,7 +12,7 @@ use crate::{
 };
 
 use sd_core_file_path_helper::IsolatedFilePathData;
-use sd_core_indexer_rules::{IndexerRule, IndexerRuler};
+use sd_core_indexer_rules::{cache, IndexerRuler};
 use sd_core_prisma_helpers::location_with_indexer_rules;
 
 use sd_task_system::{
@@ -244,12 +244,11 @@ impl IndexerJob {
 		sub_path: Option<PathBuf>,
 	) -> Result<Self, IndexerError> {
 		Ok(Self {
-			indexer_ruler: location
-				.indexer_rules
-				.iter()
-				.map(|rule| IndexerRule::try_from(&rule.indexer_rule))
-				.collect::<Result<Vec<_>, _>>()
-				.map(IndexerRuler::new)?,
+			indexer_ruler: cache::location_rules(
+				&location.pub_id,
+				location.indexer_rules.iter().map(|rule| &rule.indexer_rule),
+			)
+			.map(IndexerRuler::new)?,
 			iso_file_path_factory: IsoFilePathFactory {
 				location_id: location.id,
 				location_path: maybe_missing(&location.path, "location.path")
@@ -1,6 +1,6 @@
 use crate::{Error, NonCriticalJobError};
 
-use sd_core_indexer_rules::{IndexerRule, IndexerRuler};
+use sd_core_indexer_rules::{cache, IndexerRuler};
 use sd_core_prisma_helpers::location_with_indexer_rules;
 use sd_core_sync::Manager as SyncManager;
 
@@ -127,13 +127,12 @@ async fn walk(
 		.dispatch(WalkDirTask::new(
 			ToWalkEntry::from(&*to_walk_path),
 			to_walk_path,
-			location
-				.indexer_rules
-				.iter()
-				.map(|rule| IndexerRule::try_from(&rule.indexer_rule))
-				.collect::<Result<Vec<_>, _>>()
-				.map(IndexerRuler::new)
-				.map_err(IndexerError::from)?,
+			cache::location_rules(
+				&location.pub_id,
+				location.indexer_rules.iter().map(|rule| &rule.indexer_rule),
+			)
+			.map(IndexerRuler::new)
+			.map_err(IndexerError::from)?,
 			IsoFilePathFactory {
 				location_id: location.id,
 				location_path,
@@ -4,7 +4,9 @@ use crate::{
 };
 
 use sd_core_file_path_helper::{FilePathError, FilePathMetadata, IsolatedFilePathData};
-use sd_core_indexer_rules::{IndexerRuler, MetadataForIndexerRules, RuleKind};
+use sd_core_indexer_rules::{
+	rejected_by_entry_rules, IndexerRuler, MetadataForIndexerRules, RuleKind,
+};
 use sd_core_prisma_helpers::{file_path_pub_and_cas_ids, file_path_walker};
 
 use sd_prisma::prisma::file_path;
@@ -186,6 +188,14 @@ impl MetadataForIndexerRules for InnerMetadata {
 	fn is_dir(&self) -> bool {
 		self.is_dir
 	}
+
+	fn size_in_bytes(&self) -> u64 {
+		self.size_in_bytes
+	}
+
+	fn modified_at(&self) -> Option<DateTime<Utc>> {
+		Some(self.modified_at)
+	}
 }
 
 impl From<InnerMetadata> for FilePathMetadata {
@@ -851,6 +861,15 @@ async fn process_rules_results(
 				return (accepted, accepted_ancestors);
 			}
 
+			if rejected_by_entry_rules(&acceptance_per_rule_kind) {
+				trace!(
+					"Path {} rejected by its extension, size, age or a .gitignore file",
+					current_path.display()
+				);
+
+				return (accepted, accepted_ancestors);
+			}
+
 			let is_dir = metadata.is_dir();
 
 			if is_dir
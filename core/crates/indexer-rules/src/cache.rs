@@ -0,0 +1,186 @@
+//! Compiled indexer rules, so walking a location again doesn't compile the globs of its rules
+//! again.
+//!
+//! The rules of a location are compiled again once any of them is added, removed or modified.
+
+use crate::{
+	seed::{no_hidden, no_os_protected, no_sd_ignored},
+	IndexerRule, IndexerRuleError,
+};
+
+use sd_prisma::prisma::indexer_rule;
+use sd_utils::chain_optional_iter;
+
+use std::{
+	collections::HashMap,
+	convert::Infallible,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use chrono::{DateTime, FixedOffset};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use specta::Type;
+
+static CACHE: Lazy<Mutex<RulesCache>> = Lazy::new(Mutex::default);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RuleSet {
+	/// The rules of the location with this pub id
+	Location(Vec<u8>),
+	/// The system rules applied to paths listed outside of locations
+	Ephemeral {
+		with_hidden_files: bool,
+		is_local: bool,
+	},
+}
+
+/// The ids of the rules of a set along with their last modification
+type RulesVersion = Vec<(indexer_rule::id::Type, Option<DateTime<FixedOffset>>)>;
+
+#[derive(Debug, Default)]
+struct RulesCache {
+	rule_sets: HashMap<RuleSet, (RulesVersion, Vec<IndexerRule>)>,
+	hits: u32,
+	misses: u32,
+	compile_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+pub struct IndexerRulesCacheStats {
+	/// Rule sets reused without compiling them
+	pub hits: u32,
+	/// Rule sets compiled, as they weren't cached yet or their rules changed
+	pub misses: u32,
+	pub cached_rule_sets: u32,
+	/// Time spent compiling rule sets since the node started
+	pub compile_time_ms: u32,
+}
+
+impl RulesCache {
+	fn get_or_compile<E>(
+		&mut self,
+		rule_set: RuleSet,
+		version: RulesVersion,
+		compile: impl FnOnce() -> Result<Vec<IndexerRule>, E>,
+	) -> Result<Vec<IndexerRule>, E> {
+		if let Some((cached_version, rules)) = self.rule_sets.get(&rule_set) {
+			if *cached_version == version {
+				self.hits = self.hits.saturating_add(1);
+				return Ok(rules.clone());
+			}
+		}
+
+		let start = Instant::now();
+		let rules = compile()?;
+		self.compile_time += start.elapsed();
+		self.misses = self.misses.saturating_add(1);
+
+		self.rule_sets.insert(rule_set, (version, rules.clone()));
+
+		Ok(rules)
+	}
+
+	fn stats(&self) -> IndexerRulesCacheStats {
+		IndexerRulesCacheStats {
+			hits: self.hits,
+			misses: self.misses,
+			cached_rule_sets: u32::try_from(self.rule_sets.len()).unwrap_or(u32::MAX),
+			compile_time_ms: u32::try_from(self.compile_time.as_millis()).unwrap_or(u32::MAX),
+		}
+	}
+}
+
+/// Returns the compiled `rules` of the location with `location_pub_id`, only compiling them if
+/// they changed since they were last compiled, along with the rule for the `.sdignore` files.
+#[allow(clippy::missing_panics_doc)]
+pub fn location_rules<'rule>(
+	location_pub_id: &[u8],
+	rules: impl IntoIterator<Item = &'rule indexer_rule::Data>,
+) -> Result<Vec<IndexerRule>, IndexerRuleError> {
+	let rules = rules.into_iter().collect::<Vec<_>>();
+
+	CACHE
+		.lock()
+		.expect("indexer rules cache lock poisoned")
+		.get_or_compile(
+			RuleSet::Location(location_pub_id.to_vec()),
+			rules
+				.iter()
+				.map(|rule| (rule.id, rule.date_modified))
+				.collect(),
+			|| {
+				rules
+					.into_iter()
+					.map(IndexerRule::try_from)
+					.chain([Ok(IndexerRule::from(no_sd_ignored()))])
+					.collect()
+			},
+		)
+}
+
+/// Returns the compiled system rules applied to paths listed outside of locations.
+///
+/// The `.sdignore` files can only be read from the paths of this device, so they're only honored
+/// when `is_local`.
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+pub fn ephemeral_rules(with_hidden_files: bool, is_local: bool) -> Vec<IndexerRule> {
+	CACHE
+		.lock()
+		.expect("indexer rules cache lock poisoned")
+		.get_or_compile(
+			RuleSet::Ephemeral {
+				with_hidden_files,
+				is_local,
+			},
+			vec![],
+			|| {
+				Ok(chain_optional_iter(
+					[IndexerRule::from(no_os_protected())],
+					[
+						(!with_hidden_files).then(|| IndexerRule::from(no_hidden())),
+						is_local.then(|| IndexerRule::from(no_sd_ignored())),
+					],
+				))
+			},
+		)
+		.unwrap_or_else(|never: Infallible| match never {})
+}
+
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+pub fn stats() -> IndexerRulesCacheStats {
+	CACHE
+		.lock()
+		.expect("indexer rules cache lock poisoned")
+		.stats()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compiles_rules_again_once_they_change() {
+		let mut cache = RulesCache::default();
+		let location = RuleSet::Location(vec![1]);
+		let compile = || Ok::<_, Infallible>(vec![IndexerRule::from(no_hidden())]);
+
+		assert!(cache
+			.get_or_compile(location.clone(), vec![(1, None)], compile)
+			.is_ok());
+		assert!(cache
+			.get_or_compile(location.clone(), vec![(1, None)], compile)
+			.is_ok());
+		assert!(cache
+			.get_or_compile(location, vec![(1, None), (2, None)], compile)
+			.is_ok());
+
+		let stats = cache.stats();
+		assert_eq!(stats.hits, 1);
+		assert_eq!(stats.misses, 2);
+		assert_eq!(stats.cached_rule_sets, 1);
+	}
+}
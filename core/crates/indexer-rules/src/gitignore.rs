@@ -0,0 +1,169 @@
+//! The files in the `.gitignore` syntax read while applying the `RejectIgnoredByGitIgnore` and
+//! `RejectIgnoredBySdIgnore` rules.
+
+use crate::IndexerRuleError;
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	io,
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+};
+
+use ignore::{
+	gitignore::{Gitignore, GitignoreBuilder},
+	Match,
+};
+use tokio::fs;
+use tracing::warn;
+
+const GIT_DIR_NAME: &str = ".git";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreFile {
+	/// Only applies inside of the git repository it is in
+	GitIgnore,
+	/// Applies to everything below the directory it is in
+	SdIgnore,
+}
+
+impl IgnoreFile {
+	const fn file_name(self) -> &'static str {
+		match self {
+			Self::GitIgnore => ".gitignore",
+			Self::SdIgnore => ".sdignore",
+		}
+	}
+}
+
+#[derive(Debug)]
+struct DirectoryIgnores {
+	gitignore: Option<Gitignore>,
+	is_repository_root: bool,
+}
+
+/// The parsed ignore files by the directory they're in, so the entries of a directory don't read
+/// the same files again.
+///
+/// Cloning it gives an empty cache, as the compiled rules of a location are kept around between
+/// walks and the ignore files may have changed in the meantime.
+#[derive(Debug)]
+pub struct IgnoreFileCache {
+	file: IgnoreFile,
+	directories: Arc<RwLock<HashMap<PathBuf, Arc<DirectoryIgnores>>>>,
+}
+
+impl Clone for IgnoreFileCache {
+	fn clone(&self) -> Self {
+		Self::new(self.file)
+	}
+}
+
+impl IgnoreFileCache {
+	#[must_use]
+	pub fn new(file: IgnoreFile) -> Self {
+		Self {
+			file,
+			directories: Arc::default(),
+		}
+	}
+
+	/// Checks the ignore files of the ancestors of `path`, where the deepest file with a pattern
+	/// matching `path` or one of its parents decides, so the entries inside of an ignored directory
+	/// are ignored too. The `.gitignore` files are only checked up to the root of the git
+	/// repository `path` is in.
+	pub(crate) async fn is_ignored(
+		&self,
+		path: &Path,
+		is_dir: bool,
+	) -> Result<bool, IndexerRuleError> {
+		for directory in path.ancestors().skip(1) {
+			let ignores = self.directory_ignores(directory).await?;
+
+			if let Some(gitignore) = &ignores.gitignore {
+				match gitignore.matched_path_or_any_parents(path, is_dir) {
+					Match::Ignore(_) => return Ok(true),
+					Match::Whitelist(_) => return Ok(false),
+					Match::None => {}
+				}
+			}
+
+			if self.file == IgnoreFile::GitIgnore && ignores.is_repository_root {
+				break;
+			}
+		}
+
+		Ok(false)
+	}
+
+	async fn directory_ignores(
+		&self,
+		directory: &Path,
+	) -> Result<Arc<DirectoryIgnores>, IndexerRuleError> {
+		if let Some(ignores) = self
+			.directories
+			.read()
+			.expect("ignore files cache lock poisoned")
+			.get(directory)
+		{
+			return Ok(Arc::clone(ignores));
+		}
+
+		let gitignore_path = directory.join(self.file.file_name());
+
+		let gitignore = match fs::read_to_string(&gitignore_path).await {
+			Ok(contents) => {
+				let mut builder = GitignoreBuilder::new(directory);
+				for line in contents.lines() {
+					// Git skips the patterns it can't parse, so we do the same
+					if let Err(e) = builder.add_line(Some(gitignore_path.clone()), line) {
+						warn!("Skipping invalid pattern: {e:#?}");
+					}
+				}
+
+				Some(builder.build()?)
+			}
+			// The `.sdignore` files are looked for up to the root of the filesystem, where some
+			// directories may not be readable
+			Err(e)
+				if matches!(
+					e.kind(),
+					io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+				) =>
+			{
+				None
+			}
+			Err(e) => {
+				return Err(IndexerRuleError::IgnoreFileIO(FileIOError::from((
+					gitignore_path,
+					e,
+				))))
+			}
+		};
+
+		let is_repository_root = if self.file == IgnoreFile::GitIgnore {
+			let git_dir_path = directory.join(GIT_DIR_NAME);
+
+			fs::try_exists(&git_dir_path)
+				.await
+				.map_err(|e| IndexerRuleError::IgnoreFileIO(FileIOError::from((git_dir_path, e))))?
+		} else {
+			false
+		};
+
+		Ok(Arc::clone(
+			self.directories
+				.write()
+				.expect("ignore files cache lock poisoned")
+				.entry(directory.to_path_buf())
+				.or_insert_with(|| {
+					Arc::new(DirectoryIgnores {
+						gitignore,
+						is_repository_root,
+					})
+				}),
+		))
+	}
+}
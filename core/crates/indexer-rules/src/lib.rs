@@ -53,9 +53,13 @@ use tokio::{fs, sync::RwLock};
 use tracing::debug;
 use uuid::Uuid;
 
+pub mod cache;
+pub mod gitignore;
 pub mod seed;
 mod serde_impl;
 
+use gitignore::{IgnoreFile, IgnoreFileCache};
+
 #[derive(Error, Debug)]
 pub enum IndexerRuleError {
 	// User errors
@@ -65,6 +69,8 @@ pub enum IndexerRuleError {
 	Glob(#[from] globset::Error),
 	#[error(transparent)]
 	NonUtf8Path(#[from] NonUtf8PathError),
+	#[error("invalid indexer rule parameters: {0}")]
+	InvalidRuleParameters(String),
 
 	// Internal Errors
 	#[error("indexer rule parameters encode error: {0}")]
@@ -75,6 +81,12 @@ pub enum IndexerRuleError {
 	AcceptByItsChildrenFileIO(FileIOError),
 	#[error("reject by its children file I/O error: {0}")]
 	RejectByItsChildrenFileIO(FileIOError),
+	#[error("file metadata I/O error: {0}")]
+	MetadataFileIO(FileIOError),
+	#[error("ignore file I/O error: {0}")]
+	IgnoreFileIO(FileIOError),
+	#[error("ignore file parse error: {0}")]
+	IgnoreFile(#[from] ignore::Error),
 	#[error("database error: {0}")]
 	Database(#[from] prisma_client_rust::QueryError),
 	#[error("missing-field: {0}")]
@@ -86,7 +98,8 @@ impl From<IndexerRuleError> for rspc::Error {
 		match err {
 			IndexerRuleError::InvalidRuleKindInt(_)
 			| IndexerRuleError::Glob(_)
-			| IndexerRuleError::NonUtf8Path(_) => {
+			| IndexerRuleError::NonUtf8Path(_)
+			| IndexerRuleError::InvalidRuleParameters(_) => {
 				Self::with_cause(ErrorCode::BadRequest, err.to_string(), err)
 			}
 
@@ -103,6 +116,17 @@ impl From<IndexerRuleError> for rspc::Error {
 ///
 /// In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
 /// `parameters` field must be a vector of strings containing the names of the directories.
+///
+/// In case of `RuleKind::AcceptFilesByExtension` or `RuleKind::RejectFilesByExtension`, it will be a
+/// vector of extensions, with or without the leading dot.
+///
+/// In case of `RuleKind::AcceptFilesBySize` or `RuleKind::RejectFilesBySize`, it will be the minimum
+/// and maximum sizes in bytes, and for `RuleKind::AcceptFilesByAge` or `RuleKind::RejectFilesByAge`
+/// the minimum and maximum ages in seconds since the files were modified. An empty string leaves
+/// that end of the range open.
+///
+/// In case of `RuleKind::RejectIgnoredByGitIgnore` or `RuleKind::RejectIgnoredBySdIgnore` the
+/// `parameters` field is ignored.
 #[derive(Type, Deserialize)]
 pub struct IndexerRuleCreateArgs {
 	pub name: String,
@@ -128,30 +152,7 @@ impl IndexerRuleCreateArgs {
 			self.rules
 		);
 
-		let rules_data = rmp_serde::to_vec_named(
-			&self
-				.rules
-				.into_iter()
-				.map(|(kind, parameters)| match kind {
-					RuleKind::AcceptFilesByGlob => {
-						RulePerKind::new_accept_files_by_globs_str(parameters)
-					}
-					RuleKind::RejectFilesByGlob => {
-						RulePerKind::new_reject_files_by_globs_str(parameters)
-					}
-					RuleKind::AcceptIfChildrenDirectoriesArePresent => {
-						Ok(RulePerKind::AcceptIfChildrenDirectoriesArePresent(
-							parameters.into_iter().collect(),
-						))
-					}
-					RuleKind::RejectIfChildrenDirectoriesArePresent => {
-						Ok(RulePerKind::RejectIfChildrenDirectoriesArePresent(
-							parameters.into_iter().collect(),
-						))
-					}
-				})
-				.collect::<Result<Vec<_>, _>>()?,
-		)?;
+		let rules_data = encode_rules(self.rules)?;
 
 		if self.dry_run {
 			return Ok(None);
@@ -176,6 +177,53 @@ impl IndexerRuleCreateArgs {
 	}
 }
 
+/// `IndexerRuleUpdateArgs` is the argument received from the client using rspc to change an
+/// existing indexer rule, with the same `rules` as [`IndexerRuleCreateArgs`] replacing all the
+/// current ones.
+#[derive(Type, Deserialize)]
+pub struct IndexerRuleUpdateArgs {
+	pub id: indexer_rule::id::Type,
+	pub name: Option<String>,
+	pub rules: Option<Vec<(RuleKind, Vec<String>)>>,
+}
+
+impl IndexerRuleUpdateArgs {
+	pub async fn update(self, db: &PrismaClient) -> Result<indexer_rule::Data, IndexerRuleError> {
+		use indexer_rule::{date_modified, id, name, rules_per_kind};
+
+		debug!(
+			"Trying to update indexer rule (id = {}, name = {:?}, params = {:?})",
+			self.id, self.name, self.rules
+		);
+
+		let mut params = vec![date_modified::set(Some(Utc::now().into()))];
+
+		if let Some(new_name) = self.name {
+			params.push(name::set(Some(new_name)));
+		}
+
+		if let Some(rules) = self.rules {
+			params.push(rules_per_kind::set(Some(encode_rules(rules)?)));
+		}
+
+		db.indexer_rule()
+			.update(id::equals(self.id), params)
+			.exec()
+			.await
+			.map_err(Into::into)
+	}
+}
+
+fn encode_rules(rules: Vec<(RuleKind, Vec<String>)>) -> Result<Vec<u8>, IndexerRuleError> {
+	rmp_serde::to_vec_named(
+		&rules
+			.into_iter()
+			.map(|(kind, parameters)| RulePerKind::new(kind, parameters))
+			.collect::<Result<Vec<_>, _>>()?,
+	)
+	.map_err(Into::into)
+}
+
 #[repr(i32)]
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, Eq, PartialEq, Hash)]
@@ -184,13 +232,111 @@ pub enum RuleKind {
 	RejectFilesByGlob = 1,
 	AcceptIfChildrenDirectoriesArePresent = 2,
 	RejectIfChildrenDirectoriesArePresent = 3,
+	AcceptFilesByExtension = 4,
+	RejectFilesByExtension = 5,
+	AcceptFilesBySize = 6,
+	RejectFilesBySize = 7,
+	AcceptFilesByAge = 8,
+	RejectFilesByAge = 9,
+	RejectIgnoredByGitIgnore = 10,
+	RejectIgnoredBySdIgnore = 11,
 }
 
 impl RuleKind {
 	#[must_use]
 	pub const fn variant_count() -> usize {
 		// TODO: Use https://doc.rust-lang.org/std/mem/fn.variant_count.html if it ever gets stabilized
-		4
+		12
+	}
+}
+
+/// The kinds of rules that only look at an entry itself, so they're checked the same way for
+/// every entry, unlike the globs or the children directories rules.
+const ENTRY_RULE_KINDS: [(RuleKind, bool); 8] = [
+	(RuleKind::AcceptFilesByExtension, false),
+	(RuleKind::RejectFilesByExtension, true),
+	(RuleKind::AcceptFilesBySize, false),
+	(RuleKind::RejectFilesBySize, true),
+	(RuleKind::AcceptFilesByAge, false),
+	(RuleKind::RejectFilesByAge, true),
+	(RuleKind::RejectIgnoredByGitIgnore, true),
+	(RuleKind::RejectIgnoredBySdIgnore, true),
+];
+
+/// Checks the results of the rules that only look at an entry itself, like its extension, size,
+/// age or the ignore files above it.
+///
+/// Like the globs, an entry is rejected by any of the rejecting rules of a kind, or by all of the
+/// accepting rules of a kind.
+#[must_use]
+pub fn rejected_by_entry_rules(acceptance_per_rule_kind: &HashMap<RuleKind, Vec<bool>>) -> bool {
+	ENTRY_RULE_KINDS.iter().any(|(kind, is_reject)| {
+		acceptance_per_rule_kind.get(kind).map_or(false, |results| {
+			if *is_reject {
+				results.iter().any(|accept| !accept)
+			} else {
+				results.iter().all(|accept| !accept)
+			}
+		})
+	})
+}
+
+/// Inclusive range of the sizes in bytes or the ages in seconds that a rule matches, open ended
+/// where a bound is missing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueRange {
+	pub min: Option<u64>,
+	pub max: Option<u64>,
+}
+
+impl ValueRange {
+	fn from_parameters(parameters: &[String]) -> Result<Self, IndexerRuleError> {
+		let parse_bound = |bound: Option<&String>| {
+			bound
+				.map(|bound| bound.trim())
+				.filter(|bound| !bound.is_empty())
+				.map(|bound| {
+					bound.parse::<u64>().map_err(|_| {
+						IndexerRuleError::InvalidRuleParameters(format!(
+							"<bound='{bound}'> isn't a positive integer"
+						))
+					})
+				})
+				.transpose()
+		};
+
+		if parameters.len() > 2 {
+			return Err(IndexerRuleError::InvalidRuleParameters(format!(
+				"expected a minimum and a maximum, got {} parameters",
+				parameters.len()
+			)));
+		}
+
+		let range = Self {
+			min: parse_bound(parameters.first())?,
+			max: parse_bound(parameters.get(1))?,
+		};
+
+		match range {
+			Self {
+				min: Some(min),
+				max: Some(max),
+			} if min > max => Err(IndexerRuleError::InvalidRuleParameters(format!(
+				"<min={min}> is greater than <max={max}>"
+			))),
+			Self {
+				min: None,
+				max: None,
+			} => Err(IndexerRuleError::InvalidRuleParameters(
+				"a minimum or a maximum is required".to_string(),
+			)),
+			range => Ok(range),
+		}
+	}
+
+	#[must_use]
+	pub fn contains(&self, value: u64) -> bool {
+		self.min.map_or(true, |min| value >= min) && self.max.map_or(true, |max| value <= max)
 	}
 }
 
@@ -202,7 +348,7 @@ impl RuleKind {
 /// In case of `ParametersPerKind::AcceptIfChildrenDirectoriesArePresent` or
 /// `ParametersPerKind::RejectIfChildrenDirectoriesArePresent`
 /// first we change the data structure to a vector, then we serialize it.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RulePerKind {
 	// TODO: Add an indexer rule that filter files based on their extended attributes
 	// https://learn.microsoft.com/en-us/windows/win32/fileio/file-attribute-constants
@@ -211,9 +357,56 @@ pub enum RulePerKind {
 	RejectFilesByGlob(Vec<Glob>, GlobSet),
 	AcceptIfChildrenDirectoriesArePresent(HashSet<String>),
 	RejectIfChildrenDirectoriesArePresent(HashSet<String>),
+	/// Lowercase extensions, without the leading dot
+	AcceptFilesByExtension(HashSet<String>),
+	RejectFilesByExtension(HashSet<String>),
+	AcceptFilesBySize(ValueRange),
+	RejectFilesBySize(ValueRange),
+	/// Seconds since the files were last modified
+	AcceptFilesByAge(ValueRange),
+	RejectFilesByAge(ValueRange),
+	RejectIgnoredByGitIgnore(IgnoreFileCache),
+	RejectIgnoredBySdIgnore(IgnoreFileCache),
 }
 
 impl RulePerKind {
+	pub fn new(kind: RuleKind, parameters: Vec<String>) -> Result<Self, IndexerRuleError> {
+		match kind {
+			RuleKind::AcceptFilesByGlob => Self::new_accept_files_by_globs_str(parameters),
+			RuleKind::RejectFilesByGlob => Self::new_reject_files_by_globs_str(parameters),
+			RuleKind::AcceptIfChildrenDirectoriesArePresent => Ok(
+				Self::AcceptIfChildrenDirectoriesArePresent(parameters.into_iter().collect()),
+			),
+			RuleKind::RejectIfChildrenDirectoriesArePresent => Ok(
+				Self::RejectIfChildrenDirectoriesArePresent(parameters.into_iter().collect()),
+			),
+			RuleKind::AcceptFilesByExtension => Ok(Self::AcceptFilesByExtension(
+				normalize_extensions(parameters),
+			)),
+			RuleKind::RejectFilesByExtension => Ok(Self::RejectFilesByExtension(
+				normalize_extensions(parameters),
+			)),
+			RuleKind::AcceptFilesBySize => {
+				ValueRange::from_parameters(&parameters).map(Self::AcceptFilesBySize)
+			}
+			RuleKind::RejectFilesBySize => {
+				ValueRange::from_parameters(&parameters).map(Self::RejectFilesBySize)
+			}
+			RuleKind::AcceptFilesByAge => {
+				ValueRange::from_parameters(&parameters).map(Self::AcceptFilesByAge)
+			}
+			RuleKind::RejectFilesByAge => {
+				ValueRange::from_parameters(&parameters).map(Self::RejectFilesByAge)
+			}
+			RuleKind::RejectIgnoredByGitIgnore => Ok(Self::RejectIgnoredByGitIgnore(
+				IgnoreFileCache::new(IgnoreFile::GitIgnore),
+			)),
+			RuleKind::RejectIgnoredBySdIgnore => Ok(Self::RejectIgnoredBySdIgnore(
+				IgnoreFileCache::new(IgnoreFile::SdIgnore),
+			)),
+		}
+	}
+
 	fn new_files_by_globs_str_and_kind(
 		globs_str: impl IntoIterator<Item = impl AsRef<str>>,
 		kind_fn: impl Fn(Vec<Glob>, GlobSet) -> Self,
@@ -251,12 +444,22 @@ impl RulePerKind {
 
 pub trait MetadataForIndexerRules: Send + Sync + 'static {
 	fn is_dir(&self) -> bool;
+	fn size_in_bytes(&self) -> u64;
+	fn modified_at(&self) -> Option<DateTime<Utc>>;
 }
 
 impl MetadataForIndexerRules for Metadata {
 	fn is_dir(&self) -> bool {
 		self.is_dir()
 	}
+
+	fn size_in_bytes(&self) -> u64 {
+		self.len()
+	}
+
+	fn modified_at(&self) -> Option<DateTime<Utc>> {
+		self.modified().ok().map(Into::into)
+	}
 }
 
 impl RulePerKind {
@@ -285,6 +488,16 @@ impl RulePerKind {
 				RuleKind::RejectFilesByGlob,
 				reject_by_glob(source, reject_glob_set),
 			)),
+
+			_ => {
+				let source = source.as_ref();
+
+				let metadata = fs::metadata(source).await.map_err(|e| {
+					IndexerRuleError::MetadataFileIO(FileIOError::from((source, e)))
+				})?;
+
+				self.apply_with_metadata(source, &metadata).await
+			}
 		}
 	}
 
@@ -313,11 +526,47 @@ impl RulePerKind {
 				RuleKind::RejectFilesByGlob,
 				reject_by_glob(source, reject_glob_set),
 			)),
+
+			// Directories are left to the other rules, as they don't have extensions, sizes or
+			// ages of their own
+			Self::AcceptFilesByExtension(extensions) => Ok((
+				RuleKind::AcceptFilesByExtension,
+				metadata.is_dir() || has_extension(source, extensions),
+			)),
+			Self::RejectFilesByExtension(extensions) => Ok((
+				RuleKind::RejectFilesByExtension,
+				metadata.is_dir() || !has_extension(source, extensions),
+			)),
+			Self::AcceptFilesBySize(range) => Ok((
+				RuleKind::AcceptFilesBySize,
+				metadata.is_dir() || range.contains(metadata.size_in_bytes()),
+			)),
+			Self::RejectFilesBySize(range) => Ok((
+				RuleKind::RejectFilesBySize,
+				metadata.is_dir() || !range.contains(metadata.size_in_bytes()),
+			)),
+			Self::AcceptFilesByAge(range) => Ok((
+				RuleKind::AcceptFilesByAge,
+				metadata.is_dir() || age_in_range(metadata, range),
+			)),
+			Self::RejectFilesByAge(range) => Ok((
+				RuleKind::RejectFilesByAge,
+				metadata.is_dir() || !age_in_range(metadata, range),
+			)),
+
+			Self::RejectIgnoredByGitIgnore(cache) => cache
+				.is_ignored(source.as_ref(), metadata.is_dir())
+				.await
+				.map(|ignored| (RuleKind::RejectIgnoredByGitIgnore, !ignored)),
+			Self::RejectIgnoredBySdIgnore(cache) => cache
+				.is_ignored(source.as_ref(), metadata.is_dir())
+				.await
+				.map(|ignored| (RuleKind::RejectIgnoredBySdIgnore, !ignored)),
 		}
 	}
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexerRule {
 	pub id: Option<i32>,
 	pub name: String,
@@ -472,6 +721,41 @@ fn reject_by_glob(source: impl AsRef<Path>, reject_glob_set: &GlobSet) -> bool {
 	!accept_by_glob(source.as_ref(), reject_glob_set)
 }
 
+fn normalize_extensions(extensions: Vec<String>) -> HashSet<String> {
+	extensions
+		.into_iter()
+		.map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+		.filter(|extension| !extension.is_empty())
+		.collect()
+}
+
+/// Checks the extension of a file, along with the compound ones like `tar.gz`
+#[must_use]
+pub fn has_extension(source: impl AsRef<Path>, extensions: &HashSet<String>) -> bool {
+	source
+		.as_ref()
+		.file_name()
+		.and_then(|name| name.to_str())
+		.map_or(false, |name| {
+			let name = name.to_lowercase();
+
+			name.match_indices('.')
+				// Skipping the leading dot of hidden files, as it doesn't start an extension
+				.filter(|(index, _)| *index > 0)
+				.any(|(index, _)| extensions.contains(&name[index + 1..]))
+		})
+}
+
+fn age_in_range(metadata: &impl MetadataForIndexerRules, range: &ValueRange) -> bool {
+	metadata.modified_at().map_or(false, |modified_at| {
+		range.contains(
+			u64::try_from((Utc::now() - modified_at).num_seconds())
+				// Modified in the future, as far as the clock of this machine knows
+				.unwrap_or(0),
+		)
+	})
+}
+
 #[deprecated = "Use `[accept_dir_for_its_children_with_metadata]` instead"]
 async fn accept_dir_for_its_children(
 	source: impl AsRef<Path> + Send,
@@ -829,6 +1113,144 @@ mod tests {
 		assert!(check_rule(&rule, not_project).await);
 	}
 
+	#[tokio::test]
+	async fn test_files_by_extension_and_size() {
+		let root = tempdir().unwrap();
+
+		let small_photo = root.path().join("photo.JPG");
+		let big_photo = root.path().join("big_photo.jpg");
+		let archive = root.path().join("backup.tar.gz");
+		let dir = root.path().join("photos.jpg");
+
+		fs::write(&small_photo, [0u8; 10]).await.unwrap();
+		fs::write(&big_photo, [0u8; 1000]).await.unwrap();
+		fs::write(&archive, [0u8; 10]).await.unwrap();
+		fs::create_dir(&dir).await.unwrap();
+
+		let rule = IndexerRule::new(
+			"small photos".to_string(),
+			false,
+			vec![
+				RulePerKind::new(
+					RuleKind::AcceptFilesByExtension,
+					vec![".jpg".to_string(), "png".to_string()],
+				)
+				.unwrap(),
+				RulePerKind::new(
+					RuleKind::RejectFilesBySize,
+					vec!["100".to_string(), String::new()],
+				)
+				.unwrap(),
+			],
+		);
+
+		assert!(check_rule(&rule, &small_photo).await);
+		assert!(!check_rule(&rule, &big_photo).await);
+		assert!(!check_rule(&rule, &archive).await);
+		// Directories don't have extensions or sizes of their own
+		assert!(check_rule(&rule, &dir).await);
+
+		let rule = IndexerRule::new(
+			"no archives".to_string(),
+			false,
+			vec![
+				RulePerKind::new(RuleKind::RejectFilesByExtension, vec!["tar.gz".to_string()])
+					.unwrap(),
+			],
+		);
+
+		assert!(check_rule(&rule, &small_photo).await);
+		assert!(!check_rule(&rule, &archive).await);
+	}
+
+	#[test]
+	fn test_invalid_ranges() {
+		assert!(RulePerKind::new(RuleKind::AcceptFilesBySize, vec![]).is_err());
+		assert!(RulePerKind::new(
+			RuleKind::AcceptFilesBySize,
+			vec!["10".to_string(), "1".to_string()]
+		)
+		.is_err());
+		assert!(RulePerKind::new(RuleKind::RejectFilesByAge, vec!["a day".to_string()]).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_reject_ignored_by_git_ignore() {
+		let root = tempdir().unwrap();
+
+		let repository = root.path().join("repository");
+		let build = repository.join("build");
+		let sources = repository.join("src");
+
+		fs::create_dir_all(repository.join(".git")).await.unwrap();
+		fs::create_dir(&build).await.unwrap();
+		fs::create_dir(&sources).await.unwrap();
+		// Outside of the repository, so it doesn't apply
+		fs::write(root.path().join(".gitignore"), "*.rs\n")
+			.await
+			.unwrap();
+		fs::write(repository.join(".gitignore"), "build/\n*.log\n")
+			.await
+			.unwrap();
+		fs::write(sources.join(".gitignore"), "!important.log\n")
+			.await
+			.unwrap();
+
+		let main = sources.join("main.rs");
+		let log = sources.join("debug.log");
+		let important_log = sources.join("important.log");
+		let build_output = build.join("release").join("app");
+
+		fs::create_dir(build.join("release")).await.unwrap();
+		for file in [&main, &log, &important_log, &build_output] {
+			fs::write(file, b"").await.unwrap();
+		}
+
+		let rule = IndexerRule::new(
+			"git ignored".to_string(),
+			false,
+			vec![RulePerKind::new(RuleKind::RejectIgnoredByGitIgnore, vec![]).unwrap()],
+		);
+
+		assert!(check_rule(&rule, &main).await);
+		assert!(check_rule(&rule, &important_log).await);
+		assert!(!check_rule(&rule, &log).await);
+		assert!(!check_rule(&rule, &build).await);
+		assert!(!check_rule(&rule, &build_output).await);
+		assert!(check_rule(&rule, &sources).await);
+	}
+
+	#[tokio::test]
+	async fn test_reject_ignored_by_sd_ignore() {
+		let root = tempdir().unwrap();
+
+		let project = root.path().join("project");
+		let node_modules = project.join("node_modules");
+		let dist = project.join("dist");
+
+		fs::create_dir_all(project.join(".git")).await.unwrap();
+		fs::create_dir(&node_modules).await.unwrap();
+		fs::create_dir(&dist).await.unwrap();
+		// Unlike the `.gitignore` files, it applies to the git repositories below it too
+		fs::write(root.path().join(".sdignore"), "node_modules/\n")
+			.await
+			.unwrap();
+		fs::write(project.join(".sdignore"), "dist/\n")
+			.await
+			.unwrap();
+
+		let package = node_modules.join("ignore").join("package.json");
+		fs::create_dir(node_modules.join("ignore")).await.unwrap();
+		fs::write(&package, b"{}").await.unwrap();
+
+		let rule = IndexerRule::from(seed::no_sd_ignored());
+
+		assert!(!check_rule(&rule, &node_modules).await);
+		assert!(!check_rule(&rule, &package).await);
+		assert!(!check_rule(&rule, &dist).await);
+		assert!(check_rule(&rule, &project).await);
+	}
+
 	impl PartialEq for RulePerKind {
 		fn eq(&self, other: &Self) -> bool {
 			match (self, other) {
@@ -850,6 +1272,25 @@ mod tests {
 					Self::RejectIfChildrenDirectoriesArePresent(other_childrens),
 				) => self_childrens == other_childrens,
 
+				(
+					Self::AcceptFilesByExtension(self_extensions),
+					Self::AcceptFilesByExtension(other_extensions),
+				)
+				| (
+					Self::RejectFilesByExtension(self_extensions),
+					Self::RejectFilesByExtension(other_extensions),
+				) => self_extensions == other_extensions,
+
+				(Self::AcceptFilesBySize(self_range), Self::AcceptFilesBySize(other_range))
+				| (Self::RejectFilesBySize(self_range), Self::RejectFilesBySize(other_range))
+				| (Self::AcceptFilesByAge(self_range), Self::AcceptFilesByAge(other_range))
+				| (Self::RejectFilesByAge(self_range), Self::RejectFilesByAge(other_range)) => {
+					self_range == other_range
+				}
+
+				(Self::RejectIgnoredByGitIgnore(_), Self::RejectIgnoredByGitIgnore(_))
+				| (Self::RejectIgnoredBySdIgnore(_), Self::RejectIgnoredBySdIgnore(_)) => true,
+
 				_ => false,
 			}
 		}
@@ -875,12 +1316,20 @@ mod tests {
 		let actual = IndexerRule::new(
 			"No Hidden".to_string(),
 			true,
-			vec![RulePerKind::RejectFilesByGlob(
-				vec![Glob::new("**/.*").unwrap()],
-				Glob::new("**/.*")
-					.and_then(|glob| GlobSetBuilder::new().add(glob).build())
-					.unwrap(),
-			)],
+			vec![
+				RulePerKind::RejectFilesByGlob(
+					vec![Glob::new("**/.*").unwrap()],
+					Glob::new("**/.*")
+						.and_then(|glob| GlobSetBuilder::new().add(glob).build())
+						.unwrap(),
+				),
+				RulePerKind::RejectFilesBySize(ValueRange {
+					min: Some(1024),
+					max: None,
+				}),
+				RulePerKind::RejectIgnoredByGitIgnore(IgnoreFileCache::new(IgnoreFile::GitIgnore)),
+				RulePerKind::RejectIgnoredBySdIgnore(IgnoreFileCache::new(IgnoreFile::SdIgnore)),
+			],
 		);
 
 		let expected =
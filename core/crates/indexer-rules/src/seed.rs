@@ -4,7 +4,7 @@ use chrono::Utc;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::{IndexerRule, IndexerRuleError, RulePerKind};
+use super::{IndexerRule, IndexerRuleError, RuleKind, RulePerKind};
 
 #[derive(Error, Debug)]
 pub enum SeederError {
@@ -38,9 +38,15 @@ pub async fn new_or_existing_library(db: &PrismaClient) -> Result<(), SeederErro
 	use indexer_rule::{date_created, date_modified, default, name, rules_per_kind};
 
 	// DO NOT REORDER THIS ARRAY!
-	for (i, rule) in [no_os_protected(), no_hidden(), no_git(), only_images()]
-		.into_iter()
-		.enumerate()
+	for (i, rule) in [
+		no_os_protected(),
+		no_hidden(),
+		no_git(),
+		only_images(),
+		no_git_ignored(),
+	]
+	.into_iter()
+	.enumerate()
 	{
 		let pub_id = sd_utils::uuid_to_bytes(Uuid::from_u128(i as u128));
 		let rules = rmp_serde::to_vec_named(&rule.rules).map_err(IndexerRuleError::from)?;
@@ -210,3 +216,27 @@ fn only_images() -> SystemIndexerRule {
 		.expect("this is hardcoded and should always work")],
 	}
 }
+
+/// Honors the `.sdignore` files found while walking, which are always applied instead of being
+/// one of the rules of the library.
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+pub fn no_sd_ignored() -> SystemIndexerRule {
+	SystemIndexerRule {
+		name: "No Spacedrive Ignored",
+		default: false,
+		rules: vec![RulePerKind::new(RuleKind::RejectIgnoredBySdIgnore, vec![])
+			.expect("this is hardcoded and should always work")],
+	}
+}
+
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+fn no_git_ignored() -> SystemIndexerRule {
+	SystemIndexerRule {
+		name: "No Git Ignored",
+		default: false,
+		rules: vec![RulePerKind::new(RuleKind::RejectIgnoredByGitIgnore, vec![])
+			.expect("this is hardcoded and should always work")],
+	}
+}
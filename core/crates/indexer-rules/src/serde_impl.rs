@@ -3,7 +3,7 @@ use std::{collections::HashSet, marker::PhantomData};
 use globset::{Glob, GlobSetBuilder};
 use serde::{de, ser, Deserialize, Serialize};
 
-use super::RulePerKind;
+use super::{IgnoreFile, IgnoreFileCache, RulePerKind, ValueRange};
 
 /// We're implementing `Serialize` by hand as `GlobSet`s aren't serializable, so we ignore them on
 /// serialization
@@ -31,6 +31,55 @@ impl Serialize for RulePerKind {
 					"RejectIfChildrenDirectoriesArePresent",
 					children,
 				),
+			Self::AcceptFilesByExtension(ref extensions) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				4,
+				"AcceptFilesByExtension",
+				extensions,
+			),
+			Self::RejectFilesByExtension(ref extensions) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				5,
+				"RejectFilesByExtension",
+				extensions,
+			),
+			Self::AcceptFilesBySize(ref range) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				6,
+				"AcceptFilesBySize",
+				range,
+			),
+			Self::RejectFilesBySize(ref range) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				7,
+				"RejectFilesBySize",
+				range,
+			),
+			Self::AcceptFilesByAge(ref range) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				8,
+				"AcceptFilesByAge",
+				range,
+			),
+			Self::RejectFilesByAge(ref range) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				9,
+				"RejectFilesByAge",
+				range,
+			),
+			// The cache of the ignore files is only kept while walking
+			Self::RejectIgnoredByGitIgnore(ref _cache) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				10,
+				"RejectIgnoredByGitIgnore",
+				&(),
+			),
+			Self::RejectIgnoredBySdIgnore(ref _cache) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				11,
+				"RejectIgnoredBySdIgnore",
+				&(),
+			),
 		}
 	}
 }
@@ -46,6 +95,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			"RejectFilesByGlob",
 			"AcceptIfChildrenDirectoriesArePresent",
 			"RejectIfChildrenDirectoriesArePresent",
+			"AcceptFilesByExtension",
+			"RejectFilesByExtension",
+			"AcceptFilesBySize",
+			"RejectFilesBySize",
+			"AcceptFilesByAge",
+			"RejectFilesByAge",
+			"RejectIgnoredByGitIgnore",
+			"RejectIgnoredBySdIgnore",
 		];
 
 		enum Fields {
@@ -53,6 +110,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			RejectFilesByGlob,
 			AcceptIfChildrenDirectoriesArePresent,
 			RejectIfChildrenDirectoriesArePresent,
+			AcceptFilesByExtension,
+			RejectFilesByExtension,
+			AcceptFilesBySize,
+			RejectFilesBySize,
+			AcceptFilesByAge,
+			RejectFilesByAge,
+			RejectIgnoredByGitIgnore,
+			RejectIgnoredBySdIgnore,
 		}
 
 		struct FieldsVisitor;
@@ -65,7 +130,15 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"`AcceptFilesByGlob` \
 				or `RejectFilesByGlob` \
 				or `AcceptIfChildrenDirectoriesArePresent` \
-				or `RejectIfChildrenDirectoriesArePresent`",
+				or `RejectIfChildrenDirectoriesArePresent` \
+				or `AcceptFilesByExtension` \
+				or `RejectFilesByExtension` \
+				or `AcceptFilesBySize` \
+				or `RejectFilesBySize` \
+				or `AcceptFilesByAge` \
+				or `RejectFilesByAge` \
+				or `RejectIgnoredByGitIgnore` \
+				or `RejectIgnoredBySdIgnore`",
 				)
 			}
 
@@ -78,9 +151,17 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					1 => Ok(Fields::RejectFilesByGlob),
 					2 => Ok(Fields::AcceptIfChildrenDirectoriesArePresent),
 					3 => Ok(Fields::RejectIfChildrenDirectoriesArePresent),
+					4 => Ok(Fields::AcceptFilesByExtension),
+					5 => Ok(Fields::RejectFilesByExtension),
+					6 => Ok(Fields::AcceptFilesBySize),
+					7 => Ok(Fields::RejectFilesBySize),
+					8 => Ok(Fields::AcceptFilesByAge),
+					9 => Ok(Fields::RejectFilesByAge),
+					10 => Ok(Fields::RejectIgnoredByGitIgnore),
+					11 => Ok(Fields::RejectIgnoredBySdIgnore),
 					_ => Err(de::Error::invalid_value(
 						de::Unexpected::Unsigned(value),
-						&"variant index 0 <= i < 3",
+						&"variant index 0 <= i < 12",
 					)),
 				}
 			}
@@ -97,6 +178,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					"AcceptFilesByExtension" => Ok(Fields::AcceptFilesByExtension),
+					"RejectFilesByExtension" => Ok(Fields::RejectFilesByExtension),
+					"AcceptFilesBySize" => Ok(Fields::AcceptFilesBySize),
+					"RejectFilesBySize" => Ok(Fields::RejectFilesBySize),
+					"AcceptFilesByAge" => Ok(Fields::AcceptFilesByAge),
+					"RejectFilesByAge" => Ok(Fields::RejectFilesByAge),
+					"RejectIgnoredByGitIgnore" => Ok(Fields::RejectIgnoredByGitIgnore),
+					"RejectIgnoredBySdIgnore" => Ok(Fields::RejectIgnoredBySdIgnore),
 					_ => Err(de::Error::unknown_variant(value, VARIANTS)),
 				}
 			}
@@ -113,6 +202,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					b"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					b"AcceptFilesByExtension" => Ok(Fields::AcceptFilesByExtension),
+					b"RejectFilesByExtension" => Ok(Fields::RejectFilesByExtension),
+					b"AcceptFilesBySize" => Ok(Fields::AcceptFilesBySize),
+					b"RejectFilesBySize" => Ok(Fields::RejectFilesBySize),
+					b"AcceptFilesByAge" => Ok(Fields::AcceptFilesByAge),
+					b"RejectFilesByAge" => Ok(Fields::RejectFilesByAge),
+					b"RejectIgnoredByGitIgnore" => Ok(Fields::RejectIgnoredByGitIgnore),
+					b"RejectIgnoredBySdIgnore" => Ok(Fields::RejectIgnoredBySdIgnore),
 					_ => Err(de::Error::unknown_variant(
 						&String::from_utf8_lossy(bytes),
 						VARIANTS,
@@ -198,6 +295,52 @@ impl<'de> Deserialize<'de> for RulePerKind {
 						reject_if_children_directories_are_present,
 					)
 					.map(Self::Value::RejectIfChildrenDirectoriesArePresent),
+					(Fields::AcceptFilesByExtension, accept_files_by_extension) => {
+						de::VariantAccess::newtype_variant::<HashSet<String>>(
+							accept_files_by_extension,
+						)
+						.map(Self::Value::AcceptFilesByExtension)
+					}
+					(Fields::RejectFilesByExtension, reject_files_by_extension) => {
+						de::VariantAccess::newtype_variant::<HashSet<String>>(
+							reject_files_by_extension,
+						)
+						.map(Self::Value::RejectFilesByExtension)
+					}
+					(Fields::AcceptFilesBySize, accept_files_by_size) => {
+						de::VariantAccess::newtype_variant::<ValueRange>(accept_files_by_size)
+							.map(Self::Value::AcceptFilesBySize)
+					}
+					(Fields::RejectFilesBySize, reject_files_by_size) => {
+						de::VariantAccess::newtype_variant::<ValueRange>(reject_files_by_size)
+							.map(Self::Value::RejectFilesBySize)
+					}
+					(Fields::AcceptFilesByAge, accept_files_by_age) => {
+						de::VariantAccess::newtype_variant::<ValueRange>(accept_files_by_age)
+							.map(Self::Value::AcceptFilesByAge)
+					}
+					(Fields::RejectFilesByAge, reject_files_by_age) => {
+						de::VariantAccess::newtype_variant::<ValueRange>(reject_files_by_age)
+							.map(Self::Value::RejectFilesByAge)
+					}
+					(Fields::RejectIgnoredByGitIgnore, reject_ignored_by_git_ignore) => {
+						de::VariantAccess::newtype_variant::<()>(reject_ignored_by_git_ignore).map(
+							|()| {
+								Self::Value::RejectIgnoredByGitIgnore(IgnoreFileCache::new(
+									IgnoreFile::GitIgnore,
+								))
+							},
+						)
+					}
+					(Fields::RejectIgnoredBySdIgnore, reject_ignored_by_sd_ignore) => {
+						de::VariantAccess::newtype_variant::<()>(reject_ignored_by_sd_ignore).map(
+							|()| {
+								Self::Value::RejectIgnoredBySdIgnore(IgnoreFileCache::new(
+									IgnoreFile::SdIgnore,
+								))
+							},
+						)
+					}
 				})
 			}
 		}
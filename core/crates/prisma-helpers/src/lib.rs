@@ -51,7 +51,19 @@ file_path::select!(file_path_for_object_validator {
 	is_dir
 	name
 	extension
+	size_in_bytes_bytes
+	integrity_checksum
+});
+file_path::select!(file_path_for_location_validator {
+	id
+	materialized_path
+	is_dir
+	name
+	extension
+	size_in_bytes_bytes
+	cas_id
 	integrity_checksum
+	date_modified
 });
 file_path::select!(file_path_for_media_processor {
 	id
@@ -154,7 +166,9 @@ job::select!(job_without_data {
 	action
 	status
 	parent_id
+	location_id
 	errors_text
+	retries
 	metadata
 	date_created
 	date_started
@@ -186,8 +200,13 @@ impl From<location_with_indexer_rules::Data> for location::Data {
 			hidden: data.hidden,
 			date_created: data.date_created,
 			scan_state: data.scan_state,
+			remote: data.remote,
+			validation_interval_days: data.validation_interval_days,
+			date_validated: data.date_validated,
 			file_paths: None,
 			indexer_rules: None,
+			trashed_files: None,
+			validation_mismatches: None,
 			instance: None,
 		}
 	}
@@ -210,8 +229,13 @@ impl From<&location_with_indexer_rules::Data> for location::Data {
 			hidden: data.hidden,
 			date_created: data.date_created,
 			scan_state: data.scan_state,
+			remote: data.remote.clone(),
+			validation_interval_days: data.validation_interval_days,
+			date_validated: data.date_validated,
 			file_paths: None,
 			indexer_rules: None,
+			trashed_files: None,
+			validation_mismatches: None,
 			instance: None,
 		}
 	}
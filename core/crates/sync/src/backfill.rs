@@ -29,6 +29,7 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 					db.tag()
 						.find_many(vec![tag::id::gt(cursor)])
 						.order_by(tag::id::order(SortOrder::Asc))
+						.include(tag::include!({ parent: select { pub_id } }))
 						.exec()
 				},
 				|tag| tag.id,
@@ -50,6 +51,14 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 												t.date_modified.map(|v| {
 													(tag::date_modified::NAME, msgpack!(v))
 												}),
+												t.parent.map(|p| {
+													(
+														tag::parent::NAME,
+														msgpack!(prisma_sync::tag::SyncId {
+															pub_id: p.pub_id
+														}),
+													)
+												}),
 											],
 										),
 									)
@@ -200,6 +209,9 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 												option_sync_entry!(md.copyright, copyright),
 												option_sync_entry!(md.exif_version, exif_version),
 												option_sync_entry!(md.epoch_time, epoch_time),
+												option_sync_entry!(md.camera_model, camera_model),
+												option_sync_entry!(md.latitude, latitude),
+												option_sync_entry!(md.longitude, longitude),
 											],
 										),
 									)
@@ -9,7 +9,7 @@ use sd_prisma::{
 };
 use sd_sync::CRDTOperation;
 use tokio::sync::{mpsc, oneshot, Mutex};
-use tracing::debug;
+use tracing::{debug, info_span, Instrument};
 use uhlc::{Timestamp, NTP64};
 use uuid::Uuid;
 
@@ -115,9 +115,21 @@ impl Actor {
 						event.messages.last().unwrap().timestamp.as_u64(),
 					);
 
-					for op in event.messages {
-						self.receive_crdt_operation(op).await;
+					let span = info_span!(
+						"sync_ingest",
+						instance.id = %event.instance_id,
+						operations = event.messages.len(),
+					);
+
+					let messages = event.messages;
+
+					async {
+						for op in messages {
+							self.receive_crdt_operation(op).await;
+						}
 					}
+					.instrument(span)
+					.await;
 				}
 
 				match event.has_more {
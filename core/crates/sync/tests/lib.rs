@@ -4,9 +4,12 @@ use sd_core_sync::*;
 
 use sd_prisma::{prisma, prisma_sync};
 use sd_sync::*;
-use sd_utils::uuid_to_bytes;
+use sd_utils::{msgpack, uuid_to_bytes};
+
+use std::time::Duration;
 
 use mock_instance::Instance;
+use tokio::time::timeout;
 use uuid::Uuid;
 
 async fn write_test_location(instance: &Instance) -> Result<(), Box<dyn std::error::Error>> {
@@ -96,3 +99,112 @@ async fn operations_send_and_ingest() -> Result<(), Box<dyn std::error::Error>>
 
 	Ok(())
 }
+
+#[tokio::test]
+async fn unparenting_a_tag_syncs() -> Result<(), Box<dyn std::error::Error>> {
+	let instance1 = Instance::new(Uuid::new_v4()).await;
+	let instance2 = Instance::new(Uuid::new_v4()).await;
+
+	Instance::pair(&instance1, &instance2).await;
+
+	let mut sync_rx = instance2.sync_rx.resubscribe();
+
+	let parent = uuid_to_bytes(Uuid::new_v4());
+	let child = uuid_to_bytes(Uuid::new_v4());
+
+	for (pub_id, name) in [(&parent, "Parent"), (&child, "Child")] {
+		let (sync_ops, db_ops): (Vec<_>, Vec<_>) =
+			[sync_db_entry!(name.to_string(), prisma::tag::name)]
+				.into_iter()
+				.unzip();
+
+		instance1
+			.sync
+			.write_ops(
+				&instance1.db,
+				(
+					instance1.sync.shared_create(
+						prisma_sync::tag::SyncId {
+							pub_id: pub_id.clone(),
+						},
+						sync_ops,
+					),
+					instance1.db.tag().create(pub_id.clone(), db_ops),
+				),
+			)
+			.await?;
+	}
+
+	instance1
+		.sync
+		.write_op(
+			&instance1.db,
+			instance1.sync.shared_update(
+				prisma_sync::tag::SyncId {
+					pub_id: child.clone(),
+				},
+				prisma::tag::parent::NAME,
+				msgpack!(prisma_sync::tag::SyncId {
+					pub_id: parent.clone()
+				}),
+			),
+			instance1.db.tag().update(
+				prisma::tag::pub_id::equals(child.clone()),
+				vec![prisma::tag::parent::connect(prisma::tag::pub_id::equals(
+					parent.clone(),
+				))],
+			),
+		)
+		.await?;
+
+	instance1
+		.sync
+		.write_op(
+			&instance1.db,
+			instance1.sync.shared_update(
+				prisma_sync::tag::SyncId {
+					pub_id: child.clone(),
+				},
+				prisma::tag::parent_id::NAME,
+				msgpack!(nil),
+			),
+			instance1.db.tag().update(
+				prisma::tag::pub_id::equals(child.clone()),
+				vec![prisma::tag::parent::disconnect()],
+			),
+		)
+		.await?;
+
+	// 2 creates with their names, then the parent set and unset
+	timeout(Duration::from_secs(10), async {
+		while instance2
+			.sync
+			.get_ops(GetOpsArgs {
+				clocks: vec![],
+				count: 100,
+			})
+			.await?
+			.len() < 6
+		{
+			sync_rx.recv().await?;
+		}
+
+		Ok::<_, Box<dyn std::error::Error>>(())
+	})
+	.await??;
+
+	let tag = instance2
+		.db
+		.tag()
+		.find_unique(prisma::tag::pub_id::equals(child))
+		.exec()
+		.await?
+		.expect("the child tag was synced");
+
+	assert_eq!(tag.parent_id, None);
+
+	instance1.teardown().await;
+	instance2.teardown().await;
+
+	Ok(())
+}
@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "job" ADD COLUMN "location_id" INTEGER;
+
+-- CreateIndex
+CREATE INDEX "job_date_created_idx" ON "job"("date_created");
@@ -0,0 +1,12 @@
+-- CreateTable
+CREATE TABLE "plugin_metadata" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "plugin" TEXT NOT NULL,
+    "key" TEXT NOT NULL,
+    "value" TEXT NOT NULL,
+    "object_id" INTEGER NOT NULL,
+    CONSTRAINT "plugin_metadata_object_id_fkey" FOREIGN KEY ("object_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "plugin_metadata_object_id_plugin_key_key" ON "plugin_metadata"("object_id", "plugin", "key");
@@ -0,0 +1,21 @@
+-- CreateIndex
+CREATE INDEX "file_path_object_id_idx" ON "file_path"("object_id");
+
+-- CreateIndex
+-- Extension filters match the stored extensions exactly, which this index serves
+CREATE INDEX "file_path_extension_idx" ON "file_path"("extension");
+
+-- CreateIndex
+CREATE INDEX "file_path_date_created_idx" ON "file_path"("date_created");
+
+-- CreateIndex
+CREATE INDEX "file_path_date_modified_idx" ON "file_path"("date_modified");
+
+-- CreateIndex
+CREATE INDEX "file_path_date_indexed_idx" ON "file_path"("date_indexed");
+
+-- CreateIndex
+CREATE INDEX "object_kind_idx" ON "object"("kind");
+
+-- CreateIndex
+CREATE INDEX "object_date_accessed_idx" ON "object"("date_accessed");
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "saved_search" ADD COLUMN "is_live" BOOLEAN;
@@ -0,0 +1,17 @@
+-- CreateTable
+CREATE TABLE "trashed_file" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "original_path" TEXT NOT NULL,
+    "is_dir" BOOLEAN NOT NULL,
+    "size_in_bytes_bytes" BLOB,
+    "date_deleted" DATETIME NOT NULL,
+    "location_id" INTEGER NOT NULL,
+    CONSTRAINT "trashed_file_location_id_fkey" FOREIGN KEY ("location_id") REFERENCES "location" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "trashed_file_pub_id_key" ON "trashed_file"("pub_id");
+
+-- CreateIndex
+CREATE INDEX "trashed_file_date_deleted_idx" ON "trashed_file"("date_deleted");
@@ -0,0 +1,23 @@
+-- AlterTable
+ALTER TABLE "media_data" ADD COLUMN "camera_model" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "latitude" REAL;
+ALTER TABLE "media_data" ADD COLUMN "longitude" REAL;
+
+-- Fill the new columns from the media data already extracted
+UPDATE "media_data" SET
+    "camera_model" = json_extract(CAST("camera_data" AS TEXT), '$.device_model')
+WHERE json_valid(CAST("camera_data" AS TEXT));
+
+UPDATE "media_data" SET
+    "latitude" = json_extract(CAST("media_location" AS TEXT), '$.latitude'),
+    "longitude" = json_extract(CAST("media_location" AS TEXT), '$.longitude')
+WHERE json_valid(CAST("media_location" AS TEXT));
+
+-- CreateIndex
+CREATE INDEX "media_data_epoch_time_idx" ON "media_data"("epoch_time");
+
+-- CreateIndex
+CREATE INDEX "media_data_camera_model_idx" ON "media_data"("camera_model");
+
+-- CreateIndex
+CREATE INDEX "media_data_latitude_longitude_idx" ON "media_data"("latitude", "longitude");
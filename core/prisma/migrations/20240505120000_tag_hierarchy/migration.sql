@@ -0,0 +1,20 @@
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_tag" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "color" TEXT,
+    "is_hidden" BOOLEAN,
+    "date_created" DATETIME,
+    "date_modified" DATETIME,
+    "parent_id" INTEGER,
+    CONSTRAINT "tag_parent_id_fkey" FOREIGN KEY ("parent_id") REFERENCES "tag" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_tag" ("color", "date_created", "date_modified", "id", "is_hidden", "name", "pub_id") SELECT "color", "date_created", "date_modified", "id", "is_hidden", "name", "pub_id" FROM "tag";
+DROP TABLE "tag";
+ALTER TABLE "new_tag" RENAME TO "tag";
+CREATE UNIQUE INDEX "tag_pub_id_key" ON "tag"("pub_id");
+CREATE INDEX "tag_parent_id_idx" ON "tag"("parent_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
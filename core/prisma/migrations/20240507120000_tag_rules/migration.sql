@@ -0,0 +1,28 @@
+-- CreateTable
+CREATE TABLE "tag_rule" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "filters" TEXT NOT NULL,
+    "remove_unmatched" BOOLEAN NOT NULL DEFAULT true,
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "date_created" DATETIME,
+    "date_modified" DATETIME,
+    "tag_id" INTEGER NOT NULL,
+    CONSTRAINT "tag_rule_tag_id_fkey" FOREIGN KEY ("tag_id") REFERENCES "tag" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE "tag_rule_action" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "applied" BOOLEAN NOT NULL,
+    "date" DATETIME NOT NULL,
+    "rule_id" INTEGER NOT NULL,
+    "object_id" INTEGER NOT NULL,
+    CONSTRAINT "tag_rule_action_rule_id_fkey" FOREIGN KEY ("rule_id") REFERENCES "tag_rule" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "tag_rule_action_object_id_fkey" FOREIGN KEY ("object_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "tag_rule_tag_id_idx" ON "tag_rule"("tag_id");
+
+-- CreateIndex
+CREATE INDEX "tag_rule_action_rule_id_object_id_idx" ON "tag_rule_action"("rule_id", "object_id");
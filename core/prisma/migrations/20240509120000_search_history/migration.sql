@@ -0,0 +1,13 @@
+-- CreateTable
+CREATE TABLE "search_history" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "query" TEXT NOT NULL,
+    "count" INTEGER NOT NULL DEFAULT 1,
+    "date_searched" DATETIME NOT NULL
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "search_history_query_key" ON "search_history"("query");
+
+-- CreateIndex
+CREATE INDEX "search_history_date_searched_idx" ON "search_history"("date_searched");
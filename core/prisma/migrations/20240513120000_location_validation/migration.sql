@@ -0,0 +1,19 @@
+-- AlterTable
+ALTER TABLE "location" ADD COLUMN "validation_interval_days" INTEGER;
+ALTER TABLE "location" ADD COLUMN "date_validated" DATETIME;
+
+-- CreateTable
+CREATE TABLE "validation_mismatch" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "kind" INTEGER NOT NULL,
+    "file_path_id" INTEGER NOT NULL,
+    "path" TEXT NOT NULL,
+    "expected_cas_id" TEXT NOT NULL,
+    "actual_cas_id" TEXT,
+    "date_detected" DATETIME NOT NULL,
+    "location_id" INTEGER NOT NULL,
+    CONSTRAINT "validation_mismatch_location_id_fkey" FOREIGN KEY ("location_id") REFERENCES "location" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "validation_mismatch_location_id_idx" ON "validation_mismatch"("location_id");
@@ -0,0 +1,137 @@
+use crate::{
+	invalidate_query,
+	node::api_tokens::{
+		ApiToken, ApiTokenScope, DEFAULT_REQUESTS_PER_MINUTE, MAX_REQUESTS_PER_MINUTE,
+	},
+};
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::error;
+use uuid::Uuid;
+
+use super::{Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.api_tokens
+					.tokens()
+					.to_vec())
+			})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct CreateApiTokenArgs {
+				pub name: String,
+				pub scopes: Vec<ApiTokenScope>,
+				#[serde(default)]
+				pub requests_per_minute: Option<u32>,
+			}
+
+			#[derive(Type, Serialize)]
+			pub struct CreatedApiToken {
+				pub token: ApiToken,
+				/// Only returned here, the node keeps a hash of it
+				pub secret: String,
+			}
+
+			R.mutation(
+				|node,
+				 CreateApiTokenArgs {
+				     name,
+				     scopes,
+				     requests_per_minute,
+				 }: CreateApiTokenArgs| async move {
+					if name.is_empty() || name.len() > 250 {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"invalid token name".to_string(),
+						));
+					}
+
+					if scopes.is_empty() {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"a token needs at least one scope".to_string(),
+						));
+					}
+
+					let requests_per_minute =
+						requests_per_minute.unwrap_or(DEFAULT_REQUESTS_PER_MINUTE);
+					if !(1..=MAX_REQUESTS_PER_MINUTE).contains(&requests_per_minute) {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							format!(
+								"requests per minute must be between 1 and {MAX_REQUESTS_PER_MINUTE}"
+							),
+						));
+					}
+
+					let mut created = None;
+					node.config
+						.update_preferences(|preferences| {
+							created = Some(preferences.api_tokens.create(
+								name,
+								scopes,
+								requests_per_minute,
+							));
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to save API token: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to save API token".to_string(),
+								e,
+							)
+						})?;
+
+					invalidate_query!(node; node, "apiTokens.list");
+
+					let (token, secret) = created.expect("the token is created by the update");
+
+					Ok(CreatedApiToken { token, secret })
+				},
+			)
+		})
+		.procedure("revoke", {
+			R.mutation(|node, id: Uuid| async move {
+				let mut revoked = false;
+				node.config
+					.update_preferences(|preferences| {
+						revoked = preferences.api_tokens.revoke(id);
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to revoke API token: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to revoke API token".to_string(),
+							e,
+						)
+					})?;
+
+				if !revoked {
+					return Err(rspc::Error::new(
+						ErrorCode::NotFound,
+						format!("API token '{id}' not found"),
+					));
+				}
+
+				node.api_token_limiter.forget(id);
+
+				invalidate_query!(node; node, "apiTokens.list");
+
+				Ok(())
+			})
+		})
+}
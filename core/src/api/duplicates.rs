@@ -0,0 +1,162 @@
+use crate::{
+	location::{find_location, LocationError},
+	object::duplicates::{
+		cluster_summaries, clusters_of, old_duplicate_finder_job::OldDuplicateFinderJobInit,
+		ClusterSummary, DuplicateCluster,
+	},
+	old_job::Job,
+};
+
+use sd_prisma::prisma::{file_path, location};
+
+use std::cmp::Reverse;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{
+	utils::{library, Pagination, MAX_TAKE},
+	Ctx, R,
+};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct ListDuplicatesArgs {
+				/// Only the clusters with a file path in this location
+				#[specta(optional)]
+				pub location_id: Option<location::id::Type>,
+				/// Only the clusters whose contents were compared by `duplicates.find`
+				#[serde(default)]
+				#[specta(optional)]
+				pub verified_only: bool,
+				/// Up to [`MAX_TAKE`], which is also the default
+				#[serde(default)]
+				#[specta(optional)]
+				pub take: Option<u8>,
+				#[serde(default)]
+				#[specta(optional)]
+				pub cursor: Option<DuplicatesCursor>,
+			}
+
+			/// The last cluster of a page, to list the ones after it
+			#[derive(Type, Serialize, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct DuplicatesCursor {
+				pub reclaimable_bytes: String,
+				pub cas_id: String,
+				pub file_path_id: file_path::id::Type,
+			}
+
+			#[derive(Serialize, Type)]
+			#[serde(rename_all = "camelCase")]
+			pub struct Duplicates {
+				/// The biggest savings first
+				pub clusters: Vec<DuplicateCluster>,
+				/// Of all the clusters, not only the ones in this page
+				pub reclaimable_bytes: String,
+				/// `None` on the last page
+				pub cursor: Option<DuplicatesCursor>,
+			}
+
+			// Clusters with the same savings are ordered by cas id, and then by their first file
+			// path as a cas id can have clusters of different sizes or checksums
+			fn order_key(summary: &ClusterSummary) -> (Reverse<u64>, &str, file_path::id::Type) {
+				(
+					Reverse(summary.reclaimable()),
+					&summary.cas_id,
+					summary.first_file_path_id,
+				)
+			}
+
+			R.with2(library()).query(
+				|(_, library),
+				 ListDuplicatesArgs {
+				     location_id,
+				     verified_only,
+				     take,
+				     cursor,
+				 }| async move {
+					let after = cursor
+						.map(
+							|DuplicatesCursor {
+							     reclaimable_bytes,
+							     cas_id,
+							     file_path_id,
+							 }| {
+								reclaimable_bytes
+									.parse::<u64>()
+									.map(|reclaimable| (Reverse(reclaimable), cas_id, file_path_id))
+									.map_err(|e| {
+										rspc::Error::with_cause(
+											ErrorCode::BadRequest,
+											"Invalid duplicates cursor".to_string(),
+											e,
+										)
+									})
+							},
+						)
+						.transpose()?;
+
+					// The clusters are grouped by the database, only the ones in the page read
+					// their file paths
+					let mut summaries =
+						cluster_summaries(&library.db, location_id, verified_only).await?;
+
+					let reclaimable_bytes = summaries
+						.iter()
+						.map(ClusterSummary::reclaimable)
+						.sum::<u64>()
+						.to_string();
+
+					if let Some((reclaimable, cas_id, file_path_id)) = &after {
+						summaries.retain(|summary| {
+							order_key(summary) > (*reclaimable, cas_id.as_str(), *file_path_id)
+						});
+					}
+
+					summaries.sort_by(|a, b| order_key(a).cmp(&order_key(b)));
+
+					let pagination = Pagination::new(take.map(u16::from), 0, u16::from(MAX_TAKE));
+
+					summaries.truncate(usize::try_from(pagination.fetch()).unwrap_or(usize::MAX));
+
+					let (summaries, cursor) =
+						pagination.paginate(summaries, |last| DuplicatesCursor {
+							reclaimable_bytes: last.reclaimable().to_string(),
+							cas_id: last.cas_id.clone(),
+							file_path_id: last.first_file_path_id,
+						});
+
+					Ok(Duplicates {
+						clusters: clusters_of(&library.db, &summaries).await?,
+						reclaimable_bytes,
+						cursor,
+					})
+				},
+			)
+		})
+		.procedure("find", {
+			// Groups the duplicates of a location in the background, comparing their full contents
+			// if `verify` is set
+			R.with2(library()).mutation(
+				|(node, library), args: OldDuplicateFinderJobInit| async move {
+					if find_location(&library, args.location_id)
+						.exec()
+						.await?
+						.is_none()
+					{
+						return Err(LocationError::IdNotFound(args.location_id).into());
+					}
+
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+}
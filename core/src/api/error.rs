@@ -0,0 +1,240 @@
+use crate::p2p::operations::SpacedropError;
+
+use sd_p2p::{NewStreamError, RemoteIdentity};
+use sd_prisma::prisma::{location, saved_search};
+use sd_utils::error::FileIOError;
+
+use std::io;
+
+use rspc::ErrorCode;
+use serde::Serialize;
+use serde_json::{json, Value};
+use specta::Type;
+use thiserror::Error;
+
+/// Stable identifiers of the failures in [`ApiError`], for clients to branch on.
+#[derive(Debug, Clone, Copy, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiErrorCode {
+	DirectoryNotFound,
+	SavedSearchNotFound,
+	InvalidSearchPattern,
+	PathNotFound,
+	PathPermissionDenied,
+	SearchBackendFailed,
+	DirectoryWatchFailed,
+	ThumbnailLookupFailed,
+	PeerNotFound,
+	PeerUnreachable,
+	PeerStreamFailed,
+	NothingToSend,
+	FileUnreadable,
+	PeerNotPaired,
+	TransfersPaused,
+}
+
+/// What clients receive as the message of an [`rspc::Error`] built from an [`ApiError`], encoded
+/// as JSON.
+#[derive(Debug, Serialize, Type)]
+pub struct ApiErrorPayload {
+	pub code: ApiErrorCode,
+	/// Meant for humans, clients should branch on `code` instead
+	pub message: String,
+	/// Values the failure is about, like the id of the missing resource
+	pub context: Value,
+}
+
+/// Failures of the API with a machine-readable code and the context needed to act on them.
+#[derive(Debug, Error)]
+pub enum ApiError {
+	// Search
+	#[error("directory not found")]
+	DirectoryNotFound {
+		location_id: location::id::Type,
+		path: String,
+	},
+	#[error("saved search not found")]
+	SavedSearchNotFound { id: saved_search::id::Type },
+	#[error("invalid search pattern: {reason}")]
+	InvalidPattern { pattern: String, reason: String },
+	#[error("failed to read '{path}'")]
+	EphemeralSearch {
+		path: String,
+		#[source]
+		source: opendal::Error,
+	},
+	#[error("failed to watch '{path}'")]
+	DirectoryWatch {
+		path: String,
+		#[source]
+		source: notify::Error,
+	},
+	#[error("failed to check that the thumbnail exists")]
+	ThumbnailLookup {
+		cas_id: String,
+		#[source]
+		source: FileIOError,
+	},
+
+	// Transport
+	#[error("peer not found")]
+	PeerNotFound { identity: RemoteIdentity },
+	#[error("failed to connect to peer")]
+	PeerUnreachable {
+		identity: RemoteIdentity,
+		#[source]
+		source: NewStreamError,
+	},
+	#[error("failed to write to the stream of peer")]
+	PeerStream {
+		identity: RemoteIdentity,
+		#[source]
+		source: io::Error,
+	},
+	#[error(transparent)]
+	Spacedrop(#[from] SpacedropError),
+}
+
+impl ApiError {
+	pub fn code(&self) -> ApiErrorCode {
+		match self {
+			Self::DirectoryNotFound { .. } => ApiErrorCode::DirectoryNotFound,
+			Self::SavedSearchNotFound { .. } => ApiErrorCode::SavedSearchNotFound,
+			Self::InvalidPattern { .. } => ApiErrorCode::InvalidSearchPattern,
+			Self::EphemeralSearch { source, .. } => match source.kind() {
+				opendal::ErrorKind::NotFound => ApiErrorCode::PathNotFound,
+				opendal::ErrorKind::PermissionDenied => ApiErrorCode::PathPermissionDenied,
+				_ => ApiErrorCode::SearchBackendFailed,
+			},
+			Self::DirectoryWatch { source, .. } => match source.kind {
+				notify::ErrorKind::PathNotFound => ApiErrorCode::PathNotFound,
+				_ => ApiErrorCode::DirectoryWatchFailed,
+			},
+			Self::ThumbnailLookup { .. } => ApiErrorCode::ThumbnailLookupFailed,
+			Self::PeerNotFound { .. } | Self::Spacedrop(SpacedropError::PeerNotFound(_)) => {
+				ApiErrorCode::PeerNotFound
+			}
+			Self::PeerUnreachable { .. } | Self::Spacedrop(SpacedropError::Connect { .. }) => {
+				ApiErrorCode::PeerUnreachable
+			}
+			Self::PeerStream { .. } => ApiErrorCode::PeerStreamFailed,
+			Self::Spacedrop(SpacedropError::NoFiles) => ApiErrorCode::NothingToSend,
+			Self::Spacedrop(SpacedropError::OpenFile { .. }) => ApiErrorCode::FileUnreadable,
+			Self::Spacedrop(SpacedropError::NotPaired(_)) => ApiErrorCode::PeerNotPaired,
+			Self::Spacedrop(SpacedropError::Paused) => ApiErrorCode::TransfersPaused,
+		}
+	}
+
+	fn context(&self) -> Value {
+		match self {
+			Self::DirectoryNotFound { location_id, path } => {
+				json!({ "locationId": location_id, "path": path })
+			}
+			Self::SavedSearchNotFound { id } => json!({ "id": id }),
+			Self::InvalidPattern { pattern, reason } => {
+				json!({ "pattern": pattern, "reason": reason })
+			}
+			Self::EphemeralSearch { path, source } => {
+				json!({ "path": path, "cause": source.to_string() })
+			}
+			Self::DirectoryWatch { path, source } => {
+				json!({ "path": path, "cause": source.to_string() })
+			}
+			Self::ThumbnailLookup { cas_id, source } => {
+				json!({ "casId": cas_id, "cause": source.to_string() })
+			}
+			Self::PeerNotFound { identity }
+			| Self::Spacedrop(
+				SpacedropError::PeerNotFound(identity) | SpacedropError::NotPaired(identity),
+			) => {
+				json!({ "identity": identity.to_string() })
+			}
+			Self::PeerUnreachable { identity, source }
+			| Self::Spacedrop(SpacedropError::Connect { identity, source }) => {
+				json!({ "identity": identity.to_string(), "cause": source.to_string() })
+			}
+			Self::PeerStream { identity, source } => {
+				json!({ "identity": identity.to_string(), "cause": source.to_string() })
+			}
+			Self::Spacedrop(SpacedropError::NoFiles | SpacedropError::Paused) => json!({}),
+			Self::Spacedrop(SpacedropError::OpenFile { path, source }) => {
+				json!({ "path": path, "cause": source.to_string() })
+			}
+		}
+	}
+
+	fn error_code(&self) -> ErrorCode {
+		match self.code() {
+			ApiErrorCode::DirectoryNotFound
+			| ApiErrorCode::SavedSearchNotFound
+			| ApiErrorCode::PathNotFound
+			| ApiErrorCode::PeerNotFound => ErrorCode::NotFound,
+			ApiErrorCode::PathPermissionDenied | ApiErrorCode::PeerNotPaired => {
+				ErrorCode::Forbidden
+			}
+			ApiErrorCode::InvalidSearchPattern
+			| ApiErrorCode::NothingToSend
+			| ApiErrorCode::FileUnreadable => ErrorCode::BadRequest,
+			ApiErrorCode::TransfersPaused => ErrorCode::PreconditionFailed,
+			ApiErrorCode::SearchBackendFailed
+			| ApiErrorCode::DirectoryWatchFailed
+			| ApiErrorCode::ThumbnailLookupFailed
+			| ApiErrorCode::PeerUnreachable
+			| ApiErrorCode::PeerStreamFailed => ErrorCode::InternalServerError,
+		}
+	}
+
+	pub fn payload(&self) -> ApiErrorPayload {
+		ApiErrorPayload {
+			code: self.code(),
+			message: self.to_string(),
+			context: self.context(),
+		}
+	}
+}
+
+impl From<ApiError> for rspc::Error {
+	fn from(e: ApiError) -> Self {
+		let message = serde_json::to_string(&e.payload())
+			.expect("error payloads are always serializable to JSON");
+
+		Self::with_cause(e.error_code(), message, e)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn payload_carries_code_and_context() {
+		let payload = serde_json::to_value(
+			ApiError::DirectoryNotFound {
+				location_id: 1,
+				path: "photos/2024".to_string(),
+			}
+			.payload(),
+		)
+		.unwrap();
+
+		assert_eq!(
+			payload,
+			json!({
+				"code": "DIRECTORY_NOT_FOUND",
+				"message": "directory not found",
+				"context": { "locationId": 1, "path": "photos/2024" }
+			})
+		);
+	}
+
+	#[test]
+	fn search_failures_follow_their_cause() {
+		let error = ApiError::EphemeralSearch {
+			path: "/nope/".to_string(),
+			source: opendal::Error::new(opendal::ErrorKind::NotFound, "not found"),
+		};
+
+		assert_eq!(error.code(), ApiErrorCode::PathNotFound);
+		assert!(matches!(error.error_code(), ErrorCode::NotFound));
+	}
+}
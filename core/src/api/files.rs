@@ -1,17 +1,28 @@
 use crate::{
-	api::utils::library,
+	api::{
+		search::{PathFrom, RemoteCredentials},
+		utils::library,
+		CoreEvent,
+	},
 	invalidate_query,
 	library::Library,
 	location::{get_location_path_from_location_id, LocationError},
 	object::{
 		fs::{
-			error::FileSystemJobsError, find_available_filename_for_duplicate,
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
+			error::FileSystemJobsError,
+			find_available_filename_for_duplicate, get_many_files_datas,
+			old_copy::OldFileCopierJobInit,
+			old_cut::OldFileCutterJobInit,
+			old_delete::OldFileDeleterJobInit,
+			old_erase::OldFileEraserJobInit,
+			operator::{ConflictResolution, FileOp, FileOpKind, LocationFiles},
+			trash::move_to_trash,
 		},
-		media::media_data_image_from_prisma_data,
+		media::media_metadata_from_prisma_data,
 	},
-	old_job::Job,
+	old_job::{DryRunAction, DryRunReport, Job},
+	util::MaybeUndefined,
+	Node,
 };
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
@@ -20,24 +31,25 @@ use sd_core_prisma_helpers::{
 };
 
 use sd_cache::{CacheNode, Model, NormalisedResult, Reference};
-use sd_file_ext::kind::ObjectKind;
 use sd_images::ConvertibleExtension;
-use sd_media_metadata::MediaMetadata;
 use sd_prisma::{
-	prisma::{file_path, location, object},
+	prisma::{file_path, location, object, tag, tag_on_object},
 	prisma_sync,
 };
 use sd_sync::OperationFactory;
 use sd_utils::{db::maybe_missing, error::FileIOError, msgpack};
 
 use std::{
+	collections::BTreeMap,
 	ffi::OsString,
-	path::{Path, PathBuf},
+	path::{Component, Path, PathBuf},
 	sync::Arc,
+	time::{Duration, Instant},
 };
 
 use chrono::{DateTime, FixedOffset, Utc};
 use futures::future::join_all;
+use opendal::Operator;
 use regex::Regex;
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
@@ -45,12 +57,186 @@ use specta::Type;
 use tokio::{fs, io, task::spawn_blocking};
 use tracing::{error, warn};
 use trash;
+use uuid::Uuid;
 
 use super::{Ctx, R};
 
 const UNTITLED_FOLDER_STR: &str = "Untitled Folder";
 const UNTITLED_FILE_STR: &str = "Untitled";
 const UNTITLED_TEXT_FILE_STR: &str = "Untitled.txt";
+/// Most objects changed by a single `files.bulkUpdate` call
+const MAX_BULK_OBJECTS: usize = 10_000;
+
+/// Where the files of `files.copy`, `files.move` and `files.delete` are.
+#[derive(Type, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum FileOpSource {
+	/// File paths of a location
+	#[serde(rename_all = "camelCase")]
+	Indexed {
+		location_id: location::id::Type,
+		file_path_ids: Vec<file_path::id::Type>,
+	},
+	/// Paths outside of the locations, like the ones listed by `search.ephemeralPaths`
+	Ephemeral {
+		from: PathFrom,
+		#[specta(optional)]
+		credentials: Option<RemoteCredentials>,
+		paths: Vec<String>,
+	},
+}
+
+/// The folder the files of `files.copy` and `files.move` go into.
+#[derive(Type, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum FileOpTarget {
+	/// A folder of a location, by its path from the root of the location
+	#[serde(rename_all = "camelCase")]
+	Location {
+		location_id: location::id::Type,
+		path: String,
+	},
+	Ephemeral {
+		from: PathFrom,
+		#[specta(optional)]
+		credentials: Option<RemoteCredentials>,
+		path: String,
+	},
+}
+
+#[derive(Type, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileOpArgs {
+	source: FileOpSource,
+	target: FileOpTarget,
+	#[serde(default)]
+	#[specta(optional)]
+	conflict: ConflictResolution,
+}
+
+impl FileOpSource {
+	/// The operator and paths of the files, along with their location for the indexed ones.
+	async fn into_paths(
+		self,
+		library: &Library,
+	) -> Result<(Operator, Vec<String>, Option<LocationFiles>), rspc::Error> {
+		match self {
+			Self::Indexed {
+				location_id,
+				file_path_ids,
+			} => {
+				let location_path =
+					get_location_path_from_location_id(&library.db, location_id).await?;
+
+				let files =
+					get_many_files_datas(&library.db, &location_path, &file_path_ids).await?;
+				let paths = files
+					.iter()
+					.map(|file| file.full_path.to_string_lossy().to_string())
+					.collect();
+
+				Ok((
+					PathFrom::Path.operator(library, None).await?,
+					paths,
+					Some(LocationFiles {
+						location_id,
+						location_path,
+						files,
+					}),
+				))
+			}
+			Self::Ephemeral {
+				from,
+				credentials,
+				paths,
+			} => Ok((from.operator(library, credentials).await?, paths, None)),
+		}
+	}
+}
+
+impl FileOpTarget {
+	async fn into_path(self, library: &Library) -> Result<(Operator, String), rspc::Error> {
+		match self {
+			Self::Location { location_id, path } => {
+				let location_path =
+					get_location_path_from_location_id(&library.db, location_id).await?;
+
+				Ok((
+					PathFrom::Path.operator(library, None).await?,
+					location_sub_path(&location_path, &path)?
+						.to_string_lossy()
+						.to_string(),
+				))
+			}
+			Self::Ephemeral {
+				from,
+				credentials,
+				path,
+			} => Ok((from.operator(library, credentials).await?, path)),
+		}
+	}
+}
+
+/// The full path of `path` within the location at `location_path`, rejecting the paths which
+/// would leave it.
+fn location_sub_path(location_path: &Path, path: &str) -> Result<PathBuf, rspc::Error> {
+	let outside = || {
+		rspc::Error::new(
+			ErrorCode::BadRequest,
+			format!("Path '{path}' is outside of the location"),
+		)
+	};
+
+	let sub_path = Path::new(path.trim_start_matches('/'));
+	if sub_path
+		.components()
+		.any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+	{
+		return Err(outside());
+	}
+
+	let full_path = location_path
+		.join(sub_path)
+		.components()
+		.collect::<PathBuf>();
+	if !full_path.starts_with(location_path) {
+		return Err(outside());
+	}
+
+	Ok(full_path)
+}
+
+async fn spawn_file_op(
+	node: Arc<Node>,
+	library: Arc<Library>,
+	kind: FileOpKind,
+	FileOpArgs {
+		source,
+		target,
+		conflict,
+	}: FileOpArgs,
+) -> Result<Uuid, rspc::Error> {
+	let (source, paths, _) = source.into_paths(&library).await?;
+	let target = target.into_path(&library).await?;
+
+	let op = FileOp {
+		kind,
+		source,
+		paths,
+		target: Some(target),
+		conflict,
+		trash: None,
+	};
+
+	if let Some(path) = op.target_in_source() {
+		return Err(rspc::Error::new(
+			ErrorCode::BadRequest,
+			format!("Can't copy or move '{path}' into itself"),
+		));
+	}
+
+	Ok(op.spawn(node, library))
+}
 
 #[derive(Type, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -144,16 +330,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.select(object::select!({ id kind media_data }))
 						.exec()
 						.await?
-						.and_then(|obj| {
-							Some(match obj.kind {
-								Some(v) if v == ObjectKind::Image as i32 => {
-									MediaMetadata::Image(Box::new(
-										media_data_image_from_prisma_data(obj.media_data?).ok()?,
-									))
-								}
-								_ => return None, // TODO(brxken128): audio and video
-							})
-						})
+						.and_then(|obj| media_metadata_from_prisma_data(obj.kind, obj.media_data?))
 						.ok_or_else(|| {
 							rspc::Error::new(ErrorCode::NotFound, "Object not found".to_string())
 						})
@@ -276,6 +453,268 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("setRating", {
+			#[derive(Type, Deserialize)]
+			pub struct SetRatingArgs {
+				pub id: i32,
+				/// From 1 to 5 stars, or `None` to remove the rating
+				pub rating: Option<i32>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SetRatingArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					if matches!(args.rating, Some(rating) if !(1..=5).contains(&rating)) {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"Rating must be between 1 and 5".to_string(),
+						));
+					}
+
+					let object = db
+						.object()
+						.find_unique(object::id::equals(args.id))
+						.select(object::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(
+								rspc::ErrorCode::NotFound,
+								"Object not found".to_string(),
+							)
+						})?;
+
+					sync.write_op(
+						db,
+						sync.shared_update(
+							prisma_sync::object::SyncId {
+								pub_id: object.pub_id,
+							},
+							object::rating::NAME,
+							msgpack!(&args.rating),
+						),
+						db.object().update(
+							object::id::equals(args.id),
+							vec![object::rating::set(args.rating)],
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "search.paths");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("bulkUpdate", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct BulkUpdateArgs {
+				pub object_ids: Vec<object::id::Type>,
+				#[serde(default)]
+				pub assign_tags: Vec<tag::id::Type>,
+				#[serde(default)]
+				pub unassign_tags: Vec<tag::id::Type>,
+				/// From 1 to 5 stars, `null` removes the rating and a missing field leaves it untouched
+				#[serde(default)]
+				pub rating: MaybeUndefined<i32>,
+				/// `null` removes the note and a missing field leaves it untouched
+				#[serde(default)]
+				pub note: MaybeUndefined<String>,
+				#[serde(default)]
+				pub favorite: Option<bool>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: BulkUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					if args.object_ids.len() > MAX_BULK_OBJECTS {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							format!("At most {MAX_BULK_OBJECTS} objects can be updated at once"),
+						));
+					}
+
+					if matches!(args.rating, MaybeUndefined::Value(rating) if !(1..=5).contains(&rating))
+					{
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"Rating must be between 1 and 5".to_string(),
+						));
+					}
+
+					if args
+						.assign_tags
+						.iter()
+						.any(|tag_id| args.unassign_tags.contains(tag_id))
+					{
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"A tag can't be both assigned and unassigned".to_string(),
+						));
+					}
+
+					let tag_ids = args
+						.assign_tags
+						.iter()
+						.chain(&args.unassign_tags)
+						.copied()
+						.collect::<Vec<_>>();
+
+					let (objects, tags) = db
+						._batch((
+							db.object()
+								.find_many(vec![object::id::in_vec(args.object_ids)])
+								.select(object::select!({ id pub_id })),
+							db.tag()
+								.find_many(vec![tag::id::in_vec(tag_ids.clone())])
+								.select(tag::select!({ id pub_id })),
+						))
+						.await?;
+
+					if let Some(missing) = tag_ids
+						.iter()
+						.find(|tag_id| !tags.iter().any(|tag| tag.id == **tag_id))
+					{
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Tag {missing} not found"),
+						));
+					}
+
+					if objects.is_empty() {
+						return Ok(0);
+					}
+
+					let object_ids = objects.iter().map(|o| o.id).collect::<Vec<_>>();
+					let tag_pub_id = |tag_id: tag::id::Type| {
+						tags.iter()
+							.find(|tag| tag.id == tag_id)
+							.map(|tag| tag.pub_id.clone())
+							.expect("tags were checked above")
+					};
+
+					let mut sync_ops = vec![];
+					let mut params = vec![];
+
+					let mut update_field =
+						|name: &'static str, value: rmpv::Value, param: object::SetParam| {
+							sync_ops.extend(objects.iter().map(|o| {
+								sync.shared_update(
+									prisma_sync::object::SyncId {
+										pub_id: o.pub_id.clone(),
+									},
+									name,
+									value.clone(),
+								)
+							}));
+							params.push(param);
+						};
+
+					if let Some(rating) = Option::<Option<i32>>::from(args.rating) {
+						update_field(
+							object::rating::NAME,
+							msgpack!(rating),
+							object::rating::set(rating),
+						);
+					}
+
+					if let Some(note) = Option::<Option<String>>::from(args.note) {
+						update_field(object::note::NAME, msgpack!(&note), object::note::set(note));
+					}
+
+					if let Some(favorite) = args.favorite {
+						update_field(
+							object::favorite::NAME,
+							msgpack!(favorite),
+							object::favorite::set(Some(favorite)),
+						);
+					}
+
+					let relation_sync_id = |tag_id: tag::id::Type, object_pub_id: Vec<u8>| {
+						prisma_sync::tag_on_object::SyncId {
+							tag: prisma_sync::tag::SyncId {
+								pub_id: tag_pub_id(tag_id),
+							},
+							object: prisma_sync::object::SyncId {
+								pub_id: object_pub_id,
+							},
+						}
+					};
+
+					let mut tag_creates = vec![];
+					for &tag_id in &args.assign_tags {
+						for o in &objects {
+							tag_creates.push(tag_on_object::CreateUnchecked {
+								tag_id,
+								object_id: o.id,
+								_params: vec![tag_on_object::date_created::set(Some(
+									Utc::now().into(),
+								))],
+							});
+
+							sync_ops.extend(
+								sync.relation_create(
+									relation_sync_id(tag_id, o.pub_id.clone()),
+									[],
+								),
+							);
+						}
+					}
+
+					for &tag_id in &args.unassign_tags {
+						sync_ops.extend(objects.iter().map(|o| {
+							sync.relation_delete(relation_sync_id(tag_id, o.pub_id.clone()))
+						}));
+					}
+
+					// Everything is written in a single batch, so either every object is updated or none
+					sync.write_ops(
+						db,
+						(
+							sync_ops,
+							(
+								(!params.is_empty())
+									.then(|| {
+										db.object().update_many(
+											vec![object::id::in_vec(object_ids.clone())],
+											params,
+										)
+									})
+									.into_iter()
+									.collect::<Vec<_>>(),
+								(!args.unassign_tags.is_empty())
+									.then(|| {
+										db.tag_on_object().delete_many(vec![
+											tag_on_object::tag_id::in_vec(args.unassign_tags),
+											tag_on_object::object_id::in_vec(object_ids),
+										])
+									})
+									.into_iter()
+									.collect::<Vec<_>>(),
+								(!tag_creates.is_empty())
+									.then(|| {
+										db.tag_on_object()
+											.create_many(tag_creates)
+											.skip_duplicates()
+									})
+									.into_iter()
+									.collect::<Vec<_>>(),
+							),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "search.paths");
+					invalidate_query!(library, "search.objects");
+					invalidate_query!(library, "tags.getForObject");
+					invalidate_query!(library, "tags.getWithObjects");
+
+					Ok(objects.len() as u32)
+				})
+		})
 		.procedure("createFolder", {
 			#[derive(Type, Deserialize)]
 			pub struct CreateFolderArgs {
@@ -353,7 +792,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					let objects = db
 						.object()
 						.find_many(vec![object::id::in_vec(ids)])
-						.select(object::select!({ id pub_id }))
+						.select(object::select!({ id pub_id access_count }))
 						.exec()
 						.await?;
 
@@ -362,28 +801,38 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					let (sync_params, db_params): (Vec<_>, Vec<_>) = objects
 						.into_iter()
 						.map(|d| {
+							// Counted here instead of incremented by the database, as the count is
+							// synced along with the access time
+							let access_count = d.access_count.unwrap_or_default() + 1;
+
 							(
-								sync.shared_update(
-									prisma_sync::object::SyncId { pub_id: d.pub_id },
-									object::date_accessed::NAME,
-									msgpack!(date_accessed),
+								[
+									sync.shared_update(
+										prisma_sync::object::SyncId {
+											pub_id: d.pub_id.clone(),
+										},
+										object::date_accessed::NAME,
+										msgpack!(date_accessed),
+									),
+									sync.shared_update(
+										prisma_sync::object::SyncId { pub_id: d.pub_id },
+										object::access_count::NAME,
+										msgpack!(access_count),
+									),
+								],
+								db.object().update(
+									object::id::equals(d.id),
+									vec![
+										object::date_accessed::set(Some(date_accessed)),
+										object::access_count::set(Some(access_count)),
+									],
 								),
-								d.id,
 							)
 						})
 						.unzip();
 
-					sync.write_ops(
-						db,
-						(
-							sync_params,
-							db.object().update_many(
-								vec![object::id::in_vec(db_params)],
-								vec![object::date_accessed::set(Some(date_accessed))],
-							),
-						),
-					)
-					.await?;
+					sync.write_ops(db, (sync_params.into_iter().flatten().collect(), db_params))
+						.await?;
 
 					invalidate_query!(library, "search.paths");
 					invalidate_query!(library, "search.objects");
@@ -449,7 +898,53 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				.mutation(|(node, library), args: OldFileDeleterJobInit| async move {
 					match args.file_path_ids.len() {
 						0 => Ok(()),
-						1 => {
+						// Dry runs always go through the job, so they get a report of what would happen
+						1 if !args.dry_run && !args.permanent => {
+							let location_path =
+								get_location_path_from_location_id(&library.db, args.location_id)
+									.await?;
+
+							for file_data in get_many_files_datas(
+								&library.db,
+								&location_path,
+								&args.file_path_ids,
+							)
+							.await?
+							{
+								match move_to_trash(
+									&library,
+									args.location_id,
+									&location_path,
+									&file_data,
+								)
+								.await
+								{
+									Ok(_) => {}
+									Err(FileSystemJobsError::FileIO(FileIOError {
+										source,
+										..
+									})) if source.kind() == io::ErrorKind::NotFound => {
+										warn!(
+											"File not found in the file system, will remove from database: {}",
+											file_data.full_path.display()
+										);
+										library
+											.db
+											.file_path()
+											.delete(file_path::id::equals(file_data.file_path.id))
+											.exec()
+											.await?;
+									}
+									Err(e) => return Err(e.into()),
+								}
+							}
+
+							invalidate_query!(library, "search.paths");
+							invalidate_query!(library, "trash.list");
+
+							Ok(())
+						}
+						1 if !args.dry_run => {
 							let (maybe_location, maybe_file_path) = library
 								.db
 								._batch((
@@ -457,7 +952,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 										.db
 										.location()
 										.find_unique(location::id::equals(args.location_id))
-										.select(location::select!({ path })),
+										.select(location::select!({ path remote })),
 									library
 										.db
 										.file_path()
@@ -466,8 +961,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 								))
 								.await?;
 
-							let location_path = maybe_location
-								.ok_or(LocationError::IdNotFound(args.location_id))?
+							let location = maybe_location
+								.ok_or(LocationError::IdNotFound(args.location_id))?;
+							if location.remote.is_some() {
+								return Err(LocationError::RemoteLocation(args.location_id).into());
+							}
+							let location_path = location
 								.path
 								.ok_or(LocationError::MissingPath(args.location_id))?;
 
@@ -521,7 +1020,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				.mutation(|(node, library), args: OldFileDeleterJobInit| async move {
 					match args.file_path_ids.len() {
 						0 => Ok(()),
-						1 => {
+						// Dry runs always go through the job, so they get a report of what would happen
+						1 if !args.dry_run => {
 							let (maybe_location, maybe_file_path) = library
 								.db
 								._batch((
@@ -529,7 +1029,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 										.db
 										.location()
 										.find_unique(location::id::equals(args.location_id))
-										.select(location::select!({ path })),
+										.select(location::select!({ path remote })),
 									library
 										.db
 										.file_path()
@@ -538,8 +1038,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 								))
 								.await?;
 
-							let location_path = maybe_location
-								.ok_or(LocationError::IdNotFound(args.location_id))?
+							let location = maybe_location
+								.ok_or(LocationError::IdNotFound(args.location_id))?;
+							if location.remote.is_some() {
+								return Err(LocationError::RemoteLocation(args.location_id).into());
+							}
+							let location_path = location
 								.path
 								.ok_or(LocationError::MissingPath(args.location_id))?;
 
@@ -726,6 +1230,74 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.map_err(Into::into)
 				})
 		})
+		.procedure("copy", {
+			// Copies through the same code the files of the locations, of the paths outside of them
+			// and of remote services, returning the id of the operation in `files.opProgress`
+			R.with2(library())
+				.mutation(|(node, library), args: FileOpArgs| async move {
+					spawn_file_op(node, library, FileOpKind::Copy, args).await
+				})
+		})
+		.procedure("move", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileOpArgs| async move {
+					spawn_file_op(node, library, FileOpKind::Move, args).await
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(node, library), source: FileOpSource| async move {
+					// The files of the locations go into their trash, to be restored with `trash.restore`
+					let (source, paths, trash) = source.into_paths(&library).await?;
+
+					Ok(FileOp {
+						kind: FileOpKind::Delete,
+						source,
+						paths,
+						target: None,
+						conflict: ConflictResolution::default(),
+						trash,
+					}
+					.spawn(node, library))
+				})
+		})
+		.procedure("opProgress", {
+			// The progress of the `files.copy`, `files.move` and `files.delete` operations of the
+			// library, at most 30 times a second for each of them
+			R.with2(library())
+				.subscription(|(node, library), _: ()| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+					let mut intervals = BTreeMap::<Uuid, Instant>::new();
+
+					async_stream::stream! {
+						while let Ok(event) = event_bus_rx.recv().await {
+							let CoreEvent::FileOpProgress(progress) = event else {
+								continue;
+							};
+
+							if progress.library_id != library.id {
+								continue;
+							}
+
+							// The last event of each operation is always sent
+							if progress.done {
+								intervals.remove(&progress.id);
+								yield progress;
+								continue;
+							}
+
+							let instant = intervals.entry(progress.id).or_insert_with(Instant::now);
+							if instant.elapsed() <= Duration::from_secs_f64(1.0 / 30.0) {
+								continue;
+							}
+
+							yield progress;
+
+							*instant = Instant::now();
+						}
+					}
+				})
+		})
 		.procedure("renameFile", {
 			#[derive(Type, Deserialize)]
 			pub struct RenameOne {
@@ -738,6 +1310,9 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub from_pattern: FromPattern,
 				pub to_pattern: String,
 				pub from_file_path_ids: Vec<file_path::id::Type>,
+				/// Only report the renames that would happen, without renaming anything
+				#[serde(default)]
+				pub dry_run: bool,
 			}
 
 			#[derive(Type, Deserialize)]
@@ -828,10 +1403,11 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						from_pattern,
 						to_pattern,
 						from_file_path_ids,
+						dry_run,
 					}: RenameMany,
 					location_path: impl AsRef<Path>,
 					library: &Library,
-				) -> Result<(), rspc::Error> {
+				) -> Result<Option<DryRunReport>, rspc::Error> {
 					let location_path = location_path.as_ref();
 
 					let Ok(from_regex) = Regex::new(&from_pattern.pattern) else {
@@ -841,7 +1417,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						));
 					};
 
-					let errors = join_all(
+					let (actions, errors): (Vec<_>, Vec<_>) = join_all(
 						library
 							.db
 							.file_path()
@@ -871,6 +1447,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 											ErrorCode::BadRequest,
 											"Invalid file name".to_string(),
 										))
+									} else if dry_run {
+										Ok(DryRunAction::Rename { from, to })
 									} else {
 										fs::rename(&from, &to).await.map_err(|e| {
 											error!(
@@ -883,28 +1461,30 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 												"Failed to rename file".to_string(),
 												e,
 											)
-										})
+										})?;
+
+										Ok(DryRunAction::Rename { from, to })
 									}
 								}
 							}),
 					)
 					.await
 					.into_iter()
-					.filter_map(Result::err)
-					.collect::<Vec<_>>();
+					.partition(Result::is_ok);
 
 					if !errors.is_empty() {
 						return Err(rspc::Error::new(
 							rspc::ErrorCode::Conflict,
 							errors
 								.into_iter()
+								.filter_map(Result::err)
 								.map(|e| e.to_string())
 								.collect::<Vec<_>>()
 								.join("\n"),
 						));
 					}
 
-					Ok(())
+					Ok(dry_run.then(|| actions.into_iter().filter_map(Result::ok).collect()))
 				}
 			}
 
@@ -915,7 +1495,9 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 
 					let res = match kind {
 						RenameKind::One(one) => {
-							RenameFileArgs::rename_one(one, location_path, &library).await
+							RenameFileArgs::rename_one(one, location_path, &library)
+								.await
+								.map(|()| None)
 						}
 						RenameKind::Many(many) => {
 							RenameFileArgs::rename_many(many, location_path, &library).await
@@ -1014,3 +1596,30 @@ pub struct FromPattern {
 	pub pattern: String,
 	pub replace_all: bool,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn location_targets_stay_in_their_location() {
+		let location_path = Path::new("/locations/photos");
+
+		assert_eq!(
+			location_sub_path(location_path, "/2024/./trip").expect("in the location"),
+			Path::new("/locations/photos/2024/trip")
+		);
+		assert_eq!(
+			location_sub_path(location_path, "/").expect("the location root"),
+			location_path
+		);
+
+		for path in [
+			"../../etc/passwd",
+			"/2024/../../../etc/passwd",
+			"//etc/passwd/..",
+		] {
+			assert!(location_sub_path(location_path, path).is_err(), "{path}");
+		}
+	}
+}
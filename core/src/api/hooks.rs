@@ -0,0 +1,183 @@
+use crate::{
+	invalidate_query,
+	library::{HookRunner, ScriptHook, WebhookEventFilter},
+};
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::Deserialize;
+use specta::Type;
+use tracing::error;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+const DEFAULT_TIMEOUT_SECS: u32 = 60;
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.hooks) })
+		})
+		.procedure("setEnabled", {
+			R.mutation(|node, enabled: bool| async move {
+				node.config
+					.update_preferences(|preferences| {
+						preferences.script_hooks.enabled = enabled;
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update script hook preferences: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update script hook preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct CreateHookArgs {
+				pub name: String,
+				pub command: String,
+				pub events: Vec<WebhookEventFilter>,
+				#[serde(default)]
+				pub timeout_secs: Option<u32>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 CreateHookArgs {
+				     name,
+				     command,
+				     events,
+				     timeout_secs,
+				 }: CreateHookArgs| async move {
+					let hook = ScriptHook {
+						id: Uuid::new_v4(),
+						name,
+						command,
+						events,
+						timeout_secs: timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+					};
+
+					ensure_enabled(&library.hook_runner)?;
+					validate_hook(&hook)?;
+
+					let id = hook.id;
+
+					library
+						.update_config(
+							|config| config.hooks.push(hook),
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "hooks.list");
+
+					Ok(id)
+				},
+			)
+		})
+		.procedure("update", {
+			R.with2(library())
+				.mutation(|(node, library), hook: ScriptHook| async move {
+					ensure_enabled(&library.hook_runner)?;
+					validate_hook(&hook)?;
+
+					if !library
+						.config()
+						.await
+						.hooks
+						.iter()
+						.any(|existing| existing.id == hook.id)
+					{
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Hook '{}' not found", hook.id),
+						));
+					}
+
+					library
+						.update_config(
+							|config| {
+								if let Some(existing) = config
+									.hooks
+									.iter_mut()
+									.find(|existing| existing.id == hook.id)
+								{
+									*existing = hook;
+								}
+							},
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "hooks.list");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(node, library), id: Uuid| async move {
+					library
+						.update_config(
+							|config| config.hooks.retain(|hook| hook.id != id),
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "hooks.list");
+
+					Ok(())
+				})
+		})
+		.procedure("invocations", {
+			R.with2(library())
+				.query(|(_, library), hook_id: Option<Uuid>| async move {
+					Ok(library.hook_runner.invocations(hook_id))
+				})
+		})
+}
+
+fn ensure_enabled(runner: &HookRunner) -> Result<(), rspc::Error> {
+	if !runner.enabled() {
+		return Err(rspc::Error::new(
+			ErrorCode::Forbidden,
+			"Script hooks are disabled on this node".to_string(),
+		));
+	}
+
+	Ok(())
+}
+
+fn validate_hook(hook: &ScriptHook) -> Result<(), rspc::Error> {
+	if hook.command.trim().is_empty() {
+		return Err(rspc::Error::new(
+			ErrorCode::BadRequest,
+			"Hook command can't be empty".to_string(),
+		));
+	}
+
+	if hook.timeout_secs == 0 {
+		return Err(rspc::Error::new(
+			ErrorCode::BadRequest,
+			"Hook timeout must be at least a second".to_string(),
+		));
+	}
+
+	Ok(())
+}
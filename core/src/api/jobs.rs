@@ -4,9 +4,16 @@ use crate::{
 	object::{
 		media::OldMediaProcessorJobInit,
 		old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
-		validation::old_validator_job::OldObjectValidatorJobInit,
+		old_metadata_importer::{ImportSource, OldMetadataImporterJobInit},
+		validation::{old_validator_job::OldObjectValidatorJobInit, ChecksumSettings},
+		xmp::{old_xmp_exporter_job::OldXmpExporterJobInit, XmpSidecarNaming},
+	},
+	old_job::{
+		get_job_history, preferences::JobOffloadPreferences, prune_job_history, Job,
+		JobHistoryEntry, JobHistoryFilterArgs, JobHistoryRetention, JobNotificationSettings,
+		JobPriority, JobProgressEvent, JobReport, JobResourceLimits, JobStatus, OldJobs,
+		RetryPolicy,
 	},
-	old_job::{Job, JobReport, JobStatus, OldJobs},
 };
 
 use sd_core_prisma_helpers::job_without_data;
@@ -16,19 +23,27 @@ use sd_prisma::prisma::{job, location, SortOrder};
 use std::{
 	collections::{hash_map::Entry, BTreeMap, HashMap, VecDeque},
 	path::PathBuf,
-	time::Instant,
 };
 
 use chrono::{DateTime, Utc};
 use prisma_client_rust::or;
-use rspc::alpha::AlphaRouter;
+use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use tokio::time::Duration;
-use tracing::{info, trace};
+use tokio::{
+	sync::broadcast::error::RecvError,
+	time::{sleep_until, Duration, Instant},
+};
+use tracing::{error, info, trace};
 use uuid::Uuid;
 
-use super::{utils::library, CoreEvent, Ctx, R};
+use super::{
+	utils::{library, Pagination, MAX_TAKE},
+	CoreEvent, Ctx, R,
+};
+
+/// Minimum time between the events of `progressSubscribe`, enough for a smooth progress bar
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(33);
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
@@ -66,6 +81,112 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}
 				})
 		})
+		.procedure("progressSubscribe", {
+			// Streams the progress of a single job, with its throughput and the time it has left,
+			// until the job completes, fails or is canceled. Queued jobs send their first progress
+			// once they are dispatched to a worker
+			R.with2(library())
+				.subscription(|(node, library), job_id: Uuid| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					let status = library
+						.db
+						.job()
+						.find_unique(job::id::equals(job_id.as_bytes().to_vec()))
+						.select(job::select!({ status }))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Job not found".to_string())
+						})?
+						.status
+						.map(JobStatus::try_from)
+						.transpose()
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to read the job status".to_string(),
+								e,
+							)
+						})?;
+					let already_finished = status
+						.is_some_and(|status| status.is_finished() && status != JobStatus::Paused);
+
+					Ok(async_stream::stream! {
+						if already_finished {
+							return;
+						}
+
+						// The latest progress held back by the throttle, sent once it's over so the
+						// last one isn't lost
+						let mut pending = None::<JobProgressEvent>;
+						let mut last_sent_at = None::<Instant>;
+
+						loop {
+							let event = tokio::select! {
+								event = event_bus_rx.recv() => Some(event),
+								() = sleep_until(
+									last_sent_at.map_or_else(Instant::now, |at| at + PROGRESS_INTERVAL)
+								), if pending.is_some() => None,
+							};
+
+							match event {
+								None => {
+									if let Some(progress_event) = pending.take() {
+										last_sent_at = Some(Instant::now());
+										yield progress_event;
+									}
+								}
+								Some(Ok(CoreEvent::JobProgress(progress_event)))
+									if progress_event.library_id == library.id
+										&& progress_event.id == job_id =>
+								{
+									if last_sent_at.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+										pending = Some(progress_event);
+									} else {
+										pending = None;
+										last_sent_at = Some(Instant::now());
+										yield progress_event;
+									}
+								}
+								Some(Ok(CoreEvent::JobFinished { library_id, id, .. }))
+									if library_id == library.id && id == job_id =>
+								{
+									if let Some(progress_event) = pending.take() {
+										yield progress_event;
+									}
+
+									break;
+								}
+								// Only the latest progress matters, so we can skip the ones we missed
+								Some(Ok(_) | Err(RecvError::Lagged(_))) => {}
+								Some(Err(RecvError::Closed)) => break,
+							}
+						}
+					})
+				})
+		})
+		.procedure("itemProgress", {
+			// Streams progress on each item processed by running jobs, optionally only for a single job
+			R.with2(library())
+				.subscription(|(node, library), job_id: Option<Uuid>| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					async_stream::stream! {
+						while let Ok(event) = event_bus_rx.recv().await {
+							match event {
+								CoreEvent::JobItemProgress(item_event)
+									if item_event.library_id == library.id
+										&& job_id.map_or(true, |job_id| job_id == item_event.id) =>
+								{
+									yield item_event
+								}
+								_ => {}
+							}
+						}
+					}
+				})
+		})
 		.procedure("reports", {
 			// Reports provides the client with a list of JobReports
 			// - we query with a custom select! to avoid returning paused job cache `job.data`
@@ -230,6 +351,318 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					ret
 				})
 		})
+		.procedure("priorities", {
+			R.query(|node, _: ()| async move { Ok(node.old_jobs.priorities().await) })
+		})
+		.procedure("setPriority", {
+			#[derive(Type, Deserialize)]
+			pub struct SetPriorityArgs {
+				pub id: Uuid,
+				pub priority: JobPriority,
+			}
+
+			R.mutation(
+				|node, SetPriorityArgs { id, priority }: SetPriorityArgs| async move {
+					node.old_jobs.set_priority(id, priority).await?;
+
+					invalidate_query!(node; node, "jobs.priorities");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("retryPolicies", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.jobs
+					.retry_policies()
+					.clone())
+			})
+		})
+		.procedure("setRetryPolicy", {
+			#[derive(Type, Deserialize)]
+			pub struct SetRetryPolicyArgs {
+				pub job_name: String,
+				pub policy: Option<RetryPolicy>,
+			}
+
+			R.mutation(
+				|node, SetRetryPolicyArgs { job_name, policy }: SetRetryPolicyArgs| async move {
+					node.config
+						.update_preferences(|preferences| {
+							preferences.jobs.set_retry_policy(job_name, policy);
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to update job retry policy: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to update job retry policy".to_string(),
+								e,
+							)
+						})?;
+
+					invalidate_query!(node; node, "jobs.retryPolicies");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("resourceLimits", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.jobs
+					.all_resource_limits()
+					.clone())
+			})
+		})
+		.procedure("setResourceLimits", {
+			#[derive(Type, Deserialize)]
+			pub struct SetResourceLimitsArgs {
+				pub job_name: String,
+				pub limits: JobResourceLimits,
+			}
+
+			R.mutation(
+				|node, SetResourceLimitsArgs { job_name, limits }: SetResourceLimitsArgs| async move {
+					node.config
+						.update_preferences(|preferences| {
+							preferences.jobs.set_resource_limits(job_name, limits);
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to update job resource limits: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to update job resource limits".to_string(),
+								e,
+							)
+						})?;
+
+					invalidate_query!(node; node, "jobs.resourceLimits");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("concurrencyLimits", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.jobs
+					.concurrency_limits()
+					.clone())
+			})
+		})
+		.procedure("setConcurrencyLimit", {
+			#[derive(Type, Deserialize)]
+			pub struct SetConcurrencyLimitArgs {
+				pub job_name: String,
+				pub limit: Option<u32>,
+			}
+
+			R.mutation(
+				|node, SetConcurrencyLimitArgs { job_name, limit }: SetConcurrencyLimitArgs| async move {
+					node.config
+						.update_preferences(|preferences| {
+							preferences.jobs.set_concurrency_limit(job_name, limit);
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to update job concurrency limit: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to update job concurrency limit".to_string(),
+								e,
+							)
+						})?;
+
+					invalidate_query!(node; node, "jobs.concurrencyLimits");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("offloadSettings", {
+			R.query(
+				|node, _: ()| async move { Ok(node.config.get().await.preferences.jobs.offload()) },
+			)
+		})
+		.procedure("setOffloadSettings", {
+			R.mutation(|node, offload: JobOffloadPreferences| async move {
+				node.config
+					.update_preferences(|preferences| {
+						preferences.jobs.set_offload(offload);
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update job offload settings: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update job offload settings".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "jobs.offloadSettings");
+
+				Ok(())
+			})
+		})
+		.procedure("notificationSettings", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library.config().await.job_notifications)
+			})
+		})
+		.procedure("setNotificationSettings", {
+			R.with2(library()).mutation(
+				|(node, library), settings: JobNotificationSettings| async move {
+					library
+						.update_config(
+							|config| config.job_notifications = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "jobs.notificationSettings");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("checksumSettings", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.checksums) })
+		})
+		.procedure("setChecksumSettings", {
+			R.with2(library())
+				.mutation(|(node, library), settings: ChecksumSettings| async move {
+					library
+						.update_config(
+							|config| config.checksums = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "jobs.checksumSettings");
+
+					Ok(())
+				})
+		})
+		.procedure("history", {
+			#[derive(Type, Deserialize)]
+			pub struct JobHistoryArgs {
+				#[serde(default)]
+				pub take: Option<u8>,
+				#[serde(default)]
+				pub cursor: Option<Uuid>,
+				#[serde(default)]
+				pub filters: Vec<JobHistoryFilterArgs>,
+			}
+
+			#[derive(Serialize, Type)]
+			pub struct JobHistoryPage {
+				items: Vec<JobHistoryEntry>,
+				cursor: Option<Uuid>,
+			}
+
+			R.with2(library()).query(
+				|(_, library),
+				 JobHistoryArgs {
+				     take,
+				     cursor,
+				     filters,
+				 }: JobHistoryArgs| async move {
+					let (items, cursor) = get_job_history(
+						&library,
+						filters,
+						cursor,
+						Pagination::new(take.map(u16::from), 0, u16::from(MAX_TAKE)),
+					)
+					.await
+					.map_err(|e| {
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to fetch job history".to_string(),
+							e,
+						)
+					})?;
+
+					Ok(JobHistoryPage { items, cursor })
+				},
+			)
+		})
+		.procedure("historyRetention", {
+			R.query(|node, _: ()| async move {
+				Ok(node.config.get().await.preferences.jobs.history_retention())
+			})
+		})
+		.procedure("setHistoryRetention", {
+			R.mutation(|node, retention: JobHistoryRetention| async move {
+				node.config
+					.update_preferences(|preferences| {
+						preferences.jobs.set_history_retention(retention);
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update job history retention: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update job history retention".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "jobs.historyRetention");
+
+				Ok(())
+			})
+		})
+		.procedure("pruneHistory", {
+			#[derive(Type, Deserialize)]
+			pub struct PruneHistoryArgs {
+				/// Only count the jobs that would be removed
+				#[serde(default)]
+				pub dry_run: bool,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), args: PruneHistoryArgs| async move {
+					let retention = node.config.get().await.preferences.jobs.history_retention();
+
+					let removed = prune_job_history(&library, retention, args.dry_run)
+						.await
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to prune job history".to_string(),
+								e,
+							)
+						})?;
+
+					if !args.dry_run {
+						invalidate_query!(library, "jobs.reports");
+						invalidate_query!(library, "jobs.history");
+					}
+
+					Ok(removed as u32)
+				})
+		})
 		.procedure("generateThumbsForLocation", {
 			#[derive(Type, Deserialize)]
 			pub struct GenerateThumbsForLocationArgs {
@@ -339,6 +772,55 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				},
 			)
 		})
+		.procedure("importMetadata", {
+			#[derive(Type, Deserialize)]
+			pub struct ImportMetadataArgs {
+				pub id: location::id::Type,
+				pub source: ImportSource,
+				pub source_root: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), args: ImportMetadataArgs| async move {
+					if find_location(&library, args.id).exec().await?.is_none() {
+						return Err(LocationError::IdNotFound(args.id).into());
+					}
+
+					Job::new(OldMetadataImporterJobInit {
+						location_id: args.id,
+						source: args.source,
+						source_root: args.source_root,
+					})
+					.spawn(&node, &library)
+					.await
+					.map_err(Into::into)
+				})
+		})
+		.procedure("exportXmp", {
+			#[derive(Type, Deserialize)]
+			pub struct ExportXmpArgs {
+				pub id: location::id::Type,
+				pub path: PathBuf,
+				#[serde(default)]
+				pub naming: XmpSidecarNaming,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), args: ExportXmpArgs| async move {
+					let Some(location) = find_location(&library, args.id).exec().await? else {
+						return Err(LocationError::IdNotFound(args.id).into());
+					};
+
+					Job::new(OldXmpExporterJobInit {
+						location,
+						sub_path: Some(args.path),
+						naming: args.naming,
+					})
+					.spawn(&node, &library)
+					.await
+					.map_err(Into::into)
+				})
+		})
 		.procedure("newThumbnail", {
 			R.with2(library())
 				.subscription(|(node, _), _: ()| async move {
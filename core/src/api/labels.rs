@@ -1,5 +1,7 @@
 use crate::{
-	invalidate_query, library::Library, object::media::old_thumbnail::get_indexed_thumb_key,
+	invalidate_query,
+	library::Library,
+	object::media::old_thumbnail::{get_indexed_thumb_key, ThumbnailTier},
 };
 
 use sd_core_prisma_helpers::label_with_objects;
@@ -46,10 +48,13 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 									label_object.object.file_paths.into_iter().next()
 								})
 								.filter_map(|file_path_data| {
-									file_path_data
-										.cas_id
-										.as_ref()
-										.map(|cas_id| get_indexed_thumb_key(cas_id, library.id))
+									file_path_data.cas_id.as_ref().map(|cas_id| {
+										get_indexed_thumb_key(
+											cas_id,
+											library.id,
+											ThumbnailTier::Grid,
+										)
+									})
 								}) // Filter out None values and transform each element to Vec<Vec<String>>
 								.collect::<Vec<_>>(), // Collect into Vec<Vec<Vec<String>>>
 						})
@@ -1,17 +1,21 @@
 use crate::{
+	api::search::SearchFilterArgs,
 	invalidate_query,
-	library::{update_library_statistics, Library, LibraryConfig, LibraryName},
+	library::{update_library_statistics, DatabaseSettings, Library, LibraryConfig, LibraryName},
 	location::{scan_location, LocationCreateArgs, ScanState},
 	util::MaybeUndefined,
 	Node,
 };
 
 use futures::StreamExt;
-use prisma_client_rust::raw;
+use prisma_client_rust::{or, raw};
 use sd_cache::{Model, Normalise, NormalisedResult, NormalisedResults};
 use sd_file_ext::kind::ObjectKind;
 use sd_p2p::RemoteIdentity;
-use sd_prisma::prisma::{indexer_rule, object, statistics};
+use sd_prisma::prisma::{
+	file_path, indexer_rule, location, object, statistics, PrismaClient, SortOrder,
+};
+use sd_utils::db::size_in_bytes_from_db;
 use tokio_stream::wrappers::IntervalStream;
 use tracing::{info, warn};
 
@@ -44,6 +48,8 @@ use super::{utils::library, Ctx, R};
 const ONE_MINUTE: Duration = Duration::from_secs(60);
 const TWO_MINUTES: Duration = Duration::from_secs(60 * 2);
 const FIVE_MINUTES: Duration = Duration::from_secs(60 * 5);
+/// Number of file paths read at once from the database while adding up the sizes per kind
+const KIND_STATISTICS_BATCH_SIZE: i64 = 10_000;
 
 static STATISTICS_UPDATERS: Lazy<Mutex<HashMap<Uuid, chan::Sender<Instant>>>> =
 	Lazy::new(|| Mutex::new(HashMap::new()));
@@ -64,12 +70,19 @@ impl Model for LibraryConfigWrapped {
 }
 
 impl LibraryConfigWrapped {
+	/// The secrets of the webhooks are left out, the clients only learn if a webhook has one
+	/// through `webhooks.list`.
 	pub async fn from_library(library: &Library) -> Self {
+		let mut config = library.config().await;
+		for webhook in &mut config.webhooks {
+			webhook.secret = None;
+		}
+
 		Self {
 			uuid: library.id,
 			instance_id: library.instance_uuid,
 			instance_public_key: library.identity.to_remote_identity(),
-			config: library.config().await,
+			config,
 		}
 	}
 }
@@ -83,14 +96,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					.get_all()
 					.await
 					.into_iter()
-					.map(|lib| async move {
-						LibraryConfigWrapped {
-							uuid: lib.id,
-							instance_id: lib.instance_uuid,
-							instance_public_key: lib.identity.to_remote_identity(),
-							config: lib.config().await,
-						}
-					})
+					.map(|lib| async move { LibraryConfigWrapped::from_library(&lib).await })
 					.collect::<Vec<_>>()
 					.join()
 					.await;
@@ -132,36 +138,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				})
 		})
 		.procedure("kindStatistics", {
-			#[derive(Serialize, Deserialize, Type, Default)]
-			pub struct KindStatistic {
-				kind: i32,
-				name: String,
-				count: i32,
-				total_bytes: String,
-			}
-			#[derive(Serialize, Deserialize, Type, Default)]
-			pub struct KindStatistics {
-				statistics: Vec<KindStatistic>,
-			}
 			R.with2(library()).query(|(_, library), _: ()| async move {
-				let mut statistics: Vec<KindStatistic> = vec![];
-				for kind in ObjectKind::iter() {
-					let count = library
-						.db
-						.object()
-						.count(vec![object::kind::equals(Some(kind as i32))])
-						.exec()
-						.await?;
-
-					statistics.push(KindStatistic {
-						kind: kind as i32,
-						name: kind.to_string(),
-						count: count as i32,
-						total_bytes: "0".to_string(),
-					});
-				}
-
-				Ok(KindStatistics { statistics })
+				kind_statistics(&library.db, vec![])
+					.await
+					.map_err(Into::into)
 			})
 		})
 		.procedure("create", {
@@ -408,6 +388,188 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				}),
 		)
+		.procedure("databaseSettings", {
+			#[derive(Serialize, Type)]
+			#[serde(rename_all = "camelCase")]
+			pub struct DatabaseSettingsProfiles {
+				current: DatabaseSettings,
+				default: DatabaseSettings,
+				large_library: DatabaseSettings,
+			}
+
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(DatabaseSettingsProfiles {
+					current: library.config().await.database,
+					default: DatabaseSettings::default(),
+					large_library: DatabaseSettings::large_library(),
+				})
+			})
+		})
+		.procedure(
+			"setDatabaseSettings",
+			R.with2(library())
+				.mutation(|(node, library), settings: DatabaseSettings| async move {
+					settings.validate()?;
+					settings.apply(&library.db).await?;
+
+					library
+						.update_config(
+							|config| config.database = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "library.databaseSettings");
+
+					Ok(())
+				}),
+		)
+		.merge("statistics.", mount_statistics_routes())
+}
+
+fn mount_statistics_routes() -> AlphaRouter<Ctx> {
+	R.router().procedure("kinds", {
+		#[derive(Deserialize, Type)]
+		#[serde(rename_all = "camelCase")]
+		pub struct KindStatisticsArgs {
+			#[serde(default)]
+			location_id: Option<location::id::Type>,
+			#[serde(default)]
+			filters: Vec<SearchFilterArgs>,
+		}
+
+		R.with2(library()).query(
+			|(_, library),
+			 KindStatisticsArgs {
+			     location_id,
+			     filters,
+			 }: KindStatisticsArgs| async move {
+				let mut params = location_id
+					.map(|location_id| vec![file_path::location_id::equals(Some(location_id))])
+					.unwrap_or_default();
+				for filter in filters {
+					params.extend(filter.into_file_path_params(&library.db).await?);
+				}
+
+				kind_statistics(&library.db, params)
+					.await
+					.map_err(Into::into)
+			},
+		)
+	})
+}
+
+#[derive(Serialize, Deserialize, Type, Default)]
+pub struct KindStatistic {
+	kind: i32,
+	name: String,
+	/// Number of objects of the kind
+	count: i32,
+	/// Size of every file of the kind, so the copies of an object are all counted
+	total_bytes: String,
+}
+
+#[derive(Serialize, Deserialize, Type, Default)]
+pub struct KindStatistics {
+	statistics: Vec<KindStatistic>,
+}
+
+/// Counts the objects and adds up the sizes of the files matching `params`, per kind. Every kind
+/// is listed, even the ones without any object.
+///
+/// The objects are counted by the database, and the sizes are added up reading the file paths in
+/// batches, as SQLite can't add up the sizes stored as bytes.
+async fn kind_statistics(
+	db: &PrismaClient,
+	mut params: Vec<file_path::WhereParam>,
+) -> Result<KindStatistics, prisma_client_rust::QueryError> {
+	params.extend([
+		file_path::is_dir::equals(Some(false)),
+		file_path::object_id::not(None),
+	]);
+
+	let counts = ObjectKind::iter()
+		.map(|kind| {
+			let kind_param = if kind == ObjectKind::Unknown {
+				or![
+					object::kind::equals(None),
+					object::kind::equals(Some(kind as i32))
+				]
+			} else {
+				object::kind::equals(Some(kind as i32))
+			};
+
+			db.object()
+				.count(vec![kind_param, object::file_paths::some(params.clone())])
+				.exec()
+		})
+		.collect::<Vec<_>>()
+		.join()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut total_bytes_by_kind = HashMap::<i32, u64>::new();
+	let mut last_id = 0;
+
+	loop {
+		let file_paths = db
+			.file_path()
+			.find_many(
+				params
+					.iter()
+					.cloned()
+					.chain([file_path::id::gt(last_id)])
+					.collect(),
+			)
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(KIND_STATISTICS_BATCH_SIZE)
+			.select(file_path::select!({ id size_in_bytes_bytes object: select { kind } }))
+			.exec()
+			.await?;
+
+		let Some(last) = file_paths.last() else {
+			break;
+		};
+		last_id = last.id;
+		let is_last_batch = (file_paths.len() as i64) < KIND_STATISTICS_BATCH_SIZE;
+
+		for file_path in file_paths {
+			*total_bytes_by_kind
+				.entry(
+					file_path
+						.object
+						.and_then(|object| object.kind)
+						.unwrap_or(ObjectKind::Unknown as i32),
+				)
+				.or_default() += file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.map(size_in_bytes_from_db)
+				.unwrap_or_default();
+		}
+
+		if is_last_batch {
+			break;
+		}
+	}
+
+	Ok(KindStatistics {
+		statistics: ObjectKind::iter()
+			.zip(counts)
+			.map(|(kind, count)| KindStatistic {
+				kind: kind as i32,
+				name: kind.to_string(),
+				count: count as i32,
+				total_bytes: total_bytes_by_kind
+					.remove(&(kind as i32))
+					.unwrap_or_default()
+					.to_string(),
+			})
+			.collect(),
+	})
 }
 
 async fn update_statistics_loop(
@@ -1,24 +1,41 @@
 use crate::{
 	invalidate_query,
 	location::{
-		delete_location, find_location, indexer::OldIndexerJobInit, light_scan_location,
-		relink_location, scan_location, scan_location_sub_path, LocationCreateArgs, LocationError,
-		LocationUpdateArgs, ScanState,
+		delete_location, find_location,
+		indexer::OldIndexerJobInit,
+		ingest::{self, IngestInbox, IngestSettings, IngestSource},
+		light_scan_location, relink_location,
+		remote::RemoteLocationCreateArgs,
+		scan_location, scan_location_sub_path,
+		usage::usage_tree,
+		LocationCreateArgs, LocationError, LocationUpdateArgs, ScanState,
 	},
-	object::old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
-	old_job::StatefulJob,
+	object::{
+		old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
+		validation::{old_location_validator_job::OldLocationValidatorJobInit, MismatchKind},
+	},
+	old_job::{Job, StatefulJob},
 	p2p::PeerMetadata,
 	util::AbortOnDrop,
 };
 
-use sd_core_indexer_rules::IndexerRuleCreateArgs;
+use sd_core_file_path_helper::{check_file_path_exists, IsolatedFilePathData};
+use sd_core_indexer_rules::{
+	cache as indexer_rules_cache, IndexerRuleCreateArgs, IndexerRuleUpdateArgs,
+};
 use sd_core_prisma_helpers::{
 	file_path_with_object, label_with_objects, location_with_indexer_rules, object_with_file_paths,
 };
 
-use sd_cache::{CacheNode, Model, Normalise, NormalisedResult, NormalisedResults, Reference};
+use sd_cache::{
+	CacheKey, CacheNode, Model, Normalise, NormalisedResult, NormalisedResults, Reference,
+};
 use sd_indexer::NonIndexedPathItem;
-use sd_prisma::prisma::{file_path, indexer_rule, indexer_rules_in_location, location, SortOrder};
+use sd_media_metadata::MediaMetadata;
+use sd_prisma::prisma::{
+	file_path, indexer_rule, indexer_rules_in_location, location, object, validation_mismatch,
+	SortOrder,
+};
 
 use std::path::{Path, PathBuf};
 
@@ -29,7 +46,11 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use tracing::{debug, error};
 
-use super::{utils::library, Ctx, R};
+use super::{
+	error::ApiError,
+	utils::{library, paginate, CursorArgs, NormalisedPage},
+	Ctx, R,
+};
 
 // it includes the shard hex formatted as ([["f02", "cab34a76fbf3469f"]])
 // Will be None if no thumbnail exists
@@ -41,10 +62,18 @@ pub enum ExplorerItem {
 	Path {
 		thumbnail: Option<ThumbnailKey>,
 		item: file_path_with_object::Data,
+		/// The media data of the object, only read when the search asks for it
+		#[serde(skip_serializing_if = "Option::is_none")]
+		#[specta(optional)]
+		media_data: Option<MediaMetadata>,
 	},
 	Object {
 		thumbnail: Option<ThumbnailKey>,
 		item: object_with_file_paths::Data,
+		/// The media data of the object, only read when the search asks for it
+		#[serde(skip_serializing_if = "Option::is_none")]
+		#[specta(optional)]
+		media_data: Option<MediaMetadata>,
 	},
 	Location {
 		item: location::Data,
@@ -89,6 +118,18 @@ impl ExplorerItem {
 			ExplorerItem::Label { item, .. } => format!("{ty}:{}", item.name),
 		}
 	}
+
+	pub fn file_path_cache_key(id: file_path::id::Type) -> CacheKey {
+		CacheKey::new::<Self>(format!("FilePath:{id}"))
+	}
+
+	pub fn object_cache_key(id: object::id::Type) -> CacheKey {
+		CacheKey::new::<Self>(format!("Object:{id}"))
+	}
+
+	pub fn location_cache_key(id: location::id::Type) -> CacheKey {
+		CacheKey::new::<Self>(format!("Location:{id}"))
+	}
 }
 
 #[derive(Serialize, Type, Debug)]
@@ -201,19 +242,40 @@ impl ExplorerItem {
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("list", {
-			R.with2(library()).query(|(_, library), _: ()| async move {
-				let locations = library
-					.db
-					.location()
-					.find_many(vec![])
-					.order_by(location::date_created::order(SortOrder::Desc))
-					.exec()
-					.await?;
+			R.with2(library()).query(
+				|(_, library), args: Option<CursorArgs<location::id::Type>>| async move {
+					let mut query = library
+						.db
+						.location()
+						.find_many(vec![])
+						.order_by(location::date_created::order(SortOrder::Desc))
+						.order_by(location::id::order(SortOrder::Desc));
 
-				let (nodes, items) = locations.normalise(|i| i.id.to_string());
+					// Without arguments we keep returning every location
+					if let Some(args) = &args {
+						query = query.take(args.fetch());
 
-				Ok(NormalisedResults { items, nodes })
-			})
+						if let Some(cursor) = args.cursor {
+							query = query.cursor(location::id::equals(cursor)).skip(1);
+						}
+					}
+
+					let locations = query.exec().await?;
+
+					let (locations, cursor) = match &args {
+						Some(args) => paginate(locations, args.take(), |location| location.id),
+						None => (locations, None),
+					};
+
+					let (nodes, items) = locations.normalise(|i| i.id.to_string());
+
+					Ok(NormalisedPage {
+						items,
+						nodes,
+						cursor,
+					})
+				},
+			)
 		})
 		.procedure("get", {
 			R.with2(library())
@@ -320,6 +382,16 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}
 				})
 		})
+		.procedure("createRemote", {
+			R.with2(library()).mutation(
+				|(node, library), args: RemoteLocationCreateArgs| async move {
+					let location = args.create(&library).await?;
+					let id = location.id;
+					scan_location(&node, &library, location, ScanState::Pending).await?;
+					Ok(id)
+				},
+			)
+		})
 		.procedure("update", {
 			R.with2(library())
 				.mutation(|(node, library), args: LocationUpdateArgs| async move {
@@ -332,6 +404,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.with2(library()).mutation(
 				|(node, library), location_id: location::id::Type| async move {
 					delete_location(&node, &library, location_id).await?;
+					library.evict_cache_nodes(vec![
+						CacheKey::new::<location::Data>(location_id.to_string()),
+						ExplorerItem::location_cache_key(location_id),
+					]);
 					invalidate_query!(library, "locations.list");
 					Ok(())
 				},
@@ -483,6 +559,268 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				},
 			)
 		})
+		.procedure("ingest", {
+			#[derive(Deserialize, Type)]
+			#[serde(rename_all = "camelCase")]
+			pub struct IngestArgs {
+				pub location_id: location::id::Type,
+				pub source: IngestSource,
+				/// Name of the stored file, taken from the source if missing
+				#[serde(default)]
+				pub name: Option<String>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 IngestArgs {
+				     location_id,
+				     source,
+				     name,
+				 }: IngestArgs| async move {
+					ingest::ingest(&node, &library, location_id, source, name)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("ingestInbox", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.ingest_inbox) })
+		})
+		.procedure("setIngestInbox", {
+			R.with2(library())
+				.mutation(|(node, library), inbox: Option<IngestInbox>| async move {
+					if let Some(inbox) = &inbox {
+						if !inbox.path.is_dir() {
+							return Err(LocationError::NotDirectory(
+								inbox.path.clone().into_boxed_path(),
+							)
+							.into());
+						}
+
+						if find_location(&library, inbox.location_id)
+							.exec()
+							.await?
+							.is_none()
+						{
+							return Err(LocationError::IdNotFound(inbox.location_id).into());
+						}
+					}
+
+					library
+						.update_config(
+							|config| config.ingest_inbox = inbox,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "locations.ingestInbox");
+
+					Ok(())
+				})
+		})
+		.procedure("ingestSettings", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.ingest) })
+		})
+		.procedure("setIngestSettings", {
+			R.with2(library())
+				.mutation(|(node, library), settings: IngestSettings| async move {
+					library
+						.update_config(
+							|config| config.ingest = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "locations.ingestSettings");
+
+					Ok(())
+				})
+		})
+		.procedure("usageTree", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct UsageTreeArgs {
+				pub location_id: location::id::Type,
+				/// Directory at the root of the tree, the whole location if missing
+				#[serde(default)]
+				pub path: Option<String>,
+				/// Levels of sub directories in the tree, at most 8
+				pub depth: u8,
+			}
+
+			R.with2(library()).query(
+				|(_, library),
+				 UsageTreeArgs {
+				     location_id,
+				     path,
+				     depth,
+				 }: UsageTreeArgs| async move {
+					if find_location(&library, location_id).exec().await?.is_none() {
+						return Err(LocationError::IdNotFound(location_id).into());
+					}
+
+					let materialized_path = match path {
+						Some(path) if !path.is_empty() && path != "/" => {
+							let iso_file_path =
+								IsolatedFilePathData::from_relative_str(location_id, &path);
+
+							if !check_file_path_exists::<LocationError>(&iso_file_path, &library.db)
+								.await?
+							{
+								return Err(
+									ApiError::DirectoryNotFound { location_id, path }.into()
+								);
+							}
+
+							// Only directories have children
+							iso_file_path
+								.materialized_path_for_children()
+								.ok_or_else(|| ApiError::DirectoryNotFound {
+									location_id,
+									path: path.clone(),
+								})?
+						}
+						_ => "/".to_string(),
+					};
+
+					usage_tree(&library.db, location_id, materialized_path, depth)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("validate", {
+			// Hashes the files of the location again in the background, to find the ones which
+			// don't match their index anymore
+			R.with2(library()).mutation(
+				|(node, library), args: OldLocationValidatorJobInit| async move {
+					if find_location(&library, args.location_id)
+						.exec()
+						.await?
+						.is_none()
+					{
+						return Err(LocationError::IdNotFound(args.location_id).into());
+					}
+
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("validationReport", {
+			#[derive(Type, Serialize)]
+			#[serde(rename_all = "camelCase")]
+			struct ValidationMismatch {
+				kind: MismatchKind,
+				file_path_id: file_path::id::Type,
+				path: String,
+				expected_cas_id: String,
+				/// Missing if the file couldn't be found
+				actual_cas_id: Option<String>,
+				date_detected: DateTime<FixedOffset>,
+			}
+
+			#[derive(Type, Serialize)]
+			#[serde(rename_all = "camelCase")]
+			struct ValidationReport {
+				/// Days between the scheduled validations, they're only run on demand if missing
+				interval_days: Option<i32>,
+				date_validated: Option<DateTime<FixedOffset>>,
+				mismatches: Vec<ValidationMismatch>,
+			}
+
+			R.with2(library())
+				.query(|(_, library), location_id: location::id::Type| async move {
+					let location = find_location(&library, location_id)
+						.select(location::select!({ validation_interval_days date_validated }))
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?;
+
+					let mismatches = library
+						.db
+						.validation_mismatch()
+						.find_many(vec![validation_mismatch::location_id::equals(location_id)])
+						.order_by(validation_mismatch::path::order(SortOrder::Asc))
+						.exec()
+						.await?
+						.into_iter()
+						.filter_map(|mismatch| {
+							Some(ValidationMismatch {
+								kind: MismatchKind::try_from(mismatch.kind).ok()?,
+								file_path_id: mismatch.file_path_id,
+								path: mismatch.path,
+								expected_cas_id: mismatch.expected_cas_id,
+								actual_cas_id: mismatch.actual_cas_id,
+								date_detected: mismatch.date_detected,
+							})
+						})
+						.collect();
+
+					Ok(ValidationReport {
+						interval_days: location.validation_interval_days,
+						date_validated: location.date_validated,
+						mismatches,
+					})
+				})
+		})
+		.procedure("setValidationSchedule", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct SetValidationScheduleArgs {
+				pub location_id: location::id::Type,
+				/// Days between the validations, like 30 to validate monthly, or `None` to only
+				/// validate on demand
+				pub interval_days: Option<u16>,
+			}
+
+			R.with2(library()).mutation(
+				|(_, library),
+				 SetValidationScheduleArgs {
+				     location_id,
+				     interval_days,
+				 }: SetValidationScheduleArgs| async move {
+					if interval_days == Some(0) {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"Validations must be at least a day apart".to_string(),
+						));
+					}
+
+					if find_location(&library, location_id)
+						.exec()
+						.await?
+						.is_none()
+					{
+						return Err(LocationError::IdNotFound(location_id).into());
+					}
+
+					library
+						.db
+						.location()
+						.update(
+							location::id::equals(location_id),
+							vec![location::validation_interval_days::set(
+								interval_days.map(i32::from),
+							)],
+						)
+						.exec()
+						.await?;
+
+					invalidate_query!(library, "locations.validationReport");
+
+					Ok(())
+				},
+			)
+		})
 		.procedure(
 			"online",
 			R.subscription(|node, _: ()| async move {
@@ -567,6 +905,43 @@ fn mount_indexer_rule_routes() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("update", {
+			R.with2(library())
+				.mutation(|(_, library), args: IndexerRuleUpdateArgs| async move {
+					let indexer_rule_id = args.id;
+
+					match library
+						.db
+						.indexer_rule()
+						.find_unique(indexer_rule::id::equals(indexer_rule_id))
+						.select(indexer_rule::select!({ default }))
+						.exec()
+						.await?
+					{
+						Some(indexer_rule) if indexer_rule.default.unwrap_or_default() => {
+							return Err(rspc::Error::new(
+								ErrorCode::Forbidden,
+								format!("Indexer rule <id={indexer_rule_id}> can't be modified"),
+							));
+						}
+						Some(_) => {}
+						None => {
+							return Err(rspc::Error::new(
+								ErrorCode::NotFound,
+								format!("Indexer rule <id={indexer_rule_id}> not found"),
+							));
+						}
+					}
+
+					args.update(&library.db).await?;
+
+					invalidate_query!(library, "locations.indexer_rules.list");
+					invalidate_query!(library, "locations.indexer_rules.get");
+					invalidate_query!(library, "locations.indexer_rules.listForLocation");
+
+					Ok(())
+				})
+		})
 		.procedure("get", {
 			R.with2(library())
 				.query(|(_, library), indexer_rule_id: i32| async move {
@@ -612,4 +987,8 @@ fn mount_indexer_rule_routes() -> AlphaRouter<Ctx> {
 					Ok(NormalisedResults { items, nodes })
 				})
 		})
+		// how often the indexer walks reused their compiled rules, to debug slow rules
+		.procedure("cacheStats", {
+			R.query(|_, _: ()| async move { Ok(indexer_rules_cache::stats()) })
+		})
 }
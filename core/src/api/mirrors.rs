@@ -0,0 +1,178 @@
+use crate::{
+	invalidate_query,
+	location::{
+		find_location,
+		mirror::{check_location, run_mirror, validate_remote, LocationMirror, MirrorDirection},
+		LocationError,
+	},
+};
+
+use sd_core_prisma_helpers::location_with_indexer_rules;
+
+use sd_prisma::prisma::location;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::Deserialize;
+use specta::Type;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.mirrors) })
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct CreateMirrorArgs {
+				pub location_id: location::id::Type,
+				pub remote: String,
+				pub direction: MirrorDirection,
+				#[serde(default)]
+				pub bandwidth_limit_kib: Option<u32>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 CreateMirrorArgs {
+				     location_id,
+				     remote,
+				     direction,
+				     bandwidth_limit_kib,
+				 }: CreateMirrorArgs| async move {
+					validate_remote(&remote)?;
+
+					let location = find_location(&library, location_id)
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?;
+					check_location(&library, &location).await?;
+
+					let id = Uuid::new_v4();
+
+					library
+						.update_config(
+							|config| {
+								config.mirrors.push(LocationMirror {
+									id,
+									location_id,
+									remote,
+									direction,
+									bandwidth_limit_kib,
+								})
+							},
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "mirrors.list");
+
+					Ok(id)
+				},
+			)
+		})
+		.procedure("update", {
+			R.with2(library())
+				.mutation(|(node, library), mirror: LocationMirror| async move {
+					validate_remote(&mirror.remote)?;
+
+					let Some(existing) = library
+						.config()
+						.await
+						.mirrors
+						.into_iter()
+						.find(|existing| existing.id == mirror.id)
+					else {
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Mirror '{}' not found", mirror.id),
+						));
+					};
+
+					// A mirror is created for a location, syncing it with another would lose files
+					if mirror.location_id != existing.location_id {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							"The location of a mirror can't be changed".to_string(),
+						));
+					}
+
+					let location = find_location(&library, existing.location_id)
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(existing.location_id))?;
+					check_location(&library, &location).await?;
+
+					library
+						.update_config(
+							|config| {
+								if let Some(existing) = config
+									.mirrors
+									.iter_mut()
+									.find(|existing| existing.id == mirror.id)
+								{
+									*existing = mirror;
+								}
+							},
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "mirrors.list");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(node, library), id: Uuid| async move {
+					library
+						.update_config(
+							|config| config.mirrors.retain(|mirror| mirror.id != id),
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "mirrors.list");
+
+					Ok(())
+				})
+		})
+		.procedure("run", {
+			R.with2(library())
+				.mutation(|(node, library), id: Uuid| async move {
+					let Some(mirror) = library
+						.config()
+						.await
+						.mirrors
+						.into_iter()
+						.find(|mirror| mirror.id == id)
+					else {
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Mirror '{id}' not found"),
+						));
+					};
+
+					let location = find_location(&library, mirror.location_id)
+						.include(location_with_indexer_rules::include())
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(mirror.location_id))?;
+					check_location(&library, &location::Data::from(&location)).await?;
+
+					run_mirror(&node, &library, location, mirror)
+						.await
+						.map_err(Into::into)
+				})
+		})
+}
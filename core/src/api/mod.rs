@@ -4,12 +4,13 @@ use crate::{
 		config::{NodeConfig, NodePreferences, P2PDiscoveryState, Port},
 		get_hardware_model_name, HardwareModel,
 	},
-	old_job::JobProgressEvent,
+	object::fs::operator::FileOpProgressEvent,
+	old_job::{JobItemProgressEvent, JobProgressEvent, JobStatus},
 	p2p::{into_listener2, Listener2},
 	Node,
 };
 
-use sd_cache::patch_typedef;
+use sd_cache::{patch_typedef, CacheKey};
 use sd_p2p::RemoteIdentity;
 use std::sync::{atomic::Ordering, Arc};
 
@@ -19,28 +20,40 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use uuid::Uuid;
 
+mod api_tokens;
 mod auth;
 mod backups;
 mod cloud;
 // mod categories;
+mod duplicates;
 mod ephemeral_files;
+pub mod error;
 mod files;
+mod hooks;
 mod jobs;
 mod keys;
 mod labels;
 mod libraries;
 pub mod locations;
+mod mirrors;
 mod models;
 mod nodes;
 pub mod notifications;
 mod p2p;
+#[cfg(feature = "plugins")]
+mod plugins;
 mod preferences;
+mod previews;
+mod remote_admin;
 pub(crate) mod search;
 mod sync;
 mod tags;
+mod thumbnails;
+mod trash;
 pub mod utils;
 pub mod volumes;
 mod web_api;
+mod webhooks;
 
 use utils::{InvalidRequests, InvalidateOperationEvent};
 
@@ -53,9 +66,20 @@ pub type Router = rspc::Router<Ctx>;
 /// Represents an internal core event, these are exposed to client via a rspc subscription.
 #[derive(Debug, Clone, Serialize, Type)]
 pub enum CoreEvent {
-	NewThumbnail { thumb_key: Vec<String> },
+	NewThumbnail {
+		thumb_key: Vec<String>,
+	},
 	JobProgress(JobProgressEvent),
+	JobItemProgress(JobItemProgressEvent),
+	/// A job completed, failed or was canceled, so it won't progress anymore
+	JobFinished {
+		library_id: Uuid,
+		id: Uuid,
+		status: JobStatus,
+	},
 	InvalidateOperation(InvalidateOperationEvent),
+	EvictCacheNodes(Vec<CacheKey>),
+	FileOpProgress(FileOpProgressEvent),
 }
 
 /// All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
@@ -195,26 +219,35 @@ pub(crate) fn mount() -> Arc<Router> {
 			})
 		})
 		.merge("api.", web_api::mount())
+		.merge("apiTokens.", api_tokens::mount())
 		.merge("auth.", auth::mount())
 		.merge("cloud.", cloud::mount())
 		.merge("search.", search::mount())
 		.merge("library.", libraries::mount())
 		.merge("volumes.", volumes::mount())
 		.merge("tags.", tags::mount())
+		.merge("thumbnails.", thumbnails::mount())
 		.merge("labels.", labels::mount())
 		// .merge("categories.", categories::mount())
 		// .merge("keys.", keys::mount())
 		.merge("locations.", locations::mount())
 		.merge("ephemeralFiles.", ephemeral_files::mount())
 		.merge("files.", files::mount())
+		.merge("duplicates.", duplicates::mount())
+		.merge("trash.", trash::mount())
 		.merge("jobs.", jobs::mount())
 		.merge("p2p.", p2p::mount())
 		.merge("models.", models::mount())
 		.merge("nodes.", nodes::mount())
 		.merge("sync.", sync::mount())
 		.merge("preferences.", preferences::mount())
+		.merge("previews.", previews::mount())
 		.merge("notifications.", notifications::mount())
 		.merge("backups.", backups::mount())
+		.merge("webhooks.", webhooks::mount())
+		.merge("mirrors.", mirrors::mount())
+		.merge("hooks.", hooks::mount())
+		.merge("remoteAdmin.", remote_admin::mount())
 		.merge("invalidation.", utils::mount_invalidate())
 		.sd_patch_types_dangerously(|type_map| {
 			patch_typedef(type_map);
@@ -229,6 +262,9 @@ pub(crate) fn mount() -> Arc<Router> {
 			);
 		});
 
+	#[cfg(feature = "plugins")]
+	let r = r.merge("plugins.", plugins::mount());
+
 	let r = r
 		.build(
 			#[allow(clippy::let_and_return)]
@@ -1,6 +1,10 @@
 use crate::{
 	invalidate_query,
-	node::config::{P2PDiscoveryState, Port},
+	node::{
+		background_policy::BackgroundPolicyPreferences,
+		config::{P2PDiscoveryState, Port},
+	},
+	telemetry::TelemetryPreferences,
 };
 
 use sd_prisma::prisma::{instance, location};
@@ -192,4 +196,102 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				},
 			)
 		})
+		.procedure("updateSearchPreferences", {
+			#[derive(Deserialize, Type)]
+			pub struct UpdateSearchPreferences {
+				/// Clamped between 1 and 10000
+				pub max_objects_take: u16,
+			}
+			R.mutation(
+				|node, UpdateSearchPreferences { max_objects_take }: UpdateSearchPreferences| async move {
+					node.config
+						.update_preferences(|preferences| {
+							preferences.search.set_max_objects_take(max_objects_take);
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to update search preferences: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to update search preferences".to_string(),
+								e,
+							)
+						})
+				},
+			)
+		})
+		.procedure("updateTelemetryPreferences", {
+			R.mutation(|node, preferences: TelemetryPreferences| async move {
+				if let Some(endpoint) = &preferences.otlp_endpoint {
+					if reqwest::Url::parse(endpoint).is_err() {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							format!("Invalid OTLP endpoint '{endpoint}'"),
+						));
+					}
+				}
+
+				node.config
+					.update_preferences(|node_preferences| {
+						node_preferences.telemetry = preferences;
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update telemetry preferences: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update telemetry preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
+		.procedure("backgroundPolicy", {
+			R.query(|node, _: ()| async move { Ok(node.background_policy.state()) })
+		})
+		.procedure("updateBackgroundPolicy", {
+			R.mutation(|node, preferences: BackgroundPolicyPreferences| async move {
+				if preferences
+					.pause_below_battery_percent
+					.is_some_and(|percent| percent > 100)
+				{
+					return Err(rspc::Error::new(
+						ErrorCode::BadRequest,
+						"Battery percentage must be between 0 and 100".to_string(),
+					));
+				}
+
+				if preferences
+					.quiet_hours
+					.is_some_and(|quiet_hours| !quiet_hours.is_valid())
+				{
+					return Err(rspc::Error::new(
+						ErrorCode::BadRequest,
+						"Quiet hours must be minutes within a day".to_string(),
+					));
+				}
+
+				node.config
+					.update_preferences(|node_preferences| {
+						node_preferences.background_policy = preferences;
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update background policy preferences: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update background policy preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
 }
@@ -1,12 +1,17 @@
-use crate::p2p::{operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata};
+use crate::{
+	api::error::ApiError,
+	invalidate_query,
+	p2p::{operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata},
+};
 
 use sd_p2p::{PeerConnectionCandidate, RemoteIdentity};
 
 use rspc::{alpha::AlphaRouter, ErrorCode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use specta::Type;
 use std::path::PathBuf;
 use tokio::io::AsyncWriteExt;
+use tracing::error;
 use uuid::Uuid;
 
 use super::{Ctx, R};
@@ -58,34 +63,51 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("state", {
 			R.query(|node, _: ()| async move { Ok(node.p2p.state().await) })
 		})
+		.procedure("metrics", {
+			#[derive(Serialize, Type)]
+			#[serde(rename_all = "camelCase")]
+			pub struct PeerMetrics {
+				identity: RemoteIdentity,
+				/// Since the node started, so transfer speeds are computed between two calls
+				bytes_sent: String,
+				bytes_received: String,
+				active_streams: u32,
+				/// Of the last ping of the peer
+				rtt_ms: Option<f64>,
+			}
+
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.p2p
+					.p2p
+					.metrics()
+					.stats()
+					.into_iter()
+					.map(|stats| PeerMetrics {
+						identity: stats.identity,
+						bytes_sent: stats.bytes_sent.to_string(),
+						bytes_received: stats.bytes_received.to_string(),
+						active_streams: stats.active_streams,
+						rtt_ms: stats.rtt.map(|rtt| rtt.as_secs_f64() * 1000.0),
+					})
+					.collect::<Vec<_>>())
+			})
+		})
 		.procedure("debugConnect", {
 			R.mutation(|node, identity: RemoteIdentity| async move {
 				let peer = { node.p2p.p2p.peers().get(&identity).cloned() };
 				let mut stream = peer
-					.ok_or(rspc::Error::new(
-						ErrorCode::InternalServerError,
-						"big man, not found".into(),
-					))?
+					.ok_or(ApiError::PeerNotFound { identity })?
 					.new_stream()
 					.await
-					.map_err(|err| {
-						rspc::Error::new(
-							ErrorCode::InternalServerError,
-							format!("error in peer.new_stream: {:?}", err),
-						)
-					})?;
+					.map_err(|source| ApiError::PeerUnreachable { identity, source })?;
 
 				stream
 					.write_all(&Header::Ping.to_bytes())
 					.await
-					.map_err(|err| {
-						rspc::Error::new(
-							ErrorCode::InternalServerError,
-							format!("error sending ping header: {:?}", err),
-						)
-					})?;
+					.map_err(|source| ApiError::PeerStream { identity, source })?;
 
-				Ok("connected")
+				Ok::<_, rspc::Error>("connected")
 			})
 		})
 		.procedure("spacedrop", {
@@ -98,6 +120,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.mutation(|node, args: SpacedropArgs| async move {
 				operations::spacedrop(
 					node.p2p.clone(),
+					&node.background_policy,
 					args.identity,
 					args.file_path
 						.into_iter()
@@ -105,9 +128,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.collect::<Vec<_>>(),
 				)
 				.await
-				.map_err(|_err| {
-					rspc::Error::new(ErrorCode::InternalServerError, "todo: error".into())
-				})
+				.map_err(|e| rspc::Error::from(ApiError::from(e)))
 			})
 		})
 		.procedure("acceptSpacedrop", {
@@ -124,6 +145,64 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.mutation(|node, id: Uuid| async move {
 				node.p2p.cancel_spacedrop(id).await;
 
+				Ok(())
+			})
+		})
+		.merge("pair.", mount_pairing_routes())
+}
+
+fn mount_pairing_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.pairing
+					.paired()
+					.to_vec())
+			})
+		})
+		.procedure("start", {
+			R.mutation(|node, identity: RemoteIdentity| async move {
+				operations::pair(node.p2p.clone(), identity)
+					.await
+					.map_err(Into::into)
+			})
+		})
+		.procedure("confirm", {
+			#[derive(Type, Deserialize)]
+			pub struct ConfirmPairingArgs {
+				id: Uuid,
+				accepted: bool,
+			}
+
+			R.mutation(
+				|node, ConfirmPairingArgs { id, accepted }: ConfirmPairingArgs| async move {
+					node.p2p.confirm_pairing(id, accepted).await;
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("remove", {
+			R.mutation(|node, identity: RemoteIdentity| async move {
+				node.config
+					.update_preferences(|preferences| preferences.pairing.remove(identity))
+					.await
+					.map_err(|e| {
+						error!("failed to update pairing preferences: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update pairing preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "p2p.pair.list");
+
 				Ok(())
 			})
 		})
@@ -0,0 +1,138 @@
+use crate::{
+	invalidate_query,
+	plugins::{PluginCapabilities, PluginExports, PluginManifest},
+	Node,
+};
+
+use std::collections::BTreeMap;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::error;
+
+use super::{Ctx, R};
+
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+	pub manifest: PluginManifest,
+	pub exports: PluginExports,
+}
+
+async fn reload(node: &Node) -> Result<Vec<String>, rspc::Error> {
+	let errors = node.plugins.reload().await.map_err(|e| {
+		rspc::Error::new(
+			ErrorCode::InternalServerError,
+			format!("Failed to load plugins: {e}"),
+		)
+	})?;
+
+	invalidate_query!(node; node, "plugins.list");
+	invalidate_query!(node; node, "plugins.pending");
+
+	// Plugins failing to load are skipped, the others are still usable
+	Ok(errors.into_iter().map(|e| e.to_string()).collect())
+}
+
+async fn update_grants(
+	node: &Node,
+	update_fn: impl FnOnce(&mut BTreeMap<String, PluginCapabilities>),
+) -> Result<(), rspc::Error> {
+	node.config
+		.update_preferences(|preferences| update_fn(&mut preferences.plugins.grants))
+		.await
+		.map_err(|e| {
+			error!("failed to update plugin grants: {e:#?}");
+			rspc::Error::with_cause(
+				ErrorCode::InternalServerError,
+				"Failed to update plugin grants".to_string(),
+				e,
+			)
+		})
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.plugins
+					.list()
+					.await
+					.into_iter()
+					.map(|plugin| PluginInfo {
+						manifest: plugin.manifest.clone(),
+						exports: plugin.exports,
+					})
+					.collect::<Vec<_>>())
+			})
+		})
+		.procedure("pending", {
+			R.query(|node, _: ()| async move { Ok(node.plugins.pending().await) })
+		})
+		.procedure("reload", {
+			R.mutation(|node, _: ()| async move { reload(&node).await })
+		})
+		.procedure("approve", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct ApprovePluginArgs {
+				pub name: String,
+				/// The capabilities shown to the user by `plugins.pending`, so a plugin asking
+				/// for more in the meantime isn't approved
+				pub capabilities: PluginCapabilities,
+			}
+
+			R.mutation(
+				|node, ApprovePluginArgs { name, capabilities }: ApprovePluginArgs| async move {
+					update_grants(&node, |grants| {
+						grants.insert(name, capabilities.clamped());
+					})
+					.await?;
+
+					reload(&node).await
+				},
+			)
+		})
+		.procedure("revoke", {
+			R.mutation(|node, name: String| async move {
+				update_grants(&node, |grants| {
+					grants.remove(&name);
+				})
+				.await?;
+
+				reload(&node).await
+			})
+		})
+		.procedure("searchFilter", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct PluginSearchFilterArgs {
+				pub plugin: String,
+				pub filter: String,
+				pub query: String,
+			}
+
+			R.query(
+				|node,
+				 PluginSearchFilterArgs {
+				     plugin,
+				     filter,
+				     query,
+				 }: PluginSearchFilterArgs| async move {
+					let Some(loaded) = node.plugins.get(&plugin).await else {
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Plugin '{plugin}' not found"),
+						));
+					};
+
+					loaded
+						.search_filter(&filter, &query)
+						.await
+						.map_err(|e| rspc::Error::new(ErrorCode::BadRequest, e.to_string()))
+				},
+			)
+		})
+}
@@ -9,7 +9,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("update", {
 			R.with2(library())
 				.mutation(|(_, library), args: LibraryPreferences| async move {
-					args.write(&library.db).await?;
+					args.write(&library).await?;
 
 					Ok(())
 				})
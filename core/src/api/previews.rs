@@ -0,0 +1,38 @@
+use crate::object::media::old_thumbnail::request_pdf_pages;
+
+use sd_file_ext::extensions::DocumentExtension;
+
+use std::str::FromStr;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+
+use super::{thumbnails::ThumbnailSource, utils::library, Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router().procedure("pages", {
+		R.with2(library())
+			.query(|(node, library), source: ThumbnailSource| async move {
+				let (path, extension, cas_id, kind) = source.resolve(&library).await?;
+
+				if !matches!(
+					DocumentExtension::from_str(&extension),
+					Ok(DocumentExtension::Pdf)
+				) {
+					return Err(rspc::Error::new(
+						ErrorCode::BadRequest,
+						"Only PDFs have paged previews".to_string(),
+					));
+				}
+
+				request_pdf_pages(&node, &path, &cas_id, kind)
+					.await
+					.map_err(|e| {
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to render PDF pages".to_string(),
+							e,
+						)
+					})
+			})
+	})
+}
@@ -0,0 +1,152 @@
+use crate::{
+	invalidate_query,
+	p2p::operations::admin::{
+		self, AdminError, AdminRequest, AdminResponse, RemoteAdminPermission,
+		RemoteNodeSettingsUpdate,
+	},
+};
+
+use sd_p2p::RemoteIdentity;
+use sd_prisma::prisma::location;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::Deserialize;
+use specta::Type;
+use tracing::error;
+use uuid::Uuid;
+
+use super::{Ctx, R};
+
+const DEFAULT_LOG_LINES: u32 = 200;
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("grants", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.preferences
+					.remote_admin
+					.grants()
+					.to_vec())
+			})
+		})
+		.procedure("setGrant", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct SetGrantArgs {
+				pub identity: RemoteIdentity,
+				/// Revokes the access of the node if missing
+				pub permission: Option<RemoteAdminPermission>,
+			}
+
+			R.mutation(
+				|node,
+				 SetGrantArgs {
+				     identity,
+				     permission,
+				 }: SetGrantArgs| async move {
+					node.config
+						.update_preferences(|preferences| {
+							preferences.remote_admin.set_grant(identity, permission);
+						})
+						.await
+						.map_err(|e| {
+							error!("failed to update remote admin preferences: {e:#?}");
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to update remote admin preferences".to_string(),
+								e,
+							)
+						})?;
+
+					invalidate_query!(node; node, "remoteAdmin.grants");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("jobs", {
+			R.query(|node, identity: RemoteIdentity| async move {
+				match admin::request(&node, identity, AdminRequest::Jobs).await? {
+					AdminResponse::Jobs(reports) => Ok::<_, rspc::Error>(reports),
+					_ => Err(AdminError::UnexpectedResponse.into()),
+				}
+			})
+		})
+		.procedure("logs", {
+			#[derive(Type, Deserialize)]
+			pub struct LogsArgs {
+				pub identity: RemoteIdentity,
+				#[serde(default)]
+				pub lines: Option<u32>,
+			}
+
+			R.query(|node, LogsArgs { identity, lines }: LogsArgs| async move {
+				let request = AdminRequest::Logs {
+					lines: lines.unwrap_or(DEFAULT_LOG_LINES),
+				};
+
+				match admin::request(&node, identity, request).await? {
+					AdminResponse::Logs(lines) => Ok::<_, rspc::Error>(lines),
+					_ => Err(AdminError::UnexpectedResponse.into()),
+				}
+			})
+		})
+		.procedure("settings", {
+			R.query(|node, identity: RemoteIdentity| async move {
+				match admin::request(&node, identity, AdminRequest::Settings).await? {
+					AdminResponse::Settings(settings) => Ok::<_, rspc::Error>(settings),
+					_ => Err(AdminError::UnexpectedResponse.into()),
+				}
+			})
+		})
+		.procedure("rescan", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct RemoteRescanArgs {
+				pub identity: RemoteIdentity,
+				pub library_id: Uuid,
+				pub location_id: location::id::Type,
+			}
+
+			R.mutation(
+				|node,
+				 RemoteRescanArgs {
+				     identity,
+				     library_id,
+				     location_id,
+				 }: RemoteRescanArgs| async move {
+					let request = AdminRequest::Rescan {
+						library_id,
+						location_id,
+					};
+
+					match admin::request(&node, identity, request).await? {
+						AdminResponse::Done => Ok::<_, rspc::Error>(()),
+						_ => Err(AdminError::UnexpectedResponse.into()),
+					}
+				},
+			)
+		})
+		.procedure("updateSettings", {
+			#[derive(Type, Deserialize)]
+			pub struct UpdateSettingsArgs {
+				pub identity: RemoteIdentity,
+				pub update: RemoteNodeSettingsUpdate,
+			}
+
+			R.mutation(
+				|node, UpdateSettingsArgs { identity, update }: UpdateSettingsArgs| async move {
+					match admin::request(&node, identity, AdminRequest::UpdateSettings(update))
+						.await?
+					{
+						AdminResponse::Done => Ok::<_, rspc::Error>(()),
+						_ => Err(AdminError::UnexpectedResponse.into()),
+					}
+				},
+			)
+		})
+}
@@ -0,0 +1,290 @@
+use crate::{invalidate_query, library::Library};
+
+use sd_prisma::prisma::PrismaClient;
+
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use mini_moka::sync::Cache;
+use once_cell::sync::Lazy;
+use prisma_client_rust::raw;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::error;
+use uuid::Uuid;
+
+use super::SearchFilterArgs;
+
+/// Counts older than this are returned as stale, and counted again in the background
+const STALE_AFTER: Duration = Duration::from_secs(10);
+/// Filter sets not counted for this long are dropped
+const IDLE_FOR: Duration = Duration::from_secs(10 * 60);
+const CAPACITY: u64 = 1000;
+/// Libraries with fewer objects are counted exactly, as it's quick enough
+const APPROXIMATE_FROM: u32 = 100_000;
+
+static COUNTS: Lazy<CountCache> = Lazy::new(CountCache::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum CountTarget {
+	Paths,
+	Objects,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+pub struct CachedCount {
+	pub count: u32,
+	/// The count may be outdated, the query is invalidated once it's counted again
+	pub stale: bool,
+	/// The count was estimated from the statistics of the database instead of counted
+	pub approximate: bool,
+}
+
+/// The library, what's counted and the serialized filters
+type CountKey = (Uuid, CountTarget, String);
+
+struct CountCache {
+	counts: Cache<CountKey, (u32, Instant)>,
+	refreshing: Mutex<HashSet<CountKey>>,
+}
+
+impl Default for CountCache {
+	fn default() -> Self {
+		Self {
+			counts: Cache::builder()
+				.max_capacity(CAPACITY)
+				.time_to_idle(IDLE_FOR)
+				.build(),
+			refreshing: Mutex::default(),
+		}
+	}
+}
+
+impl CountCache {
+	fn get(&self, key: &CountKey, now: Instant) -> Option<CachedCount> {
+		self.counts.get(key).map(|(count, counted_at)| CachedCount {
+			count,
+			stale: now.saturating_duration_since(counted_at) >= STALE_AFTER,
+			approximate: false,
+		})
+	}
+
+	fn insert(&self, key: CountKey, count: u32, now: Instant) {
+		self.counts.insert(key, (count, now));
+	}
+
+	/// Returns `false` if the key is already being counted again.
+	fn start_refresh(&self, key: &CountKey) -> bool {
+		self.refreshing
+			.lock()
+			.expect("count cache lock poisoned")
+			.insert(key.clone())
+	}
+
+	fn finish_refresh(&self, key: &CountKey) {
+		self.refreshing
+			.lock()
+			.expect("count cache lock poisoned")
+			.remove(key);
+	}
+}
+
+/// Returns the count of the items matching `filters`, from the cache when it was already counted.
+///
+/// A stale count is returned right away and counted again in the background, so toggling filters
+/// back and forth doesn't run the same `COUNT` over and over.
+pub(super) async fn cached_count(
+	library: &Arc<Library>,
+	target: CountTarget,
+	filters: Vec<SearchFilterArgs>,
+) -> Result<CachedCount, rspc::Error> {
+	if target == CountTarget::Objects && filters.is_empty() {
+		if let Some(approximate) = approximate_objects_count(&library.db).await? {
+			return Ok(approximate);
+		}
+	}
+
+	let key = count_key(library.id, target, &filters);
+
+	match COUNTS.get(&key, Instant::now()) {
+		Some(cached) if cached.stale => {
+			if COUNTS.start_refresh(&key) {
+				tokio::spawn(refresh(Arc::clone(library), key, filters));
+			}
+
+			Ok(cached)
+		}
+		Some(cached) => Ok(cached),
+		None => {
+			let count = count(&library.db, target, filters).await?;
+			COUNTS.insert(key, count, Instant::now());
+
+			Ok(CachedCount {
+				count,
+				stale: false,
+				approximate: false,
+			})
+		}
+	}
+}
+
+/// Estimates the objects of the library without scanning them, `None` if there are too few of
+/// them to be worth it.
+///
+/// The row count from the last `ANALYZE` is used when there is one, or else the highest id, which
+/// counts the deleted objects too and so is returned as stale.
+async fn approximate_objects_count(db: &PrismaClient) -> Result<Option<CachedCount>, rspc::Error> {
+	#[derive(Deserialize)]
+	struct Stat {
+		stat: String,
+	}
+
+	#[derive(Deserialize)]
+	struct MaxId {
+		max_id: Option<i64>,
+	}
+
+	// `sqlite_stat1` only exists once the database has been analyzed
+	let analyzed = db
+		._query_raw::<Stat>(raw!(
+			"SELECT stat FROM sqlite_stat1 WHERE tbl = 'object' LIMIT 1"
+		))
+		.exec()
+		.await
+		.ok()
+		.and_then(|stats| stats.into_iter().next())
+		.and_then(|Stat { stat }| rows_from_stat(&stat));
+
+	let (estimate, stale) = match analyzed {
+		Some(rows) => (rows, false),
+		None => (
+			db._query_raw::<MaxId>(raw!("SELECT MAX(id) AS max_id FROM object"))
+				.exec()
+				.await?
+				.into_iter()
+				.next()
+				.and_then(|MaxId { max_id }| max_id)
+				.map_or(0, |max_id| u32::try_from(max_id).unwrap_or(u32::MAX)),
+			true,
+		),
+	};
+
+	Ok((estimate >= APPROXIMATE_FROM).then_some(CachedCount {
+		count: estimate,
+		stale,
+		approximate: true,
+	}))
+}
+
+/// The first number of a `sqlite_stat1` row is the amount of rows of its table.
+fn rows_from_stat(stat: &str) -> Option<u32> {
+	stat.split_whitespace()
+		.next()?
+		.parse::<u64>()
+		.ok()
+		.map(|rows| u32::try_from(rows).unwrap_or(u32::MAX))
+}
+
+/// Stores a count made along with a page of results, so the next count of these filters is fresh.
+pub(super) fn insert_count(
+	library_id: Uuid,
+	target: CountTarget,
+	filters: &[SearchFilterArgs],
+	count: u32,
+) {
+	COUNTS.insert(
+		count_key(library_id, target, filters),
+		count,
+		Instant::now(),
+	);
+}
+
+fn count_key(library_id: Uuid, target: CountTarget, filters: &[SearchFilterArgs]) -> CountKey {
+	(
+		library_id,
+		target,
+		serde_json::to_string(filters).expect("search filters are always serializable to JSON"),
+	)
+}
+
+async fn refresh(library: Arc<Library>, key: CountKey, filters: Vec<SearchFilterArgs>) {
+	let target = key.1;
+
+	match count(&library.db, target, filters).await {
+		Ok(count) => {
+			COUNTS.insert(key.clone(), count, Instant::now());
+
+			match target {
+				CountTarget::Paths => invalidate_query!(library, "search.pathsCount"),
+				CountTarget::Objects => invalidate_query!(library, "search.objectsCount"),
+			}
+		}
+		Err(e) => error!("Failed to refresh a cached search count: {e:#?}"),
+	}
+
+	COUNTS.finish_refresh(&key);
+}
+
+async fn count(
+	db: &PrismaClient,
+	target: CountTarget,
+	filters: Vec<SearchFilterArgs>,
+) -> Result<u32, rspc::Error> {
+	let count = match target {
+		CountTarget::Paths => {
+			let mut params = Vec::new();
+
+			for filter in filters {
+				params.extend(filter.into_file_path_params(db).await?);
+			}
+
+			db.file_path().count(params).exec().await?
+		}
+		CountTarget::Objects => {
+			let mut params = Vec::new();
+
+			for filter in filters {
+				params.extend(filter.into_object_params(db).await?);
+			}
+
+			db.object().count(params).exec().await?
+		}
+	};
+
+	Ok(count as u32)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_rows_from_stat() {
+		assert_eq!(rows_from_stat("1203344 2 1"), Some(1_203_344));
+		assert_eq!(rows_from_stat(""), None);
+	}
+
+	#[test]
+	fn counts_go_stale() {
+		let cache = CountCache::default();
+		let key = (Uuid::new_v4(), CountTarget::Paths, "[]".to_string());
+		let now = Instant::now();
+
+		assert!(cache.get(&key, now).is_none());
+
+		cache.insert(key.clone(), 42, now);
+		assert!(!cache.get(&key, now).unwrap().stale);
+
+		let later = cache.get(&key, now + STALE_AFTER).unwrap();
+		assert_eq!(later.count, 42);
+		assert!(later.stale);
+
+		assert!(cache.start_refresh(&key));
+		assert!(!cache.start_refresh(&key));
+		cache.finish_refresh(&key);
+		assert!(cache.start_refresh(&key));
+	}
+}
@@ -0,0 +1,296 @@
+//! A single stream serving every view of the explorer, so the clients read the children of a
+//! path, the objects, the entries of a non indexed path and the results of a saved search the same
+//! way.
+
+use crate::{
+	api::{locations::ExplorerItem, utils::library},
+	library::Library,
+	util::unsafe_streamed_query,
+	Node,
+};
+
+use sd_cache::{CacheNode, Normalise, Reference};
+use sd_core_prisma_helpers::{file_path_with_object, object_with_file_paths};
+use sd_prisma::prisma::{self, saved_search};
+
+use std::sync::Arc;
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use rspc::alpha::AlphaRouter;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{
+	default_group_directories, ephemeral_paths, file_path, file_path_params, into_explorer_items,
+	into_object_explorer_items, object, object_params, paths_query,
+	saved::{saved_search, SearchTarget},
+	Ctx, PathFrom, SearchFilterArgs, FIRST_STREAMED_BATCH, MAX_STREAMED_BATCH, R,
+};
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum ExplorerSource {
+	/// The file paths matching the filters, like `search.paths`
+	#[serde(rename_all = "camelCase")]
+	Paths {
+		#[specta(optional)]
+		order: Option<file_path::FilePathOrder>,
+		#[serde(default)]
+		filters: Vec<SearchFilterArgs>,
+		#[serde(default = "default_group_directories")]
+		group_directories: bool,
+	},
+	/// The objects matching the filters, like `search.objects`
+	Objects {
+		#[specta(optional)]
+		order: Option<object::ObjectOrder>,
+		#[serde(default)]
+		filters: Vec<SearchFilterArgs>,
+	},
+	/// The entries of a path outside of the locations, like `search.ephemeralPaths`
+	#[serde(rename_all = "camelCase")]
+	Ephemeral {
+		path: String,
+		with_hidden_files: bool,
+	},
+	/// The results of the saved search with this id
+	SavedSearch { id: saved_search::id::Type },
+}
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerStreamArgs {
+	source: ExplorerSource,
+	/// The `cursor` of the last batch received, to resume the stream after it
+	#[specta(optional)]
+	cursor: Option<i32>,
+}
+
+#[derive(Serialize, Type, Debug)]
+pub(super) struct ExplorerBatch {
+	pub items: Vec<Reference<ExplorerItem>>,
+	pub nodes: Vec<CacheNode>,
+	pub errors: Vec<String>,
+	/// Resumes the stream after this batch, missing on the last batch and for non indexed paths,
+	/// which are always listed in full
+	pub cursor: Option<i32>,
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router().procedure("stream", {
+		// Every item of the view in batches, the first ones small so they're rendered right away
+		R.with2(library()).subscription(
+			|(node, library), ExplorerStreamArgs { source, cursor }| async move {
+				let offset = cursor.unwrap_or_default();
+
+				let stream = match source {
+					ExplorerSource::Paths {
+						order,
+						filters,
+						group_directories,
+					} => {
+						let params = file_path_params(&library.db, filters).await?;
+
+						stream_paths(node, library, params, order, group_directories, offset)
+							.boxed()
+					}
+					ExplorerSource::Objects { order, filters } => {
+						let params = object_params(&library.db, filters).await?;
+
+						stream_objects(node, library, params, order, offset).boxed()
+					}
+					ExplorerSource::Ephemeral {
+						path,
+						with_hidden_files,
+					} => ephemeral_paths(
+						node,
+						library,
+						PathFrom::Path,
+						None,
+						path,
+						with_hidden_files,
+					)
+					.await?
+					.map(|batch| ExplorerBatch {
+						items: batch.entries,
+						nodes: batch.nodes,
+						errors: batch.errors,
+						cursor: None,
+					})
+					.boxed(),
+					ExplorerSource::SavedSearch { id } => match saved_search(&library, id).await? {
+						(SearchTarget::Paths, filters) => {
+							let params = file_path_params(&library.db, filters).await?;
+
+							stream_paths(
+								node,
+								library,
+								params,
+								None,
+								default_group_directories(),
+								offset,
+							)
+							.boxed()
+						}
+						(SearchTarget::Objects, filters) => {
+							let params = object_params(&library.db, filters).await?;
+
+							stream_objects(node, library, params, None, offset).boxed()
+						}
+					},
+				};
+
+				Ok(unsafe_streamed_query(stream))
+			},
+		)
+	})
+}
+
+/// The file paths matching `params` from `offset` on, in batches doubling in size up to
+/// [`MAX_STREAMED_BATCH`].
+pub(super) fn stream_paths(
+	node: Arc<Node>,
+	library: Arc<Library>,
+	params: Vec<prisma::file_path::WhereParam>,
+	order: Option<file_path::FilePathOrder>,
+	group_directories: bool,
+	mut offset: i32,
+) -> impl Stream<Item = ExplorerBatch> + Send {
+	stream! {
+		let mut batch_size = FIRST_STREAMED_BATCH;
+		// The last path sent, to go on from it when they're only ordered by id
+		let mut last = None;
+
+		loop {
+			let pagination = match (&order, last) {
+				// Skipping the paths already sent gets slower the further the stream goes, which
+				// matters for the searches matching hundreds of thousands of them
+				(None, Some((id, is_dir))) => file_path::OrderAndPagination::Cursor {
+					id,
+					cursor: file_path::FilePathCursor {
+						is_dir,
+						variant: file_path::FilePathCursorVariant::None,
+					},
+				},
+				(order, _) => file_path::OrderAndPagination::Offset {
+					offset,
+					order: order.clone(),
+				},
+			};
+
+			let query = paths_query(
+				&library.db,
+				params.clone(),
+				Some(pagination),
+				group_directories,
+			)
+			// Ties are ordered by id, so no path is skipped or sent twice
+			.order_by(prisma::file_path::id::order(prisma::SortOrder::Asc))
+			.take(batch_size)
+			.include(file_path_with_object::include());
+
+			let batch = match query.exec().await {
+				Ok(file_paths) => file_paths,
+				Err(e) => {
+					yield ExplorerBatch::failed(e.to_string(), offset);
+					break;
+				}
+			};
+
+			let is_last = (batch.len() as i64) < batch_size;
+			offset += batch.len() as i32;
+			batch_size = (batch_size * 2).min(MAX_STREAMED_BATCH);
+			last = batch
+				.last()
+				.map(|file_path| (file_path.id, file_path.is_dir.unwrap_or_default()));
+
+			yield ExplorerBatch::new(
+				into_explorer_items(&node, &library, batch, false).await,
+				(!is_last).then_some(offset),
+			);
+
+			if is_last {
+				break;
+			}
+		}
+	}
+}
+
+/// The objects matching `params` from `offset` on, batched like [`stream_paths`].
+fn stream_objects(
+	node: Arc<Node>,
+	library: Arc<Library>,
+	params: Vec<prisma::object::WhereParam>,
+	order: Option<object::ObjectOrder>,
+	mut offset: i32,
+) -> impl Stream<Item = ExplorerBatch> + Send {
+	stream! {
+		let mut batch_size = FIRST_STREAMED_BATCH;
+
+		loop {
+			let mut query = library.db.object().find_many(params.clone());
+
+			object::OrderAndPagination::Offset {
+				offset,
+				order: order.clone(),
+			}
+			.apply(&mut query);
+
+			let query = query
+				// Ties are ordered by id, so no object is skipped or sent twice
+				.order_by(prisma::object::id::order(prisma::SortOrder::Asc))
+				.take(batch_size)
+				.include(object_with_file_paths::include());
+
+			let batch = match query.exec().await {
+				Ok(objects) => objects,
+				Err(e) => {
+					yield ExplorerBatch::failed(e.to_string(), offset);
+					break;
+				}
+			};
+
+			let is_last = (batch.len() as i64) < batch_size;
+			offset += batch.len() as i32;
+			batch_size = (batch_size * 2).min(MAX_STREAMED_BATCH);
+
+			yield ExplorerBatch::new(
+				into_object_explorer_items(&node, &library, batch, true).await,
+				(!is_last).then_some(offset),
+			);
+
+			if is_last {
+				break;
+			}
+		}
+	}
+}
+
+impl ExplorerBatch {
+	fn new(items: Result<Vec<ExplorerItem>, rspc::Error>, cursor: Option<i32>) -> Self {
+		let (items, errors) = match items {
+			Ok(items) => (items, vec![]),
+			Err(e) => (vec![], vec![e.to_string()]),
+		};
+
+		let (nodes, items) = items.normalise(|item| item.id());
+
+		Self {
+			items,
+			nodes,
+			errors,
+			cursor,
+		}
+	}
+
+	/// A batch which couldn't be read, the stream can be resumed from its `offset` to try again.
+	fn failed(error: String, offset: i32) -> Self {
+		Self {
+			items: vec![],
+			nodes: vec![],
+			errors: vec![error],
+			cursor: Some(offset),
+		}
+	}
+}
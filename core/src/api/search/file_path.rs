@@ -1,19 +1,18 @@
-use crate::location::LocationError;
+use crate::{api::error::ApiError, location::LocationError};
 
 use sd_core_file_path_helper::{check_file_path_exists, IsolatedFilePathData};
+use sd_core_prisma_helpers::file_path_with_object;
 
 use sd_prisma::prisma::{self, file_path};
 
+use std::iter;
+
 use chrono::{DateTime, FixedOffset, Utc};
-use prisma_client_rust::{OrderByQuery, PaginatedQuery, WhereQuery};
-use rspc::ErrorCode;
+use prisma_client_rust::{and, operator, or, OrderByQuery, PaginatedQuery, WhereQuery};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use super::{
-	object::*,
-	utils::{self, *},
-};
+use super::{object::*, pattern::NamePattern, utils::*};
 
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase", tag = "field", content = "value")]
@@ -65,6 +64,10 @@ pub enum FilePathFilterArgs {
 	// #[deprecated]
 	// Search(String),
 	Name(TextMatch),
+	/// A case insensitive regex matched against the names with their extension, like `^IMG_\d{4}`
+	NameRegex(String),
+	/// A case insensitive glob matched against the whole names with their extension, like `*.torrent`
+	NameGlob(String),
 	Extension(InOrNotIn<String>),
 	CreatedAt(Range<DateTime<Utc>>),
 	ModifiedAt(Range<DateTime<Utc>>),
@@ -97,10 +100,11 @@ impl FilePathFilterArgs {
 						IsolatedFilePathData::from_relative_str(location_id, &path);
 
 					if !check_file_path_exists::<LocationError>(&parent_iso_file_path, db).await? {
-						return Err(rspc::Error::new(
-							ErrorCode::NotFound,
-							"Directory not found".into(),
-						));
+						return Err(ApiError::DirectoryNotFound {
+							location_id,
+							path: path.clone(),
+						}
+						.into());
 					}
 
 					parent_iso_file_path.materialized_path_for_children()
@@ -127,6 +131,10 @@ impl FilePathFilterArgs {
 				})
 				.map(|v| vec![v])
 				.unwrap_or_default(),
+			Self::NameRegex(v) if v.is_empty() => vec![],
+			Self::NameRegex(v) => vec![id::in_vec(NamePattern::regex(&v)?.matching_ids(db).await?)],
+			Self::NameGlob(v) if v.is_empty() => vec![],
+			Self::NameGlob(v) => vec![id::in_vec(NamePattern::glob(&v)?.matching_ids(db).await?)],
 			Self::Extension(v) => v
 				.into_param(extension::in_vec, extension::not_in_vec)
 				.map(|v| vec![v])
@@ -156,141 +164,292 @@ impl FilePathFilterArgs {
 	}
 }
 
-#[derive(Deserialize, Type, Debug)]
+macro_rules! nullable_field {
+	// The file paths without an object are ordered like the ones whose object has no value
+	(object.$field:ident) => {
+		NullableField {
+			past: |order, data| {
+				file_path::object::is(vec![match order {
+					SortOrder::Asc => prisma::object::$field::gt(data),
+					SortOrder::Desc => prisma::object::$field::lt(data),
+				}])
+			},
+			equals: |data| file_path::object::is(vec![prisma::object::$field::equals(Some(data))]),
+			is_null: || {
+				or![
+					file_path::object_id::equals(None),
+					file_path::object::is(vec![prisma::object::$field::equals(None)])
+				]
+			},
+			id_after: file_path::id::gt,
+		}
+	};
+	($field:ident) => {
+		NullableField {
+			past: |order, data| match order {
+				SortOrder::Asc => file_path::$field::gt(data),
+				SortOrder::Desc => file_path::$field::lt(data),
+			},
+			equals: |data| file_path::$field::equals(Some(data)),
+			is_null: || file_path::$field::equals(None),
+			id_after: file_path::id::gt,
+		}
+	};
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum FilePathObjectCursor {
-	DateAccessed(CursorOrderItem<DateTime<FixedOffset>>),
-	Kind(CursorOrderItem<i32>),
+	DateAccessed(CursorOrderItem<Option<DateTime<FixedOffset>>>),
+	Kind(CursorOrderItem<Option<i32>>),
 }
 
 impl FilePathObjectCursor {
-	fn apply(self, query: &mut file_path::FindManyQuery) {
-		macro_rules! arm {
-			($field:ident, $item:ident) => {{
-				let item = $item;
+	/// Matches the file paths after the one with this cursor and `id`, and how they're ordered.
+	fn into_params(self, id: i32) -> (file_path::WhereParam, file_path::OrderByWithRelationParam) {
+		match self {
+			Self::Kind(item) => {
+				let order = item.order.into();
 
-				query.add_where(match item.order {
-					SortOrder::Asc => {
-						prisma::file_path::object::is(vec![prisma::object::$field::gt(item.data)])
-					}
-					SortOrder::Desc => {
-						prisma::file_path::object::is(vec![prisma::object::$field::lt(item.data)])
-					}
-				});
+				(
+					nullable_field!(object.kind).after(item, id),
+					file_path::object::order(vec![prisma::object::kind::order(order)]),
+				)
+			}
+			Self::DateAccessed(item) => {
+				let order = item.order.into();
 
-				query.add_order_by(prisma::file_path::object::order(vec![
-					prisma::object::$field::order(item.order.into()),
-				]));
-			}};
+				(
+					nullable_field!(object.date_accessed).after(item, id),
+					file_path::object::order(vec![prisma::object::date_accessed::order(order)]),
+				)
+			}
 		}
+	}
+
+	/// The cursor of the file paths after `last`, in the same order.
+	fn after(&self, last: &file_path_with_object::Data) -> Self {
+		let object = last.object.as_ref();
 
 		match self {
-			FilePathObjectCursor::Kind(item) => arm!(kind, item),
-			FilePathObjectCursor::DateAccessed(item) => {
-				arm!(date_accessed, item)
-			}
-		};
+			Self::Kind(item) => Self::Kind(CursorOrderItem {
+				order: item.order,
+				data: object.and_then(|object| object.kind),
+			}),
+			Self::DateAccessed(item) => Self::DateAccessed(CursorOrderItem {
+				order: item.order,
+				data: object.and_then(|object| object.date_accessed),
+			}),
+		}
 	}
 }
 
-#[derive(Deserialize, Type, Debug)]
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum FilePathCursorVariant {
 	None,
-	Name(CursorOrderItem<String>),
+	Name(CursorOrderItem<Option<String>>),
+	/// Only orders the file paths, as the database can't compare their sizes to go past the cursor,
+	/// so only their first page is read this way and the next ones by offset
 	SizeInBytes(SortOrder),
-	DateCreated(CursorOrderItem<DateTime<FixedOffset>>),
-	DateModified(CursorOrderItem<DateTime<FixedOffset>>),
-	DateIndexed(CursorOrderItem<DateTime<FixedOffset>>),
+	DateCreated(CursorOrderItem<Option<DateTime<FixedOffset>>>),
+	DateModified(CursorOrderItem<Option<DateTime<FixedOffset>>>),
+	DateIndexed(CursorOrderItem<Option<DateTime<FixedOffset>>>),
 	Object(FilePathObjectCursor),
 }
 
 impl FilePathCursorVariant {
-	pub fn apply(self, query: &mut file_path::FindManyQuery, id: i32) {
+	/// Matches the file paths after the one with this cursor and `id`, and how they're ordered if
+	/// not only by id.
+	fn into_params(
+		self,
+		id: i32,
+	) -> (
+		Vec<file_path::WhereParam>,
+		Option<file_path::OrderByWithRelationParam>,
+	) {
 		macro_rules! arm {
 			($field:ident, $item:ident) => {{
-				let item = $item;
-
-				let data = item.data.clone();
+				let order = $item.order.into();
 
-				query.add_where(prisma_client_rust::or![
-					match item.order {
-						SortOrder::Asc => prisma::file_path::$field::gt(data),
-						SortOrder::Desc => prisma::file_path::$field::lt(data),
-					},
-					prisma_client_rust::and![
-						prisma::file_path::$field::equals(Some(item.data)),
-						match item.order {
-							SortOrder::Asc => prisma::file_path::id::gt(id),
-							SortOrder::Desc => prisma::file_path::id::lt(id),
-						}
-					]
-				]);
-
-				query.add_order_by(prisma::file_path::$field::order(item.order.into()));
+				(
+					vec![nullable_field!($field).after($item, id)],
+					Some(file_path::$field::order(order)),
+				)
 			}};
 		}
 
 		match self {
-			Self::None => {
-				query.add_where(prisma::file_path::id::gt(id));
-			}
-			Self::SizeInBytes(order) => {
-				query.add_order_by(prisma::file_path::size_in_bytes_bytes::order(order.into()));
-			}
+			Self::None => (vec![file_path::id::gt(id)], None),
 			Self::Name(item) => arm!(name, item),
-			Self::DateCreated(item) => {
-				arm!(date_created, item)
-			}
-			Self::DateModified(item) => {
-				arm!(date_modified, item)
-			}
-			Self::DateIndexed(item) => {
-				arm!(date_indexed, item)
+			Self::SizeInBytes(order) => (
+				vec![],
+				Some(file_path::size_in_bytes_bytes::order(order.into())),
+			),
+			Self::DateCreated(item) => arm!(date_created, item),
+			Self::DateModified(item) => arm!(date_modified, item),
+			Self::DateIndexed(item) => arm!(date_indexed, item),
+			Self::Object(obj) => {
+				let (after, order) = obj.into_params(id);
+
+				(vec![after], Some(order))
 			}
-			Self::Object(obj) => obj.apply(query),
-		};
+		}
+	}
+
+	/// The cursor of the file paths after `last`, in the same order, `None` for the orders that
+	/// can't be paginated with a cursor.
+	fn after(&self, last: &file_path_with_object::Data) -> Option<Self> {
+		macro_rules! arm {
+			($variant:ident, $item:ident, $field:ident) => {
+				Self::$variant(CursorOrderItem {
+					order: $item.order,
+					data: last.$field.clone(),
+				})
+			};
+		}
+
+		Some(match self {
+			Self::None => Self::None,
+			Self::Name(item) => arm!(Name, item, name),
+			Self::SizeInBytes(_) => return None,
+			Self::DateCreated(item) => arm!(DateCreated, item, date_created),
+			Self::DateModified(item) => arm!(DateModified, item, date_modified),
+			Self::DateIndexed(item) => arm!(DateIndexed, item, date_indexed),
+			Self::Object(obj) => Self::Object(obj.after(last)),
+		})
 	}
 }
 
-#[derive(Deserialize, Type, Debug)]
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FilePathCursor {
 	pub is_dir: bool,
 	pub variant: FilePathCursorVariant,
 }
 
-pub type OrderAndPagination =
-	utils::OrderAndPagination<prisma::file_path::id::Type, FilePathOrder, FilePathCursor>;
+/// The orders and pagination shared with the objects, along with `Relevance` which puts the best
+/// matches first, ranked from the text of the `name` filter, how recently the file paths were
+/// modified and how often they're opened. Only the
+/// [`MAX_RANKED`](super::relevance::MAX_RANKED) most recently modified matches are ranked, along
+/// with the ones whose name starts with the text.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[specta(rename = "FilePathOrderAndPagination")]
+pub enum OrderAndPagination {
+	OrderOnly(FilePathOrder),
+	Offset {
+		offset: i32,
+		order: Option<FilePathOrder>,
+	},
+	Cursor {
+		id: prisma::file_path::id::Type,
+		cursor: FilePathCursor,
+	},
+	Relevance {
+		offset: i32,
+	},
+}
 
 impl OrderAndPagination {
+	/// The file paths skipped before the page, negative offsets reading the first one.
+	pub fn offset(&self) -> i32 {
+		match self {
+			Self::Offset { offset, .. } | Self::Relevance { offset } => (*offset).max(0),
+			Self::OrderOnly(_) | Self::Cursor { .. } => 0,
+		}
+	}
+
+	/// Where the file paths following the `fetched` ones read with `pagination`, the last of them
+	/// being `last`, start from.
+	pub(super) fn after(
+		pagination: Option<&Self>,
+		fetched: usize,
+		last: &file_path_with_object::Data,
+	) -> Option<Self> {
+		let fetched = i32::try_from(fetched).ok()?;
+
+		Some(match pagination {
+			None => Self::Offset {
+				offset: fetched,
+				order: None,
+			},
+			Some(Self::OrderOnly(order)) => Self::Offset {
+				offset: fetched,
+				order: Some(order.clone()),
+			},
+			Some(Self::Offset { offset, order }) => Self::Offset {
+				offset: offset + fetched,
+				order: order.clone(),
+			},
+			Some(Self::Relevance { offset }) => Self::Relevance {
+				offset: offset + fetched,
+			},
+			Some(Self::Cursor { cursor, .. }) => match cursor.variant.after(last) {
+				Some(variant) => Self::Cursor {
+					id: last.id,
+					cursor: FilePathCursor {
+						is_dir: last.is_dir.unwrap_or_default(),
+						variant,
+					},
+				},
+				// The first page was read in this order, the next ones are read by offset
+				None => {
+					let FilePathCursorVariant::SizeInBytes(order) = cursor.variant else {
+						return None;
+					};
+
+					Self::Offset {
+						offset: fetched,
+						order: Some(FilePathOrder::SizeInBytes(order)),
+					}
+				}
+			},
+		})
+	}
+
 	pub fn apply(self, query: &mut file_path::FindManyQuery, group_directories: bool) {
 		match self {
 			Self::OrderOnly(order) => {
 				query.add_order_by(order.into_param());
 			}
 			Self::Offset { offset, order } => {
-				query.set_skip(offset as i64);
+				query.set_skip(i64::from(offset.max(0)));
 
 				if let Some(order) = order {
 					query.add_order_by(order.into_param())
 				}
 			}
 			Self::Cursor { id, cursor } => {
-				// This may seem dumb but it's vital!
-				// If we're grouping by directories + all directories have been fetched,
-				// we don't want to include them in the results.
-				// It's important to keep in mind that since the `order_by` for
-				// `group_directories` comes before all other orderings,
-				// all other orderings will be applied independently to directories and paths.
-				if group_directories && !cursor.is_dir {
-					query.add_where(prisma::file_path::is_dir::not(Some(true)))
+				let (after, order) = cursor.variant.into_params(id);
+
+				// Since the `order_by` for `group_directories` comes before all other orderings,
+				// they're applied independently to directories and files: the cursor only goes
+				// through the group it's in, and the files after the directories are read from
+				// their start.
+				match (group_directories, cursor.is_dir) {
+					(true, true) => query.add_where(or![
+						operator::and(
+							iter::once(file_path::is_dir::equals(Some(true)))
+								.chain(after)
+								.collect()
+						),
+						file_path::is_dir::not(Some(true))
+					]),
+					(true, false) => {
+						query.add_where(file_path::is_dir::not(Some(true)));
+						after.into_iter().for_each(|param| query.add_where(param));
+					}
+					(false, _) => after.into_iter().for_each(|param| query.add_where(param)),
 				}
 
-				cursor.variant.apply(query, id);
+				if let Some(order) = order {
+					query.add_order_by(order);
+				}
 
-				query.add_order_by(prisma::file_path::id::order(prisma::SortOrder::Asc));
+				query.add_order_by(file_path::id::order(prisma::SortOrder::Asc));
 			}
+			Self::Relevance { offset } => query.set_skip(i64::from(offset.max(0))),
 		}
 	}
 }
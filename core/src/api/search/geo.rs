@@ -0,0 +1,216 @@
+//! Distances on the Earth's surface, which SQLite can't compute, so circles are matched by the
+//! boxes stacked over them, which SQLite can filter the stored latitudes and longitudes with.
+
+use sd_prisma::prisma::media_data;
+
+use prisma_client_rust::operator::{and, or};
+
+use super::media_data::GpsBoundingBox;
+
+/// Mean radius of the Earth, in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+/// Boxes stacked from south to north over a circle, the more of them the fewer points outside of
+/// it around its edges are matched
+const CIRCLE_STRIPS: u32 = 8;
+
+/// The points at most `meters` away from a center, in degrees.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Circle {
+	latitude: f64,
+	longitude: f64,
+	meters: f64,
+}
+
+impl Circle {
+	pub fn new(latitude: f64, longitude: f64, meters: f64) -> Self {
+		Self {
+			latitude: latitude.clamp(-90.0, 90.0),
+			longitude: wrap_longitude(longitude),
+			meters: meters.max(0.0),
+		}
+	}
+
+	/// The smallest box containing the circle, spanning every longitude if it covers a pole.
+	pub fn bounding_box(&self) -> GpsBoundingBox {
+		let angle = (self.meters / EARTH_RADIUS).to_degrees();
+
+		let north = self.latitude + angle;
+		let south = self.latitude - angle;
+
+		if north >= 90.0 || south <= -90.0 {
+			return GpsBoundingBox {
+				north: north.min(90.0),
+				south: south.max(-90.0),
+				east: 180.0,
+				west: -180.0,
+			};
+		}
+
+		// How far the circle reaches east and west, at the latitude where it's widest
+		let longitude_angle = (angle.to_radians().sin() / self.latitude.to_radians().cos())
+			.min(1.0)
+			.asin()
+			.to_degrees();
+
+		GpsBoundingBox {
+			north,
+			south,
+			east: wrap_longitude(self.longitude + longitude_angle),
+			west: wrap_longitude(self.longitude - longitude_angle),
+		}
+	}
+
+	/// The boxes covering the circle, each of them as wide as the circle is between its
+	/// latitudes, or its bounding box if it covers a pole.
+	pub fn covering_boxes(&self) -> Vec<GpsBoundingBox> {
+		let angle = (self.meters / EARTH_RADIUS).to_degrees();
+		let south = self.latitude - angle;
+
+		if self.latitude + angle >= 90.0 || south <= -90.0 {
+			return vec![self.bounding_box()];
+		}
+
+		// The circle is widest slightly closer to the pole than its center
+		let widest_latitude = (self.latitude.to_radians().sin() / angle.to_radians().cos())
+			.clamp(-1.0, 1.0)
+			.asin()
+			.to_degrees();
+		let height = 2.0 * angle / f64::from(CIRCLE_STRIPS);
+
+		(0..CIRCLE_STRIPS)
+			.map(|strip| {
+				let south = south + height * f64::from(strip);
+				let north = south + height;
+
+				let half_width = [south, north, widest_latitude.clamp(south, north)]
+					.into_iter()
+					.map(|latitude| self.half_width_at(latitude, angle))
+					.fold(0.0, f64::max);
+
+				if half_width >= 180.0 {
+					GpsBoundingBox {
+						north,
+						south,
+						east: 180.0,
+						west: -180.0,
+					}
+				} else {
+					GpsBoundingBox {
+						north,
+						south,
+						east: wrap_longitude(self.longitude + half_width),
+						west: wrap_longitude(self.longitude - half_width),
+					}
+				}
+			})
+			.collect()
+	}
+
+	/// How far the circle of `angle` degrees reaches east and west at `latitude`, in degrees.
+	fn half_width_at(&self, latitude: f64, angle: f64) -> f64 {
+		let (latitude, center_latitude, angle) = (
+			latitude.to_radians(),
+			self.latitude.to_radians(),
+			angle.to_radians(),
+		);
+
+		((angle.cos() - latitude.sin() * center_latitude.sin())
+			/ (latitude.cos() * center_latitude.cos()))
+		.clamp(-1.0, 1.0)
+		.acos()
+		.to_degrees()
+	}
+
+	pub fn into_params(self) -> Vec<media_data::WhereParam> {
+		vec![or(self
+			.covering_boxes()
+			.into_iter()
+			.map(|bounding_box| and(bounding_box.into_params()))
+			.collect())]
+	}
+
+	#[cfg(test)]
+	fn contains(&self, latitude: f64, longitude: f64) -> bool {
+		haversine_distance((self.latitude, self.longitude), (latitude, longitude)) <= self.meters
+	}
+}
+
+/// The great-circle distance in meters between two `(latitude, longitude)` pairs, in degrees.
+#[cfg(test)]
+fn haversine_distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+	let (from_latitude, to_latitude) = (from.0.to_radians(), to.0.to_radians());
+	let latitude_delta = (to.0 - from.0).to_radians();
+	let longitude_delta = (to.1 - from.1).to_radians();
+
+	let a = (latitude_delta / 2.0).sin().powi(2)
+		+ from_latitude.cos() * to_latitude.cos() * (longitude_delta / 2.0).sin().powi(2);
+
+	2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
+fn wrap_longitude(longitude: f64) -> f64 {
+	(longitude + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn measures_distances_between_coordinates() {
+		// Paris to London, about 344 km
+		let distance = haversine_distance((48.8566, 2.3522), (51.5074, -0.1278));
+		assert!((distance - 343_500.0).abs() < 1_000.0);
+
+		assert_eq!(haversine_distance((10.0, 20.0), (10.0, 20.0)), 0.0);
+	}
+
+	#[test]
+	fn boxes_contain_their_circle() {
+		let circle = Circle::new(35.6762, 139.6503, 5_000.0);
+		let bounding_box = circle.bounding_box();
+		assert!(bounding_box.west < bounding_box.east);
+		assert!(circle.contains(35.69, 139.68));
+		assert!(!circle.contains(35.8, 139.7));
+
+		// Crosses the antimeridian, so the box wraps around
+		let bounding_box = Circle::new(0.0, 179.99, 10_000.0).bounding_box();
+		assert!(bounding_box.west > bounding_box.east);
+
+		// Covers the pole, so every longitude is in the box
+		let bounding_box = Circle::new(89.99, 0.0, 10_000.0).bounding_box();
+		assert_eq!((bounding_box.west, bounding_box.east), (-180.0, 180.0));
+		assert_eq!(bounding_box.north, 90.0);
+	}
+
+	#[test]
+	fn stacked_boxes_cover_their_circle_closely() {
+		let circle = Circle::new(48.8566, 2.3522, 20_000.0);
+		let boxes = circle.covering_boxes();
+		assert_eq!(boxes.len(), CIRCLE_STRIPS as usize);
+
+		let in_boxes = |latitude: f64, longitude: f64| {
+			boxes.iter().any(|b| {
+				(b.south..=b.north).contains(&latitude) && (b.west..=b.east).contains(&longitude)
+			})
+		};
+
+		for step in 0..360 {
+			let bearing = f64::from(step).to_radians();
+			// Just inside the circle, converted to degrees with a flat projection
+			let meters = 19_900.0;
+			let latitude = circle.latitude + (meters * bearing.cos() / EARTH_RADIUS).to_degrees();
+			let longitude = circle.longitude
+				+ (meters * bearing.sin() / EARTH_RADIUS / circle.latitude.to_radians().cos())
+					.to_degrees();
+
+			assert!(circle.contains(latitude, longitude));
+			assert!(in_boxes(latitude, longitude));
+		}
+
+		// The corners of the bounding box are left out
+		let bounding_box = circle.bounding_box();
+		assert!(!in_boxes(bounding_box.north, bounding_box.east));
+		assert!(!in_boxes(bounding_box.south, bounding_box.west));
+	}
+}
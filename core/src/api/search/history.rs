@@ -0,0 +1,295 @@
+//! The text of the searches submitted in the library, recorded unless the library opted out, and the
+//! suggestions completing what's being typed in the search bar.
+
+use crate::{
+	api::utils::{library, Pagination, MAX_TAKE},
+	invalidate_query,
+};
+
+use sd_prisma::prisma::{search_history, tag, PrismaClient, SortOrder};
+
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use prisma_client_rust::{raw, PrismaValue};
+use rspc::alpha::AlphaRouter;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{Ctx, R};
+
+/// Older searches are forgotten past this many
+const MAX_HISTORY_LEN: i64 = 200;
+/// Searches refined by typing more of the text, when recorded this recently, are replaced by the
+/// longer text
+const TYPING_WINDOW_SECS: i64 = 10;
+/// Suggestions returned by default, and at most
+const DEFAULT_SUGGESTIONS: u8 = 10;
+const MAX_SUGGESTIONS: u8 = 50;
+
+/// Whether the searches are recorded, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHistorySettings {
+	pub enabled: bool,
+}
+
+impl Default for SearchHistorySettings {
+	fn default() -> Self {
+		Self { enabled: true }
+	}
+}
+
+#[derive(Serialize, Type, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "text")]
+pub enum SearchSuggestion {
+	/// A search run before
+	History(String),
+	Tag(String),
+	/// The name of a file path, without its extension
+	FileName(String),
+}
+
+impl SearchSuggestion {
+	fn text(&self) -> &str {
+		match self {
+			Self::History(text) | Self::Tag(text) | Self::FileName(text) => text,
+		}
+	}
+}
+
+async fn record(db: &PrismaClient, query: String) -> prisma_client_rust::Result<()> {
+	let now = Utc::now();
+	let typing_since = now - Duration::seconds(TYPING_WINDOW_SECS);
+
+	let typed_prefixes = db
+		.search_history()
+		.find_many(vec![search_history::date_searched::gte(
+			typing_since.into(),
+		)])
+		.select(search_history::select!({ id query }))
+		.exec()
+		.await?
+		.into_iter()
+		.filter(|searched| searched.query != query && query.starts_with(&searched.query))
+		.map(|searched| searched.id)
+		.collect::<Vec<_>>();
+
+	if !typed_prefixes.is_empty() {
+		db.search_history()
+			.delete_many(vec![search_history::id::in_vec(typed_prefixes)])
+			.exec()
+			.await?;
+	}
+
+	db.search_history()
+		.upsert(
+			search_history::query::equals(query.clone()),
+			search_history::create(query, now.into(), vec![]),
+			vec![
+				search_history::count::increment(1),
+				search_history::date_searched::set(now.into()),
+			],
+		)
+		.exec()
+		.await?;
+
+	let forgotten = db
+		.search_history()
+		.find_many(vec![])
+		.order_by(search_history::date_searched::order(SortOrder::Desc))
+		.skip(MAX_HISTORY_LEN)
+		.select(search_history::select!({ id }))
+		.exec()
+		.await?;
+
+	if !forgotten.is_empty() {
+		db.search_history()
+			.delete_many(vec![search_history::id::in_vec(
+				forgotten.into_iter().map(|searched| searched.id).collect(),
+			)])
+			.exec()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Completions of `prefix` from the history, the tags and the names of the file paths, in this
+/// order and without repeating the same text.
+pub(super) async fn suggestions(
+	db: &PrismaClient,
+	prefix: &str,
+	take: Option<u8>,
+) -> Result<Vec<SearchSuggestion>, rspc::Error> {
+	#[derive(Deserialize)]
+	struct FileName {
+		name: String,
+	}
+
+	let take = take
+		.unwrap_or(DEFAULT_SUGGESTIONS)
+		.clamp(1, MAX_SUGGESTIONS);
+
+	if prefix.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let (history, tags) = db
+		._batch((
+			db.search_history()
+				.find_many(vec![search_history::query::starts_with(prefix.to_string())])
+				.order_by(search_history::date_searched::order(SortOrder::Desc))
+				.take(i64::from(take))
+				.select(search_history::select!({ query })),
+			db.tag()
+				.find_many(vec![tag::name::starts_with(prefix.to_string())])
+				.order_by(tag::name::order(SortOrder::Asc))
+				.take(i64::from(take))
+				.select(tag::select!({ name })),
+		))
+		.await?;
+
+	let file_names = db
+		._query_raw::<FileName>(raw!(
+			"SELECT DISTINCT name FROM file_path
+			WHERE name LIKE {} ESCAPE '\\'
+			ORDER BY name
+			LIMIT {}",
+			PrismaValue::String(format!("{}%", escape_like(prefix))),
+			PrismaValue::Int(i64::from(take))
+		))
+		.exec()
+		.await?;
+
+	let mut seen = HashSet::new();
+
+	Ok(history
+		.into_iter()
+		.map(|searched| SearchSuggestion::History(searched.query))
+		.chain(
+			tags.into_iter()
+				.filter_map(|tag| tag.name.map(SearchSuggestion::Tag)),
+		)
+		.chain(
+			file_names
+				.into_iter()
+				.map(|file_name| SearchSuggestion::FileName(file_name.name)),
+		)
+		.filter(|suggestion| seen.insert(suggestion.text().to_lowercase()))
+		.take(usize::from(take))
+		.collect())
+}
+
+/// Matches the `%` and `_` of `text` literally in a `LIKE` pattern.
+fn escape_like(text: &str) -> String {
+	text.replace('\\', "\\\\")
+		.replace('%', "\\%")
+		.replace('_', "\\_")
+}
+
+pub(super) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			// The most recent searches first
+			R.with2(library())
+				.query(|(_, library), take: Option<u8>| async move {
+					Ok(library
+						.db
+						.search_history()
+						.find_many(vec![])
+						.order_by(search_history::date_searched::order(SortOrder::Desc))
+						.take(i64::from(
+							Pagination::new(take.map(u16::from), 0, u16::from(MAX_TAKE)).take(),
+						))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("record", {
+			// Called by the search bar once a search is submitted, as running the search itself
+			// happens again on every refetch
+			R.with2(library())
+				.mutation(|(_, library), query: String| async move {
+					let query = query.trim().to_lowercase();
+
+					if query.is_empty() || !library.config().await.search_history.enabled {
+						return Ok(());
+					}
+
+					record(&library.db, query).await?;
+
+					invalidate_query!(library, "search.history.list");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			// Forgets the given searches, or all of them if no id is given
+			R.with2(library()).mutation(
+				|(_, library), ids: Option<Vec<search_history::id::Type>>| async move {
+					library
+						.db
+						.search_history()
+						.delete_many(
+							ids.map(|ids| vec![search_history::id::in_vec(ids)])
+								.unwrap_or_default(),
+						)
+						.exec()
+						.await?;
+
+					invalidate_query!(library, "search.history.list");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("settings", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library.config().await.search_history)
+			})
+		})
+		.procedure("setSettings", {
+			R.with2(library()).mutation(
+				|(node, library), settings: SearchHistorySettings| async move {
+					let enabled = settings.enabled;
+
+					library
+						.update_config(
+							|config| config.search_history = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					// Opting out also forgets what was recorded
+					if !enabled {
+						library
+							.db
+							.search_history()
+							.delete_many(vec![])
+							.exec()
+							.await?;
+
+						invalidate_query!(library, "search.history.list");
+					}
+
+					invalidate_query!(library, "search.history.settings");
+
+					Ok(())
+				},
+			)
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escapes_like_wildcards() {
+		assert_eq!(escape_like("100%_done\\"), "100\\%\\_done\\\\");
+		assert_eq!(escape_like("holiday"), "holiday");
+	}
+}
@@ -0,0 +1,130 @@
+//! Listings of non indexed paths, so going back to a folder listed a moment ago shows it right
+//! away instead of walking it again.
+//!
+//! A listing is walked again once the modification time of its folder changes, which happens when
+//! entries are added to, removed from or renamed in it. Changes to the contents of its files don't
+//! change it, so listings are also dropped after [`LISTING_TTL`].
+
+use sd_indexer::NonIndexedPathItem;
+
+use std::{
+	collections::HashMap,
+	sync::Mutex,
+	time::{Duration, Instant, SystemTime},
+};
+
+use once_cell::sync::Lazy;
+
+const LISTING_TTL: Duration = Duration::from_secs(60);
+const CAPACITY: usize = 16;
+
+/// The entries of a listing as they were batched when walking it, with the errors as messages
+pub(super) type ListingBatch = Vec<Result<NonIndexedPathItem, String>>;
+
+static LISTINGS: Lazy<Mutex<HashMap<ListingKey, Listing>>> = Lazy::new(Mutex::default);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct ListingKey {
+	backend: &'static str,
+	path: String,
+	/// The rules of a listing only depend on whether it lists hidden files
+	with_hidden_files: bool,
+}
+
+impl ListingKey {
+	pub(super) fn new(backend: &'static str, path: &str, with_hidden_files: bool) -> Self {
+		Self {
+			backend,
+			path: path.to_string(),
+			with_hidden_files,
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Listing {
+	batches: Vec<ListingBatch>,
+	/// Modification time of the folder when it was walked
+	modified: SystemTime,
+	listed_at: Instant,
+}
+
+/// Modification time of the folder at `path`, `None` if it can't be read, so it isn't cached.
+pub(super) async fn folder_modified(path: &str) -> Option<SystemTime> {
+	tokio::fs::metadata(path)
+		.await
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}
+
+/// Returns the listing if it was cached and its folder hasn't been modified since.
+pub(super) fn cached_listing(key: &ListingKey, modified: SystemTime) -> Option<Vec<ListingBatch>> {
+	let mut listings = LISTINGS.lock().expect("listings cache lock poisoned");
+
+	match listings.get_mut(key) {
+		Some(listing)
+			if listing.modified == modified && listing.listed_at.elapsed() < LISTING_TTL =>
+		{
+			Some(listing.batches.clone())
+		}
+		Some(_) => {
+			listings.remove(key);
+			None
+		}
+		None => None,
+	}
+}
+
+/// Caches a listing walked in full, `modified` being the modification time of its folder from
+/// before it was walked.
+pub(super) fn insert(key: ListingKey, modified: SystemTime, batches: Vec<ListingBatch>) {
+	let mut listings = LISTINGS.lock().expect("listings cache lock poisoned");
+
+	listings.retain(|_, listing| listing.listed_at.elapsed() < LISTING_TTL);
+
+	if listings.len() >= CAPACITY && !listings.contains_key(&key) {
+		if let Some(oldest) = listings
+			.iter()
+			.min_by_key(|(_, listing)| listing.listed_at)
+			.map(|(key, _)| key.clone())
+		{
+			listings.remove(&oldest);
+		}
+	}
+
+	listings.insert(
+		key,
+		Listing {
+			batches,
+			modified,
+			listed_at: Instant::now(),
+		},
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn walks_modified_folders_again() {
+		let key = ListingKey::new("fs", "/listing-cache-test/", false);
+		let modified = SystemTime::now();
+
+		assert!(cached_listing(&key, modified).is_none());
+
+		insert(key.clone(), modified, vec![vec![Err("denied".to_string())]]);
+		assert_eq!(
+			cached_listing(&key, modified).map(|batches| batches.len()),
+			Some(1)
+		);
+		assert!(cached_listing(
+			&ListingKey::new("fs", "/listing-cache-test/", true),
+			modified
+		)
+		.is_none());
+
+		assert!(cached_listing(&key, modified + Duration::from_secs(1)).is_none());
+		assert!(cached_listing(&key, modified).is_none());
+	}
+}
@@ -1,9 +1,11 @@
 use sd_prisma::prisma::{self, media_data};
 
+use chrono::{DateTime, Utc};
+use prisma_client_rust::{and, or};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use super::utils::*;
+use super::{geo::Circle, utils::*};
 
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase", tag = "field", content = "value")]
@@ -27,3 +29,76 @@ impl MediaDataOrder {
 		}
 	}
 }
+
+/// The area between two latitudes and two longitudes, in degrees. It crosses the antimeridian when
+/// `west` is greater than `east`.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct GpsBoundingBox {
+	pub north: f64,
+	pub south: f64,
+	pub east: f64,
+	pub west: f64,
+}
+
+impl GpsBoundingBox {
+	pub fn into_params(self) -> Vec<media_data::WhereParam> {
+		use media_data::*;
+
+		let Self {
+			north,
+			south,
+			east,
+			west,
+		} = self;
+
+		vec![
+			latitude::gte(south),
+			latitude::lte(north),
+			if west <= east {
+				and![longitude::gte(west), longitude::lte(east)]
+			} else {
+				or![longitude::gte(west), longitude::lte(east)]
+			},
+		]
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaDataFilterArgs {
+	CapturedAt(Range<DateTime<Utc>>),
+	CameraModel(TextMatch),
+	Location(GpsBoundingBox),
+	/// Taken at most `meters` away from the point at `lat` and `lon`, in degrees, as well as some
+	/// points just outside of the circle
+	WithinRadius {
+		lat: f64,
+		lon: f64,
+		meters: f64,
+	},
+}
+
+impl MediaDataFilterArgs {
+	pub fn into_params(self) -> Vec<media_data::WhereParam> {
+		use media_data::*;
+
+		match self {
+			Self::CapturedAt(v) => vec![match v {
+				Range::From(v) => epoch_time::gte(v.timestamp()),
+				Range::To(v) => epoch_time::lte(v.timestamp()),
+			}],
+			Self::CameraModel(v) => v
+				.into_param(
+					camera_model::contains,
+					camera_model::starts_with,
+					camera_model::ends_with,
+					|s| camera_model::equals(Some(s)),
+				)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
+			Self::Location(v) => v.into_params(),
+			Self::WithinRadius { lat, lon, meters } => Circle::new(lat, lon, meters).into_params(),
+		}
+	}
+}
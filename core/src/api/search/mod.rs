@@ -1,60 +1,582 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
 
 use crate::{
-	api::{locations::ExplorerItem, utils::library},
+	api::{
+		error::ApiError,
+		locations::ExplorerItem,
+		utils::{library, InvalidateOperationEvent, Pagination, MAX_TAKE},
+		CoreEvent,
+	},
 	library::Library,
-	location::LocationError,
+	location::{
+		remote::{sftp_location_operator, WebdavConnection},
+		LocationError,
+	},
 	object::{
-		cas::generate_cas_id,
-		media::old_thumbnail::{
-			get_ephemeral_thumb_key, get_indexed_thumb_key, BatchToProcess, GenerateThumbnailArgs,
+		cas::ephemeral_cas_id,
+		media::{
+			media_metadata_from_prisma_data,
+			old_thumbnail::{
+				can_generate_thumbnail_for_extension, get_ephemeral_thumb_key,
+				get_indexed_thumb_key, get_indexed_thumbnail_path, remote_cas_id,
+				remote_thumbnailer, BatchToProcess, GenerateThumbnailArgs, RemoteFile,
+				ThumbnailTier,
+			},
 		},
 	},
+	preferences::LibraryPreferences,
 	util::{unsafe_streamed_query, BatchedStream},
+	Node,
 };
 
-use opendal::{services::Fs, Operator};
+use opendal::{
+	services::{Fs, Ftp, S3},
+	Operator,
+};
 
-use sd_cache::{CacheNode, Model, Normalise, Reference};
-use sd_core_indexer_rules::seed::{no_hidden, no_os_protected};
-use sd_core_indexer_rules::IndexerRule;
+use sd_cache::{CacheNode, Model, Normalise, NormalisedDiff, Reference};
+use sd_core_indexer_rules::cache::ephemeral_rules;
 use sd_core_prisma_helpers::{file_path_with_object, object_with_file_paths};
 use sd_file_ext::kind::ObjectKind;
-use sd_prisma::prisma::{self, location, PrismaClient};
-use sd_utils::chain_optional_iter;
+use sd_indexer::ListingPage;
+use sd_media_metadata::MediaMetadata;
+use sd_prisma::prisma::{self, location, saved_search, PrismaClient};
+use sd_utils::{error::FileIOError, from_bytes_to_uuid};
 
 use async_stream::stream;
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use tokio::{
+	sync::{broadcast, mpsc},
+	time,
+};
 use tracing::{error, warn};
+use uuid::Uuid;
 
+mod count;
+mod explorer;
 pub mod file_path;
+mod geo;
+pub mod history;
+mod listing_cache;
 pub mod media_data;
 pub mod object;
+mod pattern;
+pub mod preferences;
+mod prefetch;
+mod relevance;
 pub mod saved;
 mod utils;
 
-pub use self::{file_path::*, object::*, utils::*};
+pub use self::{count::CachedCount, file_path::*, object::*, utils::*};
+
+use self::{
+	count::{cached_count, insert_count, CountTarget},
+	explorer::{stream_paths, ExplorerBatch},
+	listing_cache::{cached_listing, folder_modified, ListingBatch, ListingKey},
+	prefetch::{prefetch_next_page, prefetched_page, PageKey},
+};
 
 use super::{Ctx, R};
 
-const MAX_TAKE: u8 = 100;
+/// Nothing tells the clients when a non indexed path changes, so they only keep it for a while
+const EPHEMERAL_NODE_TTL: Duration = Duration::from_secs(5 * 60);
+const LIVE_SEARCH_DEBOUNCE: Duration = Duration::from_millis(250);
+/// The first batch of the streamed searches is small so it's rendered right away, the next ones
+/// double up to [`MAX_STREAMED_BATCH`]
+const FIRST_STREAMED_BATCH: i64 = 100;
+const MAX_STREAMED_BATCH: i64 = 5000;
+/// Objects read by each query of a page, so big pages don't include the file paths of too many
+/// objects at once
+const OBJECTS_CHUNK: usize = 1000;
+/// Entries of a page of a S3 bucket, the size of the pages of its API
+const S3_PAGE_ENTRIES: usize = 1000;
 
 #[derive(Serialize, Type, Debug)]
-struct SearchData<T: Model> {
-	cursor: Option<Vec<u8>>,
+struct SearchData<T: Model, C> {
+	/// The `orderAndPagination` of the next page, missing once there are no more
+	cursor: Option<C>,
 	items: Vec<Reference<T>>,
 	nodes: Vec<CacheNode>,
+	/// Amount of items matching the filters, counted in the same round trip as the page when
+	/// `withCount` is set
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[specta(optional)]
+	count: Option<u32>,
 }
 
-impl<T: Model> Model for SearchData<T> {
+/// The items found, the pagination of the next page and the count of the items when requested
+type SearchResults<C> = (Vec<ExplorerItem>, Option<C>, Option<u32>);
+
+impl<T: Model, C> Model for SearchData<T, C> {
 	fn name() -> &'static str {
 		T::name()
 	}
 }
 
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FilePathSearchArgs {
+	/// Up to [`MAX_TAKE`], which is also the default
+	#[specta(optional)]
+	take: Option<u16>,
+	#[specta(optional)]
+	order_and_pagination: Option<file_path::OrderAndPagination>,
+	#[serde(default)]
+	filters: Vec<SearchFilterArgs>,
+	#[serde(default = "default_group_directories")]
+	group_directories: bool,
+	/// Also count the file paths matching the filters, like `search.pathsCount`
+	#[serde(default)]
+	#[specta(optional)]
+	with_count: bool,
+	/// Don't look for the thumbnails of the file paths, for views not showing them
+	#[serde(default)]
+	#[specta(optional)]
+	skip_thumbnails: bool,
+	/// Also read the media data of the file paths, for views showing it like the inspector
+	#[serde(default)]
+	#[specta(optional)]
+	with_media_data: bool,
+	/// Read the next page in the background, for offset pages
+	#[serde(default)]
+	#[specta(optional)]
+	prefetch: bool,
+	/// Apply the default filters, order and grouping stored in the preferences of this view
+	#[specta(optional)]
+	use_defaults: Option<ViewDefaults>,
+}
+
+/// A view whose defaults are stored in the preferences of the library.
+#[derive(Deserialize, Type, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum ViewDefaults {
+	Location { id: location::id::Type },
+	SavedSearch { id: saved_search::id::Type },
+}
+
+fn default_group_directories() -> bool {
+	true
+}
+
+impl FilePathSearchArgs {
+	fn pagination(&self) -> Pagination {
+		Pagination::new(
+			self.take,
+			self.order_and_pagination
+				.as_ref()
+				.map_or(0, |order_and_pagination| order_and_pagination.offset()),
+			u16::from(MAX_TAKE),
+		)
+	}
+
+	/// The offset and order of the page, unless it's paginated with a cursor.
+	fn offset_page(&self) -> Option<(i32, Option<&file_path::FilePathOrder>)> {
+		match &self.order_and_pagination {
+			None => Some((0, None)),
+			Some(file_path::OrderAndPagination::OrderOnly(order)) => Some((0, Some(order))),
+			Some(file_path::OrderAndPagination::Offset { offset, order }) => {
+				Some(((*offset).max(0), order.as_ref()))
+			}
+			// Ranked pages aren't cached, as their order changes with time
+			Some(
+				file_path::OrderAndPagination::Cursor { .. }
+				| file_path::OrderAndPagination::Relevance { .. },
+			) => None,
+		}
+	}
+
+	fn page_key(&self, library_id: Uuid) -> Option<PageKey> {
+		let (offset, order) = self.offset_page()?;
+
+		Some(PageKey::new(
+			library_id,
+			"search.paths",
+			&(
+				&self.filters,
+				order,
+				self.pagination().take(),
+				self.group_directories,
+				self.skip_thumbnails,
+				self.with_media_data,
+			),
+			offset,
+		))
+	}
+
+	fn next_page(&self) -> Option<Self> {
+		let (_, order) = self.offset_page()?;
+		let next = self.pagination().next();
+
+		Some(Self {
+			take: Some(next.take()),
+			order_and_pagination: Some(file_path::OrderAndPagination::Offset {
+				offset: next.skip(),
+				order: order.cloned(),
+			}),
+			filters: self.filters.clone(),
+			group_directories: self.group_directories,
+			with_count: false,
+			skip_thumbnails: self.skip_thumbnails,
+			with_media_data: self.with_media_data,
+			prefetch: false,
+			use_defaults: None,
+		})
+	}
+
+	/// Applies the defaults of the view in `use_defaults`: their filters are added to the ones of
+	/// the client and their grouping replaces its own, but their order is only used if the client
+	/// doesn't order the view itself.
+	async fn with_defaults(mut self, library: &Library) -> Result<Self, rspc::Error> {
+		let Some(view) = self.use_defaults.take() else {
+			return Ok(self);
+		};
+
+		let pub_id = match view {
+			ViewDefaults::Location { id } => {
+				library
+					.db
+					.location()
+					.find_unique(location::id::equals(id))
+					.select(location::select!({ pub_id }))
+					.exec()
+					.await?
+					.ok_or(LocationError::IdNotFound(id))?
+					.pub_id
+			}
+			ViewDefaults::SavedSearch { id } => {
+				library
+					.db
+					.saved_search()
+					.find_unique(saved_search::id::equals(id))
+					.select(saved_search::select!({ pub_id }))
+					.exec()
+					.await?
+					.ok_or(ApiError::SavedSearchNotFound { id })?
+					.pub_id
+			}
+		};
+		let pub_id = from_bytes_to_uuid(&pub_id);
+
+		let preferences = LibraryPreferences::read(&library.db).await?;
+
+		let settings = match view {
+			ViewDefaults::Location { .. } => preferences.location_explorer(&pub_id),
+			ViewDefaults::SavedSearch { .. } => preferences.saved_search_explorer(&pub_id),
+		};
+
+		let Some(settings) = settings else {
+			return Ok(self);
+		};
+
+		self.filters.extend(settings.filters().iter().cloned());
+
+		if let Some(group_directories) = settings.group_directories() {
+			self.group_directories = group_directories;
+		}
+
+		match &mut self.order_and_pagination {
+			None => {
+				self.order_and_pagination = settings
+					.order()
+					.cloned()
+					.map(file_path::OrderAndPagination::OrderOnly)
+			}
+			Some(file_path::OrderAndPagination::Offset {
+				order: order @ None,
+				..
+			}) => *order = settings.order().cloned(),
+			Some(_) => {}
+		}
+
+		Ok(self)
+	}
+}
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ObjectSearchArgs {
+	/// Up to the `max_objects_take` search preference of the node, which is also the default
+	#[specta(optional)]
+	take: Option<u16>,
+	#[specta(optional)]
+	order_and_pagination: Option<object::OrderAndPagination>,
+	#[serde(default)]
+	filters: Vec<SearchFilterArgs>,
+	/// Also count the objects matching the filters, like `search.objectsCount`
+	#[serde(default)]
+	#[specta(optional)]
+	with_count: bool,
+	/// Don't look for the thumbnails of the objects, for views not showing them
+	#[serde(default)]
+	#[specta(optional)]
+	skip_thumbnails: bool,
+	/// Also read the media data of the objects, for views showing it like the inspector
+	#[serde(default)]
+	#[specta(optional)]
+	with_media_data: bool,
+	/// Read the next page in the background, for offset pages
+	#[serde(default)]
+	#[specta(optional)]
+	prefetch: bool,
+}
+
+impl ObjectSearchArgs {
+	fn pagination(&self, max_take: u16) -> Pagination {
+		Pagination::new(
+			self.take,
+			self.order_and_pagination
+				.as_ref()
+				.map_or(0, |order_and_pagination| order_and_pagination.offset()),
+			max_take,
+		)
+	}
+
+	/// The offset and order of the page, unless it's paginated with a cursor.
+	fn offset_page(&self) -> Option<(i32, Option<&object::ObjectOrder>)> {
+		match &self.order_and_pagination {
+			None => Some((0, None)),
+			Some(object::OrderAndPagination::OrderOnly(order)) => Some((0, Some(order))),
+			Some(object::OrderAndPagination::Offset { offset, order }) => {
+				Some(((*offset).max(0), order.as_ref()))
+			}
+			Some(object::OrderAndPagination::Cursor { .. }) => None,
+		}
+	}
+
+	fn page_key(&self, library_id: Uuid, max_take: u16) -> Option<PageKey> {
+		let (offset, order) = self.offset_page()?;
+
+		Some(PageKey::new(
+			library_id,
+			"search.objects",
+			&(
+				&self.filters,
+				order,
+				self.pagination(max_take).take(),
+				self.skip_thumbnails,
+				self.with_media_data,
+			),
+			offset,
+		))
+	}
+
+	fn next_page(&self, max_take: u16) -> Option<Self> {
+		let (_, order) = self.offset_page()?;
+		let next = self.pagination(max_take).next();
+
+		Some(Self {
+			take: Some(next.take()),
+			order_and_pagination: Some(object::OrderAndPagination::Offset {
+				offset: next.skip(),
+				order: order.cloned(),
+			}),
+			filters: self.filters.clone(),
+			with_count: false,
+			skip_thumbnails: self.skip_thumbnails,
+			with_media_data: self.with_media_data,
+			prefetch: false,
+		})
+	}
+}
+
+#[derive(Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PathFrom {
+	Path,
+	/// A FTP server, connected to with the FTP credentials of the search
+	Ftp,
+	/// A S3 bucket, or one of a service compatible with S3 like MinIO
+	#[serde(rename_all = "camelCase")]
+	S3 {
+		bucket: String,
+		/// `us-east-1` if missing, which the services compatible with S3 usually accept
+		#[specta(optional)]
+		region: Option<String>,
+		/// The URL of a service compatible with S3, like `http://localhost:9000` for MinIO
+		#[specta(optional)]
+		endpoint: Option<String>,
+	},
+	/// The SSH server of a remote location, whose files are read over SFTP with the connection
+	/// stored for the location
+	#[serde(rename_all = "camelCase")]
+	Sftp {
+		location_id: location::id::Type,
+	},
+	/// A WebDAV server, like the ones of Nextcloud and ownCloud
+	#[serde(rename_all = "camelCase")]
+	Webdav {
+		/// Like `https://cloud.example.com/remote.php/dav/files/user` for Nextcloud
+		endpoint: String,
+		/// The server is read anonymously if missing
+		#[specta(optional)]
+		user: Option<String>,
+		/// Not kept anywhere, unlike the passwords of the remote locations
+		#[specta(optional)]
+		password: Option<String>,
+	},
+	// TODO: GDrive
+}
+
+impl PathFrom {
+	/// The OpenDAL operator reading the files of this source, at the root of its file system.
+	pub(crate) async fn operator(
+		&self,
+		library: &Library,
+		credentials: Option<RemoteCredentials>,
+	) -> Result<Operator, rspc::Error> {
+		Ok(match self {
+			PathFrom::Path => {
+				let mut fs = Fs::default();
+				fs.root("/");
+				Operator::new(fs)
+					.map_err(|source| ApiError::EphemeralSearch {
+						path: "/".to_string(),
+						source,
+					})?
+					.finish()
+			}
+			PathFrom::Ftp => {
+				let FtpCredentials {
+					endpoint,
+					user,
+					password,
+				} = RemoteCredentials::ftp(credentials)?;
+
+				let mut ftp = Ftp::default();
+				ftp.endpoint(&endpoint);
+				if let Some(user) = &user {
+					ftp.user(user);
+				}
+				if let Some(password) = &password {
+					ftp.password(password);
+				}
+
+				Operator::new(ftp)
+					.map_err(|source| ApiError::EphemeralSearch {
+						path: endpoint,
+						source,
+					})?
+					.finish()
+			}
+			PathFrom::S3 {
+				bucket,
+				region,
+				endpoint,
+			} => {
+				let mut s3 = S3::default();
+				s3.bucket(bucket);
+				s3.region(region.as_deref().unwrap_or("us-east-1"));
+				if let Some(endpoint) = endpoint {
+					s3.endpoint(endpoint);
+				}
+				// Only the credentials of the search are used, never the ones of this node (from its
+				// environment, AWS profile or EC2 metadata), as the endpoint can be any server
+				s3.disable_config_load();
+				s3.disable_ec2_metadata();
+				match RemoteCredentials::s3(credentials)? {
+					Some(S3Credentials {
+						access_key_id,
+						secret_access_key,
+					}) => {
+						s3.access_key_id(&access_key_id);
+						s3.secret_access_key(&secret_access_key);
+					}
+					None => {
+						s3.allow_anonymous();
+					}
+				}
+
+				Operator::new(s3)
+					.map_err(|source| ApiError::EphemeralSearch {
+						path: bucket.clone(),
+						source,
+					})?
+					.finish()
+			}
+			PathFrom::Sftp { location_id } => sftp_location_operator(library, *location_id).await?,
+			PathFrom::Webdav {
+				endpoint,
+				user,
+				password,
+			} => WebdavConnection {
+				endpoint: endpoint.clone(),
+				user: user.clone(),
+			}
+			.operator(password.as_deref())?,
+		})
+	}
+}
+
+/// The credentials of a FTP server or S3 bucket given along with the [`PathFrom`] of a search, as
+/// they're not kept anywhere.
+#[derive(Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum RemoteCredentials {
+	Ftp(FtpCredentials),
+	S3(S3Credentials),
+}
+
+impl RemoteCredentials {
+	fn ftp(credentials: Option<Self>) -> Result<FtpCredentials, rspc::Error> {
+		match credentials {
+			Some(Self::Ftp(credentials)) => Ok(credentials),
+			_ => Err(rspc::Error::new(
+				ErrorCode::BadRequest,
+				"The credentials of the FTP server are missing".to_string(),
+			)),
+		}
+	}
+
+	/// The bucket is read anonymously without credentials, for the public ones
+	fn s3(credentials: Option<Self>) -> Result<Option<S3Credentials>, rspc::Error> {
+		match credentials {
+			Some(Self::S3(credentials)) => Ok(Some(credentials)),
+			None => Ok(None),
+			Some(Self::Ftp(_)) => Err(rspc::Error::new(
+				ErrorCode::BadRequest,
+				"FTP credentials can't be used for a S3 bucket".to_string(),
+			)),
+		}
+	}
+}
+
+#[derive(Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct S3Credentials {
+	access_key_id: String,
+	secret_access_key: String,
+}
+
+#[derive(Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FtpCredentials {
+	/// Like `ftp://example.com:21`
+	endpoint: String,
+	#[specta(optional)]
+	user: Option<String>,
+	#[specta(optional)]
+	password: Option<String>,
+}
+
+#[derive(Serialize, Type, Debug)]
+struct EphemeralPathsResultItem {
+	pub entries: Vec<Reference<ExplorerItem>>,
+	pub errors: Vec<String>,
+	pub nodes: Vec<CacheNode>,
+	/// Only in the last item of a page of a S3 bucket with more entries, to give as the
+	/// `startAfter` of the search listing the next page
+	pub next_page: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum SearchFilterArgs {
@@ -71,11 +593,11 @@ impl SearchFilterArgs {
 	) -> Result<Vec<T>, rspc::Error> {
 		Ok(match self {
 			Self::FilePath(v) => file_path(v.into_params(db).await?),
-			Self::Object(v) => object(v.into_params()),
+			Self::Object(v) => object(v.into_params(db).await?),
 		})
 	}
 
-	async fn into_file_path_params(
+	pub(crate) async fn into_file_path_params(
 		self,
 		db: &PrismaClient,
 	) -> Result<Vec<prisma::file_path::WhereParam>, rspc::Error> {
@@ -83,7 +605,7 @@ impl SearchFilterArgs {
 			.await
 	}
 
-	async fn into_object_params(
+	pub(crate) async fn into_object_params(
 		self,
 		db: &PrismaClient,
 	) -> Result<Vec<prisma::object::WhereParam>, rspc::Error> {
@@ -95,273 +617,345 @@ impl SearchFilterArgs {
 pub fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("ephemeralPaths", {
-			#[derive(Deserialize, Type, Debug, PartialEq, Eq)]
-			#[serde(rename_all = "camelCase")]
-			enum PathFrom {
-				Path,
-				// TODO: FTP + S3 + GDrive
-			}
-
 			#[derive(Deserialize, Type, Debug)]
 			#[serde(rename_all = "camelCase")]
 			struct EphemeralPathSearchArgs {
 				from: PathFrom,
 				path: String,
 				with_hidden_files: bool,
-			}
-
-			#[derive(Serialize, Type, Debug)]
-			struct EphemeralPathsResultItem {
-				pub entries: Vec<Reference<ExplorerItem>>,
-				pub errors: Vec<String>,
-				pub nodes: Vec<CacheNode>,
+				#[specta(optional)]
+				credentials: Option<RemoteCredentials>,
+				/// To list the next page of a S3 bucket, from the `next_page` of the previous one
+				#[specta(optional)]
+				start_after: Option<String>,
 			}
 
 			R.with2(library()).subscription(
 				|(node, library),
 				 EphemeralPathSearchArgs {
 				     from,
-				     mut path,
+				     path,
 				     with_hidden_files,
+				     credentials,
+				     start_after,
 				 }| async move {
-					let service = match from {
-						PathFrom::Path => {
-							let mut fs = Fs::default();
-							fs.root("/");
-							Operator::new(fs)
-								.map_err(|err| {
-									rspc::Error::new(
-										ErrorCode::InternalServerError,
-										err.to_string(),
-									)
-								})?
-								.finish()
-						}
-					};
-
-					let rules = chain_optional_iter(
-						[IndexerRule::from(no_os_protected())],
-						[(!with_hidden_files).then(|| IndexerRule::from(no_hidden()))],
-					);
-
-					// OpenDAL is specific about paths (and the rest of Spacedrive is not)
-					if !path.ends_with('/') {
-						path.push('/');
-					}
+					Ok(unsafe_streamed_query(
+						ephemeral_paths(
+							node,
+							library,
+							from,
+							credentials,
+							path,
+							with_hidden_files,
+							start_after,
+						)
+						.await?,
+					))
+				},
+			)
+		})
+		.procedure("ephemeralPathsLive", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct LiveEphemeralPathsArgs {
+				path: String,
+				with_hidden_files: bool,
+			}
 
-					let stream =
-						sd_indexer::ephemeral(service, rules, &path)
-							.await
-							.map_err(|err| {
-								rspc::Error::new(ErrorCode::InternalServerError, err.to_string())
-							})?;
-
-					let mut stream = BatchedStream::new(stream);
-					Ok(unsafe_streamed_query(stream! {
-						let mut to_generate = vec![];
-
-						while let Some(result) = stream.next().await {
-							// We optimize for the case of no errors because it should be way more common.
-							let mut entries = Vec::with_capacity(result.len());
-							let mut errors = Vec::with_capacity(0);
-
-							// For this batch we check if any directories are actually locations, so the UI can link directly to them
-							let locations = library
-								.db
-								.location()
-								.find_many(vec![location::path::in_vec(
-									result.iter().filter_map(|e| match e {
-										Ok(e) if ObjectKind::from_i32(e.kind) == ObjectKind::Folder => Some(e.path.clone()),
-										_ => None
-									}).collect::<Vec<_>>()
-								)])
-								.exec()
+			// Sends the entries of a path outside of any location, then the changes to them as
+			// they're added, removed or modified
+			R.with2(library()).subscription(
+				|(node, library),
+				 LiveEphemeralPathsArgs {
+				     path,
+				     with_hidden_files,
+				 }| async move {
+					let (watcher, mut changes_rx) = watch_directory(&path)?;
+
+					Ok(stream! {
+						// Watches the directory until the client stops listening
+						let _watcher = watcher;
+						let rules = ephemeral_rules(with_hidden_files, true);
+						let mut diff = NormalisedDiff::new();
+						// By listed path, so the entries which changed are replaced in place
+						let mut items = BTreeMap::new();
+						let mut change = DirectoryChange::Rescan;
+						let mut use_cache = true;
+						let mut submitted_thumbnails = false;
+
+						loop {
+							match change {
+								DirectoryChange::Rescan => match ephemeral_items(
+									node.clone(),
+									library.clone(),
+									PathFrom::Path,
+									None,
+									path.clone(),
+									with_hidden_files,
+									use_cache,
+									None,
+								)
 								.await
-								.and_then(|l| {
-									Ok(l.into_iter()
-										.filter_map(|item| item.path.clone().map(|l| (l, item)))
-										.collect::<HashMap<_, _>>())
-								})
-								.map_err(|err| error!("Looking up locations failed: {err:?}"))
-								.unwrap_or_default();
-
-							for item in result {
-								match item {
-									Ok(item) => {
-										let kind = ObjectKind::from_i32(item.kind);
-										let should_generate_thumbnail = {
-											#[cfg(feature = "ffmpeg")]
-											{
-												matches!(
-													kind,
-													ObjectKind::Image | ObjectKind::Video | ObjectKind::Document
-												)
-											}
+								{
+									Ok(batches) => {
+										items.clear();
 
-											#[cfg(not(feature = "ffmpeg"))]
-											{
-												matches!(kind, ObjectKind::Image | ObjectKind::Document)
-											}
-										};
-
-										// TODO: This requires all paths to be loaded before thumbnailing starts.
-										// TODO: This copies the existing functionality but will not fly with Cloud locations (as loading paths will be *way* slower)
-										// TODO: https://linear.app/spacedriveapp/issue/ENG-1719/cloud-thumbnailer
-										let thumbnail = if should_generate_thumbnail {
-											if from == PathFrom::Path {
-												let size = u64::from_be_bytes((&*item.size_in_bytes_bytes).try_into().expect("Invalid size"));
-												if let Ok(cas_id) = generate_cas_id(&item.path, size).await.map_err(|err| error!("Error generating cas id for '{:?}': {err:?}", item.path)) {
-													if ObjectKind::from_i32(item.kind) == ObjectKind::Document {
-														to_generate.push(GenerateThumbnailArgs::new(
-															item.extension.clone(),
-															cas_id.clone(),
-															PathBuf::from(&item.path),
-														));
-													} else {
-														to_generate.push(GenerateThumbnailArgs::new(
-															item.extension.clone(),
-															cas_id.clone(),
-															PathBuf::from(&item.path),
-														));
-													}
-
-													Some(get_ephemeral_thumb_key(&cas_id))
-												} else {
-													None
-												}
-											} else {
-												warn!("Thumbnailer not supported for cloud locations");
-												None
-											}
-										} else {
-											None
-										};
+										for (entries, errors, _) in batches.collect::<Vec<_>>().await {
+											items.extend(entries.into_iter().filter_map(|item| {
+												listed_path(&item).map(|path| (path, item))
+											}));
 
-										entries.push(if let Some(item) = locations.get(&item.path) {
-											ExplorerItem::Location {
-												item: item.clone(),
+											for e in errors {
+												warn!("Failed to read an entry of '{path}': {e}");
 											}
-										} else {
-											ExplorerItem::NonIndexedPath {
-												thumbnail,
-												item,
+										}
+									}
+									Err(e) => error!("Failed to update the entries of '{path}': {e:#?}"),
+								},
+								// Only the entries which changed are read again
+								DirectoryChange::Entries(changed) => {
+									let mut batch = ListingBatch::new();
+
+									for changed in changed {
+										if !tokio::fs::try_exists(&changed).await.unwrap_or(false) {
+											items.remove(&changed);
+											continue;
+										}
+
+										match sd_indexer::ephemeral_entry(&rules, &changed).await {
+											Ok(Some(item)) => batch.push(Ok(item)),
+											// Rejected by the rules now, like a file made hidden
+											Ok(None) => {
+												items.remove(&changed);
 											}
-										});
-									},
-									Err(e) => errors.push(e.to_string()),
+											Err(e) => warn!("Failed to read an entry of '{path}': {e}"),
+										}
+									}
+
+									let (entries, errors) = explorer_items(
+										&node,
+										&library,
+										&PathFrom::Path,
+										None,
+										batch,
+										&mut submitted_thumbnails,
+									)
+									.await;
+
+									items.extend(entries.into_iter().filter_map(|item| {
+										listed_path(&item).map(|path| (path, item))
+									}));
+
+									for e in errors {
+										warn!("Failed to read an entry of '{path}': {e}");
+									}
 								}
 							}
 
-							let (nodes, entries) = entries.normalise(|item: &ExplorerItem| item.id());
+							let delta = diff.diff_refs(items.values(), |item| item.id());
 
-							yield EphemeralPathsResultItem {
-								entries,
-								errors,
-								nodes,
-							};
-						}
+							if !delta.is_empty() {
+								yield delta;
+							}
+
+							// A file whose contents changed doesn't change its directory
+							use_cache = false;
 
-						if to_generate.len() > 0 {
-							node.thumbnailer
-								.new_ephemeral_thumbnails_batch(BatchToProcess::new(
-									to_generate,
-									false,
-									false,
-								))
-								.await;
+							match wait_for_directory_change(&mut changes_rx).await {
+								Some(next) => change = next.of_entries(Path::new(&path)),
+								None => break,
+							}
 						}
-					}))
+					})
 				},
 			)
 		})
 		.procedure("paths", {
+			R.with2(library())
+				.query(|(node, library), args: FilePathSearchArgs| async move {
+					let args = args.with_defaults(&library).await?;
+					let page = args.page_key(library.id);
+
+					if let Some(data) = prefetched_page(page.as_ref(), args.with_count) {
+						return Ok(data);
+					}
+
+					let take = args.pagination().take();
+					let next = args.prefetch.then(|| args.next_page()).flatten();
+
+					let (items, cursor, count) = find_paths(&node, &library, args).await?;
+
+					if let (Some(page), Some(next), Some(_)) = (page, next, &cursor) {
+						prefetch_next_page(page, take, async move {
+							find_paths(&node, &library, next).await
+						});
+					}
+
+					let (nodes, items) = items.normalise(|item| item.id());
+
+					Ok(SearchData {
+						items,
+						cursor,
+						nodes,
+						count,
+					})
+				})
+		})
+		.procedure("pathsLive", {
 			#[derive(Deserialize, Type, Debug)]
 			#[serde(rename_all = "camelCase")]
-			struct FilePathSearchArgs {
+			struct LivePathsArgs {
 				#[specta(optional)]
-				take: Option<u8>,
+				take: Option<u16>,
 				#[specta(optional)]
-				order_and_pagination: Option<file_path::OrderAndPagination>,
+				order: Option<file_path::FilePathOrder>,
 				#[serde(default)]
 				filters: Vec<SearchFilterArgs>,
 				#[serde(default = "default_group_directories")]
 				group_directories: bool,
 			}
 
-			fn default_group_directories() -> bool {
-				true
-			}
-
-			R.with2(library()).query(
+			// Sends the first page of `search.paths`, then the changes to it as the file paths change
+			R.with2(library()).subscription(
 				|(node, library),
-				 FilePathSearchArgs {
+				 LivePathsArgs {
 				     take,
-				     order_and_pagination,
+				     order,
 				     filters,
 				     group_directories,
 				 }| async move {
-					let Library { db, .. } = library.as_ref();
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					stream! {
+						let mut diff = NormalisedDiff::new();
+
+						loop {
+							match find_paths(
+								&node,
+								&library,
+								FilePathSearchArgs {
+									take,
+									order_and_pagination: order
+										.clone()
+										.map(file_path::OrderAndPagination::OrderOnly),
+									filters: filters.clone(),
+									group_directories,
+									with_count: false,
+									skip_thumbnails: false,
+									with_media_data: false,
+									prefetch: false,
+									use_defaults: None,
+								},
+							)
+							.await
+							{
+								Ok((items, _, _)) => {
+									let delta = diff.diff(items, |item| item.id());
 
-					let params = {
-						let mut params = Vec::new();
+									if !delta.is_empty() {
+										yield delta;
+									}
+								}
+								Err(e) => error!("Failed to update live search results: {e:#?}"),
+							}
 
-						for filter in filters {
-							params.extend(filter.into_file_path_params(db).await?);
+							if !wait_for_invalidation(&mut event_bus_rx, &["search.paths", "search.objects"])
+								.await
+							{
+								break;
+							}
 						}
+					}
+				},
+			)
+		})
+		.procedure("pathsStreamed", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct StreamedPathsArgs {
+				#[specta(optional)]
+				order: Option<file_path::FilePathOrder>,
+				#[serde(default)]
+				filters: Vec<SearchFilterArgs>,
+				#[serde(default = "default_group_directories")]
+				group_directories: bool,
+			}
 
-						params
-					};
-
-					let mut query = db.file_path().find_many(params);
+			#[derive(Serialize, Type, Debug)]
+			struct StreamedPathsBatch {
+				items: Vec<Reference<ExplorerItem>>,
+				errors: Vec<String>,
+				nodes: Vec<CacheNode>,
+			}
 
-					if let Some(take) = take {
-						query = query.take(take as i64);
-					}
+			// All of the results of `search.paths`, sent in batches as they are read so big
+			// directories are rendered progressively
+			R.with2(library()).subscription(
+				|(node, library),
+				 StreamedPathsArgs {
+				     order,
+				     filters,
+				     group_directories,
+				 }| async move {
+					let params = file_path_params(&library.db, filters).await?;
+
+					Ok(unsafe_streamed_query(
+						stream_paths(node, library, params, order, group_directories, 0).map(
+							|ExplorerBatch {
+							     items,
+							     errors,
+							     nodes,
+							     ..
+							 }| StreamedPathsBatch {
+								items,
+								errors,
+								nodes,
+							},
+						),
+					))
+				},
+			)
+		})
+		.procedure("names", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct NameSearchArgs {
+				query: String,
+				#[specta(optional)]
+				take: Option<u8>,
+			}
 
-					// WARN: this order_by for grouping directories MUST always come before the other order_by
-					if group_directories {
-						query = query
-							.order_by(prisma::file_path::is_dir::order(prisma::SortOrder::Desc));
-					}
+			// The file paths whose name contains the query, from the name index of the library,
+			// for the quick open palette
+			R.with2(library()).query(
+				|(node, library), NameSearchArgs { query, take }| async move {
+					let take = Pagination::new(take.map(u16::from), 0, u16::from(MAX_TAKE)).take();
 
-					// WARN: this order_by for sorting data MUST always come after the other order_by
-					if let Some(order_and_pagination) = order_and_pagination {
-						order_and_pagination.apply(&mut query, group_directories)
-					}
+					let ids = library
+						.name_index
+						.search(&library, &query, usize::from(take))
+						.await?;
 
-					let file_paths = query
+					let mut file_paths = library
+						.db
+						.file_path()
+						.find_many(vec![prisma::file_path::id::in_vec(ids.clone())])
 						.include(file_path_with_object::include())
 						.exec()
 						.await?;
 
-					let mut items = Vec::with_capacity(file_paths.len());
-
-					for file_path in file_paths {
-						let thumbnail_exists_locally = if let Some(cas_id) = &file_path.cas_id {
-							library
-								.thumbnail_exists(&node, cas_id)
-								.await
-								.map_err(LocationError::from)?
-						} else {
-							false
-						};
-
-						items.push(ExplorerItem::Path {
-							thumbnail: file_path
-								.cas_id
-								.as_ref()
-								.filter(|_| thumbnail_exists_locally)
-								.map(|i| get_indexed_thumb_key(i, library.id)),
-							item: file_path,
-						})
-					}
+					// In the order of the index, the best matches first
+					file_paths
+						.sort_by_key(|file_path| ids.iter().position(|id| *id == file_path.id));
 
+					let items = into_explorer_items(&node, &library, file_paths, true).await?;
 					let (nodes, items) = items.normalise(|item| item.id());
 
-					Ok(SearchData {
-						items,
+					Ok(SearchData::<_, ()> {
 						cursor: None,
+						items,
 						nodes,
+						count: None,
 					})
 				},
 			)
@@ -377,102 +971,27 @@ pub fn mount() -> AlphaRouter<Ctx> {
 
 			R.with2(library())
 				.query(|(_, library), Args { filters }| async move {
-					let Library { db, .. } = library.as_ref();
-
-					Ok(db
-						.file_path()
-						.count({
-							let mut params = Vec::new();
-
-							for filter in filters {
-								params.extend(filter.into_file_path_params(db).await?);
-							}
-
-							params
-						})
-						.exec()
-						.await? as u32)
+					cached_count(&library, CountTarget::Paths, filters).await
 				})
 		})
 		.procedure("objects", {
-			#[derive(Deserialize, Type, Debug)]
-			#[serde(rename_all = "camelCase")]
-			struct ObjectSearchArgs {
-				take: u8,
-				#[specta(optional)]
-				order_and_pagination: Option<object::OrderAndPagination>,
-				#[serde(default)]
-				filters: Vec<SearchFilterArgs>,
-			}
-
-			R.with2(library()).query(
-				|(node, library),
-				 ObjectSearchArgs {
-				     take,
-				     order_and_pagination,
-				     filters,
-				 }| async move {
-					let Library { db, .. } = library.as_ref();
-
-					let take = take.max(MAX_TAKE);
-
-					let mut query = db
-						.object()
-						.find_many({
-							let mut params = Vec::new();
-
-							for filter in filters {
-								params.extend(filter.into_object_params(db).await?);
-							}
-
-							params
-						})
-						.take(take as i64);
+			R.with2(library())
+				.query(|(node, library), args: ObjectSearchArgs| async move {
+					let max_take = max_objects_take(&node).await;
+					let page = args.page_key(library.id, max_take);
 
-					if let Some(order_and_pagination) = order_and_pagination {
-						order_and_pagination.apply(&mut query);
+					if let Some(data) = prefetched_page(page.as_ref(), args.with_count) {
+						return Ok(data);
 					}
 
-					let (objects, cursor) = {
-						let mut objects = query
-							.include(object_with_file_paths::include())
-							.exec()
-							.await?;
-
-						let cursor = (objects.len() as u8 > take)
-							.then(|| objects.pop())
-							.flatten()
-							.map(|r| r.pub_id);
-
-						(objects, cursor)
-					};
-
-					let mut items = Vec::with_capacity(objects.len());
-
-					for object in objects {
-						let cas_id = object
-							.file_paths
-							.iter()
-							.map(|fp| fp.cas_id.as_ref())
-							.find_map(|c| c);
-
-						let thumbnail_exists_locally = if let Some(cas_id) = cas_id {
-							library.thumbnail_exists(&node, cas_id).await.map_err(|e| {
-								rspc::Error::with_cause(
-									ErrorCode::InternalServerError,
-									"Failed to check that thumbnail exists".to_string(),
-									e,
-								)
-							})?
-						} else {
-							false
-						};
+					let take = args.pagination(max_take).take();
+					let next = args.prefetch.then(|| args.next_page(max_take)).flatten();
+
+					let (items, cursor, count) = find_objects(&node, &library, args).await?;
 
-						items.push(ExplorerItem::Object {
-							thumbnail: cas_id
-								.filter(|_| thumbnail_exists_locally)
-								.map(|cas_id| get_indexed_thumb_key(cas_id, library.id)),
-							item: object,
+					if let (Some(page), Some(next), Some(_)) = (page, next, &cursor) {
+						prefetch_next_page(page, take, async move {
+							find_objects(&node, &library, next).await
 						});
 					}
 
@@ -482,9 +1001,9 @@ pub fn mount() -> AlphaRouter<Ctx> {
 						nodes,
 						items,
 						cursor,
+						count,
 					})
-				},
-			)
+				})
 		})
 		.procedure("objectsCount", {
 			#[derive(Deserialize, Type, Debug)]
@@ -497,22 +1016,816 @@ pub fn mount() -> AlphaRouter<Ctx> {
 
 			R.with2(library())
 				.query(|(_, library), Args { filters }| async move {
-					let Library { db, .. } = library.as_ref();
+					cached_count(&library, CountTarget::Objects, filters).await
+				})
+		})
+		.procedure("suggest", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SuggestArgs {
+				query: String,
+				#[specta(optional)]
+				take: Option<u8>,
+			}
+
+			// Completions of the text typed in the search bar
+			R.with2(library())
+				.query(|(_, library), SuggestArgs { query, take }| async move {
+					history::suggestions(&library.db, &query, take).await
+				})
+		})
+		.merge("saved.", saved::mount())
+		.merge("history.", history::mount())
+		.merge("explorer.", explorer::mount())
+}
 
-					Ok(db
-						.object()
-						.count({
-							let mut params = Vec::new();
+/// Lists the entries of `path` outside of any location, like [`ephemeral_items`], normalised for
+/// the clients.
+async fn ephemeral_paths(
+	node: Arc<Node>,
+	library: Arc<Library>,
+	from: PathFrom,
+	credentials: Option<RemoteCredentials>,
+	path: String,
+	with_hidden_files: bool,
+	start_after: Option<String>,
+) -> Result<impl Stream<Item = EphemeralPathsResultItem> + Send, rspc::Error> {
+	Ok(ephemeral_items(
+		node,
+		library,
+		from,
+		credentials,
+		path,
+		with_hidden_files,
+		true,
+		start_after,
+	)
+	.await?
+	.map(|(entries, errors, next_page)| {
+		let (nodes, entries) = entries.normalise(|item| item.id());
+
+		EphemeralPathsResultItem {
+			entries,
+			errors,
+			nodes: nodes
+				.into_iter()
+				.map(|node| node.with_ttl(EPHEMERAL_NODE_TTL))
+				.collect(),
+			next_page,
+		}
+	}))
+}
 
-							for filter in filters {
-								params.extend(filter.into_object_params(db).await?);
-							}
+/// Lists the entries of `path` outside of any location, in batches as they are walked along with
+/// the errors of the entries which couldn't be read, generating the thumbnails of the files of each
+/// batch along with it.
+///
+/// The listing is read from the cache of the folder unless `use_cache` is `false`, which is needed
+/// when the contents of its files changed, as that doesn't change the folder.
+///
+/// Failing to connect to a remote server is reported in the errors of the first batch, like the
+/// entries which couldn't be read, so the clients show it in place of the listing.
+///
+/// S3 buckets are listed in pages of [`S3_PAGE_ENTRIES`] starting after `start_after`, the last
+/// batch of a page having the entry the next one starts after.
+#[allow(clippy::too_many_arguments)]
+async fn ephemeral_items(
+	node: Arc<Node>,
+	library: Arc<Library>,
+	from: PathFrom,
+	credentials: Option<RemoteCredentials>,
+	mut path: String,
+	with_hidden_files: bool,
+	use_cache: bool,
+	start_after: Option<String>,
+) -> Result<impl Stream<Item = (Vec<ExplorerItem>, Vec<String>, Option<String>)> + Send, rspc::Error>
+{
+	let service = from.operator(&library, credentials).await?;
+
+	let rules = ephemeral_rules(with_hidden_files, from == PathFrom::Path);
+
+	// OpenDAL is specific about paths (and the rest of Spacedrive is not)
+	if !path.ends_with('/') {
+		path.push('/');
+	}
 
-							params
-						})
-						.exec()
-						.await? as u32)
+	// Only local folders tell when they change, so they're the only listings cached
+	let listing = match &from {
+		PathFrom::Path => folder_modified(&path)
+			.await
+			.map(|modified| (ListingKey::new("fs", &path, with_hidden_files), modified)),
+		PathFrom::Ftp | PathFrom::S3 { .. } | PathFrom::Sftp { .. } | PathFrom::Webdav { .. } => {
+			None
+		}
+	};
+
+	let cached = listing
+		.as_ref()
+		.filter(|_| use_cache)
+		.and_then(|(key, modified)| cached_listing(key, *modified));
+
+	// Remote files are downloaded to generate their thumbnails while they're listed
+	let remote_thumbnails = (from != PathFrom::Path).then(|| {
+		(
+			service.clone(),
+			remote_thumbnailer(node.clone(), service.clone()),
+		)
+	});
+
+	// Buckets can hold millions of objects under a prefix, which would take ages to list at once
+	let page = matches!(from, PathFrom::S3 { .. }).then(|| ListingPage {
+		start_after,
+		max_entries: S3_PAGE_ENTRIES,
+	});
+
+	let (mut stream, mut walked, next_page) = if let Some(batches) = cached {
+		(futures::stream::iter(batches).boxed(), None, None)
+	} else {
+		match sd_indexer::ephemeral_page(service, rules, &path, page).await {
+			Ok((stream, next_page)) => {
+				let stream = BatchedStream::new(stream).map(|batch| {
+					batch
+						.into_iter()
+						.map(|entry| entry.map_err(|e| e.to_string()))
+						.collect::<ListingBatch>()
+				});
+
+				(
+					stream.boxed(),
+					listing.map(|(key, modified)| (key, modified, Vec::new())),
+					Some(next_page),
+				)
+			}
+			Err(e) if from != PathFrom::Path => {
+				let batch: ListingBatch =
+					vec![Err(format!("Failed to connect to the server: {e}"))];
+
+				(futures::stream::iter([batch]).boxed(), None, None)
+			}
+			Err(source) => return Err(ApiError::EphemeralSearch { path, source }.into()),
+		}
+	};
+
+	Ok(stream! {
+		let mut submitted_thumbnails = false;
+
+		while let Some(result) = stream.next().await {
+			if let Some((_, _, batches)) = &mut walked {
+				batches.push(result.clone());
+			}
+
+			let (entries, errors) = explorer_items(
+				&node,
+				&library,
+				&from,
+				remote_thumbnails.as_ref(),
+				result,
+				&mut submitted_thumbnails,
+			)
+			.await;
+
+			yield (entries, errors, None);
+		}
+
+		if let Some(next_page) = next_page {
+			if let Ok(Some(next_page)) = next_page.await {
+				yield (vec![], vec![], Some(next_page));
+			}
+		}
+
+		// Only cached once walked in full, the client may stop listening halfway
+		if let Some((key, modified, batches)) = walked {
+			listing_cache::insert(key, modified, batches);
+		}
+	})
+}
+
+/// Turns a batch of listed entries into the items sent to the clients, linking to the locations
+/// the folders are, and submits the thumbnails of its files to generate.
+///
+/// `submitted_thumbnails` is set once a batch of thumbnails of the listing was submitted, so the
+/// next ones continue it.
+async fn explorer_items(
+	node: &Node,
+	library: &Library,
+	from: &PathFrom,
+	remote_thumbnails: Option<&(Operator, mpsc::UnboundedSender<RemoteFile>)>,
+	result: ListingBatch,
+	submitted_thumbnails: &mut bool,
+) -> (Vec<ExplorerItem>, Vec<String>) {
+	let mut to_generate = vec![];
+
+	// We optimize for the case of no errors because it should be way more common.
+	let mut entries = Vec::with_capacity(result.len());
+	let mut errors = Vec::with_capacity(0);
+
+	// For this batch we check if any directories are actually locations, so the UI can link directly to them
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::path::in_vec(
+			result
+				.iter()
+				.filter_map(|e| match e {
+					Ok(e) if ObjectKind::from_i32(e.kind) == ObjectKind::Folder => {
+						Some(e.path.clone())
+					}
+					_ => None,
 				})
+				.collect::<Vec<_>>(),
+		)])
+		.exec()
+		.await
+		.and_then(|l| {
+			Ok(l.into_iter()
+				.filter_map(|item| item.path.clone().map(|l| (l, item)))
+				.collect::<HashMap<_, _>>())
 		})
-		.merge("saved.", saved::mount())
+		.map_err(|err| error!("Looking up locations failed: {err:?}"))
+		.unwrap_or_default();
+
+	for item in result {
+		match item {
+			Ok(item) => {
+				// Going by the extension rather than the kind, as only some image, video and
+				// document formats get thumbnails
+				let should_generate_thumbnail =
+					can_generate_thumbnail_for_extension(&item.extension);
+
+				let thumbnail = if should_generate_thumbnail {
+					if *from == PathFrom::Path {
+						let cas_id = match tokio::fs::metadata(&item.path).await {
+							Ok(metadata) => Ok(ephemeral_cas_id(&item.path, &metadata)),
+							Err(e) => Err(e),
+						};
+						if let Ok(cas_id) = cas_id.map_err(|err| {
+							error!("Error generating cas id for '{:?}': {err:?}", item.path)
+						}) {
+							if ObjectKind::from_i32(item.kind) == ObjectKind::Document {
+								to_generate.push(GenerateThumbnailArgs::new(
+									item.extension.clone(),
+									cas_id.clone(),
+									PathBuf::from(&item.path),
+								));
+							} else {
+								to_generate.push(GenerateThumbnailArgs::new(
+									item.extension.clone(),
+									cas_id.clone(),
+									PathBuf::from(&item.path),
+								));
+							}
+
+							Some(get_ephemeral_thumb_key(&cas_id, ThumbnailTier::Grid))
+						} else {
+							None
+						}
+					} else if let Some((operator, thumbnails_tx)) = remote_thumbnails {
+						let cas_id = remote_cas_id(operator, &item);
+
+						thumbnails_tx
+							.send(RemoteFile {
+								path: item.path.clone(),
+								extension: item.extension.clone(),
+								cas_id: cas_id.clone(),
+							})
+							.ok();
+
+						Some(get_ephemeral_thumb_key(&cas_id, ThumbnailTier::Grid))
+					} else {
+						None
+					}
+				} else {
+					None
+				};
+
+				entries.push(if let Some(item) = locations.get(&item.path) {
+					ExplorerItem::Location { item: item.clone() }
+				} else {
+					ExplorerItem::NonIndexedPath { thumbnail, item }
+				});
+			}
+			Err(e) => errors.push(e),
+		}
+	}
+
+	// Submitted with their batch instead of once the walk is done, so the thumbnails of big
+	// directories show up progressively, each with a `NewThumbnail` event
+	if !to_generate.is_empty() {
+		let batch = BatchToProcess::new(to_generate, false, false);
+
+		node.thumbnailer
+			.new_ephemeral_thumbnails_batch(if *submitted_thumbnails {
+				batch.continuing()
+			} else {
+				batch
+			})
+			.await;
+
+		*submitted_thumbnails = true;
+	}
+
+	(entries, errors)
+}
+
+async fn find_paths(
+	node: &Node,
+	library: &Library,
+	args: FilePathSearchArgs,
+) -> Result<SearchResults<file_path::OrderAndPagination>, rspc::Error> {
+	let Library { db, .. } = library;
+
+	let pagination = args.pagination();
+	let FilePathSearchArgs {
+		order_and_pagination,
+		filters,
+		group_directories,
+		with_count,
+		skip_thumbnails,
+		with_media_data,
+		..
+	} = args;
+
+	let count_filters = with_count.then(|| filters.clone());
+	let name_query = relevance::name_query(&filters);
+
+	let params = file_path_params(db, filters).await?;
+
+	let count_params = with_count.then(|| params.clone());
+
+	let (file_paths, count) = match order_and_pagination.clone() {
+		Some(file_path::OrderAndPagination::Relevance { .. }) => {
+			let file_paths = relevance::ranked_paths(
+				db,
+				params,
+				name_query.as_deref(),
+				pagination.skip(),
+				// One more than requested, to know if there is a next page
+				usize::from(pagination.take()) + 1,
+				group_directories,
+			)
+			.await?;
+
+			let count = match count_params {
+				Some(count_params) => Some(db.file_path().count(count_params).exec().await? as u32),
+				None => None,
+			};
+
+			(file_paths, count)
+		}
+		order_and_pagination => {
+			// One more than requested, to know if there is a next page
+			let query = paths_query(db, params, order_and_pagination, group_directories)
+				.take(pagination.fetch());
+
+			match count_params {
+				// Counted in the same round trip as the page
+				Some(count_params) => {
+					let (file_paths, count) = db
+						._batch((query, db.file_path().count(count_params)))
+						.await?;
+
+					(file_paths, Some(count as u32))
+				}
+				None => (query.exec().await?, None),
+			}
+		}
+	};
+
+	if let (Some(filters), Some(count)) = (count_filters, count) {
+		insert_count(library.id, CountTarget::Paths, &filters, count);
+	}
+
+	let (file_paths, cursor) = pagination.paginate(file_paths, |last| {
+		file_path::OrderAndPagination::after(
+			order_and_pagination.as_ref(),
+			usize::from(pagination.take()),
+			last,
+		)
+	});
+
+	let mut items = into_explorer_items(node, library, file_paths, !skip_thumbnails).await?;
+
+	if with_media_data {
+		read_media_data(db, &mut items).await?;
+	}
+
+	Ok((items, cursor.flatten(), count))
+}
+
+async fn find_objects(
+	node: &Node,
+	library: &Library,
+	args: ObjectSearchArgs,
+) -> Result<SearchResults<object::OrderAndPagination>, rspc::Error> {
+	let Library { db, .. } = library;
+
+	let pagination = args.pagination(max_objects_take(node).await);
+	let ObjectSearchArgs {
+		order_and_pagination,
+		filters,
+		with_count,
+		skip_thumbnails,
+		with_media_data,
+		..
+	} = args;
+
+	let count_filters = with_count.then(|| filters.clone());
+
+	let params = object_params(db, filters).await?;
+
+	let mut count_params = with_count.then(|| params.clone());
+
+	// One more than requested, to know if there is a next page
+	let fetch = usize::from(pagination.take()) + 1;
+
+	let mut objects = Vec::with_capacity(fetch);
+	let mut count = None;
+	let mut chunk_pagination = order_and_pagination.clone();
+
+	loop {
+		let chunk_take = (fetch - objects.len()).min(OBJECTS_CHUNK);
+
+		let mut query = db
+			.object()
+			.find_many(params.clone())
+			.take(chunk_take as i64);
+
+		if let Some(chunk_pagination) = chunk_pagination.clone() {
+			chunk_pagination.apply(&mut query);
+		}
+
+		let query = query.include(object_with_file_paths::include());
+
+		let chunk = match count_params.take() {
+			// Counted in the same round trip as the first chunk
+			Some(count_params) => {
+				let (chunk, chunk_count) =
+					db._batch((query, db.object().count(count_params))).await?;
+				count = Some(chunk_count as u32);
+
+				chunk
+			}
+			None => query.exec().await?,
+		};
+
+		let next = chunk.last().and_then(|last| {
+			object::OrderAndPagination::after(chunk_pagination.as_ref(), chunk.len(), last)
+		});
+		let last_chunk = chunk.len() < chunk_take;
+		objects.extend(chunk);
+
+		match next {
+			Some(next) if !last_chunk && objects.len() < fetch => chunk_pagination = Some(next),
+			_ => break,
+		}
+	}
+
+	if let (Some(filters), Some(count)) = (count_filters, count) {
+		insert_count(library.id, CountTarget::Objects, &filters, count);
+	}
+
+	let (objects, cursor) = pagination.paginate(objects, |last| {
+		object::OrderAndPagination::after(
+			order_and_pagination.as_ref(),
+			usize::from(pagination.take()),
+			last,
+		)
+	});
+
+	let mut items = into_object_explorer_items(node, library, objects, !skip_thumbnails).await?;
+
+	if with_media_data {
+		read_media_data(db, &mut items).await?;
+	}
+
+	Ok((items, cursor.flatten(), count))
+}
+
+/// The most objects a page of `search.objects` holds, from the search preferences of the node.
+async fn max_objects_take(node: &Node) -> u16 {
+	node.config
+		.get()
+		.await
+		.preferences
+		.search
+		.max_objects_take()
+}
+
+async fn file_path_params(
+	db: &PrismaClient,
+	filters: Vec<SearchFilterArgs>,
+) -> Result<Vec<prisma::file_path::WhereParam>, rspc::Error> {
+	let mut params = Vec::new();
+
+	for filter in filters {
+		params.extend(filter.into_file_path_params(db).await?);
+	}
+
+	Ok(params)
+}
+
+async fn object_params(
+	db: &PrismaClient,
+	filters: Vec<SearchFilterArgs>,
+) -> Result<Vec<prisma::object::WhereParam>, rspc::Error> {
+	let mut params = Vec::new();
+
+	for filter in filters {
+		params.extend(filter.into_object_params(db).await?);
+	}
+
+	Ok(params)
+}
+
+fn paths_query(
+	db: &PrismaClient,
+	params: Vec<prisma::file_path::WhereParam>,
+	order_and_pagination: Option<file_path::OrderAndPagination>,
+	group_directories: bool,
+) -> prisma::file_path::FindManyQuery<'_> {
+	let mut query = db.file_path().find_many(params);
+
+	// WARN: this order_by for grouping directories MUST always come before the other order_by
+	if group_directories {
+		query = query.order_by(prisma::file_path::is_dir::order(prisma::SortOrder::Desc));
+	}
+
+	// WARN: this order_by for sorting data MUST always come after the other order_by
+	if let Some(order_and_pagination) = order_and_pagination {
+		order_and_pagination.apply(&mut query, group_directories)
+	}
+
+	query
+}
+
+/// The key of the grid thumbnail of `cas_id` if it was generated. It's recorded as accessed here,
+/// as the clients cache the thumbnails they load and don't request them again.
+async fn indexed_thumb_key(
+	node: &Node,
+	library: &Library,
+	cas_id: &str,
+) -> Result<Option<Vec<String>>, FileIOError> {
+	if !library.thumbnail_exists(node, cas_id).await? {
+		return Ok(None);
+	}
+
+	node.thumbnailer.record_access(get_indexed_thumbnail_path(
+		node,
+		cas_id,
+		library.id,
+		ThumbnailTier::Grid,
+	));
+
+	Ok(Some(get_indexed_thumb_key(
+		cas_id,
+		library.id,
+		ThumbnailTier::Grid,
+	)))
+}
+
+async fn into_explorer_items(
+	node: &Node,
+	library: &Library,
+	file_paths: Vec<file_path_with_object::Data>,
+	with_thumbnails: bool,
+) -> Result<Vec<ExplorerItem>, rspc::Error> {
+	let mut items = Vec::with_capacity(file_paths.len());
+
+	for file_path in file_paths {
+		let thumbnail = match &file_path.cas_id {
+			Some(cas_id) if with_thumbnails => indexed_thumb_key(node, library, cas_id)
+				.await
+				.map_err(LocationError::from)?,
+			_ => None,
+		};
+
+		items.push(ExplorerItem::Path {
+			thumbnail,
+			item: file_path,
+			media_data: None,
+		})
+	}
+
+	Ok(items)
+}
+
+async fn into_object_explorer_items(
+	node: &Node,
+	library: &Library,
+	objects: Vec<object_with_file_paths::Data>,
+	with_thumbnails: bool,
+) -> Result<Vec<ExplorerItem>, rspc::Error> {
+	let mut items = Vec::with_capacity(objects.len());
+
+	for object in objects {
+		let cas_id = object
+			.file_paths
+			.iter()
+			.map(|fp| fp.cas_id.as_ref())
+			.find_map(|c| c)
+			.filter(|_| with_thumbnails);
+
+		let thumbnail = if let Some(cas_id) = cas_id {
+			indexed_thumb_key(node, library, cas_id)
+				.await
+				.map_err(|source| ApiError::ThumbnailLookup {
+					cas_id: cas_id.clone(),
+					source,
+				})?
+		} else {
+			None
+		};
+
+		items.push(ExplorerItem::Object {
+			thumbnail,
+			item: object,
+			media_data: None,
+		});
+	}
+
+	Ok(items)
+}
+
+/// Reads the media data of the objects of `items` with a single query for all of them, instead of
+/// one per item once they're shown.
+async fn read_media_data(db: &PrismaClient, items: &mut [ExplorerItem]) -> Result<(), rspc::Error> {
+	/// The id and kind of the object of the item, and where its media data goes
+	fn object_of(
+		item: &mut ExplorerItem,
+	) -> Option<(i32, Option<i32>, &mut Option<MediaMetadata>)> {
+		match item {
+			ExplorerItem::Path {
+				item, media_data, ..
+			} => item
+				.object
+				.as_ref()
+				.map(|object| (object.id, object.kind, media_data)),
+			ExplorerItem::Object {
+				item, media_data, ..
+			} => Some((item.id, item.kind, media_data)),
+			_ => None,
+		}
+	}
+
+	let object_ids = items
+		.iter_mut()
+		.filter_map(object_of)
+		.map(|(id, _, _)| id)
+		.collect::<Vec<_>>();
+
+	if object_ids.is_empty() {
+		return Ok(());
+	}
+
+	let media_data = db
+		.media_data()
+		.find_many(vec![prisma::media_data::object_id::in_vec(object_ids)])
+		.exec()
+		.await?
+		.into_iter()
+		.map(|data| (data.object_id, data))
+		.collect::<HashMap<_, _>>();
+
+	// Many file paths can share an object, so its media data is kept for all of them
+	for (id, kind, item_media_data) in items.iter_mut().filter_map(object_of) {
+		if let Some(data) = media_data.get(&id) {
+			*item_media_data = media_metadata_from_prisma_data(kind, data.clone());
+		}
+	}
+
+	Ok(())
+}
+
+/// A change to the entries of a watched directory.
+enum DirectoryChange {
+	/// These entries were added, removed or modified
+	Entries(HashSet<PathBuf>),
+	/// Changes were missed, so any entry may have changed
+	Rescan,
+}
+
+impl DirectoryChange {
+	fn merge(&mut self, other: Self) {
+		match (self, other) {
+			(Self::Entries(paths), Self::Entries(other)) => paths.extend(other),
+			(this, _) => *this = Self::Rescan,
+		}
+	}
+
+	/// Only keeps the changes to the entries of `dir`, as the ones of the directory itself don't
+	/// change them. Entries reported under another path, like through a symlink, can't be told
+	/// apart, so `dir` is listed again.
+	fn of_entries(self, dir: &Path) -> Self {
+		match self {
+			Self::Entries(paths)
+				if paths
+					.iter()
+					.all(|path| path == dir || path.parent() == Some(dir)) =>
+			{
+				Self::Entries(paths.into_iter().filter(|path| path != dir).collect())
+			}
+			Self::Entries(_) | Self::Rescan => Self::Rescan,
+		}
+	}
+}
+
+/// The path an entry of a non indexed directory was listed at.
+fn listed_path(item: &ExplorerItem) -> Option<PathBuf> {
+	match item {
+		ExplorerItem::NonIndexedPath { item, .. } => Some(PathBuf::from(&item.path)),
+		ExplorerItem::Location { item } => item.path.as_ref().map(PathBuf::from),
+		_ => None,
+	}
+}
+
+/// Watches the entries of the directory at `path`, but not the ones of its subdirectories, sending
+/// to the receiver the ones which are added, removed or modified.
+fn watch_directory(
+	path: &str,
+) -> Result<(RecommendedWatcher, mpsc::UnboundedReceiver<DirectoryChange>), rspc::Error> {
+	let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+
+	let watch_error = |source| ApiError::DirectoryWatch {
+		path: path.to_string(),
+		source,
+	};
+
+	let mut watcher = RecommendedWatcher::new(
+		move |result: notify::Result<Event>| {
+			let change = match result {
+				Ok(event) if event.kind.is_access() => return,
+				Ok(event) if event.need_rescan() => DirectoryChange::Rescan,
+				Ok(event) => DirectoryChange::Entries(event.paths.into_iter().collect()),
+				Err(e) => {
+					warn!("Failed to watch a non indexed directory: {e:#?}");
+					DirectoryChange::Rescan
+				}
+			};
+
+			// The receiver is only dropped once the client stops listening
+			changes_tx.send(change).ok();
+		},
+		Config::default(),
+	)
+	.map_err(watch_error)?;
+
+	watcher
+		.watch(Path::new(path), RecursiveMode::NonRecursive)
+		.map_err(watch_error)?;
+
+	Ok((watcher, changes_rx))
+}
+
+/// Waits for a watched directory to change, returning `None` once it isn't watched anymore.
+///
+/// The changes made within [`LIVE_SEARCH_DEBOUNCE`] of each other are waited for together, like
+/// the ones of a copy.
+async fn wait_for_directory_change(
+	changes_rx: &mut mpsc::UnboundedReceiver<DirectoryChange>,
+) -> Option<DirectoryChange> {
+	let mut change = changes_rx.recv().await?;
+
+	loop {
+		match time::timeout(LIVE_SEARCH_DEBOUNCE, changes_rx.recv()).await {
+			Err(_) => return Some(change),
+			Ok(Some(next)) => change.merge(next),
+			Ok(None) => return None,
+		}
+	}
+}
+
+/// Waits for one of the queries with these `keys` to be invalidated, returning `false` once the
+/// node is shutting down.
+///
+/// The changes made within [`LIVE_SEARCH_DEBOUNCE`] of each other are waited for together, like
+/// the ones of a job.
+async fn wait_for_invalidation(
+	event_bus_rx: &mut broadcast::Receiver<CoreEvent>,
+	keys: &[&str],
+) -> bool {
+	let is_change = |event: &CoreEvent| match event {
+		CoreEvent::InvalidateOperation(InvalidateOperationEvent::All) => true,
+		CoreEvent::InvalidateOperation(InvalidateOperationEvent::Single(event)) => {
+			keys.contains(&event.key)
+		}
+		_ => false,
+	};
+
+	loop {
+		match event_bus_rx.recv().await {
+			Ok(event) if is_change(&event) => break,
+			Ok(_) => {}
+			// Missed events may have been changes
+			Err(broadcast::error::RecvError::Lagged(_)) => break,
+			Err(broadcast::error::RecvError::Closed) => return false,
+		}
+	}
+
+	loop {
+		match time::timeout(LIVE_SEARCH_DEBOUNCE, event_bus_rx.recv()).await {
+			Err(_) => return true,
+			Ok(Ok(_) | Err(broadcast::error::RecvError::Lagged(_))) => {}
+			Ok(Err(broadcast::error::RecvError::Closed)) => return false,
+		}
+	}
 }
@@ -1,5 +1,7 @@
 // use crate::library::Category;
+use crate::object::tag::with_descendant_ids;
 
+use sd_core_prisma_helpers::object_with_file_paths;
 use sd_prisma::prisma::{self, label_on_object, object, tag_on_object};
 
 use chrono::{DateTime, FixedOffset};
@@ -12,37 +14,34 @@ use super::{
 	utils::{self, *},
 };
 
-#[derive(Deserialize, Type, Debug)]
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ObjectCursor {
 	None,
-	DateAccessed(CursorOrderItem<DateTime<FixedOffset>>),
-	Kind(CursorOrderItem<i32>),
+	DateAccessed(CursorOrderItem<Option<DateTime<FixedOffset>>>),
+	Kind(CursorOrderItem<Option<i32>>),
 }
 
 impl ObjectCursor {
 	fn apply(self, query: &mut object::FindManyQuery, id: i32) {
 		macro_rules! arm {
 			($field:ident, $item:ident) => {{
-				let item = $item;
-
-				let data = item.data.clone();
+				let order = $item.order.into();
 
-				query.add_where(or![
-					match item.order {
-						SortOrder::Asc => object::$field::gt(data),
-						SortOrder::Desc => object::$field::lt(data),
-					},
-					prisma_client_rust::and![
-						object::$field::equals(Some(item.data)),
-						match item.order {
-							SortOrder::Asc => object::id::gt(id),
-							SortOrder::Desc => object::id::lt(id),
-						}
-					]
-				]);
+				query.add_where(
+					NullableField {
+						past: |order, data| match order {
+							SortOrder::Asc => object::$field::gt(data),
+							SortOrder::Desc => object::$field::lt(data),
+						},
+						equals: |data| object::$field::equals(Some(data)),
+						is_null: || object::$field::equals(None),
+						id_after: object::id::gt,
+					}
+					.after($item, id),
+				);
 
-				query.add_order_by(object::$field::order(item.order.into()));
+				query.add_order_by(object::$field::order(order));
 			}};
 		}
 
@@ -113,15 +112,32 @@ pub enum ObjectFilterArgs {
 	Hidden(ObjectHiddenFilter),
 	Kind(InOrNotIn<i32>),
 	Tags(InOrNotIn<i32>),
+	/// Like `Tags`, also matching the tags nested under them
+	TagAnyDescendant(InOrNotIn<i32>),
 	Labels(InOrNotIn<i32>),
 	DateAccessed(Range<chrono::DateTime<FixedOffset>>),
+	PluginMetadata(PluginMetadataFilter),
+	MediaData(MediaDataFilterArgs),
+}
+
+/// Matches objects with a metadata extracted by a plugin, with any value if none is given.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginMetadataFilter {
+	pub plugin: String,
+	pub key: String,
+	#[serde(default)]
+	pub value: Option<TextMatch>,
 }
 
 impl ObjectFilterArgs {
-	pub fn into_params(self) -> Vec<object::WhereParam> {
+	pub async fn into_params(
+		self,
+		db: &prisma::PrismaClient,
+	) -> Result<Vec<object::WhereParam>, rspc::Error> {
 		use object::*;
 
-		match self {
+		Ok(match self {
 			Self::Favorite(v) => vec![favorite::equals(Some(v))],
 			Self::Hidden(v) => v.to_param().map(|v| vec![v]).unwrap_or_default(),
 			Self::Tags(v) => v
@@ -131,6 +147,19 @@ impl ObjectFilterArgs {
 				)
 				.map(|v| vec![v])
 				.unwrap_or_default(),
+			Self::TagAnyDescendant(v) => {
+				let v = match v {
+					InOrNotIn::In(ids) => InOrNotIn::In(with_descendant_ids(db, ids).await?),
+					InOrNotIn::NotIn(ids) => InOrNotIn::NotIn(with_descendant_ids(db, ids).await?),
+				};
+
+				v.into_param(
+					|v| tags::some(vec![tag_on_object::tag_id::in_vec(v)]),
+					|v| tags::none(vec![tag_on_object::tag_id::in_vec(v)]),
+				)
+				.map(|v| vec![v])
+				.unwrap_or_default()
+			}
 			Self::Labels(v) => v
 				.into_param(
 					|v| labels::some(vec![label_on_object::label_id::in_vec(v)]),
@@ -151,7 +180,27 @@ impl ObjectFilterArgs {
 					},
 				]
 			}
-		}
+			Self::PluginMetadata(PluginMetadataFilter { plugin, key, value }) => {
+				vec![plugin_metadata::some(
+					[
+						Some(prisma::plugin_metadata::plugin::equals(plugin)),
+						Some(prisma::plugin_metadata::key::equals(key)),
+						value.and_then(|v| {
+							v.into_param(
+								prisma::plugin_metadata::value::contains,
+								prisma::plugin_metadata::value::starts_with,
+								prisma::plugin_metadata::value::ends_with,
+								prisma::plugin_metadata::value::equals,
+							)
+						}),
+					]
+					.into_iter()
+					.flatten()
+					.collect(),
+				)]
+			}
+			Self::MediaData(v) => vec![media_data::is(v.into_params())],
+		})
 	}
 }
 
@@ -165,7 +214,7 @@ impl OrderAndPagination {
 				query.add_order_by(order.into_param());
 			}
 			Self::Offset { offset, order } => {
-				query.set_skip(offset as i64);
+				query.set_skip(i64::from(offset.max(0)));
 
 				if let Some(order) = order {
 					query.add_order_by(order.into_param())
@@ -174,8 +223,50 @@ impl OrderAndPagination {
 			Self::Cursor { id, cursor } => {
 				cursor.apply(query, id);
 
-				query.add_order_by(object::pub_id::order(prisma::SortOrder::Asc))
+				// Ties are always ordered by id
+				query.add_order_by(object::id::order(prisma::SortOrder::Asc))
 			}
 		}
 	}
+
+	/// Where the objects following the `fetched` ones read with `pagination`, the last of them
+	/// being `last`, start from.
+	pub(super) fn after(
+		pagination: Option<&Self>,
+		fetched: usize,
+		last: &object_with_file_paths::Data,
+	) -> Option<Self> {
+		let fetched = i32::try_from(fetched).ok()?;
+
+		Some(match pagination {
+			None => Self::Offset {
+				offset: fetched,
+				order: None,
+			},
+			Some(Self::OrderOnly(order)) => Self::Offset {
+				offset: fetched,
+				order: Some(order.clone()),
+			},
+			Some(Self::Offset { offset, order }) => Self::Offset {
+				offset: offset + fetched,
+				order: order.clone(),
+			},
+			Some(Self::Cursor { cursor, .. }) => Self::Cursor {
+				id: last.id,
+				cursor: match cursor {
+					ObjectCursor::None => ObjectCursor::None,
+					ObjectCursor::Kind(item) => ObjectCursor::Kind(CursorOrderItem {
+						order: item.order,
+						data: last.kind,
+					}),
+					ObjectCursor::DateAccessed(item) => {
+						ObjectCursor::DateAccessed(CursorOrderItem {
+							order: item.order,
+							data: last.date_accessed,
+						})
+					}
+				},
+			},
+		})
+	}
 }
@@ -0,0 +1,313 @@
+//! Patterns matched against the names of the file paths, with their extension, to filter the
+//! search by the ids of the matching ones. Both are case insensitive, like the rest of the search.
+//!
+//! Globs are turned into a `LIKE` pattern for SQLite, and only checked here when they use
+//! character classes or alternatives, which `LIKE` can't express. SQLite can't evaluate regexes,
+//! so the file paths are read in batches and matched here.
+
+use crate::api::error::ApiError;
+
+use sd_prisma::prisma::{file_path, PrismaClient, SortOrder};
+
+use globset::{GlobBuilder, GlobMatcher};
+use prisma_client_rust::{raw, PrismaValue};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+/// Longer patterns are refused, as they're matched against every path of the library
+const MAX_PATTERN_LEN: usize = 256;
+/// Bounds the memory used by a compiled regex, refusing the ones with huge repetitions
+const MAX_REGEX_SIZE: usize = 1024 * 1024;
+/// File paths read at once to be matched
+const MATCH_BATCH_SIZE: i64 = 10_000;
+/// Patterns matching more file paths are refused, as the ids are given to SQLite as variables,
+/// which it limits to 32766 per query
+const MAX_MATCHES: usize = 30_000;
+
+file_path::select!(file_path_name { id name extension });
+
+#[derive(Deserialize)]
+struct FilePathName {
+	id: file_path::id::Type,
+	name: Option<String>,
+	extension: Option<String>,
+}
+
+pub(super) enum NamePattern {
+	Regex(Regex),
+	Glob {
+		matcher: GlobMatcher,
+		/// Escaped with `\`
+		like: String,
+		/// Whether `like` matches the same names as the glob, instead of more of them
+		exact: bool,
+	},
+}
+
+impl NamePattern {
+	/// Matches anywhere in the name unless anchored, like `^IMG_\d{4}`.
+	pub fn regex(pattern: &str) -> Result<Self, ApiError> {
+		check_len(pattern)?;
+
+		RegexBuilder::new(pattern)
+			.case_insensitive(true)
+			.size_limit(MAX_REGEX_SIZE)
+			.dfa_size_limit(MAX_REGEX_SIZE)
+			.build()
+			.map(Self::Regex)
+			.map_err(|e| invalid(pattern, e))
+	}
+
+	/// Matches the whole name, like `*.torrent`.
+	pub fn glob(pattern: &str) -> Result<Self, ApiError> {
+		check_len(pattern)?;
+
+		let matcher = GlobBuilder::new(pattern)
+			.literal_separator(true)
+			.case_insensitive(true)
+			.build()
+			.map_err(|e| invalid(pattern, e))?
+			.compile_matcher();
+		let (like, exact) = glob_to_like(pattern);
+
+		Ok(Self::Glob {
+			matcher,
+			like,
+			exact,
+		})
+	}
+
+	fn is_match(&self, name: &str) -> bool {
+		match self {
+			Self::Regex(regex) => regex.is_match(name),
+			Self::Glob { matcher, .. } => matcher.is_match(name),
+		}
+	}
+
+	fn is_match_with_extension(&self, name: Option<&str>, extension: Option<&str>) -> bool {
+		let name = name.unwrap_or_default();
+
+		match extension {
+			Some(extension) if !extension.is_empty() => {
+				self.is_match(&format!("{name}.{extension}"))
+			}
+			_ => self.is_match(name),
+		}
+	}
+
+	/// The ids of the file paths whose name matches, along with their extension as the clients
+	/// show it.
+	pub async fn matching_ids(
+		&self,
+		db: &PrismaClient,
+	) -> Result<Vec<file_path::id::Type>, rspc::Error> {
+		let ids = match self {
+			Self::Glob { like, exact, .. } => {
+				let mut ids = vec![];
+				let mut last_id = 0;
+
+				loop {
+					let batch = db
+						._query_raw::<FilePathName>(raw!(
+							"SELECT id, name, extension FROM file_path
+							WHERE id > {} AND (CASE WHEN extension IS NULL OR extension = '' THEN name
+								ELSE name || '.' || extension END) LIKE {} ESCAPE '\\'
+							ORDER BY id LIMIT {}",
+							PrismaValue::Int(last_id as i64),
+							PrismaValue::String(like.clone()),
+							PrismaValue::Int(MATCH_BATCH_SIZE)
+						))
+						.exec()
+						.await?;
+
+					let Some(last) = batch.last() else {
+						break;
+					};
+					last_id = last.id;
+
+					let is_last = (batch.len() as i64) < MATCH_BATCH_SIZE;
+
+					ids.extend(batch.into_iter().filter_map(|file_path| {
+						(*exact
+							|| self.is_match_with_extension(
+								file_path.name.as_deref(),
+								file_path.extension.as_deref(),
+							))
+						.then_some(file_path.id)
+					}));
+
+					if is_last || ids.len() > MAX_MATCHES {
+						break;
+					}
+				}
+
+				ids
+			}
+			Self::Regex(_) => self.scan_matching_ids(db).await?,
+		};
+
+		if ids.len() > MAX_MATCHES {
+			return Err(ApiError::InvalidPattern {
+				pattern: match self {
+					Self::Regex(regex) => regex.as_str().to_string(),
+					Self::Glob { matcher, .. } => matcher.glob().glob().to_string(),
+				},
+				reason: format!("matches more than {MAX_MATCHES} files, it must be narrowed"),
+			}
+			.into());
+		}
+
+		Ok(ids)
+	}
+
+	async fn scan_matching_ids(
+		&self,
+		db: &PrismaClient,
+	) -> Result<Vec<file_path::id::Type>, rspc::Error> {
+		let mut ids = vec![];
+		let mut last_id = None;
+
+		loop {
+			let batch = db
+				.file_path()
+				.find_many(last_id.map(file_path::id::gt).into_iter().collect())
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.take(MATCH_BATCH_SIZE)
+				.select(file_path_name::select())
+				.exec()
+				.await?;
+
+			let Some(last) = batch.last() else {
+				break;
+			};
+			last_id = Some(last.id);
+
+			let is_last = (batch.len() as i64) < MATCH_BATCH_SIZE;
+
+			ids.extend(batch.into_iter().filter_map(|file_path| {
+				self.is_match_with_extension(
+					file_path.name.as_deref(),
+					file_path.extension.as_deref(),
+				)
+				.then_some(file_path.id)
+			}));
+
+			if is_last || ids.len() > MAX_MATCHES {
+				break;
+			}
+		}
+
+		Ok(ids)
+	}
+}
+
+/// The `LIKE` pattern matching the names matched by the glob, escaped with `\`, and whether it
+/// matches exactly them. Character classes and alternatives are replaced by wildcards, so the
+/// names have to be checked against the glob then.
+fn glob_to_like(glob: &str) -> (String, bool) {
+	fn push_literal(like: &mut String, c: char) {
+		if matches!(c, '%' | '_' | '\\') {
+			like.push('\\');
+		}
+		like.push(c);
+	}
+
+	let mut like = String::with_capacity(glob.len());
+	let mut exact = true;
+	let mut chars = glob.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'*' => like.push('%'),
+			'?' => like.push('_'),
+			'\\' => {
+				if let Some(c) = chars.next() {
+					push_literal(&mut like, c);
+				}
+			}
+			// A class matches a single character, a `]` right after its start being part of it
+			'[' => {
+				exact = false;
+				chars.next_if(|c| matches!(c, '!' | '^'));
+				chars.next_if_eq(&']');
+				for c in chars.by_ref() {
+					if c == ']' {
+						break;
+					}
+				}
+				like.push('_');
+			}
+			'{' => {
+				exact = false;
+				for c in chars.by_ref() {
+					if c == '}' {
+						break;
+					}
+				}
+				like.push('%');
+			}
+			c => push_literal(&mut like, c),
+		}
+	}
+
+	(like, exact)
+}
+
+fn check_len(pattern: &str) -> Result<(), ApiError> {
+	if pattern.len() > MAX_PATTERN_LEN {
+		return Err(ApiError::InvalidPattern {
+			pattern: pattern.to_string(),
+			reason: format!("longer than {MAX_PATTERN_LEN} characters"),
+		});
+	}
+
+	Ok(())
+}
+
+fn invalid(pattern: &str, e: impl ToString) -> ApiError {
+	ApiError::InvalidPattern {
+		pattern: pattern.to_string(),
+		reason: e.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_names_with_their_extension() {
+		let glob = NamePattern::glob("*.torrent").unwrap();
+		assert!(glob.is_match("ubuntu.torrent"));
+		assert!(!glob.is_match("ubuntu.torrent.part"));
+
+		let regex = NamePattern::regex(r"^IMG_\d{4}").unwrap();
+		assert!(regex.is_match("IMG_0042.jpg"));
+		assert!(!regex.is_match("DSC_0042.jpg"));
+	}
+
+	#[test]
+	fn case_insensitive() {
+		assert!(NamePattern::glob("*.JPG").unwrap().is_match("photo.jpg"));
+		assert!(NamePattern::regex("^img_")
+			.unwrap()
+			.is_match("IMG_0042.jpg"));
+	}
+
+	#[test]
+	fn globs_to_escaped_like_patterns() {
+		assert_eq!(glob_to_like("*.torrent"), ("%.torrent".to_string(), true));
+		assert_eq!(glob_to_like("IMG_????"), ("IMG\\_____".to_string(), true));
+		assert_eq!(glob_to_like("100%\\*"), ("100\\%*".to_string(), true));
+		assert_eq!(glob_to_like("[]a]b*"), ("_b%".to_string(), false));
+		assert_eq!(glob_to_like("*.{jpg,png}"), ("%.%".to_string(), false));
+	}
+
+	#[test]
+	fn refuses_invalid_and_complex_patterns() {
+		assert!(NamePattern::regex("(unclosed").is_err());
+		assert!(NamePattern::glob("[unclosed").is_err());
+		assert!(NamePattern::regex(&"a".repeat(MAX_PATTERN_LEN + 1)).is_err());
+		assert!(NamePattern::regex(r"(\w{1000}){1000}").is_err());
+	}
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::api::utils::MAX_TAKE;
+
+/// Most objects a node can be asked to return in a single page of `search.objects`
+pub const OBJECTS_TAKE_LIMIT: u16 = 10_000;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Type)]
+pub struct SearchPreferences {
+	/// Most objects returned in a single page of `search.objects`
+	max_objects_take: u16,
+}
+
+impl Default for SearchPreferences {
+	fn default() -> Self {
+		Self {
+			max_objects_take: u16::from(MAX_TAKE),
+		}
+	}
+}
+
+impl SearchPreferences {
+	pub fn max_objects_take(&self) -> u16 {
+		self.max_objects_take
+	}
+
+	pub fn set_max_objects_take(&mut self, max_objects_take: u16) -> &mut Self {
+		self.max_objects_take = max_objects_take.clamp(1, OBJECTS_TAKE_LIMIT);
+
+		self
+	}
+}
@@ -0,0 +1,187 @@
+//! Next pages of the explorer searches, read in the background while the current page is shown,
+//! so scrolling to them doesn't wait on the database.
+//!
+//! Only offset pages are prefetched, a prefetched page is served once and dropped after
+//! [`PREFETCH_TTL`], or once the first page of its search is read again.
+
+use crate::api::locations::ExplorerItem;
+
+use sd_cache::Normalise;
+
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::error;
+use uuid::Uuid;
+
+use super::{SearchData, SearchResults};
+
+const PREFETCH_TTL: Duration = Duration::from_secs(15);
+const CAPACITY: usize = 64;
+
+/// The items of the page and the pagination of the next one, kept as JSON as it's the one of
+/// either file paths or objects
+pub(super) type PrefetchedPage = (Vec<ExplorerItem>, Option<Value>);
+
+static PAGES: Lazy<Mutex<HashMap<PageKey, (PrefetchedPage, Instant)>>> = Lazy::new(Mutex::default);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct PageKey {
+	library_id: Uuid,
+	procedure: &'static str,
+	/// The serialized arguments of the search, apart from its pagination
+	search: String,
+	offset: i32,
+}
+
+impl PageKey {
+	pub(super) fn new(
+		library_id: Uuid,
+		procedure: &'static str,
+		search: &impl Serialize,
+		offset: i32,
+	) -> Self {
+		Self {
+			library_id,
+			procedure,
+			search: serde_json::to_string(search)
+				.expect("search arguments are always serializable to JSON"),
+			offset,
+		}
+	}
+
+	pub(super) fn is_first(&self) -> bool {
+		self.offset == 0
+	}
+
+	pub(super) fn next(&self, take: u16) -> Self {
+		Self {
+			offset: self.offset + i32::from(take),
+			..self.clone()
+		}
+	}
+
+	fn same_search(&self, other: &Self) -> bool {
+		self.library_id == other.library_id
+			&& self.procedure == other.procedure
+			&& self.search == other.search
+	}
+}
+
+/// Returns the page if it was prefetched, unless it has to be counted, which prefetched pages
+/// aren't.
+pub(super) fn prefetched_page<C: DeserializeOwned>(
+	page: Option<&PageKey>,
+	with_count: bool,
+) -> Option<SearchData<ExplorerItem, C>> {
+	let (items, cursor) = take(page.filter(|_| !with_count)?)?;
+	let cursor = cursor.map(serde_json::from_value).transpose().ok()?;
+	let (nodes, items) = items.normalise(|item| item.id());
+
+	Some(SearchData {
+		cursor,
+		items,
+		nodes,
+		count: None,
+	})
+}
+
+/// Reads the page after `page` with `read` in the background.
+pub(super) fn prefetch_next_page<C: Serialize>(
+	page: PageKey,
+	take: u16,
+	read: impl Future<Output = Result<SearchResults<C>, rspc::Error>> + Send + 'static,
+) {
+	// The results of the search may have changed since its next page was prefetched
+	if page.is_first() {
+		clear(&page);
+	}
+
+	let next = page.next(take);
+
+	tokio::spawn(async move {
+		match read.await {
+			Ok((items, cursor, _)) => insert(
+				next,
+				(
+					items,
+					cursor.map(|cursor| {
+						serde_json::to_value(cursor).expect("paginations are always serializable")
+					}),
+				),
+			),
+			Err(e) => error!("Failed to prefetch the next page of a search: {e:#?}"),
+		}
+	});
+}
+
+/// Returns the page if it was prefetched, it's only served once.
+fn take(key: &PageKey) -> Option<PrefetchedPage> {
+	PAGES
+		.lock()
+		.expect("prefetched pages lock poisoned")
+		.remove(key)
+		.filter(|(_, prefetched_at)| prefetched_at.elapsed() < PREFETCH_TTL)
+		.map(|(page, _)| page)
+}
+
+fn insert(key: PageKey, page: PrefetchedPage) {
+	let mut pages = PAGES.lock().expect("prefetched pages lock poisoned");
+
+	pages.retain(|_, (_, prefetched_at)| prefetched_at.elapsed() < PREFETCH_TTL);
+
+	if pages.len() >= CAPACITY {
+		if let Some(oldest) = pages
+			.iter()
+			.min_by_key(|(_, (_, prefetched_at))| *prefetched_at)
+			.map(|(key, _)| key.clone())
+		{
+			pages.remove(&oldest);
+		}
+	}
+
+	pages.insert(key, (page, Instant::now()));
+}
+
+/// Drops the pages prefetched for the search of `key`, as its results are being read again.
+fn clear(key: &PageKey) {
+	PAGES
+		.lock()
+		.expect("prefetched pages lock poisoned")
+		.retain(|other, _| !key.same_search(other));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn serves_prefetched_pages_once() {
+		let library_id = Uuid::new_v4();
+		let first = PageKey::new(library_id, "search.paths", &("filters", 10), 0);
+		let next = first.next(10);
+
+		assert_eq!(
+			next,
+			PageKey::new(library_id, "search.paths", &("filters", 10), 10)
+		);
+
+		insert(next.clone(), (vec![], Some(Value::from(1))));
+		assert_eq!(
+			take(&next).map(|(_, cursor)| cursor),
+			Some(Some(Value::from(1)))
+		);
+		assert!(take(&next).is_none());
+
+		insert(next.clone(), (vec![], None));
+		clear(&first);
+		assert!(take(&next).is_none());
+	}
+}
@@ -0,0 +1,167 @@
+//! The `Relevance` order of `search.paths`, which ranks the file paths by how likely each one is
+//! the one looked for. SQLite can't sort by such a score, so it's computed here for the file paths
+//! matching the filters.
+//!
+//! Only the [`MAX_RANKED`] most recently modified of them are ranked, so big libraries don't have
+//! to be read whole for every page, along with as many of the ones whose name starts with the text
+//! looked for, so exact matches are found however old they are. Older file paths only containing
+//! the text are left out.
+
+use sd_core_prisma_helpers::file_path_with_object;
+use sd_prisma::prisma::{self, file_path, PrismaClient};
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+
+use super::{file_path::FilePathFilterArgs, SearchFilterArgs};
+
+/// File paths ranked out of the most recently modified ones, and out of the ones whose name
+/// starts with the text looked for
+pub(super) const MAX_RANKED: i64 = 5_000;
+
+const EXACT_NAME_WEIGHT: f64 = 100.0;
+const NAME_PREFIX_WEIGHT: f64 = 50.0;
+const RECENCY_WEIGHT: f64 = 30.0;
+/// The recency score of a file path halves every this many days since it was modified
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+const ACCESS_WEIGHT: f64 = 30.0;
+/// Opening a file path this many times earns half of [`ACCESS_WEIGHT`]
+const HALF_ACCESS_COUNT: f64 = 5.0;
+
+file_path::select!(file_path_to_rank {
+	id
+	is_dir
+	name
+	extension
+	date_modified
+	object: select { access_count }
+});
+
+/// The text looked for, from the first `name` filter.
+pub(super) fn name_query(filters: &[SearchFilterArgs]) -> Option<String> {
+	filters.iter().find_map(|filter| match filter {
+		SearchFilterArgs::FilePath(FilePathFilterArgs::Name(name)) if !name.is_empty() => {
+			Some(name.text().to_lowercase())
+		}
+		_ => None,
+	})
+}
+
+/// The `take` best ranked file paths matching `params` after the first `offset` ones, with the
+/// directories first if `group_directories`.
+pub(super) async fn ranked_paths(
+	db: &PrismaClient,
+	params: Vec<file_path::WhereParam>,
+	name_query: Option<&str>,
+	offset: i32,
+	take: usize,
+	group_directories: bool,
+) -> Result<Vec<file_path_with_object::Data>, rspc::Error> {
+	let candidates_query = |params: Vec<file_path::WhereParam>| {
+		db.file_path()
+			.find_many(params)
+			.order_by(file_path::date_modified::order(prisma::SortOrder::Desc))
+			.take(MAX_RANKED)
+			.select(file_path_to_rank::select())
+	};
+
+	let candidates = match name_query {
+		Some(query) => {
+			let name_params = params
+				.iter()
+				.cloned()
+				.chain([file_path::name::starts_with(query.to_string())])
+				.collect();
+
+			let (mut recent, named) = db
+				._batch((candidates_query(params), candidates_query(name_params)))
+				.await?;
+
+			let ids = recent
+				.iter()
+				.map(|file_path| file_path.id)
+				.collect::<HashSet<_>>();
+
+			recent.extend(
+				named
+					.into_iter()
+					.filter(|file_path| !ids.contains(&file_path.id)),
+			);
+
+			recent
+		}
+		None => candidates_query(params).exec().await?,
+	};
+
+	let now = Utc::now();
+
+	let mut ranked = candidates
+		.into_iter()
+		.map(|file_path| {
+			let is_dir = group_directories && file_path.is_dir.unwrap_or_default();
+
+			(is_dir, score(&file_path, name_query, now), file_path.id)
+		})
+		.collect::<Vec<_>>();
+
+	// Ties are ordered by id, so the pages don't overlap
+	ranked.sort_by(|(a_is_dir, a_score, a_id), (b_is_dir, b_score, b_id)| {
+		b_is_dir
+			.cmp(a_is_dir)
+			.then(b_score.total_cmp(a_score))
+			.then(a_id.cmp(b_id))
+	});
+
+	let ranks = ranked
+		.into_iter()
+		.skip(usize::try_from(offset).unwrap_or_default())
+		.take(take)
+		.enumerate()
+		.map(|(rank, (_, _, id))| (id, rank))
+		.collect::<HashMap<_, _>>();
+
+	let mut file_paths = db
+		.file_path()
+		.find_many(vec![file_path::id::in_vec(ranks.keys().copied().collect())])
+		.include(file_path_with_object::include())
+		.exec()
+		.await?;
+
+	file_paths.sort_by_key(|file_path| ranks.get(&file_path.id).copied());
+
+	Ok(file_paths)
+}
+
+fn score(file_path: &file_path_to_rank::Data, name_query: Option<&str>, now: DateTime<Utc>) -> f64 {
+	let mut score = 0.0;
+
+	if let Some(query) = name_query {
+		let name = file_path.name.as_deref().unwrap_or_default().to_lowercase();
+		let full_name = match file_path.extension.as_deref() {
+			Some(extension) if !extension.is_empty() => format!("{name}.{extension}"),
+			_ => name.clone(),
+		};
+
+		if name == query || full_name.to_lowercase() == query {
+			score += EXACT_NAME_WEIGHT;
+		} else if name.starts_with(query) {
+			score += NAME_PREFIX_WEIGHT;
+		}
+	}
+
+	if let Some(date_modified) = file_path.date_modified {
+		let age = now - date_modified.with_timezone(&Utc);
+		let days = age.num_seconds().max(0) as f64 / 86_400.0;
+
+		score += RECENCY_WEIGHT * 0.5_f64.powf(days / RECENCY_HALF_LIFE_DAYS);
+	}
+
+	if let Some(access_count) = file_path.object.as_ref().and_then(|o| o.access_count) {
+		let access_count = f64::from(access_count.max(0));
+
+		score += ACCESS_WEIGHT * access_count / (access_count + HALF_ACCESS_COUNT);
+	}
+
+	score
+}
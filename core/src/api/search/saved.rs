@@ -1,23 +1,33 @@
 use std::str::FromStr;
 
-use crate::{api::utils::library, invalidate_query, library::Library};
+use crate::{
+	api::{error::ApiError, locations::ExplorerItem, utils::library},
+	invalidate_query,
+	library::Library,
+	Node,
+};
 
+use sd_cache::NormalisedDiff;
 use sd_prisma::{prisma::saved_search, prisma_sync};
 use sd_sync::{option_sync_db_entry, sync_db_entry, OperationFactory};
 use sd_utils::chain_optional_iter;
 
+use async_stream::stream;
 use chrono::{DateTime, FixedOffset, Utc};
-use rspc::alpha::AlphaRouter;
+use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{de::IgnoredAny, Deserialize};
 use specta::Type;
 use tracing::error;
 use uuid::Uuid;
 
-use super::{Ctx, R};
+use super::{
+	find_objects, find_paths, wait_for_invalidation, Ctx, FilePathFilterArgs, FilePathSearchArgs,
+	ObjectSearchArgs, SearchFilterArgs, TextMatch, R,
+};
 
 #[derive(Type, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
-enum SearchTarget {
+pub(super) enum SearchTarget {
 	#[default]
 	Paths,
 	Objects,
@@ -62,6 +72,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					pub description: Option<String>,
 					#[specta(optional)]
 					pub icon: Option<String>,
+					/// Re-evaluated as the file paths and objects change, see `search.saved.subscribe`
+					#[serde(default)]
+					#[specta(optional)]
+					pub is_live: bool,
 				}
 
 				|(_, library), args: Args| async move {
@@ -74,6 +88,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							sync_db_entry!(date_created, saved_search::date_created),
 							sync_db_entry!(args.name, saved_search::name),
 							sync_db_entry!(args.target.to_string(), saved_search::target),
+							sync_db_entry!(args.is_live, saved_search::is_live),
 						],
 						[
 							option_sync_db_entry!(
@@ -148,6 +163,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					icon
 					search
 					filters
+					is_live
 				});
 
 				|(_, library), (id, args): (saved_search::id::Type, Args)| async move {
@@ -160,9 +176,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.select(saved_search::select!({ pub_id }))
 						.exec()
 						.await?
-						.ok_or_else(|| {
-							rspc::Error::new(rspc::ErrorCode::NotFound, "search not found".into())
-						})?;
+						.ok_or(ApiError::SavedSearchNotFound { id })?;
 
 					let (sync_params, db_params): (Vec<_>, Vec<_>) = chain_optional_iter(
 						[sync_db_entry!(updated_at, saved_search::date_modified)],
@@ -172,6 +186,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							option_sync_db_entry!(args.icon.flatten(), saved_search::icon),
 							option_sync_db_entry!(args.search.flatten(), saved_search::search),
 							option_sync_db_entry!(args.filters.flatten(), saved_search::filters),
+							option_sync_db_entry!(args.is_live.flatten(), saved_search::is_live),
 						],
 					)
 					.into_iter()
@@ -206,6 +221,60 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				}
 			})
 		})
+		.procedure("subscribe", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct LiveSavedSearchArgs {
+				id: saved_search::id::Type,
+				#[specta(optional)]
+				take: Option<u8>,
+			}
+
+			// Sends the first results of a live saved search, then the changes to them as the file
+			// paths, the objects or the saved search itself change, until it isn't live anymore
+			R.with2(library()).subscription(
+				|(node, library), LiveSavedSearchArgs { id, take }| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					// Checked right away so the client doesn't wait on a search which never updates
+					if live_results(&node, &library, id, take).await?.is_none() {
+						return Err(rspc::Error::new(
+							ErrorCode::BadRequest,
+							format!("Saved search <id='{id}'> isn't live"),
+						));
+					}
+
+					Ok(stream! {
+						let mut diff = NormalisedDiff::new();
+
+						loop {
+							match live_results(&node, &library, id, take).await {
+								Ok(Some(items)) => {
+									let delta = diff.diff(items, |item| item.id());
+
+									if !delta.is_empty() {
+										yield delta;
+									}
+								}
+								Ok(None) => break,
+								Err(e) => error!(
+									"Failed to update the results of saved search <id='{id}'>: {e:#?}"
+								),
+							}
+
+							if !wait_for_invalidation(
+								&mut event_bus_rx,
+								&["search.paths", "search.objects", "search.saved.get"],
+							)
+							.await
+							{
+								break;
+							}
+						}
+					})
+				},
+			)
+		})
 		.procedure("delete", {
 			R.with2(library())
 				.mutation(|(_, library), search_id: i32| async move {
@@ -217,9 +286,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.select(saved_search::select!({ pub_id }))
 						.exec()
 						.await?
-						.ok_or_else(|| {
-							rspc::Error::new(rspc::ErrorCode::NotFound, "search not found".into())
-						})?;
+						.ok_or(ApiError::SavedSearchNotFound { id: search_id })?;
 
 					sync.write_op(
 						db,
@@ -239,3 +306,109 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				})
 		})
 }
+
+/// What the saved search with `id` looks for, and the filters it looks for them with.
+pub(super) async fn saved_search(
+	library: &Library,
+	id: saved_search::id::Type,
+) -> Result<(SearchTarget, Vec<SearchFilterArgs>), rspc::Error> {
+	let saved = library
+		.db
+		.saved_search()
+		.find_unique(saved_search::id::equals(id))
+		.exec()
+		.await?
+		.ok_or(ApiError::SavedSearchNotFound { id })?;
+
+	search_of(saved)
+}
+
+fn search_of(
+	saved: saved_search::Data,
+) -> Result<(SearchTarget, Vec<SearchFilterArgs>), rspc::Error> {
+	let mut filters = match saved.filters {
+		Some(filters) => serde_json::from_str::<Vec<SearchFilterArgs>>(&filters).map_err(|e| {
+			rspc::Error::with_cause(
+				ErrorCode::InternalServerError,
+				"Failed to parse the filters of the saved search".to_string(),
+				e,
+			)
+		})?,
+		None => vec![],
+	};
+
+	// Like the clients do with the text of their searches
+	if let Some(search) = saved.search.filter(|search| !search.is_empty()) {
+		filters.push(SearchFilterArgs::FilePath(FilePathFilterArgs::Name(
+			TextMatch::Contains(search),
+		)));
+	}
+
+	let target = saved
+		.target
+		.as_deref()
+		.map(SearchTarget::from_str)
+		.transpose()
+		.map_err(|e| rspc::Error::new(ErrorCode::InternalServerError, e))?
+		.unwrap_or_default();
+
+	Ok((target, filters))
+}
+
+/// The first `take` results of the saved search with `id`, `None` once it isn't live anymore.
+async fn live_results(
+	node: &Node,
+	library: &Library,
+	id: saved_search::id::Type,
+	take: Option<u8>,
+) -> Result<Option<Vec<ExplorerItem>>, rspc::Error> {
+	let Some(saved) = library
+		.db
+		.saved_search()
+		.find_unique(saved_search::id::equals(id))
+		.exec()
+		.await?
+		.filter(|saved| saved.is_live.unwrap_or_default())
+	else {
+		return Ok(None);
+	};
+
+	let (items, _, _) = match search_of(saved)? {
+		(SearchTarget::Paths, filters) => {
+			find_paths(
+				node,
+				library,
+				FilePathSearchArgs {
+					take: take.map(u16::from),
+					order_and_pagination: None,
+					filters,
+					group_directories: true,
+					with_count: false,
+					skip_thumbnails: false,
+					with_media_data: false,
+					prefetch: false,
+					use_defaults: None,
+				},
+			)
+			.await?
+		}
+		(SearchTarget::Objects, filters) => {
+			find_objects(
+				node,
+				library,
+				ObjectSearchArgs {
+					take: take.map(u16::from),
+					order_and_pagination: None,
+					filters,
+					with_count: false,
+					skip_thumbnails: false,
+					with_media_data: false,
+					prefetch: false,
+				},
+			)
+			.await?
+		}
+	};
+
+	Ok(Some(items))
+}
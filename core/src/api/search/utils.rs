@@ -1,5 +1,9 @@
 use sd_prisma::prisma;
 
+use prisma_client_rust::{
+	operator::{and, not, or},
+	Operator,
+};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
@@ -42,14 +46,46 @@ impl From<SortOrder> for prisma::SortOrder {
 // 	}
 // }
 
-#[derive(Deserialize, Type, Debug)]
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CursorOrderItem<T> {
 	pub order: SortOrder,
 	pub data: T,
 }
 
-#[derive(Deserialize, Type, Debug)]
+/// How to match the items past a cursor on a field that can be `NULL`, which SQLite orders before
+/// every value in ascending order and after them in descending order.
+pub struct NullableField<T, W> {
+	/// The items whose value comes after this one, in this order
+	pub past: fn(SortOrder, T) -> W,
+	pub equals: fn(T) -> W,
+	pub is_null: fn() -> W,
+	/// The items whose id comes after this one, as ties are always ordered by id
+	pub id_after: fn(i32) -> W,
+}
+
+impl<T: Clone, W: From<Operator<W>>> NullableField<T, W> {
+	/// Matches the items after the one with this cursor and `id`.
+	pub fn after(&self, item: CursorOrderItem<Option<T>>, id: i32) -> W {
+		let tie = |same| and(vec![same, (self.id_after)(id)]);
+
+		match (item.order, item.data) {
+			(SortOrder::Asc, Some(data)) => or(vec![
+				(self.past)(item.order, data.clone()),
+				tie((self.equals)(data)),
+			]),
+			(SortOrder::Desc, Some(data)) => or(vec![
+				(self.past)(item.order, data.clone()),
+				tie((self.equals)(data)),
+				(self.is_null)(),
+			]),
+			(SortOrder::Asc, None) => or(vec![tie((self.is_null)()), not(vec![(self.is_null)()])]),
+			(SortOrder::Desc, None) => tie((self.is_null)()),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum OrderAndPagination<TId, TOrder, TCursor> {
 	OrderOnly(TOrder),
@@ -57,6 +93,17 @@ pub enum OrderAndPagination<TId, TOrder, TCursor> {
 	Cursor { id: TId, cursor: TCursor },
 }
 
+impl<TId, TOrder, TCursor> OrderAndPagination<TId, TOrder, TCursor> {
+	/// The items skipped before the page, negative offsets reading the first one like with
+	/// [`Pagination`](crate::api::utils::Pagination).
+	pub fn offset(&self) -> i32 {
+		match self {
+			Self::Offset { offset, .. } => (*offset).max(0),
+			Self::OrderOnly(_) | Self::Cursor { .. } => 0,
+		}
+	}
+}
+
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum InOrNotIn<T> {
@@ -105,6 +152,12 @@ impl TextMatch {
 		}
 	}
 
+	pub fn text(&self) -> &str {
+		match self {
+			Self::Contains(v) | Self::StartsWith(v) | Self::EndsWith(v) | Self::Equals(v) => v,
+		}
+	}
+
 	// 3. Update the to_param method of TextMatch
 	pub fn into_param<TParam>(
 		self,
@@ -2,10 +2,31 @@ use rspc::alpha::AlphaRouter;
 use sd_core_sync::GetOpsArgs;
 use std::sync::atomic::Ordering;
 
-use crate::util::MaybeUndefined;
+use crate::{library::Library, util::MaybeUndefined};
 
 use super::{utils::library, Ctx, R};
 
+#[derive(serde::Serialize, specta::Type)]
+struct SyncStatus {
+	ingest: bool,
+	cloud_send: bool,
+	cloud_receive: bool,
+	cloud_ingest: bool,
+}
+
+impl SyncStatus {
+	fn from_library(library: &Library) -> Self {
+		let cloud_sync = &library.cloud.sync;
+
+		Self {
+			ingest: library.sync.shared.active.load(Ordering::Relaxed),
+			cloud_send: cloud_sync.send_active.load(Ordering::Relaxed),
+			cloud_receive: cloud_sync.receive_active.load(Ordering::Relaxed),
+			cloud_ingest: cloud_sync.ingest_active.load(Ordering::Relaxed),
+		}
+	}
+}
+
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("newMessage", {
@@ -75,29 +96,19 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					.load(Ordering::Relaxed))
 			})
 		})
+		.procedure("status", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(SyncStatus::from_library(&library)) })
+		})
 		.procedure("active", {
 			R.with2(library())
 				.subscription(|(_, library), _: ()| async move {
-					#[derive(serde::Serialize, specta::Type)]
-					#[specta(rename = "SyncStatus")]
-					struct Data {
-						ingest: bool,
-						cloud_send: bool,
-						cloud_receive: bool,
-						cloud_ingest: bool,
-					}
-
 					async_stream::stream! {
 						let cloud_sync = &library.cloud.sync;
 						let sync = &library.sync.shared;
 
 						loop {
-							yield Data {
-							  ingest: sync.active.load(Ordering::Relaxed),
-								cloud_send: cloud_sync.send_active.load(Ordering::Relaxed),
-								cloud_receive: cloud_sync.receive_active.load(Ordering::Relaxed),
-								cloud_ingest: cloud_sync.ingest_active.load(Ordering::Relaxed),
-							};
+							yield SyncStatus::from_library(&library);
 
 							tokio::select! {
 								_ = cloud_sync.notifier.notified() => {},
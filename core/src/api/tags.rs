@@ -1,14 +1,26 @@
-use crate::{invalidate_query, library::Library, object::tag::TagCreateArgs};
+use crate::{
+	api::search::SearchFilterArgs,
+	invalidate_query,
+	library::Library,
+	object::tag::{
+		rules::old_tag_rules_job::OldTagRulesJobInit, with_descendant_ids, TagCreateArgs,
+	},
+	old_job::Job,
+	Node,
+};
 
-use sd_cache::{CacheNode, Normalise, NormalisedResult, NormalisedResults, Reference};
+use sd_cache::{CacheKey, CacheNode, Normalise, NormalisedResult, NormalisedResults, Reference};
 use sd_prisma::{
-	prisma::{file_path, object, tag, tag_on_object},
+	prisma::{
+		file_path, location, object, tag, tag_on_object, tag_rule, tag_rule_action, PrismaClient,
+		SortOrder,
+	},
 	prisma_sync,
 };
 use sd_sync::OperationFactory;
-use sd_utils::{msgpack, uuid_to_bytes};
+use sd_utils::{chain_optional_iter, msgpack, uuid_to_bytes};
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
 use chrono::{DateTime, Utc};
 use itertools::{Either, Itertools};
@@ -17,18 +29,50 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use uuid::Uuid;
 
-use super::{utils::library, Ctx, R};
+use super::{
+	utils::{library, paginate, CursorArgs, NormalisedPage},
+	Ctx, R,
+};
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("list", {
-			R.with2(library()).query(|(_, library), _: ()| async move {
-				let tags = library.db.tag().find_many(vec![]).exec().await?;
+			R.with2(library()).query(
+				|(_, library), args: Option<CursorArgs<tag::id::Type>>| async move {
+					let mut query = library
+						.db
+						.tag()
+						// Tags are ordered by id, so the next page starts right after the cursor
+						.find_many(
+							args.as_ref()
+								.and_then(|args| args.cursor)
+								.map(tag::id::gt)
+								.into_iter()
+								.collect(),
+						)
+						.order_by(tag::id::order(SortOrder::Asc));
+
+					// Without arguments we keep returning every tag
+					if let Some(args) = &args {
+						query = query.take(args.fetch());
+					}
+
+					let tags = query.exec().await?;
 
-				let (nodes, items) = tags.normalise(|i| i.id.to_string());
+					let (tags, cursor) = match &args {
+						Some(args) => paginate(tags, args.take(), |tag| tag.id),
+						None => (tags, None),
+					};
+
+					let (nodes, items) = tags.normalise(|i| i.id.to_string());
 
-				Ok(NormalisedResults { nodes, items })
-			})
+					Ok(NormalisedPage {
+						nodes,
+						items,
+						cursor,
+					})
+				},
+			)
 		})
 		.procedure("getForObject", {
 			R.with2(library())
@@ -104,6 +148,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("create", {
 			R.with2(library())
 				.mutation(|(_, library), args: TagCreateArgs| async move {
+					if let Some(parent_id) = args.parent_id {
+						find_tag(&library.db, parent_id).await?;
+					}
+
 					let created_tag = args.exec(&library).await?;
 
 					invalidate_query!(library, "tags.list");
@@ -356,6 +404,78 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("reparent", {
+			#[derive(Type, Deserialize)]
+			pub struct TagReparentArgs {
+				pub id: tag::id::Type,
+				/// Makes it a top level tag if `None`
+				pub parent_id: Option<tag::id::Type>,
+			}
+
+			R.with2(library()).mutation(
+				|(_, library), TagReparentArgs { id, parent_id }: TagReparentArgs| async move {
+					let Library { sync, db, .. } = library.as_ref();
+
+					let tag = find_tag(db, id).await?;
+
+					let parent = match parent_id {
+						Some(parent_id) => {
+							if with_descendant_ids(db, vec![id])
+								.await?
+								.contains(&parent_id)
+							{
+								return Err(rspc::Error::new(
+									ErrorCode::BadRequest,
+									"A tag can't be nested under itself or its descendants".into(),
+								));
+							}
+
+							Some(find_tag(db, parent_id).await?)
+						}
+						None => None,
+					};
+
+					sync.write_ops(
+						db,
+						(
+							vec![match &parent {
+								Some(parent) => sync.shared_update(
+									prisma_sync::tag::SyncId { pub_id: tag.pub_id },
+									tag::parent::NAME,
+									msgpack!(prisma_sync::tag::SyncId {
+										pub_id: parent.pub_id.clone()
+									}),
+								),
+								// Relations can only be synced as connected, so the column is unset instead
+								None => sync.shared_update(
+									prisma_sync::tag::SyncId { pub_id: tag.pub_id },
+									tag::parent_id::NAME,
+									msgpack!(nil),
+								),
+							}],
+							db.tag().update(
+								tag::id::equals(id),
+								vec![
+									match parent {
+										Some(parent) => {
+											tag::parent::connect(tag::id::equals(parent.id))
+										}
+										None => tag::parent::disconnect(),
+									},
+									tag::date_modified::set(Some(Utc::now().into())),
+								],
+							),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "tags.list");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				},
+			)
+		})
 		.procedure(
 			"delete",
 			R.with2(library())
@@ -374,9 +494,244 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.exec()
 						.await?;
 
+					library.evict_cache_nodes(vec![CacheKey::new::<tag::Data>(tag_id.to_string())]);
 					invalidate_query!(library, "tags.list");
 
 					Ok(())
 				}),
 		)
+		.merge("rules.", mount_rule_routes())
+}
+
+fn mount_rule_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), tag_id: Option<tag::id::Type>| async move {
+					Ok(library
+						.db
+						.tag_rule()
+						.find_many(tag_id.map(tag_rule::tag_id::equals).into_iter().collect())
+						.order_by(tag_rule::id::order(SortOrder::Asc))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct TagRuleCreateArgs {
+				pub tag_id: tag::id::Type,
+				pub filters: Vec<SearchFilterArgs>,
+				/// Also removes the tag from the objects the rule applied it to once they stop
+				/// matching, `true` by default
+				#[serde(default)]
+				#[specta(optional)]
+				pub remove_unmatched: Option<bool>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 TagRuleCreateArgs {
+				     tag_id,
+				     filters,
+				     remove_unmatched,
+				 }: TagRuleCreateArgs| async move {
+					find_tag(&library.db, tag_id).await?;
+
+					let date_created = Utc::now().into();
+
+					let rule = library
+						.db
+						.tag_rule()
+						.create(
+							serialize_filters(&filters)?,
+							tag::id::equals(tag_id),
+							chain_optional_iter(
+								[
+									tag_rule::date_created::set(Some(date_created)),
+									tag_rule::date_modified::set(Some(date_created)),
+								],
+								[remove_unmatched.map(tag_rule::remove_unmatched::set)],
+							),
+						)
+						.exec()
+						.await?;
+
+					evaluate_rule(&node, &library, rule.id).await?;
+
+					invalidate_query!(library, "tags.rules.list");
+
+					Ok(rule)
+				},
+			)
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct TagRuleUpdateArgs {
+				pub id: tag_rule::id::Type,
+				#[specta(optional)]
+				pub filters: Option<Vec<SearchFilterArgs>>,
+				#[specta(optional)]
+				pub remove_unmatched: Option<bool>,
+				#[specta(optional)]
+				pub enabled: Option<bool>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 TagRuleUpdateArgs {
+				     id,
+				     filters,
+				     remove_unmatched,
+				     enabled,
+				 }: TagRuleUpdateArgs| async move {
+					let rule = library
+						.db
+						.tag_rule()
+						.update(
+							tag_rule::id::equals(id),
+							chain_optional_iter(
+								[tag_rule::date_modified::set(Some(Utc::now().into()))],
+								[
+									filters
+										.as_deref()
+										.map(serialize_filters)
+										.transpose()?
+										.map(tag_rule::filters::set),
+									remove_unmatched.map(tag_rule::remove_unmatched::set),
+									enabled.map(tag_rule::enabled::set),
+								],
+							),
+						)
+						.exec()
+						.await?;
+
+					if rule.enabled {
+						evaluate_rule(&node, &library, rule.id).await?;
+					}
+
+					invalidate_query!(library, "tags.rules.list");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), id: tag_rule::id::Type| async move {
+					library
+						.db
+						.tag_rule()
+						.delete(tag_rule::id::equals(id))
+						.exec()
+						.await?;
+
+					invalidate_query!(library, "tags.rules.list");
+					invalidate_query!(library, "tags.rules.actions");
+
+					Ok(())
+				})
+		})
+		.procedure("actions", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct TagRuleActionsArgs {
+				pub rule_id: tag_rule::id::Type,
+				/// Amount of actions in the page, up to 100 which is also the default
+				#[serde(default)]
+				#[specta(optional)]
+				pub take: Option<u8>,
+				/// The `cursor` returned with the previous page, missing for the first page
+				#[serde(default)]
+				#[specta(optional)]
+				pub cursor: Option<tag_rule_action::id::Type>,
+			}
+
+			#[derive(Serialize, Type)]
+			pub struct TagRuleActions {
+				/// The latest first
+				pub items: Vec<tag_rule_action::Data>,
+				/// Missing on the last page
+				pub cursor: Option<tag_rule_action::id::Type>,
+			}
+
+			// The tags applied and removed by a rule, to audit what it did
+			R.with2(library()).query(
+				|(_, library),
+				 TagRuleActionsArgs {
+				     rule_id,
+				     take,
+				     cursor,
+				 }: TagRuleActionsArgs| async move {
+					let args = CursorArgs { take, cursor };
+
+					let actions = library
+						.db
+						.tag_rule_action()
+						.find_many(chain_optional_iter(
+							[tag_rule_action::rule_id::equals(rule_id)],
+							[cursor.map(tag_rule_action::id::lt)],
+						))
+						.order_by(tag_rule_action::id::order(SortOrder::Desc))
+						.take(args.fetch())
+						.exec()
+						.await?;
+
+					let (items, cursor) = paginate(actions, args.take(), |action| action.id);
+
+					Ok(TagRuleActions { items, cursor })
+				},
+			)
+		})
+}
+
+/// Evaluates the rule with `id` on the locations of this node, in the background.
+async fn evaluate_rule(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	id: tag_rule::id::Type,
+) -> Result<(), rspc::Error> {
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::instance_id::equals(Some(
+			library.config().await.instance_id,
+		))])
+		.select(location::select!({ id }))
+		.exec()
+		.await?;
+
+	for location in locations {
+		Job::new(OldTagRulesJobInit {
+			location_id: location.id,
+			rule_id: Some(id),
+		})
+		.spawn(node, library)
+		.await?;
+	}
+
+	Ok(())
+}
+
+fn serialize_filters(filters: &[SearchFilterArgs]) -> Result<String, rspc::Error> {
+	serde_json::to_string(filters).map_err(|e| {
+		rspc::Error::with_cause(
+			ErrorCode::InternalServerError,
+			"Failed to serialize the filters of the tag rule".to_string(),
+			e,
+		)
+	})
+}
+
+tag::select!(tag_ids { id pub_id });
+
+async fn find_tag(db: &PrismaClient, id: tag::id::Type) -> Result<tag_ids::Data, rspc::Error> {
+	db.tag()
+		.find_unique(tag::id::equals(id))
+		.select(tag_ids::select())
+		.exec()
+		.await?
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Tag not found".to_string()))
 }
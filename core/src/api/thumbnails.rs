@@ -0,0 +1,279 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	object::{
+		cas::ephemeral_cas_id,
+		media::old_thumbnail::{
+			can_generate_thumbnail_for_extension, preferences::ThumbnailSettings,
+			request_thumbnail, BatchToProcess, GenerateThumbnailArgs, ThumbnailKind, ThumbnailTier,
+		},
+	},
+	Node,
+};
+
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma::{file_path, object};
+use sd_utils::error::FileIOError;
+
+use std::path::PathBuf;
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::fs;
+use tracing::error;
+
+use super::{utils::library, Ctx, R};
+
+#[derive(Type, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub(super) enum ThumbnailSource {
+	/// A file indexed in one of the library's locations
+	Indexed { id: file_path::id::Type },
+	/// A file outside of the library's locations, shown through an ephemeral path
+	Ephemeral { path: PathBuf },
+}
+
+#[derive(Type, Deserialize)]
+struct RequestThumbnailArgs {
+	source: ThumbnailSource,
+	tier: ThumbnailTier,
+}
+
+/// How much space the thumbnails take on disk, for all libraries
+#[derive(Type, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailCacheStats {
+	size_in_bytes: String,
+	count: u32,
+	/// In MiB, thumbnails are never evicted if `None`
+	budget_mb: Option<u32>,
+}
+
+impl ThumbnailSource {
+	/// Finds the file to generate thumbnails from, with its extension, cas id and the kind of
+	/// thumbnails it gets.
+	pub(super) async fn resolve(
+		self,
+		library: &Library,
+	) -> Result<(PathBuf, String, String, ThumbnailKind), rspc::Error> {
+		match self {
+			Self::Indexed { id } => {
+				let file_path = library
+					.db
+					.file_path()
+					.find_unique(file_path::id::equals(id))
+					.select(file_path::select!({ cas_id extension }))
+					.exec()
+					.await?
+					.ok_or_else(|| {
+						rspc::Error::new(ErrorCode::NotFound, "File path not found".to_string())
+					})?;
+
+				let cas_id = file_path.cas_id.ok_or_else(|| {
+					rspc::Error::new(
+						ErrorCode::BadRequest,
+						"File wasn't identified yet".to_string(),
+					)
+				})?;
+
+				let path = library
+					.get_file_paths(vec![id])
+					.await?
+					.remove(&id)
+					.flatten()
+					.ok_or_else(|| {
+						rspc::Error::new(
+							ErrorCode::NotFound,
+							"File isn't available on this device".to_string(),
+						)
+					})?;
+
+				Ok((
+					path,
+					file_path.extension.unwrap_or_default(),
+					cas_id,
+					ThumbnailKind::Indexed(library.id),
+				))
+			}
+			Self::Ephemeral { path } => {
+				let metadata = fs::metadata(&path)
+					.await
+					.map_err(|e| FileIOError::from((&path, e)))?;
+
+				let cas_id = ephemeral_cas_id(&path, &metadata);
+
+				let extension = path
+					.extension()
+					.and_then(|extension| extension.to_str())
+					.unwrap_or_default()
+					.to_lowercase();
+
+				Ok((path, extension, cas_id, ThumbnailKind::Ephemeral))
+			}
+		}
+	}
+}
+
+/// Queues the thumbnails of the library's videos to be generated again, in the background, so the
+/// ones generated before the animated thumbnails setting changed follow it.
+async fn regenerate_video_thumbnails(
+	node: &Node,
+	library: &Library,
+	animated_videos: bool,
+) -> Result<(), rspc::Error> {
+	let file_paths = library
+		.db
+		.file_path()
+		.find_many(vec![
+			file_path::cas_id::not(None),
+			file_path::object::is(vec![object::kind::equals(Some(ObjectKind::Video as i32))]),
+		])
+		.select(file_path::select!({ id cas_id extension }))
+		.exec()
+		.await?;
+
+	let mut full_paths = library
+		.get_file_paths(file_paths.iter().map(|file_path| file_path.id).collect())
+		.await?;
+
+	let batch = file_paths
+		.into_iter()
+		.filter_map(|file_path| {
+			let extension = file_path.extension.unwrap_or_default();
+
+			if !can_generate_thumbnail_for_extension(&extension) {
+				return None;
+			}
+
+			Some(GenerateThumbnailArgs::new(
+				extension,
+				file_path.cas_id?,
+				// Files from the locations of other devices have no path here
+				full_paths.remove(&file_path.id).flatten()?,
+			))
+		})
+		.collect::<Vec<_>>();
+
+	if !batch.is_empty() {
+		node.thumbnailer
+			.new_indexed_thumbnails_batch(
+				BatchToProcess::new(batch, true, true).with_animated_videos(animated_videos),
+				library.id,
+			)
+			.await;
+	}
+
+	Ok(())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("settings", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.thumbnails) })
+		})
+		.procedure("setSettings", {
+			R.with2(library())
+				.mutation(|(node, library), settings: ThumbnailSettings| async move {
+					let animated_videos = settings.animated_videos;
+					let animated_videos_changed =
+						library.config().await.thumbnails.animated_videos != animated_videos;
+
+					library
+						.update_config(
+							|config| config.thumbnails = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					// The existing video thumbnails are replaced to match the new setting
+					if animated_videos_changed {
+						regenerate_video_thumbnails(&node, &library, animated_videos).await?;
+					}
+
+					invalidate_query!(library, "thumbnails.settings");
+
+					Ok(())
+				})
+		})
+		.procedure("cacheStats", {
+			R.query(|node, _: ()| async move {
+				let (size_in_bytes, count) = node.thumbnailer.cache_size().await.map_err(|e| {
+					rspc::Error::with_cause(
+						ErrorCode::InternalServerError,
+						"Failed to read the thumbnails cache".to_string(),
+						e,
+					)
+				})?;
+
+				Ok(ThumbnailCacheStats {
+					size_in_bytes: size_in_bytes.to_string(),
+					count: u32::try_from(count).unwrap_or(u32::MAX),
+					budget_mb: node
+						.config
+						.get()
+						.await
+						.preferences
+						.thumbnailer
+						.cache_budget_mb(),
+				})
+			})
+		})
+		.procedure("setCacheBudget", {
+			R.mutation(|node, budget_mb: Option<u32>| async move {
+				node.config
+					.update_preferences(|preferences| {
+						preferences.thumbnailer.set_cache_budget_mb(budget_mb);
+					})
+					.await
+					.map_err(|e| {
+						error!("failed to update thumbnails cache budget: {e:#?}");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update thumbnails cache budget".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+				invalidate_query!(node; node, "thumbnails.cacheStats");
+
+				Ok(())
+			})
+		})
+		.procedure("clear", {
+			R.mutation(|node, _: ()| async move {
+				node.thumbnailer.clear_cache().await.map_err(|e| {
+					rspc::Error::with_cause(
+						ErrorCode::InternalServerError,
+						"Failed to clear the thumbnails cache".to_string(),
+						e,
+					)
+				})?;
+
+				invalidate_query!(node; node, "thumbnails.cacheStats");
+
+				Ok(())
+			})
+		})
+		.procedure("request", {
+			R.with2(library()).query(
+				|(node, library), RequestThumbnailArgs { source, tier }: RequestThumbnailArgs| async move {
+					let (path, extension, cas_id, kind) = source.resolve(&library).await?;
+
+					request_thumbnail(&node, &path, &extension, &cas_id, kind, tier)
+						.await
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to generate thumbnail".to_string(),
+								e,
+							)
+						})
+				},
+			)
+		})
+}
@@ -0,0 +1,104 @@
+use crate::{
+	invalidate_query,
+	location::{find_location, location_with_indexer_rules, scan_location_sub_path, LocationError},
+	object::fs::trash::{self, TrashSettings},
+};
+
+use sd_prisma::prisma::{trashed_file, SortOrder};
+
+use std::collections::BTreeSet;
+
+use rspc::alpha::AlphaRouter;
+
+use super::{utils::library, Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			// The most recently deleted first
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.trashed_file()
+					.find_many(vec![])
+					.order_by(trashed_file::date_deleted::order(SortOrder::Desc))
+					.exec()
+					.await?)
+			})
+		})
+		.procedure("restore", {
+			R.with2(library()).mutation(
+				|(node, library), ids: Vec<trashed_file::id::Type>| async move {
+					let trashed_files = library
+						.db
+						.trashed_file()
+						.find_many(vec![trashed_file::id::in_vec(ids)])
+						.exec()
+						.await?;
+
+					// Restored files are indexed again by scanning the folders they were moved into
+					let mut to_scan = BTreeSet::new();
+					for trashed in &trashed_files {
+						to_scan.insert((
+							trashed.location_id,
+							trash::restore(&library, trashed).await?,
+						));
+					}
+
+					for (location_id, sub_path) in to_scan {
+						let location = find_location(&library, location_id)
+							.include(location_with_indexer_rules::include())
+							.exec()
+							.await?
+							.ok_or(LocationError::IdNotFound(location_id))?;
+
+						scan_location_sub_path(&node, &library, location, sub_path).await?;
+					}
+
+					invalidate_query!(library, "trash.list");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("empty", {
+			// Permanently removes the given trashed files, or all of them if no id is given
+			R.with2(library()).mutation(
+				|(_, library), ids: Option<Vec<trashed_file::id::Type>>| async move {
+					let params = ids
+						.map(|ids| vec![trashed_file::id::in_vec(ids)])
+						.unwrap_or_default();
+
+					trash::purge(&library, params)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("settings", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.config().await.trash) })
+		})
+		.procedure("setSettings", {
+			R.with2(library())
+				.mutation(|(node, library), settings: TrashSettings| async move {
+					library
+						.update_config(
+							|config| config.trash = settings,
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "trash.settings");
+
+					// A shorter retention applies right away
+					trash::purge_expired(&library)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				})
+		})
+}
@@ -403,4 +403,17 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 			}
 		})
 	})
+	// Nodes to drop from the normalised cache of the client, without refetching the queries using them
+	.procedure("evictions", {
+		R.subscription(|node, _: ()| async move {
+			let mut event_bus_rx = node.event_bus.0.subscribe();
+			stream! {
+				while let Ok(event) = event_bus_rx.recv().await {
+					if let CoreEvent::EvictCacheNodes(keys) = event {
+						yield keys;
+					}
+				}
+			}
+		})
+	})
 }
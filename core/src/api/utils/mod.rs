@@ -4,9 +4,11 @@ use tokio::{fs, io};
 
 mod invalidate;
 mod library;
+mod pagination;
 
 pub use invalidate::*;
 pub(crate) use library::*;
+pub use pagination::*;
 
 /// Returns the size of the file or directory
 pub async fn get_size(path: impl AsRef<Path>) -> Result<u64, io::Error> {
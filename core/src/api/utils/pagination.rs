@@ -0,0 +1,183 @@
+use sd_cache::{CacheNode, Model, Reference};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Most items returned in a single page
+pub const MAX_TAKE: u8 = 100;
+
+/// A page of at most `take` items after the first `skip` ones, validated the same way by every
+/// procedure: a missing `take` reads as many items as allowed, and the values out of range are
+/// clamped instead of rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pagination {
+	take: u16,
+	skip: i32,
+}
+
+impl Pagination {
+	/// Between 1 and `max_take` items, after at least none.
+	pub fn new(take: Option<u16>, skip: i32, max_take: u16) -> Self {
+		let max_take = max_take.max(1);
+
+		Self {
+			take: take.unwrap_or(max_take).clamp(1, max_take),
+			skip: skip.max(0),
+		}
+	}
+
+	pub fn take(&self) -> u16 {
+		self.take
+	}
+
+	pub fn skip(&self) -> i32 {
+		self.skip
+	}
+
+	/// Amount of items to fetch from the database, one more than the page holds so
+	/// [`Pagination::paginate`] knows if there is a next page.
+	pub fn fetch(&self) -> i64 {
+		i64::from(self.take) + 1
+	}
+
+	/// The page of the same size right after this one.
+	pub fn next(&self) -> Self {
+		Self {
+			take: self.take,
+			skip: self.skip.saturating_add(i32::from(self.take)),
+		}
+	}
+
+	/// Trims the items fetched with [`Pagination::fetch`] to the page, see [`paginate`].
+	pub fn paginate<T, C>(
+		&self,
+		items: Vec<T>,
+		cursor_of: impl FnOnce(&T) -> C,
+	) -> (Vec<T>, Option<C>) {
+		paginate(items, self.take, cursor_of)
+	}
+}
+
+/// Arguments of the list procedures supporting cursor pagination.
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorArgs<C> {
+	/// Amount of items in the page, up to [`MAX_TAKE`] which is also the default
+	#[serde(default)]
+	pub take: Option<u8>,
+	/// The `cursor` returned with the previous page, missing for the first page
+	#[serde(default)]
+	pub cursor: Option<C>,
+}
+
+impl<C> CursorArgs<C> {
+	pub fn pagination(&self) -> Pagination {
+		Pagination::new(self.take.map(u16::from), 0, u16::from(MAX_TAKE))
+	}
+
+	pub fn take(&self) -> u16 {
+		self.pagination().take()
+	}
+
+	/// Amount of items to fetch from the database, see [`Pagination::fetch`].
+	pub fn fetch(&self) -> i64 {
+		self.pagination().fetch()
+	}
+}
+
+/// A page of normalised items, with the cursor of the next page.
+#[derive(Debug, Serialize, Type)]
+pub struct NormalisedPage<T: Model + Type, C> {
+	pub items: Vec<Reference<T>>,
+	pub nodes: Vec<CacheNode>,
+	/// Missing on the last page
+	pub cursor: Option<C>,
+}
+
+/// Trims the items fetched with one extra item, see [`CursorArgs::fetch`], to `take` items,
+/// returning the cursor of the next page if there is one.
+pub fn paginate<T, C>(
+	mut items: Vec<T>,
+	take: u16,
+	cursor_of: impl FnOnce(&T) -> C,
+) -> (Vec<T>, Option<C>) {
+	if items.len() <= usize::from(take) {
+		return (items, None);
+	}
+
+	items.truncate(usize::from(take));
+	let cursor = items.last().map(cursor_of);
+
+	(items, cursor)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use proptest::prelude::*;
+
+	#[test]
+	fn paginates_one_extra_item() {
+		assert_eq!(paginate(vec![1, 2, 3], 2, |i| *i), (vec![1, 2], Some(2)));
+		assert_eq!(paginate(vec![1, 2], 2, |i| *i), (vec![1, 2], None));
+		assert_eq!(paginate(Vec::<i32>::new(), 2, |i| *i), (vec![], None));
+	}
+
+	#[test]
+	fn clamps_take() {
+		let args = |take| CursorArgs::<i32> { take, cursor: None };
+
+		assert_eq!(args(None).take(), u16::from(MAX_TAKE));
+		assert_eq!(args(Some(0)).take(), 1);
+		assert_eq!(args(Some(200)).take(), u16::from(MAX_TAKE));
+		assert_eq!(args(Some(10)).fetch(), 11);
+	}
+
+	proptest! {
+		#[test]
+		fn pages_stay_in_range(take: Option<u16>, skip: i32, max_take: u16) {
+			let pagination = Pagination::new(take, skip, max_take);
+
+			prop_assert!(pagination.take() >= 1);
+			prop_assert!(pagination.take() <= max_take.max(1));
+			prop_assert!(pagination.skip() >= 0);
+			prop_assert_eq!(pagination.fetch(), i64::from(pagination.take()) + 1);
+		}
+
+		#[test]
+		fn takes_what_is_allowed(take: u16, max_take in 1u16..) {
+			let pagination = Pagination::new(Some(take), 0, max_take);
+
+			if (1..=max_take).contains(&take) {
+				prop_assert_eq!(pagination.take(), take);
+			}
+
+			// Missing takes read as much as allowed
+			prop_assert_eq!(Pagination::new(None, 0, max_take).take(), max_take);
+		}
+
+		#[test]
+		fn next_pages_follow(take: Option<u16>, skip: i32, max_take: u16) {
+			let pagination = Pagination::new(take, skip, max_take);
+			let next = pagination.next();
+
+			prop_assert_eq!(next.take(), pagination.take());
+			prop_assert!(next.skip() >= pagination.skip());
+			prop_assert_eq!(
+				next.skip(),
+				pagination.skip().saturating_add(i32::from(pagination.take()))
+			);
+		}
+
+		#[test]
+		fn paginates_to_the_page(len in 0usize..300, take: Option<u16>, max_take in 1u16..200) {
+			let pagination = Pagination::new(take, 0, max_take);
+			let (items, cursor) = pagination.paginate((0..len).collect(), |i| *i);
+
+			prop_assert_eq!(items.len(), len.min(usize::from(pagination.take())));
+			prop_assert_eq!(cursor.is_some(), len > usize::from(pagination.take()));
+			prop_assert_eq!(cursor, cursor.and(items.last().copied()));
+		}
+	}
+}
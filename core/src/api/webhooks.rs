@@ -0,0 +1,193 @@
+use crate::{
+	invalidate_query,
+	library::{Webhook, WebhookEventFilter},
+	util::MaybeUndefined,
+};
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// A webhook as listed to the clients, which never get its secret back.
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookInfo {
+	pub id: Uuid,
+	pub url: String,
+	pub has_secret: bool,
+	pub events: Vec<WebhookEventFilter>,
+	pub max_retries: u32,
+}
+
+impl From<Webhook> for WebhookInfo {
+	fn from(
+		Webhook {
+			id,
+			url,
+			secret,
+			events,
+			max_retries,
+		}: Webhook,
+	) -> Self {
+		Self {
+			id,
+			url,
+			has_secret: secret.is_some(),
+			events,
+			max_retries,
+		}
+	}
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.config()
+					.await
+					.webhooks
+					.into_iter()
+					.map(WebhookInfo::from)
+					.collect::<Vec<_>>())
+			})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct CreateWebhookArgs {
+				pub url: String,
+				#[serde(default)]
+				pub secret: Option<String>,
+				pub events: Vec<WebhookEventFilter>,
+				#[serde(default)]
+				pub max_retries: Option<u32>,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 CreateWebhookArgs {
+				     url,
+				     secret,
+				     events,
+				     max_retries,
+				 }: CreateWebhookArgs| async move {
+					validate_url(&url)?;
+
+					let id = Uuid::new_v4();
+
+					library
+						.update_config(
+							|config| {
+								config.webhooks.push(Webhook {
+									id,
+									url,
+									secret,
+									events,
+									max_retries: max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+								})
+							},
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "webhooks.list");
+
+					Ok(id)
+				},
+			)
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct UpdateWebhookArgs {
+				pub id: Uuid,
+				pub url: String,
+				/// `null` removes the secret and a missing field leaves it untouched
+				#[serde(default)]
+				pub secret: MaybeUndefined<String>,
+				pub events: Vec<WebhookEventFilter>,
+				pub max_retries: u32,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), args: UpdateWebhookArgs| async move {
+					validate_url(&args.url)?;
+
+					if !library
+						.config()
+						.await
+						.webhooks
+						.iter()
+						.any(|existing| existing.id == args.id)
+					{
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Webhook '{}' not found", args.id),
+						));
+					}
+
+					library
+						.update_config(
+							|config| {
+								if let Some(existing) = config
+									.webhooks
+									.iter_mut()
+									.find(|existing| existing.id == args.id)
+								{
+									*existing = Webhook {
+										id: args.id,
+										url: args.url,
+										secret: Option::<Option<_>>::from(args.secret)
+											.unwrap_or_else(|| existing.secret.take()),
+										events: args.events,
+										max_retries: args.max_retries,
+									};
+								}
+							},
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "webhooks.list");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(node, library), id: Uuid| async move {
+					library
+						.update_config(
+							|config| config.webhooks.retain(|webhook| webhook.id != id),
+							node.libraries
+								.libraries_dir
+								.join(format!("{}.sdlibrary", library.id)),
+						)
+						.await?;
+
+					invalidate_query!(library, "webhooks.list");
+
+					Ok(())
+				})
+		})
+}
+
+fn validate_url(url: &str) -> Result<(), rspc::Error> {
+	match reqwest::Url::parse(url) {
+		Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+		_ => Err(rspc::Error::new(
+			ErrorCode::BadRequest,
+			format!("Invalid webhook URL '{url}'"),
+		)),
+	}
+}
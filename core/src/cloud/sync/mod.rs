@@ -42,7 +42,16 @@ pub async fn declare_actors(
 				let active = state.send_active.clone();
 				let active_notifier = state.notifier.clone();
 
-				move || send::run_actor(library_id, sync, node, active, active_notifier)
+				move || {
+					send::run_actor(
+						library_id,
+						sync,
+						node.background_policy.clone(),
+						node,
+						active,
+						active_notifier,
+					)
+				}
 			},
 			autorun,
 		)
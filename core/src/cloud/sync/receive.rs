@@ -1,4 +1,8 @@
-use crate::{library::Libraries, Node};
+use crate::{
+	library::{Libraries, WebhookEvent},
+	node::background_policy::Subsystem,
+	Node,
+};
 
 use sd_cloud_api::RequestConfigProvider;
 use sd_p2p::RemoteIdentity;
@@ -39,6 +43,10 @@ pub async fn run_actor(
 	active_notify: Arc<Notify>,
 ) {
 	loop {
+		node.background_policy
+			.wait_until_allowed(Subsystem::Sync)
+			.await;
+
 		active.store(true, Ordering::Relaxed);
 		active_notify.notify_waiters();
 
@@ -249,6 +257,15 @@ pub async fn upsert_instance(
 	node_id: Uuid,
 	metadata: HashMap<String, String>,
 ) -> prisma_client_rust::Result<()> {
+	let is_new = db
+		.instance()
+		.find_unique(instance::pub_id::equals(uuid_to_bytes(uuid)))
+		.exec()
+		.await?
+		.is_none();
+
+	let node_name = metadata.get("name").cloned();
+
 	db.instance()
 		.upsert(
 			instance::pub_id::equals(uuid_to_bytes(uuid)),
@@ -272,5 +289,17 @@ pub async fn upsert_instance(
 	// Called again so the new instances are picked up
 	libraries.update_instances_by_id(library_id).await;
 
+	if is_new {
+		if let Some(library) = libraries.get_library(&library_id).await {
+			library
+				.emit_webhook_event(WebhookEvent::DevicePaired {
+					instance_id: uuid,
+					identity,
+					node_name,
+				})
+				.await;
+		}
+	}
+
 	Ok(())
 }
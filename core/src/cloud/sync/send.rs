@@ -1,3 +1,5 @@
+use crate::node::background_policy::{BackgroundPolicy, Subsystem};
+
 use sd_core_sync::{SyncMessage, NTP64};
 
 use sd_cloud_api::RequestConfigProvider;
@@ -19,11 +21,14 @@ use super::{err_break, CompressedCRDTOperations};
 pub async fn run_actor(
 	library_id: Uuid,
 	sync: Arc<sd_core_sync::Manager>,
+	background_policy: Arc<BackgroundPolicy>,
 	cloud_api_config_provider: Arc<impl RequestConfigProvider>,
 	state: Arc<AtomicBool>,
 	state_notify: Arc<Notify>,
 ) {
 	loop {
+		background_policy.wait_until_allowed(Subsystem::Sync).await;
+
 		state.store(true, Ordering::Relaxed);
 		state_notify.notify_waiters();
 
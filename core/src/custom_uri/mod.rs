@@ -44,7 +44,12 @@ use tokio::{
 use tracing::{error, warn};
 use uuid::Uuid;
 
-use self::{serve_file::serve_file, utils::*};
+pub(crate) use self::serve_file::serve_file;
+
+use self::{
+	serve_file::{serve_file_with_cache, CachePolicy},
+	utils::*,
+};
 
 mod async_read_body;
 mod mpsc_to_async_write;
@@ -216,6 +221,13 @@ pub fn base_router() -> Router<LocalState> {
 					.then_some(())
 					.ok_or_else(|| not_found(()))?;
 
+					// Thumbnails are named after the `cas_id` of their files, so they never change
+					let cas_id = path
+						.file_stem()
+						.and_then(OsStr::to_str)
+						.ok_or_else(|| not_found(()))?
+						.to_string();
+
 					let file = File::open(&path).await.map_err(|err| {
 						InfallibleResponse::builder()
 							.status(if err.kind() == io::ErrorKind::NotFound {
@@ -225,13 +237,16 @@ pub fn base_router() -> Router<LocalState> {
 							})
 							.body(body::boxed(Full::from("")))
 					})?;
+					state.node.thumbnailer.record_access(path);
+
 					let metadata = file.metadata().await;
-					serve_file(
+					serve_file_with_cache(
 						file,
 						metadata,
 						request.into_parts().0,
 						InfallibleResponse::builder()
 							.header("Content-Type", HeaderValue::from_static("image/webp")),
+						CachePolicy::Immutable { etag: cas_id },
 					)
 					.await
 				},
@@ -408,7 +423,7 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 }
 
 // TODO: This should possibly be determined from magic bytes when the file is indexed and stored it in the DB on the file path
-async fn infer_the_mime_type(
+pub(crate) async fn infer_the_mime_type(
 	ext: &str,
 	file: &mut File,
 	metadata: &Metadata,
@@ -1,11 +1,15 @@
 use crate::util::InfallibleResponse;
 
-use std::{fs::Metadata, time::UNIX_EPOCH};
+use std::{
+	fs::Metadata,
+	time::{SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
 	body::{self, BoxBody, Full, StreamBody},
 	http::{header, request, HeaderValue, Method, Response, StatusCode},
 };
+use chrono::{DateTime, Utc};
 use http_range::HttpRange;
 use tokio::{
 	fs::File,
@@ -19,20 +23,104 @@ use super::{async_read_body::AsyncReadBody, utils::*};
 // default capacity 64KiB
 const DEFAULT_CAPACITY: usize = 65536;
 
+/// Format of the `Last-Modified` and `If-Modified-Since` headers
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// How clients can cache a served file.
+#[derive(Debug)]
+pub(crate) enum CachePolicy {
+	/// Clients revalidate the file on every use, with an ETag from its modification time
+	Revalidate,
+	/// The file never changes for the given ETag, like thumbnails named after the `cas_id` of the
+	/// files they were generated from, so clients can cache it forever
+	Immutable { etag: String },
+}
+
 /// Serve a Tokio file as a HTTP response.
 ///
 /// This function takes care of:
-///  - 304 Not Modified using ETag's
+///  - 304 Not Modified using ETag's and Last-Modified dates
 ///  - Range requests for partial content
 ///
 /// BE AWARE this function does not do any path traversal protection so that's up to the caller!
 pub(crate) async fn serve_file(
+	file: File,
+	metadata: io::Result<Metadata>,
+	req: request::Parts,
+	resp: InfallibleResponse,
+) -> Result<Response<BoxBody>, Response<BoxBody>> {
+	serve_file_with_cache(file, metadata, req, resp, CachePolicy::Revalidate).await
+}
+
+/// Same as [`serve_file`], also taking care of the `Cache-Control`, `Last-Modified` and
+/// `If-Modified-Since` headers for the given [`CachePolicy`].
+pub(crate) async fn serve_file_with_cache(
 	mut file: File,
 	metadata: io::Result<Metadata>,
 	req: request::Parts,
 	mut resp: InfallibleResponse,
+	cache: CachePolicy,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
 	if let Ok(metadata) = metadata {
+		let modified_at = metadata.modified().ok();
+
+		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag
+		let etag = match cache {
+			CachePolicy::Revalidate => modified_at.map(|time| {
+				// The ETag's can be any value so we just use the modified time to make it easy.
+				format!(
+					r#""{}""#,
+					time.duration_since(UNIX_EPOCH)
+						.expect("are you a time traveler? cause that's the only explanation for this error")
+						.as_millis()
+				)
+			}),
+			CachePolicy::Immutable { etag } => {
+				resp = resp.header(
+					header::CACHE_CONTROL,
+					HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+				);
+
+				Some(format!(r#""{etag}""#))
+			}
+		};
+
+		if let Some(etag) = &etag {
+			if let Ok(etag_header) = HeaderValue::from_str(etag) {
+				resp = resp.header(header::ETAG, etag_header);
+			} else {
+				error!("Failed to convert ETag into header value!");
+			}
+		}
+
+		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Last-Modified
+		let last_modified = modified_at.map(http_date);
+		if let Some(last_modified) = &last_modified {
+			if let Ok(last_modified_header) = HeaderValue::from_str(last_modified) {
+				resp = resp.header(header::LAST_MODIFIED, last_modified_header);
+			}
+		}
+
+		// Used for normal requests, `If-Modified-Since` is ignored when `If-None-Match` is sent
+		let not_modified = match req.headers.get(header::IF_NONE_MATCH) {
+			Some(if_none_match) => etag
+				.as_deref()
+				.is_some_and(|etag| etag_matches(if_none_match, etag)),
+			None => req
+				.headers
+				.get(header::IF_MODIFIED_SINCE)
+				.and_then(|since| since.to_str().ok())
+				.zip(modified_at)
+				.is_some_and(|(since, modified_at)| not_modified_since(since, modified_at)),
+		};
+
+		if not_modified {
+			return Ok(resp
+				.status(StatusCode::NOT_MODIFIED)
+				.body(body::boxed(Full::from(""))));
+		}
+
 		// We only accept range queries if `files.metadata() == Ok(_)`
 		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Accept-Ranges
 		resp = resp
@@ -51,45 +139,19 @@ pub(crate) async fn serve_file(
 				.body(body::boxed(Full::from(""))));
 		}
 
-		// ETag
-		let mut status_code = StatusCode::PARTIAL_CONTENT;
-		if let Ok(time) = metadata.modified() {
-			let etag_header =
-				format!(
-				r#""{}""#,
-				// The ETag's can be any value so we just use the modified time to make it easy.
-				time.duration_since(UNIX_EPOCH)
-					.expect("are you a time traveler? cause that's the only explanation for this error")
-					.as_millis()
-			);
-
-			// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag
-			if let Ok(etag_header) = HeaderValue::from_str(&etag_header) {
-				resp = resp.header("etag", etag_header);
-			} else {
-				error!("Failed to convert ETag into header value!");
-			}
-
-			// Used for normal requests
-			if let Some(etag) = req.headers.get("If-None-Match") {
-				if etag.as_bytes() == etag_header.as_bytes() {
-					return Ok(resp
-						.status(StatusCode::NOT_MODIFIED)
-						.body(body::boxed(Full::from(""))));
-				}
-			}
-
-			// Used checking if the resource has been modified since starting the download
-			if let Some(if_range) = req.headers.get("If-Range") {
-				// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-Range
-				if if_range.as_bytes() != etag_header.as_bytes() {
-					status_code = StatusCode::OK
-				}
-			}
-		};
+		// Used checking if the resource has been modified since starting the download, the
+		// whole file is sent if it was
+		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-Range
+		let range_is_current = req.headers.get(header::IF_RANGE).map_or(true, |if_range| {
+			etag.as_deref()
+				.is_some_and(|etag| if_range.as_bytes() == etag.as_bytes())
+				|| last_modified
+					.as_deref()
+					.is_some_and(|last_modified| if_range.as_bytes() == last_modified.as_bytes())
+		});
 
 		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Range_requests
-		if req.method == Method::GET {
+		if req.method == Method::GET && range_is_current {
 			if let Some(range) = req.headers.get("range") {
 				// TODO: Error handling
 				let ranges = HttpRange::parse(range.to_str().map_err(bad_request)?, metadata.len())
@@ -124,7 +186,7 @@ pub(crate) async fn serve_file(
 					.map_err(internal_server_error)?;
 
 				return Ok(resp
-					.status(status_code)
+					.status(StatusCode::PARTIAL_CONTENT)
 					.header(
 						"Content-Range",
 						HeaderValue::from_str(&format!(
@@ -151,3 +213,63 @@ pub(crate) async fn serve_file(
 
 	Ok(resp.body(body::boxed(StreamBody::new(ReaderStream::new(file)))))
 }
+
+fn http_date(time: SystemTime) -> String {
+	DateTime::<Utc>::from(time)
+		.format(HTTP_DATE_FORMAT)
+		.to_string()
+}
+
+/// Checks an `If-None-Match` header, which can hold a list of ETags, weak ones or `*`.
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+	let Ok(if_none_match) = if_none_match.to_str() else {
+		return false;
+	};
+
+	if_none_match.split(',').map(str::trim).any(|candidate| {
+		candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+	})
+}
+
+/// HTTP dates have a precision of seconds, so the file is modified if it's a second newer.
+fn not_modified_since(since: &str, modified_at: SystemTime) -> bool {
+	DateTime::parse_from_rfc2822(since)
+		.map(|since| since.timestamp() >= DateTime::<Utc>::from(modified_at).timestamp())
+		.unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::time::Duration;
+
+	#[test]
+	fn matches_etags() {
+		let etag = r#""abc""#;
+
+		assert!(etag_matches(&HeaderValue::from_static(r#""abc""#), etag));
+		assert!(etag_matches(
+			&HeaderValue::from_static(r#""x", W/"abc""#),
+			etag
+		));
+		assert!(etag_matches(&HeaderValue::from_static("*"), etag));
+		assert!(!etag_matches(&HeaderValue::from_static(r#""abcd""#), etag));
+	}
+
+	#[test]
+	fn compares_http_dates() {
+		let modified_at = UNIX_EPOCH + Duration::from_millis(784_111_777_500);
+
+		assert_eq!(http_date(modified_at), "Sun, 06 Nov 1994 08:49:37 GMT");
+		assert!(not_modified_since(
+			"Sun, 06 Nov 1994 08:49:37 GMT",
+			modified_at
+		));
+		assert!(!not_modified_since(
+			"Sun, 06 Nov 1994 08:49:36 GMT",
+			modified_at
+		));
+		assert!(!not_modified_since("yesterday", modified_at));
+	}
+}
@@ -0,0 +1,648 @@
+//! Read only GraphQL schema over the libraries of the node, served by the REST gateway.
+//!
+//! Searches take the same [`SearchFilterArgs`] as the app, as a JSON scalar. Queries are limited in
+//! depth and complexity, and the nested fields are batched with a [`DataLoader`] so a page of
+//! results doesn't run a query per row.
+
+use crate::{api::search::SearchFilterArgs, library::Library, Node};
+
+use sd_prisma::prisma::{file_path, location, object, tag, tag_on_object, SortOrder};
+use sd_utils::{db::size_in_bytes_from_db, from_bytes_to_uuid};
+
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+use async_graphql::{
+	async_trait,
+	dataloader::{DataLoader, Loader},
+	ComplexObject, Context, EmptyMutation, EmptySubscription, Error, Json, Object, Result, Schema,
+	SimpleObject,
+};
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+const MAX_TAKE: i32 = 100;
+/// Past this, `skip` gets slow on big libraries, filters should be used to narrow the results
+const MAX_SKIP: i32 = 10_000;
+const MAX_DEPTH: usize = 8;
+/// Every field costs 1, and a paginated list costs its fields times the page size
+const MAX_COMPLEXITY: usize = 10_000;
+
+pub(crate) type LibrarySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub(crate) fn schema(node: Arc<Node>) -> LibrarySchema {
+	Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+		.data(DataLoader::new(
+			LibraryLoader {
+				node: Arc::clone(&node),
+			},
+			tokio::spawn,
+		))
+		.data(node)
+		.limit_depth(MAX_DEPTH)
+		.limit_complexity(MAX_COMPLEXITY)
+		.finish()
+}
+
+async fn get_library(ctx: &Context<'_>, library_id: Uuid) -> Result<Arc<Library>> {
+	ctx.data::<Arc<Node>>()?
+		.libraries
+		.get_library(&library_id)
+		.await
+		.ok_or_else(|| Error::new(format!("Library '{library_id}' not found")))
+}
+
+fn pagination(take: Option<i32>, skip: Option<i32>) -> Result<(i64, i64)> {
+	let skip = skip.unwrap_or(0).max(0);
+	if skip > MAX_SKIP {
+		return Err(Error::new(format!(
+			"skip can't be over {MAX_SKIP}, use filters to narrow the results instead"
+		)));
+	}
+
+	Ok((
+		take.unwrap_or(MAX_TAKE).clamp(0, MAX_TAKE) as i64,
+		skip as i64,
+	))
+}
+
+fn page_complexity(take: Option<i32>, child_complexity: usize) -> usize {
+	take.unwrap_or(MAX_TAKE).clamp(0, MAX_TAKE) as usize * child_complexity
+}
+
+fn loader<'a>(ctx: &'a Context<'_>) -> Result<&'a DataLoader<LibraryLoader>> {
+	ctx.data::<DataLoader<LibraryLoader>>()
+}
+
+/// Batches the nested fields of the nodes, the keys being the library and the id of the parent.
+pub(crate) struct LibraryLoader {
+	node: Arc<Node>,
+}
+
+impl LibraryLoader {
+	/// Groups the keys by library, so each library is queried once per batch.
+	async fn by_library(
+		&self,
+		keys: impl IntoIterator<Item = (Uuid, i32)>,
+	) -> Result<Vec<(Arc<Library>, Vec<i32>)>> {
+		let mut grouped = HashMap::<_, Vec<_>>::new();
+		for (library_id, id) in keys {
+			grouped.entry(library_id).or_default().push(id);
+		}
+
+		let mut libraries = Vec::with_capacity(grouped.len());
+		for (library_id, keys) in grouped {
+			let library = self
+				.node
+				.libraries
+				.get_library(&library_id)
+				.await
+				.ok_or_else(|| Error::new(format!("Library '{library_id}' not found")))?;
+
+			libraries.push((library, keys));
+		}
+
+		Ok(libraries)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct LocationById(Uuid, i32);
+
+#[async_trait::async_trait]
+impl Loader<LocationById> for LibraryLoader {
+	type Value = location::Data;
+	type Error = Error;
+
+	async fn load(
+		&self,
+		keys: &[LocationById],
+	) -> Result<HashMap<LocationById, Self::Value>, Self::Error> {
+		let mut values = HashMap::with_capacity(keys.len());
+
+		for (library, keys) in self
+			.by_library(
+				keys.iter()
+					.map(|LocationById(library_id, id)| (*library_id, *id)),
+			)
+			.await?
+		{
+			let library_id = library.id;
+			values.extend(
+				library
+					.db
+					.location()
+					.find_many(vec![location::id::in_vec(keys)])
+					.exec()
+					.await?
+					.into_iter()
+					.map(|data| (LocationById(library_id, data.id), data)),
+			);
+		}
+
+		Ok(values)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct ObjectById(Uuid, i32);
+
+#[async_trait::async_trait]
+impl Loader<ObjectById> for LibraryLoader {
+	type Value = object::Data;
+	type Error = Error;
+
+	async fn load(
+		&self,
+		keys: &[ObjectById],
+	) -> Result<HashMap<ObjectById, Self::Value>, Self::Error> {
+		let mut values = HashMap::with_capacity(keys.len());
+
+		for (library, keys) in self
+			.by_library(
+				keys.iter()
+					.map(|ObjectById(library_id, id)| (*library_id, *id)),
+			)
+			.await?
+		{
+			let library_id = library.id;
+			values.extend(
+				library
+					.db
+					.object()
+					.find_many(vec![object::id::in_vec(keys)])
+					.exec()
+					.await?
+					.into_iter()
+					.map(|data| (ObjectById(library_id, data.id), data)),
+			);
+		}
+
+		Ok(values)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct FilePathsOfObject(Uuid, i32);
+
+#[async_trait::async_trait]
+impl Loader<FilePathsOfObject> for LibraryLoader {
+	type Value = Vec<file_path::Data>;
+	type Error = Error;
+
+	async fn load(
+		&self,
+		keys: &[FilePathsOfObject],
+	) -> Result<HashMap<FilePathsOfObject, Self::Value>, Self::Error> {
+		let mut values = HashMap::<_, Vec<_>>::with_capacity(keys.len());
+
+		for (library, keys) in self
+			.by_library(
+				keys.iter()
+					.map(|FilePathsOfObject(library_id, id)| (*library_id, *id)),
+			)
+			.await?
+		{
+			let library_id = library.id;
+			for data in library
+				.db
+				.file_path()
+				.find_many(vec![file_path::object_id::in_vec(keys)])
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.exec()
+				.await?
+			{
+				if let Some(object_id) = data.object_id {
+					values
+						.entry(FilePathsOfObject(library_id, object_id))
+						.or_default()
+						.push(data);
+				}
+			}
+		}
+
+		Ok(values)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct TagsOfObject(Uuid, i32);
+
+#[async_trait::async_trait]
+impl Loader<TagsOfObject> for LibraryLoader {
+	type Value = Vec<tag::Data>;
+	type Error = Error;
+
+	async fn load(
+		&self,
+		keys: &[TagsOfObject],
+	) -> Result<HashMap<TagsOfObject, Self::Value>, Self::Error> {
+		let mut values = HashMap::<_, Vec<_>>::with_capacity(keys.len());
+
+		for (library, keys) in self
+			.by_library(
+				keys.iter()
+					.map(|TagsOfObject(library_id, id)| (*library_id, *id)),
+			)
+			.await?
+		{
+			let library_id = library.id;
+			let links = library
+				.db
+				.tag_on_object()
+				.find_many(vec![tag_on_object::object_id::in_vec(keys)])
+				.select(tag_on_object::select!({ object_id tag_id }))
+				.exec()
+				.await?;
+
+			let tags = library
+				.db
+				.tag()
+				.find_many(vec![tag::id::in_vec(
+					links
+						.iter()
+						.map(|link| link.tag_id)
+						.collect::<HashSet<_>>()
+						.into_iter()
+						.collect(),
+				)])
+				.exec()
+				.await?
+				.into_iter()
+				.map(|data| (data.id, data))
+				.collect::<HashMap<_, _>>();
+
+			for link in links {
+				if let Some(tag) = tags.get(&link.tag_id) {
+					values
+						.entry(TagsOfObject(library_id, link.object_id))
+						.or_default()
+						.push(tag.clone());
+				}
+			}
+		}
+
+		Ok(values)
+	}
+}
+
+pub(crate) struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+	async fn libraries(&self, ctx: &Context<'_>) -> Result<Vec<LibraryNode>> {
+		let mut libraries = vec![];
+
+		for library in ctx.data::<Arc<Node>>()?.libraries.get_all().await {
+			libraries.push(LibraryNode {
+				id: library.id,
+				name: library.config().await.name.to_string(),
+			});
+		}
+
+		Ok(libraries)
+	}
+
+	async fn locations(&self, ctx: &Context<'_>, library_id: Uuid) -> Result<Vec<LocationNode>> {
+		let library = get_library(ctx, library_id).await?;
+
+		Ok(library
+			.db
+			.location()
+			.find_many(vec![])
+			.order_by(location::date_created::order(SortOrder::Desc))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| LocationNode::new(&library, data))
+			.collect())
+	}
+
+	async fn tags(&self, ctx: &Context<'_>, library_id: Uuid) -> Result<Vec<TagNode>> {
+		let library = get_library(ctx, library_id).await?;
+
+		Ok(library
+			.db
+			.tag()
+			.find_many(vec![])
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| TagNode::new(&library, data))
+			.collect())
+	}
+
+	#[graphql(complexity = "page_complexity(take, child_complexity)")]
+	async fn file_paths(
+		&self,
+		ctx: &Context<'_>,
+		library_id: Uuid,
+		filters: Option<Json<Vec<SearchFilterArgs>>>,
+		take: Option<i32>,
+		skip: Option<i32>,
+	) -> Result<Vec<FilePathNode>> {
+		let library = get_library(ctx, library_id).await?;
+		let (take, skip) = pagination(take, skip)?;
+
+		let mut params = vec![];
+		for filter in filters.map(|Json(filters)| filters).unwrap_or_default() {
+			params.extend(
+				filter
+					.into_file_path_params(&library.db)
+					.await
+					.map_err(|e| Error::new(e.to_string()))?,
+			);
+		}
+
+		Ok(library
+			.db
+			.file_path()
+			.find_many(params)
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(take)
+			.skip(skip)
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| FilePathNode::new(&library, data))
+			.collect())
+	}
+
+	#[graphql(complexity = "page_complexity(take, child_complexity)")]
+	async fn objects(
+		&self,
+		ctx: &Context<'_>,
+		library_id: Uuid,
+		filters: Option<Json<Vec<SearchFilterArgs>>>,
+		take: Option<i32>,
+		skip: Option<i32>,
+	) -> Result<Vec<ObjectNode>> {
+		let library = get_library(ctx, library_id).await?;
+		let (take, skip) = pagination(take, skip)?;
+
+		let mut params = vec![];
+		for filter in filters.map(|Json(filters)| filters).unwrap_or_default() {
+			params.extend(
+				filter
+					.into_object_params(&library.db)
+					.await
+					.map_err(|e| Error::new(e.to_string()))?,
+			);
+		}
+
+		Ok(library
+			.db
+			.object()
+			.find_many(params)
+			.order_by(object::id::order(SortOrder::Asc))
+			.take(take)
+			.skip(skip)
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| ObjectNode::new(&library, data))
+			.collect())
+	}
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Library")]
+pub(crate) struct LibraryNode {
+	id: Uuid,
+	name: String,
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Location", complex)]
+pub(crate) struct LocationNode {
+	#[graphql(skip)]
+	library: Arc<Library>,
+	id: i32,
+	pub_id: Uuid,
+	name: Option<String>,
+	path: Option<String>,
+	is_archived: Option<bool>,
+	hidden: Option<bool>,
+	date_created: Option<DateTime<FixedOffset>>,
+}
+
+impl LocationNode {
+	fn new(library: &Arc<Library>, data: location::Data) -> Self {
+		Self {
+			library: Arc::clone(library),
+			id: data.id,
+			pub_id: from_bytes_to_uuid(&data.pub_id),
+			name: data.name,
+			path: data.path,
+			is_archived: data.is_archived,
+			hidden: data.hidden,
+			date_created: data.date_created,
+		}
+	}
+}
+
+#[ComplexObject]
+impl LocationNode {
+	#[graphql(complexity = "page_complexity(take, child_complexity)")]
+	async fn file_paths(&self, take: Option<i32>, skip: Option<i32>) -> Result<Vec<FilePathNode>> {
+		let (take, skip) = pagination(take, skip)?;
+
+		Ok(self
+			.library
+			.db
+			.file_path()
+			.find_many(vec![file_path::location_id::equals(Some(self.id))])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(take)
+			.skip(skip)
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| FilePathNode::new(&self.library, data))
+			.collect())
+	}
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "FilePath", complex)]
+pub(crate) struct FilePathNode {
+	#[graphql(skip)]
+	library: Arc<Library>,
+	id: i32,
+	pub_id: Uuid,
+	is_dir: Option<bool>,
+	cas_id: Option<String>,
+	location_id: Option<i32>,
+	object_id: Option<i32>,
+	materialized_path: Option<String>,
+	name: Option<String>,
+	extension: Option<String>,
+	hidden: Option<bool>,
+	/// As a string, GraphQL integers are only 32 bits
+	size_in_bytes: Option<String>,
+	date_created: Option<DateTime<FixedOffset>>,
+	date_modified: Option<DateTime<FixedOffset>>,
+	date_indexed: Option<DateTime<FixedOffset>>,
+}
+
+impl FilePathNode {
+	fn new(library: &Arc<Library>, data: file_path::Data) -> Self {
+		Self {
+			library: Arc::clone(library),
+			id: data.id,
+			pub_id: from_bytes_to_uuid(&data.pub_id),
+			is_dir: data.is_dir,
+			cas_id: data.cas_id,
+			location_id: data.location_id,
+			object_id: data.object_id,
+			materialized_path: data.materialized_path,
+			name: data.name,
+			extension: data.extension,
+			hidden: data.hidden,
+			size_in_bytes: data
+				.size_in_bytes_bytes
+				.as_deref()
+				.map(|size| size_in_bytes_from_db(size).to_string()),
+			date_created: data.date_created,
+			date_modified: data.date_modified,
+			date_indexed: data.date_indexed,
+		}
+	}
+}
+
+#[ComplexObject]
+impl FilePathNode {
+	async fn location(&self, ctx: &Context<'_>) -> Result<Option<LocationNode>> {
+		let Some(location_id) = self.location_id else {
+			return Ok(None);
+		};
+
+		Ok(loader(ctx)?
+			.load_one(LocationById(self.library.id, location_id))
+			.await?
+			.map(|data| LocationNode::new(&self.library, data)))
+	}
+
+	async fn object(&self, ctx: &Context<'_>) -> Result<Option<ObjectNode>> {
+		let Some(object_id) = self.object_id else {
+			return Ok(None);
+		};
+
+		Ok(loader(ctx)?
+			.load_one(ObjectById(self.library.id, object_id))
+			.await?
+			.map(|data| ObjectNode::new(&self.library, data)))
+	}
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Object", complex)]
+pub(crate) struct ObjectNode {
+	#[graphql(skip)]
+	library: Arc<Library>,
+	id: i32,
+	pub_id: Uuid,
+	/// See `ObjectKind` for the possible values
+	kind: Option<i32>,
+	favorite: Option<bool>,
+	hidden: Option<bool>,
+	important: Option<bool>,
+	note: Option<String>,
+	date_created: Option<DateTime<FixedOffset>>,
+	date_accessed: Option<DateTime<FixedOffset>>,
+}
+
+impl ObjectNode {
+	fn new(library: &Arc<Library>, data: object::Data) -> Self {
+		Self {
+			library: Arc::clone(library),
+			id: data.id,
+			pub_id: from_bytes_to_uuid(&data.pub_id),
+			kind: data.kind,
+			favorite: data.favorite,
+			hidden: data.hidden,
+			important: data.important,
+			note: data.note,
+			date_created: data.date_created,
+			date_accessed: data.date_accessed,
+		}
+	}
+}
+
+#[ComplexObject]
+impl ObjectNode {
+	async fn file_paths(&self, ctx: &Context<'_>) -> Result<Vec<FilePathNode>> {
+		Ok(loader(ctx)?
+			.load_one(FilePathsOfObject(self.library.id, self.id))
+			.await?
+			.unwrap_or_default()
+			.into_iter()
+			.map(|data| FilePathNode::new(&self.library, data))
+			.collect())
+	}
+
+	async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<TagNode>> {
+		Ok(loader(ctx)?
+			.load_one(TagsOfObject(self.library.id, self.id))
+			.await?
+			.unwrap_or_default()
+			.into_iter()
+			.map(|data| TagNode::new(&self.library, data))
+			.collect())
+	}
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Tag", complex)]
+pub(crate) struct TagNode {
+	#[graphql(skip)]
+	library: Arc<Library>,
+	id: i32,
+	pub_id: Uuid,
+	name: Option<String>,
+	color: Option<String>,
+	is_hidden: Option<bool>,
+	date_created: Option<DateTime<FixedOffset>>,
+	date_modified: Option<DateTime<FixedOffset>>,
+}
+
+impl TagNode {
+	fn new(library: &Arc<Library>, data: tag::Data) -> Self {
+		Self {
+			library: Arc::clone(library),
+			id: data.id,
+			pub_id: from_bytes_to_uuid(&data.pub_id),
+			name: data.name,
+			color: data.color,
+			is_hidden: data.is_hidden,
+			date_created: data.date_created,
+			date_modified: data.date_modified,
+		}
+	}
+}
+
+#[ComplexObject]
+impl TagNode {
+	#[graphql(complexity = "page_complexity(take, child_complexity)")]
+	async fn objects(&self, take: Option<i32>, skip: Option<i32>) -> Result<Vec<ObjectNode>> {
+		let (take, skip) = pagination(take, skip)?;
+
+		Ok(self
+			.library
+			.db
+			.object()
+			.find_many(vec![object::tags::some(vec![
+				tag_on_object::tag_id::equals(self.id),
+			])])
+			.order_by(object::id::order(SortOrder::Asc))
+			.take(take)
+			.skip(skip)
+			.exec()
+			.await?
+			.into_iter()
+			.map(|data| ObjectNode::new(&self.library, data))
+			.collect())
+	}
+}
@@ -2,6 +2,7 @@
 
 use crate::{
 	api::{CoreEvent, Router},
+	invalidate_query,
 	location::LocationManagerError,
 	object::media::old_thumbnail::old_actor::OldThumbnailer,
 };
@@ -36,17 +37,26 @@ mod cloud;
 pub(crate) mod crypto;
 pub mod custom_uri;
 mod env;
+#[cfg(feature = "graphql")]
+pub(crate) mod graphql;
 pub mod library;
 pub(crate) mod location;
+pub mod metrics;
 pub(crate) mod node;
 pub(crate) mod notifications;
 pub(crate) mod object;
 pub(crate) mod old_job;
 pub(crate) mod p2p;
+#[cfg(feature = "plugins")]
+pub mod plugins;
 pub(crate) mod preferences;
+pub mod rest_api;
+pub(crate) mod telemetry;
 #[doc(hidden)] // TODO(@Oscar): Make this private when breaking out `utils` into `sd-utils`
 pub mod util;
+pub mod virtual_fs;
 pub(crate) mod volume;
+pub mod webdav;
 
 pub use env::Env;
 
@@ -64,12 +74,16 @@ pub struct Node {
 	pub event_bus: (broadcast::Sender<CoreEvent>, broadcast::Receiver<CoreEvent>),
 	pub notifications: Notifications,
 	pub thumbnailer: OldThumbnailer,
+	pub background_policy: Arc<node::background_policy::BackgroundPolicy>,
+	pub api_token_limiter: node::api_tokens::ApiTokenLimiter,
 	pub files_over_p2p_flag: Arc<AtomicBool>,
 	pub cloud_sync_flag: Arc<AtomicBool>,
 	pub env: Arc<env::Env>,
 	pub http: reqwest::Client,
 	#[cfg(feature = "ai")]
 	pub old_image_labeller: Option<OldImageLabeler>,
+	#[cfg(feature = "plugins")]
+	pub plugins: Arc<plugins::Plugins>,
 }
 
 impl fmt::Debug for Node {
@@ -112,13 +126,24 @@ impl Node {
 			config.get().await.image_labeler_version
 		};
 
+		#[cfg(feature = "telemetry")]
+		telemetry::watch_preferences(config.preferences_watcher());
+
+		let background_policy =
+			node::background_policy::BackgroundPolicy::new(config.preferences_watcher());
+
 		let (locations, locations_actor) = location::Locations::new();
-		let (old_jobs, jobs_actor) = old_job::OldJobs::new();
+		let (old_jobs, jobs_actor) =
+			old_job::OldJobs::new(config.preferences_watcher(), background_policy.subscribe());
 		let libraries = library::Libraries::new(data_dir.join("libraries")).await?;
 
 		let (p2p, start_p2p) = p2p::P2PManager::new(config.clone(), libraries.clone())
 			.await
 			.map_err(NodeError::P2PManager)?;
+		#[cfg(feature = "plugins")]
+		let plugins =
+			plugins::Plugins::new(data_dir.join("plugins"), config.preferences_watcher()).await;
+
 		let node = Arc::new(Node {
 			data_dir: data_dir.to_path_buf(),
 			old_jobs,
@@ -130,8 +155,11 @@ impl Node {
 				libraries.clone(),
 				event_bus.0.clone(),
 				config.preferences_watcher(),
+				background_policy.subscribe(),
 			)
 			.await,
+			background_policy,
+			api_token_limiter: Default::default(),
 			config,
 			event_bus,
 			libraries,
@@ -149,6 +177,8 @@ impl Node {
 				error!("Failed to initialize image labeller. AI features will be disabled: {e:#?}");
 			})
 			.ok(),
+			#[cfg(feature = "plugins")]
+			plugins,
 		});
 
 		// Restore backend feature flags
@@ -168,6 +198,16 @@ impl Node {
 		locations_actor.start(node.clone());
 		node.libraries.init(&node).await?;
 		jobs_actor.start(node.clone());
+
+		tokio::spawn({
+			let node = node.clone();
+			async move {
+				let mut background_policy_rx = node.background_policy.subscribe();
+				while background_policy_rx.changed().await.is_ok() {
+					invalidate_query!(node; node, "nodes.backgroundPolicy");
+				}
+			}
+		});
 		start_p2p(
 			node.clone(),
 			axum::Router::new()
@@ -218,7 +258,13 @@ impl Node {
 			);
 		}
 
-		tracing_subscriber::registry()
+		let registry = tracing_subscriber::registry();
+
+		// Must be the first layer, as it's reloaded when the telemetry preferences change
+		#[cfg(feature = "telemetry")]
+		let registry = registry.with(telemetry::layer());
+
+		registry
 			.with(
 				tracing_subscriber::fmt::layer()
 					.with_file(true)
@@ -256,6 +302,7 @@ impl Node {
 		self.thumbnailer.shutdown().await;
 		self.old_jobs.shutdown().await;
 		self.p2p.shutdown().await;
+		self.libraries.store_name_indexes().await;
 		#[cfg(feature = "ai")]
 		if let Some(image_labeller) = &self.old_image_labeller {
 			image_labeller.shutdown().await;
@@ -351,7 +398,7 @@ pub enum NodeError {
 	#[error("failed to initialize location manager: {0}")]
 	LocationManager(#[from] LocationManagerError),
 	#[error("failed to initialize p2p manager: {0}")]
-	P2PManager(String),
+	P2PManager(sd_p2p::QuicTransportError),
 	#[error("invalid platform integer: {0}")]
 	InvalidPlatformInt(u8),
 	#[cfg(debug_assertions)]
@@ -1,5 +1,15 @@
 use crate::{
+	api::search::history::SearchHistorySettings,
+	location::{
+		ingest::{IngestInbox, IngestSettings},
+		mirror::LocationMirror,
+	},
 	node::config::NodeConfig,
+	object::{
+		fs::trash::TrashSettings, media::old_thumbnail::preferences::ThumbnailSettings,
+		validation::ChecksumSettings,
+	},
+	old_job::JobNotificationSettings,
 	util::version_manager::{Kind, ManagedVersion, VersionManager, VersionManagerError},
 };
 
@@ -24,7 +34,7 @@ use tokio::fs;
 use tracing::error;
 use uuid::Uuid;
 
-use super::name::LibraryName;
+use super::{name::LibraryName, DatabaseSettings, ScriptHook, Webhook};
 
 /// LibraryConfig holds the configuration for a specific library. This is stored as a '{uuid}.sdlibrary' file.
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
@@ -43,6 +53,39 @@ pub struct LibraryConfig {
 	// true = sync is enabled as either the library is new or it has been manually toggled on
 	#[serde(default)]
 	pub generate_sync_operations: Arc<AtomicBool>,
+	/// Which finished jobs should notify the user, and how
+	#[serde(default)]
+	pub job_notifications: JobNotificationSettings,
+	/// Webhooks notified about events of the library
+	#[serde(default)]
+	pub webhooks: Vec<Webhook>,
+	/// Shell commands run by the node on events of the library
+	#[serde(default)]
+	pub hooks: Vec<ScriptHook>,
+	/// Locations mirrored to or from rclone remotes
+	#[serde(default)]
+	pub mirrors: Vec<LocationMirror>,
+	/// Folder whose files are moved into a location and indexed as soon as they appear
+	#[serde(default)]
+	pub ingest_inbox: Option<IngestInbox>,
+	/// How big the files ingested into the locations can be
+	#[serde(default)]
+	pub ingest: IngestSettings,
+	/// How long the files deleted from the locations stay in their trash
+	#[serde(default)]
+	pub trash: TrashSettings,
+	/// SQLite settings of the database of the library
+	#[serde(default)]
+	pub database: DatabaseSettings,
+	/// Whether the searches run in the library are recorded to suggest them again
+	#[serde(default)]
+	pub search_history: SearchHistorySettings,
+	/// How the thumbnails of the library's files are generated
+	#[serde(default)]
+	pub thumbnails: ThumbnailSettings,
+	/// Whether the files are checksummed in the background, after being identified by their cas id
+	#[serde(default)]
+	pub checksums: ChecksumSettings,
 	version: LibraryConfigVersion,
 }
 
@@ -97,6 +140,17 @@ impl LibraryConfig {
 			cloud_id: None,
 			// will always be `true` eventually
 			generate_sync_operations: Arc::new(AtomicBool::new(generate_sync_operations)),
+			job_notifications: JobNotificationSettings::default(),
+			webhooks: vec![],
+			hooks: vec![],
+			mirrors: vec![],
+			ingest_inbox: None,
+			ingest: IngestSettings::default(),
+			trash: TrashSettings::default(),
+			database: DatabaseSettings::default(),
+			search_history: SearchHistorySettings::default(),
+			thumbnails: ThumbnailSettings::default(),
+			checksums: ChecksumSettings::default(),
 		};
 
 		this.save(path).await.map(|()| this)
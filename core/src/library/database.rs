@@ -0,0 +1,201 @@
+//! SQLite settings of the database of a library, stored in its config and applied whenever the
+//! library is loaded or they change.
+
+use crate::Node;
+
+use sd_prisma::prisma::PrismaClient;
+
+use std::{sync::Arc, time::Duration};
+
+use prisma_client_rust::{raw, QueryError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+use thiserror::Error;
+use tokio::time;
+use tracing::warn;
+use uuid::Uuid;
+
+/// How often the statistics of the tables are gathered again, for the query planner and the
+/// approximate counts of the searches
+const ANALYZE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Rows of each index read by `ANALYZE`, so it stays quick on libraries with millions of files
+const ANALYSIS_LIMIT: u32 = 1000;
+
+const MAX_CACHE_SIZE_MIB: u32 = 4 * 1024;
+const MAX_MMAP_SIZE_MIB: u32 = 64 * 1024;
+const MAX_BUSY_TIMEOUT_MS: u32 = 5 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseSettings {
+	/// Write-ahead logging, so the explorer can read while the indexer writes
+	pub wal: bool,
+	/// Pages written to the WAL before it's checkpointed back into the database, 0 to only
+	/// checkpoint when the library is closed
+	pub wal_autocheckpoint: u32,
+	/// Memory for the page cache of SQLite
+	pub cache_size_mib: u32,
+	/// Part of the database file mapped into memory, 0 to read it with regular I/O
+	pub mmap_size_mib: u32,
+	/// How long a query waits for the database to be unlocked before failing
+	pub busy_timeout_ms: u32,
+}
+
+impl Default for DatabaseSettings {
+	fn default() -> Self {
+		Self {
+			wal: true,
+			wal_autocheckpoint: 1000,
+			cache_size_mib: 16,
+			// Memory mapping misbehaves with libraries stored on network drives
+			mmap_size_mib: 0,
+			busy_timeout_ms: 15_000,
+		}
+	}
+}
+
+impl DatabaseSettings {
+	/// For libraries with millions of file paths, on a local drive of a machine with a few GiB of
+	/// memory to spare.
+	///
+	/// The bigger cache and memory mapping keep the indexes of the file paths in memory, which is
+	/// what searches of big libraries spend their time reading, and the rarer checkpoints stop the
+	/// indexer from stalling on each of them.
+	pub fn large_library() -> Self {
+		Self {
+			wal: true,
+			wal_autocheckpoint: 10_000,
+			cache_size_mib: 256,
+			mmap_size_mib: 1024,
+			busy_timeout_ms: 30_000,
+		}
+	}
+
+	pub fn validate(&self) -> Result<(), DatabaseSettingsError> {
+		if self.cache_size_mib > MAX_CACHE_SIZE_MIB {
+			return Err(DatabaseSettingsError::OutOfRange {
+				setting: "cacheSizeMib",
+				max: MAX_CACHE_SIZE_MIB,
+			});
+		}
+
+		if self.mmap_size_mib > MAX_MMAP_SIZE_MIB {
+			return Err(DatabaseSettingsError::OutOfRange {
+				setting: "mmapSizeMib",
+				max: MAX_MMAP_SIZE_MIB,
+			});
+		}
+
+		if self.busy_timeout_ms > MAX_BUSY_TIMEOUT_MS {
+			return Err(DatabaseSettingsError::OutOfRange {
+				setting: "busyTimeoutMs",
+				max: MAX_BUSY_TIMEOUT_MS,
+			});
+		}
+
+		Ok(())
+	}
+
+	/// The pragmas setting these settings, the library has a single connection so they apply to
+	/// every query.
+	fn pragmas(&self) -> Vec<String> {
+		vec![
+			format!(
+				"PRAGMA journal_mode = {};",
+				if self.wal { "WAL" } else { "DELETE" }
+			),
+			format!("PRAGMA wal_autocheckpoint = {};", self.wal_autocheckpoint),
+			// Negative sizes are in KiB instead of pages
+			format!(
+				"PRAGMA cache_size = -{};",
+				u64::from(self.cache_size_mib) * 1024
+			),
+			format!(
+				"PRAGMA mmap_size = {};",
+				u64::from(self.mmap_size_mib) * 1024 * 1024
+			),
+			format!("PRAGMA busy_timeout = {};", self.busy_timeout_ms),
+		]
+	}
+
+	pub(crate) async fn apply(&self, db: &PrismaClient) -> Result<(), QueryError> {
+		for pragma in self.pragmas() {
+			// Some pragmas return the new value, so they are run as queries
+			db._query_raw::<Value>(raw!(&pragma)).exec().await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Gathers the statistics of the tables of the library, into `sqlite_stat1`.
+pub(crate) async fn analyze(db: &PrismaClient) -> Result<(), QueryError> {
+	db._query_raw::<Value>(raw!(&format!("PRAGMA analysis_limit = {ANALYSIS_LIMIT};")))
+		.exec()
+		.await?;
+	db._execute_raw(raw!("ANALYZE;")).exec().await?;
+
+	Ok(())
+}
+
+/// Gathers the statistics of the tables of the library periodically, until the library is
+/// unloaded.
+pub(crate) async fn analyze_periodically(node: Arc<Node>, library_id: Uuid) {
+	let mut interval = time::interval(ANALYZE_INTERVAL);
+
+	loop {
+		interval.tick().await;
+
+		let Some(library) = node.libraries.get_library(&library_id).await else {
+			break;
+		};
+
+		if let Err(e) = analyze(&library.db).await {
+			warn!("Failed to analyze the database of library <id='{library_id}'>: {e:#?}");
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum DatabaseSettingsError {
+	#[error("'{setting}' can't be more than {max}")]
+	OutOfRange { setting: &'static str, max: u32 },
+}
+
+impl From<DatabaseSettingsError> for rspc::Error {
+	fn from(e: DatabaseSettingsError) -> Self {
+		Self::with_cause(rspc::ErrorCode::BadRequest, e.to_string(), e)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn builds_pragmas() {
+		assert_eq!(
+			DatabaseSettings::large_library().pragmas(),
+			[
+				"PRAGMA journal_mode = WAL;",
+				"PRAGMA wal_autocheckpoint = 10000;",
+				"PRAGMA cache_size = -262144;",
+				"PRAGMA mmap_size = 1073741824;",
+				"PRAGMA busy_timeout = 30000;",
+			]
+		);
+	}
+
+	#[test]
+	fn rejects_out_of_range_settings() {
+		assert!(DatabaseSettings::default().validate().is_ok());
+		assert!(DatabaseSettings::large_library().validate().is_ok());
+		assert!(DatabaseSettings {
+			mmap_size_mib: MAX_MMAP_SIZE_MIB + 1,
+			..Default::default()
+		}
+		.validate()
+		.is_err());
+	}
+}
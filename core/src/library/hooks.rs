@@ -0,0 +1,411 @@
+use std::{
+	collections::VecDeque,
+	path::PathBuf,
+	process::Stdio,
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{
+	fs,
+	io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt},
+	process::Command,
+	sync::{watch, Semaphore},
+	time::timeout,
+};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::node::config::NodePreferences;
+
+use super::{webhooks, WebhookEvent, WebhookEventFilter, WebhookPayload};
+
+/// Invocations kept in memory for each library, the oldest ones are dropped first
+const MAX_INVOCATIONS: usize = 200;
+/// Output kept from each stream of an invocation, the rest is discarded
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+/// Hooks of the same library running at the same time, the others wait for their turn
+const MAX_CONCURRENT_HOOKS: usize = 4;
+
+/// Environment variables passed to the hooks, every other one is cleared
+#[cfg(unix)]
+const INHERITED_VARS: &[&str] = &["PATH", "HOME", "LANG", "TMPDIR"];
+#[cfg(windows)]
+const INHERITED_VARS: &[&str] = &[
+	"PATH",
+	"PATHEXT",
+	"SYSTEMROOT",
+	"COMSPEC",
+	"USERPROFILE",
+	"TEMP",
+	"TMP",
+];
+
+/// A shell command run by the node when events of the library happen, stored in the library
+/// config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptHook {
+	pub id: Uuid,
+	pub name: String,
+	/// Run with `sh -c`, or `cmd /C` on Windows, receiving the same JSON body as webhooks on stdin
+	pub command: String,
+	/// Events running this hook, matched like the ones of webhooks
+	pub events: Vec<WebhookEventFilter>,
+	/// The hook is killed if it runs for longer
+	#[serde(default = "default_timeout_secs")]
+	pub timeout_secs: u32,
+}
+
+fn default_timeout_secs() -> u32 {
+	60
+}
+
+/// Hooks run arbitrary commands as the user running the node, so they have to be turned on for
+/// the node before any of them can be created or run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+pub struct ScriptHookPreferences {
+	#[serde(default)]
+	pub enabled: bool,
+}
+
+/// Log of a single run of a hook.
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct HookInvocation {
+	pub id: Uuid,
+	pub hook_id: Uuid,
+	pub event: String,
+	pub started_at: DateTime<Utc>,
+	pub duration_ms: u32,
+	/// Missing if the hook didn't start, timed out or was killed by a signal
+	pub exit_code: Option<i32>,
+	pub timed_out: bool,
+	/// Why the hook couldn't be started
+	pub error: Option<String>,
+	pub stdout: String,
+	pub stderr: String,
+}
+
+/// Runs the script hooks of a library, keeping the logs of their last invocations.
+#[derive(Debug)]
+pub struct HookRunner {
+	invocations: Mutex<VecDeque<HookInvocation>>,
+	permits: Semaphore,
+	node_preferences: watch::Receiver<NodePreferences>,
+}
+
+impl HookRunner {
+	pub fn new(node_preferences: watch::Receiver<NodePreferences>) -> Self {
+		Self {
+			invocations: Mutex::default(),
+			permits: Semaphore::new(MAX_CONCURRENT_HOOKS),
+			node_preferences,
+		}
+	}
+
+	/// Whether the node allows script hooks to run.
+	pub fn enabled(&self) -> bool {
+		self.node_preferences.borrow().script_hooks.enabled
+	}
+
+	/// The last invocations, newest first, of every hook or only the given one.
+	pub fn invocations(&self, hook_id: Option<Uuid>) -> Vec<HookInvocation> {
+		self.invocations
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.rev()
+			.filter(|invocation| hook_id.map_or(true, |id| invocation.hook_id == id))
+			.cloned()
+			.collect()
+	}
+
+	fn record(&self, invocation: HookInvocation) {
+		let mut invocations = self
+			.invocations
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		if invocations.len() >= MAX_INVOCATIONS {
+			invocations.pop_front();
+		}
+
+		invocations.push_back(invocation);
+	}
+}
+
+/// Runs every hook of the library interested in the event, each one in its own task.
+pub(super) fn dispatch(
+	runner: &Arc<HookRunner>,
+	library_id: Uuid,
+	hooks: &[ScriptHook],
+	event: &WebhookEvent,
+) {
+	if !runner.enabled() {
+		debug!("Script hooks are disabled on this node, not running them");
+		return;
+	}
+
+	for hook in hooks {
+		let Some(event) = webhooks::matching_event(&hook.events, event) else {
+			continue;
+		};
+
+		let payload = match serde_json::to_vec(&WebhookPayload {
+			library_id,
+			timestamp: Utc::now(),
+			event: &event,
+		}) {
+			Ok(payload) => payload,
+			Err(e) => {
+				error!("Failed to serialize hook payload: {e:#?}");
+				continue;
+			}
+		};
+
+		tokio::spawn(run(
+			Arc::clone(runner),
+			library_id,
+			hook.clone(),
+			event.name(),
+			payload,
+		));
+	}
+}
+
+async fn run(
+	runner: Arc<HookRunner>,
+	library_id: Uuid,
+	hook: ScriptHook,
+	event_name: &'static str,
+	payload: Vec<u8>,
+) {
+	let Ok(_permit) = runner.permits.acquire().await else {
+		return;
+	};
+
+	let mut invocation = HookInvocation {
+		id: Uuid::new_v4(),
+		hook_id: hook.id,
+		event: event_name.to_string(),
+		started_at: Utc::now(),
+		duration_ms: 0,
+		exit_code: None,
+		timed_out: false,
+		error: None,
+		stdout: String::new(),
+		stderr: String::new(),
+	};
+
+	let start = Instant::now();
+
+	if let Err(e) = execute(library_id, &hook, &mut invocation, payload).await {
+		warn!(
+			"Failed to run '{event_name}' event hook <id='{}', name='{}'>: {e:#?}",
+			hook.id, hook.name
+		);
+		invocation.error = Some(e.to_string());
+	} else {
+		debug!(
+			"Ran '{event_name}' event hook <id='{}', name='{}'>: exit code {:?}{}",
+			hook.id,
+			hook.name,
+			invocation.exit_code,
+			if invocation.timed_out {
+				", timed out"
+			} else {
+				""
+			}
+		);
+	}
+
+	invocation.duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u32::MAX);
+
+	runner.record(invocation);
+}
+
+/// Runs the hook with a cleared environment, in a scratch directory of its own, killing it with
+/// every process it started if it runs past its timeout.
+///
+/// This isn't a sandbox: the hook runs with every privilege of the user running the node, and can
+/// read and write anything that user can.
+async fn execute(
+	library_id: Uuid,
+	hook: &ScriptHook,
+	invocation: &mut HookInvocation,
+	payload: Vec<u8>,
+) -> io::Result<()> {
+	let work_dir = scratch_dir(hook.id);
+	fs::create_dir_all(&work_dir).await?;
+
+	let mut command = shell_command(&hook.command);
+	command
+		.env_clear()
+		.envs(
+			INHERITED_VARS
+				.iter()
+				.filter_map(|var| std::env::var_os(var).map(|value| (var, value))),
+		)
+		.env("SD_LIBRARY_ID", library_id.to_string())
+		.env("SD_HOOK_ID", hook.id.to_string())
+		.env("SD_INVOCATION_ID", invocation.id.to_string())
+		.env("SD_EVENT", &invocation.event)
+		.current_dir(&work_dir)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true);
+
+	// Its own process group, so whatever the hook starts is killed with it
+	#[cfg(unix)]
+	command.process_group(0);
+
+	let mut child = command.spawn()?;
+
+	let stdin = child.stdin.take();
+	let stdout = child.stdout.take();
+	let stderr = child.stderr.take();
+
+	let run = async {
+		let write_payload = async move {
+			if let Some(mut stdin) = stdin {
+				// Hooks aren't required to read the payload
+				stdin.write_all(&payload).await.ok();
+			}
+		};
+
+		let ((), stdout, stderr, status) = tokio::join!(
+			write_payload,
+			read_capped(stdout),
+			read_capped(stderr),
+			child.wait()
+		);
+
+		(stdout, stderr, status)
+	};
+
+	let result = timeout(Duration::from_secs(hook.timeout_secs.into()), run).await;
+
+	match result {
+		Ok((stdout, stderr, status)) => {
+			invocation.exit_code = status?.code();
+			invocation.stdout = String::from_utf8_lossy(&stdout).into_owned();
+			invocation.stderr = String::from_utf8_lossy(&stderr).into_owned();
+		}
+		Err(_) => {
+			invocation.timed_out = true;
+
+			#[cfg(unix)]
+			if let Some(pid) = child.id() {
+				// SAFETY: Sending a signal has no memory safety requirements
+				unsafe {
+					libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+				}
+			}
+
+			child.kill().await.ok();
+		}
+	}
+
+	Ok(())
+}
+
+fn scratch_dir(hook_id: Uuid) -> PathBuf {
+	std::env::temp_dir()
+		.join("spacedrive-hooks")
+		.join(hook_id.to_string())
+}
+
+#[cfg(unix)]
+fn shell_command(script: &str) -> Command {
+	let mut command = Command::new("sh");
+	command.arg("-c").arg(script);
+	command
+}
+
+#[cfg(windows)]
+fn shell_command(script: &str) -> Command {
+	let mut command = Command::new("cmd");
+	command.arg("/C").arg(script);
+	command
+}
+
+/// Reads the stream up to [`MAX_OUTPUT_BYTES`], discarding the rest so the hook never blocks
+/// writing it.
+async fn read_capped(stream: Option<impl AsyncRead + Unpin>) -> Vec<u8> {
+	let mut output = vec![];
+
+	if let Some(mut stream) = stream {
+		(&mut stream)
+			.take(MAX_OUTPUT_BYTES)
+			.read_to_end(&mut output)
+			.await
+			.ok();
+
+		io::copy(&mut stream, &mut io::sink()).await.ok();
+	}
+
+	output
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+
+	fn hook(command: &str, timeout_secs: u32) -> ScriptHook {
+		ScriptHook {
+			id: Uuid::new_v4(),
+			name: "test".to_string(),
+			command: command.to_string(),
+			events: vec![],
+			timeout_secs,
+		}
+	}
+
+	fn invocation(hook: &ScriptHook) -> HookInvocation {
+		HookInvocation {
+			id: Uuid::new_v4(),
+			hook_id: hook.id,
+			event: "filesAdded".to_string(),
+			started_at: Utc::now(),
+			duration_ms: 0,
+			exit_code: None,
+			timed_out: false,
+			error: None,
+			stdout: String::new(),
+			stderr: String::new(),
+		}
+	}
+
+	#[tokio::test]
+	async fn captures_output() {
+		let hook = hook("cat; echo \"$SD_EVENT\" >&2; exit 3", 10);
+		let mut invocation = invocation(&hook);
+
+		execute(Uuid::new_v4(), &hook, &mut invocation, b"{}".to_vec())
+			.await
+			.unwrap();
+
+		assert_eq!(invocation.exit_code, Some(3));
+		assert_eq!(invocation.stdout, "{}");
+		assert_eq!(invocation.stderr, "filesAdded\n");
+		assert!(!invocation.timed_out);
+	}
+
+	#[tokio::test]
+	async fn kills_hooks_past_timeout() {
+		let hook = hook("sleep 30", 1);
+		let mut invocation = invocation(&hook);
+
+		execute(Uuid::new_v4(), &hook, &mut invocation, vec![])
+			.await
+			.unwrap();
+
+		assert!(invocation.timed_out);
+		assert_eq!(invocation.exit_code, None);
+	}
+}
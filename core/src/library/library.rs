@@ -1,12 +1,20 @@
 use crate::{
-	api::CoreEvent, cloud, object::media::old_thumbnail::get_indexed_thumbnail_path, sync, Node,
+	api::{
+		notifications::{Notification, NotificationData, NotificationId},
+		CoreEvent,
+	},
+	cloud,
+	notifications::Notifications,
+	object::media::old_thumbnail::{get_indexed_thumbnail_path, ThumbnailTier},
+	sync, Node,
 };
 
+use sd_cache::CacheKey;
 use sd_core_file_path_helper::IsolatedFilePathData;
 use sd_core_prisma_helpers::file_path_to_full_path;
 
 use sd_p2p::Identity;
-use sd_prisma::prisma::{file_path, location, PrismaClient};
+use sd_prisma::prisma::{file_path, location, notification, PrismaClient};
 use sd_utils::{db::maybe_missing, error::FileIOError};
 
 use std::{
@@ -16,11 +24,14 @@ use std::{
 	sync::Arc,
 };
 
+use chrono::{DateTime, Utc};
 use tokio::{fs, io, sync::broadcast, sync::RwLock};
-use tracing::warn;
+use tracing::{error, warn};
 use uuid::Uuid;
 
-use super::{LibraryConfig, LibraryManagerError};
+use super::{
+	hooks, webhooks, HookRunner, LibraryConfig, LibraryManagerError, NameIndex, WebhookEvent,
+};
 
 // TODO: Finish this
 // pub enum LibraryNew {
@@ -54,6 +65,13 @@ pub struct Library {
 	// Look, I think this shouldn't be here but our current invalidation system needs it.
 	// TODO(@Oscar): Get rid of this with the new invalidation system.
 	event_bus_tx: broadcast::Sender<CoreEvent>,
+	notifications: Notifications,
+	/// Client sending the requests of the library webhooks
+	http: reqwest::Client,
+	/// Runs the script hooks of the library
+	pub hook_runner: Arc<HookRunner>,
+	/// Names of the file paths, for the quick open palette
+	pub name_index: NameIndex,
 
 	pub actors: Arc<sd_actors::Actors>,
 }
@@ -98,6 +116,10 @@ impl Library {
 			do_cloud_sync,
 			env: node.env.clone(),
 			event_bus_tx: node.event_bus.0.clone(),
+			notifications: node.notifications.clone(),
+			http: node.http.clone(),
+			hook_runner: Arc::new(HookRunner::new(node.config.preferences_watcher())),
+			name_index: NameIndex::default(),
 			actors,
 		})
 	}
@@ -118,6 +140,54 @@ impl Library {
 		config.save(config_path).await.map_err(Into::into)
 	}
 
+	/// Persists a notification in the library database and sends it to the clients.
+	pub async fn emit_notification(&self, data: NotificationData, expires: Option<DateTime<Utc>>) {
+		let bytes = match rmp_serde::to_vec_named(&data) {
+			Ok(bytes) => bytes,
+			Err(e) => {
+				error!("Error serializing library notification: {e:#?}");
+				return;
+			}
+		};
+
+		match self
+			.db
+			.notification()
+			.create(
+				bytes,
+				vec![notification::expires_at::set(expires.map(Into::into))],
+			)
+			.exec()
+			.await
+		{
+			Ok(notification) => {
+				self.notifications._internal_send(Notification {
+					id: NotificationId::Library(self.id, notification.id as u32),
+					data,
+					read: false,
+					expires,
+				});
+			}
+			Err(e) => {
+				error!("Error saving library notification: {e:#?}");
+			}
+		}
+	}
+
+	/// Sends the event to the webhooks and script hooks of the library interested in it, in the
+	/// background.
+	pub async fn emit_webhook_event(&self, event: WebhookEvent) {
+		let config = self.config.read().await;
+
+		if !config.webhooks.is_empty() {
+			webhooks::dispatch(&self.http, self.id, &config.webhooks, &event);
+		}
+
+		if !config.hooks.is_empty() {
+			hooks::dispatch(&self.hook_runner, self.id, &config.hooks, &event);
+		}
+	}
+
 	// TODO: Remove this once we replace the old invalidation system
 	pub(crate) fn emit(&self, event: CoreEvent) {
 		if let Err(e) = self.event_bus_tx.send(event) {
@@ -125,8 +195,15 @@ impl Library {
 		}
 	}
 
+	/// Drops the nodes from the normalised cache of the clients, for items which don't exist anymore.
+	pub(crate) fn evict_cache_nodes(&self, keys: Vec<CacheKey>) {
+		if !keys.is_empty() {
+			self.emit(CoreEvent::EvictCacheNodes(keys));
+		}
+	}
+
 	pub async fn thumbnail_exists(&self, node: &Node, cas_id: &str) -> Result<bool, FileIOError> {
-		let thumb_path = get_indexed_thumbnail_path(node, cas_id, self.id);
+		let thumb_path = get_indexed_thumbnail_path(node, cas_id, self.id, ThumbnailTier::Grid);
 
 		match fs::metadata(&thumb_path).await {
 			Ok(_) => Ok(true),
@@ -151,9 +228,11 @@ impl Library {
 				.file_path()
 				.find_many(vec![
 					// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
-					file_path::location::is(vec![location::instance_id::equals(Some(
-						self.config().await.instance_id,
-					))]),
+					file_path::location::is(vec![
+						location::instance_id::equals(Some(self.config().await.instance_id)),
+						// The files of remote locations are on their servers
+						location::remote::equals(None),
+					]),
 					file_path::id::in_vec(ids),
 				])
 				.select(file_path_to_full_path::select())
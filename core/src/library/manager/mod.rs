@@ -2,7 +2,9 @@ use crate::{
 	api::{utils::InvalidateOperationEvent, CoreEvent},
 	cloud, invalidate_query,
 	location::metadata::{LocationMetadataError, SpacedriveLocationMetadataFile},
+	node::background_policy::Subsystem,
 	object::tag,
+	old_job::prune_job_history,
 	p2p, sync,
 	util::{mpscrr, MaybeUndefined},
 	Node,
@@ -365,6 +367,16 @@ impl Libraries {
 			.try_join()
 			.await?;
 
+		let name_index_path = self.name_index_path(&library.id);
+		if let Err(e) = fs::remove_file(&name_index_path).await {
+			if e.kind() != io::ErrorKind::NotFound {
+				warn!(
+					"Failed to remove the name index of the library: {:#?}",
+					FileIOError::from((name_index_path, e))
+				);
+			}
+		}
+
 		// We only remove here after files deletion
 		let library = libraries_write_guard
 			.remove(id)
@@ -377,6 +389,20 @@ impl Libraries {
 		Ok(())
 	}
 
+	fn name_index_path(&self, library_id: &Uuid) -> PathBuf {
+		self.libraries_dir.join(format!("{library_id}.names"))
+	}
+
+	/// Saves the name indexes of the libraries, so they are searchable right away on the next run.
+	pub(crate) async fn store_name_indexes(&self) {
+		for library in self.get_all().await {
+			library
+				.name_index
+				.store(self.name_index_path(&library.id))
+				.await;
+		}
+	}
+
 	// get_ctx will return the library context for the given library id.
 	pub async fn get_library(&self, library_id: &Uuid) -> Option<Arc<Library>> {
 		self.libraries.read().await.get(library_id).cloned()
@@ -415,6 +441,10 @@ impl Libraries {
 		let node_config = node.config.get().await;
 		let config = LibraryConfig::load(config_path, &node_config, &db).await?;
 
+		if let Err(e) = config.database.apply(&db).await {
+			warn!("Failed to apply the database settings of library '{id}': {e:#?}");
+		}
+
 		let instances = db.instance().find_many(vec![]).exec().await?;
 
 		let instance = instances
@@ -509,6 +539,11 @@ impl Libraries {
 		)
 		.await;
 
+		library
+			.name_index
+			.load(self.name_index_path(&library.id))
+			.await;
+
 		// This is an exception. Generally subscribe to this by `self.tx.subscribe`.
 		tokio::spawn(sync_rx_actor(library.clone(), node.clone(), sync.rx));
 
@@ -545,6 +580,43 @@ impl Libraries {
 			error!("Failed to resume jobs for library. {:#?}", e);
 		}
 
+		if let Err(e) = prune_job_history(
+			&library,
+			node.config.get().await.preferences.jobs.history_retention(),
+			false,
+		)
+		.await
+		{
+			error!("Failed to prune job history for library. {:#?}", e);
+		}
+
+		tokio::spawn(crate::location::ingest::watch_inbox(
+			node.clone(),
+			library.id,
+		));
+
+		tokio::spawn(crate::object::fs::trash::purge_periodically(
+			node.clone(),
+			library.id,
+		));
+
+		tokio::spawn(crate::library::analyze_periodically(
+			node.clone(),
+			library.id,
+		));
+
+		tokio::spawn(
+			crate::object::validation::old_location_validator_job::validate_periodically(
+				node.clone(),
+				library.id,
+			),
+		);
+
+		tokio::spawn(crate::location::remote::poll_periodically(
+			node.clone(),
+			library.id,
+		));
+
 		tokio::spawn({
 			let this = self.clone();
 			let node = node.clone();
@@ -698,6 +770,11 @@ async fn sync_rx_actor(
 				InvalidateOperationEvent::all(),
 			)),
 			SyncMessage::Created => {
+				// Peers get every operation created in the meantime once sync is allowed again
+				node.background_policy
+					.wait_until_allowed(Subsystem::Sync)
+					.await;
+
 				p2p::sync::originator(library.id, &library.sync, &node.p2p).await
 			}
 		}
@@ -1,14 +1,22 @@
 mod config;
+mod database;
+mod hooks;
 #[allow(clippy::module_inception)]
 mod library;
 mod manager;
 mod name;
+mod name_index;
 mod statistics;
+mod webhooks;
 
 pub use config::*;
+pub use database::*;
+pub use hooks::*;
 pub use library::*;
 pub use manager::*;
 pub use name::*;
+pub use name_index::*;
 pub use statistics::*;
+pub use webhooks::*;
 
 pub type LibraryId = uuid::Uuid;
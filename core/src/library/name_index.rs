@@ -0,0 +1,477 @@
+//! In memory index of the names of the file paths of a library, so the quick open palette finds
+//! them in milliseconds instead of scanning the `file_path` table.
+//!
+//! Names are matched by their trigrams. The index is read from the database on the first search
+//! and then kept up to date by the indexer and the watcher as they write file paths. It's saved
+//! when the node shuts down, and read again in the background after being loaded, as the files
+//! synced from other devices in the meantime aren't in it.
+
+use sd_prisma::prisma::{file_path, PrismaClient, SortOrder};
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	path::Path,
+	sync::{Arc, Mutex, RwLock},
+};
+
+use prisma_client_rust::QueryError;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io};
+use tracing::{error, trace};
+
+use super::Library;
+
+/// File paths read from the database at once while rebuilding the index
+const REBUILD_BATCH: i64 = 50_000;
+
+type Trigram = [u8; 3];
+
+#[derive(Default)]
+pub struct NameIndex {
+	names: RwLock<Option<Names>>,
+	/// Set while the names are read from the database, with the changes made in the meantime to
+	/// apply on top of them
+	pending: Mutex<Option<Vec<Change>>>,
+}
+
+#[derive(Debug)]
+enum Change {
+	Insert(file_path::id::Type, String),
+	Remove(file_path::id::Type),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Names {
+	ids: Vec<file_path::id::Type>,
+	/// Lowercased names, with their extension, empty for the removed file paths
+	names: Vec<String>,
+	#[serde(skip)]
+	trigrams: HashMap<Trigram, Vec<u32>>,
+	#[serde(skip)]
+	positions: HashMap<file_path::id::Type, u32>,
+	/// Loaded from the last run, so they may be outdated
+	#[serde(skip)]
+	loaded: bool,
+}
+
+impl Names {
+	fn new(ids: Vec<file_path::id::Type>, names: Vec<String>) -> Self {
+		let mut this = Self {
+			ids,
+			names,
+			trigrams: HashMap::new(),
+			positions: HashMap::new(),
+			loaded: false,
+		};
+		this.index_trigrams();
+		this
+	}
+
+	fn index_trigrams(&mut self) {
+		self.trigrams.clear();
+		self.positions.clear();
+
+		for position in 0..self.names.len() as u32 {
+			if !self.names[position as usize].is_empty() {
+				self.positions.insert(self.ids[position as usize], position);
+				self.index(position);
+			}
+		}
+	}
+
+	fn index(&mut self, position: u32) {
+		for trigram in trigrams(&self.names[position as usize]) {
+			let positions = self.trigrams.entry(trigram).or_default();
+			// A trigram appearing more than once in a name is only listed once for it
+			if let Err(idx) = positions.binary_search(&position) {
+				positions.insert(idx, position);
+			}
+		}
+	}
+
+	fn unindex(&mut self, position: u32) {
+		for trigram in trigrams(&self.names[position as usize]) {
+			if let Some(positions) = self.trigrams.get_mut(&trigram) {
+				if let Ok(idx) = positions.binary_search(&position) {
+					positions.remove(idx);
+				}
+			}
+		}
+	}
+
+	fn apply(&mut self, change: Change) {
+		match change {
+			Change::Insert(id, name) => {
+				if let Some(&position) = self.positions.get(&id) {
+					if self.names[position as usize] != name {
+						self.unindex(position);
+						self.names[position as usize] = name;
+						self.index(position);
+					}
+				} else if !name.is_empty() {
+					let position = self.names.len() as u32;
+					self.ids.push(id);
+					self.names.push(name);
+					self.positions.insert(id, position);
+					self.index(position);
+				}
+			}
+			Change::Remove(id) => {
+				if let Some(position) = self.positions.remove(&id) {
+					self.unindex(position);
+					self.names[position as usize].clear();
+				}
+
+				// The removed names are kept as empty ones, until they're most of the index
+				if self.positions.len() < self.names.len() / 2 {
+					self.compact();
+				}
+			}
+		}
+	}
+
+	fn compact(&mut self) {
+		let (ids, names) = self
+			.ids
+			.drain(..)
+			.zip(self.names.drain(..))
+			.filter(|(_, name)| !name.is_empty())
+			.unzip();
+
+		self.ids = ids;
+		self.names = names;
+		self.index_trigrams();
+	}
+
+	/// Returns the ids of up to `take` file paths whose name contains `query`, the names starting
+	/// with it and the shortest ones first.
+	fn search(&self, query: &str, take: usize) -> Vec<file_path::id::Type> {
+		let query = query.to_lowercase();
+
+		if query.is_empty() {
+			return vec![];
+		}
+
+		let query_trigrams = trigrams(&query).collect::<Vec<_>>();
+
+		let mut matches = if query_trigrams.is_empty() {
+			// Too short to have trigrams, but still quick enough to check every name
+			(0..self.names.len() as u32)
+				.filter(|&position| self.names[position as usize].contains(&query))
+				.collect::<Vec<_>>()
+		} else {
+			let Some(mut postings) = query_trigrams
+				.iter()
+				.map(|trigram| self.trigrams.get(trigram))
+				.collect::<Option<Vec<_>>>()
+			else {
+				return vec![];
+			};
+
+			// Intersecting from the rarest trigram keeps the candidates few
+			postings.sort_by_key(|positions| positions.len());
+
+			let mut candidates = postings[0].clone();
+			for positions in &postings[1..] {
+				candidates.retain(|position| positions.binary_search(position).is_ok());
+			}
+
+			// Having all the trigrams doesn't mean having them in the right order
+			candidates.retain(|&position| self.names[position as usize].contains(&query));
+			candidates
+		};
+
+		matches.sort_by_cached_key(|&position| {
+			let name = &self.names[position as usize];
+			(!name.starts_with(&query), name.len())
+		});
+
+		matches
+			.into_iter()
+			.take(take)
+			.map(|position| self.ids[position as usize])
+			.collect()
+	}
+}
+
+fn trigrams(name: &str) -> impl Iterator<Item = Trigram> + '_ {
+	name.as_bytes()
+		.windows(3)
+		.map(|window| [window[0], window[1], window[2]])
+}
+
+impl NameIndex {
+	/// Returns the ids of up to `take` file paths whose name contains `query`, case insensitively.
+	///
+	/// The index is read from the database on the first search, after that an outdated index is
+	/// searched right away and rebuilt in the background.
+	pub async fn search(
+		&self,
+		library: &Arc<Library>,
+		query: &str,
+		take: usize,
+	) -> Result<Vec<file_path::id::Type>, QueryError> {
+		if let Some(names) = &*self.names.read().expect("name index lock poisoned") {
+			if names.loaded && self.start_rebuild() {
+				tokio::spawn(refresh(Arc::clone(library)));
+			}
+
+			return Ok(names.search(query, take));
+		}
+
+		if !self.start_rebuild() {
+			// Another search is already reading them, which we can't wait for
+			return Ok(read_names(&library.db).await?.search(query, take));
+		}
+
+		match read_names(&library.db).await {
+			Ok(names) => {
+				let found = names.search(query, take);
+				self.finish_rebuild(Some(names));
+				Ok(found)
+			}
+			Err(e) => {
+				self.finish_rebuild(None);
+				Err(e)
+			}
+		}
+	}
+
+	/// Indexes the names of file paths just created or renamed, as `(id, name, extension)`.
+	pub(crate) fn insert<'a>(
+		&self,
+		file_paths: impl IntoIterator<Item = (file_path::id::Type, &'a str, &'a str)>,
+	) {
+		self.record(
+			file_paths
+				.into_iter()
+				.map(|(id, name, extension)| Change::Insert(id, full_name(name, extension))),
+		);
+	}
+
+	pub(crate) fn remove(&self, ids: impl IntoIterator<Item = file_path::id::Type>) {
+		self.record(ids.into_iter().map(Change::Remove));
+	}
+
+	fn record(&self, changes: impl Iterator<Item = Change>) {
+		// Always locking the names before the pending changes, like `finish_rebuild` does
+		let mut names = self.names.write().expect("name index lock poisoned");
+		let mut pending = self.pending.lock().expect("name index lock poisoned");
+
+		if names.is_none() && pending.is_none() {
+			// Nothing was read yet, the changes are read with the rest on the first search
+			return;
+		}
+
+		for change in changes {
+			if let Some(pending) = pending.as_mut() {
+				pending.push(match &change {
+					Change::Insert(id, name) => Change::Insert(*id, name.clone()),
+					Change::Remove(id) => Change::Remove(*id),
+				});
+			}
+
+			if let Some(names) = names.as_mut() {
+				names.apply(change);
+			}
+		}
+	}
+
+	/// Returns if we can read the names from the database, `false` if they're already being read.
+	fn start_rebuild(&self) -> bool {
+		let mut pending = self.pending.lock().expect("name index lock poisoned");
+
+		if pending.is_some() {
+			return false;
+		}
+
+		*pending = Some(vec![]);
+		true
+	}
+
+	fn finish_rebuild(&self, names: Option<Names>) {
+		let mut current = self.names.write().expect("name index lock poisoned");
+		let changes = self
+			.pending
+			.lock()
+			.expect("name index lock poisoned")
+			.take();
+
+		if let Some(mut names) = names {
+			for change in changes.into_iter().flatten() {
+				names.apply(change);
+			}
+
+			*current = Some(names);
+		}
+	}
+
+	/// Loads the names saved by [`Self::store`], which are rebuilt on the first search.
+	pub(crate) async fn load(&self, path: impl AsRef<Path>) {
+		let path = path.as_ref();
+
+		let mut names = match fs::read(path).await {
+			Ok(bytes) => match rmp_serde::from_slice::<Names>(&bytes) {
+				Ok(names) => names,
+				Err(e) => {
+					error!("Failed to deserialize the name index: {e:#?}");
+					return;
+				}
+			},
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {
+				trace!("No name index saved at '{}'", path.display());
+				return;
+			}
+			Err(e) => {
+				error!(
+					"Failed to read the name index: {:#?}",
+					FileIOError::from((path, e))
+				);
+				return;
+			}
+		};
+
+		names.index_trigrams();
+		names.loaded = true;
+
+		*self.names.write().expect("name index lock poisoned") = Some(names);
+	}
+
+	pub(crate) async fn store(&self, path: impl AsRef<Path>) {
+		let path = path.as_ref();
+
+		let bytes = match &*self.names.read().expect("name index lock poisoned") {
+			Some(names) => rmp_serde::to_vec_named(names),
+			None => return,
+		};
+
+		let Ok(bytes) = bytes.map_err(|e| {
+			error!("Failed to serialize the name index: {e:#?}");
+		}) else {
+			return;
+		};
+
+		if let Err(e) = fs::write(path, bytes).await {
+			error!(
+				"Failed to write the name index: {:#?}",
+				FileIOError::from((path, e))
+			);
+		}
+	}
+}
+
+async fn refresh(library: Arc<Library>) {
+	let names = read_names(&library.db)
+		.await
+		.map_err(|e| error!("Failed to rebuild the name index: {e:#?}"))
+		.ok();
+
+	library.name_index.finish_rebuild(names);
+}
+
+fn full_name(name: &str, extension: &str) -> String {
+	if extension.is_empty() {
+		name.to_lowercase()
+	} else {
+		format!("{name}.{extension}").to_lowercase()
+	}
+}
+
+async fn read_names(db: &PrismaClient) -> Result<Names, QueryError> {
+	let mut ids = Vec::new();
+	let mut names = Vec::new();
+	let mut last_id = None;
+
+	loop {
+		let batch = db
+			.file_path()
+			.find_many(
+				last_id
+					.map(|id| vec![file_path::id::gt(id)])
+					.unwrap_or_default(),
+			)
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(REBUILD_BATCH)
+			.select(file_path::select!({ id name extension }))
+			.exec()
+			.await?;
+
+		let is_last = (batch.len() as i64) < REBUILD_BATCH;
+		last_id = batch.last().map(|file_path| file_path.id).or(last_id);
+
+		for file_path in batch {
+			let Some(name) = file_path.name.filter(|name| !name.is_empty()) else {
+				continue;
+			};
+
+			ids.push(file_path.id);
+			names.push(full_name(
+				&name,
+				file_path.extension.as_deref().unwrap_or_default(),
+			));
+		}
+
+		if is_last {
+			break;
+		}
+	}
+
+	Ok(Names::new(ids, names))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_names_containing_the_query() {
+		let names = Names::new(
+			vec![1, 2, 3, 4, 5],
+			[
+				"holiday.jpg",
+				"my holiday photos",
+				"ho",
+				"report.pdf",
+				"abcxbcd",
+			]
+			.into_iter()
+			.map(ToString::to_string)
+			.collect(),
+		);
+
+		assert_eq!(names.search("HOLIDAY", 10), [1, 2]);
+		assert_eq!(names.search("ho", 10), [3, 1, 2]);
+		assert_eq!(names.search("ho", 1), [3]);
+		assert_eq!(names.search("day.j", 10), [1]);
+		// Has all the trigrams, but not next to each other
+		assert!(names.search("abcd", 10).is_empty());
+		assert!(names.search("missing", 10).is_empty());
+	}
+
+	#[test]
+	fn follows_inserted_and_removed_names() {
+		let mut names = Names::new(
+			vec![1, 2, 3],
+			["holiday.jpg", "report.pdf", "notes.txt"]
+				.into_iter()
+				.map(ToString::to_string)
+				.collect(),
+		);
+
+		names.apply(Change::Insert(4, full_name("Holiday", "png")));
+		names.apply(Change::Insert(2, full_name("summary", "pdf")));
+		assert_eq!(names.search("holiday", 10), [1, 4]);
+		assert_eq!(names.search("pdf", 10), [2]);
+		assert!(names.search("report", 10).is_empty());
+
+		names.apply(Change::Remove(1));
+		names.apply(Change::Remove(3));
+		assert_eq!(names.search("holiday", 10), [4]);
+
+		// Once most names are removed, they're dropped from the index
+		names.apply(Change::Remove(2));
+		assert_eq!(names.ids, [4]);
+		assert_eq!(names.search("holiday", 10), [4]);
+	}
+}
@@ -0,0 +1,354 @@
+use crate::old_job::{JobReport, JobStatus};
+
+use sd_p2p::RemoteIdentity;
+use sd_prisma::prisma::location;
+
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use specta::Type;
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+pub const SIGNATURE_HEADER: &str = "X-Spacedrive-Signature";
+pub const EVENT_HEADER: &str = "X-Spacedrive-Event";
+pub const DELIVERY_HEADER: &str = "X-Spacedrive-Delivery";
+
+/// A webhook of the library, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+	pub id: Uuid,
+	/// URL receiving a HTTP POST request with a [`WebhookPayload`] as JSON body
+	pub url: String,
+	/// Signs the body with HMAC-SHA256, sent hex encoded in the `X-Spacedrive-Signature` header
+	#[serde(default)]
+	pub secret: Option<String>,
+	/// Events sending a request to this webhook
+	pub events: Vec<WebhookEventFilter>,
+	/// Times a failed request is retried, with an exponential backoff
+	#[serde(default = "default_max_retries")]
+	pub max_retries: u32,
+}
+
+fn default_max_retries() -> u32 {
+	3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WebhookEventFilter {
+	/// Files indexed in the library, any file matches the empty filters
+	#[serde(rename_all = "camelCase")]
+	FilesAdded {
+		#[serde(default)]
+		location_ids: HashSet<location::id::Type>,
+		/// Lowercase extensions, without the dot
+		#[serde(default)]
+		extensions: HashSet<String>,
+	},
+	/// Jobs that finished, every job and status match the empty filters
+	#[serde(rename_all = "camelCase")]
+	JobFinished {
+		#[serde(default)]
+		job_names: HashSet<String>,
+		#[serde(default)]
+		statuses: HashSet<JobStatus>,
+	},
+	/// A new device joined the library
+	DevicePaired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddedFile {
+	pub pub_id: Uuid,
+	pub location_id: location::id::Type,
+	/// Path relative to the location root
+	pub path: String,
+	pub extension: String,
+	pub is_dir: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WebhookEvent {
+	#[serde(rename_all = "camelCase")]
+	FilesAdded { files: Vec<AddedFile> },
+	#[serde(rename_all = "camelCase")]
+	JobFinished {
+		id: Uuid,
+		name: String,
+		action: Option<String>,
+		status: JobStatus,
+		started_at: Option<DateTime<Utc>>,
+		completed_at: Option<DateTime<Utc>>,
+		errors: Vec<String>,
+	},
+	#[serde(rename_all = "camelCase")]
+	DevicePaired {
+		instance_id: Uuid,
+		identity: RemoteIdentity,
+		node_name: Option<String>,
+	},
+}
+
+impl WebhookEvent {
+	pub fn job_finished(report: &JobReport) -> Self {
+		Self::JobFinished {
+			id: report.id,
+			name: report.name.clone(),
+			action: report.action.clone(),
+			status: report.status,
+			started_at: report.started_at,
+			completed_at: report.completed_at,
+			errors: report.errors_text.clone(),
+		}
+	}
+
+	pub(super) fn name(&self) -> &'static str {
+		match self {
+			Self::FilesAdded { .. } => "filesAdded",
+			Self::JobFinished { .. } => "jobFinished",
+			Self::DevicePaired { .. } => "devicePaired",
+		}
+	}
+
+	/// The part of the event the filter is interested in, if any
+	fn filter(&self, filter: &WebhookEventFilter) -> Option<Self> {
+		match (self, filter) {
+			(
+				Self::FilesAdded { files },
+				WebhookEventFilter::FilesAdded {
+					location_ids,
+					extensions,
+				},
+			) => {
+				let files = files
+					.iter()
+					.filter(|file| {
+						(location_ids.is_empty() || location_ids.contains(&file.location_id))
+							&& (extensions.is_empty()
+								|| extensions.contains(&file.extension.to_lowercase()))
+					})
+					.cloned()
+					.collect::<Vec<_>>();
+
+				(!files.is_empty()).then_some(Self::FilesAdded { files })
+			}
+			(
+				Self::JobFinished { name, status, .. },
+				WebhookEventFilter::JobFinished {
+					job_names,
+					statuses,
+				},
+			) => ((job_names.is_empty() || job_names.contains(name))
+				&& (statuses.is_empty() || statuses.contains(status)))
+			.then(|| self.clone()),
+			(Self::DevicePaired { .. }, WebhookEventFilter::DevicePaired) => Some(self.clone()),
+			_ => None,
+		}
+	}
+}
+
+/// Body of the requests sent to webhooks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload<'a> {
+	pub library_id: Uuid,
+	pub timestamp: DateTime<Utc>,
+	pub event: &'a WebhookEvent,
+}
+
+impl Webhook {
+	/// The event to send to this webhook, merging what all its filters are interested in.
+	fn matching_event(&self, event: &WebhookEvent) -> Option<WebhookEvent> {
+		matching_event(&self.events, event)
+	}
+}
+
+/// The part of the event the filters are interested in, merging what each one matched.
+pub(super) fn matching_event(
+	filters: &[WebhookEventFilter],
+	event: &WebhookEvent,
+) -> Option<WebhookEvent> {
+	let mut matched = filters.iter().filter_map(|filter| event.filter(filter));
+	let first = matched.next()?;
+
+	Some(match first {
+		WebhookEvent::FilesAdded { files } => {
+			let mut seen = files.iter().map(|file| file.pub_id).collect::<HashSet<_>>();
+			let mut files = files;
+
+			for event in matched {
+				if let WebhookEvent::FilesAdded { files: more } = event {
+					files.extend(more.into_iter().filter(|file| seen.insert(file.pub_id)));
+				}
+			}
+
+			WebhookEvent::FilesAdded { files }
+		}
+		event => event,
+	})
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+	let mut mac =
+		Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+	mac.update(body);
+
+	format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Sends the event to every webhook of the library interested in it, each one in its own task.
+pub(super) fn dispatch(
+	http: &reqwest::Client,
+	library_id: Uuid,
+	webhooks: &[Webhook],
+	event: &WebhookEvent,
+) {
+	for webhook in webhooks {
+		let Some(event) = webhook.matching_event(event) else {
+			continue;
+		};
+
+		let body = match serde_json::to_vec(&WebhookPayload {
+			library_id,
+			timestamp: Utc::now(),
+			event: &event,
+		}) {
+			Ok(body) => body,
+			Err(e) => {
+				error!("Failed to serialize webhook payload: {e:#?}");
+				continue;
+			}
+		};
+
+		tokio::spawn(deliver(http.clone(), webhook.clone(), event.name(), body));
+	}
+}
+
+async fn deliver(http: reqwest::Client, webhook: Webhook, event_name: &'static str, body: Vec<u8>) {
+	let delivery_id = Uuid::new_v4();
+	let signature = webhook.secret.as_deref().map(|secret| sign(secret, &body));
+
+	let mut delay = FIRST_RETRY_DELAY;
+
+	for attempt in 0..=webhook.max_retries {
+		let mut request = http
+			.post(&webhook.url)
+			.timeout(WEBHOOK_TIMEOUT)
+			.header(reqwest::header::CONTENT_TYPE, "application/json")
+			.header(EVENT_HEADER, event_name)
+			.header(DELIVERY_HEADER, delivery_id.to_string())
+			.body(body.clone());
+
+		if let Some(signature) = &signature {
+			request = request.header(SIGNATURE_HEADER, signature);
+		}
+
+		match request
+			.send()
+			.await
+			.and_then(|response| response.error_for_status())
+		{
+			Ok(_) => {
+				debug!(
+					"Delivered '{event_name}' event to Webhook<id='{}'>",
+					webhook.id
+				);
+				return;
+			}
+			Err(e) if attempt < webhook.max_retries => {
+				warn!(
+					"Failed to deliver '{event_name}' event to Webhook<id='{}'>, retrying in {delay:?}: {e:#?}",
+					webhook.id
+				);
+				sleep(delay).await;
+				delay *= 2;
+			}
+			Err(e) => error!(
+				"Failed to deliver '{event_name}' event to Webhook<id='{}'>, giving up: {e:#?}",
+				webhook.id
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn added_file(location_id: location::id::Type, extension: &str) -> AddedFile {
+		AddedFile {
+			pub_id: Uuid::new_v4(),
+			location_id,
+			path: format!("/file.{extension}"),
+			extension: extension.to_string(),
+			is_dir: false,
+		}
+	}
+
+	#[test]
+	fn filters_added_files() {
+		let webhook = Webhook {
+			id: Uuid::new_v4(),
+			url: "http://localhost".to_string(),
+			secret: None,
+			events: vec![
+				WebhookEventFilter::FilesAdded {
+					location_ids: [1].into_iter().collect(),
+					extensions: ["jpg".to_string()].into_iter().collect(),
+				},
+				WebhookEventFilter::FilesAdded {
+					location_ids: HashSet::new(),
+					extensions: ["png".to_string()].into_iter().collect(),
+				},
+			],
+			max_retries: 0,
+		};
+
+		let event = WebhookEvent::FilesAdded {
+			files: vec![
+				added_file(1, "JPG"),
+				added_file(2, "jpg"),
+				added_file(2, "png"),
+				added_file(1, "txt"),
+			],
+		};
+
+		let Some(WebhookEvent::FilesAdded { files }) = webhook.matching_event(&event) else {
+			panic!("files must match");
+		};
+
+		assert_eq!(
+			files
+				.iter()
+				.map(|file| (file.location_id, file.extension.as_str()))
+				.collect::<Vec<_>>(),
+			vec![(1, "JPG"), (2, "png")]
+		);
+
+		assert!(webhook
+			.matching_event(&WebhookEvent::FilesAdded {
+				files: vec![added_file(1, "txt")]
+			})
+			.is_none());
+	}
+
+	#[test]
+	fn signs_payload() {
+		// Test vector from RFC 4231
+		assert_eq!(
+			sign("Jefe", b"what do ya want for nothing?"),
+			"sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+		);
+	}
+}
@@ -52,6 +52,22 @@ pub enum LocationError {
 	NestedLocation(Box<Path>),
 	#[error(transparent)]
 	NonUtf8Path(#[from] NonUtf8PathError),
+	#[error("failed to reach the remote location at '{endpoint}': {source}")]
+	Remote {
+		endpoint: String,
+		#[source]
+		source: opendal::Error,
+	},
+	#[error("remote locations over {0} aren't supported on this platform")]
+	UnsupportedRemote(&'static str),
+	#[error("invalid remote host '{0}'")]
+	InvalidRemoteHost(String),
+	#[error(
+		"location <id='{0}'> is on a remote server, its files can't be changed from this node"
+	)]
+	RemoteLocation(location::id::Type),
+	#[error("location <id='{0}'> isn't a remote location over SFTP")]
+	NotSftpLocation(location::id::Type),
 
 	// Internal Errors
 	#[error(transparent)]
@@ -78,6 +94,14 @@ pub enum LocationError {
 	MissingField(#[from] MissingFieldError),
 	#[error("invalid location scan state value: {0}")]
 	InvalidScanStateValue(i32),
+	#[error("invalid remote source of location <id='{id}'>: {source}")]
+	InvalidRemoteSource {
+		id: location::id::Type,
+		#[source]
+		source: serde_json::Error,
+	},
+	#[error("failed to keep the password of the remote location: {0}")]
+	Keystore(String),
 }
 
 impl From<LocationError> for rspc::Error {
@@ -94,9 +118,14 @@ impl From<LocationError> for rspc::Error {
 			}
 
 			// User's fault errors
-			NotDirectory(_) | NestedLocation(_) | LocationAlreadyExists(_) => {
-				Self::with_cause(ErrorCode::BadRequest, err.to_string(), err)
-			}
+			NotDirectory(_)
+			| NestedLocation(_)
+			| LocationAlreadyExists(_)
+			| Remote { .. }
+			| UnsupportedRemote(_)
+			| InvalidRemoteHost(_)
+			| RemoteLocation(_)
+			| NotSftpLocation(_) => Self::with_cause(ErrorCode::BadRequest, err.to_string(), err),
 
 			// Custom error message is used to differentiate these errors in the frontend
 			// TODO: A better solution would be for rspc to support sending custom data alongside errors
@@ -1,4 +1,9 @@
-use crate::library::Library;
+use crate::{
+	api::locations::ExplorerItem,
+	library::{AddedFile, Library, WebhookEvent},
+	metrics::{IndexerOperation, METRICS},
+	old_job::{JobItemProgress, JobReportUpdate},
+};
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData, IsolatedFilePathDataParts};
 use sd_core_indexer_rules::IndexerRuleError;
@@ -25,12 +30,14 @@ use tracing::{trace, warn};
 use super::location_with_indexer_rules;
 
 pub mod old_indexer_job;
+pub mod old_remote_indexer_job;
 mod old_shallow;
 mod old_walk;
 
 use old_walk::WalkedEntry;
 
 pub use old_indexer_job::OldIndexerJobInit;
+pub use old_remote_indexer_job::OldRemoteIndexerJobInit;
 pub use old_shallow::*;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -81,6 +88,26 @@ impl From<IndexerError> for rspc::Error {
 	}
 }
 
+/// The entries written to the database by a save or update step, as items of the job progress.
+fn indexed_items(location_path: &Path, entries: &[WalkedEntry]) -> Vec<JobReportUpdate> {
+	entries
+		.iter()
+		.map(|entry| {
+			let size = entry.metadata.size_in_bytes;
+
+			JobReportUpdate::Item(
+				JobItemProgress::new(
+					location_path
+						.join(&entry.iso_file_path)
+						.display()
+						.to_string(),
+				)
+				.with_bytes(size, size),
+			)
+		})
+		.collect()
+}
+
 async fn execute_indexer_save_step(
 	location: &location_with_indexer_rules::Data,
 	OldIndexerJobSaveStep { walked, .. }: &OldIndexerJobSaveStep,
@@ -163,6 +190,45 @@ async fn execute_indexer_save_step(
 
 	trace!("Inserted {count} records");
 
+	METRICS.record_indexed_paths(IndexerOperation::Created, count as u64);
+
+	if count > 0 {
+		let created = db
+			.file_path()
+			.find_many(vec![file_path::pub_id::in_vec(
+				walked
+					.iter()
+					.map(|entry| sd_utils::uuid_to_bytes(entry.pub_id))
+					.collect(),
+			)])
+			.select(file_path::select!({ id name extension }))
+			.exec()
+			.await?;
+
+		library.name_index.insert(created.iter().map(|file_path| {
+			(
+				file_path.id,
+				file_path.name.as_deref().unwrap_or_default(),
+				file_path.extension.as_deref().unwrap_or_default(),
+			)
+		}));
+
+		library
+			.emit_webhook_event(WebhookEvent::FilesAdded {
+				files: walked
+					.iter()
+					.map(|entry| AddedFile {
+						pub_id: entry.pub_id,
+						location_id: location.id,
+						path: entry.iso_file_path.to_string(),
+						extension: entry.iso_file_path.extension().to_string(),
+						is_dir: entry.iso_file_path.is_dir(),
+					})
+					.collect(),
+			})
+			.await;
+	}
+
 	Ok(count)
 }
 
@@ -248,6 +314,8 @@ async fn execute_indexer_update_step(
 
 	trace!("Updated {updated:?} records");
 
+	METRICS.record_indexed_paths(IndexerOperation::Updated, updated.len() as u64);
+
 	Ok(updated.len() as i64)
 }
 
@@ -262,9 +330,10 @@ fn iso_file_path_factory(
 
 async fn remove_non_existing_file_paths(
 	to_remove: impl IntoIterator<Item = file_path_pub_and_cas_ids::Data>,
-	db: &PrismaClient,
-	sync: &sd_core_sync::Manager,
+	library: &Library,
 ) -> Result<u64, IndexerError> {
+	let Library { db, sync, .. } = library;
+
 	let (sync_params, db_params): (Vec<_>, Vec<_>) = to_remove
 		.into_iter()
 		.map(|d| {
@@ -275,15 +344,27 @@ async fn remove_non_existing_file_paths(
 		})
 		.unzip();
 
-	sync.write_ops(
-		db,
-		(
-			sync_params,
-			db.file_path()
-				.delete_many(vec![file_path::id::in_vec(db_params)]),
-		),
-	)
-	.await?;
+	let removed = sync
+		.write_ops(
+			db,
+			(
+				sync_params,
+				db.file_path()
+					.delete_many(vec![file_path::id::in_vec(db_params.clone())]),
+			),
+		)
+		.await?;
+
+	library.name_index.remove(db_params.iter().copied());
+
+	library.evict_cache_nodes(
+		db_params
+			.into_iter()
+			.map(ExplorerItem::file_path_cache_key)
+			.collect(),
+	);
+
+	METRICS.record_indexed_paths(IndexerOperation::Removed, removed as u64);
 
 	Ok(0)
 }
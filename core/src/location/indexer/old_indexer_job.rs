@@ -13,7 +13,7 @@ use sd_core_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
 	IsolatedFilePathData,
 };
-use sd_core_indexer_rules::IndexerRule;
+use sd_core_indexer_rules::{cache, IndexerRule};
 
 use sd_prisma::{
 	prisma::{file_path, location},
@@ -35,10 +35,10 @@ use prisma_client_rust::operator::or;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::time::Instant;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use super::{
-	execute_indexer_save_step, execute_indexer_update_step, iso_file_path_factory,
+	execute_indexer_save_step, execute_indexer_update_step, indexed_items, iso_file_path_factory,
 	old_walk::{keep_walking, walk, ToWalkEntry, WalkResult},
 	remove_non_existing_file_paths, reverse_update_directories_sizes, IndexerError,
 	OldIndexerJobSaveStep, OldIndexerJobUpdateStep,
@@ -165,15 +165,15 @@ impl StatefulJob for OldIndexerJobInit {
 		let location_path = maybe_missing(&init.location.path, "location.path").map(Path::new)?;
 
 		let db = Arc::clone(&ctx.library.db);
-		let sync = &ctx.library.sync;
 
-		let indexer_rules = init
-			.location
-			.indexer_rules
-			.iter()
-			.map(|rule| IndexerRule::try_from(&rule.indexer_rule))
-			.collect::<Result<Vec<_>, _>>()
-			.map_err(IndexerError::from)?;
+		let indexer_rules = cache::location_rules(
+			&init.location.pub_id,
+			init.location
+				.indexer_rules
+				.iter()
+				.map(|rule| &rule.indexer_rule),
+		)
+		.map_err(IndexerError::from)?;
 
 		let to_walk_path = match &init.sub_path {
 			Some(sub_path) if sub_path != Path::new("") => {
@@ -210,11 +210,13 @@ impl StatefulJob for OldIndexerJobInit {
 			&to_walk_path,
 			&indexer_rules,
 			update_notifier_fn(ctx),
+			|| ctx.checkpoint(),
 			file_paths_db_fetcher_fn!(&db),
 			to_remove_db_fetcher_fn!(location_id, &db),
 			iso_file_path_factory(location_id, location_path),
 			50_000,
 		)
+		.instrument(info_span!("indexer_walk", location.id = location_id))
 		.await?;
 		let scan_read_time = scan_start.elapsed();
 		let to_remove = to_remove.collect::<Vec<_>>();
@@ -237,7 +239,7 @@ impl StatefulJob for OldIndexerJobInit {
 
 		let db_delete_start = Instant::now();
 		// TODO pass these uuids to sync system
-		let removed_count = remove_non_existing_file_paths(to_remove, &db, sync).await?;
+		let removed_count = remove_non_existing_file_paths(to_remove, &ctx.library).await?;
 		let db_delete_time = db_delete_start.elapsed();
 
 		let total_new_paths = &mut 0;
@@ -351,6 +353,8 @@ impl StatefulJob for OldIndexerJobInit {
 
 				let count = execute_indexer_save_step(&init.location, step, &ctx.library).await?;
 
+				ctx.progress(indexed_items(&data.location_path, &step.walked));
+
 				new_metadata.indexed_count = count as u64;
 				new_metadata.db_write_time = start_time.elapsed();
 
@@ -371,6 +375,8 @@ impl StatefulJob for OldIndexerJobInit {
 
 				let count = execute_indexer_update_step(to_update, &ctx.library).await?;
 
+				ctx.progress(indexed_items(&data.location_path, &to_update.to_update));
+
 				new_metadata.updated_count = count as u64;
 				new_metadata.db_write_time = start_time.elapsed();
 
@@ -383,7 +389,6 @@ impl StatefulJob for OldIndexerJobInit {
 					maybe_missing(&init.location.path, "location.path").map(Path::new)?;
 
 				let db = Arc::clone(&ctx.library.db);
-				let sync = &ctx.library.sync;
 
 				let scan_start = Instant::now();
 
@@ -402,6 +407,7 @@ impl StatefulJob for OldIndexerJobInit {
 					to_remove_db_fetcher_fn!(location_id, &db),
 					iso_file_path_factory(location_id, location_path),
 				)
+				.instrument(info_span!("indexer_walk", location.id = location_id))
 				.await?;
 
 				new_metadata.paths_and_sizes = paths_and_sizes;
@@ -411,7 +417,7 @@ impl StatefulJob for OldIndexerJobInit {
 				let db_delete_time = Instant::now();
 				// TODO pass these uuids to sync system
 				new_metadata.removed_count =
-					remove_non_existing_file_paths(to_remove, &db, sync).await?;
+					remove_non_existing_file_paths(to_remove, &ctx.library).await?;
 				new_metadata.db_write_time = db_delete_time.elapsed();
 
 				let to_walk_count = to_walk.len();
@@ -578,7 +584,7 @@ fn update_notifier_fn(ctx: &WorkerContext) -> impl FnMut(&Path, usize) + '_ {
 	}
 }
 
-async fn update_directories_sizes(
+pub(super) async fn update_directories_sizes(
 	paths_and_sizes: &HashMap<PathBuf, u64>,
 	location_id: location::id::Type,
 	location_path: impl AsRef<Path>,
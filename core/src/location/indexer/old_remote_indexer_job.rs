@@ -0,0 +1,619 @@
+use crate::{
+	invalidate_query,
+	location::{
+		location_with_indexer_rules, remote::RemoteSource, update_location_size, LocationError,
+		ScanState,
+	},
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_core_file_path_helper::{FilePathMetadata, IsolatedFilePathData};
+use sd_core_indexer_rules::{cache, IndexerRule, RulePerKind};
+use sd_core_prisma_helpers::{file_path_pub_and_cas_ids, file_path_walker};
+
+use sd_prisma::prisma::{file_path, location, PrismaClient, SortOrder};
+use sd_utils::{
+	db::{maybe_missing, size_in_bytes_from_db},
+	from_bytes_to_uuid,
+};
+
+use std::{
+	collections::{hash_map::DefaultHasher, HashMap, HashSet},
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset};
+use futures::TryStreamExt;
+use itertools::Itertools;
+use opendal::{Metakey, Operator};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Instant;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::{
+	execute_indexer_save_step, execute_indexer_update_step,
+	old_indexer_job::update_directories_sizes, old_walk::WalkedEntry,
+	remove_non_existing_file_paths, IndexerError, OldIndexerJobSaveStep, OldIndexerJobUpdateStep,
+};
+
+/// Number of files to write in the database at each step
+const BATCH_SIZE: usize = 1000;
+/// Number of file paths of the location read at once from the database to be compared with the
+/// listed ones
+const DB_BATCH_SIZE: i64 = 1000;
+
+/// `OldRemoteIndexerJobInit` indexes a location whose files are on a remote server, listing them
+/// all at once through OpenDAL as there's no cheap way to walk a remote directory at a time.
+///
+/// Only the glob indexer rules are applied, as the ones about children directories need to read
+/// the file system of this node.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldRemoteIndexerJobInit {
+	pub location: location_with_indexer_rules::Data,
+}
+
+impl Hash for OldRemoteIndexerJobInit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldRemoteIndexerJobData {
+	endpoint: String,
+	location_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldRemoteIndexerJobRunMetadata {
+	db_write_time: Duration,
+	scan_read_time: Duration,
+	total_paths: u64,
+	total_updated_paths: u64,
+	total_save_steps: u64,
+	total_update_steps: u64,
+	indexed_count: u64,
+	updated_count: u64,
+	removed_count: u64,
+	paths_and_sizes: HashMap<PathBuf, u64>,
+}
+
+impl JobRunMetadata for OldRemoteIndexerJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.db_write_time += new_data.db_write_time;
+		self.scan_read_time += new_data.scan_read_time;
+		self.total_paths += new_data.total_paths;
+		self.total_updated_paths += new_data.total_updated_paths;
+		self.total_save_steps += new_data.total_save_steps;
+		self.total_update_steps += new_data.total_update_steps;
+		self.indexed_count += new_data.indexed_count;
+		self.updated_count += new_data.updated_count;
+		self.removed_count += new_data.removed_count;
+
+		for (path, size) in new_data.paths_and_sizes {
+			*self.paths_and_sizes.entry(path).or_default() += size;
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum OldRemoteIndexerJobStep {
+	Save(OldIndexerJobSaveStep),
+	Update(OldIndexerJobUpdateStep),
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldRemoteIndexerJobInit {
+	type Data = OldRemoteIndexerJobData;
+	type Step = OldRemoteIndexerJobStep;
+	type RunMetadata = OldRemoteIndexerJobRunMetadata;
+
+	const NAME: &'static str = "remote_indexer";
+	const IS_BATCHED: bool = true;
+
+	fn target_location(&self) -> location::id::Type {
+		self.location.id
+	}
+
+	/// Lists the whole location on the server and compares it with its file paths in the
+	/// database, removing the missing ones right away.
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let location_id = init.location.id;
+		let location_path = maybe_missing(&init.location.path, "location.path").map(Path::new)?;
+
+		let Some(source) = RemoteSource::of(location_id, init.location.remote.as_deref())? else {
+			return Err(JobError::EarlyFinish {
+				name: Self::NAME.to_string(),
+				reason: format!("location <id='{location_id}'> isn't a remote location"),
+			});
+		};
+		let endpoint = source.endpoint();
+		let operator = source.location_operator(ctx.library.id, &init.location.pub_id)?;
+
+		let indexer_rules = cache::location_rules(
+			&init.location.pub_id,
+			init.location
+				.indexer_rules
+				.iter()
+				.map(|rule| &rule.indexer_rule),
+		)
+		.map_err(IndexerError::from)?;
+
+		ctx.progress(vec![JobReportUpdate::Message(format!(
+			"Listing the files of {endpoint}"
+		))]);
+
+		let scan_start = Instant::now();
+		let entries = list_remote_entries(
+			operator,
+			&endpoint,
+			location_id,
+			location_path,
+			&indexer_rules,
+		)
+		.await?;
+		let scan_read_time = scan_start.elapsed();
+
+		let mut paths_and_sizes = HashMap::<_, u64>::new();
+		for (path, metadata) in entries.values() {
+			if metadata.size_in_bytes == 0 {
+				continue;
+			}
+			for ancestor in path.ancestors().skip(1) {
+				*paths_and_sizes.entry(ancestor.to_path_buf()).or_default() +=
+					metadata.size_in_bytes;
+				if ancestor == location_path {
+					break;
+				}
+			}
+		}
+
+		let db_start = Instant::now();
+		let (walked, to_update, to_remove) =
+			compare_with_db(location_id, entries, &ctx.library.db).await?;
+
+		debug!(
+			"Remote indexer job found {} file_paths to be removed",
+			to_remove.len()
+		);
+
+		ctx.node
+			.thumbnailer
+			.remove_indexed_cas_ids(
+				to_remove
+					.iter()
+					.filter_map(|file_path| file_path.cas_id.clone())
+					.collect::<Vec<_>>(),
+				ctx.library.id,
+			)
+			.await;
+
+		let removed_count = to_remove.len() as u64;
+		remove_non_existing_file_paths(to_remove, &ctx.library).await?;
+		let db_write_time = db_start.elapsed();
+
+		let total_paths = walked.len() as u64;
+		let total_updated_paths = to_update.len() as u64;
+
+		let save_steps = walked
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.enumerate()
+			.map(|(chunk_idx, chunk)| {
+				OldRemoteIndexerJobStep::Save(OldIndexerJobSaveStep {
+					chunk_idx,
+					walked: chunk.collect(),
+				})
+			})
+			.collect::<Vec<_>>();
+		let total_save_steps = save_steps.len() as u64;
+
+		let update_steps = to_update
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.enumerate()
+			.map(|(chunk_idx, chunk)| {
+				OldRemoteIndexerJobStep::Update(OldIndexerJobUpdateStep {
+					chunk_idx,
+					to_update: chunk.collect(),
+				})
+			})
+			.collect::<Vec<_>>();
+		let total_update_steps = update_steps.len() as u64;
+
+		ctx.progress(vec![
+			JobReportUpdate::TaskCount((total_save_steps + total_update_steps) as usize),
+			JobReportUpdate::Message(format!(
+				"Starting saving {total_paths} files or directories, \
+				{total_updated_paths} files or directories to update",
+			)),
+		]);
+
+		*data = Some(OldRemoteIndexerJobData {
+			endpoint,
+			location_path: location_path.to_path_buf(),
+		});
+
+		Ok((
+			OldRemoteIndexerJobRunMetadata {
+				db_write_time,
+				scan_read_time,
+				total_paths,
+				total_updated_paths,
+				total_save_steps,
+				total_update_steps,
+				indexed_count: 0,
+				updated_count: 0,
+				removed_count,
+				paths_and_sizes,
+			},
+			save_steps
+				.into_iter()
+				.chain(update_steps)
+				.collect::<Vec<_>>(),
+		)
+			.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+		let mut new_metadata = Self::RunMetadata::default();
+		let start_time = Instant::now();
+
+		match step {
+			OldRemoteIndexerJobStep::Save(step) => {
+				ctx.progress(vec![
+					JobReportUpdate::CompletedTaskCount(step.chunk_idx + 1),
+					JobReportUpdate::Message(format!(
+						"Writing chunk {} of {} to database",
+						step.chunk_idx, run_metadata.total_save_steps
+					)),
+				]);
+
+				new_metadata.indexed_count =
+					execute_indexer_save_step(&init.location, step, &ctx.library).await? as u64;
+			}
+			OldRemoteIndexerJobStep::Update(step) => {
+				ctx.progress(vec![
+					JobReportUpdate::CompletedTaskCount(
+						run_metadata.total_save_steps as usize + step.chunk_idx + 1,
+					),
+					JobReportUpdate::Message(format!(
+						"Updating chunk {} of {} to database",
+						step.chunk_idx, run_metadata.total_update_steps
+					)),
+				]);
+
+				new_metadata.updated_count =
+					execute_indexer_update_step(step, &ctx.library).await? as u64;
+			}
+		}
+
+		new_metadata.db_write_time = start_time.elapsed();
+
+		Ok(new_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		if let Some(data) = data {
+			info!(
+				"Scan of {}{} completed in {:?}. {} new files found, \
+				indexed {} files in db, updated {} entries. db write completed in {:?}",
+				data.endpoint,
+				data.location_path.display(),
+				run_metadata.scan_read_time,
+				run_metadata.total_paths,
+				run_metadata.indexed_count,
+				run_metadata.total_updated_paths,
+				run_metadata.db_write_time,
+			);
+
+			if run_metadata.indexed_count > 0
+				|| run_metadata.removed_count > 0
+				|| run_metadata.updated_count > 0
+			{
+				invalidate_query!(ctx.library, "search.paths");
+
+				update_directories_sizes(
+					&run_metadata.paths_and_sizes,
+					init.location.id,
+					&data.location_path,
+					&ctx.library,
+				)
+				.await?;
+
+				update_location_size(init.location.id, &ctx.library)
+					.await
+					.map_err(IndexerError::from)?;
+			}
+
+			ctx.library
+				.db
+				.location()
+				.update(
+					location::id::equals(init.location.id),
+					vec![location::scan_state::set(ScanState::Indexed as i32)],
+				)
+				.exec()
+				.await
+				.map_err(IndexerError::from)?;
+		}
+
+		// The sizes of every directory are too heavy to be kept in the report
+		let run_metadata = Self::RunMetadata {
+			paths_and_sizes: HashMap::new(),
+			..*run_metadata
+		};
+
+		Ok(Some(json!({"init: ": init, "run_metadata": run_metadata})))
+	}
+}
+
+/// Digest of the paths, sizes and modification dates of the files and directories of the remote
+/// location, changing whenever something is added, edited or removed on its server.
+pub(crate) async fn remote_listing_fingerprint(
+	library_id: Uuid,
+	location: &location_with_indexer_rules::Data,
+	source: &RemoteSource,
+) -> Result<u64, JobError> {
+	let location_id = location.id;
+	let location_path = maybe_missing(&location.path, "location.path").map(Path::new)?;
+
+	let endpoint = source.endpoint();
+	let operator = source.location_operator(library_id, &location.pub_id)?;
+
+	let indexer_rules = cache::location_rules(
+		&location.pub_id,
+		location.indexer_rules.iter().map(|rule| &rule.indexer_rule),
+	)
+	.map_err(IndexerError::from)?;
+
+	let mut entries = list_remote_entries(
+		operator,
+		&endpoint,
+		location_id,
+		location_path,
+		&indexer_rules,
+	)
+	.await?
+	.into_values()
+	.collect::<Vec<_>>();
+	entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+	let mut hasher = DefaultHasher::new();
+	for (path, metadata) in entries {
+		path.hash(&mut hasher);
+		metadata.size_in_bytes.hash(&mut hasher);
+		metadata.modified_at.hash(&mut hasher);
+	}
+
+	Ok(hasher.finish())
+}
+
+/// Lists every file and directory under the location on the server, keyed by their isolated path,
+/// skipping the ones rejected by the glob rules and everything inside rejected directories.
+async fn list_remote_entries(
+	operator: Operator,
+	endpoint: &str,
+	location_id: location::id::Type,
+	location_path: &Path,
+	indexer_rules: &[IndexerRule],
+) -> Result<HashMap<IsolatedFilePathData<'static>, (PathBuf, FilePathMetadata)>, JobError> {
+	let to_location_error = |source| LocationError::Remote {
+		endpoint: endpoint.to_string(),
+		source,
+	};
+
+	// OpenDAL only lists directories when their path ends with a `/`
+	let listed = operator
+		.lister_with(&format!(
+			"{}/",
+			location_path.to_string_lossy().trim_end_matches('/')
+		))
+		.recursive(true)
+		.metakey(Metakey::Mode | Metakey::ContentLength | Metakey::LastModified)
+		.await
+		.map_err(to_location_error)?
+		.try_collect::<Vec<_>>()
+		.await
+		.map_err(to_location_error)?
+		.into_iter()
+		.map(|entry| {
+			let metadata = entry.metadata();
+			let is_dir = metadata.is_dir();
+			let modified_at = metadata.last_modified().unwrap_or_default();
+
+			(
+				Path::new("/").join(entry.path().trim_end_matches('/')),
+				FilePathMetadata {
+					// There are no inodes over the network
+					inode: 0,
+					size_in_bytes: if is_dir { 0 } else { metadata.content_length() },
+					// SFTP doesn't tell when files were created
+					created_at: modified_at,
+					modified_at,
+					hidden: entry.name().starts_with('.'),
+				},
+				is_dir,
+			)
+		})
+		.filter(|(path, _, _)| path != location_path)
+		.collect::<Vec<_>>();
+
+	// A recursive listing doesn't always give directories before their children
+	let rejected_dirs = listed
+		.iter()
+		.filter(|(path, _, is_dir)| *is_dir && rejected_by_globs(indexer_rules, path, true))
+		.map(|(path, _, _)| path.as_path())
+		.collect::<HashSet<_>>();
+
+	listed
+		.iter()
+		.filter(|(path, _, is_dir)| {
+			!rejected_by_globs(indexer_rules, path, *is_dir)
+				&& !path
+					.ancestors()
+					.skip(1)
+					.take_while(|ancestor| *ancestor != location_path)
+					.any(|ancestor| rejected_dirs.contains(ancestor))
+		})
+		.map(|(path, metadata, is_dir)| {
+			IsolatedFilePathData::new(location_id, location_path, path, *is_dir)
+				.map(|iso_file_path| (iso_file_path, (path.clone(), *metadata)))
+				.map_err(|e| IndexerError::from(e).into())
+		})
+		.collect()
+}
+
+/// Applies the glob rules like the walker of the local locations does, but leaving every
+/// directory to the rejecting globs, as the accepting ones are about files.
+fn rejected_by_globs(indexer_rules: &[IndexerRule], path: &Path, is_dir: bool) -> bool {
+	let mut accepted = None;
+
+	for rule in indexer_rules.iter().flat_map(|rule| &rule.rules) {
+		match rule {
+			RulePerKind::RejectFilesByGlob(_, reject_glob_set)
+				if reject_glob_set.is_match(path) =>
+			{
+				return true;
+			}
+			RulePerKind::AcceptFilesByGlob(_, accept_glob_set) if !is_dir => {
+				accepted = Some(accepted.unwrap_or(false) || accept_glob_set.is_match(path));
+			}
+			_ => {}
+		}
+	}
+
+	accepted == Some(false)
+}
+
+/// Splits the listed entries into the ones to create and the ones to update, and finds the file
+/// paths of the location that aren't on the server anymore.
+async fn compare_with_db(
+	location_id: location::id::Type,
+	mut entries: HashMap<IsolatedFilePathData<'static>, (PathBuf, FilePathMetadata)>,
+	db: &PrismaClient,
+) -> Result<
+	(
+		Vec<WalkedEntry>,
+		Vec<WalkedEntry>,
+		Vec<file_path_pub_and_cas_ids::Data>,
+	),
+	IndexerError,
+> {
+	let mut to_update = vec![];
+	let mut missing_pub_ids = vec![];
+	let mut skip = 0;
+
+	loop {
+		let file_paths = db
+			.file_path()
+			.find_many(vec![file_path::location_id::equals(Some(location_id))])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.skip(skip)
+			.take(DB_BATCH_SIZE)
+			.select(file_path_walker::select())
+			.exec()
+			.await?;
+
+		let fetched = file_paths.len() as i64;
+		skip += fetched;
+
+		for file_path in file_paths {
+			let Ok(iso_file_path) = IsolatedFilePathData::try_from(file_path.clone()) else {
+				continue;
+			};
+
+			let Some((_, metadata)) = entries.remove(&iso_file_path) else {
+				// The root of the location has no name, and it's never listed
+				if !iso_file_path.is_root() {
+					missing_pub_ids.push(file_path.pub_id);
+				}
+				continue;
+			};
+
+			// Datetimes stored in DB loses a bit of precision, so we check against a delta, and the
+			// sizes of directories are computed by us later
+			let changed = file_path.date_modified.map_or(true, |date_modified| {
+				DateTime::<FixedOffset>::from(metadata.modified_at) - date_modified
+					> chrono::Duration::milliseconds(1)
+			}) || file_path.hidden != Some(metadata.hidden)
+				|| (!iso_file_path.is_dir()
+					&& file_path
+						.size_in_bytes_bytes
+						.as_deref()
+						.map(size_in_bytes_from_db)
+						!= Some(metadata.size_in_bytes));
+
+			if changed {
+				to_update.push(WalkedEntry {
+					pub_id: from_bytes_to_uuid(&file_path.pub_id),
+					maybe_object_id: file_path.object_id,
+					iso_file_path,
+					metadata,
+				});
+			}
+		}
+
+		if fetched < DB_BATCH_SIZE {
+			break;
+		}
+	}
+
+	let walked = entries
+		.into_iter()
+		.map(|(iso_file_path, (_, metadata))| WalkedEntry {
+			pub_id: Uuid::new_v4(),
+			maybe_object_id: None,
+			iso_file_path,
+			metadata,
+		})
+		.collect();
+
+	let to_remove = db
+		._batch(
+			missing_pub_ids
+				.into_iter()
+				.chunks(200)
+				.into_iter()
+				.map(|pub_ids| {
+					db.file_path()
+						.find_many(vec![file_path::pub_id::in_vec(pub_ids.collect())])
+						.select(file_path_pub_and_cas_ids::select())
+				})
+				.collect::<Vec<_>>(),
+		)
+		.await?
+		.into_iter()
+		.flatten()
+		.collect();
+
+	Ok((walked, to_update, to_remove))
+}
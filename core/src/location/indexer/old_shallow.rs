@@ -15,7 +15,7 @@ use sd_core_file_path_helper::{
 	check_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
 	IsolatedFilePathData,
 };
-use sd_core_indexer_rules::IndexerRule;
+use sd_core_indexer_rules::cache;
 
 use sd_utils::db::maybe_missing;
 
@@ -47,14 +47,12 @@ pub async fn old_shallow(
 	let location_path = maybe_missing(&location.path, "location.path").map(Path::new)?;
 
 	let db = library.db.clone();
-	let sync = &library.sync;
 
-	let indexer_rules = location
-		.indexer_rules
-		.iter()
-		.map(|rule| IndexerRule::try_from(&rule.indexer_rule))
-		.collect::<Result<Vec<_>, _>>()
-		.map_err(IndexerError::from)?;
+	let indexer_rules = cache::location_rules(
+		&location.pub_id,
+		location.indexer_rules.iter().map(|rule| &rule.indexer_rule),
+	)
+	.map_err(IndexerError::from)?;
 
 	let (add_root, to_walk_path) = if sub_path != Path::new("") && sub_path != Path::new("/") {
 		let full_path = ensure_sub_path_is_in_location(&location_path, &sub_path)
@@ -103,7 +101,7 @@ pub async fn old_shallow(
 
 	errors.into_iter().for_each(|e| error!("{e}"));
 
-	remove_non_existing_file_paths(to_remove, &db, sync).await?;
+	remove_non_existing_file_paths(to_remove, library).await?;
 
 	let mut new_directories_to_scan = HashSet::new();
 
@@ -1,5 +1,7 @@
+use crate::object::fs::trash::TRASH_DIR_NAME;
+
 use sd_core_file_path_helper::{FilePathMetadata, IsolatedFilePathData};
-use sd_core_indexer_rules::{IndexerRule, RuleKind};
+use sd_core_indexer_rules::{rejected_by_entry_rules, IndexerRule, RuleKind};
 use sd_core_prisma_helpers::{file_path_pub_and_cas_ids, file_path_walker};
 
 use sd_prisma::prisma::file_path;
@@ -7,6 +9,7 @@ use sd_utils::{db::inode_from_db, error::FileIOError};
 
 use std::{
 	collections::{HashMap, HashSet, VecDeque},
+	ffi::OsStr,
 	future::Future,
 	hash::{Hash, Hasher},
 	path::{Path, PathBuf},
@@ -114,10 +117,13 @@ where
 /// This function walks through the filesystem, applying the rules to each entry and then returning
 /// a list of accepted entries. There are some useful comments in the implementation of this function
 /// in case of doubts.
-pub(super) async fn walk<FilePathDBFetcherFut, ToRemoveDbFetcherFut>(
+///
+/// The `checkpoint` is awaited before walking each directory, so the walk can be paused.
+pub(super) async fn walk<FilePathDBFetcherFut, ToRemoveDbFetcherFut, CheckpointFut>(
 	root: impl AsRef<Path>,
 	indexer_rules: &[IndexerRule],
 	mut update_notifier: impl FnMut(&Path, usize),
+	checkpoint: impl Fn() -> CheckpointFut,
 	file_paths_db_fetcher: impl Fn(Vec<file_path::WhereParam>) -> FilePathDBFetcherFut,
 	to_remove_db_fetcher: impl Fn(
 		IsolatedFilePathData<'static>,
@@ -137,6 +143,7 @@ where
 	FilePathDBFetcherFut: Future<Output = Result<Vec<file_path_walker::Data>, IndexerError>>,
 	ToRemoveDbFetcherFut:
 		Future<Output = Result<Vec<file_path_pub_and_cas_ids::Data>, IndexerError>>,
+	CheckpointFut: Future<Output = ()>,
 {
 	let root = root.as_ref();
 
@@ -153,6 +160,8 @@ where
 	let mut to_remove = vec![];
 
 	while let Some(entry) = to_walk.pop_front() {
+		checkpoint().await;
+
 		let last_indexed_count = indexed_paths.len();
 
 		let (entry_size, current_to_remove) = inner_walk_single_dir(
@@ -496,6 +505,12 @@ where
 			accept_by_children_dir
 		);
 
+		// Deleted files are kept in the trash folder of the location until restored or purged
+		if current_path.file_name() == Some(OsStr::new(TRASH_DIR_NAME)) {
+			trace!("Path {} skipped as trash", current_path.display());
+			continue 'entries;
+		}
+
 		let Ok(rules_per_kind) = IndexerRule::apply_all(indexer_rules, &current_path)
 			.await
 			.map_err(|e| errors.push(e.into()))
@@ -515,6 +530,14 @@ where
 			continue 'entries;
 		}
 
+		if rejected_by_entry_rules(&rules_per_kind) {
+			trace!(
+				"Path {} rejected by its extension, size, age or a .gitignore file",
+				current_path.display()
+			);
+			continue 'entries;
+		}
+
 		let Ok(metadata) = entry
 			.metadata()
 			.await
@@ -823,6 +846,7 @@ mod tests {
 			root_path.to_path_buf(),
 			&[],
 			|_, _| {},
+			|| async {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
 			|path, is_dir| {
@@ -888,6 +912,7 @@ mod tests {
 			root_path.to_path_buf(),
 			only_photos_rule,
 			|_, _| {},
+			|| async {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
 			|path, is_dir| {
@@ -962,6 +987,7 @@ mod tests {
 			root_path.to_path_buf(),
 			git_repos,
 			|_, _| {},
+			|| async {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
 			|path, is_dir| {
@@ -1054,6 +1080,7 @@ mod tests {
 			root_path.to_path_buf(),
 			git_repos_no_deps_no_build_dirs,
 			|_, _| {},
+			|| async {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
 			|path, is_dir| {
@@ -0,0 +1,436 @@
+//! Quick ingest of files shared with the node, from the share sheet of the OS, a URL or an inbox
+//! folder, into a location.
+//!
+//! Ingested files are stored at the root of the location and indexed right away, instead of
+//! waiting for the watcher or a rescan, so their object can be returned to the caller. Files
+//! copied, downloaded or uploaded are limited to the size set in the [`IngestSettings`] of the
+//! library. The files of the inbox folder, already being on the node, aren't limited.
+
+use crate::{
+	library::Library,
+	location::{get_location_path_from_location_id, LocationError},
+	object::fs::{error::FileSystemJobsError, find_available_filename_for_duplicate},
+	Node,
+};
+
+use sd_core_file_path_helper::{filter_existing_file_path_params, IsolatedFilePathData};
+
+use sd_prisma::prisma::{file_path, location, object};
+use sd_utils::{error::FileIOError, from_bytes_to_uuid};
+
+use std::{
+	io,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs::{self, OpenOptions},
+	io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+	time,
+};
+use tokio_util::io::StreamReader;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::manager::index_new_file;
+
+const INBOX_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Inbox files modified more recently than this may still be being written
+const INBOX_SETTLE_TIME: Duration = Duration::from_secs(5);
+/// Name of downloads whose URL doesn't end with a file name
+const DEFAULT_DOWNLOAD_NAME: &str = "download";
+const DEFAULT_MAX_SIZE_MIB: u32 = 4096;
+
+/// Limits of the ingested files, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestSettings {
+	/// Biggest file that can be copied, downloaded or uploaded, there's no limit if `None`
+	pub max_size_mib: Option<u32>,
+}
+
+impl Default for IngestSettings {
+	fn default() -> Self {
+		Self {
+			max_size_mib: Some(DEFAULT_MAX_SIZE_MIB),
+		}
+	}
+}
+
+impl IngestSettings {
+	fn max_bytes(&self) -> u64 {
+		self.max_size_mib.map_or(u64::MAX, |max_size_mib| {
+			u64::from(max_size_mib) * 1024 * 1024
+		})
+	}
+}
+
+/// A folder watched for new files, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestInbox {
+	pub path: PathBuf,
+	/// Location the files are moved into
+	pub location_id: location::id::Type,
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum IngestSource {
+	/// A file on this node, which is copied and left untouched
+	Path(PathBuf),
+	/// An `http` or `https` URL, which is downloaded
+	Url(String),
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestedFile {
+	pub file_path_id: file_path::id::Type,
+	pub object_id: object::id::Type,
+	pub object_pub_id: Uuid,
+	/// Where the file was stored, its name is changed if the location already had one with it
+	pub path: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum IngestError {
+	#[error("no file name to store the file with")]
+	MissingFileName,
+	#[error("invalid URL '{0}', only http and https URLs are supported")]
+	InvalidUrl(String),
+	#[error("failed to download '{url}'")]
+	Download {
+		url: String,
+		#[source]
+		source: reqwest::Error,
+	},
+	#[error("the file is bigger than the {max_bytes} bytes allowed")]
+	TooLarge { max_bytes: u64 },
+	#[error("the file was stored at '{}' but couldn't be indexed", .0.display())]
+	NotIndexed(Box<Path>),
+	#[error(transparent)]
+	Location(#[from] LocationError),
+	#[error(transparent)]
+	FileSystem(#[from] FileSystemJobsError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+}
+
+impl From<IngestError> for rspc::Error {
+	fn from(e: IngestError) -> Self {
+		match e {
+			IngestError::Location(e) => e.into(),
+			IngestError::MissingFileName | IngestError::InvalidUrl(_) => {
+				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+			IngestError::Download { ref source, .. } if source.is_status() => {
+				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+			IngestError::TooLarge { .. } => {
+				Self::with_cause(ErrorCode::PayloadTooLarge, e.to_string(), e)
+			}
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+/// Stores the file of `source` in the location and indexes it, `name` defaulting to the name of
+/// the file or the end of the URL.
+pub async fn ingest(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	source: IngestSource,
+	name: Option<String>,
+) -> Result<IngestedFile, IngestError> {
+	match source {
+		IngestSource::Path(path) => {
+			let name = name
+				.or_else(|| file_name(&path))
+				.ok_or(IngestError::MissingFileName)?;
+			let file = fs::File::open(&path)
+				.await
+				.map_err(|e| FileIOError::from((&path, e)))?;
+
+			ingest_reader(node, library, location_id, &name, file).await
+		}
+		IngestSource::Url(url) => {
+			let parsed = reqwest::Url::parse(&url)
+				.ok()
+				.filter(|parsed| matches!(parsed.scheme(), "http" | "https"))
+				.ok_or_else(|| IngestError::InvalidUrl(url.clone()))?;
+
+			let name = name
+				.or_else(|| {
+					parsed
+						.path_segments()
+						.and_then(Iterator::last)
+						.filter(|segment| !segment.is_empty())
+						.map(ToString::to_string)
+				})
+				.unwrap_or_else(|| DEFAULT_DOWNLOAD_NAME.to_string());
+
+			let response = node
+				.http
+				.get(parsed)
+				.send()
+				.await
+				.and_then(reqwest::Response::error_for_status)
+				.map_err(|source| IngestError::Download {
+					url: url.clone(),
+					source,
+				})?;
+
+			// Checked again while writing it, as the server may not tell or lie about it
+			let max_bytes = library.config().await.ingest.max_bytes();
+			if response
+				.content_length()
+				.is_some_and(|length| length > max_bytes)
+			{
+				return Err(IngestError::TooLarge { max_bytes });
+			}
+
+			ingest_stream(node, library, location_id, &name, response.bytes_stream()).await
+		}
+	}
+}
+
+/// Stores a file streamed to the node, like an upload, in the location and indexes it.
+pub async fn ingest_stream<E>(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	name: &str,
+	stream: impl Stream<Item = Result<Bytes, E>>,
+) -> Result<IngestedFile, IngestError>
+where
+	E: std::error::Error + Send + Sync + 'static,
+{
+	let reader = StreamReader::new(Box::pin(
+		stream.map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+	));
+
+	ingest_reader(node, library, location_id, name, reader).await
+}
+
+async fn ingest_reader(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	name: &str,
+	reader: impl AsyncRead + Unpin,
+) -> Result<IngestedFile, IngestError> {
+	let max_bytes = library.config().await.ingest.max_bytes();
+	let location_path = get_location_path_from_location_id(&library.db, location_id).await?;
+	let (target, mut file) = create_target(&location_path, name).await?;
+
+	// Reading a byte more than allowed to know if the file is bigger
+	let mut reader = reader.take(max_bytes.saturating_add(1));
+
+	let written = async {
+		let written = tokio::io::copy(&mut reader, &mut file).await?;
+		file.flush().await?;
+		Ok::<_, io::Error>(written)
+	}
+	.await;
+
+	drop(file);
+
+	let error = match written {
+		Ok(written) if written > max_bytes => IngestError::TooLarge { max_bytes },
+		Ok(_) => return index(node, library, location_id, &location_path, target).await,
+		Err(e) => FileIOError::from((&target, e)).into(),
+	};
+
+	remove_partial(&target).await;
+
+	Err(error)
+}
+
+/// Moves the file into the location, falling back to a copy when they are on different
+/// filesystems, and indexes it.
+async fn ingest_moving(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	path: &Path,
+) -> Result<IngestedFile, IngestError> {
+	let name = file_name(path).ok_or(IngestError::MissingFileName)?;
+	let location_path = get_location_path_from_location_id(&library.db, location_id).await?;
+	// The empty file created reserves the name, and is replaced by the moved one
+	let (target, _) = create_target(&location_path, &name).await?;
+
+	if fs::rename(path, &target).await.is_err() {
+		if let Err(e) = fs::copy(path, &target).await {
+			remove_partial(&target).await;
+			return Err(FileIOError::from((path, e)).into());
+		}
+
+		fs::remove_file(path)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))?;
+	}
+
+	index(node, library, location_id, &location_path, target).await
+}
+
+async fn remove_partial(target: &Path) {
+	if let Err(e) = fs::remove_file(target).await {
+		warn!(
+			"Failed to remove partially ingested file '{}': {e:#?}",
+			target.display()
+		);
+	}
+}
+
+async fn index(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	location_path: &Path,
+	target: PathBuf,
+) -> Result<IngestedFile, IngestError> {
+	// The watcher may index the file first, which is fine as long as it ends up indexed
+	if let Err(e) = index_new_file(location_id, &target, node, library).await {
+		warn!(
+			"Failed to index ingested file '{}': {e:#?}",
+			target.display()
+		);
+	}
+
+	let iso_file_path = IsolatedFilePathData::new(location_id, location_path, &target, false)
+		.map_err(LocationError::from)?;
+
+	let Some(file_path::Data {
+		id: file_path_id,
+		object: Some(Some(object)),
+		..
+	}) = library
+		.db
+		.file_path()
+		.find_first(filter_existing_file_path_params(&iso_file_path))
+		.with(file_path::object::fetch())
+		.exec()
+		.await?
+	else {
+		return Err(IngestError::NotIndexed(target.into_boxed_path()));
+	};
+
+	debug!("Ingested '{}'", target.display());
+
+	Ok(IngestedFile {
+		file_path_id,
+		object_id: object.id,
+		object_pub_id: from_bytes_to_uuid(&object.pub_id),
+		path: target,
+	})
+}
+
+/// Creates the file the ingested one is stored in, with a suffixed name if the location already
+/// has a file with it. It's only created if missing, so a file appearing in the meantime is never
+/// overwritten.
+async fn create_target(
+	location_path: &Path,
+	name: &str,
+) -> Result<(PathBuf, fs::File), IngestError> {
+	// Only the file name is kept, so a name can't point outside of the location
+	let name = file_name(Path::new(name)).ok_or(IngestError::MissingFileName)?;
+	let target = location_path.join(name);
+	let mut candidate = target.clone();
+
+	loop {
+		match OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&candidate)
+			.await
+		{
+			Ok(file) => return Ok((candidate, file)),
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+				candidate = find_available_filename_for_duplicate(&target).await?;
+			}
+			Err(e) => return Err(FileIOError::from((&candidate, e)).into()),
+		}
+	}
+}
+
+fn file_name(path: &Path) -> Option<String> {
+	path.file_name()
+		.and_then(|name| name.to_str())
+		.filter(|name| !name.starts_with('.'))
+		.map(ToString::to_string)
+}
+
+/// Moves the files appearing in the inbox folder of the library into its location, until the
+/// library is unloaded.
+pub(crate) async fn watch_inbox(node: Arc<Node>, library_id: Uuid) {
+	let mut interval = time::interval(INBOX_POLL_INTERVAL);
+
+	loop {
+		interval.tick().await;
+
+		let Some(library) = node.libraries.get_library(&library_id).await else {
+			break;
+		};
+
+		let Some(inbox) = library.config().await.ingest_inbox else {
+			continue;
+		};
+
+		if let Err(e) = drain_inbox(&node, &library, &inbox).await {
+			warn!(
+				"Failed to read the ingest inbox '{}': {e:#?}",
+				inbox.path.display()
+			);
+		}
+	}
+}
+
+async fn drain_inbox(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	inbox: &IngestInbox,
+) -> Result<(), FileIOError> {
+	let mut entries = fs::read_dir(&inbox.path)
+		.await
+		.map_err(|e| FileIOError::from((&inbox.path, e)))?;
+
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((&inbox.path, e)))?
+	{
+		let path = entry.path();
+
+		let Ok(metadata) = entry.metadata().await else {
+			continue;
+		};
+
+		let settled = metadata
+			.modified()
+			.ok()
+			.and_then(|modified| SystemTime::now().duration_since(modified).ok())
+			.is_some_and(|elapsed| elapsed >= INBOX_SETTLE_TIME);
+
+		if !metadata.is_file() || !settled || file_name(&path).is_none() {
+			continue;
+		}
+
+		if let Err(e) = ingest_moving(node, library, inbox.location_id, &path).await {
+			warn!("Failed to ingest '{}': {e:#?}", path.display());
+		}
+	}
+
+	Ok(())
+}
@@ -26,6 +26,8 @@ use uuid::Uuid;
 
 mod watcher;
 
+pub(crate) use watcher::index_new_file;
+
 mod helpers;
 
 #[derive(Clone, Copy, Debug)]
@@ -386,6 +388,11 @@ impl Locations {
 						ManagementMessageAction::Add => {
 							response_tx.send(
 							if let Some(location) = get_location(location_id, &library).await {
+								// Remote locations can't be watched, they're polled for changes by their library instead
+								if location.remote.is_some() {
+									debug!("Location {location_id} is remote, not watching it");
+									Ok(())
+								} else {
 								match check_online(&location, &node, &library).await {
 									Ok(is_online) => {
 
@@ -418,6 +425,7 @@ impl Locations {
 										Ok(()) // TODO: Probs should be error but that will break startup when location is offline
 									}
 								}
+								}
 							} else {
 								warn!(
 									"Location not found in database to be watched: {}",
@@ -126,6 +126,7 @@ impl<'lib> EventHandler<'lib> for AndroidEventHandler<'lib> {
 					fs::metadata(to_path)
 						.await
 						.map_err(|e| FileIOError::from((to_path, e)))?,
+					&mut self.to_recalculate_size,
 					self.library,
 				)
 				.await?;
@@ -131,6 +131,7 @@ impl<'lib> EventHandler<'lib> for LinuxEventHandler<'lib> {
 					fs::metadata(to_path)
 						.await
 						.map_err(|e| FileIOError::from((to_path, e)))?,
+					&mut self.to_recalculate_size,
 					self.library,
 				)
 				.await?;
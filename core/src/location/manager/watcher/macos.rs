@@ -356,7 +356,15 @@ impl MacOsEventHandler<'_> {
 						);
 
 						// We found a new path for this old path, so we can rename it
-						rename(self.location_id, &path, &old_path, meta, self.library).await?;
+						rename(
+							self.location_id,
+							&path,
+							&old_path,
+							meta,
+							&mut self.to_recalculate_size,
+							self.library,
+						)
+						.await?;
 					} else {
 						trace!("No match for new path yet: {}", path.display());
 						self.new_paths_map.insert(inode, (Instant::now(), path));
@@ -399,6 +407,7 @@ impl MacOsEventHandler<'_> {
 						fs::metadata(&new_path)
 							.await
 							.map_err(|e| FileIOError::from((&new_path, e)))?,
+						&mut self.to_recalculate_size,
 						self.library,
 					)
 					.await?;
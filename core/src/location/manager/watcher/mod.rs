@@ -34,6 +34,8 @@ mod utils;
 
 use utils::check_event;
 
+pub(crate) use utils::index_new_file;
+
 #[cfg(target_os = "linux")]
 type Handler<'lib> = linux::LinuxEventHandler<'lib>;
 
@@ -1,19 +1,23 @@
 use crate::{
+	api::locations::ExplorerItem,
 	invalidate_query,
-	library::Library,
+	library::{AddedFile, Library, WebhookEvent},
 	location::{
 		create_file_path, delete_directory, find_location,
 		indexer::reverse_update_directories_sizes, location_with_indexer_rules,
 		manager::LocationManagerError, scan_location_sub_path, update_location_size,
 	},
 	object::{
+		fs::trash::TRASH_DIR_NAME,
 		media::{
 			media_data_extractor::{can_extract_media_data_for_image, extract_media_data},
 			media_data_image_to_query_params,
-			old_thumbnail::get_indexed_thumbnail_path,
+			old_thumbnail::remove_indexed_thumbnails,
 		},
 		old_file_identifier::FileMetadata,
+		tag::rules::apply_rules_to_objects,
 		validation::hash::file_checksum,
+		xmp::{import_sidecar, XMP_EXTENSION},
 	},
 	Node,
 };
@@ -35,7 +39,7 @@ use sd_sync::OperationFactory;
 use sd_utils::{
 	db::{inode_from_db, inode_to_db, maybe_missing},
 	error::FileIOError,
-	msgpack, uuid_to_bytes,
+	from_bytes_to_uuid, msgpack, uuid_to_bytes,
 };
 
 #[cfg(target_family = "unix")]
@@ -67,11 +71,14 @@ use uuid::Uuid;
 use super::{INode, HUNDRED_MILLIS};
 
 pub(super) fn check_event(event: &Event, ignore_paths: &HashSet<PathBuf>) -> bool {
-	// if path includes .DS_Store, .spacedrive file creation or is in the `ignore_paths` set, we ignore
+	// if path includes .DS_Store, .spacedrive file creation, is in the trash or is in the
+	// `ignore_paths` set, we ignore
 	!event.paths.iter().any(|p| {
 		p.file_name()
 			.and_then(OsStr::to_str)
 			.map_or(false, |name| name == ".DS_Store" || name == ".spacedrive")
+			|| p.components()
+				.any(|component| component.as_os_str() == TRASH_DIR_NAME)
 			|| ignore_paths.contains(p)
 	})
 }
@@ -142,15 +149,29 @@ pub(super) async fn create_file(
 	node: &Arc<Node>,
 	library: &Arc<Library>,
 ) -> Result<(), LocationManagerError> {
-	inner_create_file(
-		location_id,
-		extract_location_path(location_id, library).await?,
-		path,
-		metadata,
-		node,
-		library,
-	)
-	.await
+	let path = path.as_ref();
+	let location_path = extract_location_path(location_id, library).await?;
+
+	inner_create_file(location_id, &location_path, path, metadata, node, library).await?;
+
+	import_if_xmp_sidecar(location_id, location_path, path, library).await;
+
+	Ok(())
+}
+
+/// Indexes a file the node itself just wrote into the location, without waiting for the watcher.
+pub(crate) async fn index_new_file(
+	location_id: location::id::Type,
+	path: impl AsRef<Path>,
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+) -> Result<(), LocationManagerError> {
+	let path = path.as_ref();
+	let metadata = fs::metadata(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	create_file(location_id, path, &metadata, node, library).await
 }
 
 async fn inner_create_file(
@@ -244,6 +265,18 @@ async fn inner_create_file(
 	let created_file =
 		create_file_path(library, iso_file_path_parts, cas_id.clone(), metadata).await?;
 
+	library
+		.emit_webhook_event(WebhookEvent::FilesAdded {
+			files: vec![AddedFile {
+				pub_id: from_bytes_to_uuid(&created_file.pub_id),
+				location_id,
+				path: iso_file_path.to_string(),
+				extension: extension.clone(),
+				is_dir: false,
+			}],
+		})
+		.await;
+
 	object::select!(object_ids { id pub_id });
 
 	let existing_object = db
@@ -316,6 +349,8 @@ async fn inner_create_file(
 		// Running in a detached task as thumbnail generation can take a while and we don't want to block the watcher
 
 		if let Some(cas_id) = cas_id {
+			let animated_videos = library.config().await.thumbnails.animated_videos;
+
 			spawn({
 				let extension = extension.clone();
 				let path = path.to_path_buf();
@@ -325,7 +360,13 @@ async fn inner_create_file(
 				async move {
 					if let Err(e) = node
 						.thumbnailer
-						.generate_single_indexed_thumbnail(&extension, cas_id, path, library_id)
+						.generate_single_indexed_thumbnail(
+							&extension,
+							cas_id,
+							path,
+							library_id,
+							animated_videos,
+						)
 						.await
 					{
 						error!("Failed to generate thumbnail in the watcher: {e:#?}");
@@ -372,6 +413,10 @@ async fn inner_create_file(
 		}
 	}
 
+	if let Err(e) = apply_rules_to_objects(library, &[object_id]).await {
+		error!("Failed to evaluate tag rules in the watcher: {e:#?}");
+	}
+
 	invalidate_query!(library, "search.paths");
 	invalidate_query!(library, "search.objects");
 
@@ -408,11 +453,11 @@ pub(super) async fn update_file(
 		.exec()
 		.await?
 	{
-		inner_update_file(location_path, file_path, full_path, node, library, None).await
+		inner_update_file(&location_path, file_path, full_path, node, library, None).await
 	} else {
 		inner_create_file(
 			location_id,
-			location_path,
+			&location_path,
 			full_path,
 			&metadata,
 			node,
@@ -423,7 +468,47 @@ pub(super) async fn update_file(
 	.map(|_| {
 		invalidate_query!(library, "search.paths");
 		invalidate_query!(library, "search.objects");
-	})
+	})?;
+
+	import_if_xmp_sidecar(location_id, location_path, full_path, library).await;
+
+	Ok(())
+}
+
+/// Imports a sidecar written by another app, its rating and note replacing the ones in the
+/// library as they're newer.
+async fn import_if_xmp_sidecar(
+	location_id: location::id::Type,
+	location_path: impl AsRef<Path>,
+	path: impl AsRef<Path>,
+	library: &Library,
+) {
+	let path = path.as_ref();
+
+	let is_sidecar = path
+		.extension()
+		.and_then(OsStr::to_str)
+		.is_some_and(|extension| extension.eq_ignore_ascii_case(XMP_EXTENSION));
+
+	if !is_sidecar {
+		return;
+	}
+
+	let result = match IsolatedFilePathData::new(location_id, location_path, path, false) {
+		Ok(iso_file_path) => import_sidecar(library, &iso_file_path, path, true).await,
+		Err(e) => Err(e.into()),
+	};
+
+	match result {
+		Ok(true) => {
+			invalidate_query!(library, "search.paths");
+			invalidate_query!(library, "search.objects");
+			invalidate_query!(library, "tags.list");
+			invalidate_query!(library, "tags.getWithObjects");
+		}
+		Ok(false) => {}
+		Err(e) => error!("Failed to import XMP sidecar in the watcher: {e:#?}"),
+	}
 }
 
 async fn inner_update_file(
@@ -648,13 +733,18 @@ async fn inner_update_file(
 							let node = Arc::clone(node);
 							let path = full_path.to_path_buf();
 							let library_id = library.id;
+							let animated_videos = library.config().await.thumbnails.animated_videos;
 							let old_cas_id = old_cas_id.clone();
 							spawn(async move {
 								let was_overwritten = old_cas_id == cas_id;
 								if let Err(e) = node
 									.thumbnailer
 									.generate_single_indexed_thumbnail(
-										&ext, cas_id, path, library_id,
+										&ext,
+										cas_id,
+										path,
+										library_id,
+										animated_videos,
 									)
 									.await
 								{
@@ -664,14 +754,12 @@ async fn inner_update_file(
 								// If only a few bytes changed, cas_id will probably remains intact
 								// so we overwrote our previous thumbnail, so we can't remove it
 								if !was_overwritten {
-									// remove the old thumbnail as we're generating a new one
-									let thumb_path =
-										get_indexed_thumbnail_path(&node, &old_cas_id, library_id);
-									if let Err(e) = fs::remove_file(&thumb_path).await {
-										error!(
-											"Failed to remove old thumbnail: {:#?}",
-											FileIOError::from((thumb_path, e))
-										);
+									// remove the old thumbnails as we're generating a new one
+									if let Err(e) =
+										remove_indexed_thumbnails(&node, &old_cas_id, library_id)
+											.await
+									{
+										error!("Failed to remove old thumbnails: {e:#?}");
 									}
 								}
 							});
@@ -721,6 +809,20 @@ async fn inner_update_file(
 			}
 		}
 
+		// The file path may be linked to a new object now, so we read it again
+		if let Some(object_id) = db
+			.file_path()
+			.find_unique(file_path::pub_id::equals(file_path.pub_id.clone()))
+			.select(file_path::select!({ object_id }))
+			.exec()
+			.await?
+			.and_then(|file_path| file_path.object_id)
+		{
+			if let Err(e) = apply_rules_to_objects(library, &[object_id]).await {
+				error!("Failed to evaluate tag rules in the watcher: {e:#?}");
+			}
+		}
+
 		invalidate_query!(library, "search.paths");
 		invalidate_query!(library, "search.objects");
 	} else if is_hidden != file_path.hidden.unwrap_or_default() {
@@ -753,11 +855,22 @@ pub(super) async fn rename(
 	new_path: impl AsRef<Path>,
 	old_path: impl AsRef<Path>,
 	new_path_metadata: Metadata,
+	to_recalculate_size: &mut HashMap<PathBuf, Instant>,
 	library: &Library,
 ) -> Result<(), LocationManagerError> {
 	let location_path = extract_location_path(location_id, library).await?;
 	let old_path = old_path.as_ref();
 	let new_path = new_path.as_ref();
+
+	// Moving between directories changes the size of both of them and of their ancestors
+	if let (Some(old_parent), Some(new_parent)) = (old_path.parent(), new_path.parent()) {
+		if old_parent != new_parent {
+			let now = Instant::now();
+			to_recalculate_size.insert(old_parent.to_path_buf(), now);
+			to_recalculate_size.insert(new_parent.to_path_buf(), now);
+		}
+	}
+
 	let Library { db, sync, .. } = library;
 
 	let old_path_materialized_str =
@@ -897,6 +1010,10 @@ pub(super) async fn rename(
 		)
 		.await?;
 
+		library
+			.name_index
+			.insert([(file_path.id, new_parts.name, new_parts.extension)]);
+
 		invalidate_query!(library, "search.paths");
 		invalidate_query!(library, "search.objects");
 	}
@@ -954,6 +1071,9 @@ pub(super) async fn remove_by_file_path(
 					Some(&IsolatedFilePathData::try_from(file_path)?),
 				)
 				.await?;
+
+				// The nodes of its children are dropped by the clients once no query uses them
+				library.evict_cache_nodes(vec![ExplorerItem::file_path_cache_key(file_path.id)]);
 			} else {
 				sync.write_op(
 					db,
@@ -964,8 +1084,13 @@ pub(super) async fn remove_by_file_path(
 				)
 				.await?;
 
+				library.name_index.remove([file_path.id]);
+
+				let mut evicted = vec![ExplorerItem::file_path_cache_key(file_path.id)];
+
 				if let Some(object_id) = file_path.object_id {
-					db.object()
+					let removed_objects = db
+						.object()
 						.delete_many(vec![
 							object::id::equals(object_id),
 							// https://www.prisma.io/docs/reference/api-reference/prisma-client-reference#none
@@ -973,7 +1098,13 @@ pub(super) async fn remove_by_file_path(
 						])
 						.exec()
 						.await?;
+
+					if removed_objects > 0 {
+						evicted.push(ExplorerItem::object_cache_key(object_id));
+					}
 				}
+
+				library.evict_cache_nodes(evicted);
 			}
 		}
 		Err(e) => return Err(FileIOError::from((path, e)).into()),
@@ -1070,9 +1201,11 @@ pub(super) async fn recalculate_directories_size(
 					reverse_update_directories_sizes(path, location_id, location_path, library)
 						.await?;
 					should_invalidate = true;
-				} else {
-					should_update_location_size = true;
 				}
+
+				// The location size adds up the sizes of its top level directories, which are
+				// the last ancestors to be updated
+				should_update_location_size = true;
 			}
 		} else {
 			buffer.push((path, instant));
@@ -122,6 +122,7 @@ impl<'lib> EventHandler<'lib> for WindowsEventHandler<'lib> {
 						fs::metadata(&paths[0])
 							.await
 							.map_err(|e| FileIOError::from((&paths[0], e)))?,
+						&mut self.to_recalculate_size,
 						self.library,
 					)
 					.await?;
@@ -177,6 +178,7 @@ impl<'lib> EventHandler<'lib> for WindowsEventHandler<'lib> {
 						fs::metadata(&new_path)
 							.await
 							.map_err(|e| FileIOError::from((&new_path, e)))?,
+						&mut self.to_recalculate_size,
 						self.library,
 					)
 					.await?;
@@ -198,6 +200,7 @@ impl<'lib> EventHandler<'lib> for WindowsEventHandler<'lib> {
 						fs::metadata(&path)
 							.await
 							.map_err(|e| FileIOError::from((&path, e)))?,
+						&mut self.to_recalculate_size,
 						self.library,
 					)
 					.await?;
@@ -0,0 +1,268 @@
+//! Mirroring of locations to and from the remotes configured in rclone, like `gdrive:Photos`.
+
+use crate::{
+	library::Library,
+	location::indexer::OldIndexerJobInit,
+	object::{
+		media::OldMediaProcessorJobInit,
+		old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
+		xmp::old_xmp_importer_job::OldXmpImporterJobInit,
+	},
+	old_job::{JobBuilder, JobManagerError},
+	Node,
+};
+
+use sd_core_prisma_helpers::location_with_indexer_rules;
+
+use sd_prisma::prisma::location;
+
+use std::{
+	ffi::OsStr,
+	process::{ExitStatus, Stdio},
+	sync::Arc,
+};
+
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	io::{AsyncBufReadExt, BufReader},
+	process::Command,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+pub mod old_mirror_job;
+
+use old_mirror_job::OldMirrorJobInit;
+
+/// Path of the rclone binary, looked up in the `PATH` if not set
+const RCLONE_PATH_VAR: &str = "SD_RCLONE_PATH";
+/// Error messages kept from the rclone logs, to explain why it failed
+const MAX_ERROR_MESSAGES: usize = 5;
+
+/// A mirror of a location, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationMirror {
+	pub id: Uuid,
+	pub location_id: location::id::Type,
+	/// rclone remote and path, like `gdrive:Photos`, set up with `rclone config`
+	pub remote: String,
+	pub direction: MirrorDirection,
+	/// In KiB/s, unlimited if missing
+	#[serde(default)]
+	pub bandwidth_limit_kib: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum MirrorDirection {
+	/// Makes the remote a copy of the location
+	Push,
+	/// Makes the location a copy of the remote, indexing it afterwards
+	Pull,
+}
+
+#[derive(Error, Debug)]
+pub enum MirrorError {
+	#[error("invalid rclone remote '{0}', expected '<remote>:<path>'")]
+	InvalidRemote(String),
+	#[error("location <id='{0}'> isn't a local location of this device, so it can't be mirrored")]
+	NotLocal(location::id::Type),
+	#[error("failed to run rclone, is it installed? {0}")]
+	Run(std::io::Error),
+	#[error("rclone failed with {status}: {message}")]
+	Rclone { status: ExitStatus, message: String },
+}
+
+impl From<MirrorError> for rspc::Error {
+	fn from(e: MirrorError) -> Self {
+		match e {
+			MirrorError::InvalidRemote(_) | MirrorError::NotLocal(_) => {
+				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+/// Checks the location is on this device and not read from a server, as rclone syncs its path
+/// here, deleting the files the remote lacks when pulling.
+pub async fn check_location(
+	library: &Library,
+	location: &location::Data,
+) -> Result<(), MirrorError> {
+	if location.remote.is_some() || location.instance_id != Some(library.config().await.instance_id)
+	{
+		return Err(MirrorError::NotLocal(location.id));
+	}
+
+	Ok(())
+}
+
+/// Checks the remote is a `<remote>:<path>` rclone remote, and not a local path or a flag.
+pub fn validate_remote(remote: &str) -> Result<(), MirrorError> {
+	match remote.split_once(':') {
+		// Single letters are Windows drives
+		Some((name, _))
+			if name.len() > 1 && !name.starts_with('-') && !name.contains(['/', '\\']) =>
+		{
+			Ok(())
+		}
+		_ => Err(MirrorError::InvalidRemote(remote.to_string())),
+	}
+}
+
+/// Runs the mirror as a job, followed by a scan of the location when it was pulled, so the index
+/// matches its new files.
+pub async fn run_mirror(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location: location_with_indexer_rules::Data,
+	mirror: LocationMirror,
+) -> Result<(), JobManagerError> {
+	let location_base_data = location::Data::from(&location);
+	let direction = mirror.direction;
+
+	let job = JobBuilder::new(OldMirrorJobInit {
+		location: location_base_data.clone(),
+		mirror,
+	})
+	.with_action("mirror_location")
+	.with_metadata(json!({"location": location_base_data.clone()}))
+	.build();
+
+	// Only the locations of this device are indexed here
+	match direction {
+		MirrorDirection::Pull if location_base_data.remote.is_none() => job
+			.queue_next(OldIndexerJobInit {
+				location,
+				sub_path: None,
+			})
+			.queue_next(OldFileIdentifierJobInit {
+				location: location_base_data.clone(),
+				sub_path: None,
+			})
+			.queue_next(OldXmpImporterJobInit {
+				location: location_base_data.clone(),
+				sub_path: None,
+			})
+			.queue_next(OldMediaProcessorJobInit {
+				location: location_base_data,
+				sub_path: None,
+				regenerate_thumbnails: false,
+				regenerate_labels: false,
+			}),
+		MirrorDirection::Pull | MirrorDirection::Push => job,
+	}
+	.spawn(node, library)
+	.await
+}
+
+/// Transfer stats logged by rclone.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RcloneStats {
+	pub bytes: u64,
+	pub total_bytes: u64,
+	pub transfers: u64,
+	pub total_transfers: u64,
+	pub errors: u64,
+}
+
+#[derive(Deserialize)]
+struct RcloneLogLine {
+	#[serde(default)]
+	level: String,
+	#[serde(default)]
+	msg: String,
+	#[serde(default)]
+	stats: Option<RcloneStats>,
+}
+
+/// Runs `rclone sync`, making `destination` a copy of `source`, with the stats rclone logs every
+/// second given to `on_stats`.
+///
+/// rclone is killed if the returned future is dropped, like when the job is paused or canceled.
+pub(super) async fn rclone_sync(
+	source: impl AsRef<OsStr>,
+	destination: impl AsRef<OsStr>,
+	bandwidth_limit_kib: Option<u32>,
+	mut on_stats: impl FnMut(&RcloneStats) + Send,
+) -> Result<RcloneStats, MirrorError> {
+	let rclone = std::env::var(RCLONE_PATH_VAR).unwrap_or_else(|_| "rclone".to_string());
+
+	let mut command = Command::new(rclone);
+	command
+		.args([
+			"sync",
+			"--use-json-log",
+			"--stats=1s",
+			"--stats-log-level=NOTICE",
+		])
+		.args(bandwidth_limit_kib.map(|limit| format!("--bwlimit={limit}K")))
+		// Paths starting with a dash aren't taken as flags
+		.arg("--")
+		.arg(source.as_ref())
+		.arg(destination.as_ref())
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true);
+
+	debug!("Running {command:?}");
+
+	let mut child = command.spawn().map_err(MirrorError::Run)?;
+
+	let mut last_stats = RcloneStats::default();
+	let mut error_messages = vec![];
+
+	if let Some(stderr) = child.stderr.take() {
+		let mut lines = BufReader::new(stderr).lines();
+
+		while let Some(line) = lines.next_line().await.map_err(MirrorError::Run)? {
+			let Ok(log_line) = serde_json::from_str::<RcloneLogLine>(&line) else {
+				debug!("rclone: {line}");
+				continue;
+			};
+
+			if let Some(stats) = log_line.stats {
+				on_stats(&stats);
+				last_stats = stats;
+			} else if log_line.level == "error" {
+				warn!("rclone: {}", log_line.msg);
+				if error_messages.len() < MAX_ERROR_MESSAGES {
+					error_messages.push(log_line.msg);
+				}
+			}
+		}
+	}
+
+	let status = child.wait().await.map_err(MirrorError::Run)?;
+
+	if status.success() {
+		Ok(last_stats)
+	} else {
+		Err(MirrorError::Rclone {
+			status,
+			message: error_messages.join("; "),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validates_remotes() {
+		assert!(validate_remote("gdrive:Photos").is_ok());
+		assert!(validate_remote("s3:bucket/path").is_ok());
+		assert!(validate_remote("C:\\Photos").is_err());
+		assert!(validate_remote("/home/user/Photos").is_err());
+		assert!(validate_remote("--config=x:y").is_err());
+	}
+}
@@ -0,0 +1,141 @@
+use crate::old_job::{
+	CurrentStep, JobError, JobInitOutput, JobItemProgress, JobReportUpdate, JobResult,
+	JobRunMetadata, JobStepOutput, StatefulJob, WorkerContext,
+};
+
+use sd_prisma::prisma::location;
+use sd_utils::db::maybe_missing;
+
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use super::{check_location, rclone_sync, validate_remote, LocationMirror, MirrorDirection};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldMirrorJobInit {
+	pub location: location::Data,
+	pub mirror: LocationMirror,
+}
+
+impl Hash for OldMirrorJobInit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		self.mirror.id.hash(state);
+	}
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldMirrorMetadata {
+	transferred_bytes: u64,
+	transferred_files: u64,
+	errors: u64,
+}
+
+impl JobRunMetadata for OldMirrorMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.transferred_bytes += new_data.transferred_bytes;
+		self.transferred_files += new_data.transferred_files;
+		self.errors += new_data.errors;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldMirrorJobInit {
+	type Data = ();
+	type Step = ();
+	type RunMetadata = OldMirrorMetadata;
+
+	const NAME: &'static str = "location_mirror";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location.id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		validate_remote(&self.mirror.remote)?;
+		check_location(&ctx.library, &self.location).await?;
+
+		ctx.progress(vec![JobReportUpdate::Message(
+			match self.mirror.direction {
+				MirrorDirection::Push => format!("Mirroring to {}", self.mirror.remote),
+				MirrorDirection::Pull => format!("Mirroring from {}", self.mirror.remote),
+			},
+		)]);
+
+		// Must fill in the data, otherwise the job will not run
+		*data = Some(());
+
+		// rclone does the whole sync in a single run
+		Ok(vec![()].into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		_: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let location_path = maybe_missing(&self.location.path, "location.path")?;
+		let remote = &self.mirror.remote;
+
+		let (source, destination) = match self.mirror.direction {
+			MirrorDirection::Push => (location_path, remote),
+			MirrorDirection::Pull => (remote, location_path),
+		};
+
+		let stats = rclone_sync(
+			source,
+			destination,
+			self.mirror.bandwidth_limit_kib,
+			|stats| {
+				ctx.progress(vec![
+					JobReportUpdate::TaskCount(stats.total_transfers as usize),
+					JobReportUpdate::CompletedTaskCount(stats.transfers as usize),
+					JobReportUpdate::Item(
+						JobItemProgress::new(remote.clone())
+							.with_bytes(stats.bytes, stats.total_bytes),
+					),
+				])
+			},
+		)
+		.await?;
+
+		Ok(OldMirrorMetadata {
+			transferred_bytes: stats.bytes,
+			transferred_files: stats.transfers,
+			errors: stats.errors,
+		}
+		.into())
+	}
+
+	async fn finalize(
+		&self,
+		_: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		info!(
+			"Mirrored location <id='{}'> {} '{}': {} files and {} bytes transferred",
+			init.location.id,
+			match init.mirror.direction {
+				MirrorDirection::Push => "to",
+				MirrorDirection::Pull => "from",
+			},
+			init.mirror.remote,
+			run_metadata.transferred_files,
+			run_metadata.transferred_bytes,
+		);
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
@@ -4,6 +4,9 @@ use crate::{
 	object::{
 		media::{old_media_processor, OldMediaProcessorJobInit},
 		old_file_identifier::{self, old_file_identifier_job::OldFileIdentifierJobInit},
+		tag::rules::old_tag_rules_job::OldTagRulesJobInit,
+		validation::old_validator_job::OldObjectValidatorJobInit,
+		xmp::old_xmp_importer_job::OldXmpImporterJobInit,
 	},
 	old_job::{JobBuilder, JobError, JobManagerError},
 	Node,
@@ -44,11 +47,15 @@ use uuid::Uuid;
 
 mod error;
 pub mod indexer;
+pub mod ingest;
 mod manager;
 pub mod metadata;
+pub mod mirror;
+pub mod remote;
+pub mod usage;
 
 pub use error::LocationError;
-use indexer::OldIndexerJobInit;
+use indexer::{OldIndexerJobInit, OldRemoteIndexerJobInit};
 pub use manager::{LocationManagerError, Locations};
 use metadata::SpacedriveLocationMetadataFile;
 
@@ -471,7 +478,25 @@ pub async fn scan_location(
 		return Ok(());
 	}
 
+	if location.remote.is_some() {
+		return scan_remote_location(node, library, location).await;
+	}
+
 	let location_base_data = location::Data::from(&location);
+	let location_id = location_base_data.id;
+
+	// The files are only identified by a cas id hashing samples of their contents, so their whole
+	// contents are checksummed last, being the slowest step of the scan
+	let full_checksums =
+		library
+			.config()
+			.await
+			.checksums
+			.in_background
+			.then(|| OldObjectValidatorJobInit {
+				location: location_base_data.clone(),
+				sub_path: None,
+			});
 
 	debug!("Scanning location with state: {location_scan_state:?}");
 
@@ -488,12 +513,21 @@ pub async fn scan_location(
 				location: location_base_data.clone(),
 				sub_path: None,
 			})
+			.queue_next(OldXmpImporterJobInit {
+				location: location_base_data.clone(),
+				sub_path: None,
+			})
 			.queue_next(OldMediaProcessorJobInit {
 				location: location_base_data,
 				sub_path: None,
 				regenerate_thumbnails: false,
 				regenerate_labels: false,
 			})
+			.queue_next(OldTagRulesJobInit {
+				location_id,
+				rule_id: None,
+			})
+			.maybe_queue_next(full_checksums)
 			.spawn(node, library)
 			.await
 		}
@@ -505,12 +539,21 @@ pub async fn scan_location(
 			.with_action("scan_location_already_indexed")
 			.with_metadata(json!({"location": location_base_data.clone()}))
 			.build()
+			.queue_next(OldXmpImporterJobInit {
+				location: location_base_data.clone(),
+				sub_path: None,
+			})
 			.queue_next(OldMediaProcessorJobInit {
 				location: location_base_data,
 				sub_path: None,
 				regenerate_thumbnails: false,
 				regenerate_labels: false,
 			})
+			.queue_next(OldTagRulesJobInit {
+				location_id,
+				rule_id: None,
+			})
+			.maybe_queue_next(full_checksums)
 			.spawn(node, library)
 			.await
 		}
@@ -524,6 +567,11 @@ pub async fn scan_location(
 			.with_action("scan_location_files_already_identified")
 			.with_metadata(json!({"location": location_base_data}))
 			.build()
+			.queue_next(OldTagRulesJobInit {
+				location_id,
+				rule_id: None,
+			})
+			.maybe_queue_next(full_checksums)
 			.spawn(node, library)
 			.await
 		}
@@ -544,7 +592,13 @@ pub async fn scan_location_sub_path(
 		return Ok(());
 	}
 
+	// The whole remote location is listed at once anyway
+	if location.remote.is_some() {
+		return scan_remote_location(node, library, location).await;
+	}
+
 	let location_base_data = location::Data::from(&location);
+	let location_id = location_base_data.id;
 
 	JobBuilder::new(OldIndexerJobInit {
 		location,
@@ -560,12 +614,20 @@ pub async fn scan_location_sub_path(
 		location: location_base_data.clone(),
 		sub_path: Some(sub_path.clone()),
 	})
+	.queue_next(OldXmpImporterJobInit {
+		location: location_base_data.clone(),
+		sub_path: Some(sub_path.clone()),
+	})
 	.queue_next(OldMediaProcessorJobInit {
 		location: location_base_data,
 		sub_path: Some(sub_path),
 		regenerate_thumbnails: false,
 		regenerate_labels: false,
 	})
+	.queue_next(OldTagRulesJobInit {
+		location_id,
+		rule_id: None,
+	})
 	.spawn(node, library)
 	.await
 	.map_err(Into::into)
@@ -584,6 +646,12 @@ pub async fn light_scan_location(
 		return Ok(());
 	}
 
+	// Listing a remote directory is too slow to be done while browsing it, their full scans
+	// keep them up to date instead
+	if location.remote.is_some() {
+		return Ok(());
+	}
+
 	let location_base_data = location::Data::from(&location);
 
 	indexer::old_shallow(&location, &sub_path, &node, &library).await?;
@@ -601,6 +669,24 @@ pub async fn light_scan_location(
 	Ok(())
 }
 
+/// Remote locations are only indexed, as the other jobs read the files from the file system of
+/// this node.
+async fn scan_remote_location(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location: location_with_indexer_rules::Data,
+) -> Result<(), JobManagerError> {
+	let location_base_data = location::Data::from(&location);
+
+	JobBuilder::new(OldRemoteIndexerJobInit { location })
+		.with_action("scan_remote_location")
+		.with_metadata(json!({ "location": location_base_data }))
+		.build()
+		.spawn(node, library)
+		.await
+		.map_err(Into::into)
+}
+
 pub async fn relink_location(
 	Library { db, id, sync, .. }: &Library,
 	location_path: impl AsRef<Path>,
@@ -666,7 +752,10 @@ async fn create_location(
 
 	if db
 		.location()
-		.count(vec![location::path::equals(Some(path.clone()))])
+		.count(vec![
+			location::path::equals(Some(path.clone())),
+			location::remote::equals(None),
+		])
 		.exec()
 		.await? > 0
 	{
@@ -775,7 +864,9 @@ pub async fn delete_location(
 	// TODO: This should really be queued to the proper node so it will always run
 	// TODO: Deal with whether a location is online or not
 	// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
-	if location.instance_id == Some(library.config().await.instance_id) {
+	// Nothing was written to the directories of remote locations
+	if location.instance_id == Some(library.config().await.instance_id) && location.remote.is_none()
+	{
 		if let Some(path) = &location.path {
 			if let Ok(Some(mut metadata)) = SpacedriveLocationMetadataFile::try_load(path).await {
 				metadata
@@ -799,6 +890,12 @@ pub async fn delete_location(
 		start.elapsed()
 	);
 
+	if location.remote.is_some() {
+		if let Err(e) = remote::forget_password(library.id, &location.pub_id) {
+			warn!("Failed to forget the password of remote location <id='{location_id}'>: {e:#?}");
+		}
+	}
+
 	let start = Instant::now();
 
 	library
@@ -862,8 +959,19 @@ pub async fn delete_directory(
 		})],
 	);
 
+	let removed = db
+		.file_path()
+		.find_many(children_params.clone())
+		.select(file_path::select!({ id }))
+		.exec()
+		.await?;
+
 	db.file_path().delete_many(children_params).exec().await?;
 
+	library
+		.name_index
+		.remove(removed.into_iter().map(|file_path| file_path.id));
+
 	// library.orphan_remover.invoke().await;
 
 	invalidate_query!(library, "search.paths");
@@ -989,6 +1097,11 @@ pub async fn get_location_path_from_location_id(
 			maybe_location
 				.ok_or(LocationError::IdNotFound(location_id))
 				.and_then(|location| {
+					// The path of a remote location is on its server, not on this node
+					if location.remote.is_some() {
+						return Err(LocationError::RemoteLocation(location_id));
+					}
+
 					location
 						.path
 						.map(PathBuf::from)
@@ -998,7 +1111,7 @@ pub async fn get_location_path_from_location_id(
 }
 
 pub async fn create_file_path(
-	crate::location::Library { db, sync, .. }: &crate::location::Library,
+	library @ crate::location::Library { db, sync, .. }: &crate::location::Library,
 	IsolatedFilePathDataParts {
 		materialized_path,
 		is_dir,
@@ -1099,5 +1212,9 @@ pub async fn create_file_path(
 		)
 		.await?;
 
+	library
+		.name_index
+		.insert([(created_path.id, name, extension)]);
+
 	Ok(created_path)
 }
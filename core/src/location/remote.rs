@@ -0,0 +1,579 @@
+//! Locations whose files are on a server reached over the network instead of the file system of
+//! this node, listed through OpenDAL by the [`OldRemoteIndexerJobInit`] job.
+//!
+//! Their servers can't tell about changes like the file systems watched for the local locations,
+//! so they're listed periodically by [`poll_periodically`], scanning the ones that changed.
+//!
+//! [`OldRemoteIndexerJobInit`]: super::indexer::old_remote_indexer_job::OldRemoteIndexerJobInit
+
+use crate::{invalidate_query, library::Library, Node};
+
+use sd_core_prisma_helpers::location_with_indexer_rules;
+use sd_prisma::{prisma::location, prisma_sync};
+use sd_sync::*;
+use sd_utils::msgpack;
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use opendal::Operator;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::time::{self, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::{
+	find_location, indexer::old_remote_indexer_job::remote_listing_fingerprint,
+	link_location_and_indexer_rules, scan_remote_location, LocationError,
+};
+
+/// Time between the listings of the remote locations looking for changes on their servers
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The server the files of a remote location are read from, kept as JSON in its `remote` column.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum RemoteSource {
+	Sftp(SftpConnection),
+	Webdav(WebdavConnection),
+}
+
+impl RemoteSource {
+	/// The remote source of a location, `None` for the ones on the file system of this node.
+	pub fn of(
+		location_id: location::id::Type,
+		remote: Option<&str>,
+	) -> Result<Option<Self>, LocationError> {
+		remote
+			.map(|remote| {
+				serde_json::from_str(remote).map_err(|source| LocationError::InvalidRemoteSource {
+					id: location_id,
+					source,
+				})
+			})
+			.transpose()
+	}
+
+	/// Refers to the server in errors and in the UI, without any of its secrets.
+	pub fn endpoint(&self) -> String {
+		match self {
+			Self::Sftp(sftp) => sftp.endpoint(),
+			Self::Webdav(webdav) => webdav.endpoint.clone(),
+		}
+	}
+
+	/// The OpenDAL operator reading the files of the server, at the root of its file system.
+	/// The password is only used by the sources logging in with one.
+	pub fn operator(&self, password: Option<&str>) -> Result<Operator, LocationError> {
+		match self {
+			Self::Sftp(sftp) => sftp.operator(),
+			Self::Webdav(webdav) => webdav.operator(password),
+		}
+	}
+
+	/// The operator of a remote location, logging in with the password kept for it in the keyring.
+	pub fn location_operator(
+		&self,
+		library_id: Uuid,
+		location_pub_id: &[u8],
+	) -> Result<Operator, LocationError> {
+		let password = match self {
+			Self::Sftp(_) => None,
+			Self::Webdav(_) => keystore::load(library_id, location_pub_id)?,
+		};
+
+		self.operator(password.as_deref())
+	}
+}
+
+/// A SSH server whose files are read over SFTP.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SftpConnection {
+	pub host: String,
+	/// 22 if missing
+	#[serde(default)]
+	#[specta(optional)]
+	pub port: Option<u16>,
+	/// The user of the `ssh` configuration of this node if missing
+	#[serde(default)]
+	#[specta(optional)]
+	pub user: Option<String>,
+	pub auth: SftpAuth,
+}
+
+/// How to log into the SSH server. Passwords aren't supported, as OpenDAL connects through the
+/// `ssh` binary of this node, which can't be given one.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SftpAuth {
+	/// The keys of the SSH agent and of the `ssh` configuration of this node
+	Agent,
+	/// A private key file on this node
+	Key { path: PathBuf },
+}
+
+impl SftpConnection {
+	/// Like `ssh://user@example.com:22`
+	pub fn endpoint(&self) -> String {
+		match &self.user {
+			Some(user) => format!("ssh://{user}@{}:{}", self.host, self.port.unwrap_or(22)),
+			None => format!("ssh://{}:{}", self.host, self.port.unwrap_or(22)),
+		}
+	}
+
+	/// The host and user are given to the `ssh` binary, so they must not be taken for its options
+	/// or split into more arguments.
+	fn validate(&self) -> Result<(), LocationError> {
+		let is_invalid = |value: &str| {
+			value.is_empty()
+				|| value.starts_with('-')
+				|| value.contains(|c: char| c.is_whitespace() || c.is_control() || c == '@')
+		};
+
+		if is_invalid(&self.host) {
+			return Err(LocationError::InvalidRemoteHost(self.host.clone()));
+		}
+
+		if let Some(user) = self.user.as_deref().filter(|user| is_invalid(user)) {
+			return Err(LocationError::InvalidRemoteHost(format!(
+				"{user}@{}",
+				self.host
+			)));
+		}
+
+		Ok(())
+	}
+
+	#[cfg(unix)]
+	pub fn operator(&self) -> Result<Operator, LocationError> {
+		use opendal::services::Sftp;
+
+		self.validate()?;
+
+		let mut sftp = Sftp::default();
+		sftp.endpoint(&format!("ssh://{}:{}", self.host, self.port.unwrap_or(22)));
+		sftp.root("/");
+		if let Some(user) = &self.user {
+			sftp.user(user);
+		}
+		if let SftpAuth::Key { path } = &self.auth {
+			sftp.key(&path.to_string_lossy());
+		}
+
+		Ok(Operator::new(sftp)
+			.map_err(|source| LocationError::Remote {
+				endpoint: self.endpoint(),
+				source,
+			})?
+			.finish())
+	}
+
+	#[cfg(not(unix))]
+	pub fn operator(&self) -> Result<Operator, LocationError> {
+		Err(LocationError::UnsupportedRemote("SFTP"))
+	}
+}
+
+/// The operator of a remote location over SFTP, for browsing its server outside of the indexed
+/// directory. Browsing is only allowed with the connection stored for a location, so the SSH keys
+/// of this node can't be used to reach any other server.
+pub async fn sftp_location_operator(
+	library: &Library,
+	location_id: location::id::Type,
+) -> Result<Operator, LocationError> {
+	let location = find_location(library, location_id)
+		.select(location::select!({ pub_id remote }))
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+
+	match RemoteSource::of(location_id, location.remote.as_deref())? {
+		Some(source @ RemoteSource::Sftp(_)) => {
+			source.location_operator(library.id, &location.pub_id)
+		}
+		_ => Err(LocationError::NotSftpLocation(location_id)),
+	}
+}
+
+/// A WebDAV server, like the ones of Nextcloud and ownCloud.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebdavConnection {
+	/// Like `https://cloud.example.com/remote.php/dav/files/user` for Nextcloud
+	pub endpoint: String,
+	/// The server is read anonymously if missing
+	#[serde(default)]
+	#[specta(optional)]
+	pub user: Option<String>,
+}
+
+impl WebdavConnection {
+	pub fn operator(&self, password: Option<&str>) -> Result<Operator, LocationError> {
+		use opendal::services::Webdav;
+
+		let mut webdav = Webdav::default();
+		webdav.endpoint(&self.endpoint);
+		webdav.root("/");
+		if let Some(user) = &self.user {
+			webdav.username(user);
+		}
+		if let Some(password) = password {
+			webdav.password(password);
+		}
+
+		Ok(Operator::new(webdav)
+			.map_err(|source| LocationError::Remote {
+				endpoint: self.endpoint.clone(),
+				source,
+			})?
+			.finish())
+	}
+}
+
+/// Lists the remote locations of the library periodically, until it's unloaded, scanning the ones
+/// whose listing changed since the previous poll.
+///
+/// Every remote location is scanned on the first poll, as they may have changed while the node
+/// was off.
+pub(crate) async fn poll_periodically(node: Arc<Node>, library_id: Uuid) {
+	let mut interval = time::interval_at(Instant::now() + POLL_INTERVAL, POLL_INTERVAL);
+	let mut fingerprints = HashMap::new();
+
+	loop {
+		interval.tick().await;
+
+		let Some(library) = node.libraries.get_library(&library_id).await else {
+			break;
+		};
+
+		if let Err(e) = poll_remote_locations(&node, &library, &mut fingerprints).await {
+			warn!("Failed to poll the remote locations of library <id='{library_id}'>: {e:#?}");
+		}
+	}
+}
+
+async fn poll_remote_locations(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	fingerprints: &mut HashMap<location::id::Type, u64>,
+) -> Result<(), LocationError> {
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![
+			location::remote::not(None),
+			location::instance_id::equals(Some(library.config().await.instance_id)),
+		])
+		.include(location_with_indexer_rules::include())
+		.exec()
+		.await?;
+
+	fingerprints
+		.retain(|location_id, _| locations.iter().any(|location| location.id == *location_id));
+
+	for location in locations {
+		let location_id = location.id;
+		let Some(source) = RemoteSource::of(location_id, location.remote.as_deref())? else {
+			continue;
+		};
+
+		// The server may just be unreachable for now, it's listed again on the next poll
+		let fingerprint = match remote_listing_fingerprint(library.id, &location, &source).await {
+			Ok(fingerprint) => fingerprint,
+			Err(e) => {
+				debug!("Failed to list remote location <id='{location_id}'>: {e}");
+				continue;
+			}
+		};
+
+		if fingerprints.get(&location_id) == Some(&fingerprint) {
+			continue;
+		}
+
+		debug!(
+			"Remote location <id='{location_id}'> changed on {}, scanning it",
+			source.endpoint()
+		);
+
+		// Fails while the location is still being scanned, so it's compared again on the next poll
+		match scan_remote_location(node, library, location).await {
+			Ok(()) => {
+				fingerprints.insert(location_id, fingerprint);
+			}
+			Err(e) => debug!("Failed to scan remote location <id='{location_id}'>: {e:#?}"),
+		}
+	}
+
+	Ok(())
+}
+
+/// Forgets the password of a remote location, when it's deleted.
+pub fn forget_password(library_id: Uuid, location_pub_id: &[u8]) -> Result<(), LocationError> {
+	keystore::remove(library_id, location_pub_id)
+}
+
+/// The passwords of the remote locations are kept in the keyring of the OS, away from the database
+/// which is synced and backed up. The key manager of the libraries isn't ported to this version
+/// yet, so the keyring is used directly.
+///
+/// On Linux it's the one of Secret Service, like GNOME Keyring or KWallet, as the keys of the
+/// kernel keyring don't survive a reboot.
+#[cfg(all(
+	feature = "keyring",
+	any(target_os = "linux", target_os = "macos", target_os = "ios")
+))]
+mod keystore {
+	use sd_crypto::{
+		keyring::{Identifier, Keyring, KeyringBackend},
+		Protected,
+	};
+	use sd_utils::from_bytes_to_uuid;
+
+	use uuid::Uuid;
+
+	use super::LocationError;
+
+	const APPLICATION: &str = "Spacedrive";
+	const USAGE: &str = "Remote location password";
+
+	fn keyring() -> Result<Keyring, LocationError> {
+		#[cfg(target_os = "linux")]
+		let backend = KeyringBackend::Linux(sd_crypto::keyring::LinuxKeyring::SecretService);
+		#[cfg(target_os = "macos")]
+		let backend = KeyringBackend::MacOS;
+		#[cfg(target_os = "ios")]
+		let backend = KeyringBackend::Ios;
+
+		Keyring::new(backend).map_err(|e| LocationError::Keystore(e.to_string()))
+	}
+
+	fn identifier(library_id: Uuid, location_pub_id: &[u8]) -> Identifier {
+		Identifier::new(
+			&format!("{library_id}/{}", from_bytes_to_uuid(location_pub_id)),
+			USAGE,
+			APPLICATION,
+		)
+	}
+
+	pub(super) fn store(
+		library_id: Uuid,
+		location_pub_id: &[u8],
+		password: &str,
+	) -> Result<(), LocationError> {
+		keyring()?
+			.insert(
+				&identifier(library_id, location_pub_id),
+				Protected::new(password.as_bytes().to_vec()),
+			)
+			.map_err(|e| LocationError::Keystore(e.to_string()))
+	}
+
+	pub(super) fn load(
+		library_id: Uuid,
+		location_pub_id: &[u8],
+	) -> Result<Option<String>, LocationError> {
+		let keyring = keyring()?;
+		let identifier = identifier(library_id, location_pub_id);
+
+		if !keyring.contains_key(&identifier) {
+			return Ok(None);
+		}
+
+		keyring
+			.get(&identifier)
+			.map_err(|e| LocationError::Keystore(e.to_string()))
+			.and_then(|password| {
+				String::from_utf8(password.expose().clone())
+					.map(Some)
+					.map_err(|e| LocationError::Keystore(e.to_string()))
+			})
+	}
+
+	pub(super) fn remove(library_id: Uuid, location_pub_id: &[u8]) -> Result<(), LocationError> {
+		let keyring = keyring()?;
+		let identifier = identifier(library_id, location_pub_id);
+
+		if keyring.contains_key(&identifier) {
+			keyring
+				.remove(&identifier)
+				.map_err(|e| LocationError::Keystore(e.to_string()))?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Without a keyring, only the remote locations logging in without a password can be created.
+#[cfg(not(all(
+	feature = "keyring",
+	any(target_os = "linux", target_os = "macos", target_os = "ios")
+)))]
+mod keystore {
+	use uuid::Uuid;
+
+	use super::LocationError;
+
+	pub(super) fn store(_: Uuid, _: &[u8], _: &str) -> Result<(), LocationError> {
+		Err(LocationError::Keystore(
+			"this node has no keyring to keep passwords in".to_string(),
+		))
+	}
+
+	#[allow(clippy::unnecessary_wraps)]
+	pub(super) fn load(_: Uuid, _: &[u8]) -> Result<Option<String>, LocationError> {
+		Ok(None)
+	}
+
+	#[allow(clippy::unnecessary_wraps)]
+	pub(super) fn remove(_: Uuid, _: &[u8]) -> Result<(), LocationError> {
+		Ok(())
+	}
+}
+
+/// `RemoteLocationCreateArgs` is the argument received from the client using `rspc` to create a
+/// location for a directory of a remote server. Unlike the locations on this node, nothing is
+/// written to the directory, and the connection is checked before creating it.
+#[derive(Type, Deserialize)]
+pub struct RemoteLocationCreateArgs {
+	/// The name of the directory if missing
+	#[specta(optional)]
+	pub name: Option<String>,
+	pub source: RemoteSource,
+	/// The absolute path of the directory on the server
+	pub path: String,
+	/// Kept in the keyring of this node, for the sources logging in with one
+	#[specta(optional)]
+	pub password: Option<String>,
+	pub indexer_rules_ids: Vec<i32>,
+}
+
+impl RemoteLocationCreateArgs {
+	pub async fn create(
+		self,
+		library @ Library { db, sync, .. }: &Library,
+	) -> Result<location_with_indexer_rules::Data, LocationError> {
+		let Self {
+			name,
+			source,
+			path,
+			password,
+			indexer_rules_ids,
+		} = self;
+
+		let path = match path.trim_end_matches('/') {
+			"" => "/".to_string(),
+			path if path.starts_with('/') => path.to_string(),
+			path => format!("/{path}"),
+		};
+
+		let endpoint = source.endpoint();
+		let metadata = source
+			.operator(password.as_deref())?
+			.stat(&format!("{}/", path.trim_end_matches('/')))
+			.await
+			.map_err(|source| LocationError::Remote { endpoint, source })?;
+		if !metadata.is_dir() {
+			return Err(LocationError::NotDirectory(PathBuf::from(&path).into()));
+		}
+
+		let remote = serde_json::to_string(&source).expect("remote sources always serialize");
+
+		if db
+			.location()
+			.count(vec![
+				location::path::equals(Some(path.clone())),
+				location::remote::equals(Some(remote.clone())),
+			])
+			.exec()
+			.await? > 0
+		{
+			return Err(LocationError::LocationAlreadyExists(
+				PathBuf::from(&path).into(),
+			));
+		}
+
+		let name = name.unwrap_or_else(|| {
+			path.rsplit('/')
+				.find(|part| !part.is_empty())
+				.map_or_else(|| source.endpoint(), str::to_string)
+		});
+		let location_pub_id = Uuid::new_v4();
+		let date_created = Utc::now();
+
+		if let Some(password) = &password {
+			keystore::store(library.id, location_pub_id.as_bytes(), password)?;
+		}
+
+		let location = sync
+			.write_ops(
+				db,
+				(
+					sync.shared_create(
+						prisma_sync::location::SyncId {
+							pub_id: location_pub_id.as_bytes().to_vec(),
+						},
+						[
+							(location::name::NAME, msgpack!(&name)),
+							(location::path::NAME, msgpack!(&path)),
+							(location::date_created::NAME, msgpack!(date_created)),
+						],
+					),
+					// The connection stays on this node, as the other ones may not reach the server
+					db.location().create(
+						location_pub_id.as_bytes().to_vec(),
+						vec![
+							location::name::set(Some(name)),
+							location::path::set(Some(path)),
+							location::date_created::set(Some(date_created.into())),
+							location::instance_id::set(Some(library.config().await.instance_id)),
+							location::remote::set(Some(remote)),
+						],
+					),
+				),
+			)
+			.await?;
+
+		debug!("New remote location created in db");
+
+		if !indexer_rules_ids.is_empty() {
+			link_location_and_indexer_rules(library, location.id, &indexer_rules_ids).await?;
+		}
+
+		let location = find_location(library, location.id)
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(location.id))?;
+
+		invalidate_query!(library, "locations.list");
+
+		Ok(location)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sftp_hosts_cant_be_ssh_options() {
+		let connection = |host: &str, user: Option<&str>| SftpConnection {
+			host: host.to_string(),
+			port: None,
+			user: user.map(str::to_string),
+			auth: SftpAuth::Agent,
+		};
+
+		assert!(connection("example.com", Some("me")).validate().is_ok());
+		assert!(connection("-oProxyCommand=touch /tmp/pwned", None)
+			.validate()
+			.is_err());
+		assert!(connection("example.com -p 2222", None).validate().is_err());
+		assert!(connection("", None).validate().is_err());
+		assert!(connection("example.com", Some("-l root"))
+			.validate()
+			.is_err());
+	}
+}
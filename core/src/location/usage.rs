@@ -0,0 +1,179 @@
+use sd_prisma::prisma::{file_path, location, PrismaClient, SortOrder};
+use sd_utils::db::size_in_bytes_from_db;
+
+use std::collections::BTreeMap;
+
+use prisma_client_rust::QueryError;
+use serde::Serialize;
+use specta::Type;
+
+/// Number of file paths read at once from the database while adding up the sizes
+const BATCH_SIZE: i64 = 10_000;
+/// Deepest tree that can be asked for, as the UI can't draw anything smaller anyway
+pub const MAX_USAGE_TREE_DEPTH: u8 = 8;
+
+/// A directory with the size and count of every file under it, for treemaps of the storage used by
+/// a location.
+#[derive(Serialize, Type, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTreeNode {
+	name: String,
+	/// Relative to the location, like `/photos/2024/`
+	materialized_path: String,
+	size_in_bytes: String,
+	file_count: u32,
+	/// Biggest first, missing beyond the depth of the tree, where the whole directory is counted
+	/// in its deepest ancestor
+	children: Vec<UsageTreeNode>,
+}
+
+#[derive(Default, Debug)]
+struct DirectoryUsage {
+	size: u64,
+	file_count: u32,
+	children: BTreeMap<String, DirectoryUsage>,
+}
+
+impl DirectoryUsage {
+	/// Counts a file in this directory and in its sub directories along `relative_path`, up to
+	/// `depth` of them.
+	fn add_file(&mut self, relative_path: &str, size: u64, depth: u8) {
+		let mut directory = self;
+		let mut components = relative_path
+			.split('/')
+			.filter(|component| !component.is_empty())
+			.take(usize::from(depth));
+
+		loop {
+			directory.size += size;
+			directory.file_count += 1;
+
+			let Some(component) = components.next() else {
+				break;
+			};
+
+			directory = directory.children.entry(component.to_string()).or_default();
+		}
+	}
+
+	fn into_node(self, name: String, materialized_path: String) -> UsageTreeNode {
+		let mut children = self
+			.children
+			.into_iter()
+			.map(|(name, child)| {
+				let child_materialized_path = format!("{materialized_path}{name}/");
+				(child.size, child.into_node(name, child_materialized_path))
+			})
+			.collect::<Vec<_>>();
+
+		children.sort_by(|(a_size, a), (b_size, b)| b_size.cmp(a_size).then(a.name.cmp(&b.name)));
+
+		UsageTreeNode {
+			name,
+			materialized_path,
+			size_in_bytes: self.size.to_string(),
+			file_count: self.file_count,
+			children: children.into_iter().map(|(_, child)| child).collect(),
+		}
+	}
+}
+
+/// Adds up the sizes of the files under the directory with `materialized_path` into a tree of its
+/// sub directories, `depth` levels deep.
+///
+/// The file paths are read in batches, so big locations don't have to be in memory all at once.
+pub async fn usage_tree(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+	materialized_path: String,
+	depth: u8,
+) -> Result<UsageTreeNode, QueryError> {
+	let depth = depth.min(MAX_USAGE_TREE_DEPTH);
+	let mut usage = DirectoryUsage::default();
+	let mut last_id = 0;
+
+	loop {
+		let file_paths = db
+			.file_path()
+			.find_many(vec![
+				file_path::location_id::equals(Some(location_id)),
+				file_path::materialized_path::starts_with(materialized_path.clone()),
+				file_path::is_dir::equals(Some(false)),
+				file_path::id::gt(last_id),
+			])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(file_path::select!({ id materialized_path size_in_bytes_bytes }))
+			.exec()
+			.await?;
+
+		let Some(last) = file_paths.last() else {
+			break;
+		};
+		last_id = last.id;
+		let is_last_batch = (file_paths.len() as i64) < BATCH_SIZE;
+
+		for file_path in file_paths {
+			let Some(file_materialized_path) = file_path.materialized_path else {
+				continue;
+			};
+
+			usage.add_file(
+				file_materialized_path
+					.strip_prefix(&materialized_path)
+					.unwrap_or_default(),
+				file_path
+					.size_in_bytes_bytes
+					.as_deref()
+					.map(size_in_bytes_from_db)
+					.unwrap_or_default(),
+				depth,
+			);
+		}
+
+		if is_last_batch {
+			break;
+		}
+	}
+
+	let name = materialized_path
+		.trim_end_matches('/')
+		.rsplit('/')
+		.next()
+		.unwrap_or_default()
+		.to_string();
+
+	Ok(usage.into_node(name, materialized_path))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn adds_up_files_into_their_ancestors_up_to_the_depth() {
+		let mut usage = DirectoryUsage::default();
+		usage.add_file("", 1, 1);
+		usage.add_file("photos/", 10, 1);
+		usage.add_file("photos/2024/", 100, 1);
+		usage.add_file("music/", 5, 1);
+
+		let tree = usage.into_node(String::new(), "/".to_string());
+
+		assert_eq!(tree.size_in_bytes, "116");
+		assert_eq!(tree.file_count, 4);
+
+		let names = tree
+			.children
+			.iter()
+			.map(|child| (child.name.as_str(), child.size_in_bytes.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(names, [("photos", "110"), ("music", "5")]);
+
+		let photos = &tree.children[0];
+		assert_eq!(photos.materialized_path, "/photos/");
+		assert_eq!(photos.file_count, 2);
+		// `2024` is beyond the depth of the tree
+		assert!(photos.children.is_empty());
+	}
+}
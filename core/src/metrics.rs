@@ -0,0 +1,298 @@
+//! Counters about jobs and core services, exposed in the Prometheus text format so headless
+//! nodes can be monitored.
+
+use crate::{
+	old_job::{JobReport, JobStatus},
+	Node,
+};
+
+use std::{
+	collections::BTreeMap,
+	fmt::Write,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex,
+	},
+};
+
+use once_cell::sync::Lazy;
+
+pub(crate) static METRICS: Lazy<Metrics> = Lazy::new(Metrics::default);
+
+#[derive(Debug, Default)]
+struct JobCounters {
+	runs: u64,
+	completed: u64,
+	completed_with_errors: u64,
+	failed: u64,
+	canceled: u64,
+	retried: u64,
+	errors: u64,
+	duration_secs_sum: f64,
+	duration_count: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IndexerOperation {
+	Created,
+	Updated,
+	Removed,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+	jobs: Mutex<BTreeMap<String, JobCounters>>,
+	indexer_created_paths: AtomicU64,
+	indexer_updated_paths: AtomicU64,
+	indexer_removed_paths: AtomicU64,
+	thumbnails_queued: AtomicU64,
+	thumbnails_generated: AtomicU64,
+	thumbnails_failed: AtomicU64,
+}
+
+impl Metrics {
+	fn with_job_counters(&self, job_name: &str, f: impl FnOnce(&mut JobCounters)) {
+		let mut jobs = self
+			.jobs
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		if let Some(counters) = jobs.get_mut(job_name) {
+			f(counters);
+		} else {
+			f(jobs.entry(job_name.to_string()).or_default());
+		}
+	}
+
+	/// A job started running, either for the first time or after being paused or retried.
+	pub(crate) fn record_job_run(&self, job_name: &str) {
+		self.with_job_counters(job_name, |counters| counters.runs += 1);
+	}
+
+	/// Accounts for a job run that just ended, based on the status of its report.
+	pub(crate) fn record_job_outcome(&self, report: &JobReport) {
+		self.with_job_counters(&report.name, |counters| {
+			match report.status {
+				JobStatus::Completed => counters.completed += 1,
+				JobStatus::CompletedWithErrors => counters.completed_with_errors += 1,
+				JobStatus::Failed => counters.failed += 1,
+				JobStatus::Canceled => counters.canceled += 1,
+				// A failed job waiting for a retry goes back to the queue
+				JobStatus::Queued => counters.retried += 1,
+				JobStatus::Running | JobStatus::Paused => return,
+			}
+
+			counters.errors += report.errors_text.len() as u64;
+
+			if let Some((started_at, completed_at)) = report.started_at.zip(report.completed_at) {
+				counters.duration_secs_sum +=
+					(completed_at - started_at).num_milliseconds() as f64 / 1000.0;
+				counters.duration_count += 1;
+			}
+		});
+	}
+
+	pub(crate) fn record_indexed_paths(&self, operation: IndexerOperation, count: u64) {
+		match operation {
+			IndexerOperation::Created => &self.indexer_created_paths,
+			IndexerOperation::Updated => &self.indexer_updated_paths,
+			IndexerOperation::Removed => &self.indexer_removed_paths,
+		}
+		.fetch_add(count, Ordering::Relaxed);
+	}
+
+	pub(crate) fn record_thumbnails_queued(&self, count: u64) {
+		self.thumbnails_queued.fetch_add(count, Ordering::Relaxed);
+	}
+
+	pub(crate) fn record_thumbnail_processed(&self, success: bool) {
+		if success {
+			&self.thumbnails_generated
+		} else {
+			&self.thumbnails_failed
+		}
+		.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn thumbnails_backlog(&self) -> u64 {
+		self.thumbnails_queued
+			.load(Ordering::Relaxed)
+			.saturating_sub(
+				self.thumbnails_generated.load(Ordering::Relaxed)
+					+ self.thumbnails_failed.load(Ordering::Relaxed),
+			)
+	}
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+	writeln!(out, "# HELP {name} {help}").ok();
+	writeln!(out, "# TYPE {name} {kind}").ok();
+}
+
+/// Renders all metrics of the node in the Prometheus text exposition format.
+pub async fn render(node: &Node) -> String {
+	let mut out = String::new();
+
+	write_header(
+		&mut out,
+		"sd_libraries",
+		"gauge",
+		"Libraries loaded on this node",
+	);
+	writeln!(out, "sd_libraries {}", node.libraries.get_all().await.len()).ok();
+
+	write_header(
+		&mut out,
+		"sd_jobs_queued",
+		"gauge",
+		"Jobs waiting for a free worker",
+	);
+	writeln!(out, "sd_jobs_queued {}", node.old_jobs.queued_count().await).ok();
+
+	let mut running = BTreeMap::<_, u64>::new();
+	for job_name in node.old_jobs.running_job_names().await {
+		*running.entry(job_name).or_default() += 1;
+	}
+
+	write_header(
+		&mut out,
+		"sd_jobs_running",
+		"gauge",
+		"Jobs running right now, including paused ones",
+	);
+	for (job_name, count) in running {
+		writeln!(out, "sd_jobs_running{{name=\"{job_name}\"}} {count}").ok();
+	}
+
+	{
+		let jobs = METRICS
+			.jobs
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		write_header(
+			&mut out,
+			"sd_job_runs_total",
+			"counter",
+			"Times a job started running, including resumes and retries",
+		);
+		for (job_name, counters) in jobs.iter() {
+			writeln!(
+				out,
+				"sd_job_runs_total{{name=\"{job_name}\"}} {}",
+				counters.runs
+			)
+			.ok();
+		}
+
+		write_header(
+			&mut out,
+			"sd_jobs_finished_total",
+			"counter",
+			"Job runs that ended, by their final status",
+		);
+		for (job_name, counters) in jobs.iter() {
+			for (status, count) in [
+				("completed", counters.completed),
+				("completed_with_errors", counters.completed_with_errors),
+				("failed", counters.failed),
+				("canceled", counters.canceled),
+				("retried", counters.retried),
+			] {
+				writeln!(
+					out,
+					"sd_jobs_finished_total{{name=\"{job_name}\",status=\"{status}\"}} {count}"
+				)
+				.ok();
+			}
+		}
+
+		write_header(
+			&mut out,
+			"sd_job_errors_total",
+			"counter",
+			"Non critical errors reported by jobs",
+		);
+		for (job_name, counters) in jobs.iter() {
+			writeln!(
+				out,
+				"sd_job_errors_total{{name=\"{job_name}\"}} {}",
+				counters.errors
+			)
+			.ok();
+		}
+
+		write_header(
+			&mut out,
+			"sd_job_duration_seconds",
+			"summary",
+			"Time between a job start and its completion",
+		);
+		for (job_name, counters) in jobs.iter() {
+			writeln!(
+				out,
+				"sd_job_duration_seconds_sum{{name=\"{job_name}\"}} {}",
+				counters.duration_secs_sum
+			)
+			.ok();
+			writeln!(
+				out,
+				"sd_job_duration_seconds_count{{name=\"{job_name}\"}} {}",
+				counters.duration_count
+			)
+			.ok();
+		}
+	}
+
+	write_header(
+		&mut out,
+		"sd_indexer_paths_total",
+		"counter",
+		"File paths written to the database by the indexer",
+	);
+	for (operation, counter) in [
+		("created", &METRICS.indexer_created_paths),
+		("updated", &METRICS.indexer_updated_paths),
+		("removed", &METRICS.indexer_removed_paths),
+	] {
+		writeln!(
+			out,
+			"sd_indexer_paths_total{{operation=\"{operation}\"}} {}",
+			counter.load(Ordering::Relaxed)
+		)
+		.ok();
+	}
+
+	write_header(
+		&mut out,
+		"sd_thumbnails_processed_total",
+		"counter",
+		"Thumbnails processed by the thumbnailer",
+	);
+	for (result, counter) in [
+		("generated", &METRICS.thumbnails_generated),
+		("failed", &METRICS.thumbnails_failed),
+	] {
+		writeln!(
+			out,
+			"sd_thumbnails_processed_total{{result=\"{result}\"}} {}",
+			counter.load(Ordering::Relaxed)
+		)
+		.ok();
+	}
+
+	write_header(
+		&mut out,
+		"sd_thumbnails_backlog",
+		"gauge",
+		"Thumbnails waiting to be processed by the thumbnailer",
+	);
+	writeln!(
+		out,
+		"sd_thumbnails_backlog {}",
+		METRICS.thumbnails_backlog()
+	)
+	.ok();
+
+	out
+}
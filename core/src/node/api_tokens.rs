@@ -0,0 +1,243 @@
+//! Tokens authenticating the HTTP gateway of a node, for the REST API and the rspc endpoint
+//! of `sd-server`.
+//!
+//! Only the hash of a token is stored, its secret is shown once when it's created. Every token
+//! has its own scopes and rate limit.
+
+use std::{
+	collections::HashMap,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Prefix of the token secrets, so they are easy to spot in configuration files and logs
+const SECRET_PREFIX: &str = "sd_";
+
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 120;
+pub const MAX_REQUESTS_PER_MINUTE: u32 = 10_000;
+
+#[derive(
+	Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiTokenScope {
+	/// Queries, and downloading files and thumbnails
+	Read,
+	/// Mutations, and anything else changing the node or its libraries
+	Write,
+	/// Managing the API tokens, and the rspc websocket which can call any procedure
+	Admin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+	pub id: Uuid,
+	pub name: String,
+	pub scopes: Vec<ApiTokenScope>,
+	pub requests_per_minute: u32,
+	pub created_at: DateTime<Utc>,
+	/// Hex encoded blake3 hash of the secret
+	hash: String,
+}
+
+impl ApiToken {
+	pub fn has_scope(&self, scope: ApiTokenScope) -> bool {
+		self.scopes.contains(&scope)
+	}
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct ApiTokenPreferences {
+	#[serde(default)]
+	tokens: Vec<ApiToken>,
+}
+
+impl ApiTokenPreferences {
+	pub fn tokens(&self) -> &[ApiToken] {
+		&self.tokens
+	}
+
+	/// Creates a token, returning it with its secret which can't be retrieved later.
+	pub fn create(
+		&mut self,
+		name: String,
+		scopes: Vec<ApiTokenScope>,
+		requests_per_minute: u32,
+	) -> (ApiToken, String) {
+		let mut bytes = [0; 32];
+		OsRng.fill_bytes(&mut bytes);
+		let secret = format!("{SECRET_PREFIX}{}", hex::encode(bytes));
+
+		let mut scopes = scopes;
+		scopes.sort();
+		scopes.dedup();
+
+		let token = ApiToken {
+			id: Uuid::new_v4(),
+			name,
+			scopes,
+			requests_per_minute,
+			created_at: Utc::now(),
+			hash: hash_secret(&secret),
+		};
+
+		self.tokens.push(token.clone());
+
+		(token, secret)
+	}
+
+	/// Returns `false` if there was no token with this id.
+	pub fn revoke(&mut self, id: Uuid) -> bool {
+		let len = self.tokens.len();
+		self.tokens.retain(|token| token.id != id);
+
+		self.tokens.len() != len
+	}
+
+	pub fn find(&self, secret: &str) -> Option<&ApiToken> {
+		let hash = hash_secret(secret.trim());
+
+		self.tokens.iter().find(|token| token.hash == hash)
+	}
+
+	/// Finds the token of `secret` and checks it has `scope` and is within its rate limit.
+	pub fn authorize(
+		&self,
+		limiter: &ApiTokenLimiter,
+		secret: &str,
+		scope: ApiTokenScope,
+	) -> Result<Uuid, ApiTokenRejection> {
+		let token = self.find(secret).ok_or(ApiTokenRejection::Unknown)?;
+
+		if !token.has_scope(scope) {
+			return Err(ApiTokenRejection::MissingScope(scope));
+		}
+
+		limiter
+			.check(token.id, token.requests_per_minute, Instant::now())
+			.map_err(|retry_after| ApiTokenRejection::RateLimited { retry_after })?;
+
+		Ok(token.id)
+	}
+}
+
+fn hash_secret(secret: &str) -> String {
+	blake3::hash(secret.as_bytes()).to_hex().to_string()
+}
+
+#[derive(Debug, Error)]
+pub enum ApiTokenRejection {
+	#[error("unknown API token")]
+	Unknown,
+	#[error("API token is missing the '{0:?}' scope")]
+	MissingScope(ApiTokenScope),
+	#[error("API token exceeded its rate limit")]
+	RateLimited { retry_after: Duration },
+}
+
+/// Token buckets of the API tokens, each refilling at the requests per minute of its token.
+#[derive(Debug, Default)]
+pub struct ApiTokenLimiter {
+	buckets: Mutex<HashMap<Uuid, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+	available: f64,
+	updated_at: Instant,
+}
+
+impl ApiTokenLimiter {
+	/// Takes a request from the bucket of the token, or returns how long to wait for one.
+	fn check(&self, id: Uuid, requests_per_minute: u32, now: Instant) -> Result<(), Duration> {
+		let capacity = f64::from(requests_per_minute.max(1));
+		let per_second = capacity / 60.0;
+
+		let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+		let bucket = buckets.entry(id).or_insert(Bucket {
+			available: capacity,
+			updated_at: now,
+		});
+
+		let elapsed = now.saturating_duration_since(bucket.updated_at);
+		bucket.available = (bucket.available + elapsed.as_secs_f64() * per_second).min(capacity);
+		bucket.updated_at = now;
+
+		if bucket.available < 1.0 {
+			return Err(Duration::from_secs_f64(
+				(1.0 - bucket.available) / per_second,
+			));
+		}
+
+		bucket.available -= 1.0;
+
+		Ok(())
+	}
+
+	pub fn forget(&self, id: Uuid) {
+		self.buckets
+			.lock()
+			.expect("rate limiter lock poisoned")
+			.remove(&id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn authorizes_by_secret_and_scope() {
+		let limiter = ApiTokenLimiter::default();
+		let mut preferences = ApiTokenPreferences::default();
+		let (token, secret) =
+			preferences.create("backup script".to_string(), vec![ApiTokenScope::Read], 60);
+
+		assert!(secret.starts_with(SECRET_PREFIX));
+		assert_eq!(
+			preferences
+				.authorize(&limiter, &secret, ApiTokenScope::Read)
+				.unwrap(),
+			token.id
+		);
+		assert!(matches!(
+			preferences.authorize(&limiter, &secret, ApiTokenScope::Write),
+			Err(ApiTokenRejection::MissingScope(ApiTokenScope::Write))
+		));
+		assert!(matches!(
+			preferences.authorize(&limiter, "sd_nope", ApiTokenScope::Read),
+			Err(ApiTokenRejection::Unknown)
+		));
+
+		assert!(preferences.revoke(token.id));
+		assert!(!preferences.revoke(token.id));
+		assert!(preferences.find(&secret).is_none());
+	}
+
+	#[test]
+	fn limits_requests_per_token() {
+		let limiter = ApiTokenLimiter::default();
+		let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+		let start = Instant::now();
+
+		for _ in 0..60 {
+			assert!(limiter.check(a, 60, start).is_ok());
+		}
+		assert_eq!(limiter.check(a, 60, start), Err(Duration::from_secs(1)));
+
+		// Other tokens have their own bucket
+		assert!(limiter.check(b, 60, start).is_ok());
+
+		let later = start + Duration::from_secs(1);
+		assert!(limiter.check(a, 60, later).is_ok());
+		assert!(limiter.check(a, 60, later).is_err());
+	}
+}
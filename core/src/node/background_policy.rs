@@ -0,0 +1,526 @@
+use crate::node::config::NodePreferences;
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{Local, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{
+	spawn,
+	sync::watch,
+	task::spawn_blocking,
+	time::{interval, MissedTickBehavior},
+};
+use tracing::info;
+
+/// How often the battery and network conditions are checked, which also bounds how late quiet
+/// hours are noticed
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const MINUTES_IN_A_DAY: u16 = 24 * 60;
+
+/// User settings deciding when heavy background work is held back.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundPolicyPreferences {
+	/// Pause every subsystem while running on battery below this percentage, `None` never pauses
+	#[serde(default = "default_pause_below_battery_percent")]
+	pub pause_below_battery_percent: Option<u8>,
+	/// Run indexing and thumbnailing with a single thread while running on battery
+	#[serde(default = "default_true")]
+	pub throttle_on_battery: bool,
+	/// Pause sync and transfers while connected to a metered network
+	#[serde(default = "default_true")]
+	pub pause_on_metered_network: bool,
+	#[serde(default)]
+	pub metered_network: MeteredNetwork,
+	/// Pause every subsystem during these hours
+	#[serde(default)]
+	pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_pause_below_battery_percent() -> Option<u8> {
+	Some(20)
+}
+
+fn default_true() -> bool {
+	true
+}
+
+impl Default for BackgroundPolicyPreferences {
+	fn default() -> Self {
+		Self {
+			pause_below_battery_percent: default_pause_below_battery_percent(),
+			throttle_on_battery: true,
+			pause_on_metered_network: true,
+			metered_network: MeteredNetwork::default(),
+			quiet_hours: None,
+		}
+	}
+}
+
+/// Whether the current network is metered, detection is only available on Linux through
+/// NetworkManager, so other platforms must set it by hand.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MeteredNetwork {
+	#[default]
+	Detect,
+	Metered,
+	Unmetered,
+}
+
+/// A daily time range in local time, wrapping around midnight if it ends before it starts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+	/// Minutes after midnight
+	pub start_minute: u16,
+	/// Minutes after midnight, excluded from the range
+	pub end_minute: u16,
+}
+
+impl QuietHours {
+	pub fn is_valid(&self) -> bool {
+		self.start_minute < MINUTES_IN_A_DAY && self.end_minute < MINUTES_IN_A_DAY
+	}
+
+	fn contains(&self, time: NaiveTime) -> bool {
+		// Always below `MINUTES_IN_A_DAY`
+		let minute = (time.hour() * 60 + time.minute()) as u16;
+
+		if self.start_minute <= self.end_minute {
+			(self.start_minute..self.end_minute).contains(&minute)
+		} else {
+			minute >= self.start_minute || minute < self.end_minute
+		}
+	}
+}
+
+/// Subsystems doing heavy work in the background, each one following the policy on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+	Indexing,
+	Thumbnailing,
+	Sync,
+	Transfers,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkMode {
+	#[default]
+	Normal,
+	/// Keep going, using as few resources as possible
+	Throttled,
+	/// Wait until the policy allows work again
+	Paused,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PolicyReason {
+	LowBattery,
+	OnBattery,
+	MeteredNetwork,
+	QuietHours,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryStatus {
+	pub percent: u8,
+	/// The machine isn't plugged in
+	pub discharging: bool,
+}
+
+/// What the machine is going through, as far as we can tell.
+#[derive(Debug, Clone, Copy, Default, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemConditions {
+	/// Missing on machines without a battery, or where we can't read it
+	pub battery: Option<BatteryStatus>,
+	pub metered_network_detected: bool,
+}
+
+impl SystemConditions {
+	fn probe() -> Self {
+		Self {
+			battery: battery_status(),
+			metered_network_detected: metered_network_detected(),
+		}
+	}
+}
+
+/// The outcome of the policy for each subsystem.
+#[derive(Debug, Clone, Default, Serialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundPolicyState {
+	pub indexing: WorkMode,
+	pub thumbnailing: WorkMode,
+	pub sync: WorkMode,
+	pub transfers: WorkMode,
+	/// Why some subsystems aren't running normally, empty if all of them are
+	pub reasons: Vec<PolicyReason>,
+	pub conditions: SystemConditions,
+}
+
+impl BackgroundPolicyState {
+	pub fn mode(&self, subsystem: Subsystem) -> WorkMode {
+		match subsystem {
+			Subsystem::Indexing => self.indexing,
+			Subsystem::Thumbnailing => self.thumbnailing,
+			Subsystem::Sync => self.sync,
+			Subsystem::Transfers => self.transfers,
+		}
+	}
+
+	fn evaluate(
+		preferences: &BackgroundPolicyPreferences,
+		conditions: SystemConditions,
+		now: NaiveTime,
+	) -> Self {
+		let mut reasons = vec![];
+
+		let on_battery = conditions.battery.filter(|battery| battery.discharging);
+
+		let low_battery = on_battery
+			.zip(preferences.pause_below_battery_percent)
+			.is_some_and(|(battery, threshold)| battery.percent < threshold);
+		if low_battery {
+			reasons.push(PolicyReason::LowBattery);
+		}
+
+		let throttle = !low_battery && on_battery.is_some() && preferences.throttle_on_battery;
+		if throttle {
+			reasons.push(PolicyReason::OnBattery);
+		}
+
+		let metered = match preferences.metered_network {
+			MeteredNetwork::Detect => conditions.metered_network_detected,
+			MeteredNetwork::Metered => true,
+			MeteredNetwork::Unmetered => false,
+		};
+		let pause_network = metered && preferences.pause_on_metered_network;
+		if pause_network {
+			reasons.push(PolicyReason::MeteredNetwork);
+		}
+
+		let quiet_hours = preferences
+			.quiet_hours
+			.is_some_and(|quiet_hours| quiet_hours.contains(now));
+		if quiet_hours {
+			reasons.push(PolicyReason::QuietHours);
+		}
+
+		let heavy_work = if low_battery || quiet_hours {
+			WorkMode::Paused
+		} else if throttle {
+			WorkMode::Throttled
+		} else {
+			WorkMode::Normal
+		};
+
+		let network = if low_battery || quiet_hours || pause_network {
+			WorkMode::Paused
+		} else {
+			WorkMode::Normal
+		};
+
+		Self {
+			indexing: heavy_work,
+			thumbnailing: heavy_work,
+			sync: network,
+			transfers: network,
+			reasons,
+			conditions,
+		}
+	}
+}
+
+/// Decides when subsystems can do heavy work in the background, following the
+/// [`BackgroundPolicyPreferences`] of the node and the conditions of the machine.
+#[derive(Debug)]
+pub struct BackgroundPolicy {
+	state_tx: watch::Sender<BackgroundPolicyState>,
+}
+
+impl BackgroundPolicy {
+	pub fn new(mut preferences_rx: watch::Receiver<NodePreferences>) -> Arc<Self> {
+		let (state_tx, _state_rx) = watch::channel(BackgroundPolicyState::default());
+		let this = Arc::new(Self { state_tx });
+
+		spawn({
+			let this = Arc::clone(&this);
+
+			async move {
+				let mut poll_interval = interval(POLL_INTERVAL);
+				poll_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+				let mut conditions = SystemConditions::default();
+
+				loop {
+					tokio::select! {
+						_ = poll_interval.tick() => {
+							conditions = spawn_blocking(SystemConditions::probe)
+								.await
+								.unwrap_or_default();
+						}
+						changed = preferences_rx.changed() => {
+							if changed.is_err() {
+								break;
+							}
+						}
+					}
+
+					let state = BackgroundPolicyState::evaluate(
+						&preferences_rx.borrow().background_policy,
+						conditions,
+						Local::now().time(),
+					);
+
+					this.state_tx.send_if_modified(|current| {
+						if *current == state {
+							return false;
+						}
+
+						if current.reasons != state.reasons {
+							info!("Background policy changed, reasons: {:?}", state.reasons);
+						}
+
+						*current = state;
+						true
+					});
+				}
+			}
+		});
+
+		this
+	}
+
+	pub fn state(&self) -> BackgroundPolicyState {
+		self.state_tx.borrow().clone()
+	}
+
+	pub fn mode(&self, subsystem: Subsystem) -> WorkMode {
+		self.state_tx.borrow().mode(subsystem)
+	}
+
+	pub fn subscribe(&self) -> watch::Receiver<BackgroundPolicyState> {
+		self.state_tx.subscribe()
+	}
+
+	/// Waits until the subsystem isn't paused anymore, returning right away if it isn't paused.
+	pub async fn wait_until_allowed(&self, subsystem: Subsystem) {
+		let mut state_rx = self.state_tx.subscribe();
+
+		// The sender lives as long as we do, so this never fails
+		state_rx
+			.wait_for(|state| state.mode(subsystem) != WorkMode::Paused)
+			.await
+			.ok();
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn battery_status() -> Option<BatteryStatus> {
+	use std::fs;
+
+	let mut battery = None;
+	let mut plugged_in = false;
+
+	for entry in fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+		let path = entry.path();
+		let read = |name: &str| {
+			fs::read_to_string(path.join(name))
+				.ok()
+				.map(|value| value.trim().to_string())
+		};
+
+		match read("type").as_deref() {
+			// Mice, keyboards and the like report their own batteries with a device scope
+			Some("Battery") if read("scope").as_deref() != Some("Device") => {
+				if battery.is_none() {
+					battery = read("capacity")
+						.and_then(|capacity| capacity.parse::<u8>().ok())
+						.map(|percent| (percent.min(100), read("status")));
+				}
+			}
+			Some("Mains" | "USB") => plugged_in |= read("online").as_deref() == Some("1"),
+			_ => {}
+		}
+	}
+
+	battery.map(|(percent, status)| BatteryStatus {
+		percent,
+		discharging: !plugged_in && status.as_deref() == Some("Discharging"),
+	})
+}
+
+#[cfg(target_os = "macos")]
+fn battery_status() -> Option<BatteryStatus> {
+	let output = std::process::Command::new("pmset")
+		.args(["-g", "batt"])
+		.output()
+		.ok()?;
+
+	parse_pmset_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn battery_status() -> Option<BatteryStatus> {
+	None
+}
+
+/// Parses the output of `pmset -g batt`, which looks like:
+/// ```text
+/// Now drawing from 'Battery Power'
+///  -InternalBattery-0 (id=4653155)	85%; discharging; 4:12 remaining present: true
+/// ```
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset_output(output: &str) -> Option<BatteryStatus> {
+	let percent = output
+		.lines()
+		.find(|line| line.contains("InternalBattery"))?
+		.split(|c: char| c.is_whitespace() || c == ';')
+		.find_map(|token| token.strip_suffix('%')?.parse::<u8>().ok())?;
+
+	Some(BatteryStatus {
+		percent: percent.min(100),
+		discharging: output.contains("'Battery Power'"),
+	})
+}
+
+/// Asks NetworkManager, whose `Metered` property is 1 for "yes" and 3 for "guessed yes".
+#[cfg(target_os = "linux")]
+fn metered_network_detected() -> bool {
+	std::process::Command::new("busctl")
+		.args([
+			"get-property",
+			"org.freedesktop.NetworkManager",
+			"/org/freedesktop/NetworkManager",
+			"org.freedesktop.NetworkManager",
+			"Metered",
+		])
+		.output()
+		.ok()
+		.filter(|output| output.status.success())
+		.is_some_and(|output| {
+			matches!(
+				String::from_utf8_lossy(&output.stdout).trim(),
+				"u 1" | "u 3"
+			)
+		})
+}
+
+#[cfg(not(target_os = "linux"))]
+fn metered_network_detected() -> bool {
+	false
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn at(hour: u32, minute: u32) -> NaiveTime {
+		NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+	}
+
+	#[test]
+	fn quiet_hours_wrap_around_midnight() {
+		let quiet_hours = QuietHours {
+			start_minute: 22 * 60,
+			end_minute: 7 * 60,
+		};
+
+		assert!(quiet_hours.contains(at(23, 30)));
+		assert!(quiet_hours.contains(at(3, 0)));
+		assert!(!quiet_hours.contains(at(7, 0)));
+		assert!(!quiet_hours.contains(at(12, 0)));
+
+		let quiet_hours = QuietHours {
+			start_minute: 9 * 60,
+			end_minute: 17 * 60,
+		};
+
+		assert!(quiet_hours.contains(at(9, 0)));
+		assert!(!quiet_hours.contains(at(17, 0)));
+		assert!(!quiet_hours.contains(at(20, 0)));
+	}
+
+	#[test]
+	fn battery_and_network_decide_modes() {
+		let preferences = BackgroundPolicyPreferences::default();
+		let on_battery = |percent| SystemConditions {
+			battery: Some(BatteryStatus {
+				percent,
+				discharging: true,
+			}),
+			metered_network_detected: false,
+		};
+
+		let state = BackgroundPolicyState::evaluate(&preferences, on_battery(80), at(12, 0));
+		assert_eq!(state.indexing, WorkMode::Throttled);
+		assert_eq!(state.sync, WorkMode::Normal);
+		assert_eq!(state.reasons, vec![PolicyReason::OnBattery]);
+
+		let state = BackgroundPolicyState::evaluate(&preferences, on_battery(10), at(12, 0));
+		assert_eq!(state.thumbnailing, WorkMode::Paused);
+		assert_eq!(state.transfers, WorkMode::Paused);
+		assert_eq!(state.reasons, vec![PolicyReason::LowBattery]);
+
+		let state = BackgroundPolicyState::evaluate(
+			&preferences,
+			SystemConditions {
+				battery: None,
+				metered_network_detected: true,
+			},
+			at(12, 0),
+		);
+		assert_eq!(state.indexing, WorkMode::Normal);
+		assert_eq!(state.sync, WorkMode::Paused);
+
+		let state = BackgroundPolicyState::evaluate(
+			&BackgroundPolicyPreferences {
+				metered_network: MeteredNetwork::Unmetered,
+				..preferences
+			},
+			SystemConditions {
+				battery: None,
+				metered_network_detected: true,
+			},
+			at(12, 0),
+		);
+		assert_eq!(
+			state,
+			BackgroundPolicyState {
+				conditions: state.conditions,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parses_pmset_output() {
+		assert_eq!(
+			parse_pmset_output(
+				"Now drawing from 'Battery Power'\n \
+				-InternalBattery-0 (id=4653155)\t85%; discharging; 4:12 remaining present: true\n"
+			),
+			Some(BatteryStatus {
+				percent: 85,
+				discharging: true,
+			})
+		);
+		assert_eq!(
+			parse_pmset_output(
+				"Now drawing from 'AC Power'\n \
+				-InternalBattery-0 (id=4653155)\t100%; charged; 0:00 remaining present: true\n"
+			),
+			Some(BatteryStatus {
+				percent: 100,
+				discharging: false,
+			})
+		);
+		assert_eq!(parse_pmset_output("Now drawing from 'AC Power'\n"), None);
+	}
+}
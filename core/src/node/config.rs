@@ -1,6 +1,14 @@
 use crate::{
-	api::{notifications::Notification, BackendFeature},
+	api::{notifications::Notification, search::preferences::SearchPreferences, BackendFeature},
+	library::ScriptHookPreferences,
+	node::{
+		api_tokens::ApiTokenPreferences, background_policy::BackgroundPolicyPreferences,
+		plugin_grants::PluginPreferences,
+	},
 	object::media::old_thumbnail::preferences::ThumbnailerPreferences,
+	old_job::preferences::JobsPreferences,
+	p2p::operations::{admin::RemoteAdminPreferences, pairing::PairingPreferences},
+	telemetry::TelemetryPreferences,
 	util::version_manager::{Kind, ManagedVersion, VersionManager, VersionManagerError},
 };
 
@@ -115,6 +123,24 @@ mod identity_serde {
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
 pub struct NodePreferences {
 	pub thumbnailer: ThumbnailerPreferences,
+	#[serde(default)]
+	pub jobs: JobsPreferences,
+	#[serde(default)]
+	pub telemetry: TelemetryPreferences,
+	#[serde(default)]
+	pub remote_admin: RemoteAdminPreferences,
+	#[serde(default)]
+	pub background_policy: BackgroundPolicyPreferences,
+	#[serde(default)]
+	pub api_tokens: ApiTokenPreferences,
+	#[serde(default)]
+	pub search: SearchPreferences,
+	#[serde(default)]
+	pub pairing: PairingPreferences,
+	#[serde(default)]
+	pub script_hooks: ScriptHookPreferences,
+	#[serde(default)]
+	pub plugins: PluginPreferences,
 }
 
 #[derive(
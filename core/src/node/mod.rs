@@ -1,6 +1,9 @@
+pub mod api_tokens;
+pub mod background_policy;
 pub mod config;
 mod hardware;
 mod platform;
+pub mod plugin_grants;
 
 pub use hardware::*;
 pub use platform::*;
@@ -0,0 +1,87 @@
+//! What the WASM plugins are allowed to use, and the grants of the user to them.
+//!
+//! A plugin asks for its capabilities in its manifest, which are clamped to the maximums of the
+//! node. It's only loaded once the user approved these exact capabilities, so a plugin changing
+//! what it asks for must be approved again.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+pub const MAX_MEMORY_MIB: u32 = 512;
+pub const MAX_TIMEOUT_SECS: u32 = 60;
+pub const MAX_FILE_SIZE_MIB: u32 = 256;
+
+/// What the plugin is allowed to use, anything not listed here is denied.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginCapabilities {
+	/// Hosts the plugin can send HTTP requests to, wildcards like `*.example.com` are supported
+	pub allowed_hosts: Vec<String>,
+	pub max_memory_mib: u32,
+	/// Time a single call can take before the plugin is interrupted
+	pub timeout_secs: u32,
+	/// Files bigger than this are skipped instead of being given to the plugin
+	pub max_file_size_mib: u32,
+}
+
+impl Default for PluginCapabilities {
+	fn default() -> Self {
+		Self {
+			allowed_hosts: vec![],
+			max_memory_mib: 64,
+			timeout_secs: 10,
+			max_file_size_mib: 32,
+		}
+	}
+}
+
+impl PluginCapabilities {
+	/// The capabilities limited to the maximums of the node.
+	pub fn clamped(&self) -> Self {
+		let mut allowed_hosts = self.allowed_hosts.clone();
+		allowed_hosts.sort();
+		allowed_hosts.dedup();
+
+		Self {
+			allowed_hosts,
+			max_memory_mib: self.max_memory_mib.min(MAX_MEMORY_MIB),
+			timeout_secs: self.timeout_secs.min(MAX_TIMEOUT_SECS),
+			max_file_size_mib: self.max_file_size_mib.min(MAX_FILE_SIZE_MIB),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+pub struct PluginPreferences {
+	/// The capabilities approved by the user, by plugin name
+	#[serde(default)]
+	pub grants: BTreeMap<String, PluginCapabilities>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clamps_to_node_maximums() {
+		let capabilities = PluginCapabilities {
+			allowed_hosts: vec!["b.example.com".to_string(), "a.example.com".to_string()],
+			max_memory_mib: 4096,
+			timeout_secs: 5,
+			max_file_size_mib: u32::MAX,
+		}
+		.clamped();
+
+		assert_eq!(
+			capabilities,
+			PluginCapabilities {
+				allowed_hosts: vec!["a.example.com".to_string(), "b.example.com".to_string()],
+				max_memory_mib: MAX_MEMORY_MIB,
+				timeout_secs: 5,
+				max_file_size_mib: MAX_FILE_SIZE_MIB,
+			}
+		);
+	}
+}
@@ -1,6 +1,8 @@
-use std::path::Path;
+use std::{fs::Metadata, path::Path, time::SystemTime};
 
 use blake3::Hasher;
+use mini_moka::sync::Cache;
+use once_cell::sync::Lazy;
 use static_assertions::const_assert;
 use tokio::{
 	fs::{self, File},
@@ -20,6 +22,124 @@ const_assert!((HEADER_OR_FOOTER_SIZE * 2 + SAMPLE_COUNT * SAMPLE_SIZE) < MINIMUM
 // Asserting that the sample size is larger than header/footer size, as the same buffer is used for both
 const_assert!(SAMPLE_SIZE > HEADER_OR_FOOTER_SIZE);
 
+/// Most files whose cas_id is remembered by [`generate_cas_id_cached`]
+const CAS_ID_CACHE_CAPACITY: u64 = 200_000;
+
+static CAS_ID_CACHE: Lazy<CasIdCache> = Lazy::new(|| CasIdCache::new(CAS_ID_CACHE_CAPACITY));
+
+/// Device and inode of a file
+type FileKey = (u64, u64);
+
+#[derive(Debug, Clone)]
+struct CachedCasId {
+	size: u64,
+	modified: SystemTime,
+	cas_id: String,
+}
+
+/// cas_ids of files, which are only returned while the size and modification time of the file
+/// are the ones it was computed with.
+struct CasIdCache(Cache<FileKey, CachedCasId>);
+
+impl CasIdCache {
+	fn new(capacity: u64) -> Self {
+		Self(Cache::new(capacity))
+	}
+
+	fn get(&self, key: FileKey, size: u64, modified: SystemTime) -> Option<String> {
+		let cached = self.0.get(&key)?;
+
+		if cached.size == size && cached.modified == modified {
+			Some(cached.cas_id)
+		} else {
+			// The file changed, or the inode was reused by another file
+			self.0.invalidate(&key);
+			None
+		}
+	}
+
+	fn insert(&self, key: FileKey, size: u64, modified: SystemTime, cas_id: String) {
+		self.0.insert(
+			key,
+			CachedCasId {
+				size,
+				modified,
+				cas_id,
+			},
+		);
+	}
+}
+
+#[cfg(target_family = "unix")]
+fn file_key(metadata: &Metadata) -> Option<FileKey> {
+	use std::os::unix::fs::MetadataExt;
+
+	Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(target_family = "unix"))]
+fn file_key(_: &Metadata) -> Option<FileKey> {
+	// The file index isn't exposed by the standard library on Windows yet
+	None
+}
+
+/// [`generate_cas_id`] skipping the hashing of files that didn't change since their cas_id was
+/// last generated, so rescans are cheap.
+pub async fn generate_cas_id_cached(
+	path: impl AsRef<Path>,
+	metadata: &Metadata,
+) -> Result<String, io::Error> {
+	let size = metadata.len();
+	let (Some(key), Ok(modified)) = (file_key(metadata), metadata.modified()) else {
+		return generate_cas_id(path, size).await;
+	};
+
+	if let Some(cas_id) = CAS_ID_CACHE.get(key, size, modified) {
+		return Ok(cas_id);
+	}
+
+	let cas_id = generate_cas_id(path, size).await?;
+	CAS_ID_CACHE.insert(key, size, modified, cas_id.clone());
+
+	Ok(cas_id)
+}
+
+/// Identifies a file while browsing ephemeral directories without reading it, by its path, size
+/// and modification time, so listing a directory full of huge files stays instant.
+///
+/// It's only used as the key of ephemeral thumbnails, the contents of indexed files are identified
+/// by [`generate_cas_id`].
+pub fn ephemeral_cas_id(path: impl AsRef<Path>, metadata: &Metadata) -> String {
+	let mut hasher = Hasher::new();
+	hasher.update(path.as_ref().to_string_lossy().as_bytes());
+	hasher.update(&metadata.len().to_le_bytes());
+	hasher.update(
+		&metadata
+			.modified()
+			.ok()
+			.and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+			.unwrap_or_default()
+			.as_nanos()
+			.to_le_bytes(),
+	);
+
+	hasher.finalize().to_hex()[..16].to_string()
+}
+
+/// Amount of bytes read from disk by [`generate_cas_id`] for a file of the given size
+pub const fn cas_id_read_size(size: u64) -> u64 {
+	if size <= MINIMUM_FILE_SIZE {
+		size
+	} else {
+		HEADER_OR_FOOTER_SIZE * 2 + SAMPLE_COUNT * SAMPLE_SIZE
+	}
+}
+
+/// Identifies the contents of a file by hashing its size and, for files bigger than 100KiB, only
+/// samples of its contents, so it's cheap enough to be generated while indexing.
+///
+/// The whole contents of indexed files are checksummed later in the background, as the
+/// `integrity_checksum` of their file paths, for the comparisons that can't rely on samples.
 pub async fn generate_cas_id(path: impl AsRef<Path>, size: u64) -> Result<String, io::Error> {
 	let mut hasher = Hasher::new();
 	hasher.update(&size.to_le_bytes());
@@ -60,3 +180,31 @@ pub async fn generate_cas_id(path: impl AsRef<Path>, size: u64) -> Result<String
 
 	Ok(hasher.finalize().to_hex()[..16].to_string())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::time::Duration;
+
+	#[test]
+	fn cache_misses_when_the_file_changed() {
+		let cache = CasIdCache::new(10);
+		let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+		cache.insert((1, 42), 100, modified, "abc".to_string());
+
+		assert_eq!(cache.get((1, 42), 100, modified), Some("abc".to_string()));
+		// Same inode on another device
+		assert_eq!(cache.get((2, 42), 100, modified), None);
+		assert_eq!(
+			cache.get((1, 42), 100, modified + Duration::from_secs(1)),
+			None
+		);
+		// The changed entry was evicted
+		assert_eq!(cache.get((1, 42), 100, modified), None);
+
+		cache.insert((1, 42), 100, modified, "abc".to_string());
+		assert_eq!(cache.get((1, 42), 101, modified), None);
+	}
+}
@@ -0,0 +1,224 @@
+//! Files with the same contents in the library, found by their cas id and size, and told apart
+//! by their full checksum once [`old_duplicate_finder_job`] verified them.
+
+use sd_prisma::prisma::{file_path, location, PrismaClient, SortOrder};
+
+use std::collections::{BTreeMap, HashMap};
+
+use prisma_client_rust::{raw, PrismaValue, QueryError};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+pub mod old_duplicate_finder_job;
+
+/// File paths which are copies of each other.
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCluster {
+	pub cas_id: String,
+	/// Of each of the file paths
+	pub size_in_bytes: String,
+	/// Freed by keeping a single file path of the cluster
+	pub reclaimable_bytes: String,
+	/// The full contents of the file paths were compared through their checksum, otherwise only
+	/// the samples the cas id is made of were
+	pub verified: bool,
+	pub file_paths: Vec<file_path::Data>,
+}
+
+impl DuplicateCluster {
+	pub fn reclaimable(&self) -> u64 {
+		self.reclaimable_bytes.parse().unwrap_or_default()
+	}
+}
+
+/// A cluster of duplicates without its file paths, so the clusters can be ordered and paged
+/// before reading the file paths of the ones in a page.
+#[derive(Debug)]
+pub struct ClusterSummary {
+	pub cas_id: String,
+	pub size_in_bytes: u64,
+	/// Only set on the verified clusters, which are split by their checksum
+	pub checksum: Option<String>,
+	pub file_paths_count: u64,
+	/// The lowest id of the file paths of the cluster
+	pub first_file_path_id: file_path::id::Type,
+}
+
+impl ClusterSummary {
+	pub fn reclaimable(&self) -> u64 {
+		self.size_in_bytes * self.file_paths_count.saturating_sub(1)
+	}
+}
+
+/// The clusters of duplicates with a file path in the location with `location_id`, or in the
+/// whole library.
+pub async fn duplicate_clusters(
+	db: &PrismaClient,
+	location_id: Option<location::id::Type>,
+) -> Result<Vec<DuplicateCluster>, QueryError> {
+	clusters_of(db, &cluster_summaries(db, location_id, false).await?).await
+}
+
+/// Groups the file paths in the database the same way [`into_clusters`] does, counting the file
+/// paths of each cluster instead of reading them.
+pub async fn cluster_summaries(
+	db: &PrismaClient,
+	location_id: Option<location::id::Type>,
+	verified_only: bool,
+) -> Result<Vec<ClusterSummary>, QueryError> {
+	#[derive(Deserialize)]
+	struct Summary {
+		cas_id: String,
+		size: String,
+		checksum: Option<String>,
+		count: i64,
+		first_id: file_path::id::Type,
+	}
+
+	// The sizes are big endian blobs, so they're read as hex to be parsed back into a `u64`, and
+	// the checksum is only kept if every file path of the cas id and size has one
+	let query = format!(
+		"WITH duplicate AS (
+			SELECT id, location_id, cas_id, hex(size_in_bytes_bytes) AS size,
+				CASE
+					WHEN COUNT(*) OVER same_size = COUNT(integrity_checksum) OVER same_size
+					THEN integrity_checksum
+				END AS checksum
+			FROM file_path
+			WHERE cas_id IS NOT NULL AND is_dir = 0
+			WINDOW same_size AS (PARTITION BY cas_id, hex(size_in_bytes_bytes))
+		)
+		SELECT cas_id, size, checksum, COUNT(*) AS count, MIN(id) AS first_id
+		FROM duplicate
+		GROUP BY cas_id, size, checksum
+		HAVING COUNT(*) > 1 {} {}",
+		if location_id.is_some() {
+			"AND SUM(location_id = {}) > 0"
+		} else {
+			""
+		},
+		if verified_only {
+			"AND checksum IS NOT NULL"
+		} else {
+			""
+		}
+	);
+
+	let summaries = db
+		._query_raw::<Summary>(match location_id {
+			Some(location_id) => raw!(&query, PrismaValue::Int(location_id as i64)),
+			None => raw!(&query),
+		})
+		.exec()
+		.await?;
+
+	Ok(summaries
+		.into_iter()
+		.map(|summary| ClusterSummary {
+			cas_id: summary.cas_id,
+			// Same as `into_clusters`, a size which isn't 8 bytes long reads as 0
+			size_in_bytes: (summary.size.len() == 16)
+				.then(|| u64::from_str_radix(&summary.size, 16).ok())
+				.flatten()
+				.unwrap_or_default(),
+			checksum: summary.checksum,
+			file_paths_count: u64::try_from(summary.count).unwrap_or_default(),
+			first_file_path_id: summary.first_id,
+		})
+		.collect())
+}
+
+/// The clusters of `summaries` with their file paths, in the same order.
+pub async fn clusters_of(
+	db: &PrismaClient,
+	summaries: &[ClusterSummary],
+) -> Result<Vec<DuplicateCluster>, QueryError> {
+	if summaries.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let file_paths = db
+		.file_path()
+		.find_many(vec![
+			file_path::cas_id::in_vec(
+				summaries
+					.iter()
+					.map(|summary| summary.cas_id.clone())
+					.collect(),
+			),
+			file_path::is_dir::equals(Some(false)),
+		])
+		.order_by(file_path::id::order(SortOrder::Asc))
+		.exec()
+		.await?;
+
+	// A file path is in a single cluster, so the first one tells them apart
+	let mut clusters = into_clusters(file_paths)
+		.into_iter()
+		.filter_map(|cluster| {
+			cluster
+				.file_paths
+				.first()
+				.map(|file_path| (file_path.id, cluster))
+		})
+		.collect::<HashMap<_, _>>();
+
+	Ok(summaries
+		.iter()
+		.filter_map(|summary| clusters.remove(&summary.first_file_path_id))
+		.collect())
+}
+
+/// Groups the file paths by cas id and size, then splits the groups whose file paths were all
+/// verified by their checksum.
+fn into_clusters(file_paths: Vec<file_path::Data>) -> Vec<DuplicateCluster> {
+	let mut groups = BTreeMap::<_, Vec<_>>::new();
+	for file_path in file_paths {
+		let Some(cas_id) = file_path.cas_id.clone() else {
+			continue;
+		};
+
+		let size = file_path
+			.size_in_bytes_bytes
+			.as_deref()
+			.and_then(|bytes| bytes.try_into().ok())
+			.map(u64::from_be_bytes)
+			.unwrap_or_default();
+
+		groups.entry((cas_id, size)).or_default().push(file_path);
+	}
+
+	groups
+		.into_iter()
+		.flat_map(|((cas_id, size), file_paths)| {
+			let verified = file_paths
+				.iter()
+				.all(|file_path| file_path.integrity_checksum.is_some());
+
+			let splits = if verified {
+				let mut by_checksum = BTreeMap::<_, Vec<_>>::new();
+				for file_path in file_paths {
+					by_checksum
+						.entry(file_path.integrity_checksum.clone())
+						.or_default()
+						.push(file_path);
+				}
+				by_checksum.into_values().collect()
+			} else {
+				vec![file_paths]
+			};
+
+			splits
+				.into_iter()
+				.filter(|file_paths| file_paths.len() > 1)
+				.map(move |file_paths| DuplicateCluster {
+					cas_id: cas_id.clone(),
+					size_in_bytes: size.to_string(),
+					reclaimable_bytes: (size * (file_paths.len() as u64 - 1)).to_string(),
+					verified,
+					file_paths,
+				})
+		})
+		.collect()
+}
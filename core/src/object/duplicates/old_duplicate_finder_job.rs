@@ -0,0 +1,179 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::get_location_path_from_location_id,
+	object::validation::hash::file_checksum,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobRunMetadata, JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+
+use sd_prisma::{
+	prisma::{file_path, location},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::msgpack;
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tracing::info;
+
+use super::{cluster_summaries, duplicate_clusters, ClusterSummary};
+
+#[derive(Serialize, Deserialize, Type, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OldDuplicateFinderJobInit {
+	pub location_id: location::id::Type,
+	/// Checksum the whole contents of the file paths of the location which have duplicates, as
+	/// their cas id only hashes samples of the big files
+	pub verify: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldDuplicateFinderJobData {
+	location_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldDuplicateFinderMetadata {
+	verified: u32,
+}
+
+impl JobRunMetadata for OldDuplicateFinderMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.verified += new_data.verified;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldDuplicateFinderJobInit {
+	type Data = OldDuplicateFinderJobData;
+	type Step = file_path::Data;
+	type RunMetadata = OldDuplicateFinderMetadata;
+
+	const NAME: &'static str = "duplicate_finder";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id).await?;
+
+		// The file paths of other locations are verified by the jobs of their location, as they
+		// may be on another device
+		let steps = if init.verify {
+			duplicate_clusters(db, Some(init.location_id))
+				.await?
+				.into_iter()
+				.flat_map(|cluster| cluster.file_paths)
+				.filter(|file_path| {
+					file_path.location_id == Some(init.location_id)
+						&& file_path.integrity_checksum.is_none()
+				})
+				.collect::<Vec<_>>()
+		} else {
+			vec![]
+		};
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		*data = Some(OldDuplicateFinderJobData { location_path });
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, step_number }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let Library { db, sync, .. } = &*ctx.library;
+
+		let full_path = data
+			.location_path
+			.join(IsolatedFilePathData::try_from(step)?);
+
+		ctx.progress(vec![
+			JobReportUpdate::CompletedTaskCount(step_number + 1),
+			JobReportUpdate::Message(full_path.display().to_string()),
+		]);
+
+		// A file which can't be read stays unverified, along with its cluster
+		let checksum = match file_checksum(&full_path).await {
+			Ok(checksum) => checksum,
+			Err(e) => {
+				return Ok(JobRunErrors(vec![format!(
+					"Failed to checksum '{}': {e}",
+					full_path.display()
+				)])
+				.into())
+			}
+		};
+
+		sync.write_op(
+			db,
+			sync.shared_update(
+				prisma_sync::file_path::SyncId {
+					pub_id: step.pub_id.clone(),
+				},
+				file_path::integrity_checksum::NAME,
+				msgpack!(&checksum),
+			),
+			db.file_path().update(
+				file_path::id::equals(step.id),
+				vec![file_path::integrity_checksum::set(Some(checksum))],
+			),
+		)
+		.await?;
+
+		Ok(OldDuplicateFinderMetadata { verified: 1 }.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		let clusters = cluster_summaries(&ctx.library.db, Some(init.location_id), false).await?;
+		let reclaimable = clusters
+			.iter()
+			.map(ClusterSummary::reclaimable)
+			.sum::<u64>();
+
+		info!(
+			"Found {} clusters of duplicates in location <id='{}'>, {reclaimable} bytes reclaimable, \
+			verified {} files",
+			clusters.len(),
+			init.location_id,
+			run_metadata.verified
+		);
+
+		invalidate_query!(ctx.library, "duplicates.list");
+
+		Ok(Some(json!({
+			"init": init,
+			"clusters": clusters.len(),
+			"reclaimable_bytes": reclaimable.to_string(),
+		})))
+	}
+}
@@ -24,6 +24,9 @@ pub mod old_erase;
 pub mod old_copy;
 pub mod old_cut;
 
+pub mod operator;
+pub mod trash;
+
 // pub mod decrypt;
 // pub mod encrypt;
 
@@ -122,6 +125,12 @@ pub async fn fetch_source_and_target_location_paths(
 		))
 		.await?
 	{
+		(Some(source_location), _) if source_location.remote.is_some() => {
+			Err(LocationError::RemoteLocation(source_location_id))?
+		}
+		(_, Some(target_location)) if target_location.remote.is_some() => {
+			Err(LocationError::RemoteLocation(target_location_id))?
+		}
 		(Some(source_location), Some(target_location)) => Ok((
 			maybe_missing(source_location.path.map(PathBuf::from), "location.path")?,
 			maybe_missing(target_location.path.map(PathBuf::from), "location.path")?,
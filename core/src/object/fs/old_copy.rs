@@ -2,8 +2,8 @@ use crate::{
 	invalidate_query,
 	library::Library,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobStepOutput, StatefulJob,
-		WorkerContext,
+		CurrentStep, JobCleanupId, JobError, JobInitOutput, JobItemProgress, JobReportUpdate,
+		JobResult, JobRunErrors, JobStepOutput, StatefulJob, WorkerContext,
 	},
 };
 
@@ -12,7 +12,10 @@ use sd_core_file_path_helper::{join_location_relative_path, IsolatedFilePathData
 use sd_prisma::prisma::{file_path, location};
 use sd_utils::{db::maybe_missing, error::FileIOError};
 
-use std::{hash::Hash, path::PathBuf};
+use std::{
+	hash::Hash,
+	path::{Path, PathBuf},
+};
 
 use futures_concurrency::future::TryJoin;
 use serde::{Deserialize, Serialize};
@@ -187,11 +190,20 @@ impl StatefulJob for OldFileCopierJobInit {
 					// Already exist a file with this name, so we need to find an available name
 					match find_available_filename_for_duplicate(target_full_path).await {
 						Ok(new_path) => {
-							fs::copy(&source_file_data.full_path, &new_path)
+							let cleanup_id = register_partial_copy_cleanup(ctx, &new_path);
+
+							let copied_bytes = fs::copy(&source_file_data.full_path, &new_path)
 								.await
 								// Using the ? here because we don't want to increase the completed task
 								// count in case of file system errors
-								.map_err(|e| FileIOError::from((new_path, e)))?;
+								.map_err(|e| FileIOError::from((&new_path, e)))?;
+
+							ctx.unregister_cleanup(cleanup_id);
+
+							ctx.progress(vec![JobReportUpdate::Item(
+								JobItemProgress::new(new_path.display().to_string())
+									.with_bytes(copied_bytes, copied_bytes),
+							)]);
 
 							Ok(().into())
 						}
@@ -213,12 +225,21 @@ impl StatefulJob for OldFileCopierJobInit {
 						target_full_path.display()
 					);
 
-					fs::copy(&source_file_data.full_path, &target_full_path)
+					let cleanup_id = register_partial_copy_cleanup(ctx, target_full_path);
+
+					let copied_bytes = fs::copy(&source_file_data.full_path, &target_full_path)
 						.await
 						// Using the ? here because we don't want to increase the completed task
 						// count in case of file system errors
 						.map_err(|e| FileIOError::from((target_full_path, e)))?;
 
+					ctx.unregister_cleanup(cleanup_id);
+
+					ctx.progress(vec![JobReportUpdate::Item(
+						JobItemProgress::new(target_full_path.display().to_string())
+							.with_bytes(copied_bytes, copied_bytes),
+					)]);
+
 					Ok(().into())
 				}
 				Err(e) => Err(FileIOError::from((target_full_path, e)).into()),
@@ -239,3 +260,19 @@ impl StatefulJob for OldFileCopierJobInit {
 		Ok(Some(json!({ "init": init })))
 	}
 }
+
+/// If the job is canceled in the middle of a copy, we don't want to leave a half written file behind
+fn register_partial_copy_cleanup(ctx: &WorkerContext, target_path: &Path) -> JobCleanupId {
+	let target_path = target_path.to_path_buf();
+
+	ctx.register_cleanup(
+		format!("remove partially copied file {}", target_path.display()),
+		async move {
+			match fs::remove_file(&target_path).await {
+				Ok(()) => Ok(()),
+				Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+				Err(e) => Err(FileIOError::from((target_path, e)).into()),
+			}
+		},
+	)
+}
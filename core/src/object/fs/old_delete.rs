@@ -3,7 +3,8 @@ use crate::{
 	library::Library,
 	location::get_location_path_from_location_id,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobStepOutput, StatefulJob, WorkerContext,
+		CurrentStep, DryRunAction, DryRunReport, JobError, JobInitOutput, JobResult, JobStepOutput,
+		StatefulJob, WorkerContext,
 	},
 };
 
@@ -14,7 +15,7 @@ use sd_prisma::{
 use sd_sync::OperationFactory;
 use sd_utils::{db::maybe_missing, error::FileIOError};
 
-use std::hash::Hash;
+use std::{hash::Hash, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -22,19 +23,31 @@ use specta::Type;
 use tokio::{fs, io};
 use tracing::warn;
 
-use super::{error::FileSystemJobsError, get_many_files_datas, FileData};
+use super::{error::FileSystemJobsError, get_many_files_datas, trash::move_to_trash, FileData};
 
 #[derive(Serialize, Deserialize, Hash, Type, Debug)]
 pub struct OldFileDeleterJobInit {
 	pub location_id: location::id::Type,
 	pub file_path_ids: Vec<file_path::id::Type>,
+	/// Only report what would be deleted, without touching anything
+	#[serde(default)]
+	pub dry_run: bool,
+	/// Remove the files right away, instead of moving them into the trash of the location
+	#[serde(default)]
+	#[specta(optional)]
+	pub permanent: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldFileDeleterJobData {
+	location_path: PathBuf,
 }
 
 #[async_trait::async_trait]
 impl StatefulJob for OldFileDeleterJobInit {
-	type Data = ();
+	type Data = OldFileDeleterJobData;
 	type Step = FileData;
-	type RunMetadata = ();
+	type RunMetadata = DryRunReport;
 
 	const NAME: &'static str = "file_deleter";
 
@@ -50,16 +63,14 @@ impl StatefulJob for OldFileDeleterJobInit {
 		let init = self;
 		let Library { db, .. } = &*ctx.library;
 
-		let steps = get_many_files_datas(
-			db,
-			get_location_path_from_location_id(db, init.location_id).await?,
-			&init.file_path_ids,
-		)
-		.await
-		.map_err(FileSystemJobsError::from)?;
+		let location_path = get_location_path_from_location_id(db, init.location_id).await?;
+
+		let steps = get_many_files_datas(db, &location_path, &init.file_path_ids)
+			.await
+			.map_err(FileSystemJobsError::from)?;
 
 		// Must fill in the data, otherwise the job will not run
-		*data = Some(());
+		*data = Some(OldFileDeleterJobData { location_path });
 
 		Ok(steps.into())
 	}
@@ -68,7 +79,7 @@ impl StatefulJob for OldFileDeleterJobInit {
 		&self,
 		ctx: &WorkerContext,
 		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
-		_: &Self::Data,
+		data: &Self::Data,
 		_: &Self::RunMetadata,
 	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
 		// need to handle stuff such as querying prisma for all paths of a file, and deleting all of those if requested (with a checkbox in the ui)
@@ -76,11 +87,29 @@ impl StatefulJob for OldFileDeleterJobInit {
 
 		let Library { db, sync, .. } = ctx.library.as_ref();
 
-		match if maybe_missing(step.file_path.is_dir, "file_path.is_dir")? {
+		let is_dir = maybe_missing(step.file_path.is_dir, "file_path.is_dir")?;
+
+		if self.dry_run {
+			return Ok(DryRunReport::from(DryRunAction::Delete {
+				path: step.full_path.clone(),
+				is_dir,
+			})
+			.into());
+		}
+
+		let result = if !self.permanent {
+			match move_to_trash(&ctx.library, self.location_id, &data.location_path, step).await {
+				Ok(_) => Ok(()),
+				Err(FileSystemJobsError::FileIO(FileIOError { source, .. })) => Err(source),
+				Err(e) => return Err(e.into()),
+			}
+		} else if is_dir {
 			fs::remove_dir_all(&step.full_path).await
 		} else {
 			fs::remove_file(&step.full_path).await
-		} {
+		};
+
+		match result {
 			Ok(()) => { /*	Everything is awesome! */ }
 			Err(e) if e.kind() == io::ErrorKind::NotFound => {
 				warn!(
@@ -102,18 +131,27 @@ impl StatefulJob for OldFileDeleterJobInit {
 			}
 		}
 
-		Ok(().into())
+		Ok(DryRunReport::default().into())
 	}
 
 	async fn finalize(
 		&self,
 		ctx: &WorkerContext,
 		_data: &Option<Self::Data>,
-		_run_metadata: &Self::RunMetadata,
+		run_metadata: &Self::RunMetadata,
 	) -> JobResult {
 		let init = self;
+
+		if init.dry_run {
+			return Ok(Some(json!({ "init": init, "dry_run": run_metadata })));
+		}
+
 		invalidate_query!(ctx.library, "search.paths");
 
+		if !init.permanent {
+			invalidate_query!(ctx.library, "trash.list");
+		}
+
 		// ctx.library.orphan_remover.invoke().await;
 
 		Ok(Some(json!({ "init": init })))
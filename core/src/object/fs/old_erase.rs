@@ -3,8 +3,8 @@ use crate::{
 	library::Library,
 	location::get_location_path_from_location_id,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobRunMetadata, JobStepOutput,
-		StatefulJob, WorkerContext,
+		CurrentStep, DryRunAction, DryRunReport, JobError, JobInitOutput, JobResult,
+		JobRunMetadata, JobStepOutput, StatefulJob, WorkerContext,
 	},
 };
 
@@ -17,6 +17,7 @@ use std::{hash::Hash, path::PathBuf};
 
 use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use serde_with::{serde_as, DisplayFromStr};
 use specta::Type;
 use tokio::{
@@ -38,6 +39,9 @@ pub struct OldFileEraserJobInit {
 	#[specta(type = String)]
 	#[serde_as(as = "DisplayFromStr")]
 	pub passes: usize,
+	/// Only report what would be erased, without touching any file
+	#[serde(default)]
+	pub dry_run: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,12 +52,15 @@ pub struct OldFileEraserJobData {
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct FileEraserJobRunMetadata {
 	directories_to_remove: Vec<PathBuf>,
+	#[serde(default)]
+	dry_run: DryRunReport,
 }
 
 impl JobRunMetadata for FileEraserJobRunMetadata {
 	fn update(&mut self, new_data: Self) {
 		self.directories_to_remove
 			.extend(new_data.directories_to_remove);
+		self.dry_run.update(new_data.dry_run);
 	}
 }
 
@@ -140,6 +147,13 @@ impl StatefulJob for OldFileEraserJobInit {
 				.push(step.full_path.clone());
 
 			Ok((more_steps, new_metadata).into())
+		} else if init.dry_run {
+			new_metadata.dry_run.push(DryRunAction::Erase {
+				path: step.full_path.clone(),
+				passes: u32::try_from(init.passes).unwrap_or(u32::MAX),
+			});
+
+			Ok(new_metadata.into())
 		} else {
 			{
 				let mut file = OpenOptions::new()
@@ -188,6 +202,20 @@ impl StatefulJob for OldFileEraserJobInit {
 		run_metadata: &Self::RunMetadata,
 	) -> JobResult {
 		let init = self;
+
+		if init.dry_run {
+			// Directories are removed last, so their actions come after the erased files
+			let mut report = run_metadata.dry_run.clone();
+			for path in &run_metadata.directories_to_remove {
+				report.push(DryRunAction::Delete {
+					path: path.clone(),
+					is_dir: true,
+				});
+			}
+
+			return Ok(Some(json!({ "init": init, "dry_run": report })));
+		}
+
 		try_join_all(
 			run_metadata
 				.directories_to_remove
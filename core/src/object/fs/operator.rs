@@ -0,0 +1,472 @@
+//! Copies, moves and deletes files through OpenDAL operators, so the files of the locations, of
+//! the paths outside of them and of remote services like S3 buckets go through the same code.
+
+use crate::{api::CoreEvent, invalidate_query, library::Library, Node};
+
+use sd_prisma::prisma::location;
+
+use std::{path::PathBuf, sync::Arc};
+
+use futures::TryStreamExt;
+use opendal::{EntryMode, ErrorKind, Metakey, Operator};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::spawn;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use super::{trash::move_to_trash, FileData};
+
+/// Read and written at once, so big files don't have to fit in memory
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// What to do with a file whose target already exists.
+#[derive(Deserialize, Type, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+	/// Leave both files as they are
+	#[default]
+	Skip,
+	Overwrite,
+	/// Name the new file like `photo (1).jpg`
+	KeepBoth,
+	/// Stop the operation, leaving the files already processed
+	Fail,
+}
+
+#[derive(Serialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileOpKind {
+	Copy,
+	Move,
+	Delete,
+}
+
+#[derive(Serialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOpProgressEvent {
+	pub id: Uuid,
+	pub library_id: Uuid,
+	pub kind: FileOpKind,
+	pub completed_files: u32,
+	pub total_files: u32,
+	/// Of the files copied or moved so far
+	pub completed_bytes: String,
+	/// The path being processed
+	pub current: Option<String>,
+	pub errors: Vec<String>,
+	/// Sent once, after every other event of the operation
+	pub done: bool,
+}
+
+/// Files of an operator to copy, move or delete, given by their path from its root.
+pub struct FileOp {
+	pub kind: FileOpKind,
+	pub source: Operator,
+	pub paths: Vec<String>,
+	/// The folder the files are copied or moved into, with its operator
+	pub target: Option<(Operator, String)>,
+	pub conflict: ConflictResolution,
+	/// Set when deleting files of a location, which go into its trash instead of being removed
+	pub trash: Option<LocationFiles>,
+}
+
+/// The files of a location, with the data needed to move them into its trash.
+pub struct LocationFiles {
+	pub location_id: location::id::Type,
+	pub location_path: PathBuf,
+	pub files: Vec<FileData>,
+}
+
+/// A file or folder to process, with its path in the target folder.
+struct Item {
+	source: String,
+	relative: String,
+	is_dir: bool,
+}
+
+impl FileOp {
+	/// Runs the operation in the background, sending its progress to the event bus as
+	/// [`CoreEvent::FileOpProgress`] events with the id returned.
+	pub fn spawn(self, node: Arc<Node>, library: Arc<Library>) -> Uuid {
+		let id = Uuid::new_v4();
+
+		spawn(async move {
+			let mut progress = FileOpProgressEvent {
+				id,
+				library_id: library.id,
+				kind: self.kind,
+				completed_files: 0,
+				total_files: 0,
+				completed_bytes: "0".to_string(),
+				current: None,
+				errors: vec![],
+				done: false,
+			};
+
+			if let Err(e) = self.run(&node, &library, &mut progress).await {
+				error!("File operation <id='{id}'> failed: {e:#?}");
+				progress.errors.push(e.to_string());
+			}
+
+			progress.current = None;
+			progress.done = true;
+			send(&node, &progress);
+
+			invalidate_query!(library, "search.paths");
+			invalidate_query!(library, "search.objects");
+		});
+
+		id
+	}
+
+	/// The first of the paths the target folder is, or is inside of, as copying or moving a folder
+	/// into itself would never end.
+	pub fn target_in_source(&self) -> Option<&str> {
+		let (target, target_dir) = self.target.as_ref()?;
+
+		if !same_service(&self.source, target) {
+			return None;
+		}
+
+		let target_dir = target_dir.trim_matches('/');
+
+		self.paths
+			.iter()
+			.find(|path| {
+				let path = path.trim_matches('/');
+
+				path.is_empty()
+					|| target_dir == path
+					|| target_dir
+						.strip_prefix(path)
+						.is_some_and(|rest| rest.starts_with('/'))
+			})
+			.map(String::as_str)
+	}
+
+	async fn run(
+		&self,
+		node: &Node,
+		library: &Library,
+		progress: &mut FileOpProgressEvent,
+	) -> Result<(), opendal::Error> {
+		if let Some(LocationFiles {
+			location_id,
+			location_path,
+			files,
+		}) = self
+			.trash
+			.as_ref()
+			.filter(|_| self.kind == FileOpKind::Delete)
+		{
+			progress.total_files = files.len() as u32;
+			send(node, progress);
+
+			for file in files {
+				progress.current = Some(file.full_path.to_string_lossy().to_string());
+				send(node, progress);
+
+				if let Err(e) = move_to_trash(library, *location_id, location_path, file).await {
+					progress.errors.push(format!(
+						"Failed to move '{}' into the trash: {e}",
+						file.full_path.display()
+					));
+				}
+
+				progress.completed_files += 1;
+			}
+
+			invalidate_query!(library, "trash.list");
+
+			return Ok(());
+		}
+
+		// Deleted folders are removed at once, without listing their files
+		if self.kind == FileOpKind::Delete {
+			progress.total_files = self.paths.len() as u32;
+			send(node, progress);
+
+			for path in &self.paths {
+				progress.current = Some(path.clone());
+				send(node, progress);
+
+				if let Err(e) = self.delete(path).await {
+					progress
+						.errors
+						.push(format!("Failed to delete '{path}': {e}"));
+				}
+
+				progress.completed_files += 1;
+			}
+
+			return Ok(());
+		}
+
+		let Some((target, target_dir)) = &self.target else {
+			return Ok(());
+		};
+
+		if let Some(path) = self.target_in_source() {
+			progress
+				.errors
+				.push(format!("Can't copy or move '{path}' into itself"));
+			return Ok(());
+		}
+
+		let target_dir = format!("{}/", target_dir.trim_end_matches('/'));
+		let is_same_service = same_service(&self.source, target);
+
+		let mut items = vec![];
+		for path in &self.paths {
+			items.extend(self.items(path).await?);
+		}
+
+		progress.total_files = items.iter().filter(|item| !item.is_dir).count() as u32;
+		send(node, progress);
+
+		// Renamed in place when both ends are the same service, instead of copied
+		let rename = self.kind == FileOpKind::Move
+			&& is_same_service
+			&& target.info().full_capability().rename;
+
+		let mut bytes = 0_u64;
+		let mut unmoved = false;
+
+		for item in &items {
+			let mut target_path = format!("{target_dir}{}", item.relative);
+
+			if item.is_dir {
+				target.create_dir(&format!("{target_path}/")).await?;
+				continue;
+			}
+
+			progress.current = Some(item.source.clone());
+			send(node, progress);
+
+			if target.is_exist(&target_path).await? {
+				// Overwriting a file with itself would truncate it, and moving it would delete it
+				let is_source = is_same_service
+					&& target_path.trim_matches('/') == item.source.trim_matches('/');
+				let conflict = match self.conflict {
+					ConflictResolution::Overwrite if is_source => ConflictResolution::Skip,
+					conflict => conflict,
+				};
+
+				match conflict {
+					ConflictResolution::Skip => {
+						unmoved = true;
+						progress.completed_files += 1;
+						continue;
+					}
+					ConflictResolution::Overwrite => {}
+					ConflictResolution::KeepBoth => {
+						target_path = available_path(target, &target_path).await?;
+					}
+					ConflictResolution::Fail => {
+						progress
+							.errors
+							.push(format!("'{target_path}' already exists"));
+						return Ok(());
+					}
+				}
+			}
+
+			let result = if rename {
+				self.source.rename(&item.source, &target_path).await
+			} else {
+				match self
+					.copy(target, item, &target_path, node, progress, &mut bytes)
+					.await
+				{
+					Ok(()) if self.kind == FileOpKind::Move => {
+						self.source.delete(&item.source).await
+					}
+					result => result,
+				}
+			};
+
+			if let Err(e) = result {
+				unmoved = true;
+				progress
+					.errors
+					.push(format!("Failed to process '{}': {e}", item.source));
+			}
+
+			progress.completed_files += 1;
+		}
+
+		// The moved folders are only removed once all of their files were moved out of them
+		if self.kind == FileOpKind::Move && !unmoved {
+			for item in items
+				.iter()
+				.filter(|item| item.is_dir && !item.relative.contains('/'))
+			{
+				if let Err(e) = self.source.remove_all(&format!("{}/", item.source)).await {
+					warn!("Failed to remove moved folder '{}': {e:#?}", item.source);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn delete(&self, path: &str) -> Result<(), opendal::Error> {
+		let path = path.trim_end_matches('/');
+
+		if self.source.stat(path).await?.is_dir() {
+			self.source.remove_all(&format!("{path}/")).await
+		} else {
+			self.source.delete(path).await
+		}
+	}
+
+	/// The file at `path`, or the folder at `path` with everything in it.
+	async fn items(&self, path: &str) -> Result<Vec<Item>, opendal::Error> {
+		let path = path.trim_end_matches('/');
+		let name = path.rsplit('/').next().unwrap_or(path).to_string();
+
+		if !self.source.stat(path).await?.is_dir() {
+			return Ok(vec![Item {
+				source: path.to_string(),
+				relative: name,
+				is_dir: false,
+			}]);
+		}
+
+		let root = self
+			.source
+			.info()
+			.root()
+			.trim_start_matches('/')
+			.to_string();
+		let prefix = format!("{}/", path.trim_start_matches('/'));
+
+		let mut items = vec![Item {
+			source: path.to_string(),
+			relative: name.clone(),
+			is_dir: true,
+		}];
+
+		let mut lister = self
+			.source
+			.lister_with(&format!("{path}/"))
+			.recursive(true)
+			.metakey(Metakey::Mode)
+			.await?;
+
+		while let Some(entry) = lister.try_next().await? {
+			// Listed from the root of the operator, without a leading `/`
+			let entry_path = entry.path().trim_end_matches('/');
+			let Some(relative) = entry_path
+				.strip_prefix(&root)
+				.unwrap_or(entry_path)
+				.trim_start_matches('/')
+				.strip_prefix(&prefix)
+			else {
+				continue;
+			};
+
+			if relative.is_empty() {
+				continue;
+			}
+
+			items.push(Item {
+				source: format!("{path}/{relative}"),
+				relative: format!("{name}/{relative}"),
+				is_dir: entry.metadata().mode() == EntryMode::DIR,
+			});
+		}
+
+		// Folders are created before their files
+		items.sort_by(|a, b| a.relative.cmp(&b.relative));
+
+		Ok(items)
+	}
+
+	/// Copies in chunks, or at once when both ends are the same service and it can copy files.
+	async fn copy(
+		&self,
+		target: &Operator,
+		item: &Item,
+		target_path: &str,
+		node: &Node,
+		progress: &mut FileOpProgressEvent,
+		bytes: &mut u64,
+	) -> Result<(), opendal::Error> {
+		let size = self.source.stat(&item.source).await?.content_length();
+
+		if same_service(&self.source, target) && target.info().full_capability().copy {
+			self.source.copy(&item.source, target_path).await?;
+			*bytes += size;
+		} else {
+			let mut writer = target.writer(target_path).await?;
+
+			let mut offset = 0;
+			while offset < size {
+				let end = (offset + CHUNK_SIZE).min(size);
+				let chunk = self
+					.source
+					.read_with(&item.source)
+					.range(offset..end)
+					.await?;
+
+				writer.write(chunk).await?;
+
+				*bytes += end - offset;
+				offset = end;
+
+				progress.completed_bytes = bytes.to_string();
+				send(node, progress);
+			}
+
+			writer.close().await?;
+		}
+
+		progress.completed_bytes = bytes.to_string();
+
+		Ok(())
+	}
+}
+
+fn same_service(a: &Operator, b: &Operator) -> bool {
+	let (a, b) = (a.info(), b.info());
+
+	a.scheme() == b.scheme() && a.name() == b.name() && a.root() == b.root()
+}
+
+/// The first of `path (1)`, `path (2)`... which doesn't exist yet, keeping the extension.
+async fn available_path(operator: &Operator, path: &str) -> Result<String, opendal::Error> {
+	let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+	let (stem, extension) = match name.rsplit_once('.') {
+		Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+		_ => (name, None),
+	};
+
+	for i in 1..u32::MAX {
+		let candidate = match extension {
+			Some(extension) => format!("{dir}/{stem} ({i}).{extension}"),
+			None => format!("{dir}/{stem} ({i})"),
+		};
+
+		if !operator.is_exist(&candidate).await? {
+			return Ok(candidate);
+		}
+	}
+
+	Err(opendal::Error::new(
+		ErrorKind::AlreadyExists,
+		"failed to find an available name to avoid duplication",
+	))
+}
+
+fn send(node: &Node, progress: &FileOpProgressEvent) {
+	if node
+		.event_bus
+		.0
+		.send(CoreEvent::FileOpProgress(progress.clone()))
+		.is_err()
+	{
+		warn!("Error sending event to Node's event bus");
+	}
+}
@@ -0,0 +1,268 @@
+//! The trash of the library. Files deleted through the core are moved into the `.sd_trash`
+//! folder of their location and recorded with their original path, so they can be restored until
+//! the trash is emptied or they're purged for being older than [`TrashSettings::retention_days`].
+
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::{delete_directory, get_location_path_from_location_id},
+	Node,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+
+use sd_prisma::{
+	prisma::{file_path, location, trashed_file},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{db::maybe_missing, error::FileIOError, from_bytes_to_uuid};
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+use chrono::{Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{fs, io, time};
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{error::FileSystemJobsError, find_available_filename_for_duplicate, FileData};
+
+/// Name of the folder at the root of each location where its deleted files are kept, skipped by
+/// the indexer and the watcher
+pub const TRASH_DIR_NAME: &str = ".sd_trash";
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// How long deleted files are kept, stored in the library config.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashSettings {
+	/// Days after which trashed files are purged, they're kept until the trash is emptied if `None`
+	pub retention_days: Option<u32>,
+}
+
+impl Default for TrashSettings {
+	fn default() -> Self {
+		Self {
+			retention_days: Some(DEFAULT_RETENTION_DAYS),
+		}
+	}
+}
+
+fn trashed_path(location_path: impl AsRef<Path>, pub_id: &[u8]) -> PathBuf {
+	location_path
+		.as_ref()
+		.join(TRASH_DIR_NAME)
+		.join(from_bytes_to_uuid(pub_id).to_string())
+}
+
+/// Moves the file or folder into the trash of its location and removes it from the index.
+///
+/// Fails with a [`FileIOError`] of kind [`io::ErrorKind::NotFound`] if it no longer exists.
+pub async fn move_to_trash(
+	library: &Library,
+	location_id: location::id::Type,
+	location_path: impl AsRef<Path>,
+	FileData {
+		file_path,
+		full_path,
+	}: &FileData,
+) -> Result<trashed_file::Data, FileSystemJobsError> {
+	let Library { db, sync, .. } = library;
+	let location_path = location_path.as_ref();
+
+	let is_dir = maybe_missing(file_path.is_dir, "file_path.is_dir")?;
+	let iso_file_path = IsolatedFilePathData::try_from(file_path)?;
+
+	let trash_dir = location_path.join(TRASH_DIR_NAME);
+	fs::create_dir_all(&trash_dir)
+		.await
+		.map_err(|e| FileIOError::from((&trash_dir, e)))?;
+
+	let pub_id = Uuid::new_v4().as_bytes().to_vec();
+
+	// Recorded before moving the file, so a file in the trash is never left without its record
+	let trashed = db
+		.trashed_file()
+		.create(
+			pub_id.clone(),
+			iso_file_path.to_string(),
+			is_dir,
+			Utc::now().into(),
+			location::id::equals(location_id),
+			vec![trashed_file::size_in_bytes_bytes::set(
+				file_path.size_in_bytes_bytes.clone(),
+			)],
+		)
+		.exec()
+		.await?;
+
+	if let Err(e) = fs::rename(full_path, trashed_path(location_path, &pub_id)).await {
+		if let Err(e) = db
+			.trashed_file()
+			.delete(trashed_file::id::equals(trashed.id))
+			.exec()
+			.await
+		{
+			warn!("Failed to remove the record of a file that couldn't be trashed: {e:#?}");
+		}
+
+		return Err(FileIOError::from((full_path, e)).into());
+	}
+
+	// The watcher ignores the trash folder, so the moved paths are removed from the index here
+	if is_dir {
+		delete_directory(library, location_id, Some(&iso_file_path)).await?;
+	} else {
+		sync.write_op(
+			db,
+			sync.shared_delete(prisma_sync::file_path::SyncId {
+				pub_id: file_path.pub_id.clone(),
+			}),
+			db.file_path().delete(file_path::id::equals(file_path.id)),
+		)
+		.await?;
+	}
+
+	Ok(trashed)
+}
+
+/// Moves the trashed file back to its original path, or next to it with a `name (1).ext` like
+/// name if the path was taken since.
+///
+/// Returns the closest folder of the restored path that was already there, to be scanned for
+/// indexing it again.
+pub async fn restore(
+	library: &Library,
+	trashed: &trashed_file::Data,
+) -> Result<PathBuf, FileSystemJobsError> {
+	let location_path =
+		get_location_path_from_location_id(&library.db, trashed.location_id).await?;
+
+	let mut target = location_path.join(&trashed.original_path);
+	if fs::metadata(&target).await.is_ok() {
+		target = find_available_filename_for_duplicate(&target).await?;
+	}
+
+	let parent = target
+		.parent()
+		.ok_or_else(|| FileSystemJobsError::MissingParentPath(target.clone().into_boxed_path()))?;
+
+	let mut existing_ancestor = parent;
+	while fs::metadata(existing_ancestor).await.is_err() {
+		match existing_ancestor.parent() {
+			Some(ancestor) if ancestor.starts_with(&location_path) => existing_ancestor = ancestor,
+			_ => break,
+		}
+	}
+	let existing_ancestor = existing_ancestor.to_path_buf();
+
+	fs::create_dir_all(parent)
+		.await
+		.map_err(|e| FileIOError::from((parent, e)))?;
+
+	let source = trashed_path(&location_path, &trashed.pub_id);
+	fs::rename(&source, &target)
+		.await
+		.map_err(|e| FileIOError::from((&source, e)))?;
+
+	library
+		.db
+		.trashed_file()
+		.delete(trashed_file::id::equals(trashed.id))
+		.exec()
+		.await?;
+
+	Ok(existing_ancestor)
+}
+
+/// Permanently removes the trashed files matching `params`, returning how many were.
+pub async fn purge(
+	library: &Library,
+	params: Vec<trashed_file::WhereParam>,
+) -> Result<usize, FileSystemJobsError> {
+	let db = &library.db;
+
+	let trashed_files = db.trashed_file().find_many(params).exec().await?;
+	if trashed_files.is_empty() {
+		return Ok(0);
+	}
+
+	let mut location_paths = HashMap::new();
+	let mut purged = vec![];
+
+	for trashed in trashed_files {
+		if !location_paths.contains_key(&trashed.location_id) {
+			let location_path = get_location_path_from_location_id(db, trashed.location_id).await?;
+			location_paths.insert(trashed.location_id, location_path);
+		}
+
+		let path = trashed_path(&location_paths[&trashed.location_id], &trashed.pub_id);
+
+		match if trashed.is_dir {
+			fs::remove_dir_all(&path).await
+		} else {
+			fs::remove_file(&path).await
+		} {
+			Ok(()) => purged.push(trashed.id),
+			// Removed by hand, so there is nothing left to keep track of
+			Err(e) if e.kind() == io::ErrorKind::NotFound => purged.push(trashed.id),
+			Err(e) => warn!(
+				"Failed to purge trashed file '{}': {:#?}",
+				path.display(),
+				FileIOError::from((&path, e))
+			),
+		}
+	}
+
+	let count = purged.len();
+
+	db.trashed_file()
+		.delete_many(vec![trashed_file::id::in_vec(purged)])
+		.exec()
+		.await?;
+
+	invalidate_query!(library, "trash.list");
+
+	Ok(count)
+}
+
+/// Purges the trashed files older than the retention of the library.
+pub async fn purge_expired(library: &Library) -> Result<usize, FileSystemJobsError> {
+	let Some(retention_days) = library.config().await.trash.retention_days else {
+		return Ok(0);
+	};
+
+	purge(
+		library,
+		vec![trashed_file::date_deleted::lt(
+			(Utc::now() - ChronoDuration::days(i64::from(retention_days))).into(),
+		)],
+	)
+	.await
+}
+
+/// Purges the expired trashed files of the library periodically, until the library is unloaded.
+pub(crate) async fn purge_periodically(node: Arc<Node>, library_id: Uuid) {
+	let mut interval = time::interval(PURGE_INTERVAL);
+
+	loop {
+		interval.tick().await;
+
+		let Some(library) = node.libraries.get_library(&library_id).await else {
+			break;
+		};
+
+		if let Err(e) = purge_expired(&library).await {
+			warn!("Failed to purge the trash of library <id='{library_id}'>: {e:#?}");
+		}
+	}
+}
@@ -3,7 +3,8 @@ pub mod old_media_processor;
 pub mod old_thumbnail;
 
 pub use old_media_processor::OldMediaProcessorJobInit;
-use sd_media_metadata::ImageMetadata;
+use sd_file_ext::kind::ObjectKind;
+use sd_media_metadata::{ImageMetadata, MediaMetadata};
 use sd_prisma::prisma::media_data::*;
 
 use self::media_data_extractor::MediaDataError;
@@ -12,6 +13,8 @@ pub fn media_data_image_to_query(
 	mdi: ImageMetadata,
 	object_id: object_id::Type,
 ) -> Result<CreateUnchecked, MediaDataError> {
+	let (latitude, longitude) = mdi.location.as_ref().map(|l| l.coordinates()).unzip();
+
 	Ok(CreateUnchecked {
 		object_id,
 		_params: vec![
@@ -24,6 +27,9 @@ pub fn media_data_image_to_query(
 			copyright::set(mdi.copyright),
 			exif_version::set(mdi.exif_version),
 			epoch_time::set(mdi.date_taken.map(|x| x.unix_timestamp())),
+			camera_model::set(mdi.camera_data.device_model),
+			latitude::set(latitude),
+			longitude::set(longitude),
 		],
 	})
 }
@@ -34,6 +40,8 @@ pub fn media_data_image_to_query_params(
 	use sd_sync::option_sync_db_entry;
 	use sd_utils::chain_optional_iter;
 
+	let (latitude, longitude) = mdi.location.as_ref().map(|l| l.coordinates()).unzip();
+
 	chain_optional_iter(
 		[],
 		[
@@ -44,6 +52,10 @@ pub fn media_data_image_to_query_params(
 			option_sync_db_entry!(mdi.description, description),
 			option_sync_db_entry!(mdi.copyright, copyright),
 			option_sync_db_entry!(mdi.exif_version, exif_version),
+			option_sync_db_entry!(mdi.date_taken.map(|x| x.unix_timestamp()), epoch_time),
+			option_sync_db_entry!(mdi.camera_data.device_model, camera_model),
+			option_sync_db_entry!(latitude, latitude),
+			option_sync_db_entry!(longitude, longitude),
 		],
 	)
 	.into_iter()
@@ -65,6 +77,19 @@ pub fn media_data_image_from_prisma_data(
 	})
 }
 
+/// The media data of an object of `kind`, `None` for the kinds it isn't read for yet.
+pub fn media_metadata_from_prisma_data(
+	kind: Option<i32>,
+	data: sd_prisma::prisma::media_data::Data,
+) -> Option<MediaMetadata> {
+	match kind {
+		Some(kind) if kind == ObjectKind::Image as i32 => Some(MediaMetadata::Image(Box::new(
+			media_data_image_from_prisma_data(data).ok()?,
+		))),
+		_ => None, // TODO(brxken128): audio and video
+	}
+}
+
 #[must_use]
 fn from_slice_option_to_option<T: serde::Serialize + serde::de::DeserializeOwned>(
 	value: Option<Vec<u8>>,
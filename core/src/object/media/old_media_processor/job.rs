@@ -3,22 +3,20 @@ use crate::{
 	library::Library,
 	location::ScanState,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobStepOutput,
-		StatefulJob, WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobItemProgress, JobReportUpdate, JobResult,
+		JobRunErrors, JobStepOutput, StatefulJob, WorkerContext,
 	},
+	p2p::operations::{offload::thumbnails_offload_target, offload_thumbnails},
 	Node,
 };
 
-#[cfg(feature = "ai")]
-use crate::old_job::JobRunErrors;
-
 use sd_core_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
 	IsolatedFilePathData,
 };
 use sd_core_prisma_helpers::file_path_for_media_processor;
 
-use sd_file_ext::extensions::Extension;
+use sd_p2p::RemoteIdentity;
 use sd_prisma::prisma::{location, PrismaClient};
 use sd_utils::db::maybe_missing;
 
@@ -29,6 +27,7 @@ use sd_ai::old_image_labeler::{BatchToken as ImageLabelerBatchToken, LabelerOutp
 use std::sync::Arc;
 
 use std::{
+	fmt::Display,
 	hash::Hash,
 	path::{Path, PathBuf},
 	pin::pin,
@@ -41,16 +40,17 @@ use itertools::Itertools;
 use prisma_client_rust::{raw, PrismaValue};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::time::sleep;
+use tokio::{select, time::sleep};
 use tracing::{debug, error, info, trace, warn};
 
 use super::{
 	media_data_extractor,
-	old_thumbnail::{self, GenerateThumbnailArgs},
+	old_thumbnail::{self, GenerateThumbnailArgs, ThumbnailsProgress},
 	process, BatchToProcess, MediaProcessorError, OldMediaProcessorMetadata,
 };
 
 const BATCH_SIZE: usize = 10;
+const OFFLOAD_BATCH_SIZE: usize = 50;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OldMediaProcessorJobInit {
@@ -74,7 +74,7 @@ pub struct OldMediaProcessorJobData {
 	location_path: PathBuf,
 	to_process_path: PathBuf,
 	#[serde(skip, default)]
-	maybe_thumbnailer_progress_rx: Option<chan::Receiver<(u32, u32)>>,
+	maybe_thumbnailer_progress_rx: Option<chan::Receiver<ThumbnailsProgress>>,
 	#[cfg(feature = "ai")]
 	labeler_batch_token: ImageLabelerBatchToken,
 	#[cfg(feature = "ai")]
@@ -86,8 +86,12 @@ pub struct OldMediaProcessorJobData {
 pub enum OldMediaProcessorJobStep {
 	ExtractMediaData(Vec<file_path_for_media_processor::Data>),
 	WaitThumbnails(usize),
+	OffloadThumbnails(RemoteIdentity, Vec<GenerateThumbnailArgs>),
 	#[cfg(feature = "ai")]
 	WaitLabels(usize),
+	/// Files to give to the plugin with this name
+	#[cfg(feature = "plugins")]
+	ProcessWithPlugin(String, Vec<file_path_for_media_processor::Data>),
 }
 
 #[async_trait::async_trait]
@@ -153,17 +157,35 @@ impl StatefulJob for OldMediaProcessorJobInit {
 			"Searching for media files in location {location_id} at directory \"{iso_file_path}\""
 		);
 
-		let thumbs_to_process_count = dispatch_thumbnails_for_processing(
-			location_id,
-			&location_path,
-			&iso_file_path,
-			&ctx.library,
-			&ctx.node,
-			self.regenerate_thumbnails,
-		)
-		.await?;
+		let (thumbs_to_process_count, offload_thumbs_steps) =
+			match dispatch_thumbnails_for_processing(
+				location_id,
+				&location_path,
+				&iso_file_path,
+				&ctx.library,
+				&ctx.node,
+				self.regenerate_thumbnails,
+				ctx.limiter.limits().max_threads.map(usize::from),
+			)
+			.await?
+			{
+				DispatchedThumbnails::Local(count) => (count, vec![]),
+				DispatchedThumbnails::Offloaded(identity, thumbs_args) => (
+					thumbs_args.len() as u32,
+					thumbs_args
+						.into_iter()
+						.chunks(OFFLOAD_BATCH_SIZE)
+						.into_iter()
+						.map(|chunk| {
+							OldMediaProcessorJobStep::OffloadThumbnails(identity, chunk.collect())
+						})
+						.collect::<Vec<_>>(),
+				),
+			};
 
-		let maybe_thumbnailer_progress_rx = if thumbs_to_process_count > 0 {
+		let wait_local_thumbs = thumbs_to_process_count > 0 && offload_thumbs_steps.is_empty();
+
+		let maybe_thumbnailer_progress_rx = if wait_local_thumbs {
 			let (progress_tx, progress_rx) = chan::unbounded();
 
 			ctx.node
@@ -178,6 +200,11 @@ impl StatefulJob for OldMediaProcessorJobInit {
 
 		let file_paths = get_files_for_media_data_extraction(db, &iso_file_path).await?;
 
+		#[cfg(feature = "plugins")]
+		let plugin_steps = get_plugin_steps(&ctx.node, db, &iso_file_path).await?;
+		#[cfg(not(feature = "plugins"))]
+		let plugin_steps = vec![];
+
 		#[cfg(feature = "ai")]
 		let file_paths_for_labeling =
 			get_files_for_labeling(db, &iso_file_path, self.regenerate_labels).await?;
@@ -210,13 +237,17 @@ impl StatefulJob for OldMediaProcessorJobInit {
 			.into_iter()
 			.map(|chunk| chunk.collect::<Vec<_>>())
 			.map(OldMediaProcessorJobStep::ExtractMediaData)
+			.chain(plugin_steps)
 			.chain(
-				[(thumbs_to_process_count > 0).then_some(
-					OldMediaProcessorJobStep::WaitThumbnails(thumbs_to_process_count as usize),
-				)]
+				[
+					wait_local_thumbs.then_some(OldMediaProcessorJobStep::WaitThumbnails(
+						thumbs_to_process_count as usize,
+					)),
+				]
 				.into_iter()
 				.flatten(),
 			)
+			.chain(offload_thumbs_steps)
 			.chain(
 				[
 					#[cfg(feature = "ai")]
@@ -313,12 +344,40 @@ impl StatefulJob for OldMediaProcessorJobInit {
 
 				let mut total_completed = 0;
 
-				while let Some((completed, total)) = progress_rx.next().await {
-					trace!("Received progress update from thumbnailer: {completed}/{total}",);
-					ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
-						completed as usize,
-					)]);
-					total_completed = completed;
+				// The thumbnailer works on its own, so we have to hold it back while we're paused
+				let mut paused_rx = ctx.paused_rx();
+				let mut held_back = None;
+
+				loop {
+					select! {
+						progress = progress_rx.next() => {
+							let Some((completed, total, path)) = progress else {
+								break;
+							};
+
+							trace!("Received progress update from thumbnailer: {completed}/{total}",);
+							ctx.progress(
+								path.map(|path| {
+									JobReportUpdate::Item(JobItemProgress::new(
+										path.display().to_string(),
+									))
+								})
+								.into_iter()
+								.chain([JobReportUpdate::CompletedTaskCount(completed as usize)])
+								.collect(),
+							);
+							total_completed = completed;
+						}
+						Ok(()) = paused_rx.changed() => {
+							held_back = if *paused_rx.borrow_and_update() {
+								held_back.or_else(|| {
+									Some(ctx.node.thumbnailer.hold_back_location(self.location.id))
+								})
+							} else {
+								None
+							};
+						}
+					}
 				}
 
 				if progress_rx.is_closed() && total_completed < *total_thumbs as u32 {
@@ -332,6 +391,53 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				Ok(None.into())
 			}
 
+			OldMediaProcessorJobStep::OffloadThumbnails(identity, thumbs_args) => {
+				ctx.progress(vec![
+					JobReportUpdate::Phase("thumbnails".to_string()),
+					JobReportUpdate::Message(format!(
+						"Generating {} thumbnails on node '{identity}'",
+						thumbs_args.len()
+					)),
+				]);
+
+				match offload_thumbnails(
+					&ctx.node,
+					&ctx.library,
+					*identity,
+					thumbs_args,
+					self.regenerate_thumbnails,
+				)
+				.await
+				{
+					Ok(errors) if errors.is_empty() => Ok(None.into()),
+					Ok(errors) => Ok(JobRunErrors(errors).into()),
+					Err(e) => {
+						warn!("Failed to offload thumbnails to node '{identity}', generating them locally: {e:#?}");
+
+						ctx.node
+							.thumbnailer
+							.new_indexed_thumbnails_batch(
+								BatchToProcess::new(
+									thumbs_args.clone(),
+									self.regenerate_thumbnails,
+									true,
+								)
+								.with_animated_videos(
+									ctx.library.config().await.thumbnails.animated_videos,
+								),
+								ctx.library.id,
+							)
+							.await;
+
+						Ok(JobRunErrors(vec![format!(
+							"Failed to offload thumbnails to node '{identity}', \
+							they will be generated locally: {e}"
+						)])
+						.into())
+					}
+				}
+			}
+
 			#[cfg(feature = "ai")]
 			OldMediaProcessorJobStep::WaitLabels(total_labels) => {
 				let Some(image_labeller) = ctx.node.old_image_labeller.as_ref() else {
@@ -399,6 +505,38 @@ impl StatefulJob for OldMediaProcessorJobInit {
 					Ok(None.into())
 				}
 			}
+
+			#[cfg(feature = "plugins")]
+			OldMediaProcessorJobStep::ProcessWithPlugin(plugin_name, file_paths) => {
+				let Some(plugin) = ctx.node.plugins.get(plugin_name).await else {
+					return Ok(JobRunErrors(vec![format!(
+						"Plugin '{plugin_name}' isn't loaded anymore, skipping {} files",
+						file_paths.len()
+					)])
+					.into());
+				};
+
+				ctx.progress(vec![
+					JobReportUpdate::Phase("plugins".to_string()),
+					JobReportUpdate::Message(format!(
+						"Processing {} files with plugin '{plugin_name}'",
+						file_paths.len()
+					)),
+				]);
+
+				super::plugins::process(
+					&plugin,
+					file_paths,
+					self.location.id,
+					&data.location_path,
+					&ctx.node,
+					&ctx.library,
+					self.regenerate_thumbnails,
+				)
+				.await
+				.map(Into::into)
+				.map_err(Into::into)
+			}
 		}
 	}
 
@@ -417,7 +555,7 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				.display()
 		);
 
-		if run_metadata.media_data.extracted > 0 {
+		if run_metadata.media_data.extracted > 0 || run_metadata.plugins_processed > 0 {
 			invalidate_query!(ctx.library, "search.paths");
 		}
 
@@ -436,6 +574,13 @@ impl StatefulJob for OldMediaProcessorJobInit {
 	}
 }
 
+enum DispatchedThumbnails {
+	/// Sent to our thumbnailer, the amount of thumbnails to wait for
+	Local(u32),
+	/// To be generated by another node, sent by the job itself
+	Offloaded(RemoteIdentity, Vec<GenerateThumbnailArgs>),
+}
+
 async fn dispatch_thumbnails_for_processing(
 	location_id: location::id::Type,
 	location_path: impl AsRef<Path>,
@@ -443,7 +588,8 @@ async fn dispatch_thumbnails_for_processing(
 	library: &Library,
 	node: &Node,
 	should_regenerate: bool,
-) -> Result<u32, MediaProcessorError> {
+	max_parallelism: Option<usize>,
+) -> Result<DispatchedThumbnails, MediaProcessorError> {
 	let Library { db, .. } = library;
 
 	let location_path = location_path.as_ref();
@@ -451,12 +597,26 @@ async fn dispatch_thumbnails_for_processing(
 	let mut file_paths = get_all_children_files_by_extensions(
 		db,
 		parent_iso_file_path,
-		&old_thumbnail::ALL_THUMBNAILABLE_EXTENSIONS,
+		old_thumbnail::ALL_THUMBNAILABLE_EXTENSIONS.as_slice(),
 	)
 	.await?;
 
 	if file_paths.is_empty() {
-		return Ok(0);
+		return Ok(DispatchedThumbnails::Local(0));
+	}
+
+	if let Some(identity) = thumbnails_offload_target(node, library.id).await {
+		let thumbs_args = file_paths
+			.into_iter()
+			.filter_map(|file_path| prepare_args(location_id, location_path, file_path))
+			.collect::<Vec<_>>();
+
+		debug!(
+			"Offloading {} thumbnails to node '{identity}'",
+			thumbs_args.len()
+		);
+
+		return Ok(DispatchedThumbnails::Offloaded(identity, thumbs_args));
 	}
 
 	let first_materialized_path = file_paths[0].materialized_path.clone();
@@ -483,6 +643,8 @@ async fn dispatch_thumbnails_for_processing(
 
 	let thumbs_count = background_thumbs_args.len() + foreground_thumbs_args.len();
 
+	let animated_videos = library.config().await.thumbnails.animated_videos;
+
 	debug!(
 		"Dispatching {thumbs_count} thumbnails to be processed, {} in foreground and {} in background",
 		foreground_thumbs_args.len(),
@@ -492,7 +654,9 @@ async fn dispatch_thumbnails_for_processing(
 	if !foreground_thumbs_args.is_empty() {
 		node.thumbnailer
 			.new_indexed_thumbnails_tracked_batch(
-				BatchToProcess::new(foreground_thumbs_args, should_regenerate, false),
+				BatchToProcess::new(foreground_thumbs_args, should_regenerate, false)
+					.with_max_parallelism(max_parallelism)
+					.with_animated_videos(animated_videos),
 				library.id,
 				location_id,
 			)
@@ -502,14 +666,16 @@ async fn dispatch_thumbnails_for_processing(
 	if !background_thumbs_args.is_empty() {
 		node.thumbnailer
 			.new_indexed_thumbnails_tracked_batch(
-				BatchToProcess::new(background_thumbs_args, should_regenerate, true),
+				BatchToProcess::new(background_thumbs_args, should_regenerate, true)
+					.with_max_parallelism(max_parallelism)
+					.with_animated_videos(animated_videos),
 				library.id,
 				location_id,
 			)
 			.await;
 	}
 
-	Ok(thumbs_count as u32)
+	Ok(DispatchedThumbnails::Local(thumbs_count as u32))
 }
 
 async fn get_files_for_media_data_extraction(
@@ -519,7 +685,7 @@ async fn get_files_for_media_data_extraction(
 	get_all_children_files_by_extensions(
 		db,
 		parent_iso_file_path,
-		&media_data_extractor::FILTERED_IMAGE_EXTENSIONS,
+		media_data_extractor::FILTERED_IMAGE_EXTENSIONS.as_slice(),
 	)
 	.await
 	.map_err(Into::into)
@@ -570,10 +736,50 @@ async fn get_files_for_labeling(
 	.map_err(Into::into)
 }
 
+#[cfg(feature = "plugins")]
+async fn get_plugin_steps(
+	node: &Node,
+	db: &PrismaClient,
+	parent_iso_file_path: &IsolatedFilePathData<'_>,
+) -> Result<Vec<OldMediaProcessorJobStep>, MediaProcessorError> {
+	let mut steps = vec![];
+
+	for plugin in node.plugins.list().await {
+		if plugin.manifest.extensions.is_empty()
+			|| !(plugin.exports.extract_metadata || plugin.exports.generate_thumbnail)
+		{
+			continue;
+		}
+
+		// Extensions of plugins are validated as alphanumeric when loading them
+		let file_paths = get_all_children_files_by_extensions(
+			db,
+			parent_iso_file_path,
+			plugin.manifest.extensions.as_slice(),
+		)
+		.await?;
+
+		steps.extend(
+			file_paths
+				.into_iter()
+				.chunks(BATCH_SIZE)
+				.into_iter()
+				.map(|chunk| {
+					OldMediaProcessorJobStep::ProcessWithPlugin(
+						plugin.manifest.name.clone(),
+						chunk.collect(),
+					)
+				}),
+		);
+	}
+
+	Ok(steps)
+}
+
 async fn get_all_children_files_by_extensions(
 	db: &PrismaClient,
 	parent_iso_file_path: &IsolatedFilePathData<'_>,
-	extensions: &[Extension],
+	extensions: &[impl Display],
 ) -> Result<Vec<file_path_for_media_processor::Data>, MediaProcessorError> {
 	// FIXME: Had to use format! macro because PCR doesn't support IN with Vec for SQLite
 	// We have no data coming from the user, so this is sql injection safe
@@ -17,6 +17,8 @@ use super::{
 };
 
 mod job;
+#[cfg(feature = "plugins")]
+mod plugins;
 mod shallow;
 
 pub use job::OldMediaProcessorJobInit;
@@ -43,6 +45,8 @@ pub struct OldMediaProcessorMetadata {
 	media_data: OldMediaDataExtractorMetadata,
 	thumbs_processed: u32,
 	labels_extracted: u32,
+	#[serde(default)]
+	plugins_processed: u32,
 }
 
 impl From<OldMediaDataExtractorMetadata> for OldMediaProcessorMetadata {
@@ -51,6 +55,7 @@ impl From<OldMediaDataExtractorMetadata> for OldMediaProcessorMetadata {
 			media_data,
 			thumbs_processed: 0,
 			labels_extracted: 0,
+			plugins_processed: 0,
 		}
 	}
 }
@@ -61,6 +66,7 @@ impl JobRunMetadata for OldMediaProcessorMetadata {
 		self.media_data.skipped += new_data.media_data.skipped;
 		self.thumbs_processed += new_data.thumbs_processed;
 		self.labels_extracted += new_data.labels_extracted;
+		self.plugins_processed += new_data.plugins_processed;
 	}
 }
 
@@ -0,0 +1,150 @@
+use crate::{
+	api::CoreEvent,
+	library::Library,
+	object::media::old_thumbnail::{
+		generate_thumbnail_from_bytes, get_indexed_thumb_key, get_indexed_thumbnail_path,
+		ThumbnailTier,
+	},
+	old_job::JobRunErrors,
+	plugins::LoadedPlugin,
+	Node,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+use sd_core_prisma_helpers::file_path_for_media_processor;
+
+use sd_prisma::prisma::{location, plugin_metadata};
+
+use std::{collections::HashSet, path::Path};
+
+use tokio::fs;
+use tracing::error;
+
+use super::{MediaProcessorError, OldMediaProcessorMetadata};
+
+/// Runs the metadata extractor and thumbnail generator of the plugin over the files, the ones
+/// it doesn't export being skipped.
+pub(super) async fn process(
+	plugin: &LoadedPlugin,
+	file_paths: &[file_path_for_media_processor::Data],
+	location_id: location::id::Type,
+	location_path: impl AsRef<Path>,
+	node: &Node,
+	library: &Library,
+	regenerate_thumbnails: bool,
+) -> Result<(OldMediaProcessorMetadata, JobRunErrors), MediaProcessorError> {
+	let location_path = location_path.as_ref();
+	let plugin_name = &plugin.manifest.name;
+
+	let mut run_metadata = OldMediaProcessorMetadata::default();
+	let mut errors = vec![];
+
+	// Objects the plugin already extracted metadata from are skipped
+	let objects_with_metadata = if plugin.exports.extract_metadata {
+		library
+			.db
+			.plugin_metadata()
+			.find_many(vec![
+				plugin_metadata::plugin::equals(plugin_name.clone()),
+				plugin_metadata::object_id::in_vec(
+					file_paths
+						.iter()
+						.filter_map(|file_path| file_path.object_id)
+						.collect(),
+				),
+			])
+			.select(plugin_metadata::select!({ object_id }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|metadata| metadata.object_id)
+			.collect::<HashSet<_>>()
+	} else {
+		HashSet::new()
+	};
+
+	for file_path in file_paths {
+		let iso_file_path = match IsolatedFilePathData::try_from((location_id, file_path)) {
+			Ok(iso_file_path) => iso_file_path,
+			Err(e) => {
+				error!("{e:#?}");
+				continue;
+			}
+		};
+		let extension = iso_file_path.extension().to_string();
+		let path = location_path.join(iso_file_path);
+
+		if let Some(object_id) = file_path.object_id.filter(|object_id| {
+			plugin.exports.extract_metadata && !objects_with_metadata.contains(object_id)
+		}) {
+			match plugin.extract_metadata(&path, &extension).await {
+				Ok(Some(metadata)) => {
+					library
+						.db
+						.plugin_metadata()
+						.create_many(
+							metadata
+								.into_iter()
+								.map(|(key, value)| {
+									plugin_metadata::create_unchecked(
+										plugin_name.clone(),
+										key,
+										value,
+										object_id,
+										vec![],
+									)
+								})
+								.collect(),
+						)
+						.skip_duplicates()
+						.exec()
+						.await?;
+
+					run_metadata.plugins_processed += 1;
+				}
+				Ok(None) => {}
+				Err(e) => errors.push(format!(
+					"Couldn't extract metadata of file \"{}\" with plugin '{plugin_name}': {e}",
+					path.display()
+				)),
+			}
+		}
+
+		if let (true, Some(cas_id)) = (plugin.exports.generate_thumbnail, &file_path.cas_id) {
+			let output_path =
+				get_indexed_thumbnail_path(node, cas_id, library.id, ThumbnailTier::Grid);
+
+			if !regenerate_thumbnails && fs::metadata(&output_path).await.is_ok() {
+				continue;
+			}
+
+			let result = match plugin.generate_thumbnail(&path, &extension).await {
+				Ok(Some(image)) => {
+					generate_thumbnail_from_bytes(image, &output_path, ThumbnailTier::Grid)
+						.await
+						.map(|()| true)
+						.map_err(|e| e.to_string())
+				}
+				Ok(None) => Ok(false),
+				Err(e) => Err(e.to_string()),
+			};
+
+			match result {
+				Ok(true) => {
+					node.emit(CoreEvent::NewThumbnail {
+						thumb_key: get_indexed_thumb_key(cas_id, library.id, ThumbnailTier::Grid),
+					});
+
+					run_metadata.thumbs_processed += 1;
+				}
+				Ok(false) => {}
+				Err(e) => errors.push(format!(
+					"Couldn't generate thumbnail of file \"{}\" with plugin '{plugin_name}': {e}",
+					path.display()
+				)),
+			}
+		}
+	}
+
+	Ok((run_metadata, errors.into()))
+}
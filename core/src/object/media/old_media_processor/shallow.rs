@@ -286,7 +286,8 @@ async fn dispatch_thumbnails_for_processing(
 	if !current_batch.is_empty() {
 		node.thumbnailer
 			.new_indexed_thumbnails_batch(
-				BatchToProcess::new(current_batch, should_regenerate, false),
+				BatchToProcess::new(current_batch, should_regenerate, false)
+					.with_animated_videos(library.config().await.thumbnails.animated_videos),
 				library.id,
 			)
 			.await;
@@ -0,0 +1,196 @@
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::SystemTime,
+};
+
+use tokio::{fs, io, task::spawn_blocking};
+use tracing::{debug, error, trace};
+
+use super::{cas_id_from_file_stem, ThumbnailerError, EPHEMERAL_DIR, WEBP_EXTENSION};
+
+struct CachedThumbnail {
+	path: PathBuf,
+	size: u64,
+	last_access: SystemTime,
+	/// Grid thumbnails of indexed files are only generated again when their location is scanned,
+	/// so they're the last ones to go
+	evict_last: bool,
+}
+
+async fn sub_directories(path: &Path) -> Result<Vec<PathBuf>, FileIOError> {
+	let mut read_dir = fs::read_dir(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	let mut sub_directories = vec![];
+	while let Some(entry) = read_dir
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?
+	{
+		let entry_path = entry.path();
+		if entry
+			.file_type()
+			.await
+			.map_err(|e| FileIOError::from((&entry_path, e)))?
+			.is_dir()
+		{
+			sub_directories.push(entry_path);
+		}
+	}
+
+	Ok(sub_directories)
+}
+
+/// Lists the thumbnails of every library and the ephemeral ones, with their size and the last
+/// time they were accessed, which is kept as their modification time.
+async fn list_thumbnails(
+	thumbnails_directory: &Path,
+) -> Result<Vec<CachedThumbnail>, ThumbnailerError> {
+	let mut thumbnails = vec![];
+
+	for kind_dir in sub_directories(thumbnails_directory).await? {
+		let is_ephemeral = kind_dir.file_name() == Some(EPHEMERAL_DIR.as_ref());
+
+		for shard_dir in sub_directories(&kind_dir).await? {
+			let mut read_shard_dir = fs::read_dir(&shard_dir)
+				.await
+				.map_err(|e| FileIOError::from((&shard_dir, e)))?;
+
+			while let Some(thumb_entry) = read_shard_dir
+				.next_entry()
+				.await
+				.map_err(|e| FileIOError::from((&shard_dir, e)))?
+			{
+				let path = thumb_entry.path();
+				if path.extension() != Some(WEBP_EXTENSION.as_ref()) {
+					continue;
+				}
+
+				let metadata = thumb_entry
+					.metadata()
+					.await
+					.map_err(|e| FileIOError::from((&path, e)))?;
+
+				let is_grid = path
+					.file_stem()
+					.and_then(|file_stem| file_stem.to_str())
+					.is_some_and(|file_stem| cas_id_from_file_stem(file_stem) == file_stem);
+
+				thumbnails.push(CachedThumbnail {
+					size: metadata.len(),
+					last_access: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+					evict_last: !is_ephemeral && is_grid,
+					path,
+				});
+			}
+		}
+	}
+
+	Ok(thumbnails)
+}
+
+/// Total size in bytes and count of the thumbnails in the cache
+pub(super) async fn cache_size(
+	thumbnails_directory: &Path,
+) -> Result<(u64, usize), ThumbnailerError> {
+	let thumbnails = list_thumbnails(thumbnails_directory).await?;
+
+	Ok((
+		thumbnails.iter().map(|thumbnail| thumbnail.size).sum(),
+		thumbnails.len(),
+	))
+}
+
+/// Removes every thumbnail, keeping the directories of the libraries
+pub(super) async fn clear_cache(thumbnails_directory: &Path) -> Result<(), ThumbnailerError> {
+	for CachedThumbnail { path, .. } in list_thumbnails(thumbnails_directory).await? {
+		if let Err(e) = fs::remove_file(&path).await {
+			if e.kind() != io::ErrorKind::NotFound {
+				return Err(FileIOError::from((path, e)).into());
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Stores the last access of the thumbnails served since the previous run as their modification
+/// time, then evicts the least recently accessed ones until the cache fits in `budget_bytes`.
+pub(super) async fn process_cache_eviction(
+	thumbnails_directory: Arc<PathBuf>,
+	accessed: HashMap<PathBuf, SystemTime>,
+	budget_bytes: Option<u64>,
+) {
+	if !accessed.is_empty() {
+		if let Err(e) = spawn_blocking(move || {
+			for (path, last_access) in accessed {
+				if let Err(e) = std::fs::File::options()
+					.write(true)
+					.open(&path)
+					.and_then(|file| file.set_modified(last_access))
+				{
+					// The thumbnail may have been removed since it was accessed
+					if e.kind() != io::ErrorKind::NotFound {
+						error!(
+							"Failed to store thumbnail last access: {:#?}",
+							FileIOError::from((path, e))
+						);
+					}
+				}
+			}
+		})
+		.await
+		{
+			error!("Join error on storing thumbnails last access: {e:#?}");
+		}
+	}
+
+	let Some(budget_bytes) = budget_bytes else {
+		return;
+	};
+
+	let mut thumbnails = match list_thumbnails(&thumbnails_directory).await {
+		Ok(thumbnails) => thumbnails,
+		Err(e) => {
+			error!("Error listing thumbnails to evict: {e:#?}");
+			return;
+		}
+	};
+
+	let mut cache_size = thumbnails
+		.iter()
+		.map(|thumbnail| thumbnail.size)
+		.sum::<u64>();
+	if cache_size <= budget_bytes {
+		trace!("Thumbnails cache takes {cache_size} bytes, within its budget of {budget_bytes}");
+		return;
+	}
+
+	thumbnails.sort_unstable_by_key(|thumbnail| (thumbnail.evict_last, thumbnail.last_access));
+
+	let mut evicted_count = 0;
+	for CachedThumbnail { path, size, .. } in thumbnails {
+		if cache_size <= budget_bytes {
+			break;
+		}
+
+		match fs::remove_file(&path).await {
+			Ok(()) => {
+				cache_size -= size;
+				evicted_count += 1;
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => cache_size -= size,
+			Err(e) => error!(
+				"Failed to evict thumbnail: {:#?}",
+				FileIOError::from((path, e))
+			),
+		}
+	}
+
+	debug!("Evicted {evicted_count} thumbnails to fit the cache in {budget_bytes} bytes");
+}
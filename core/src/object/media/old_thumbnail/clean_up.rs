@@ -3,13 +3,29 @@ use crate::library::LibraryId;
 use sd_prisma::prisma::{file_path, PrismaClient};
 use sd_utils::error::FileIOError;
 
-use std::{collections::HashSet, ffi::OsString, path::PathBuf, sync::Arc};
+use std::{
+	collections::HashSet,
+	ffi::OsString,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 
 use futures_concurrency::future::Join;
 use tokio::{fs, spawn};
 use tracing::{debug, error};
 
-use super::{ThumbnailerError, EPHEMERAL_DIR, WEBP_EXTENSION};
+use super::{cas_id_from_file_stem, ThumbnailerError, EPHEMERAL_DIR, WEBP_EXTENSION};
+
+/// Thumbnails are kept while their cas id exists, which is tracked by the file name of their grid
+/// tier, so this gets it for thumbnails of any tier or PDF page.
+fn grid_file_name(thumb_path: &Path) -> Option<OsString> {
+	thumb_path.file_stem()?.to_str().map(|file_stem| {
+		OsString::from(format!(
+			"{}.{WEBP_EXTENSION}",
+			cas_id_from_file_stem(file_stem)
+		))
+	})
+}
 
 pub(super) async fn process_ephemeral_clean_up(
 	thumbnails_directory: Arc<PathBuf>,
@@ -47,7 +63,8 @@ pub(super) async fn process_ephemeral_clean_up(
 				{
 					let thumb_path = thumb_entry.path();
 					if thumb_path.extension() == Some(WEBP_EXTENSION.as_ref())
-						&& !existing_ephemeral_thumbs.contains(&thumb_entry.file_name())
+						&& !grid_file_name(&thumb_path)
+							.is_some_and(|file_name| existing_ephemeral_thumbs.contains(&file_name))
 					{
 						to_remove.push(async move {
 							debug!(
@@ -136,7 +153,8 @@ pub(super) async fn process_indexed_clean_up(
 						{
 							let thumb_path = thumb_entry.path();
 							if thumb_path.extension() == Some(WEBP_EXTENSION.as_ref())
-								&& !existing_thumbs.contains(&thumb_entry.file_name())
+								&& !grid_file_name(&thumb_path)
+									.is_some_and(|file_name| existing_thumbs.contains(&file_name))
 							{
 								to_remove.push(async move {
 									debug!(
@@ -0,0 +1,273 @@
+//! Resizes thumbnails on the GPU, as resizing huge photos is most of the time the thumbnailer
+//! spends on them.
+//!
+//! Images are still decoded on the CPU. The ones bigger than the textures of the GPU, or failing to
+//! resize for any reason, are resized on the CPU instead, as are all of them when there's no GPU.
+
+use std::{borrow::Cow, sync::mpsc};
+
+use futures::executor::block_on;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use once_cell::sync::Lazy;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// Pixels of the thumbnail resized by each workgroup of the shader, along each axis
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Averages the pixels of the image covered by each pixel of the thumbnail
+const SHADER: &str = r"
+@group(0) @binding(0) var image: texture_2d<f32>;
+@group(0) @binding(1) var thumbnail: texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+	let thumbnail_size = textureDimensions(thumbnail);
+	if (id.x >= thumbnail_size.x || id.y >= thumbnail_size.y) {
+		return;
+	}
+
+	let image_size = textureDimensions(image);
+	let start = id.xy * image_size / thumbnail_size;
+	let end = max((id.xy + 1u) * image_size / thumbnail_size, start + 1u);
+
+	var sum = vec4<f32>(0.0);
+	for (var y = start.y; y < end.y; y++) {
+		for (var x = start.x; x < end.x; x++) {
+			sum += textureLoad(image, vec2<u32>(x, y), 0);
+		}
+	}
+
+	textureStore(thumbnail, id.xy, sum / f32((end.x - start.x) * (end.y - start.y)));
+}
+";
+
+/// `None` when there's no GPU to resize with
+static RESIZER: Lazy<Option<GpuResizer>> = Lazy::new(|| match block_on(GpuResizer::new()) {
+	Ok(resizer) => Some(resizer),
+	Err(e) => {
+		warn!("Resizing thumbnails on the CPU: {e}");
+		None
+	}
+});
+
+#[derive(Error, Debug)]
+enum GpuResizeError {
+	#[error("no GPU adapter found")]
+	NoAdapter,
+	#[error("the only GPU adapter is emulated on the CPU")]
+	SoftwareAdapter,
+	#[error("failed to request a GPU device: {0}")]
+	RequestDevice(#[from] wgpu::RequestDeviceError),
+	#[error("image bigger than the textures of the GPU")]
+	TooBig,
+	#[error("failed to read the thumbnail back from the GPU: {0}")]
+	ReadBack(#[from] wgpu::BufferAsyncError),
+	#[error("the GPU device was lost while resizing")]
+	DeviceLost,
+}
+
+struct GpuResizer {
+	device: wgpu::Device,
+	queue: wgpu::Queue,
+	pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuResizer {
+	async fn new() -> Result<Self, GpuResizeError> {
+		let adapter = wgpu::Instance::default()
+			.request_adapter(&wgpu::RequestAdapterOptions {
+				power_preference: wgpu::PowerPreference::HighPerformance,
+				..Default::default()
+			})
+			.await
+			.ok_or(GpuResizeError::NoAdapter)?;
+
+		let info = adapter.get_info();
+		// An emulated GPU resizes slower than the image crate does
+		if info.device_type == wgpu::DeviceType::Cpu {
+			return Err(GpuResizeError::SoftwareAdapter);
+		}
+
+		let (device, queue) = adapter
+			.request_device(
+				&wgpu::DeviceDescriptor {
+					label: Some("thumbnailer"),
+					required_features: wgpu::Features::empty(),
+					required_limits: adapter.limits(),
+				},
+				None,
+			)
+			.await?;
+
+		let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("thumbnail resize"),
+			source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+		});
+
+		let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+			label: Some("thumbnail resize"),
+			layout: None,
+			module: &module,
+			entry_point: "main",
+		});
+
+		debug!("Resizing thumbnails on the GPU '{}'", info.name);
+
+		Ok(Self {
+			device,
+			queue,
+			pipeline,
+		})
+	}
+
+	fn resize(
+		&self,
+		img: &DynamicImage,
+		width: u32,
+		height: u32,
+	) -> Result<RgbaImage, GpuResizeError> {
+		let (img_width, img_height) = img.dimensions();
+		if img_width.max(img_height) > self.device.limits().max_texture_dimension_2d {
+			return Err(GpuResizeError::TooBig);
+		}
+
+		let img = img.to_rgba8();
+
+		let image = self.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("image"),
+			size: extent(img_width, img_height),
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::Rgba8Unorm,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+
+		self.queue.write_texture(
+			image.as_image_copy(),
+			img.as_raw(),
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: Some(4 * img_width),
+				rows_per_image: Some(img_height),
+			},
+			extent(img_width, img_height),
+		);
+
+		let thumbnail = self.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("thumbnail"),
+			size: extent(width, height),
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::Rgba8Unorm,
+			usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+			view_formats: &[],
+		});
+
+		let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: None,
+			layout: &self.pipeline.get_bind_group_layout(0),
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(
+						&image.create_view(&Default::default()),
+					),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::TextureView(
+						&thumbnail.create_view(&Default::default()),
+					),
+				},
+			],
+		});
+
+		// Rows copied out of a texture must be aligned, the padding is dropped once read back
+		let row_len = 4 * width;
+		let padded_row_len = row_len.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+		let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("thumbnail"),
+			size: u64::from(padded_row_len) * u64::from(height),
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = self
+			.device
+			.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+		{
+			let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+				label: None,
+				timestamp_writes: None,
+			});
+			pass.set_pipeline(&self.pipeline);
+			pass.set_bind_group(0, &bind_group, &[]);
+			pass.dispatch_workgroups(
+				width.div_ceil(WORKGROUP_SIZE),
+				height.div_ceil(WORKGROUP_SIZE),
+				1,
+			);
+		}
+
+		encoder.copy_texture_to_buffer(
+			thumbnail.as_image_copy(),
+			wgpu::ImageCopyBuffer {
+				buffer: &output,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: Some(padded_row_len),
+					rows_per_image: Some(height),
+				},
+			},
+			extent(width, height),
+		);
+
+		self.queue.submit(Some(encoder.finish()));
+
+		let slice = output.slice(..);
+		let (tx, rx) = mpsc::channel();
+		slice.map_async(wgpu::MapMode::Read, move |res| {
+			tx.send(res).ok();
+		});
+		self.device.poll(wgpu::Maintain::Wait);
+		rx.recv().map_err(|_| GpuResizeError::DeviceLost)??;
+
+		let pixels = slice
+			.get_mapped_range()
+			.chunks(padded_row_len as usize)
+			.flat_map(|row| &row[..row_len as usize])
+			.copied()
+			.collect();
+		output.unmap();
+
+		RgbaImage::from_raw(width, height, pixels).ok_or(GpuResizeError::DeviceLost)
+	}
+}
+
+const fn extent(width: u32, height: u32) -> wgpu::Extent3d {
+	wgpu::Extent3d {
+		width,
+		height,
+		depth_or_array_layers: 1,
+	}
+}
+
+/// Returns `None` when the image has to be resized on the CPU instead.
+pub fn resize(img: &DynamicImage, width: u32, height: u32) -> Option<RgbaImage> {
+	let resizer = RESIZER.as_ref()?;
+
+	match resizer.resize(img, width, height) {
+		Ok(resized) => Some(resized),
+		Err(GpuResizeError::TooBig) => None,
+		Err(e) => {
+			warn!("Failed to resize a thumbnail on the GPU: {e}");
+			None
+		}
+	}
+}
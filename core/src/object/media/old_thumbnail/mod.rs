@@ -10,28 +10,39 @@ use sd_file_ext::extensions::{VideoExtension, ALL_VIDEO_EXTENSIONS};
 
 use std::{
 	path::{Path, PathBuf},
+	str::FromStr,
 	time::Duration,
 };
 
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use specta::Type;
 use thiserror::Error;
-use tokio::task;
+use tokio::{fs, io, task};
 use tracing::error;
 
+mod cache;
 mod clean_up;
 mod directory;
+#[cfg(feature = "gpu-thumbnails")]
+mod gpu;
 pub mod old_actor;
 pub mod preferences;
 mod process;
+mod remote;
 mod shard;
 mod state;
 mod worker;
 
-pub use process::{BatchToProcess, GenerateThumbnailArgs};
+pub use process::{
+	generate_thumbnail_at, generate_thumbnail_from_bytes, BatchToProcess, GenerateThumbnailArgs,
+};
+pub use remote::{remote_cas_id, remote_thumbnailer, RemoteFile};
 pub use shard::get_shard_hex;
+pub use state::ThumbnailsProgress;
 
 use directory::ThumbnailVersion;
+use process::generate_pdf_pages_at;
 
 // Files names constants
 const THUMBNAIL_CACHE_DIR_NAME: &str = "thumbnails";
@@ -40,14 +51,27 @@ const VERSION_FILE: &str = "version.txt";
 pub const WEBP_EXTENSION: &str = "webp";
 const EPHEMERAL_DIR: &str = "ephemeral";
 
-/// This is the target pixel count for all thumbnails to be resized to, and it is eventually downscaled
+/// This is the target pixel count for grid thumbnails to be resized to, and it is eventually downscaled
 /// to [`TARGET_QUALITY`].
 const TARGET_PX: f32 = 262144_f32;
 
-/// This is the target quality that we render thumbnails at, it is a float between 0-100
+/// This is the target quality that we render grid thumbnails at, it is a float between 0-100
 /// and is treated as a percentage (so 30% in this case, or it's the same as multiplying by `0.3`).
 const TARGET_QUALITY: f32 = 30_f32;
 
+/// How many pages of a PDF are rendered for its paged preview, from its first one
+const PDF_PREVIEW_PAGES: u16 = 8;
+
+/// How many frames are sampled across a video for its animated thumbnail, and how long each is shown
+#[cfg(feature = "ffmpeg")]
+const ANIMATED_THUMBNAIL_FRAMES: u32 = 8;
+#[cfg(feature = "ffmpeg")]
+const ANIMATED_THUMBNAIL_FRAME_DURATION: Duration = Duration::from_millis(500);
+
+/// Animated thumbnails bigger than this, in bytes, are replaced by a static one
+#[cfg(feature = "ffmpeg")]
+const MAX_ANIMATED_THUMBNAIL_SIZE: usize = 512 * 1024;
+
 // Some time constants
 const ONE_SEC: Duration = Duration::from_secs(1);
 const THIRTY_SECS: Duration = Duration::from_secs(30);
@@ -59,12 +83,114 @@ pub enum ThumbnailKind {
 	Indexed(LibraryId),
 }
 
-pub fn get_indexed_thumbnail_path(node: &Node, cas_id: &str, library_id: LibraryId) -> PathBuf {
-	get_thumbnail_path(node, cas_id, ThumbnailKind::Indexed(library_id))
+/// The sizes thumbnails are generated at. Every thumbnailable file gets a [`ThumbnailTier::Grid`]
+/// one, while the bigger ones are only generated when requested, as they take much more time and space.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailTier {
+	/// Shown on the explorer's grid and list views
+	#[default]
+	Grid,
+	/// Shown on the inspector and on big grid items
+	Detail,
+	/// Shown on the quick preview, in place of the original file when it can't be rendered
+	Preview,
+}
+
+impl ThumbnailTier {
+	pub const ALL: [Self; 3] = [Self::Grid, Self::Detail, Self::Preview];
+
+	/// The pixel count thumbnails of this tier are resized to
+	const fn target_px(self) -> f32 {
+		match self {
+			Self::Grid => TARGET_PX,
+			Self::Detail => 1048576_f32,
+			Self::Preview => 4194304_f32,
+		}
+	}
+
+	/// The webp quality thumbnails of this tier are encoded at, as a percentage
+	const fn target_quality(self) -> f32 {
+		match self {
+			Self::Grid => TARGET_QUALITY,
+			Self::Detail => 50_f32,
+			Self::Preview => 70_f32,
+		}
+	}
+
+	/// The size of the longest side of video frames extracted for thumbnails of this tier
+	#[cfg(feature = "ffmpeg")]
+	const fn video_size(self) -> u32 {
+		match self {
+			Self::Grid => 256,
+			Self::Detail => 1024,
+			Self::Preview => 2048,
+		}
+	}
+
+	/// Grid thumbnails keep being named by their cas id alone, so the ones generated before the
+	/// tiers existed remain valid, while the other tiers append their name to it.
+	fn file_stem(self, cas_id: &str) -> String {
+		match self {
+			Self::Grid => cas_id.to_string(),
+			Self::Detail => format!("{cas_id}-detail"),
+			Self::Preview => format!("{cas_id}-preview"),
+		}
+	}
+}
+
+/// PDF pages are rendered as [`ThumbnailTier::Detail`] thumbnails, named by their cas id and index.
+fn page_file_stem(cas_id: &str, page: u16) -> String {
+	format!("{cas_id}-page{page}")
+}
+
+/// Every file stem a thumbnail of `cas_id` may have, from its tiers and PDF pages
+fn all_file_stems(cas_id: &str) -> impl Iterator<Item = String> + '_ {
+	ThumbnailTier::ALL
+		.into_iter()
+		.map(|tier| tier.file_stem(cas_id))
+		.chain((0..PDF_PREVIEW_PAGES).map(|page| page_file_stem(cas_id, page)))
+}
+
+/// Gets back the cas id from the file stem of a thumbnail of any tier or PDF page
+fn cas_id_from_file_stem(file_stem: &str) -> &str {
+	file_stem
+		.strip_suffix("-detail")
+		.or_else(|| file_stem.strip_suffix("-preview"))
+		.or_else(|| {
+			file_stem.rsplit_once("-page").and_then(|(cas_id, page)| {
+				(!page.is_empty() && page.bytes().all(|byte| byte.is_ascii_digit()))
+					.then_some(cas_id)
+			})
+		})
+		.unwrap_or(file_stem)
+}
+
+pub fn get_indexed_thumbnail_path(
+	node: &Node,
+	cas_id: &str,
+	library_id: LibraryId,
+	tier: ThumbnailTier,
+) -> PathBuf {
+	get_thumbnail_path(node, cas_id, ThumbnailKind::Indexed(library_id), tier)
 }
 
 /// This does not check if a thumbnail exists, it just returns the path that it would exist at
-fn get_thumbnail_path(node: &Node, cas_id: &str, kind: ThumbnailKind) -> PathBuf {
+fn get_thumbnail_path(
+	node: &Node,
+	cas_id: &str,
+	kind: ThumbnailKind,
+	tier: ThumbnailTier,
+) -> PathBuf {
+	thumbnail_path_with_stem(node, cas_id, kind, &tier.file_stem(cas_id))
+}
+
+fn thumbnail_path_with_stem(
+	node: &Node,
+	cas_id: &str,
+	kind: ThumbnailKind,
+	file_stem: &str,
+) -> PathBuf {
 	let mut thumb_path = node.config.data_directory();
 
 	thumb_path.push(THUMBNAIL_CACHE_DIR_NAME);
@@ -75,33 +201,85 @@ fn get_thumbnail_path(node: &Node, cas_id: &str, kind: ThumbnailKind) -> PathBuf
 		}
 	}
 	thumb_path.push(get_shard_hex(cas_id));
-	thumb_path.push(cas_id);
+	thumb_path.push(file_stem);
 	thumb_path.set_extension(WEBP_EXTENSION);
 
 	thumb_path
 }
 
-pub fn get_indexed_thumb_key(cas_id: &str, library_id: LibraryId) -> Vec<String> {
-	get_thumb_key(cas_id, ThumbnailKind::Indexed(library_id))
+pub fn get_indexed_thumb_key(
+	cas_id: &str,
+	library_id: LibraryId,
+	tier: ThumbnailTier,
+) -> Vec<String> {
+	get_thumb_key(cas_id, ThumbnailKind::Indexed(library_id), tier)
 }
 
-pub fn get_ephemeral_thumb_key(cas_id: &str) -> Vec<String> {
-	get_thumb_key(cas_id, ThumbnailKind::Ephemeral)
+pub fn get_ephemeral_thumb_key(cas_id: &str, tier: ThumbnailTier) -> Vec<String> {
+	get_thumb_key(cas_id, ThumbnailKind::Ephemeral, tier)
 }
 
 // this is used to pass the relevant data to the frontend so it can request the thumbnail
 // it supports extending the shard hex to support deeper directory structures in the future
-fn get_thumb_key(cas_id: &str, kind: ThumbnailKind) -> Vec<String> {
+fn get_thumb_key(cas_id: &str, kind: ThumbnailKind, tier: ThumbnailTier) -> Vec<String> {
+	thumb_key_with_stem(cas_id, kind, tier.file_stem(cas_id))
+}
+
+fn thumb_key_with_stem(cas_id: &str, kind: ThumbnailKind, file_stem: String) -> Vec<String> {
 	vec![
 		match kind {
 			ThumbnailKind::Ephemeral => String::from(EPHEMERAL_DIR),
 			ThumbnailKind::Indexed(library_id) => library_id.to_string(),
 		},
 		get_shard_hex(cas_id).to_string(),
-		cas_id.to_string(),
+		file_stem,
 	]
 }
 
+/// Removes the thumbnails of every tier and PDF page of an indexed file, ignoring the ones never
+/// generated.
+pub async fn remove_indexed_thumbnails(
+	node: &Node,
+	cas_id: &str,
+	library_id: LibraryId,
+) -> Result<(), FileIOError> {
+	for file_stem in all_file_stems(cas_id) {
+		let thumb_path =
+			thumbnail_path_with_stem(node, cas_id, ThumbnailKind::Indexed(library_id), &file_stem);
+
+		match fs::remove_file(&thumb_path).await {
+			Ok(()) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(FileIOError::from((thumb_path, e))),
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the key of the thumbnail of `tier` for the file at `path`, generating it first if it
+/// doesn't exist yet, for the bigger tiers which aren't generated along with the grid ones.
+pub async fn request_thumbnail(
+	node: &Node,
+	path: impl AsRef<Path>,
+	extension: &str,
+	cas_id: &str,
+	kind: ThumbnailKind,
+	tier: ThumbnailTier,
+) -> Result<Vec<String>, ThumbnailerError> {
+	let output_path = get_thumbnail_path(node, cas_id, kind, tier);
+
+	match fs::metadata(&output_path).await {
+		Ok(_) => {}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			generate_thumbnail_at(path, extension, &output_path, tier).await?;
+		}
+		Err(e) => return Err(FileIOError::from((output_path, e)).into()),
+	}
+
+	Ok(get_thumb_key(cas_id, kind, tier))
+}
+
 #[cfg(feature = "ffmpeg")]
 pub(super) static THUMBNAILABLE_VIDEO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
 	ALL_VIDEO_EXTENSIONS
@@ -151,6 +329,8 @@ pub enum ThumbnailerError {
 	VersionManager(#[from] VersionManagerError<ThumbnailVersion>),
 	#[error("failed to encode webp")]
 	WebPEncoding { path: Box<Path>, reason: String },
+	#[error("failed to decode image: {0}")]
+	ImageDecoding(#[from] image::ImageError),
 	#[error("error while converting the image")]
 	SdImages {
 		path: Box<Path>,
@@ -163,6 +343,12 @@ pub enum ThumbnailerError {
 	FFmpeg(#[from] sd_ffmpeg::Error),
 	#[error("thumbnail generation timed out for {}", .0.display())]
 	TimedOut(Box<Path>),
+	#[error("failed to read remote file '{path}'")]
+	Remote {
+		path: String,
+		#[source]
+		source: opendal::Error,
+	},
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -191,11 +377,70 @@ pub const fn can_generate_thumbnail_for_image(image_extension: &ImageExtension)
 	matches!(
 		image_extension,
 		Jpg | Jpeg | Png | Webp | Gif | Svg | Heic | Heics | Heif | Heifs | Avif | Bmp | Ico
+	) || is_raw_image(image_extension)
+}
+
+/// Camera raw images, which get the JPEG preview they embed or their demosaiced sensor data as
+/// thumbnail. Canon's CR3 isn't supported yet, as the raw decoder can't read it.
+pub const fn is_raw_image(image_extension: &ImageExtension) -> bool {
+	use ImageExtension::*;
+
+	matches!(
+		image_extension,
+		Arw | Cr2 | Dng | Nef | Nrw | Orf | Pef | Raf | Rw2 | Srw
 	)
 }
 
+/// Whether the thumbnailer can generate a thumbnail for a file with this extension
+pub fn can_generate_thumbnail_for_extension(extension: &str) -> bool {
+	#[cfg(feature = "ffmpeg")]
+	if let Ok(video_extension) = VideoExtension::from_str(extension) {
+		return can_generate_thumbnail_for_video(&video_extension);
+	}
+
+	if let Ok(image_extension) = ImageExtension::from_str(extension) {
+		can_generate_thumbnail_for_image(&image_extension)
+	} else if let Ok(document_extension) = DocumentExtension::from_str(extension) {
+		can_generate_thumbnail_for_document(&document_extension)
+	} else {
+		false
+	}
+}
+
 pub const fn can_generate_thumbnail_for_document(document_extension: &DocumentExtension) -> bool {
 	use DocumentExtension::*;
 
 	matches!(document_extension, Pdf)
 }
+
+/// Returns the keys of the rendered pages of the PDF at `path`, up to [`PDF_PREVIEW_PAGES`] of them,
+/// rendering them first if they weren't yet.
+pub async fn request_pdf_pages(
+	node: &Node,
+	path: impl AsRef<Path>,
+	cas_id: &str,
+	kind: ThumbnailKind,
+) -> Result<Vec<Vec<String>>, ThumbnailerError> {
+	let page_paths = (0..PDF_PREVIEW_PAGES)
+		.map(|page| thumbnail_path_with_stem(node, cas_id, kind, &page_file_stem(cas_id, page)))
+		.collect::<Vec<_>>();
+
+	// Pages are always rendered together and in order, so the missing first one means none were
+	let mut page_count = 0;
+	for page_path in &page_paths {
+		match fs::metadata(page_path).await {
+			Ok(_) => page_count += 1,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+			Err(e) => return Err(FileIOError::from((page_path, e)).into()),
+		}
+	}
+
+	if page_count == 0 {
+		page_count = generate_pdf_pages_at(path, &page_paths).await?;
+	}
+
+	Ok((0..PDF_PREVIEW_PAGES)
+		.take(page_count)
+		.map(|page| thumb_key_with_stem(cas_id, kind, page_file_stem(cas_id, page)))
+		.collect())
+}
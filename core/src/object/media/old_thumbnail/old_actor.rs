@@ -1,13 +1,15 @@
 use crate::{
 	api::CoreEvent,
 	library::{Libraries, LibraryId, LibraryManagerEvent},
-	node::config::NodePreferences,
+	metrics::METRICS,
+	node::{background_policy::BackgroundPolicyState, config::NodePreferences},
 };
 
 use sd_prisma::prisma::{location, PrismaClient};
 use sd_utils::error::{FileIOError, NonUtf8PathError};
 
 use std::{
+	collections::HashSet,
 	path::{Path, PathBuf},
 	sync::Arc,
 };
@@ -24,9 +26,10 @@ use tracing::{error, trace};
 use uuid::Uuid;
 
 use super::{
+	cache::{cache_size, clear_cache},
 	directory::init_thumbnail_dir,
 	process::{generate_thumbnail, ThumbData},
-	state::RegisterReporter,
+	state::{RegisterReporter, ThumbnailsProgress},
 	worker::{old_worker, WorkerChannels},
 	BatchToProcess, ThumbnailKind, ThumbnailerError, ONE_SEC, THUMBNAIL_CACHE_DIR_NAME,
 };
@@ -64,10 +67,12 @@ pub struct OldThumbnailer {
 	thumbnails_directory: Arc<PathBuf>,
 	cas_ids_to_delete_tx: chan::Sender<(Vec<String>, ThumbnailKind)>,
 	thumbnails_to_generate_tx: chan::Sender<(BatchToProcess, ThumbnailKind)>,
+	thumbnails_accessed_tx: chan::Sender<PathBuf>,
 	progress_reporter_tx: chan::Sender<RegisterReporter>,
 	last_single_thumb_generated: Mutex<Instant>,
 	reporter: broadcast::Sender<CoreEvent>,
 	cancel_tx: chan::Sender<oneshot::Sender<()>>,
+	held_back_locations_tx: watch::Sender<HashSet<location::id::Type>>,
 }
 
 impl OldThumbnailer {
@@ -76,6 +81,7 @@ impl OldThumbnailer {
 		libraries_manager: Arc<Libraries>,
 		reporter: broadcast::Sender<CoreEvent>,
 		node_preferences_rx: watch::Receiver<NodePreferences>,
+		background_policy_rx: watch::Receiver<BackgroundPolicyState>,
 	) -> Self {
 		let data_dir = data_dir.as_ref();
 		let thumbnails_directory = Arc::new(
@@ -92,7 +98,9 @@ impl OldThumbnailer {
 		let (databases_tx, databases_rx) = chan::bounded(4);
 		let (thumbnails_to_generate_tx, ephemeral_thumbnails_to_generate_rx) = chan::unbounded();
 		let (cas_ids_to_delete_tx, cas_ids_to_delete_rx) = chan::bounded(16);
+		let (thumbnails_accessed_tx, thumbnails_accessed_rx) = chan::bounded(1024);
 		let (cancel_tx, cancel_rx) = chan::bounded(1);
+		let (held_back_locations_tx, held_back_locations_rx) = watch::channel(HashSet::new());
 
 		AVAILABLE_PARALLELISM
 			.set(std::thread::available_parallelism().map_or_else(
@@ -110,6 +118,8 @@ impl OldThumbnailer {
 			let thumbnails_directory = Arc::clone(&thumbnails_directory);
 			let reporter = reporter.clone();
 			let node_preferences = node_preferences_rx.clone();
+			let background_policy = background_policy_rx.clone();
+			let held_back_locations = held_back_locations_rx.clone();
 
 			async move {
 				while let Err(e) = spawn(old_worker(
@@ -117,6 +127,8 @@ impl OldThumbnailer {
 						.get()
 						.expect("BATCH_SIZE is set at thumbnailer new method"),
 					node_preferences.clone(),
+					background_policy.clone(),
+					held_back_locations.clone(),
 					reporter.clone(),
 					thumbnails_directory.clone(),
 					WorkerChannels {
@@ -124,6 +136,7 @@ impl OldThumbnailer {
 						databases_rx: databases_rx.clone(),
 						cas_ids_to_delete_rx: cas_ids_to_delete_rx.clone(),
 						thumbnails_to_generate_rx: ephemeral_thumbnails_to_generate_rx.clone(),
+						thumbnails_accessed_rx: thumbnails_accessed_rx.clone(),
 						cancel_rx: cancel_rx.clone(),
 					},
 				))
@@ -199,16 +212,20 @@ impl OldThumbnailer {
 			thumbnails_directory,
 			cas_ids_to_delete_tx,
 			thumbnails_to_generate_tx,
+			thumbnails_accessed_tx,
 			progress_reporter_tx: progress_management_tx,
 			last_single_thumb_generated: Mutex::new(Instant::now()),
 			reporter,
 			cancel_tx,
+			held_back_locations_tx,
 		}
 	}
 
 	#[inline]
 	async fn new_batch(&self, batch: BatchToProcess, kind: ThumbnailKind) {
 		if !batch.batch.is_empty() {
+			METRICS.record_thumbnails_queued(batch.batch.len() as u64);
+
 			self.thumbnails_to_generate_tx
 				.send((batch, kind))
 				.await
@@ -246,7 +263,7 @@ impl OldThumbnailer {
 	pub async fn register_reporter(
 		&self,
 		location_id: location::id::Type,
-		progress_tx: chan::Sender<(u32, u32)>,
+		progress_tx: chan::Sender<ThumbnailsProgress>,
 	) {
 		self.progress_reporter_tx
 			.send((location_id, progress_tx))
@@ -254,6 +271,20 @@ impl OldThumbnailer {
 			.expect("critical thumbnailer error: failed to send register reporter fn");
 	}
 
+	/// Holds back the thumbnails of a location until the returned guard is dropped, like while
+	/// the job waiting for them is paused.
+	pub fn hold_back_location(&self, location_id: location::id::Type) -> HeldBackLocation<'_> {
+		self.held_back_locations_tx
+			.send_modify(|held_back_locations| {
+				held_back_locations.insert(location_id);
+			});
+
+		HeldBackLocation {
+			held_back_locations_tx: &self.held_back_locations_tx,
+			location_id,
+		}
+	}
+
 	#[inline]
 	async fn remove_cas_ids(&self, cas_ids: Vec<String>, kind: ThumbnailKind) {
 		self.cas_ids_to_delete_tx
@@ -285,6 +316,24 @@ impl OldThumbnailer {
 			.expect("critical thumbnailer error: failed to receive shutdown signal response");
 	}
 
+	/// Records that a thumbnail was served, as the least recently accessed ones are evicted first
+	/// when the cache goes over its budget
+	pub fn record_access(&self, thumbnail_path: PathBuf) {
+		// Dropped if the worker is lagging behind, it only orders the evictions
+		self.thumbnails_accessed_tx.try_send(thumbnail_path).ok();
+	}
+
+	/// Total size in bytes and count of the thumbnails stored on disk
+	pub async fn cache_size(&self) -> Result<(u64, usize), ThumbnailerError> {
+		cache_size(&self.thumbnails_directory).await
+	}
+
+	/// Removes every thumbnail, the ones of indexed files are generated again when their
+	/// locations are scanned
+	pub async fn clear_cache(&self) -> Result<(), ThumbnailerError> {
+		clear_cache(&self.thumbnails_directory).await
+	}
+
 	/// WARNING!!!! DON'T USE THIS METHOD IN A LOOP!!!!!!!!!!!!! It will be pretty slow on purpose!
 	pub async fn generate_single_indexed_thumbnail(
 		&self,
@@ -292,9 +341,16 @@ impl OldThumbnailer {
 		cas_id: String,
 		path: impl AsRef<Path>,
 		library_id: LibraryId,
+		animated_videos: bool,
 	) -> Result<(), ThumbnailerError> {
-		self.generate_single_thumbnail(extension, cas_id, path, ThumbnailKind::Indexed(library_id))
-			.await
+		self.generate_single_thumbnail(
+			extension,
+			cas_id,
+			path,
+			ThumbnailKind::Indexed(library_id),
+			animated_videos,
+		)
+		.await
 	}
 
 	async fn generate_single_thumbnail(
@@ -303,6 +359,7 @@ impl OldThumbnailer {
 		cas_id: String,
 		path: impl AsRef<Path>,
 		kind: ThumbnailKind,
+		animated_videos: bool,
 	) -> Result<(), ThumbnailerError> {
 		let mut last_single_thumb_generated_guard = self.last_single_thumb_generated.lock().await;
 
@@ -321,6 +378,7 @@ impl OldThumbnailer {
 				path,
 				in_background: false,
 				should_regenerate: false,
+				animated_videos,
 				kind,
 			},
 			self.reporter.clone(),
@@ -333,3 +391,20 @@ impl OldThumbnailer {
 		res
 	}
 }
+
+/// Releases the thumbnails of a location held back with [`OldThumbnailer::hold_back_location`]
+/// when dropped.
+#[must_use]
+pub struct HeldBackLocation<'thumbnailer> {
+	held_back_locations_tx: &'thumbnailer watch::Sender<HashSet<location::id::Type>>,
+	location_id: location::id::Type,
+}
+
+impl Drop for HeldBackLocation<'_> {
+	fn drop(&mut self) {
+		self.held_back_locations_tx
+			.send_modify(|held_back_locations| {
+				held_back_locations.remove(&self.location_id);
+			});
+	}
+}
@@ -1,15 +1,25 @@
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
+/// Size of the thumbnails cache on disk, in MiB, before the least recently accessed are evicted
+const DEFAULT_CACHE_BUDGET_MB: u32 = 4096;
+
+const fn default_cache_budget_mb() -> Option<u32> {
+	Some(DEFAULT_CACHE_BUDGET_MB)
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Type)]
 pub struct ThumbnailerPreferences {
 	background_processing_percentage: u8, // 0-100
+	#[serde(default = "default_cache_budget_mb")]
+	cache_budget_mb: Option<u32>, // never evicting if `None`
 }
 
 impl Default for ThumbnailerPreferences {
 	fn default() -> Self {
 		Self {
 			background_processing_percentage: 50, // 50% of CPU cores available
+			cache_budget_mb: default_cache_budget_mb(),
 		}
 	}
 }
@@ -31,4 +41,22 @@ impl ThumbnailerPreferences {
 
 		self
 	}
+
+	pub fn cache_budget_mb(&self) -> Option<u32> {
+		self.cache_budget_mb
+	}
+
+	pub fn set_cache_budget_mb(&mut self, cache_budget_mb: Option<u32>) -> &mut Self {
+		self.cache_budget_mb = cache_budget_mb;
+
+		self
+	}
+}
+
+/// How the thumbnails of a library are generated, stored in the library config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailSettings {
+	/// Videos get a short looping thumbnail of frames sampled across them, instead of a single frame
+	pub animated_videos: bool,
 }
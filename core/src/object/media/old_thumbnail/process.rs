@@ -1,7 +1,7 @@
-use crate::api::CoreEvent;
+use crate::{api::CoreEvent, metrics::METRICS};
 
 use sd_file_ext::extensions::{DocumentExtension, ImageExtension};
-use sd_images::{format_image, scale_dimensions, ConvertibleExtension};
+use sd_images::{format_image, format_pdf_pages, scale_dimensions, ConvertibleExtension};
 use sd_media_metadata::image::Orientation;
 use sd_prisma::prisma::location;
 use sd_utils::error::FileIOError;
@@ -31,11 +31,16 @@ use webp::Encoder;
 
 use super::{
 	can_generate_thumbnail_for_document, can_generate_thumbnail_for_image, get_thumb_key,
-	preferences::ThumbnailerPreferences, shard::get_shard_hex, ThumbnailKind, ThumbnailerError,
-	EPHEMERAL_DIR, TARGET_PX, TARGET_QUALITY, THIRTY_SECS, WEBP_EXTENSION,
+	preferences::ThumbnailerPreferences, shard::get_shard_hex, ThumbnailKind, ThumbnailTier,
+	ThumbnailerError, EPHEMERAL_DIR, THIRTY_SECS, WEBP_EXTENSION,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "ffmpeg")]
+use super::{
+	ANIMATED_THUMBNAIL_FRAMES, ANIMATED_THUMBNAIL_FRAME_DURATION, MAX_ANIMATED_THUMBNAIL_SIZE,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateThumbnailArgs {
 	pub extension: String,
 	pub cas_id: String,
@@ -58,6 +63,12 @@ pub struct BatchToProcess {
 	pub(super) should_regenerate: bool,
 	pub(super) in_background: bool,
 	pub(super) location_id: Option<location::id::Type>,
+	#[serde(default)]
+	pub(super) max_parallelism: Option<usize>,
+	#[serde(default)]
+	pub(super) continues: bool,
+	#[serde(default)]
+	pub(super) animated_videos: bool,
 }
 
 impl BatchToProcess {
@@ -71,14 +82,36 @@ impl BatchToProcess {
 			should_regenerate,
 			in_background,
 			location_id: None,
+			max_parallelism: None,
+			continues: false,
+			animated_videos: false,
 		}
 	}
+
+	/// Queues this foreground batch after the other foreground ones instead of interrupting them,
+	/// for the batches following the first one of a view which is still being listed
+	pub fn continuing(mut self) -> Self {
+		self.continues = true;
+		self
+	}
+
+	/// Caps how many thumbnails of this batch are generated at the same time
+	pub fn with_max_parallelism(mut self, max_parallelism: Option<usize>) -> Self {
+		self.max_parallelism = max_parallelism;
+		self
+	}
+
+	/// Generates animated thumbnails for the videos of this batch, following the library settings
+	pub fn with_animated_videos(mut self, animated_videos: bool) -> Self {
+		self.animated_videos = animated_videos;
+		self
+	}
 }
 
 pub(super) struct ProcessorControlChannels {
 	pub stop_rx: chan::Receiver<oneshot::Sender<()>>,
 	pub done_tx: oneshot::Sender<()>,
-	pub batch_report_progress_tx: chan::Sender<(location::id::Type, u32)>,
+	pub batch_report_progress_tx: chan::Sender<(location::id::Type, PathBuf)>,
 }
 
 pub(super) async fn batch_processor(
@@ -89,6 +122,9 @@ pub(super) async fn batch_processor(
 			should_regenerate,
 			in_background,
 			location_id,
+			max_parallelism,
+			animated_videos,
+			..
 		},
 		kind,
 	): (BatchToProcess, ThumbnailKind),
@@ -114,6 +150,11 @@ pub(super) async fn batch_processor(
 		)
 	};
 
+	// Jobs can further restrict the parallelism through their resource limits
+	let in_parallel_count = max_parallelism.map_or(in_parallel_count, |max_parallelism| {
+		in_parallel_count.min(max_parallelism).max(1)
+	});
+
 	debug!(
 		"Processing thumbnails batch of kind {kind:?} with size {} in {}, \
 		at most {in_parallel_count} thumbnails at a time",
@@ -178,6 +219,7 @@ pub(super) async fn batch_processor(
 									path: &path,
 									in_background,
 									should_regenerate,
+									animated_videos,
 									kind,
 								},
 								reporter,
@@ -199,11 +241,13 @@ pub(super) async fn batch_processor(
 						})
 						.await
 						.unwrap_or_else(|_| {
-							Err(ThumbnailerError::TimedOut(path.into_boxed_path()))
+							Err(ThumbnailerError::TimedOut(path.clone().into_boxed_path()))
 						});
 
+						METRICS.record_thumbnail_processed(res.is_ok());
+
 						if let Some(location_id) = location_id {
-							report_progress_tx.send((location_id, 1)).await.ok();
+							report_progress_tx.send((location_id, path)).await.ok();
 						}
 
 						drop(permit);
@@ -267,6 +311,9 @@ pub(super) async fn batch_processor(
 						should_regenerate,
 						in_background: true, // Leftovers should always be in background
 						location_id,
+						max_parallelism,
+						continues: false,
+						animated_videos,
 					},
 					kind,
 				))
@@ -310,6 +357,7 @@ pub(super) struct ThumbData<'ext, P: AsRef<Path>> {
 	pub path: P,
 	pub in_background: bool,
 	pub should_regenerate: bool,
+	pub animated_videos: bool,
 	pub kind: ThumbnailKind,
 }
 
@@ -321,6 +369,7 @@ pub(super) async fn generate_thumbnail(
 		path,
 		in_background,
 		should_regenerate,
+		animated_videos,
 		kind,
 	}: ThumbData<'_, impl AsRef<Path>>,
 	reporter: broadcast::Sender<CoreEvent>,
@@ -352,13 +401,46 @@ pub(super) async fn generate_thumbnail(
 		return Ok(cas_id);
 	}
 
+	if !(animated_videos && generate_animated_video_thumbnail(path, extension, &output_path).await)
+	{
+		generate_thumbnail_at(path, extension, &output_path, ThumbnailTier::Grid).await?;
+	}
+
+	if !in_background {
+		trace!("Emitting new thumbnail event");
+		if reporter
+			.send(CoreEvent::NewThumbnail {
+				thumb_key: get_thumb_key(&cas_id, kind, ThumbnailTier::Grid),
+			})
+			.is_err()
+		{
+			warn!("Error sending event to Node's event bus");
+		}
+	}
+
+	trace!("Generated thumbnail for {}", path.display());
+
+	Ok(cas_id)
+}
+
+/// Generates a thumbnail of `tier` for the file at `path` and writes it to `output_path`, doing
+/// nothing for extensions we can't generate thumbnails for.
+pub async fn generate_thumbnail_at(
+	path: impl AsRef<Path>,
+	extension: &str,
+	output_path: impl AsRef<Path>,
+	tier: ThumbnailTier,
+) -> Result<(), ThumbnailerError> {
+	let path = path.as_ref();
+	let output_path = output_path.as_ref();
+
 	if let Ok(extension) = ImageExtension::from_str(extension) {
 		if can_generate_thumbnail_for_image(&extension) {
-			generate_image_thumbnail(&path, &output_path).await?;
+			generate_image_thumbnail(path, output_path, tier).await?;
 		}
 	} else if let Ok(extension) = DocumentExtension::from_str(extension) {
 		if can_generate_thumbnail_for_document(&extension) {
-			generate_image_thumbnail(&path, &output_path).await?;
+			generate_image_thumbnail(path, output_path, tier).await?;
 		}
 	}
 
@@ -369,80 +451,145 @@ pub(super) async fn generate_thumbnail(
 
 		if let Ok(extension) = VideoExtension::from_str(extension) {
 			if can_generate_thumbnail_for_video(&extension) {
-				generate_video_thumbnail(&path, &output_path).await?;
+				generate_video_thumbnail(path, output_path, tier).await?;
 			}
 		}
 	}
 
-	if !in_background {
-		trace!("Emitting new thumbnail event");
-		if reporter
-			.send(CoreEvent::NewThumbnail {
-				thumb_key: get_thumb_key(&cas_id, kind),
-			})
-			.is_err()
-		{
-			warn!("Error sending event to Node's event bus");
-		}
-	}
-
-	trace!("Generated thumbnail for {}", path.display());
-
-	Ok(cas_id)
+	Ok(())
 }
 
 async fn generate_image_thumbnail(
 	file_path: impl AsRef<Path>,
 	output_path: impl AsRef<Path>,
+	tier: ThumbnailTier,
 ) -> Result<(), ThumbnailerError> {
 	let file_path = file_path.as_ref().to_path_buf();
 
 	let webp = spawn_blocking(move || -> Result<_, ThumbnailerError> {
-		let mut img = format_image(&file_path).map_err(|e| ThumbnailerError::SdImages {
+		let img = format_image(&file_path).map_err(|e| ThumbnailerError::SdImages {
 			path: file_path.clone().into_boxed_path(),
 			error: e,
 		})?;
 
-		let (w, h) = img.dimensions();
-		let (w_scaled, h_scaled) = scale_dimensions(w as f32, h as f32, TARGET_PX);
-
-		// Optionally, resize the existing photo and convert back into DynamicImage
-		if w != w_scaled && h != h_scaled {
-			img = DynamicImage::ImageRgba8(imageops::resize(
-				&img,
-				w_scaled,
-				h_scaled,
-				imageops::FilterType::Triangle,
-			));
-		}
+		let mut img = scale_image(img, tier);
 
 		// this corrects the rotation/flip of the image based on the *available* exif data
-		// not all images have exif data, so we don't error. we also don't rotate HEIF as that's against the spec
+		// not all images have exif data, so we don't error. we also don't rotate HEIF as that's against the spec,
+		// nor raw images, which are already rotated when decoded
 		if let Some(orientation) = Orientation::from_path(&file_path) {
 			if ConvertibleExtension::try_from(file_path.as_ref())
-				.expect("we already checked if the image was convertible")
-				.should_rotate()
+				.is_ok_and(ConvertibleExtension::should_rotate)
 			{
 				img = orientation.correct_thumbnail(img);
 			}
 		}
 
-		// Create the WebP encoder for the above image
-		let encoder =
-			Encoder::from_image(&img).map_err(|reason| ThumbnailerError::WebPEncoding {
-				path: file_path.into_boxed_path(),
-				reason: reason.to_string(),
-			})?;
-
-		// Type WebPMemory is !Send, which makes the Future in this function !Send,
-		// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
-		// which implies on a unwanted clone...
-		Ok(encoder.encode(TARGET_QUALITY).deref().to_owned())
+		encode_webp(&img, &file_path, tier)
 	})
 	.await??;
 
+	write_thumbnail(output_path.as_ref(), &webp).await
+}
+
+/// Generates a thumbnail of `tier` from an already encoded image, in any format the `image` crate
+/// decodes, and writes it to `output_path`.
+pub async fn generate_thumbnail_from_bytes(
+	image: Vec<u8>,
+	output_path: impl AsRef<Path>,
+	tier: ThumbnailTier,
+) -> Result<(), ThumbnailerError> {
 	let output_path = output_path.as_ref();
 
+	let webp = spawn_blocking({
+		let output_path = output_path.to_path_buf();
+		move || {
+			encode_webp(
+				&scale_image(image::load_from_memory(&image)?, tier),
+				&output_path,
+				tier,
+			)
+		}
+	})
+	.await??;
+
+	write_thumbnail(output_path, &webp).await
+}
+
+/// Renders the first pages of the PDF at `path` as [`ThumbnailTier::Detail`] thumbnails, one for
+/// each of the `output_paths`, returning how many pages were rendered as the PDF may have fewer.
+pub(super) async fn generate_pdf_pages_at(
+	path: impl AsRef<Path>,
+	output_paths: &[PathBuf],
+) -> Result<usize, ThumbnailerError> {
+	let path = path.as_ref().to_path_buf();
+	let max_pages = u16::try_from(output_paths.len()).unwrap_or(u16::MAX);
+
+	let pages = spawn_blocking(move || -> Result<_, ThumbnailerError> {
+		format_pdf_pages(&path, max_pages)
+			.map_err(|e| ThumbnailerError::SdImages {
+				path: path.clone().into_boxed_path(),
+				error: e,
+			})?
+			.into_iter()
+			.map(|page| {
+				encode_webp(
+					&scale_image(page, ThumbnailTier::Detail),
+					&path,
+					ThumbnailTier::Detail,
+				)
+			})
+			.collect::<Result<Vec<_>, _>>()
+	})
+	.await??;
+
+	for (webp, output_path) in pages.iter().zip(output_paths) {
+		write_thumbnail(output_path, webp).await?;
+	}
+
+	Ok(pages.len())
+}
+
+fn scale_image(img: DynamicImage, tier: ThumbnailTier) -> DynamicImage {
+	let (w, h) = img.dimensions();
+	let (w_scaled, h_scaled) = scale_dimensions(w as f32, h as f32, tier.target_px());
+
+	// Optionally, resize the existing photo and convert back into DynamicImage
+	if w != w_scaled && h != h_scaled {
+		#[cfg(feature = "gpu-thumbnails")]
+		if let Some(resized) = super::gpu::resize(&img, w_scaled, h_scaled) {
+			return DynamicImage::ImageRgba8(resized);
+		}
+
+		DynamicImage::ImageRgba8(imageops::resize(
+			&img,
+			w_scaled,
+			h_scaled,
+			imageops::FilterType::Triangle,
+		))
+	} else {
+		img
+	}
+}
+
+fn encode_webp(
+	img: &DynamicImage,
+	path: &Path,
+	tier: ThumbnailTier,
+) -> Result<Vec<u8>, ThumbnailerError> {
+	// Create the WebP encoder for the above image
+	let encoder = Encoder::from_image(img).map_err(|reason| ThumbnailerError::WebPEncoding {
+		path: path.into(),
+		reason: reason.to_string(),
+	})?;
+
+	// Type WebPMemory is !Send, which makes the Future in this function !Send,
+	// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
+	// which implies on a unwanted clone...
+	Ok(encoder.encode(tier.target_quality()).deref().to_owned())
+}
+
+async fn write_thumbnail(output_path: &Path, webp: &[u8]) -> Result<(), ThumbnailerError> {
 	if let Some(shard_dir) = output_path.parent() {
 		fs::create_dir_all(shard_dir)
 			.await
@@ -454,7 +601,7 @@ async fn generate_image_thumbnail(
 		);
 	}
 
-	fs::write(output_path, &webp)
+	fs::write(output_path, webp)
 		.await
 		.map_err(|e| FileIOError::from((output_path, e)))
 		.map_err(Into::into)
@@ -464,10 +611,89 @@ async fn generate_image_thumbnail(
 async fn generate_video_thumbnail(
 	file_path: impl AsRef<Path>,
 	output_path: impl AsRef<Path>,
+	tier: ThumbnailTier,
 ) -> Result<(), ThumbnailerError> {
 	use sd_ffmpeg::to_thumbnail;
 
-	to_thumbnail(file_path, output_path, 256, TARGET_QUALITY)
-		.await
-		.map_err(Into::into)
+	to_thumbnail(
+		file_path,
+		output_path,
+		tier.video_size(),
+		tier.target_quality(),
+	)
+	.await
+	.map_err(Into::into)
+}
+
+/// Tries to generate an animated grid thumbnail, returning whether it was written. Anything but
+/// thumbnailable videos, videos too short to sample frames from and animations bigger than
+/// [`MAX_ANIMATED_THUMBNAIL_SIZE`] get a static thumbnail instead.
+#[cfg(feature = "ffmpeg")]
+async fn generate_animated_video_thumbnail(
+	path: &Path,
+	extension: &str,
+	output_path: &Path,
+) -> bool {
+	use crate::object::media::old_thumbnail::can_generate_thumbnail_for_video;
+	use sd_ffmpeg::ThumbnailerBuilder;
+	use sd_file_ext::extensions::VideoExtension;
+
+	if !VideoExtension::from_str(extension)
+		.is_ok_and(|extension| can_generate_thumbnail_for_video(&extension))
+	{
+		return false;
+	}
+
+	let tier = ThumbnailTier::Grid;
+
+	let res = async {
+		ThumbnailerBuilder::new()
+			.with_film_strip(false)
+			.size(tier.video_size())
+			.quality(tier.target_quality())?
+			.build()
+			.process_to_animated_webp_bytes(
+				path,
+				ANIMATED_THUMBNAIL_FRAMES,
+				ANIMATED_THUMBNAIL_FRAME_DURATION,
+			)
+			.await
+	}
+	.await;
+
+	match res {
+		Ok(webp) if webp.len() <= MAX_ANIMATED_THUMBNAIL_SIZE => {
+			match write_thumbnail(output_path, &webp).await {
+				Ok(()) => true,
+				Err(e) => {
+					error!("Failed to write animated thumbnail: {e:#?}");
+					false
+				}
+			}
+		}
+		Ok(webp) => {
+			debug!(
+				"Animated thumbnail for {} is too big ({} bytes), generating a static one",
+				path.display(),
+				webp.len()
+			);
+			false
+		}
+		Err(e) => {
+			debug!(
+				"Failed to generate animated thumbnail for {}, generating a static one: {e:#?}",
+				path.display()
+			);
+			false
+		}
+	}
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+async fn generate_animated_video_thumbnail(
+	_path: &Path,
+	_extension: &str,
+	_output_path: &Path,
+) -> bool {
+	false
 }
@@ -0,0 +1,161 @@
+//! Thumbnails of the files of remote services listed without indexing them, like the ones of a S3
+//! bucket, which are read through OpenDAL as they can't be opened from this device.
+
+use crate::{api::CoreEvent, Node};
+
+use sd_indexer::NonIndexedPathItem;
+use sd_utils::error::FileIOError;
+
+use std::{env, future::ready, sync::Arc};
+
+use futures::StreamExt;
+use opendal::Operator;
+use tempfile::Builder;
+use tokio::{fs, spawn, sync::mpsc};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, trace, warn};
+
+use super::{
+	generate_thumbnail_at, get_thumb_key, get_thumbnail_path, ThumbnailKind, ThumbnailTier,
+	ThumbnailerError,
+};
+
+/// Remote files downloaded at once, as each thumbnail needs its whole file
+const REMOTE_CONCURRENCY: usize = 4;
+/// Bigger files are left without a thumbnail instead of being downloaded
+const MAX_REMOTE_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// A file of a remote service to generate the thumbnail of.
+#[derive(Debug)]
+pub struct RemoteFile {
+	pub path: String,
+	pub extension: String,
+	pub cas_id: String,
+}
+
+/// Stands for the cas id of a remote file, as its contents can't be hashed without downloading
+/// it. Its etag, modification date and size are hashed along with its path, so a file changed on
+/// the server gets a new thumbnail.
+pub fn remote_cas_id(operator: &Operator, item: &NonIndexedPathItem) -> String {
+	let info = operator.info();
+
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(
+		format!(
+			"{}://{}{}{}",
+			info.scheme(),
+			info.name(),
+			info.root(),
+			item.path
+		)
+		.as_bytes(),
+	);
+	hasher.update(item.etag.as_deref().unwrap_or_default().as_bytes());
+	hasher.update(&item.date_modified.timestamp_millis().to_le_bytes());
+	hasher.update(&item.size_in_bytes_bytes);
+
+	hasher.finalize().to_hex()[..16].to_string()
+}
+
+/// Generates the ephemeral thumbnails of the remote files sent to it, downloading up to
+/// [`REMOTE_CONCURRENCY`] of them at once, until the sender is dropped.
+///
+/// The clients are told about each thumbnail as soon as it's ready, like with the local ones.
+pub fn remote_thumbnailer(
+	node: Arc<Node>,
+	operator: Operator,
+) -> mpsc::UnboundedSender<RemoteFile> {
+	let (files_tx, files_rx) = mpsc::unbounded_channel();
+
+	spawn(async move {
+		UnboundedReceiverStream::new(files_rx)
+			.map(|file| {
+				let (node, operator) = (&node, &operator);
+
+				async move {
+					match generate_remote_thumbnail(node, operator, &file).await {
+						Ok(true) => {
+							if node
+								.event_bus
+								.0
+								.send(CoreEvent::NewThumbnail {
+									thumb_key: get_thumb_key(
+										&file.cas_id,
+										ThumbnailKind::Ephemeral,
+										ThumbnailTier::Grid,
+									),
+								})
+								.is_err()
+							{
+								warn!("Error sending event to Node's event bus");
+							}
+						}
+						Ok(false) => {}
+						Err(e) => error!(
+							"Failed to generate the thumbnail of remote file '{}': {e:#?}",
+							file.path
+						),
+					}
+				}
+			})
+			.buffer_unordered(REMOTE_CONCURRENCY)
+			.for_each(|()| ready(()))
+			.await
+	});
+
+	files_tx
+}
+
+/// Returns `false` if no thumbnail was generated, as it already exists or the file is too big to
+/// be downloaded.
+async fn generate_remote_thumbnail(
+	node: &Node,
+	operator: &Operator,
+	RemoteFile {
+		path,
+		extension,
+		cas_id,
+	}: &RemoteFile,
+) -> Result<bool, ThumbnailerError> {
+	let output_path =
+		get_thumbnail_path(node, cas_id, ThumbnailKind::Ephemeral, ThumbnailTier::Grid);
+
+	if fs::metadata(&output_path).await.is_ok() {
+		trace!("Skipping thumbnail generation for remote file '{path}' because it already exists");
+		return Ok(false);
+	}
+
+	let remote_error = |source| ThumbnailerError::Remote {
+		path: path.clone(),
+		source,
+	};
+
+	let size = operator
+		.stat(path)
+		.await
+		.map_err(remote_error)?
+		.content_length();
+
+	if size > MAX_REMOTE_FILE_SIZE {
+		trace!("Skipping thumbnail generation for remote file '{path}' because it's too big");
+		return Ok(false);
+	}
+
+	let contents = operator.read(path).await.map_err(remote_error)?;
+
+	// The thumbnails are generated from files on disk, whose extension tells their format apart
+	let file = Builder::new()
+		.suffix(&format!(".{extension}"))
+		.tempfile()
+		.map_err(|e| {
+			FileIOError::from((env::temp_dir(), e, "Failed to create a temporary file"))
+		})?;
+
+	fs::write(file.path(), contents)
+		.await
+		.map_err(|e| FileIOError::from((file.path(), e)))?;
+
+	generate_thumbnail_at(file.path(), extension, &output_path, ThumbnailTier::Grid).await?;
+
+	Ok(true)
+}
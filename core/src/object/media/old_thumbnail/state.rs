@@ -1,4 +1,4 @@
-use crate::library::LibraryId;
+use crate::{library::LibraryId, metrics::METRICS};
 
 use sd_prisma::prisma::location;
 use sd_utils::error::FileIOError;
@@ -6,7 +6,7 @@ use sd_utils::error::FileIOError;
 use std::{
 	collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
 	ffi::OsString,
-	path::Path,
+	path::{Path, PathBuf},
 };
 
 use async_channel as chan;
@@ -16,8 +16,8 @@ use tokio::{fs, io};
 use tracing::{error, info, trace};
 
 use super::{
-	get_shard_hex, old_actor::ActorError, BatchToProcess, ThumbnailKind, EPHEMERAL_DIR,
-	SAVE_STATE_FILE,
+	all_file_stems, get_shard_hex, old_actor::ActorError, BatchToProcess, ThumbnailKind,
+	EPHEMERAL_DIR, SAVE_STATE_FILE, WEBP_EXTENSION,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -71,6 +71,17 @@ impl OldThumbsProcessingSaveState {
 						+ this.ephemeral_leftovers_queue.len()
 				);
 
+				// Thumbnails left over from the last run are back in the backlog
+				METRICS.record_thumbnails_queued(
+					this.queue
+						.iter()
+						.map(|(batch, _)| batch)
+						.chain(this.indexed_leftovers_queue.iter().map(|(batch, _)| batch))
+						.chain(this.ephemeral_leftovers_queue.iter())
+						.map(|batch| batch.batch.len() as u64)
+						.sum(),
+				);
+
 				this
 			}
 			Err(e) if e.kind() == io::ErrorKind::NotFound => {
@@ -126,9 +137,17 @@ pub(super) async fn remove_by_cas_ids(
 
 	cas_ids
 		.into_iter()
-		.map(|cas_id| {
-			let thumbnail_path = base_dir.join(format!("{}/{cas_id}.webp", get_shard_hex(&cas_id)));
-
+		.flat_map(|cas_id| {
+			all_file_stems(&cas_id)
+				.map(|file_stem| {
+					base_dir.join(format!(
+						"{}/{file_stem}.{WEBP_EXTENSION}",
+						get_shard_hex(&cas_id)
+					))
+				})
+				.collect::<Vec<_>>()
+		})
+		.map(|thumbnail_path| {
 			trace!("Removing thumbnail: {}", thumbnail_path.display());
 
 			async move {
@@ -146,7 +165,10 @@ pub(super) async fn remove_by_cas_ids(
 	Ok(())
 }
 
-pub(super) type RegisterReporter = (location::id::Type, chan::Sender<(u32, u32)>);
+/// Thumbnails generated and to generate on a location, with the file of the last one generated
+pub type ThumbnailsProgress = (u32, u32, Option<PathBuf>);
+
+pub(super) type RegisterReporter = (location::id::Type, chan::Sender<ThumbnailsProgress>);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) struct BookKeeper {
@@ -154,7 +176,7 @@ pub(super) struct BookKeeper {
 
 	// We can't save reporter function or a channel to disk, the job must ask again to be registered
 	#[serde(skip, default)]
-	reporter_by_location: HashMap<location::id::Type, chan::Sender<(u32, u32)>>,
+	reporter_by_location: HashMap<location::id::Type, chan::Sender<ThumbnailsProgress>>,
 }
 impl Default for BookKeeper {
 	fn default() -> Self {
@@ -183,7 +205,7 @@ impl BookKeeper {
 		};
 
 		if let Some(progress_tx) = self.reporter_by_location.get(&location_id) {
-			if progress_tx.send((in_progress, total)).await.is_err() {
+			if progress_tx.send((in_progress, total, None)).await.is_err() {
 				error!(
 					"Failed to send progress update to reporter on location <id='{location_id}'>"
 				);
@@ -194,18 +216,22 @@ impl BookKeeper {
 	pub(super) fn register_reporter(
 		&mut self,
 		location_id: location::id::Type,
-		reporter_tx: chan::Sender<(u32, u32)>,
+		reporter_tx: chan::Sender<ThumbnailsProgress>,
 	) {
 		self.reporter_by_location.insert(location_id, reporter_tx);
 	}
 
-	pub(super) async fn add_progress(&mut self, location_id: location::id::Type, progress: u32) {
+	pub(super) async fn add_progress(&mut self, location_id: location::id::Type, path: PathBuf) {
 		if let Some((current_progress, total)) = self.work_progress.get_mut(&location_id) {
-			*current_progress += progress;
+			*current_progress += 1;
 
 			if *current_progress == *total {
 				if let Some(progress_tx) = self.reporter_by_location.remove(&location_id) {
-					if progress_tx.send((*current_progress, *total)).await.is_err() {
+					if progress_tx
+						.send((*current_progress, *total, Some(path)))
+						.await
+						.is_err()
+					{
 						error!(
 							"Failed to send progress update to reporter on location <id='{location_id}'>"
 						);
@@ -214,7 +240,11 @@ impl BookKeeper {
 
 				self.work_progress.remove(&location_id);
 			} else if let Some(progress_tx) = self.reporter_by_location.get(&location_id) {
-				if progress_tx.send((*current_progress, *total)).await.is_err() {
+				if progress_tx
+					.send((*current_progress, *total, Some(path)))
+					.await
+					.is_err()
+				{
 					error!(
 						"Failed to send progress update to reporter on location <id='{location_id}'>"
 					);
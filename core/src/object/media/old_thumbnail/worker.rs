@@ -1,8 +1,22 @@
-use crate::{api::CoreEvent, node::config::NodePreferences};
+use crate::{
+	api::CoreEvent,
+	node::{
+		background_policy::{BackgroundPolicyState, Subsystem, WorkMode},
+		config::NodePreferences,
+	},
+};
 
 use sd_prisma::prisma::location;
 
-use std::{collections::HashMap, ffi::OsString, path::PathBuf, pin::pin, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	ffi::OsString,
+	mem,
+	path::PathBuf,
+	pin::pin,
+	sync::Arc,
+	time::SystemTime,
+};
 
 use async_channel as chan;
 use futures_concurrency::stream::Merge;
@@ -18,6 +32,7 @@ use tokio_stream::{
 use tracing::{debug, error, trace};
 
 use super::{
+	cache::process_cache_eviction,
 	clean_up::{process_ephemeral_clean_up, process_indexed_clean_up},
 	old_actor::DatabaseMessage,
 	preferences::ThumbnailerPreferences,
@@ -26,18 +41,24 @@ use super::{
 	BatchToProcess, ThumbnailKind, HALF_HOUR, ONE_SEC, THIRTY_SECS,
 };
 
+/// Share of the CPU cores used for background batches while the background policy throttles us
+const THROTTLED_PROCESSING_PERCENTAGE: u8 = 10;
+
 #[derive(Debug, Clone)]
 pub(super) struct WorkerChannels {
 	pub(super) progress_management_rx: chan::Receiver<RegisterReporter>,
 	pub(super) databases_rx: chan::Receiver<DatabaseMessage>,
 	pub(super) cas_ids_to_delete_rx: chan::Receiver<(Vec<String>, ThumbnailKind)>,
 	pub(super) thumbnails_to_generate_rx: chan::Receiver<(BatchToProcess, ThumbnailKind)>,
+	pub(super) thumbnails_accessed_rx: chan::Receiver<PathBuf>,
 	pub(super) cancel_rx: chan::Receiver<oneshot::Sender<()>>,
 }
 
 pub(super) async fn old_worker(
 	available_parallelism: usize,
 	node_preferences_rx: watch::Receiver<NodePreferences>,
+	background_policy_rx: watch::Receiver<BackgroundPolicyState>,
+	held_back_locations_rx: watch::Receiver<HashSet<location::id::Type>>,
 	reporter: broadcast::Sender<CoreEvent>,
 	thumbnails_directory: Arc<PathBuf>,
 	WorkerChannels {
@@ -45,6 +66,7 @@ pub(super) async fn old_worker(
 		databases_rx,
 		cas_ids_to_delete_rx,
 		thumbnails_to_generate_rx,
+		thumbnails_accessed_rx,
 		cancel_rx,
 	}: WorkerChannels,
 ) {
@@ -56,18 +78,24 @@ pub(super) async fn old_worker(
 
 	let mut databases = HashMap::new();
 
+	// Last access of the thumbnails served since the previous eviction
+	let mut accessed = HashMap::new();
+
 	#[derive(Debug)]
 	enum StreamMessage {
 		RemovalTick,
 		ToDelete((Vec<String>, ThumbnailKind)),
 		Database(DatabaseMessage),
 		NewBatch((BatchToProcess, ThumbnailKind)),
+		Accessed(PathBuf),
 		Leftovers((BatchToProcess, ThumbnailKind)),
 		NewEphemeralThumbnailsFilenames(Vec<OsString>),
 		ProgressManagement(RegisterReporter),
-		BatchProgress((location::id::Type, u32)),
+		BatchProgress((location::id::Type, PathBuf)),
 		Shutdown(oneshot::Sender<()>),
 		UpdatedPreferences(ThumbnailerPreferences),
+		UpdatedBackgroundPolicy(WorkMode),
+		UpdatedHeldBackLocations(HashSet<location::id::Type>),
 		IdleTick,
 	}
 
@@ -88,12 +116,14 @@ pub(super) async fn old_worker(
 	let mut shutdown_batch_report_progress_rx = pin!(batch_report_progress_rx.clone());
 
 	let mut current_batch_processing_rx: Option<oneshot::Receiver<()>> = None;
+	let mut current_batch_location_id = None;
 
 	let mut msg_stream = pin!((
 		IntervalStream::new(to_remove_interval).map(|_| StreamMessage::RemovalTick),
 		cas_ids_to_delete_rx.map(StreamMessage::ToDelete),
 		databases_rx.map(StreamMessage::Database),
 		thumbnails_to_generate_rx.map(StreamMessage::NewBatch),
+		thumbnails_accessed_rx.map(StreamMessage::Accessed),
 		leftovers_rx.map(StreamMessage::Leftovers),
 		ephemeral_thumbnails_cas_ids_rx.map(StreamMessage::NewEphemeralThumbnailsFilenames),
 		progress_management_rx.map(StreamMessage::ProgressManagement),
@@ -103,10 +133,16 @@ pub(super) async fn old_worker(
 		WatchStream::new(node_preferences_rx).map(|node_preferences| {
 			StreamMessage::UpdatedPreferences(node_preferences.thumbnailer)
 		}),
+		WatchStream::new(background_policy_rx).map(|state| {
+			StreamMessage::UpdatedBackgroundPolicy(state.mode(Subsystem::Thumbnailing))
+		}),
+		WatchStream::new(held_back_locations_rx).map(StreamMessage::UpdatedHeldBackLocations),
 	)
 		.merge());
 
 	let mut thumbnailer_preferences = ThumbnailerPreferences::default();
+	let mut work_mode = WorkMode::Normal;
+	let mut held_back_locations = HashSet::new();
 
 	while let Some(msg) = msg_stream.next().await {
 		match msg {
@@ -125,25 +161,53 @@ pub(super) async fn old_worker(
 					}
 				}
 
-				if current_batch_processing_rx.is_none()
-					&& (!queue.is_empty()
-						|| !indexed_leftovers_queue.is_empty()
-						|| !ephemeral_leftovers_queue.is_empty())
-				{
-					let (done_tx, done_rx) = oneshot::channel();
-					current_batch_processing_rx = Some(done_rx);
-
-					let batch_and_kind = if let Some(batch_and_kind) = queue.pop_front() {
-						batch_and_kind
-					} else if let Some((batch, library_id)) = indexed_leftovers_queue.pop_front() {
+				// Foreground batches are for what the user is looking at right now, so they run even
+				// when the background policy holds us back
+				let can_run = |batch: &BatchToProcess| {
+					(work_mode != WorkMode::Paused || !batch.in_background)
+						&& batch.location_id.map_or(true, |location_id| {
+							!held_back_locations.contains(&location_id)
+						})
+				};
+
+				if current_batch_processing_rx.is_none() {
+					let batch_and_kind = if let Some(idx) =
+						queue.iter().position(|(batch, _)| can_run(batch))
+					{
+						queue.remove(idx)
+					} else if let Some(idx) = indexed_leftovers_queue
+						.iter()
+						.position(|(batch, _)| can_run(batch))
+					{
 						// indexed leftovers have bigger priority
-						(batch, ThumbnailKind::Indexed(library_id))
-					} else if let Some(batch) = ephemeral_leftovers_queue.pop_front() {
-						(batch, ThumbnailKind::Ephemeral)
+						indexed_leftovers_queue
+							.remove(idx)
+							.map(|(batch, library_id)| (batch, ThumbnailKind::Indexed(library_id)))
+					} else if let Some(idx) = ephemeral_leftovers_queue.iter().position(can_run) {
+						ephemeral_leftovers_queue
+							.remove(idx)
+							.map(|batch| (batch, ThumbnailKind::Ephemeral))
 					} else {
+						None
+					};
+
+					let Some(batch_and_kind) = batch_and_kind else {
 						continue;
 					};
 
+					let (done_tx, done_rx) = oneshot::channel();
+					current_batch_processing_rx = Some(done_rx);
+					current_batch_location_id = batch_and_kind.0.location_id;
+
+					let mut preferences = thumbnailer_preferences.clone();
+					if work_mode == WorkMode::Throttled {
+						preferences.set_background_processing_percentage(
+							preferences
+								.background_processing_percentage()
+								.min(THROTTLED_PROCESSING_PERCENTAGE),
+						);
+					}
+
 					spawn(batch_processor(
 						thumbnails_directory.clone(),
 						batch_and_kind,
@@ -155,7 +219,7 @@ pub(super) async fn old_worker(
 						},
 						leftovers_tx.clone(),
 						reporter.clone(),
-						(available_parallelism, thumbnailer_preferences.clone()),
+						(available_parallelism, preferences),
 					));
 				}
 			}
@@ -178,6 +242,16 @@ pub(super) async fn old_worker(
 						ephemeral_file_names.clone(),
 					));
 				}
+
+				spawn(process_cache_eviction(
+					thumbnails_directory.clone(),
+					mem::take(&mut accessed),
+					cache_budget_bytes(&thumbnailer_preferences),
+				));
+			}
+
+			StreamMessage::Accessed(thumbnail_path) => {
+				accessed.insert(thumbnail_path, SystemTime::now());
 			}
 
 			StreamMessage::ToDelete((cas_ids, kind)) => {
@@ -190,6 +264,7 @@ pub(super) async fn old_worker(
 
 			StreamMessage::NewBatch((batch, kind)) => {
 				let in_background = batch.in_background;
+				let continues = batch.continues;
 
 				if let Some(location_id) = batch.location_id {
 					bookkeeper
@@ -209,13 +284,20 @@ pub(super) async fn old_worker(
 
 				if in_background {
 					queue.push_back((batch, kind));
+				} else if continues {
+					// Keeps the order of the batches of a view, which are all in foreground
+					let at = queue
+						.iter()
+						.position(|(batch, _)| batch.in_background)
+						.unwrap_or(queue.len());
+					queue.insert(at, (batch, kind));
 				} else {
 					// If a processing must be in foreground, then it takes maximum priority
 					queue.push_front((batch, kind));
 				}
 
 				// Only sends stop signal if there is a batch being processed
-				if !in_background {
+				if !in_background && !continues {
 					stop_batch(
 						&current_batch_processing_rx,
 						&stop_older_processing_tx,
@@ -247,8 +329,8 @@ pub(super) async fn old_worker(
 				ephemeral_file_names.extend(new_ephemeral_thumbs);
 			}
 
-			StreamMessage::BatchProgress((location_id, progressed)) => {
-				bookkeeper.add_progress(location_id, progressed).await;
+			StreamMessage::BatchProgress((location_id, path)) => {
+				bookkeeper.add_progress(location_id, path).await;
 			}
 
 			StreamMessage::Shutdown(cancel_tx) => {
@@ -276,10 +358,9 @@ pub(super) async fn old_worker(
 
 				// Consuming the last progress reports to keep everything up to date
 				shutdown_batch_report_progress_rx.close();
-				while let Some((location_id, progressed)) =
-					shutdown_batch_report_progress_rx.next().await
+				while let Some((location_id, path)) = shutdown_batch_report_progress_rx.next().await
 				{
-					bookkeeper.add_progress(location_id, progressed).await;
+					bookkeeper.add_progress(location_id, path).await;
 				}
 
 				// Saving state
@@ -305,6 +386,15 @@ pub(super) async fn old_worker(
 			}
 
 			StreamMessage::UpdatedPreferences(preferences) => {
+				// A smaller budget is applied right away instead of waiting for the next removal
+				if preferences.cache_budget_mb() != thumbnailer_preferences.cache_budget_mb() {
+					spawn(process_cache_eviction(
+						thumbnails_directory.clone(),
+						mem::take(&mut accessed),
+						cache_budget_bytes(&preferences),
+					));
+				}
+
 				thumbnailer_preferences = preferences;
 				stop_batch(
 					&current_batch_processing_rx,
@@ -313,10 +403,46 @@ pub(super) async fn old_worker(
 				)
 				.await;
 			}
+
+			StreamMessage::UpdatedBackgroundPolicy(new_work_mode) => {
+				if new_work_mode != work_mode {
+					work_mode = new_work_mode;
+					// The current batch goes back to the leftovers, to be processed again
+					// following the new work mode
+					stop_batch(
+						&current_batch_processing_rx,
+						&stop_older_processing_tx,
+						&stop_older_processing_rx,
+					)
+					.await;
+				}
+			}
+
+			StreamMessage::UpdatedHeldBackLocations(new_held_back_locations) => {
+				held_back_locations = new_held_back_locations;
+
+				// The current batch goes back to the leftovers if its location is now held back
+				if current_batch_location_id
+					.is_some_and(|location_id| held_back_locations.contains(&location_id))
+				{
+					stop_batch(
+						&current_batch_processing_rx,
+						&stop_older_processing_tx,
+						&stop_older_processing_rx,
+					)
+					.await;
+				}
+			}
 		}
 	}
 }
 
+fn cache_budget_bytes(preferences: &ThumbnailerPreferences) -> Option<u64> {
+	preferences
+		.cache_budget_mb()
+		.map(|budget_mb| u64::from(budget_mb) * 1024 * 1024)
+}
+
 #[inline]
 async fn stop_batch(
 	current_batch_processing_rx: &Option<oneshot::Receiver<()>>,
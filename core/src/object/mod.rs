@@ -4,12 +4,15 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 
 pub mod cas;
+pub mod duplicates;
 pub mod fs;
 pub mod media;
 pub mod old_file_identifier;
+pub mod old_metadata_importer;
 pub mod old_orphan_remover;
 pub mod tag;
 pub mod validation;
+pub mod xmp;
 
 // Objects are primarily created by the identifier from Paths
 // Some Objects are purely virtual, unless they have one or more associated Paths, which refer to a file found in a Location
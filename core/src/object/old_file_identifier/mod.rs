@@ -1,4 +1,11 @@
-use crate::{library::Library, object::cas::generate_cas_id, old_job::JobError};
+use crate::{
+	library::Library,
+	object::{
+		cas::{cas_id_read_size, generate_cas_id_cached},
+		tag::rules::apply_rules_to_objects,
+	},
+	old_job::{JobError, JobItemProgress, JobReportUpdate, JobResourceLimiter},
+};
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
 use sd_core_prisma_helpers::{file_path_for_file_identifier, object_for_file_identifier};
@@ -17,7 +24,7 @@ use std::{
 	path::Path,
 };
 
-use futures::future::join_all;
+use futures::stream::{self, StreamExt};
 use tokio::fs;
 use tracing::{error, trace};
 use uuid::Uuid;
@@ -73,7 +80,7 @@ impl FileMetadata {
 			.unwrap_or(ObjectKind::Unknown);
 
 		let cas_id = if fs_metadata.len() != 0 {
-			generate_cas_id(&path, fs_metadata.len())
+			generate_cas_id_cached(&path, &fs_metadata)
 				.await
 				.map(Some)
 				.map_err(|e| FileIOError::from((&path, e)))?
@@ -92,14 +99,35 @@ impl FileMetadata {
 	}
 }
 
+/// The file paths of a step, as items of the job progress.
+fn identified_items(
+	location: &location::Data,
+	file_paths: &[file_path_for_file_identifier::Data],
+) -> Vec<JobReportUpdate> {
+	let Some(location_path) = location.path.as_deref().map(Path::new) else {
+		return vec![];
+	};
+
+	file_paths
+		.iter()
+		.filter_map(|file_path| IsolatedFilePathData::try_from((location.id, file_path)).ok())
+		.map(|iso_file_path| {
+			JobReportUpdate::Item(JobItemProgress::new(
+				location_path.join(&iso_file_path).display().to_string(),
+			))
+		})
+		.collect()
+}
+
 async fn identifier_job_step(
 	Library { db, sync, .. }: &Library,
 	location: &location::Data,
 	file_paths: &[file_path_for_file_identifier::Data],
+	limiter: &JobResourceLimiter,
 ) -> Result<(usize, usize), JobError> {
 	let location_path = maybe_missing(&location.path, "location.path").map(Path::new)?;
 
-	let file_paths_metadatas = join_all(
+	let file_paths_metadatas = stream::iter(
 		file_paths
 			.iter()
 			.filter_map(|file_path| {
@@ -109,8 +137,15 @@ async fn identifier_job_step(
 					.ok()
 			})
 			.map(|(iso_file_path, file_path)| async move {
-				FileMetadata::new(&location_path, &iso_file_path)
-					.await
+				let metadata = FileMetadata::new(&location_path, &iso_file_path).await;
+
+				if let Ok(metadata) = &metadata {
+					limiter
+						.throttle_io(cas_id_read_size(metadata.fs_metadata.len()))
+						.await;
+				}
+
+				metadata
 					.map(|metadata| {
 						(
 							// SAFETY: This should never happen
@@ -138,10 +173,10 @@ async fn identifier_job_step(
 					.ok()
 			}),
 	)
-	.await
-	.into_iter()
-	.flatten()
-	.collect::<HashMap<_, _>>();
+	.buffer_unordered(limiter.parallelism(file_paths.len()))
+	.filter_map(|maybe_metadata| async move { maybe_metadata })
+	.collect::<HashMap<_, _>>()
+	.await;
 
 	let unique_cas_ids = file_paths_metadatas
 		.values()
@@ -374,6 +409,34 @@ fn connect_file_path_to_object<'db>(
 	)
 }
 
+/// Evaluates the tag rules on the objects of the identified file paths, a rule failing doesn't
+/// fail the identification.
+async fn apply_tag_rules(library: &Library, file_paths: &[file_path_for_file_identifier::Data]) {
+	let object_ids = match library
+		.db
+		.object()
+		.find_many(vec![object::file_paths::some(vec![file_path::id::in_vec(
+			file_paths.iter().map(|file_path| file_path.id).collect(),
+		)])])
+		.select(object::select!({ id }))
+		.exec()
+		.await
+	{
+		Ok(objects) => objects
+			.into_iter()
+			.map(|object| object.id)
+			.collect::<Vec<_>>(),
+		Err(e) => {
+			error!("Failed to read the identified objects to evaluate tag rules: {e:#?}");
+			return;
+		}
+	};
+
+	if let Err(e) = apply_rules_to_objects(library, &object_ids).await {
+		error!("Failed to evaluate tag rules on the identified objects: {e:#?}");
+	}
+}
+
 async fn process_identifier_file_paths(
 	location: &location::Data,
 	file_paths: &[file_path_for_file_identifier::Data],
@@ -381,6 +444,7 @@ async fn process_identifier_file_paths(
 	cursor: file_path::id::Type,
 	library: &Library,
 	orphan_count: usize,
+	limiter: &JobResourceLimiter,
 ) -> Result<(usize, usize, file_path::id::Type), JobError> {
 	trace!(
 		"Processing {:?} orphan Paths. ({} completed of {})",
@@ -390,7 +454,9 @@ async fn process_identifier_file_paths(
 	);
 
 	let (total_objects_created, total_objects_linked) =
-		identifier_job_step(library, location, file_paths).await?;
+		identifier_job_step(library, location, file_paths, limiter).await?;
+
+	apply_tag_rules(library, file_paths).await;
 
 	Ok((
 		total_objects_created,
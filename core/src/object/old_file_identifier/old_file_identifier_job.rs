@@ -26,7 +26,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::{debug, info, trace};
 
-use super::{process_identifier_file_paths, FileIdentifierJobError, CHUNK_SIZE};
+use super::{identified_items, process_identifier_file_paths, FileIdentifierJobError, CHUNK_SIZE};
 
 /// `FileIdentifierJobInit` takes file_paths without an object_id from a location
 /// or starting from a `sub_path` getting every descendent from this `sub_path`
@@ -219,6 +219,7 @@ impl StatefulJob for OldFileIdentifierJobInit {
 				run_metadata.cursor,
 				&ctx.library,
 				run_metadata.total_orphan_paths,
+				&ctx.limiter,
 			)
 			.await?;
 
@@ -226,7 +227,8 @@ impl StatefulJob for OldFileIdentifierJobInit {
 		new_metadata.total_objects_linked = total_objects_linked;
 		new_metadata.cursor = new_cursor;
 
-		ctx.progress(vec![
+		let mut updates = identified_items(location, &file_paths);
+		updates.extend([
 			JobReportUpdate::CompletedTaskCount(step_number * CHUNK_SIZE + file_paths.len()),
 			JobReportUpdate::Message(format!(
 				"Processed {} of {} orphan Paths",
@@ -234,6 +236,7 @@ impl StatefulJob for OldFileIdentifierJobInit {
 				run_metadata.total_orphan_paths
 			)),
 		]);
+		ctx.progress(updates);
 
 		Ok(new_metadata.into())
 	}
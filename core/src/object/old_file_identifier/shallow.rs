@@ -1,4 +1,8 @@
-use crate::{invalidate_query, library::Library, old_job::JobError};
+use crate::{
+	invalidate_query,
+	library::Library,
+	old_job::{JobError, JobResourceLimiter},
+};
 
 use sd_core_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
@@ -108,6 +112,7 @@ pub async fn old_shallow(
 			*cursor,
 			library,
 			orphan_count,
+			&JobResourceLimiter::default(),
 		)
 		.await?;
 		*cursor = new_cursor;
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, path::Path};
+
+use rusqlite::{Connection, OpenFlags};
+
+use super::{ImportedFile, MetadataImporterError};
+
+/// Parent of the tags digiKam uses for its own bookkeeping, like color labels and picks
+const INTERNAL_TAGS_ROOT: &str = "_Digikam_Internal_Tags_";
+
+/// `ImageComments.type` of the captions
+const COMMENT_TYPE: i64 = 1;
+
+/// Reads the tags, ratings and captions of the images in a `digikam4.db` database.
+pub(super) fn read(database: &Path) -> Result<Vec<ImportedFile>, MetadataImporterError> {
+	let conn = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+	let mut files = HashMap::new();
+
+	let mut stmt = conn.prepare(
+		"SELECT Images.id, AlbumRoots.specificPath, Albums.relativePath, Images.name,
+			ImageInformation.rating
+		FROM Images
+		INNER JOIN Albums ON Images.album = Albums.id
+		INNER JOIN AlbumRoots ON Albums.albumRoot = AlbumRoots.id
+		LEFT JOIN ImageInformation ON ImageInformation.imageid = Images.id
+		WHERE Images.status = 1",
+	)?;
+	let rows = stmt.query_map([], |row| {
+		Ok((
+			row.get::<_, i64>(0)?,
+			row.get::<_, String>(1)?,
+			row.get::<_, String>(2)?,
+			row.get::<_, String>(3)?,
+			row.get::<_, Option<i64>>(4)?,
+		))
+	})?;
+
+	for row in rows {
+		let (id, root, album, name, rating) = row?;
+
+		let root = root.trim_end_matches('/');
+		// The root album has `/` as relative path
+		let path = match album.trim_matches('/') {
+			"" => format!("{root}/{name}"),
+			album => format!("{root}/{album}/{name}"),
+		};
+
+		files.insert(
+			id,
+			ImportedFile {
+				path,
+				// No stars is saved as 0, and a missing rating as -1
+				rating: rating
+					.filter(|rating| (1..=5).contains(rating))
+					.map(|rating| rating as i32),
+				..Default::default()
+			},
+		);
+	}
+
+	let mut stmt = conn.prepare(
+		"SELECT ImageTags.imageid, Tags.name
+		FROM ImageTags
+		INNER JOIN Tags ON ImageTags.tagid = Tags.id
+		WHERE Tags.name != ?1
+			AND Tags.pid NOT IN (SELECT id FROM Tags WHERE name = ?1)",
+	)?;
+	let rows = stmt.query_map([INTERNAL_TAGS_ROOT], |row| {
+		Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+	})?;
+
+	for row in rows {
+		let (id, tag) = row?;
+		if let Some(file) = files.get_mut(&id) {
+			file.tags.push(tag);
+		}
+	}
+
+	let mut stmt = conn.prepare(
+		"SELECT imageid, comment FROM ImageComments
+		WHERE type = ?1 AND comment IS NOT NULL AND comment != ''
+		ORDER BY language = 'x-default' DESC",
+	)?;
+	let rows = stmt.query_map([COMMENT_TYPE], |row| {
+		Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+	})?;
+
+	for row in rows {
+		let (id, comment) = row?;
+		// Captions in the default language come first
+		if let Some(file) = files.get_mut(&id).filter(|file| file.note.is_none()) {
+			file.note = Some(comment);
+		}
+	}
+
+	Ok(files.into_values().collect())
+}
@@ -0,0 +1,127 @@
+//! Everything exports CSV files with a `Filename` column: file lists (`.efu`), and its run history
+//! with the date each file was last opened, as a Windows file time.
+
+use sd_utils::error::FileIOError;
+
+use std::{fs, path::Path};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use super::{ImportedFile, MetadataImporterError};
+
+/// Seconds between the Windows epoch, 1601-01-01, and the Unix epoch
+const WINDOWS_EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
+/// Windows file times count intervals of 100 nanoseconds
+const FILE_TIME_TICKS_PER_SEC: i64 = 10_000_000;
+
+/// Reads a file list, whose files get the `tag`, or the run history, giving the date the files
+/// were last opened.
+pub(super) fn read(file: &Path, tag: &str) -> Result<Vec<ImportedFile>, MetadataImporterError> {
+	let data = fs::read(file).map_err(|e| FileIOError::from((file, e)))?;
+	let data = String::from_utf8_lossy(&data);
+
+	let mut lines = data
+		.trim_start_matches('\u{feff}')
+		.lines()
+		.filter(|line| !line.is_empty());
+
+	let columns = lines
+		.next()
+		.map(|header| {
+			split_csv_line(header)
+				.into_iter()
+				.map(|column| column.to_lowercase())
+				.collect::<Vec<_>>()
+		})
+		.unwrap_or_default();
+
+	let filename_idx = columns
+		.iter()
+		.position(|column| column == "filename")
+		.ok_or_else(|| MetadataImporterError::InvalidSource {
+			path: file.into(),
+			reason: "missing the 'Filename' column".to_string(),
+		})?;
+
+	// "Date Run" in the run history, which is the only export with it
+	let date_run_idx = columns
+		.iter()
+		.position(|column| column.contains("run") && column.contains("date"));
+
+	Ok(lines
+		.filter_map(|line| {
+			let mut fields = split_csv_line(line);
+			if filename_idx >= fields.len() {
+				return None;
+			}
+
+			let date_run = date_run_idx.and_then(|idx| fields.get(idx)?.parse().ok());
+			let path = fields.swap_remove(filename_idx);
+
+			Some(match date_run_idx {
+				Some(_) => ImportedFile {
+					path,
+					date_accessed: date_run.and_then(from_file_time),
+					..Default::default()
+				},
+				None => ImportedFile {
+					path,
+					tags: vec![tag.to_string()],
+					..Default::default()
+				},
+			})
+		})
+		.collect())
+}
+
+fn from_file_time(file_time: i64) -> Option<DateTime<Utc>> {
+	Utc.timestamp_opt(
+		file_time / FILE_TIME_TICKS_PER_SEC - WINDOWS_EPOCH_OFFSET_SECS,
+		((file_time % FILE_TIME_TICKS_PER_SEC) * 100) as u32,
+	)
+	.single()
+}
+
+/// Splits a CSV line, whose fields may be quoted with `"`, and quotes escaped by doubling them.
+fn split_csv_line(line: &str) -> Vec<String> {
+	let mut fields = vec![];
+	let mut field = String::new();
+	let mut in_quotes = false;
+	let mut chars = line.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if in_quotes && chars.peek() == Some(&'"') => {
+				field.push('"');
+				chars.next();
+			}
+			'"' => in_quotes = !in_quotes,
+			',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+			c => field.push(c),
+		}
+	}
+	fields.push(field);
+
+	fields
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_csv_line() {
+		assert_eq!(
+			split_csv_line(r#""C:\My ""Files"", 2024\a.txt",123,,"x""#),
+			vec![r#"C:\My "Files", 2024\a.txt"#, "123", "", "x"]
+		);
+	}
+
+	#[test]
+	fn converts_file_time() {
+		assert_eq!(
+			from_file_time(133_000_000_000_000_000).map(|date| date.timestamp()),
+			Some(1_655_526_400)
+		);
+	}
+}
@@ -0,0 +1,134 @@
+//! Paths from an `mlocate.db` database, or from a list of paths like the output of
+//! `locate -0 <pattern>`, separated by NUL characters or new lines.
+
+use sd_utils::error::FileIOError;
+
+use std::{fs, path::Path};
+
+use super::{ImportedFile, MetadataImporterError};
+
+const MLOCATE_MAGIC: &[u8] = b"\0mlocate";
+
+const FILE_ENTRY: u8 = 0;
+const DIR_ENTRY: u8 = 1;
+const END_OF_DIR: u8 = 2;
+
+/// Reads the paths of the database or list, every file getting the `tag`.
+pub(super) fn read(file: &Path, tag: &str) -> Result<Vec<ImportedFile>, MetadataImporterError> {
+	let data = fs::read(file).map_err(|e| FileIOError::from((file, e)))?;
+
+	let paths = if data.starts_with(MLOCATE_MAGIC) {
+		parse_mlocate(&data).ok_or_else(|| MetadataImporterError::InvalidSource {
+			path: file.into(),
+			reason: "truncated or corrupted mlocate database".to_string(),
+		})?
+	} else {
+		let separator = if data.contains(&0) { '\0' } else { '\n' };
+
+		String::from_utf8_lossy(&data)
+			.split(separator)
+			.map(|path| path.trim_end_matches('\r'))
+			.filter(|path| !path.is_empty())
+			.map(ToString::to_string)
+			.collect()
+	};
+
+	Ok(paths
+		.into_iter()
+		.map(|path| ImportedFile {
+			path,
+			tags: vec![tag.to_string()],
+			..Default::default()
+		})
+		.collect())
+}
+
+/// The files of the database, skipping the directories.
+fn parse_mlocate(mut data: &[u8]) -> Option<Vec<String>> {
+	take(&mut data, MLOCATE_MAGIC.len())?;
+	let conf_size = u32::from_be_bytes(take(&mut data, 4)?.try_into().ok()?) as usize;
+	// Version, visibility flag and padding
+	take(&mut data, 4)?;
+	// Root of the database
+	take_str(&mut data)?;
+	take(&mut data, conf_size)?;
+
+	let mut paths = vec![];
+
+	while !data.is_empty() {
+		// Modification time of the directory and padding
+		take(&mut data, 16)?;
+		let dir = take_str(&mut data)?;
+		let dir = dir.trim_end_matches('/');
+
+		loop {
+			match take(&mut data, 1)?[0] {
+				FILE_ENTRY => paths.push(format!("{dir}/{}", take_str(&mut data)?)),
+				DIR_ENTRY => {
+					take_str(&mut data)?;
+				}
+				END_OF_DIR => break,
+				_ => return None,
+			}
+		}
+	}
+
+	Some(paths)
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+	if data.len() < len {
+		return None;
+	}
+
+	let (taken, rest) = data.split_at(len);
+	*data = rest;
+
+	Some(taken)
+}
+
+fn take_str(data: &mut &[u8]) -> Option<String> {
+	let end = data.iter().position(|byte| *byte == 0)?;
+	let string = String::from_utf8_lossy(&data[..end]).into_owned();
+	*data = &data[end + 1..];
+
+	Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_mlocate() {
+		let mut data = MLOCATE_MAGIC.to_vec();
+		data.extend(3_u32.to_be_bytes());
+		data.extend([0, 1, 0, 0]);
+		data.extend(b"/\0");
+		data.extend(b"a=b");
+
+		data.extend([0; 16]);
+		data.extend(b"/home\0");
+		data.extend([DIR_ENTRY]);
+		data.extend(b"user\0");
+		data.extend([END_OF_DIR]);
+
+		data.extend([0; 16]);
+		data.extend(b"/home/user\0");
+		data.extend([FILE_ENTRY]);
+		data.extend(b"photo.jpg\0");
+		data.extend([FILE_ENTRY]);
+		data.extend(b"notes.txt\0");
+		data.extend([END_OF_DIR]);
+
+		assert_eq!(
+			parse_mlocate(&data),
+			Some(vec![
+				"/home/user/photo.jpg".to_string(),
+				"/home/user/notes.txt".to_string()
+			])
+		);
+
+		assert_eq!(parse_mlocate(&data[..data.len() - 4]), None);
+	}
+}
@@ -0,0 +1,407 @@
+//! Imports the tags, ratings, notes and last opened dates other apps keep about the files of a
+//! location, onto the objects of the files already indexed and identified.
+
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::get_location_path_from_location_id,
+	object::tag::{find_or_create_tags, IMPORTED_TAG_COLOR},
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_core_file_path_helper::{loose_find_existing_file_path_params, FilePathError};
+
+use sd_prisma::{
+	prisma::{file_path, location, object, tag_on_object},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{error::FileIOError, msgpack};
+
+use std::{
+	hash::Hash,
+	path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use thiserror::Error;
+use tokio::task::spawn_blocking;
+use tracing::info;
+
+mod digikam;
+mod everything;
+mod locate;
+mod tagspaces;
+
+const BATCH_SIZE: usize = 100;
+
+#[derive(Error, Debug)]
+pub enum MetadataImporterError {
+	#[error("invalid source '{}': {reason}", .path.display())]
+	InvalidSource { path: Box<Path>, reason: String },
+	#[error("failed to read digiKam database: {0}")]
+	DigiKam(#[from] rusqlite::Error),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	FilePath(#[from] FilePathError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+/// Where the metadata is imported from.
+#[derive(Serialize, Deserialize, Type, Hash, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ImportSource {
+	/// The `digikam4.db` database of a digiKam collection
+	DigiKam { database: PathBuf },
+	/// The `.ts` sidecar folders and file name tags TagSpaces left in the location
+	TagSpaces,
+	/// A file list or the run history exported as CSV by Everything
+	Everything {
+		file: PathBuf,
+		/// Tag given to the files of a file list, defaulting to the name of the list
+		#[serde(default)]
+		tag: Option<String>,
+	},
+	/// An `mlocate.db` database or a list of paths given by `locate`
+	Locate {
+		file: PathBuf,
+		/// Tag given to the files, defaulting to the name of the database or list
+		#[serde(default)]
+		tag: Option<String>,
+	},
+}
+
+impl ImportSource {
+	fn read(&self, location_path: &Path) -> Result<Vec<ImportedFile>, MetadataImporterError> {
+		match self {
+			Self::DigiKam { database } => digikam::read(database),
+			Self::TagSpaces => tagspaces::read(location_path),
+			Self::Everything { file, tag } => everything::read(file, &tag_or_file_stem(tag, file)),
+			Self::Locate { file, tag } => locate::read(file, &tag_or_file_stem(tag, file)),
+		}
+	}
+}
+
+fn tag_or_file_stem(tag: &Option<String>, file: &Path) -> String {
+	tag.clone().unwrap_or_else(|| {
+		file.file_stem()
+			.map(|stem| stem.to_string_lossy().to_string())
+			.unwrap_or_else(|| "Imported".to_string())
+	})
+}
+
+/// The metadata a source has about a file.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ImportedFile {
+	pub path: String,
+	pub tags: Vec<String>,
+	/// From 1 to 5 stars
+	pub rating: Option<i32>,
+	pub note: Option<String>,
+	pub date_accessed: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Type, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OldMetadataImporterJobInit {
+	pub location_id: location::id::Type,
+	pub source: ImportSource,
+	/// Where the files of the location were when the source was made, if it differs from the
+	/// location path, like `D:\Photos` for a location at `/mnt/photos`
+	#[serde(default)]
+	pub source_root: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldMetadataImporterJobData {
+	location_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldMetadataImporterMetadata {
+	imported: u32,
+	not_found: u32,
+}
+
+impl JobRunMetadata for OldMetadataImporterMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.imported += new_data.imported;
+		self.not_found += new_data.not_found;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldMetadataImporterJobInit {
+	type Data = OldMetadataImporterJobData;
+	type Step = Vec<ImportedFile>;
+	type RunMetadata = OldMetadataImporterMetadata;
+
+	const NAME: &'static str = "metadata_importer";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id).await?;
+
+		let files = spawn_blocking({
+			let source = init.source.clone();
+			let location_path = location_path.clone();
+			move || source.read(&location_path)
+		})
+		.await??;
+
+		let location_path_str = location_path.to_string_lossy();
+		let source_root = match (&init.source, &init.source_root) {
+			// TagSpaces is read from the location itself
+			(ImportSource::TagSpaces, _) | (_, None) => &*location_path_str,
+			(_, Some(source_root)) => source_root.as_str(),
+		};
+
+		let mut files = files
+			.into_iter()
+			.filter_map(|mut file| {
+				file.path = to_location_path(&file.path, source_root, &location_path)?
+					.to_string_lossy()
+					.to_string();
+				Some(file)
+			})
+			.collect::<Vec<_>>();
+
+		info!(
+			"Found {} files to import metadata of in location <id='{}'>",
+			files.len(),
+			init.location_id
+		);
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(files.len())]);
+
+		let mut steps = vec![];
+		while !files.is_empty() {
+			steps.push(files.drain(..BATCH_SIZE.min(files.len())).collect());
+		}
+
+		*data = Some(OldMetadataImporterJobData { location_path });
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, step_number }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let run_metadata =
+			import_batch(&ctx.library, self.location_id, &data.location_path, step).await?;
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
+			step_number * BATCH_SIZE + step.len(),
+		)]);
+
+		Ok(run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		info!(
+			"Imported metadata of {} files, {} files weren't found in location <id='{}'>",
+			run_metadata.imported, run_metadata.not_found, init.location_id
+		);
+
+		invalidate_query!(ctx.library, "search.paths");
+		invalidate_query!(ctx.library, "search.objects");
+		invalidate_query!(ctx.library, "tags.list");
+		invalidate_query!(ctx.library, "tags.getWithObjects");
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
+
+/// Maps a path from the source into the location, if it's inside `source_root`.
+fn to_location_path(path: &str, source_root: &str, location_path: &Path) -> Option<PathBuf> {
+	let relative = path.strip_prefix(source_root.trim_end_matches(['/', '\\']))?;
+
+	// Siblings sharing the prefix, like `/photos-old` for `/photos`
+	if !relative.is_empty() && !relative.starts_with(['/', '\\']) {
+		return None;
+	}
+
+	relative
+		.split(['/', '\\'])
+		.filter(|segment| !segment.is_empty() && *segment != ".")
+		.try_fold(location_path.to_path_buf(), |path, segment| {
+			(segment != "..").then(|| path.join(segment))
+		})
+}
+
+async fn import_batch(
+	library: &Library,
+	location_id: location::id::Type,
+	location_path: &Path,
+	files: &[ImportedFile],
+) -> Result<OldMetadataImporterMetadata, MetadataImporterError> {
+	let Library { db, sync, .. } = library;
+
+	let mut run_metadata = OldMetadataImporterMetadata::default();
+
+	let mut objects = Vec::with_capacity(files.len());
+	for file in files {
+		let object = db
+			.file_path()
+			.find_first(loose_find_existing_file_path_params(
+				location_id,
+				location_path,
+				&file.path,
+			)?)
+			.select(file_path::select!({ object: select { id pub_id note rating date_accessed } }))
+			.exec()
+			.await?
+			.and_then(|file_path| file_path.object);
+
+		match object {
+			Some(object) => objects.push((file, object)),
+			// Not indexed yet, or not identified, so without an object to hold the metadata
+			None => run_metadata.not_found += 1,
+		}
+	}
+
+	let tags = find_or_create_tags(
+		library,
+		objects
+			.iter()
+			.flat_map(|(file, _)| file.tags.iter().cloned())
+			.collect(),
+		IMPORTED_TAG_COLOR,
+	)
+	.await?;
+
+	let (mut sync_ops, mut updates) = (vec![], vec![]);
+	let (mut tag_sync_ops, mut tag_links) = (vec![], vec![]);
+
+	for (file, object) in objects {
+		let sync_id = prisma_sync::object::SyncId {
+			pub_id: object.pub_id.clone(),
+		};
+		let mut params = vec![];
+
+		if let Some(rating) = file.rating.filter(|rating| object.rating != Some(*rating)) {
+			sync_ops.push(sync.shared_update(
+				sync_id.clone(),
+				object::rating::NAME,
+				msgpack!(rating),
+			));
+			params.push(object::rating::set(Some(rating)));
+		}
+
+		// Notes written in Spacedrive are kept
+		if let (Some(note), None) = (&file.note, &object.note) {
+			sync_ops.push(sync.shared_update(sync_id.clone(), object::note::NAME, msgpack!(note)));
+			params.push(object::note::set(Some(note.clone())));
+		}
+
+		if let Some(date_accessed) = file.date_accessed.filter(|date_accessed| {
+			object
+				.date_accessed
+				.map_or(true, |current| current < *date_accessed)
+		}) {
+			let date_accessed: DateTime<FixedOffset> = date_accessed.into();
+			sync_ops.push(sync.shared_update(
+				sync_id.clone(),
+				object::date_accessed::NAME,
+				msgpack!(date_accessed),
+			));
+			params.push(object::date_accessed::set(Some(date_accessed)));
+		}
+
+		if !params.is_empty() {
+			updates.push(db.object().update(object::id::equals(object.id), params));
+		}
+
+		for tag in file.tags.iter().filter_map(|name| tags.get(name)) {
+			tag_sync_ops.extend(sync.relation_create(
+				prisma_sync::tag_on_object::SyncId {
+					tag: prisma_sync::tag::SyncId {
+						pub_id: tag.pub_id.clone(),
+					},
+					object: sync_id.clone(),
+				},
+				[],
+			));
+			tag_links.push(tag_on_object::CreateUnchecked {
+				tag_id: tag.id,
+				object_id: object.id,
+				_params: vec![tag_on_object::date_created::set(Some(Utc::now().into()))],
+			});
+		}
+
+		run_metadata.imported += 1;
+	}
+
+	if !updates.is_empty() {
+		sync.write_ops(db, (sync_ops, updates)).await?;
+	}
+
+	if !tag_links.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				tag_sync_ops,
+				db.tag_on_object().create_many(tag_links).skip_duplicates(),
+			),
+		)
+		.await?;
+	}
+
+	Ok(run_metadata)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn maps_paths_into_location() {
+		let location_path = Path::new("/mnt/photos");
+
+		assert_eq!(
+			to_location_path(r"D:\Photos\2024\beach.jpg", r"D:\Photos\", location_path),
+			Some(PathBuf::from("/mnt/photos/2024/beach.jpg"))
+		);
+		assert_eq!(
+			to_location_path("/mnt/photos", "/mnt/photos", location_path),
+			Some(PathBuf::from("/mnt/photos"))
+		);
+		assert_eq!(
+			to_location_path("/mnt/photos-old/a.jpg", "/mnt/photos", location_path),
+			None
+		);
+		assert_eq!(
+			to_location_path("/mnt/photos/../a.jpg", "/mnt/photos", location_path),
+			None
+		);
+	}
+}
@@ -0,0 +1,152 @@
+//! TagSpaces keeps the tags and description of a file in `.ts/<file name>.json` next to it, and
+//! can also add the tags to the file name itself, like `photo[beach summer].jpg`.
+
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{ImportedFile, MetadataImporterError};
+
+const METADATA_DIR: &str = ".ts";
+/// Metadata of the folder holding the `.ts` folder
+const FOLDER_METADATA_FILE: &str = "tsm.json";
+
+#[derive(Deserialize)]
+struct Sidecar {
+	#[serde(default)]
+	tags: Vec<SidecarTag>,
+	#[serde(default)]
+	description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SidecarTag {
+	title: String,
+}
+
+/// Reads the tags and descriptions of the files inside `root`.
+pub(super) fn read(root: &Path) -> Result<Vec<ImportedFile>, MetadataImporterError> {
+	let mut files = HashMap::<PathBuf, ImportedFile>::new();
+	let mut dirs = vec![root.to_path_buf()];
+
+	while let Some(dir) = dirs.pop() {
+		let entries = match fs::read_dir(&dir) {
+			Ok(entries) => entries,
+			Err(e) => {
+				warn!("Failed to read directory '{}': {e:#?}", dir.display());
+				continue;
+			}
+		};
+
+		for entry in entries.flatten() {
+			let path = entry.path();
+			let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+				continue;
+			};
+
+			if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+				if name == METADATA_DIR {
+					read_sidecars(&path, &dir, &mut files);
+				} else if !name.starts_with('.') {
+					dirs.push(path);
+				}
+				continue;
+			}
+
+			let tags = tags_from_name(name);
+			if !tags.is_empty() {
+				file_entry(&mut files, path).tags.extend(tags);
+			}
+		}
+	}
+
+	Ok(files
+		.into_values()
+		.map(|mut file| {
+			file.tags.sort();
+			file.tags.dedup();
+			file
+		})
+		.collect())
+}
+
+fn read_sidecars(metadata_dir: &Path, dir: &Path, files: &mut HashMap<PathBuf, ImportedFile>) {
+	let Ok(entries) = fs::read_dir(metadata_dir) else {
+		return;
+	};
+
+	for entry in entries.flatten() {
+		let sidecar_path = entry.path();
+		let Some(name) = sidecar_path.file_name().and_then(|name| name.to_str()) else {
+			continue;
+		};
+
+		let path = match name {
+			FOLDER_METADATA_FILE => dir.to_path_buf(),
+			name => match name.strip_suffix(".json") {
+				Some(file_name) => dir.join(file_name),
+				// Thumbnails and other files TagSpaces generates
+				None => continue,
+			},
+		};
+
+		let sidecar = match fs::read(&sidecar_path)
+			.map_err(|e| e.to_string())
+			.and_then(|data| serde_json::from_slice::<Sidecar>(&data).map_err(|e| e.to_string()))
+		{
+			Ok(sidecar) => sidecar,
+			Err(e) => {
+				warn!(
+					"Failed to read TagSpaces sidecar '{}': {e}",
+					sidecar_path.display()
+				);
+				continue;
+			}
+		};
+
+		let file = file_entry(files, path);
+		file.tags
+			.extend(sidecar.tags.into_iter().map(|tag| tag.title));
+		file.note = sidecar
+			.description
+			.filter(|description| !description.is_empty());
+	}
+}
+
+fn file_entry(files: &mut HashMap<PathBuf, ImportedFile>, path: PathBuf) -> &mut ImportedFile {
+	files.entry(path).or_insert_with_key(|path| ImportedFile {
+		path: path.to_string_lossy().to_string(),
+		..Default::default()
+	})
+}
+
+/// Tags between brackets at the end of the name, before the extension.
+fn tags_from_name(name: &str) -> Vec<String> {
+	let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+
+	stem.strip_suffix(']')
+		.and_then(|stem| stem.rsplit_once('['))
+		.map(|(_, tags)| tags.split_whitespace().map(ToString::to_string).collect())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_tags_from_name() {
+		assert_eq!(
+			tags_from_name("photo[beach summer].jpg"),
+			vec!["beach", "summer"]
+		);
+		assert_eq!(tags_from_name("notes[todo]"), vec!["todo"]);
+		assert!(tags_from_name("photo.jpg").is_empty());
+		assert!(tags_from_name("[draft] photo.jpg").is_empty());
+	}
+}
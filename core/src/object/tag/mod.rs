@@ -1,8 +1,13 @@
 use crate::library::Library;
 
-use sd_prisma::{prisma::tag, prisma_sync};
+use sd_prisma::{
+	prisma::{tag, PrismaClient},
+	prisma_sync,
+};
 use sd_sync::*;
 
+use std::collections::{HashMap, HashSet};
+
 use chrono::{DateTime, FixedOffset, Utc};
 
 use sd_utils::msgpack;
@@ -10,12 +15,20 @@ use serde::Deserialize;
 use specta::Type;
 use uuid::Uuid;
 
+pub mod rules;
 pub mod seed;
 
+/// Color of the tags created when importing metadata from other apps
+pub const IMPORTED_TAG_COLOR: &str = "#8F8F9F";
+
 #[derive(Type, Deserialize, Clone)]
 pub struct TagCreateArgs {
 	pub name: String,
 	pub color: String,
+	/// Nests the new tag under this one
+	#[serde(default)]
+	#[specta(optional)]
+	pub parent_id: Option<tag::id::Type>,
 }
 
 impl TagCreateArgs {
@@ -26,6 +39,16 @@ impl TagCreateArgs {
 		let pub_id = Uuid::new_v4().as_bytes().to_vec();
 		let date_created: DateTime<FixedOffset> = Utc::now().into();
 
+		let parent = match self.parent_id {
+			Some(parent_id) => db
+				.tag()
+				.find_unique(tag::id::equals(parent_id))
+				.select(tag::select!({ id pub_id }))
+				.exec()
+				.await?,
+			None => None,
+		};
+
 		sync.write_ops(
 			db,
 			(
@@ -41,19 +64,95 @@ impl TagCreateArgs {
 							tag::date_created::NAME,
 							msgpack!(&date_created.to_rfc3339()),
 						),
-					],
+					]
+					.into_iter()
+					.chain(parent.as_ref().map(|parent| {
+						(
+							tag::parent::NAME,
+							msgpack!(prisma_sync::tag::SyncId {
+								pub_id: parent.pub_id.clone()
+							}),
+						)
+					})),
 				),
 				db.tag().create(
 					pub_id,
-					vec![
+					[
 						tag::name::set(Some(self.name)),
 						tag::color::set(Some(self.color)),
 						tag::is_hidden::set(Some(false)),
 						tag::date_created::set(Some(date_created)),
-					],
+					]
+					.into_iter()
+					.chain(
+						parent.map(|parent| tag::parent::connect(tag::id::equals(parent.id))),
+					)
+					.collect(),
 				),
 			),
 		)
 		.await
 	}
 }
+
+/// The `ids` along with the ids of all the tags nested under them, at any depth.
+pub async fn with_descendant_ids(
+	db: &PrismaClient,
+	ids: Vec<tag::id::Type>,
+) -> prisma_client_rust::Result<Vec<tag::id::Type>> {
+	let mut found = ids.iter().copied().collect::<HashSet<_>>();
+	let mut parents = ids;
+
+	// Stops at the tags already found, in case synced changes made a cycle
+	while !parents.is_empty() {
+		parents = db
+			.tag()
+			.find_many(vec![tag::parent_id::in_vec(parents)])
+			.select(tag::select!({ id }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|tag| tag.id)
+			.filter(|id| found.insert(*id))
+			.collect();
+	}
+
+	Ok(found.into_iter().collect())
+}
+
+/// Tags with the given names, creating the missing ones with the `color`.
+pub async fn find_or_create_tags(
+	library: &Library,
+	names: HashSet<String>,
+	color: &str,
+) -> prisma_client_rust::Result<HashMap<String, tag::Data>> {
+	if names.is_empty() {
+		return Ok(HashMap::new());
+	}
+
+	let mut tags = library
+		.db
+		.tag()
+		.find_many(vec![tag::name::in_vec(names.iter().cloned().collect())])
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|tag| Some((tag.name.clone()?, tag)))
+		.collect::<HashMap<_, _>>();
+
+	for name in names {
+		if !tags.contains_key(&name) {
+			let tag = TagCreateArgs {
+				name: name.clone(),
+				color: color.to_string(),
+				parent_id: None,
+			}
+			.exec(library)
+			.await?;
+
+			tags.insert(name, tag);
+		}
+	}
+
+	Ok(tags)
+}
@@ -0,0 +1,313 @@
+//! Rules applying their tag to the objects matching their filters, evaluated on the locations by
+//! [`old_tag_rules_job`] after they're scanned and on the objects changed by the watcher and the
+//! file identifier, and removing it once the objects stop matching.
+
+use crate::{api::search::SearchFilterArgs, invalidate_query, library::Library};
+
+use sd_prisma::{
+	prisma::{
+		file_path, location, object, tag_on_object, tag_rule, tag_rule_action, PrismaClient,
+		SortOrder,
+	},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::chain_optional_iter;
+
+use std::{
+	collections::{HashMap, HashSet},
+	ops::AddAssign,
+};
+
+use chrono::Utc;
+use rspc::ErrorCode;
+
+pub mod old_tag_rules_job;
+
+tag_rule::include!(tag_rule_with_tag { tag: select { id pub_id } });
+object::select!(object_ids { id pub_id });
+
+/// How many objects the rules tagged and untagged.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct RuleOutcome {
+	pub applied: u32,
+	pub removed: u32,
+}
+
+impl AddAssign for RuleOutcome {
+	fn add_assign(&mut self, other: Self) {
+		self.applied += other.applied;
+		self.removed += other.removed;
+	}
+}
+
+/// The filters of a rule, stored as JSON like the ones of the saved searches.
+pub fn parse_filters(filters: &str) -> Result<Vec<SearchFilterArgs>, rspc::Error> {
+	serde_json::from_str(filters).map_err(|e| {
+		rspc::Error::with_cause(
+			ErrorCode::InternalServerError,
+			"Failed to parse the filters of the tag rule".to_string(),
+			e,
+		)
+	})
+}
+
+fn in_location(location_id: location::id::Type) -> object::WhereParam {
+	object::file_paths::some(vec![file_path::location_id::equals(Some(location_id))])
+}
+
+/// Objects evaluated at once, so the objects of a big location aren't all loaded in memory
+const BATCH_SIZE: i64 = 1000;
+
+async fn rule_params(
+	db: &PrismaClient,
+	rule: &tag_rule_with_tag::Data,
+) -> Result<Vec<object::WhereParam>, rspc::Error> {
+	let mut params = vec![];
+	for filter in parse_filters(&rule.filters)? {
+		params.extend(filter.into_object_params(db).await?);
+	}
+
+	Ok(params)
+}
+
+/// Tags the objects of the location matching the `rule`, and untags the ones it tagged before
+/// which stopped matching, recording what it did. The objects are evaluated in batches.
+pub async fn apply_rule(
+	library: &Library,
+	rule: &tag_rule_with_tag::Data,
+	location_id: location::id::Type,
+) -> Result<RuleOutcome, rspc::Error> {
+	let params = rule_params(&library.db, rule).await?;
+
+	let mut outcome = RuleOutcome::default();
+	let mut last_id = None;
+
+	loop {
+		let ids = library
+			.db
+			.object()
+			.find_many(chain_optional_iter(
+				[in_location(location_id)],
+				[last_id.map(object::id::gt)],
+			))
+			.order_by(object::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(object::select!({ id }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|object| object.id)
+			.collect::<Vec<_>>();
+
+		let Some(&last) = ids.last() else {
+			break;
+		};
+		let is_last = (ids.len() as i64) < BATCH_SIZE;
+		last_id = Some(last);
+
+		outcome += apply_rule_to(library, rule, &params, ids).await?;
+
+		if is_last {
+			break;
+		}
+	}
+
+	Ok(outcome)
+}
+
+/// Evaluates the enabled rules on the given objects only, for the ones the watcher and the file
+/// identifier just created or changed, refreshing the tags shown if any changed.
+pub async fn apply_rules_to_objects(
+	library: &Library,
+	object_ids: &[object::id::Type],
+) -> Result<RuleOutcome, rspc::Error> {
+	let mut outcome = RuleOutcome::default();
+
+	if object_ids.is_empty() {
+		return Ok(outcome);
+	}
+
+	for rule in enabled_rules(library, None).await? {
+		let params = rule_params(&library.db, &rule).await?;
+
+		for batch in object_ids.chunks(BATCH_SIZE as usize) {
+			outcome += apply_rule_to(library, &rule, &params, batch.to_vec()).await?;
+		}
+	}
+
+	if outcome.applied > 0 || outcome.removed > 0 {
+		invalidate_tag_queries(library);
+	}
+
+	Ok(outcome)
+}
+
+/// Applies the `rule` to the objects with the given ids.
+async fn apply_rule_to(
+	library: &Library,
+	rule: &tag_rule_with_tag::Data,
+	params: &[object::WhereParam],
+	ids: Vec<object::id::Type>,
+) -> Result<RuleOutcome, rspc::Error> {
+	let Library { db, sync, .. } = library;
+
+	let mut params = params.to_vec();
+	params.push(object::id::in_vec(ids.clone()));
+
+	let (matching, tagged) = db
+		._batch((
+			db.object().find_many(params).select(object_ids::select()),
+			db.object()
+				.find_many(vec![
+					object::id::in_vec(ids),
+					object::tags::some(vec![tag_on_object::tag_id::equals(rule.tag_id)]),
+				])
+				.select(object_ids::select()),
+		))
+		.await?;
+
+	let matching_ids = matching.iter().map(|o| o.id).collect::<HashSet<_>>();
+	let tagged_ids = tagged.iter().map(|o| o.id).collect::<HashSet<_>>();
+
+	let to_apply = matching
+		.into_iter()
+		.filter(|o| !tagged_ids.contains(&o.id))
+		.collect::<Vec<_>>();
+
+	let unmatched = tagged
+		.into_iter()
+		.filter(|o| !matching_ids.contains(&o.id))
+		.collect::<Vec<_>>();
+
+	// Only the tags the rule applied itself are removed, not the ones the user did
+	let to_remove = if rule.remove_unmatched && !unmatched.is_empty() {
+		let applied_by_rule = db
+			.tag_rule_action()
+			.find_many(vec![
+				tag_rule_action::rule_id::equals(rule.id),
+				tag_rule_action::object_id::in_vec(unmatched.iter().map(|o| o.id).collect()),
+			])
+			.order_by(tag_rule_action::id::order(SortOrder::Asc))
+			.select(tag_rule_action::select!({ object_id applied }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|action| (action.object_id, action.applied))
+			.collect::<HashMap<_, _>>();
+
+		unmatched
+			.into_iter()
+			.filter(|o| applied_by_rule.get(&o.id).copied().unwrap_or_default())
+			.collect()
+	} else {
+		vec![]
+	};
+
+	if to_apply.is_empty() && to_remove.is_empty() {
+		return Ok(RuleOutcome::default());
+	}
+
+	let sync_id = |pub_id: &[u8]| prisma_sync::tag_on_object::SyncId {
+		tag: prisma_sync::tag::SyncId {
+			pub_id: rule.tag.pub_id.clone(),
+		},
+		object: prisma_sync::object::SyncId {
+			pub_id: pub_id.to_vec(),
+		},
+	};
+
+	if !to_apply.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				to_apply
+					.iter()
+					.flat_map(|o| sync.relation_create(sync_id(&o.pub_id), []))
+					.collect(),
+				db.tag_on_object()
+					.create_many(
+						to_apply
+							.iter()
+							.map(|o| tag_on_object::CreateUnchecked {
+								tag_id: rule.tag_id,
+								object_id: o.id,
+								_params: vec![tag_on_object::date_created::set(Some(
+									Utc::now().into(),
+								))],
+							})
+							.collect(),
+					)
+					.skip_duplicates(),
+			),
+		)
+		.await?;
+	}
+
+	if !to_remove.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				to_remove
+					.iter()
+					.map(|o| sync.relation_delete(sync_id(&o.pub_id)))
+					.collect(),
+				db.tag_on_object().delete_many(vec![
+					tag_on_object::tag_id::equals(rule.tag_id),
+					tag_on_object::object_id::in_vec(to_remove.iter().map(|o| o.id).collect()),
+				]),
+			),
+		)
+		.await?;
+	}
+
+	let date = Utc::now().into();
+
+	db.tag_rule_action()
+		.create_many(
+			to_apply
+				.iter()
+				.map(|o| (true, o.id))
+				.chain(to_remove.iter().map(|o| (false, o.id)))
+				.map(|(applied, object_id)| tag_rule_action::CreateUnchecked {
+					applied,
+					date,
+					rule_id: rule.id,
+					object_id,
+					_params: vec![],
+				})
+				.collect(),
+		)
+		.exec()
+		.await?;
+
+	Ok(RuleOutcome {
+		applied: to_apply.len() as u32,
+		removed: to_remove.len() as u32,
+	})
+}
+
+/// Refreshes the tags shown on the objects, once rules tagged or untagged some of them.
+pub(crate) fn invalidate_tag_queries(library: &Library) {
+	invalidate_query!(library, "tags.getForObject");
+	invalidate_query!(library, "tags.getWithObjects");
+	invalidate_query!(library, "tags.rules.actions");
+	invalidate_query!(library, "search.objects");
+}
+
+/// The enabled rules of the library, or only the one with `rule_id`.
+pub async fn enabled_rules(
+	library: &Library,
+	rule_id: Option<tag_rule::id::Type>,
+) -> prisma_client_rust::Result<Vec<tag_rule_with_tag::Data>> {
+	library
+		.db
+		.tag_rule()
+		.find_many(chain_optional_iter(
+			[tag_rule::enabled::equals(true)],
+			[rule_id.map(tag_rule::id::equals)],
+		))
+		.include(tag_rule_with_tag::include())
+		.exec()
+		.await
+}
@@ -0,0 +1,115 @@
+use crate::old_job::{
+	CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors, JobRunMetadata,
+	JobStepOutput, StatefulJob, WorkerContext,
+};
+
+use sd_prisma::prisma::{location, tag_rule};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use super::{apply_rule, enabled_rules, invalidate_tag_queries};
+
+/// Evaluates the tag rules on the objects of a location, queued after it's scanned and when a rule
+/// is created or changed.
+#[derive(Serialize, Deserialize, Hash, Debug)]
+pub struct OldTagRulesJobInit {
+	pub location_id: location::id::Type,
+	/// Only evaluates this rule instead of all the enabled ones
+	#[serde(default)]
+	pub rule_id: Option<tag_rule::id::Type>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldTagRulesMetadata {
+	applied: u32,
+	removed: u32,
+}
+
+impl JobRunMetadata for OldTagRulesMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.applied += new_data.applied;
+		self.removed += new_data.removed;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldTagRulesJobInit {
+	type Data = ();
+	type Step = tag_rule::id::Type;
+	type RunMetadata = OldTagRulesMetadata;
+
+	const NAME: &'static str = "tag_rules";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let steps = enabled_rules(&ctx.library, self.rule_id)
+			.await?
+			.into_iter()
+			.map(|rule| rule.id)
+			.collect::<Vec<_>>();
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		// Must fill in the data, otherwise the job will not run
+		*data = Some(());
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, step_number }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(step_number + 1)]);
+
+		// The rule may have been deleted or disabled since the job started
+		let Some(rule) = enabled_rules(&ctx.library, Some(*step)).await?.pop() else {
+			return Ok(None.into());
+		};
+
+		// A rule whose filters can't be evaluated doesn't stop the others
+		match apply_rule(&ctx.library, &rule, self.location_id).await {
+			Ok(outcome) => Ok(OldTagRulesMetadata {
+				applied: outcome.applied,
+				removed: outcome.removed,
+			}
+			.into()),
+			Err(e) => Ok(JobRunErrors(vec![format!(
+				"Failed to evaluate tag rule <id='{step}'>: {e:#?}"
+			)])
+			.into()),
+		}
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		info!(
+			"Evaluated tag rules on location <id='{}'>: {} tags applied and {} removed",
+			init.location_id, run_metadata.applied, run_metadata.removed
+		);
+
+		if run_metadata.applied > 0 || run_metadata.removed > 0 {
+			invalidate_tag_queries(&ctx.library);
+		}
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
@@ -3,15 +3,59 @@ use sd_utils::error::FileIOError;
 
 use std::path::Path;
 
+use serde::{Deserialize, Serialize};
+use specta::Type;
 use thiserror::Error;
 
 pub mod hash;
+pub mod old_location_validator_job;
 pub mod old_validator_job;
 
+/// How the full checksums of the files of a library are computed, stored in the library config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumSettings {
+	/// Checksum the whole contents of the files after each scan of their location, so duplicates
+	/// and synced file paths can be compared by it instead of only by their sampled cas id.
+	///
+	/// Off by default, as it reads every file of the location in full
+	pub in_background: bool,
+}
+
+/// Why the contents of a file didn't match the ones it was indexed with, when validating its location
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MismatchKind {
+	/// The contents changed while the modification date stayed the same, like on bit-rot
+	Corrupted = 0,
+	/// The file was edited without the index catching up, like while its location was offline
+	Modified = 1,
+	/// The file isn't at its path anymore
+	Missing = 2,
+}
+
+impl TryFrom<i32> for MismatchKind {
+	type Error = i32;
+
+	fn try_from(value: i32) -> Result<Self, Self::Error> {
+		match value {
+			0 => Ok(Self::Corrupted),
+			1 => Ok(Self::Modified),
+			2 => Ok(Self::Missing),
+			_ => Err(value),
+		}
+	}
+}
+
 #[derive(Error, Debug)]
 pub enum ValidatorError {
 	#[error("sub path not found: <path='{}'>", .0.display())]
 	SubPathNotFound(Box<Path>),
+	#[error("location isn't available, every file would be missing: <path='{}'>", .0.display())]
+	LocationUnavailable(Box<Path>),
+	#[error("remote locations can't be validated")]
+	RemoteLocation,
 
 	// Internal errors
 	#[error("database error: {0}")]
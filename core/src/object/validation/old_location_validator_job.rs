@@ -0,0 +1,344 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::LocationError,
+	object::cas::generate_cas_id,
+	old_job::{
+		CurrentStep, Job, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobRunMetadata, JobStepOutput, StatefulJob, WorkerContext,
+	},
+	Node,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+use sd_core_prisma_helpers::file_path_for_location_validator;
+
+use sd_prisma::prisma::{file_path, location, validation_mismatch};
+use sd_utils::db::maybe_missing;
+
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::{fs, io, time};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::{hash::file_checksum, MismatchKind, ValidatorError};
+
+const BATCH_SIZE: usize = 100;
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Type, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OldLocationValidatorJobInit {
+	pub location_id: location::id::Type,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldLocationValidatorJobData {
+	location_path: PathBuf,
+	/// The mismatches found by the previous validations are replaced once this one completes
+	date_started: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldLocationValidatorMetadata {
+	validated: u32,
+	mismatches: u32,
+}
+
+impl JobRunMetadata for OldLocationValidatorMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.validated += new_data.validated;
+		self.mismatches += new_data.mismatches;
+	}
+}
+
+// The location validator hashes the files of a location again, comparing them with the cas id and
+// full checksum they were indexed with, to find the ones which were corrupted or edited behind
+// the index back
+#[async_trait::async_trait]
+impl StatefulJob for OldLocationValidatorJobInit {
+	type Data = OldLocationValidatorJobData;
+	type Step = Vec<file_path_for_location_validator::Data>;
+	type RunMetadata = OldLocationValidatorMetadata;
+
+	const NAME: &'static str = "validate";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location = db
+			.location()
+			.find_unique(location::id::equals(init.location_id))
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		if location.remote.is_some() {
+			return Err(ValidatorError::RemoteLocation.into());
+		}
+
+		let location_path = maybe_missing(location.path, "location.path").map(PathBuf::from)?;
+
+		// An unmounted drive would have all of its files reported as missing
+		if fs::metadata(&location_path).await.is_err() {
+			return Err(
+				ValidatorError::LocationUnavailable(location_path.into_boxed_path()).into(),
+			);
+		}
+
+		let steps = db
+			.file_path()
+			.find_many(vec![
+				file_path::location_id::equals(Some(init.location_id)),
+				file_path::is_dir::equals(Some(false)),
+				file_path::cas_id::not(None),
+			])
+			.select(file_path_for_location_validator::select())
+			.exec()
+			.await?
+			.chunks(BATCH_SIZE)
+			.map(<[_]>::to_vec)
+			.collect::<Vec<_>>();
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		*data = Some(OldLocationValidatorJobData {
+			location_path,
+			date_started: Utc::now(),
+		});
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, step_number }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let mut mismatches = vec![];
+		let mut errors = vec![];
+
+		for file_path in step {
+			ctx.checkpoint().await;
+
+			let iso_file_path = IsolatedFilePathData::try_from((init.location_id, file_path))?;
+			let full_path = data.location_path.join(&iso_file_path);
+
+			ctx.progress(vec![JobReportUpdate::Message(
+				full_path.display().to_string(),
+			)]);
+
+			if let Some(size) = file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|bytes| bytes.try_into().ok())
+				.map(u64::from_be_bytes)
+			{
+				ctx.limiter.throttle_io(size).await;
+			}
+
+			match validate_file(&full_path, file_path).await {
+				Ok(None) => {}
+				Ok(Some((kind, actual_cas_id))) => {
+					mismatches.push(validation_mismatch::create_unchecked(
+						kind as i32,
+						file_path.id,
+						iso_file_path.to_string(),
+						maybe_missing(&file_path.cas_id, "file_path.cas_id")?.clone(),
+						Utc::now().into(),
+						init.location_id,
+						vec![validation_mismatch::actual_cas_id::set(actual_cas_id)],
+					));
+				}
+				Err(e) => errors.push(format!("Failed to validate '{}': {e}", full_path.display())),
+			}
+		}
+
+		let metadata = OldLocationValidatorMetadata {
+			validated: step.len() as u32,
+			mismatches: mismatches.len() as u32,
+		};
+
+		if !mismatches.is_empty() {
+			db.validation_mismatch()
+				.create_many(mismatches)
+				.exec()
+				.await?;
+		}
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(step_number + 1)]);
+
+		Ok((metadata, JobRunErrors(errors)).into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		db._batch((
+			db.validation_mismatch().delete_many(vec![
+				validation_mismatch::location_id::equals(init.location_id),
+				validation_mismatch::date_detected::lt(data.date_started.into()),
+			]),
+			db.location().update(
+				location::id::equals(init.location_id),
+				vec![location::date_validated::set(Some(
+					data.date_started.into(),
+				))],
+			),
+		))
+		.await?;
+
+		info!(
+			"Validated {} files of location <id='{}'>, {} didn't match their index",
+			run_metadata.validated, init.location_id, run_metadata.mismatches
+		);
+
+		invalidate_query!(ctx.library, "locations.validationReport");
+
+		Ok(Some(json!({
+			"init": init,
+			"validated": run_metadata.validated,
+			"mismatches": run_metadata.mismatches,
+		})))
+	}
+}
+
+/// Hashes the file again, returning why it doesn't match the contents it was indexed with, along
+/// with its new cas id, if it doesn't.
+async fn validate_file(
+	full_path: &Path,
+	file_path: &file_path_for_location_validator::Data,
+) -> Result<Option<(MismatchKind, Option<String>)>, io::Error> {
+	let metadata = match fs::metadata(full_path).await {
+		Ok(metadata) => metadata,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			return Ok(Some((MismatchKind::Missing, None)))
+		}
+		Err(e) => return Err(e),
+	};
+
+	let cas_id = generate_cas_id(full_path, metadata.len()).await?;
+
+	// The cas id only hashes samples of the big files, so their full checksum is compared too
+	// when it was computed
+	let matches = file_path.cas_id.as_ref() == Some(&cas_id)
+		&& match &file_path.integrity_checksum {
+			Some(checksum) => file_checksum(full_path).await? == *checksum,
+			None => true,
+		};
+
+	if matches {
+		return Ok(None);
+	}
+
+	let modified = DateTime::<Utc>::from(metadata.modified()?);
+	let was_edited = file_path.date_modified.map_or(true, |date_modified| {
+		date_modified.timestamp() != modified.timestamp()
+	});
+
+	Ok(Some((
+		if was_edited {
+			MismatchKind::Modified
+		} else {
+			MismatchKind::Corrupted
+		},
+		Some(cas_id),
+	)))
+}
+
+/// Starts the validation of the locations of the library which are due following their interval,
+/// periodically, until the library is unloaded.
+pub(crate) async fn validate_periodically(node: Arc<Node>, library_id: Uuid) {
+	let mut interval = time::interval(SCHEDULE_CHECK_INTERVAL);
+
+	loop {
+		interval.tick().await;
+
+		let Some(library) = node.libraries.get_library(&library_id).await else {
+			break;
+		};
+
+		if let Err(e) = validate_due_locations(&node, &library).await {
+			warn!("Failed to schedule the validations of library <id='{library_id}'>: {e:#?}");
+		}
+	}
+}
+
+async fn validate_due_locations(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+) -> Result<(), prisma_client_rust::QueryError> {
+	let now = Utc::now();
+
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![
+			location::validation_interval_days::not(None),
+			location::instance_id::equals(Some(library.config().await.instance_id)),
+			location::remote::equals(None),
+		])
+		.select(location::select!({ id validation_interval_days date_validated }))
+		.exec()
+		.await?;
+
+	for location in locations {
+		let Some(interval_days) = location.validation_interval_days else {
+			continue;
+		};
+
+		let is_due = location.date_validated.map_or(true, |date_validated| {
+			now - date_validated.with_timezone(&Utc)
+				>= ChronoDuration::days(i64::from(interval_days))
+		});
+
+		if is_due {
+			// Fails while the location is still being validated, it's tried again on the next check
+			if let Err(e) = Job::new(OldLocationValidatorJobInit {
+				location_id: location.id,
+			})
+			.spawn(node, library)
+			.await
+			{
+				debug!(
+					"Failed to start the scheduled validation of location <id='{}'>: {e:#?}",
+					location.id
+				);
+			}
+		}
+	}
+
+	Ok(())
+}
@@ -1,7 +1,8 @@
 use crate::{
 	library::Library,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobStepOutput, StatefulJob, WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobItemProgress, JobReportUpdate, JobResult,
+		JobStepOutput, StatefulJob, WorkerContext,
 	},
 };
 
@@ -155,7 +156,20 @@ impl StatefulJob for OldObjectValidatorJobInit {
 			))?);
 			let checksum = file_checksum(&full_path)
 				.await
-				.map_err(|e| ValidatorError::FileIO(FileIOError::from((full_path, e))))?;
+				.map_err(|e| ValidatorError::FileIO(FileIOError::from((&full_path, e))))?;
+
+			let size = file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|bytes| bytes.try_into().ok())
+				.map(u64::from_be_bytes);
+
+			let mut item_progress = JobItemProgress::new(full_path.display().to_string());
+			if let Some(size) = size {
+				ctx.limiter.throttle_io(size).await;
+				item_progress = item_progress.with_bytes(size, size);
+			}
+			ctx.progress(vec![JobReportUpdate::Item(item_progress)]);
 
 			sync.write_op(
 				db,
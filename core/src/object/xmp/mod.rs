@@ -0,0 +1,735 @@
+//! XMP metadata, kept in sidecar files next to the files by Lightroom, darktable and digiKam.
+//!
+//! Only the properties Spacedrive has values for are written, the rest of an existing sidecar,
+//! like the develop settings of darktable, being kept as is.
+
+use crate::{
+	library::Library,
+	object::tag::{find_or_create_tags, IMPORTED_TAG_COLOR},
+};
+
+use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData, IsolatedFilePathDataParts};
+
+use sd_prisma::{
+	prisma::{file_path, object, tag_on_object},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{error::FileIOError, msgpack};
+
+use std::{
+	collections::HashSet,
+	ffi::OsString,
+	path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+
+use quick_xml::{
+	escape::escape,
+	events::{BytesStart, BytesText, Event},
+	Reader, Writer,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{fs, io::AsyncWriteExt};
+
+pub mod old_xmp_exporter_job;
+pub mod old_xmp_importer_job;
+
+pub const XMP_EXTENSION: &str = "xmp";
+
+const NAMESPACES: [(&str, &str); 3] = [
+	("xmlns:dc", "http://purl.org/dc/elements/1.1/"),
+	("xmlns:xmp", "http://ns.adobe.com/xap/1.0/"),
+	("xmlns:lr", "http://ns.adobe.com/lightroom/1.0/"),
+];
+
+const DESCRIPTION: &str = "rdf:Description";
+const ITEM: &str = "rdf:li";
+const SUBJECT: &str = "dc:subject";
+const HIERARCHICAL_SUBJECT: &str = "lr:hierarchicalSubject";
+const RATING: &str = "xmp:Rating";
+const NOTE: &str = "dc:description";
+
+/// Parent of the labels in the hierarchical keywords, telling them apart from the tags
+pub const LABELS_KEYWORD: &str = "Labels";
+
+const EMPTY_PACKET: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\" x:xmptk=\"Spacedrive\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\"/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>
+";
+
+#[derive(Error, Debug)]
+pub enum XmpError {
+	#[error("sub path not found: <path='{}'>", .0.display())]
+	SubPathNotFound(Box<Path>),
+	#[error("invalid XMP: {0}")]
+	Xml(#[from] quick_xml::Error),
+	#[error("invalid XMP: missing '{DESCRIPTION}' element")]
+	MissingDescription,
+
+	// Internal errors
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	FilePath(#[from] FilePathError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+impl From<quick_xml::events::attributes::AttrError> for XmpError {
+	fn from(e: quick_xml::events::attributes::AttrError) -> Self {
+		Self::Xml(e.into())
+	}
+}
+
+/// How sidecars are named, as apps look for them in different places.
+#[derive(Serialize, Deserialize, Type, Hash, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum XmpSidecarNaming {
+	/// `photo.jpg.xmp`, used by darktable and digiKam
+	#[default]
+	AppendExtension,
+	/// `photo.xmp`, used by Lightroom and Capture One
+	ReplaceExtension,
+}
+
+impl XmpSidecarNaming {
+	pub fn sidecar_path(self, path: impl AsRef<Path>) -> PathBuf {
+		let path = path.as_ref();
+
+		match self {
+			Self::AppendExtension => {
+				let mut sidecar_path = path.as_os_str().to_owned();
+				sidecar_path.push(".xmp");
+				sidecar_path.into()
+			}
+			Self::ReplaceExtension => path.with_extension("xmp"),
+		}
+	}
+}
+
+/// Writes a sidecar to a hidden temporary file next to it, then renames it over the sidecar, so
+/// apps never read a half written one and a crash leaves the previous one intact.
+pub async fn write_sidecar(path: impl AsRef<Path>, contents: &[u8]) -> Result<(), FileIOError> {
+	let path = path.as_ref();
+
+	let mut temp_name = OsString::from(".");
+	temp_name.push(path.file_name().unwrap_or_default());
+	temp_name.push(".tmp");
+	let temp_path = path.with_file_name(temp_name);
+
+	let written = async {
+		let mut file = fs::File::create(&temp_path).await?;
+		file.write_all(contents).await?;
+		file.sync_all().await?;
+		drop(file);
+
+		fs::rename(&temp_path, path).await
+	}
+	.await;
+
+	if let Err(e) = written {
+		// Best effort, the temporary file is overwritten by the next write anyway
+		fs::remove_file(&temp_path).await.ok();
+
+		return Err(FileIOError::from((path, e)));
+	}
+
+	Ok(())
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+pub struct XmpMetadata {
+	pub tags: Vec<String>,
+	pub labels: Vec<String>,
+	/// From 1 to 5 stars
+	pub rating: Option<i32>,
+	pub note: Option<String>,
+}
+
+impl XmpMetadata {
+	pub fn is_empty(&self) -> bool {
+		self.tags.is_empty()
+			&& self.labels.is_empty()
+			&& self.rating.is_none()
+			&& self.note.is_none()
+	}
+
+	/// Properties replaced when writing the metadata
+	fn replaced_properties(&self) -> Vec<&'static str> {
+		let mut properties = vec![];
+		if !self.tags.is_empty() || !self.labels.is_empty() {
+			properties.extend([SUBJECT, HIERARCHICAL_SUBJECT]);
+		}
+		if self.rating.is_some() {
+			properties.push(RATING);
+		}
+		if self.note.is_some() {
+			properties.push(NOTE);
+		}
+
+		properties
+	}
+
+	fn to_properties_xml(&self) -> String {
+		let mut xml = String::new();
+
+		if !self.tags.is_empty() || !self.labels.is_empty() {
+			xml.push_str(&bag_xml(SUBJECT, self.tags.iter().chain(&self.labels)));
+			xml.push_str(&bag_xml(
+				HIERARCHICAL_SUBJECT,
+				self.tags.iter().cloned().chain(
+					self.labels
+						.iter()
+						.map(|label| format!("{LABELS_KEYWORD}|{label}")),
+				),
+			));
+		}
+
+		if let Some(rating) = self.rating {
+			xml.push_str(&format!("\n   <{RATING}>{rating}</{RATING}>"));
+		}
+
+		if let Some(note) = &self.note {
+			xml.push_str(&format!(
+				"\n   <{NOTE}>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\
+				\n    </rdf:Alt>\n   </{NOTE}>",
+				escape(note)
+			));
+		}
+
+		xml
+	}
+}
+
+fn bag_xml(property: &str, items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+	let items = items
+		.into_iter()
+		.map(|item| format!("\n     <rdf:li>{}</rdf:li>", escape(item.as_ref())))
+		.collect::<String>();
+
+	format!("\n   <{property}>\n    <rdf:Bag>{items}\n    </rdf:Bag>\n   </{property}>")
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WriteState {
+	BeforeDescription,
+	/// Inside the first description, at the given depth
+	InDescription(usize),
+	/// Inside a replaced property of the description, at the given depth
+	Skipping(usize),
+	AfterDescription,
+}
+
+/// Writes the metadata into the `existing` XMP packet, or into a new one.
+pub fn write_metadata(existing: Option<&str>, metadata: &XmpMetadata) -> Result<Vec<u8>, XmpError> {
+	let replaced = metadata.replaced_properties();
+	let is_replaced = |name: &[u8]| replaced.iter().any(|property| property.as_bytes() == name);
+
+	let mut reader = Reader::from_str(existing.unwrap_or(EMPTY_PACKET));
+	let mut writer = Writer::new(Vec::new());
+
+	let mut state = WriteState::BeforeDescription;
+	// Indentation before a child of the description, dropped along with replaced properties
+	let mut pending_space = None;
+
+	loop {
+		let event = reader.read_event()?;
+
+		if let WriteState::InDescription(0) = state {
+			match &event {
+				Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {
+					pending_space = Some(text.clone().into_owned());
+					continue;
+				}
+				Event::Start(e) if is_replaced(e.name().as_ref()) => {
+					pending_space = None;
+					state = WriteState::Skipping(1);
+					continue;
+				}
+				Event::Empty(e) if is_replaced(e.name().as_ref()) => {
+					pending_space = None;
+					continue;
+				}
+				Event::End(_) => {
+					pending_space = None;
+					writer.write_event(Event::Text(BytesText::from_escaped(format!(
+						"{}\n  ",
+						metadata.to_properties_xml()
+					))))?;
+				}
+				_ => {
+					if let Some(space) = pending_space.take() {
+						writer.write_event(Event::Text(space))?;
+					}
+				}
+			}
+		}
+
+		match (state, event) {
+			(_, Event::Eof) => break,
+
+			(WriteState::BeforeDescription, Event::Start(e))
+				if e.name().as_ref() == DESCRIPTION.as_bytes() =>
+			{
+				writer.write_event(Event::Start(description_start(&e, is_replaced)?))?;
+				state = WriteState::InDescription(0);
+			}
+
+			(WriteState::BeforeDescription, Event::Empty(e))
+				if e.name().as_ref() == DESCRIPTION.as_bytes() =>
+			{
+				let start = description_start(&e, is_replaced)?;
+				let end = start.to_end().into_owned();
+
+				writer.write_event(Event::Start(start))?;
+				writer.write_event(Event::Text(BytesText::from_escaped(format!(
+					"{}\n  ",
+					metadata.to_properties_xml()
+				))))?;
+				writer.write_event(Event::End(end))?;
+				state = WriteState::AfterDescription;
+			}
+
+			(WriteState::InDescription(depth), event @ Event::Start(_)) => {
+				writer.write_event(event)?;
+				state = WriteState::InDescription(depth + 1);
+			}
+
+			(WriteState::InDescription(depth), event @ Event::End(_)) => {
+				writer.write_event(event)?;
+				state = match depth {
+					0 => WriteState::AfterDescription,
+					depth => WriteState::InDescription(depth - 1),
+				};
+			}
+
+			(WriteState::Skipping(depth), Event::Start(_)) => {
+				state = WriteState::Skipping(depth + 1);
+			}
+
+			(WriteState::Skipping(depth), Event::End(_)) => {
+				state = match depth {
+					1 => WriteState::InDescription(0),
+					depth => WriteState::Skipping(depth - 1),
+				};
+			}
+
+			(WriteState::Skipping(_), _) => {}
+
+			(_, event) => writer.write_event(event)?,
+		}
+	}
+
+	if state != WriteState::AfterDescription {
+		return Err(XmpError::MissingDescription);
+	}
+
+	Ok(writer.into_inner())
+}
+
+/// Reads the metadata of an XMP packet, with the properties as elements or attributes.
+pub fn parse_metadata(xmp: &str) -> Result<XmpMetadata, XmpError> {
+	let mut reader = Reader::from_str(xmp);
+
+	let mut parents = Vec::<Vec<u8>>::new();
+	let (mut subjects, mut hierarchical_subjects) = (vec![], vec![]);
+	let mut rating = None;
+	let (mut note, mut note_is_default) = (None, false);
+	let mut item_is_default = false;
+
+	loop {
+		match reader.read_event()? {
+			Event::Eof => break,
+
+			Event::Start(e) => {
+				rating = attribute_rating(&e)?.or(rating);
+				if e.name().as_ref() == ITEM.as_bytes() {
+					item_is_default = e
+						.try_get_attribute("xml:lang")?
+						.map_or(true, |lang| lang.value.as_ref() == b"x-default");
+				}
+				parents.push(e.name().as_ref().to_vec());
+			}
+
+			Event::Empty(e) => rating = attribute_rating(&e)?.or(rating),
+
+			Event::End(_) => {
+				parents.pop();
+			}
+
+			Event::Text(text) => {
+				let text = text.unescape()?;
+				let text = text.trim();
+				if text.is_empty() {
+					continue;
+				}
+
+				let in_item_of = |property: &str| {
+					matches!(
+						parents.as_slice(),
+						[.., parent, _, item]
+							if parent.as_slice() == property.as_bytes()
+								&& item.as_slice() == ITEM.as_bytes()
+					)
+				};
+
+				if in_item_of(SUBJECT) {
+					subjects.push(text.to_string());
+				} else if in_item_of(HIERARCHICAL_SUBJECT) {
+					hierarchical_subjects.push(text.to_string());
+				} else if in_item_of(NOTE) {
+					// The default language is preferred over the others
+					if note.is_none() || (item_is_default && !note_is_default) {
+						note = Some(text.to_string());
+						note_is_default = item_is_default;
+					}
+				} else if parents
+					.last()
+					.is_some_and(|parent| parent.as_slice() == RATING.as_bytes())
+				{
+					rating = parse_rating(text).or(rating);
+				}
+			}
+
+			_ => {}
+		}
+	}
+
+	let labels = hierarchical_subjects
+		.iter()
+		.filter_map(|subject| subject.strip_prefix(LABELS_KEYWORD)?.strip_prefix('|'))
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+
+	Ok(XmpMetadata {
+		tags: subjects
+			.into_iter()
+			.filter(|subject| !labels.contains(subject))
+			.collect(),
+		labels,
+		rating,
+		note,
+	})
+}
+
+/// The rating written as an attribute of the description, as darktable does.
+fn attribute_rating(e: &BytesStart<'_>) -> Result<Option<i32>, XmpError> {
+	if e.name().as_ref() != DESCRIPTION.as_bytes() {
+		return Ok(None);
+	}
+
+	Ok(e.try_get_attribute(RATING)?
+		.map(|attr| attr.unescape_value())
+		.transpose()?
+		.and_then(|rating| parse_rating(&rating)))
+}
+
+/// Only ratings with stars, as `0` is no rating and `-1` a rejected file.
+fn parse_rating(rating: &str) -> Option<i32> {
+	rating
+		.trim()
+		.parse::<f32>()
+		.ok()
+		.map(|rating| rating.round() as i32)
+		.filter(|rating| (1..=5).contains(rating))
+}
+
+/// Imports the tags, rating and note of a sidecar onto the object of its file, returning if
+/// anything changed.
+///
+/// The rating and note already in the library are only replaced with `overwrite`, for sidecars
+/// changed after them.
+pub async fn import_sidecar(
+	library: &Library,
+	sidecar: &IsolatedFilePathData<'_>,
+	full_path: impl AsRef<Path>,
+	overwrite: bool,
+) -> Result<bool, XmpError> {
+	let full_path = full_path.as_ref();
+	let Library { db, sync, .. } = library;
+
+	let xmp = fs::read_to_string(full_path)
+		.await
+		.map_err(|e| FileIOError::from((full_path, e)))?;
+
+	let metadata = parse_metadata(&xmp)?;
+	// Labels come from Spacedrive itself, so they're never imported
+	if metadata.tags.is_empty() && metadata.rating.is_none() && metadata.note.is_none() {
+		return Ok(false);
+	}
+
+	let IsolatedFilePathDataParts {
+		location_id,
+		materialized_path,
+		name,
+		..
+	} = sidecar.to_parts();
+
+	// `photo.jpg.xmp`, then `photo.xmp`
+	let candidates = name
+		.rsplit_once('.')
+		.map(|(name, extension)| (name, Some(extension)))
+		.into_iter()
+		.chain([(name, None)]);
+
+	let mut object = None;
+	for (name, extension) in candidates {
+		object = db
+			.file_path()
+			.find_first(sd_utils::chain_optional_iter(
+				[
+					file_path::location_id::equals(Some(location_id)),
+					file_path::materialized_path::equals(Some(materialized_path.to_string())),
+					file_path::name::equals(Some(name.to_string())),
+					file_path::is_dir::equals(Some(false)),
+					file_path::extension::not(Some(XMP_EXTENSION.to_string())),
+				],
+				[extension
+					.map(|extension| file_path::extension::equals(Some(extension.to_string())))],
+			))
+			.select(file_path::select!({ object: select { id pub_id rating note } }))
+			.exec()
+			.await?
+			.and_then(|file_path| file_path.object);
+
+		if object.is_some() {
+			break;
+		}
+	}
+
+	// The file isn't identified yet, the sidecar being imported after it is
+	let Some(object) = object else {
+		return Ok(false);
+	};
+
+	let sync_id = prisma_sync::object::SyncId {
+		pub_id: object.pub_id.clone(),
+	};
+
+	let (mut sync_params, mut db_params) = (vec![], vec![]);
+
+	if let Some(rating) = metadata
+		.rating
+		.filter(|rating| object.rating != Some(*rating) && (overwrite || object.rating.is_none()))
+	{
+		sync_params.push(sync.shared_update(
+			sync_id.clone(),
+			object::rating::NAME,
+			msgpack!(rating),
+		));
+		db_params.push(object::rating::set(Some(rating)));
+	}
+
+	if let Some(note) = metadata
+		.note
+		.filter(|note| object.note.as_ref() != Some(note) && (overwrite || object.note.is_none()))
+	{
+		sync_params.push(sync.shared_update(sync_id.clone(), object::note::NAME, msgpack!(&note)));
+		db_params.push(object::note::set(Some(note)));
+	}
+
+	let object_changed = !db_params.is_empty();
+	if object_changed {
+		sync.write_ops(
+			db,
+			(
+				sync_params,
+				db.object().update(object::id::equals(object.id), db_params),
+			),
+		)
+		.await?;
+	}
+
+	let tags = find_or_create_tags(
+		library,
+		metadata.tags.into_iter().collect(),
+		IMPORTED_TAG_COLOR,
+	)
+	.await?;
+
+	let linked_tags = db
+		.tag_on_object()
+		.find_many(vec![
+			tag_on_object::object_id::equals(object.id),
+			tag_on_object::tag_id::in_vec(tags.values().map(|tag| tag.id).collect()),
+		])
+		.select(tag_on_object::select!({ tag_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|tag_on_object| tag_on_object.tag_id)
+		.collect::<HashSet<_>>();
+
+	let new_tags = tags
+		.into_values()
+		.filter(|tag| !linked_tags.contains(&tag.id))
+		.collect::<Vec<_>>();
+
+	if !new_tags.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				new_tags
+					.iter()
+					.flat_map(|tag| {
+						sync.relation_create(
+							prisma_sync::tag_on_object::SyncId {
+								tag: prisma_sync::tag::SyncId {
+									pub_id: tag.pub_id.clone(),
+								},
+								object: sync_id.clone(),
+							},
+							[],
+						)
+					})
+					.collect(),
+				db.tag_on_object().create_many(
+					new_tags
+						.iter()
+						.map(|tag| tag_on_object::CreateUnchecked {
+							tag_id: tag.id,
+							object_id: object.id,
+							_params: vec![tag_on_object::date_created::set(Some(
+								Utc::now().into(),
+							))],
+						})
+						.collect(),
+				),
+			),
+		)
+		.await?;
+	}
+
+	Ok(object_changed || !new_tags.is_empty())
+}
+
+/// The start of the description, without the replaced properties written as attributes, like
+/// darktable does with `xmp:Rating`, and declaring the namespaces of the written ones.
+fn description_start(
+	e: &BytesStart<'_>,
+	is_replaced: impl Fn(&[u8]) -> bool,
+) -> Result<BytesStart<'static>, XmpError> {
+	let mut start = BytesStart::new(DESCRIPTION);
+
+	for attr in e.attributes() {
+		let attr = attr?;
+		if !is_replaced(attr.key.as_ref()) {
+			start.push_attribute(attr);
+		}
+	}
+
+	for (key, namespace) in NAMESPACES {
+		if start.try_get_attribute(key)?.is_none() {
+			start.push_attribute((key, namespace));
+		}
+	}
+
+	Ok(start)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_unrelated_properties() {
+		let existing = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmlns:darktable="http://darktable.sf.net/" xmp:Rating="1" darktable:xmp_version="5">
+   <darktable:history>
+    <rdf:Seq>
+     <rdf:li darktable:operation="exposure"/>
+    </rdf:Seq>
+   </darktable:history>
+   <dc:subject xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <rdf:Bag>
+     <rdf:li>old</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+		let metadata = XmpMetadata {
+			tags: vec!["Beach & Sun".to_string()],
+			labels: vec!["dog".to_string()],
+			rating: Some(4),
+			note: None,
+		};
+
+		let written =
+			String::from_utf8(write_metadata(Some(existing), &metadata).unwrap()).unwrap();
+
+		assert!(written.contains(r#"darktable:operation="exposure""#));
+		assert!(written.contains(r#"darktable:xmp_version="5""#));
+		assert!(!written.contains(r#"xmp:Rating="1""#));
+		assert!(!written.contains("<rdf:li>old</rdf:li>"));
+		assert!(written.contains("<xmp:Rating>4</xmp:Rating>"));
+		assert!(written.contains("<rdf:li>Beach &amp; Sun</rdf:li>"));
+		assert!(written.contains("<rdf:li>Labels|dog</rdf:li>"));
+
+		// Writing again gives the same sidecar
+		assert_eq!(
+			String::from_utf8(write_metadata(Some(&written), &metadata).unwrap()).unwrap(),
+			written
+		);
+	}
+
+	#[test]
+	fn parses_metadata() {
+		let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmp:Rating="3">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>Beach &amp; Sun</rdf:li>
+     <rdf:li>dog</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+   <lr:hierarchicalSubject>
+    <rdf:Bag>
+     <rdf:li>Labels|dog</rdf:li>
+    </rdf:Bag>
+   </lr:hierarchicalSubject>
+   <dc:description>
+    <rdf:Alt>
+     <rdf:li xml:lang="fr-FR">Plage</rdf:li>
+     <rdf:li xml:lang="x-default">Beach</rdf:li>
+    </rdf:Alt>
+   </dc:description>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+
+		assert_eq!(
+			parse_metadata(xmp).unwrap(),
+			XmpMetadata {
+				tags: vec!["Beach & Sun".to_string()],
+				labels: vec!["dog".to_string()],
+				rating: Some(3),
+				note: Some("Beach".to_string()),
+			}
+		);
+	}
+
+	#[test]
+	fn names_sidecars() {
+		assert_eq!(
+			XmpSidecarNaming::AppendExtension.sidecar_path("/photos/a.jpg"),
+			PathBuf::from("/photos/a.jpg.xmp")
+		);
+		assert_eq!(
+			XmpSidecarNaming::ReplaceExtension.sidecar_path("/photos/a.jpg"),
+			PathBuf::from("/photos/a.xmp")
+		);
+	}
+}
@@ -0,0 +1,250 @@
+use crate::{
+	library::Library,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_core_file_path_helper::{
+	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
+	IsolatedFilePathData,
+};
+use sd_core_prisma_helpers::file_path_with_object;
+
+use sd_prisma::prisma::{file_path, label_on_object, location, object, tag_on_object};
+use sd_utils::{db::maybe_missing, error::FileIOError};
+
+use std::{
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+};
+
+use prisma_client_rust::or;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{fs, io};
+use tracing::info;
+
+use super::{
+	write_metadata, write_sidecar, XmpError, XmpMetadata, XmpSidecarNaming, XMP_EXTENSION,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldXmpExporterJobData {
+	pub location_path: PathBuf,
+}
+
+/// Writes the tags, labels, rating and note of the files to XMP sidecars, leaving the files
+/// themselves untouched.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldXmpExporterJobInit {
+	pub location: location::Data,
+	pub sub_path: Option<PathBuf>,
+	#[serde(default)]
+	pub naming: XmpSidecarNaming,
+}
+
+impl Hash for OldXmpExporterJobInit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		if let Some(ref sub_path) = self.sub_path {
+			sub_path.hash(state);
+		}
+		self.naming.hash(state);
+	}
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldXmpExporterMetadata {
+	sidecars_written: u32,
+}
+
+impl JobRunMetadata for OldXmpExporterMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.sidecars_written += new_data.sidecars_written;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldXmpExporterJobInit {
+	type Data = OldXmpExporterJobData;
+	type Step = file_path_with_object::Data;
+	type RunMetadata = OldXmpExporterMetadata;
+
+	const NAME: &'static str = "xmp_exporter";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location.id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location_id = init.location.id;
+
+		let location_path =
+			maybe_missing(&init.location.path, "location.path").map(PathBuf::from)?;
+
+		let maybe_sub_iso_file_path = match &init.sub_path {
+			Some(sub_path) if sub_path != Path::new("") => {
+				let full_path = ensure_sub_path_is_in_location(&location_path, sub_path)
+					.await
+					.map_err(XmpError::from)?;
+				ensure_sub_path_is_directory(&location_path, sub_path)
+					.await
+					.map_err(XmpError::from)?;
+
+				let sub_iso_file_path =
+					IsolatedFilePathData::new(location_id, &location_path, &full_path, true)
+						.map_err(XmpError::from)?;
+
+				ensure_file_path_exists(
+					sub_path,
+					&sub_iso_file_path,
+					db,
+					XmpError::SubPathNotFound,
+				)
+				.await?;
+
+				Some(sub_iso_file_path)
+			}
+			_ => None,
+		};
+
+		let steps = db
+			.file_path()
+			.find_many(sd_utils::chain_optional_iter(
+				[
+					file_path::location_id::equals(Some(location_id)),
+					file_path::is_dir::equals(Some(false)),
+					// Not the sidecars themselves, which would get `.xmp.xmp` sidecars
+					file_path::extension::not(Some(XMP_EXTENSION.to_string())),
+					// Only files with some metadata to export
+					file_path::object::is(vec![or![
+						object::tags::some(vec![]),
+						object::labels::some(vec![]),
+						object::rating::not(None),
+						object::note::not(None),
+					]]),
+				],
+				[maybe_sub_iso_file_path.and_then(|iso_sub_path| {
+					iso_sub_path
+						.materialized_path_for_children()
+						.map(file_path::materialized_path::starts_with)
+				})],
+			))
+			.include(file_path_with_object::include())
+			.exec()
+			.await?;
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		*data = Some(OldXmpExporterJobData { location_path });
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: file_path,
+			step_number,
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let Some(object) = &file_path.object else {
+			return Ok(().into());
+		};
+
+		let tags = db
+			.tag_on_object()
+			.find_many(vec![tag_on_object::object_id::equals(object.id)])
+			.select(tag_on_object::select!({ tag: select { name } }))
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|tag_on_object| tag_on_object.tag.name)
+			.collect();
+
+		let labels = db
+			.label_on_object()
+			.find_many(vec![label_on_object::object_id::equals(object.id)])
+			.select(label_on_object::select!({ label: select { name } }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|label_on_object| label_on_object.label.name)
+			.collect();
+
+		let metadata = XmpMetadata {
+			tags,
+			labels,
+			rating: object.rating,
+			note: object.note.clone(),
+		};
+
+		let mut run_metadata = OldXmpExporterMetadata::default();
+
+		if !metadata.is_empty() {
+			let full_path = data.location_path.join(IsolatedFilePathData::try_from((
+				init.location.id,
+				file_path,
+			))?);
+			let sidecar_path = init.naming.sidecar_path(&full_path);
+
+			let existing = match fs::read_to_string(&sidecar_path).await {
+				Ok(existing) => Some(existing),
+				Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+				Err(e) => return Err(FileIOError::from((&sidecar_path, e)).into()),
+			};
+
+			let sidecar = write_metadata(existing.as_deref(), &metadata).map_err(XmpError::from)?;
+
+			// Sidecars already up to date are left alone, keeping their modification date
+			if existing.as_deref().map(str::as_bytes) != Some(sidecar.as_slice()) {
+				write_sidecar(&sidecar_path, &sidecar).await?;
+
+				run_metadata.sidecars_written += 1;
+			}
+		}
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(step_number + 1)]);
+
+		Ok(run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		_: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		info!(
+			"finalizing XMP exporter job at {}{}: {} sidecars written",
+			data.location_path.display(),
+			init.sub_path
+				.as_ref()
+				.map(|p| format!("{}", p.display()))
+				.unwrap_or_default(),
+			run_metadata.sidecars_written
+		);
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
@@ -0,0 +1,202 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobRunMetadata, JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_core_file_path_helper::{
+	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
+	IsolatedFilePathData,
+};
+use sd_core_prisma_helpers::file_path_to_isolate;
+
+use sd_prisma::prisma::{file_path, location};
+use sd_utils::db::maybe_missing;
+
+use std::{
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use super::{import_sidecar, XmpError, XMP_EXTENSION};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldXmpImporterJobData {
+	pub location_path: PathBuf,
+}
+
+/// Imports the tags, ratings and notes of the XMP sidecars in the location, left by Lightroom,
+/// darktable or digiKam, filling the ones missing from the library.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldXmpImporterJobInit {
+	pub location: location::Data,
+	pub sub_path: Option<PathBuf>,
+}
+
+impl Hash for OldXmpImporterJobInit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		if let Some(ref sub_path) = self.sub_path {
+			sub_path.hash(state);
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldXmpImporterMetadata {
+	sidecars_imported: u32,
+}
+
+impl JobRunMetadata for OldXmpImporterMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.sidecars_imported += new_data.sidecars_imported;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldXmpImporterJobInit {
+	type Data = OldXmpImporterJobData;
+	type Step = file_path_to_isolate::Data;
+	type RunMetadata = OldXmpImporterMetadata;
+
+	const NAME: &'static str = "xmp_importer";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location.id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location_id = init.location.id;
+
+		let location_path =
+			maybe_missing(&init.location.path, "location.path").map(PathBuf::from)?;
+
+		let maybe_sub_iso_file_path = match &init.sub_path {
+			Some(sub_path) if sub_path != Path::new("") => {
+				let full_path = ensure_sub_path_is_in_location(&location_path, sub_path)
+					.await
+					.map_err(XmpError::from)?;
+				ensure_sub_path_is_directory(&location_path, sub_path)
+					.await
+					.map_err(XmpError::from)?;
+
+				let sub_iso_file_path =
+					IsolatedFilePathData::new(location_id, &location_path, &full_path, true)
+						.map_err(XmpError::from)?;
+
+				ensure_file_path_exists(
+					sub_path,
+					&sub_iso_file_path,
+					db,
+					XmpError::SubPathNotFound,
+				)
+				.await?;
+
+				Some(sub_iso_file_path)
+			}
+			_ => None,
+		};
+
+		let steps = db
+			.file_path()
+			.find_many(sd_utils::chain_optional_iter(
+				[
+					file_path::location_id::equals(Some(location_id)),
+					file_path::is_dir::equals(Some(false)),
+					file_path::extension::equals(Some(XMP_EXTENSION.to_string())),
+				],
+				[maybe_sub_iso_file_path.and_then(|iso_sub_path| {
+					iso_sub_path
+						.materialized_path_for_children()
+						.map(file_path::materialized_path::starts_with)
+				})],
+			))
+			.select(file_path_to_isolate::select())
+			.exec()
+			.await?;
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		*data = Some(OldXmpImporterJobData { location_path });
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: file_path,
+			step_number,
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let iso_file_path = IsolatedFilePathData::try_from((init.location.id, file_path))?;
+		let full_path = data.location_path.join(&iso_file_path);
+
+		let mut run_metadata = OldXmpImporterMetadata::default();
+		let mut errors = vec![];
+
+		// A broken sidecar doesn't stop the others from being imported
+		match import_sidecar(&ctx.library, &iso_file_path, &full_path, false).await {
+			Ok(true) => run_metadata.sidecars_imported += 1,
+			Ok(false) => {}
+			Err(e) => errors.push(format!(
+				"Failed to import XMP sidecar \"{}\": {e}",
+				full_path.display()
+			)),
+		}
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(step_number + 1)]);
+
+		Ok((run_metadata, JobRunErrors(errors)).into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		info!(
+			"finalizing XMP importer job at {}{}: {} sidecars imported",
+			data.location_path.display(),
+			init.sub_path
+				.as_ref()
+				.map(|p| format!("{}", p.display()))
+				.unwrap_or_default(),
+			run_metadata.sidecars_imported
+		);
+
+		if run_metadata.sidecars_imported > 0 {
+			invalidate_query!(ctx.library, "search.paths");
+			invalidate_query!(ctx.library, "search.objects");
+			invalidate_query!(ctx.library, "tags.list");
+			invalidate_query!(ctx.library, "tags.getWithObjects");
+		}
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
@@ -0,0 +1,90 @@
+use std::{
+	fmt,
+	future::Future,
+	pin::Pin,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex,
+	},
+};
+
+use tracing::{debug, error};
+
+use super::JobError;
+
+type CleanupFuture = Pin<Box<dyn Future<Output = Result<(), JobError>> + Send>>;
+
+/// Identifies a cleanup handler registered with
+/// [`WorkerContext::register_cleanup`](super::WorkerContext::register_cleanup).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobCleanupId(u64);
+
+struct CleanupHandler {
+	id: JobCleanupId,
+	description: String,
+	cleanup: CleanupFuture,
+}
+
+/// Cleanup handlers of a running job, these are only executed if the job gets canceled.
+#[derive(Default)]
+pub(super) struct JobCleanupHandlers {
+	next_id: AtomicU64,
+	handlers: Mutex<Vec<CleanupHandler>>,
+}
+
+impl fmt::Debug for JobCleanupHandlers {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("JobCleanupHandlers").finish()
+	}
+}
+
+impl JobCleanupHandlers {
+	pub(super) fn register(
+		&self,
+		description: String,
+		cleanup: impl Future<Output = Result<(), JobError>> + Send + 'static,
+	) -> JobCleanupId {
+		let id = JobCleanupId(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+		self.handlers
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.push(CleanupHandler {
+				id,
+				description,
+				cleanup: Box::pin(cleanup),
+			});
+
+		id
+	}
+
+	pub(super) fn unregister(&self, id: JobCleanupId) {
+		self.handlers
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.retain(|handler| handler.id != id);
+	}
+
+	/// Runs all registered handlers, the most recent ones first, as later work
+	/// usually depends on earlier work.
+	pub(super) async fn run(&self) {
+		let handlers = std::mem::take(
+			&mut *self
+				.handlers
+				.lock()
+				.unwrap_or_else(|poisoned| poisoned.into_inner()),
+		);
+
+		for CleanupHandler {
+			description,
+			cleanup,
+			..
+		} in handlers.into_iter().rev()
+		{
+			debug!("Running job cleanup: {description}");
+			if let Err(e) = cleanup.await {
+				error!("Failed to run job cleanup '{description}': {e:#?}");
+			}
+		}
+	}
+}
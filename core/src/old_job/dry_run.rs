@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::JobRunMetadata;
+
+/// An action that a destructive operation would take, recorded instead of being executed
+/// when running in dry-run mode.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DryRunAction {
+	Delete { path: PathBuf, is_dir: bool },
+	Erase { path: PathBuf, passes: u32 },
+	Rename { from: PathBuf, to: PathBuf },
+}
+
+/// Every action a dry run would have taken, in the order they would happen.
+///
+/// Destructive jobs take a `dry_run` flag and go through the exact same code path, only
+/// swapping the effects on disk or database for an entry in this report.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct DryRunReport {
+	pub actions: Vec<DryRunAction>,
+}
+
+impl DryRunReport {
+	pub fn push(&mut self, action: DryRunAction) {
+		self.actions.push(action);
+	}
+}
+
+impl From<DryRunAction> for DryRunReport {
+	fn from(action: DryRunAction) -> Self {
+		Self {
+			actions: vec![action],
+		}
+	}
+}
+
+impl FromIterator<DryRunAction> for DryRunReport {
+	fn from_iter<T: IntoIterator<Item = DryRunAction>>(iter: T) -> Self {
+		Self {
+			actions: iter.into_iter().collect(),
+		}
+	}
+}
+
+impl JobRunMetadata for DryRunReport {
+	fn update(&mut self, new_data: Self) {
+		self.actions.extend(new_data.actions);
+	}
+}
@@ -1,8 +1,9 @@
 use crate::{
-	location::{indexer::IndexerError, LocationError},
+	location::{indexer::IndexerError, mirror::MirrorError, LocationError},
 	object::{
 		fs::error::FileSystemJobsError, media::old_media_processor::MediaProcessorError,
-		old_file_identifier::FileIdentifierJobError, validation::ValidatorError,
+		old_file_identifier::FileIdentifierJobError, old_metadata_importer::MetadataImporterError,
+		validation::ValidatorError, xmp::XmpError,
 	},
 };
 
@@ -66,6 +67,12 @@ pub enum JobError {
 	Validator(#[from] ValidatorError),
 	#[error(transparent)]
 	FileSystemJobsError(#[from] FileSystemJobsError),
+	#[error(transparent)]
+	MetadataImporter(#[from] MetadataImporterError),
+	#[error(transparent)]
+	Xmp(#[from] XmpError),
+	#[error(transparent)]
+	Mirror(#[from] MirrorError),
 	// #[error(transparent)]
 	// CryptoError(#[from] CryptoError),
 
@@ -0,0 +1,216 @@
+use crate::{
+	api::{
+		search::{InOrNotIn, Range},
+		utils::Pagination,
+	},
+	library::Library,
+};
+
+use sd_core_prisma_helpers::job_without_data;
+
+use sd_prisma::prisma::{job, location, SortOrder};
+
+use chrono::{DateTime, Duration, Utc};
+use prisma_client_rust::operator::or;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{JobError, JobReport, JobStatus};
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum JobHistoryFilterArgs {
+	Name(InOrNotIn<String>),
+	Status(InOrNotIn<JobStatus>),
+	Location(InOrNotIn<location::id::Type>),
+	CreatedAt(Range<DateTime<Utc>>),
+}
+
+impl JobHistoryFilterArgs {
+	pub fn into_params(self) -> Vec<job::WhereParam> {
+		match self {
+			Self::Name(v) => v
+				.into_param(job::name::in_vec, job::name::not_in_vec)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
+			Self::Status(v) => {
+				let into_ints =
+					|v: Vec<JobStatus>| v.into_iter().map(|s| s as i32).collect::<Vec<_>>();
+
+				match v {
+					InOrNotIn::In(v) if !v.is_empty() => vec![job::status::in_vec(into_ints(v))],
+					InOrNotIn::NotIn(v) if !v.is_empty() => {
+						vec![job::status::not_in_vec(into_ints(v))]
+					}
+					_ => vec![],
+				}
+			}
+			Self::Location(v) => v
+				.into_param(job::location_id::in_vec, job::location_id::not_in_vec)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
+			Self::CreatedAt(v) => vec![match v {
+				Range::From(v) => job::date_created::gte(v.into()),
+				Range::To(v) => job::date_created::lte(v.into()),
+			}],
+		}
+	}
+}
+
+/// A finished or running job as shown in the history, with its error details.
+#[derive(Serialize, Type, Debug)]
+pub struct JobHistoryEntry {
+	#[serde(flatten)]
+	pub report: JobReport,
+	/// Time between the job start and its completion, in seconds
+	pub duration_secs: Option<f64>,
+	/// The last error reported by the job, falling back to the error of its last retry
+	pub last_error: Option<String>,
+}
+
+impl From<JobReport> for JobHistoryEntry {
+	fn from(report: JobReport) -> Self {
+		Self {
+			duration_secs: report.started_at.zip(report.completed_at).map(
+				|(started_at, completed_at)| {
+					(completed_at - started_at).num_milliseconds() as f64 / 1000.0
+				},
+			),
+			last_error: report
+				.errors_text
+				.last()
+				.cloned()
+				.or_else(|| report.retries.last().map(|retry| retry.error.clone())),
+			report,
+		}
+	}
+}
+
+/// Returns a page of the job history, most recent jobs first.
+///
+/// `cursor` is the id of the last job of the previous page.
+pub async fn get_job_history(
+	library: &Library,
+	filters: Vec<JobHistoryFilterArgs>,
+	cursor: Option<Uuid>,
+	pagination: Pagination,
+) -> Result<(Vec<JobHistoryEntry>, Option<Uuid>), JobError> {
+	let mut query = library
+		.db
+		.job()
+		.find_many(
+			filters
+				.into_iter()
+				.flat_map(JobHistoryFilterArgs::into_params)
+				.collect(),
+		)
+		.order_by(job::date_created::order(SortOrder::Desc))
+		.take(pagination.fetch());
+
+	if let Some(cursor) = cursor {
+		query = query
+			.cursor(job::id::equals(cursor.as_bytes().to_vec()))
+			.skip(1);
+	}
+
+	let reports = query
+		.select(job_without_data::select())
+		.exec()
+		.await?
+		.into_iter()
+		.map(JobReport::try_from)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let (reports, cursor) = pagination.paginate(reports, |report| report.id);
+
+	Ok((reports.into_iter().map(Into::into).collect(), cursor))
+}
+
+/// How long finished jobs are kept in the history.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobHistoryRetention {
+	/// Finished jobs older than this are removed, `None` keeps them forever
+	pub max_age_days: Option<u32>,
+	/// Only the most recent finished jobs are kept, `None` keeps all of them
+	pub max_entries: Option<u32>,
+}
+
+impl Default for JobHistoryRetention {
+	fn default() -> Self {
+		Self {
+			max_age_days: Some(90),
+			max_entries: Some(10_000),
+		}
+	}
+}
+
+fn finished_jobs_param() -> job::WhereParam {
+	or(vec![
+		job::status::equals(Some(JobStatus::Canceled as i32)),
+		job::status::equals(Some(JobStatus::Failed as i32)),
+		job::status::equals(Some(JobStatus::Completed as i32)),
+		job::status::equals(Some(JobStatus::CompletedWithErrors as i32)),
+	])
+}
+
+/// Removes finished jobs that are out of the retention window, returning how many were removed.
+///
+/// On a dry run nothing is removed, the returned amount is how many jobs would be.
+pub async fn prune_job_history(
+	library: &Library,
+	JobHistoryRetention {
+		max_age_days,
+		max_entries,
+	}: JobHistoryRetention,
+	dry_run: bool,
+) -> Result<i64, JobError> {
+	let mut out_of_window = Vec::with_capacity(2);
+
+	if let Some(max_age_days) = max_age_days {
+		out_of_window.push(job::date_created::lt(
+			(Utc::now() - Duration::days(i64::from(max_age_days))).into(),
+		));
+	}
+
+	if let Some(max_entries) = max_entries {
+		// Finding the most recent job out of the window, it and everything older than it goes away
+		if let Some(first_pruned) = library
+			.db
+			.job()
+			.find_many(vec![finished_jobs_param()])
+			.order_by(job::date_created::order(SortOrder::Desc))
+			.skip(i64::from(max_entries))
+			.take(1)
+			.select(job::select!({ date_created }))
+			.exec()
+			.await?
+			.pop()
+			.and_then(|data| data.date_created)
+		{
+			out_of_window.push(job::date_created::lte(first_pruned));
+		}
+	}
+
+	if out_of_window.is_empty() {
+		return Ok(0);
+	}
+
+	let params = vec![finished_jobs_param(), or(out_of_window)];
+
+	let removed = if dry_run {
+		library.db.job().count(params).exec().await?
+	} else {
+		library.db.job().delete_many(params).exec().await?
+	};
+
+	debug!(
+		"{} {removed} jobs from the history of library {}",
+		if dry_run { "Would prune" } else { "Pruned" },
+		library.id
+	);
+
+	Ok(removed)
+}
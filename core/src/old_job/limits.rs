@@ -0,0 +1,196 @@
+use crate::node::background_policy::{BackgroundPolicyState, Subsystem, WorkMode};
+
+use super::JobPriority;
+
+use std::{
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{sync::watch, time::sleep_until};
+
+/// Caps on the resources a job type can use, to keep the machine responsive while it runs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobResourceLimits {
+	/// Maximum amount of files processed at the same time, `None` lets the job decide
+	pub max_threads: Option<u16>,
+	/// Maximum disk read rate in KiB per second, `None` means unlimited
+	pub max_io_kib_per_sec: Option<u32>,
+}
+
+impl JobResourceLimits {
+	pub fn is_unlimited(&self) -> bool {
+		self.max_threads.is_none() && self.max_io_kib_per_sec.is_none()
+	}
+
+	/// Zero values make no sense as a cap, so we treat them as unlimited
+	pub(super) fn normalized(self) -> Self {
+		Self {
+			max_threads: self.max_threads.filter(|threads| *threads > 0),
+			max_io_kib_per_sec: self.max_io_kib_per_sec.filter(|rate| *rate > 0),
+		}
+	}
+}
+
+/// Enforces [`JobResourceLimits`] during a job run, available to jobs through the
+/// [`WorkerContext`](super::WorkerContext).
+#[derive(Debug, Default)]
+pub struct JobResourceLimiter {
+	limits: JobResourceLimits,
+	io_next_free_at: Mutex<Option<Instant>>,
+	/// Runs the job on a single thread while the background policy throttles its subsystem
+	background_policy: Option<(watch::Receiver<BackgroundPolicyState>, Subsystem)>,
+	/// Runs the job on a single thread while the user gives it a low priority
+	priority_rx: Option<watch::Receiver<JobPriority>>,
+}
+
+impl JobResourceLimiter {
+	pub fn new(limits: JobResourceLimits) -> Self {
+		Self {
+			limits: limits.normalized(),
+			io_next_free_at: Mutex::new(None),
+			background_policy: None,
+			priority_rx: None,
+		}
+	}
+
+	pub fn with_background_policy(
+		mut self,
+		background_policy_rx: watch::Receiver<BackgroundPolicyState>,
+		subsystem: Subsystem,
+	) -> Self {
+		self.background_policy = Some((background_policy_rx, subsystem));
+
+		self
+	}
+
+	pub fn with_priority(mut self, priority_rx: watch::Receiver<JobPriority>) -> Self {
+		self.priority_rx = Some(priority_rx);
+
+		self
+	}
+
+	/// The limits in effect right now, which are tighter while the background policy throttles
+	/// the job or while it has a low priority.
+	pub fn limits(&self) -> JobResourceLimits {
+		let throttled =
+			self.background_policy
+				.as_ref()
+				.is_some_and(|(background_policy_rx, subsystem)| {
+					background_policy_rx.borrow().mode(*subsystem) == WorkMode::Throttled
+				});
+
+		let low_priority = self
+			.priority_rx
+			.as_ref()
+			.is_some_and(|priority_rx| *priority_rx.borrow() == JobPriority::Low);
+
+		if throttled || low_priority {
+			JobResourceLimits {
+				max_threads: Some(1),
+				..self.limits
+			}
+		} else {
+			self.limits
+		}
+	}
+
+	/// How many files can be processed at the same time, given the amount the job would
+	/// like to use. Always at least 1.
+	pub fn parallelism(&self, wanted: usize) -> usize {
+		self.limits()
+			.max_threads
+			.map_or(wanted, |max_threads| wanted.min(usize::from(max_threads)))
+			.max(1)
+	}
+
+	/// Accounts for `bytes` read from disk, waiting as needed to stay under the configured rate.
+	pub async fn throttle_io(&self, bytes: u64) {
+		if let Some(wait_until) = self.reserve_io(Instant::now(), bytes) {
+			sleep_until(wait_until.into()).await;
+		}
+	}
+
+	fn reserve_io(&self, now: Instant, bytes: u64) -> Option<Instant> {
+		let rate = self.limits.max_io_kib_per_sec?;
+
+		let mut next_free_at = self
+			.io_next_free_at
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		let wait_until = next_free_at.map_or(now, |next_free_at| next_free_at.max(now))
+			+ Duration::from_secs_f64(bytes as f64 / (f64::from(rate) * 1024.0));
+
+		*next_free_at = Some(wait_until);
+
+		(wait_until > now).then_some(wait_until)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn io_reservations_queue_up() {
+		let limiter = JobResourceLimiter::new(JobResourceLimits {
+			max_threads: None,
+			max_io_kib_per_sec: Some(1024),
+		});
+
+		let now = Instant::now();
+
+		assert_eq!(
+			limiter.reserve_io(now, 1024 * 1024),
+			Some(now + Duration::from_secs(1))
+		);
+		assert_eq!(
+			limiter.reserve_io(now, 512 * 1024),
+			Some(now + Duration::from_millis(1500))
+		);
+		// After the budget was spent, we start counting from now again
+		let later = now + Duration::from_secs(10);
+		assert_eq!(
+			limiter.reserve_io(later, 1024 * 1024),
+			Some(later + Duration::from_secs(1))
+		);
+	}
+
+	#[test]
+	fn zero_limits_are_unlimited() {
+		let limiter = JobResourceLimiter::new(JobResourceLimits {
+			max_threads: Some(0),
+			max_io_kib_per_sec: Some(0),
+		});
+
+		assert_eq!(limiter.parallelism(8), 8);
+		assert_eq!(limiter.reserve_io(Instant::now(), 1024), None);
+
+		let limiter = JobResourceLimiter::new(JobResourceLimits {
+			max_threads: Some(2),
+			max_io_kib_per_sec: None,
+		});
+
+		assert_eq!(limiter.parallelism(8), 2);
+		assert_eq!(limiter.parallelism(0), 1);
+	}
+
+	#[test]
+	fn low_priority_runs_on_a_single_thread() {
+		let (priority_tx, priority_rx) = watch::channel(JobPriority::Normal);
+		let limiter =
+			JobResourceLimiter::new(JobResourceLimits::default()).with_priority(priority_rx);
+
+		assert_eq!(limiter.parallelism(8), 8);
+
+		priority_tx.send_replace(JobPriority::Low);
+		assert_eq!(limiter.parallelism(8), 1);
+
+		priority_tx.send_replace(JobPriority::High);
+		assert_eq!(limiter.parallelism(8), 8);
+	}
+}
@@ -1,16 +1,38 @@
 use crate::{
 	library::Library,
-	location::indexer::old_indexer_job::OldIndexerJobInit,
+	location::{
+		indexer::{
+			old_indexer_job::OldIndexerJobInit, old_remote_indexer_job::OldRemoteIndexerJobInit,
+		},
+		mirror::old_mirror_job::OldMirrorJobInit,
+	},
+	node::{
+		background_policy::{BackgroundPolicyState, Subsystem, WorkMode},
+		config::NodePreferences,
+	},
 	object::{
+		duplicates::old_duplicate_finder_job::OldDuplicateFinderJobInit,
 		fs::{
 			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
 			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
 		},
 		media::old_media_processor::OldMediaProcessorJobInit,
 		old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
-		validation::old_validator_job::OldObjectValidatorJobInit,
+		old_metadata_importer::OldMetadataImporterJobInit,
+		tag::rules::old_tag_rules_job::OldTagRulesJobInit,
+		validation::{
+			old_location_validator_job::OldLocationValidatorJobInit,
+			old_validator_job::OldObjectValidatorJobInit,
+		},
+		xmp::{
+			old_xmp_exporter_job::OldXmpExporterJobInit,
+			old_xmp_importer_job::OldXmpImporterJobInit,
+		},
+	},
+	old_job::{
+		worker::{emit_finished, Worker},
+		DynJob, Job, JobError,
 	},
-	old_job::{worker::Worker, DynJob, Job, JobError},
 	Node,
 };
 
@@ -19,18 +41,48 @@ use sd_prisma::prisma::job;
 use std::{
 	collections::{HashMap, HashSet, VecDeque},
 	sync::Arc,
+	time::Duration,
 };
 
 use futures::future::join_all;
 use prisma_client_rust::operator::or;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::{
+	sync::{mpsc, oneshot, watch, Mutex, RwLock},
+	task::JoinHandle,
+	time::sleep,
+};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use super::{JobIdentity, JobManagerError, JobReport, JobStatus, StatefulJob};
+use super::{
+	priority::next_in_queue, JobIdentity, JobManagerError, JobPriority, JobReport, JobStatus,
+	StatefulJob,
+};
 
 const MAX_WORKERS: usize = 5;
 
+/// The subsystem of the background policy a job belongs to, jobs without one are started by
+/// the user and always run.
+pub(super) fn job_subsystem(job_name: &str) -> Option<Subsystem> {
+	match job_name {
+		"indexer" | "remote_indexer" | "file_identifier" | "object_validator"
+		| "metadata_importer" | "duplicate_finder" | "tag_rules" | "validate" => {
+			Some(Subsystem::Indexing)
+		}
+		"media_processor" => Some(Subsystem::Thumbnailing),
+		"location_mirror" => Some(Subsystem::Transfers),
+		_ => None,
+	}
+}
+
+/// A failed job waiting for its next attempt, without a worker.
+struct PendingRetry {
+	library: Arc<Library>,
+	job: Box<dyn DynJob>,
+	/// Dispatches the job once the backoff is over, missing while the retry is paused
+	timer: Option<JoinHandle<()>>,
+}
+
 pub enum JobManagerEvent {
 	IngestJob(Arc<Library>, Box<dyn DynJob>),
 	Shutdown(oneshot::Sender<()>, Arc<OldJobs>),
@@ -44,6 +96,18 @@ pub struct Actor {
 
 impl Actor {
 	pub fn start(mut self, node: Arc<Node>) {
+		tokio::spawn({
+			let jobs = Arc::clone(&self.jobs);
+			let mut background_policy_rx = jobs.background_policy_rx.clone();
+
+			async move {
+				while background_policy_rx.changed().await.is_ok() {
+					let state = background_policy_rx.borrow_and_update().clone();
+					jobs.follow_background_policy(&state).await;
+				}
+			}
+		});
+
 		tokio::spawn(async move {
 			// FIXME: if this task crashes, the entire application is unusable
 			while let Some(event) = self.internal_receiver.recv().await {
@@ -74,11 +138,22 @@ pub struct OldJobs {
 	job_queue: RwLock<VecDeque<Box<dyn DynJob>>>,
 	running_workers: RwLock<HashMap<Uuid, Worker>>,
 	internal_sender: mpsc::UnboundedSender<JobManagerEvent>,
+	node_preferences_rx: watch::Receiver<NodePreferences>,
+	background_policy_rx: watch::Receiver<BackgroundPolicyState>,
+	/// Workers paused by the background policy, resumed once their subsystem can run again
+	held_back_workers: Mutex<HashSet<Uuid>>,
+	/// Priorities set by the user, by the id of the first job of a chain
+	priorities: RwLock<HashMap<Uuid, JobPriority>>,
+	/// Failed jobs waiting for their retry, by the id of their worker
+	pending_retries: Mutex<HashMap<Uuid, PendingRetry>>,
 }
 
 impl OldJobs {
 	/// Initializes the JobManager and spawns the internal event loop to listen for ingest.
-	pub fn new() -> (Arc<Self>, Actor) {
+	pub fn new(
+		node_preferences_rx: watch::Receiver<NodePreferences>,
+		background_policy_rx: watch::Receiver<BackgroundPolicyState>,
+	) -> (Arc<Self>, Actor) {
 		// allow the job manager to control its workers
 		let (internal_sender, internal_receiver) = mpsc::unbounded_channel();
 		let this = Arc::new(Self {
@@ -86,6 +161,11 @@ impl OldJobs {
 			job_queue: RwLock::new(VecDeque::new()),
 			running_workers: RwLock::new(HashMap::new()),
 			internal_sender,
+			node_preferences_rx,
+			background_policy_rx,
+			held_back_workers: Mutex::new(HashSet::new()),
+			priorities: RwLock::new(HashMap::new()),
+			pending_retries: Mutex::new(HashMap::new()),
 		});
 
 		(
@@ -124,7 +204,50 @@ impl OldJobs {
 		Ok(())
 	}
 
-	/// Dispatches a job to a worker if under MAX_WORKERS limit, queues it otherwise.
+	/// Checks if a job with this name can run now, given the MAX_WORKERS limit and the
+	/// concurrency limit configured for its type.
+	fn can_run(&self, running_workers: &HashMap<Uuid, Worker>, job_name: &str) -> bool {
+		running_workers.len() < MAX_WORKERS
+			&& self
+				.node_preferences_rx
+				.borrow()
+				.jobs
+				.concurrency_limit(job_name)
+				.map_or(true, |limit| {
+					running_workers
+						.values()
+						.filter(|worker| worker.job_name() == job_name)
+						.count() < limit as usize
+				})
+	}
+
+	/// Takes the job with the highest priority in the queue that is allowed to run now,
+	/// skipping the ones that are over their type's concurrency limit.
+	async fn pop_runnable_job(&self) -> Option<Box<dyn DynJob>> {
+		let running_workers = self.running_workers.read().await;
+		let priorities = self.priorities.read().await;
+		let mut job_queue = self.job_queue.write().await;
+
+		next_in_queue(
+			job_queue
+				.iter()
+				.enumerate()
+				.filter(|(_, job)| self.can_run(&running_workers, job.name()))
+				.map(|(idx, job)| {
+					(
+						idx,
+						priorities
+							.get(&job.parent_id().unwrap_or_else(|| job.id()))
+							.copied()
+							.unwrap_or_default(),
+					)
+				}),
+		)
+		.and_then(|idx| job_queue.remove(idx))
+	}
+
+	/// Dispatches a job to a worker if under MAX_WORKERS and its type's concurrency limits,
+	/// queues it otherwise.
 	async fn dispatch(
 		self: Arc<Self>,
 		node: &Arc<Node>,
@@ -137,10 +260,12 @@ impl OldJobs {
 			.take()
 			.expect("critical error: missing job on worker");
 
-		if running_workers.len() < MAX_WORKERS {
+		if self.can_run(&running_workers, job.name()) {
 			info!("Running job: {:?}", job.name());
 
 			let worker_id = job_report.parent_id.unwrap_or(job_report.id);
+			let job_name = job.name();
+			let priority = self.priority(worker_id).await;
 
 			Worker::new(
 				worker_id,
@@ -149,6 +274,7 @@ impl OldJobs {
 				library.clone(),
 				node.clone(),
 				self.clone(),
+				priority,
 			)
 			.await
 			.map_or_else(
@@ -159,6 +285,14 @@ impl OldJobs {
 					running_workers.insert(worker_id, worker);
 				},
 			);
+
+			if self.is_held_back(job_name) {
+				if let Some(worker) = running_workers.get(&worker_id) {
+					debug!("Holding back job <name='{job_name}'> due to the background policy");
+					worker.pause().await;
+					self.held_back_workers.lock().await.insert(worker_id);
+				}
+			}
 		} else {
 			debug!(
 				"Queueing job: <name='{}', hash='{}'>",
@@ -191,7 +325,9 @@ impl OldJobs {
 		let job = if next_job.is_some() {
 			next_job
 		} else {
-			self.job_queue.write().await.pop_front()
+			// The chain of jobs is over, so its priority is no longer needed
+			self.priorities.write().await.remove(&worker_id);
+			self.pop_runnable_job().await
 		};
 
 		if let Some(job) = job {
@@ -204,6 +340,62 @@ impl OldJobs {
 		}
 	}
 
+	/// Frees the worker of a failed job and dispatches the same job again after `delay`.
+	/// The job hash is kept, so the same job can't be ingested while it waits for its retry.
+	pub async fn retry(
+		self: Arc<Self>,
+		library: &Arc<Library>,
+		worker_id: Uuid,
+		job: Box<dyn DynJob>,
+		delay: Duration,
+	) {
+		self.running_workers.write().await.remove(&worker_id);
+
+		// The worker slot is free, so we can continue the queue while we wait
+		if let Some(queued_job) = self.pop_runnable_job().await {
+			self.internal_sender
+				.send(JobManagerEvent::IngestJob(library.clone(), queued_job))
+				.unwrap_or_else(|_| {
+					error!("Failed to ingest job!");
+				});
+		}
+
+		// Tracked so the retry can be paused or canceled during the backoff
+		let mut pending_retries = self.pending_retries.lock().await;
+		let timer = tokio::spawn({
+			let this = Arc::clone(&self);
+			async move {
+				sleep(delay).await;
+				this.dispatch_retry(worker_id).await;
+			}
+		});
+
+		pending_retries.insert(
+			worker_id,
+			PendingRetry {
+				library: Arc::clone(library),
+				job,
+				timer: Some(timer),
+			},
+		);
+	}
+
+	async fn dispatch_retry(&self, worker_id: Uuid) {
+		let Some(PendingRetry { library, job, .. }) =
+			self.pending_retries.lock().await.remove(&worker_id)
+		else {
+			return;
+		};
+
+		debug!("Retrying job: <name='{}', id='{}'>", job.name(), job.id());
+
+		self.internal_sender
+			.send(JobManagerEvent::IngestJob(library, job))
+			.unwrap_or_else(|_| {
+				error!("Failed to ingest job for retry!");
+			});
+	}
+
 	/// Shutdown the job manager, signaled by core on shutdown.
 	pub async fn shutdown(self: &Arc<Self>) {
 		let (tx, rx) = oneshot::channel();
@@ -218,6 +410,44 @@ impl OldJobs {
 		});
 	}
 
+	fn is_held_back(&self, job_name: &str) -> bool {
+		job_subsystem(job_name).is_some_and(|subsystem| {
+			self.background_policy_rx.borrow().mode(subsystem) == WorkMode::Paused
+		})
+	}
+
+	/// Pauses the running jobs of the subsystems paused by the background policy, resuming the
+	/// ones it paused before once their subsystem can run again. Jobs paused by the user are
+	/// left alone.
+	async fn follow_background_policy(&self, state: &BackgroundPolicyState) {
+		let running_workers = self.running_workers.read().await;
+		let mut held_back_workers = self.held_back_workers.lock().await;
+
+		for (worker_id, worker) in running_workers.iter() {
+			let paused = job_subsystem(worker.job_name())
+				.is_some_and(|subsystem| state.mode(subsystem) == WorkMode::Paused);
+
+			if paused {
+				if !worker.is_paused() {
+					debug!(
+						"Holding back job <name='{}'> due to the background policy",
+						worker.job_name()
+					);
+					worker.pause().await;
+					held_back_workers.insert(*worker_id);
+				}
+			} else if held_back_workers.remove(worker_id) {
+				debug!(
+					"Resuming job <name='{}'> held back by the background policy",
+					worker.job_name()
+				);
+				worker.resume().await;
+			}
+		}
+
+		held_back_workers.retain(|worker_id| running_workers.contains_key(worker_id));
+	}
+
 	/// Pause a specific job.
 	pub async fn pause(&self, job_id: Uuid) -> Result<(), JobManagerError> {
 		// Look up the worker for the given job ID.
@@ -227,6 +457,22 @@ impl OldJobs {
 			// Set the pause signal in the worker.
 			worker.pause().await;
 
+			Ok(())
+		} else if let Some(pending_retry) = self.pending_retries.lock().await.get_mut(&job_id) {
+			debug!("Pausing the retry of job: <id='{job_id}'>");
+
+			// The job stays here, without a timer, until it's resumed
+			if let Some(timer) = pending_retry.timer.take() {
+				timer.abort();
+			}
+
+			if let Some(report) = pending_retry.job.report_mut() {
+				report.status = JobStatus::Paused;
+				if let Err(e) = report.update(&pending_retry.library).await {
+					error!("failed to update job report: {:#?}", e);
+				}
+			}
+
 			Ok(())
 		} else {
 			Err(JobManagerError::NotFound(job_id))
@@ -241,6 +487,17 @@ impl OldJobs {
 			// Set the pause signal in the worker.
 			worker.resume().await;
 
+			Ok(())
+		} else if self
+			.pending_retries
+			.lock()
+			.await
+			.get(&job_id)
+			.is_some_and(|pending_retry| pending_retry.timer.is_none())
+		{
+			// The backoff was interrupted by the pause, so the job is retried right away
+			self.dispatch_retry(job_id).await;
+
 			Ok(())
 		} else {
 			Err(JobManagerError::NotFound(job_id))
@@ -256,12 +513,89 @@ impl OldJobs {
 			// Set the cancel signal in the worker.
 			worker.cancel().await;
 
+			Ok(())
+		} else if let Some(PendingRetry {
+			library,
+			mut job,
+			timer,
+		}) = self.pending_retries.lock().await.remove(&job_id)
+		{
+			debug!("Canceling the retry of job: <id='{job_id}'>");
+
+			if let Some(timer) = timer {
+				timer.abort();
+			}
+
+			// The job is over, so the same job can be ingested again
+			self.current_jobs_hashes.write().await.remove(&job.hash());
+			self.priorities.write().await.remove(&job_id);
+
+			if let Err(e) = job.cancel_children(&library).await {
+				error!("Failed to cancel children jobs: {e:#?}");
+			}
+
+			if let Some(report) = job.report_mut() {
+				report.status = JobStatus::Canceled;
+				report.data = None;
+				if let Err(e) = report.update(&library).await {
+					error!("failed to update job report: {:#?}", e);
+				}
+
+				emit_finished(&library, report);
+			}
+
 			Ok(())
 		} else {
 			Err(JobManagerError::NotFound(job_id))
 		}
 	}
 
+	/// Changes the priority of a running or queued job, and of the jobs that will run after it.
+	pub async fn set_priority(
+		&self,
+		job_id: Uuid,
+		priority: JobPriority,
+	) -> Result<(), JobManagerError> {
+		let running_workers = self.running_workers.read().await;
+
+		if let Some(worker) = running_workers.get(&job_id) {
+			debug!(
+				"Setting priority of running job <name='{}'> to {priority:?}",
+				worker.job_name()
+			);
+			worker.set_priority(priority);
+		} else if self
+			.job_queue
+			.read()
+			.await
+			.iter()
+			.any(|job| job.parent_id().unwrap_or_else(|| job.id()) == job_id)
+		{
+			debug!("Setting priority of queued job <id='{job_id}'> to {priority:?}");
+		} else {
+			return Err(JobManagerError::NotFound(job_id));
+		}
+
+		self.priorities.write().await.insert(job_id, priority);
+
+		Ok(())
+	}
+
+	/// The priority of a job, [`JobPriority::Normal`] unless the user changed it.
+	pub async fn priority(&self, job_id: Uuid) -> JobPriority {
+		self.priorities
+			.read()
+			.await
+			.get(&job_id)
+			.copied()
+			.unwrap_or_default()
+	}
+
+	/// The jobs the user changed the priority of, which are still running or queued.
+	pub async fn priorities(&self) -> HashMap<Uuid, JobPriority> {
+		self.priorities.read().await.clone()
+	}
+
 	/// This is called at startup to resume all paused jobs or jobs that were running
 	/// when the core was shut down.
 	/// - It will resume jobs that contain data and cancel jobs that do not.
@@ -318,6 +652,21 @@ impl OldJobs {
 		Ok(())
 	}
 
+	/// Amount of jobs waiting for a free worker.
+	pub async fn queued_count(&self) -> usize {
+		self.job_queue.read().await.len()
+	}
+
+	/// Names of the jobs with a worker, including paused ones.
+	pub async fn running_job_names(&self) -> Vec<&'static str> {
+		self.running_workers
+			.read()
+			.await
+			.values()
+			.map(Worker::job_name)
+			.collect()
+	}
+
 	// get all active jobs, including paused jobs organized by job id
 	pub async fn get_active_reports_with_id(&self) -> HashMap<Uuid, JobReport> {
 		self.running_workers
@@ -398,12 +747,20 @@ fn initialize_resumable_job(
 		jobs = [
 			OldMediaProcessorJobInit,
 			OldIndexerJobInit,
+			OldRemoteIndexerJobInit,
 			OldFileIdentifierJobInit,
 			OldObjectValidatorJobInit,
 			OldFileCutterJobInit,
 			OldFileCopierJobInit,
 			OldFileDeleterJobInit,
 			OldFileEraserJobInit,
+			OldMetadataImporterJobInit,
+			OldXmpExporterJobInit,
+			OldXmpImporterJobInit,
+			OldMirrorJobInit,
+			OldDuplicateFinderJobInit,
+			OldTagRulesJobInit,
+			OldLocationValidatorJobInit,
 		]
 	)
 }
@@ -20,17 +20,33 @@ use tokio::{
 	spawn,
 	task::{JoinError, JoinHandle},
 };
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, info_span, trace, warn, Instrument};
 use uuid::Uuid;
 
+mod cleanup;
+mod dry_run;
 mod error;
+mod history;
+mod limits;
 mod manager;
+mod notifications;
+pub mod preferences;
+mod priority;
 mod report;
+mod retry;
+mod throughput;
 mod worker;
 
+pub use cleanup::JobCleanupId;
+pub use dry_run::*;
 pub use error::*;
+pub use history::*;
+pub use limits::*;
 pub use manager::*;
+pub use notifications::JobNotificationSettings;
+pub use priority::JobPriority;
 pub use report::*;
+pub use retry::*;
 pub use worker::*;
 
 pub type JobResult = Result<JobMetadata, JobError>;
@@ -121,6 +137,17 @@ pub trait StatefulJob:
 		run_metadata: &Self::RunMetadata,
 	) -> JobResult;
 
+	/// is called when the job is canceled during its steps, after the handlers registered
+	/// with [`WorkerContext::register_cleanup`], to roll back any half-done work
+	async fn cleanup(
+		&self,
+		_ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		_run_metadata: &Self::RunMetadata,
+	) -> Result<(), JobError> {
+		Ok(())
+	}
+
 	fn hash(&self) -> u64 {
 		let mut s = DefaultHasher::new();
 		Self::NAME.hash(&mut s);
@@ -144,6 +171,8 @@ pub trait DynJob: Send + Sync {
 	fn hash(&self) -> u64;
 	fn set_next_jobs(&mut self, next_jobs: VecDeque<Box<dyn DynJob>>);
 	fn serialize_state(&self) -> Result<Vec<u8>, JobError>;
+	/// Restores a state previously obtained from [`DynJob::serialize_state`], used to run the job again
+	fn restore_state(&mut self, state: &[u8]) -> Result<(), JobError>;
 	async fn register_children(&mut self, library: &Library) -> Result<(), JobError>;
 	async fn pause_children(&mut self, library: &Library) -> Result<(), JobError>;
 	async fn cancel_children(&mut self, library: &Library) -> Result<(), JobError>;
@@ -176,8 +205,9 @@ impl<SJob: StatefulJob> JobBuilder<SJob> {
 		let id = Uuid::new_v4();
 		Self {
 			id,
+			report_builder: JobReportBuilder::new(id, SJob::NAME.to_string())
+				.with_location_id(init.target_location()),
 			init,
-			report_builder: JobReportBuilder::new(id, SJob::NAME.to_string()),
 		}
 	}
 
@@ -230,6 +260,17 @@ impl<SJob: StatefulJob> Job<SJob> {
 		self
 	}
 
+	/// [`Job::queue_next`] if there's a job to queue
+	pub fn maybe_queue_next<NextSJob>(self: Box<Self>, init: Option<NextSJob>) -> Box<Self>
+	where
+		NextSJob: StatefulJob + 'static,
+	{
+		match init {
+			Some(init) => self.queue_next(init),
+			None => self,
+		}
+	}
+
 	// this function returns an ingestible job instance from a job report
 	pub fn new_from_report(
 		mut report: JobReport,
@@ -498,18 +539,21 @@ impl<SJob: StatefulJob> DynJob for Job<SJob> {
 			let init_time = Instant::now();
 			let init_task = {
 				let ctx = Arc::clone(&ctx);
-				spawn(async move {
-					let mut new_data = None;
-					let res = stateful_job.init(&ctx, &mut new_data).await;
-
-					if let Ok(res) = res.as_ref() {
-						if !<SJob as StatefulJob>::IS_BATCHED {
-							ctx.progress(vec![JobReportUpdate::TaskCount(res.steps.len())]);
+				spawn(
+					async move {
+						let mut new_data = None;
+						let res = stateful_job.init(&ctx, &mut new_data).await;
+
+						if let Ok(res) = res.as_ref() {
+							if !<SJob as StatefulJob>::IS_BATCHED {
+								ctx.progress(vec![JobReportUpdate::TaskCount(res.steps.len())]);
+							}
 						}
-					}
 
-					(stateful_job, new_data, res)
-				})
+						(stateful_job, new_data, res)
+					}
+					.instrument(info_span!("job_init")),
+				)
 			};
 
 			let InitPhaseOutput {
@@ -572,19 +616,22 @@ impl<SJob: StatefulJob> DynJob for Job<SJob> {
 					let working_data = Arc::clone(&working_data_arc);
 					let step = Arc::clone(&step);
 					let stateful_job = Arc::clone(&stateful_job);
-					spawn(async move {
-						stateful_job
-							.execute_step(
-								&ctx,
-								CurrentStep {
-									step: &step,
-									step_number,
-								},
-								&working_data,
-								&run_metadata,
-							)
-							.await
-					})
+					spawn(
+						async move {
+							stateful_job
+								.execute_step(
+									&ctx,
+									CurrentStep {
+										step: &step,
+										step_number,
+									},
+									&working_data,
+									&run_metadata,
+								)
+								.await
+						}
+						.instrument(info_span!("job_step", step_number)),
+					)
 				};
 
 				let JobStepsPhaseOutput {
@@ -714,6 +761,12 @@ impl<SJob: StatefulJob> DynJob for Job<SJob> {
 		rmp_serde::to_vec_named(&self.state).map_err(Into::into)
 	}
 
+	fn restore_state(&mut self, state: &[u8]) -> Result<(), JobError> {
+		self.state = Some(rmp_serde::from_slice::<JobState<SJob>>(state)?);
+
+		Ok(())
+	}
+
 	async fn register_children(&mut self, library: &Library) -> Result<(), JobError> {
 		for next_job in self.next_jobs.iter_mut() {
 			if let Some(next_job_report) = next_job.report_mut() {
@@ -890,6 +943,8 @@ async fn handle_init_phase<SJob: StatefulJob>(
 								"Total paused time {:?} Job <id='{id}', name='{name}'>",
 								paused_time.elapsed()
 							);
+							worker_ctx.resume();
+
 							status = JobStatus::Running;
 
 							continue 'messages;
@@ -906,6 +961,8 @@ async fn handle_init_phase<SJob: StatefulJob>(
 							);
 							debug!("Total paused time {:?}", paused_time.elapsed());
 
+							worker_ctx.cleanups.run().await;
+
 							// Shutting down at init phase will abort the job
 							return Err(JobError::Canceled(signal_tx));
 						}
@@ -921,6 +978,9 @@ async fn handle_init_phase<SJob: StatefulJob>(
 								"Total paused time {:?} Job <id='{id}', name='{name}'>",
 								paused_time.elapsed()
 							);
+
+							worker_ctx.cleanups.run().await;
+
 							return Err(JobError::Canceled(signal_tx));
 						}
 						WorkerCommand::Pause(_) => {
@@ -960,6 +1020,8 @@ async fn handle_init_phase<SJob: StatefulJob>(
 					init_time.elapsed(),
 				);
 
+				worker_ctx.cleanups.run().await;
+
 				// Shutting down at init phase will abort the job
 				return Err(JobError::Canceled(signal_tx));
 			}
@@ -973,6 +1035,8 @@ async fn handle_init_phase<SJob: StatefulJob>(
 					init_time.elapsed()
 				);
 
+				worker_ctx.cleanups.run().await;
+
 				return Err(JobError::Canceled(signal_tx));
 			}
 			StreamMessage::NewCommand(WorkerCommand::Timeout(elapsed, tx)) => {
@@ -1014,6 +1078,31 @@ type StepArcs<SJob> = (
 	Arc<SJob>,
 );
 
+/// Runs the cleanup handlers registered by a job canceled during its steps, and then its own
+/// [`StatefulJob::cleanup`]. Errors are only logged, as the job is canceled anyway.
+async fn cleanup_canceled_job<SJob: StatefulJob>(
+	worker_ctx: &WorkerContext,
+	stateful_job: Arc<SJob>,
+	working_data: Arc<SJob::Data>,
+	run_metadata: Arc<SJob::RunMetadata>,
+) {
+	worker_ctx.cleanups.run().await;
+
+	if let Err(e) = stateful_job
+		.cleanup(
+			worker_ctx,
+			&Some(Arc::try_unwrap(working_data).expect("handle abort already ran, no more refs")),
+			&run_metadata,
+		)
+		.await
+	{
+		error!(
+			"Failed to cleanup canceled Job <name='{}'>: {e:#?}",
+			<SJob as StatefulJob>::NAME
+		);
+	}
+}
+
 #[inline]
 async fn handle_single_step<SJob: StatefulJob>(
 	JobRunWorkTable {
@@ -1117,6 +1206,8 @@ async fn handle_single_step<SJob: StatefulJob>(
 								"Total paused time {:?} Job <id='{id}', name='{name}'>",
 								paused_time.elapsed(),
 							);
+							worker_ctx.resume();
+
 							status = JobStatus::Running;
 
 							continue 'messages;
@@ -1171,6 +1262,15 @@ async fn handle_single_step<SJob: StatefulJob>(
 								"Total paused time {:?} Job <id='{id}', name='{name}'>",
 								paused_time.elapsed(),
 							);
+
+							cleanup_canceled_job(
+								&worker_ctx,
+								stateful_job,
+								working_data,
+								run_metadata,
+							)
+							.await;
+
 							return Err(JobError::Canceled(signal_tx));
 						}
 						WorkerCommand::Pause(_) => {
@@ -1242,6 +1342,9 @@ async fn handle_single_step<SJob: StatefulJob>(
 					when.elapsed(),
 					job_init_time.elapsed(),
 				);
+
+				cleanup_canceled_job(&worker_ctx, stateful_job, working_data, run_metadata).await;
+
 				return Err(JobError::Canceled(signal_tx));
 			}
 			StreamMessage::NewCommand(WorkerCommand::Timeout(elapsed, tx)) => {
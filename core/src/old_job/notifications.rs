@@ -0,0 +1,76 @@
+use crate::{
+	api::notifications::{NotificationData, NotificationKind},
+	library::{Library, WebhookEvent},
+};
+
+use std::{collections::HashSet, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{JobReport, JobStatus};
+
+/// Per library settings for notifying users when jobs are done, stored in the library config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobNotificationSettings {
+	/// Names of the jobs that trigger notifications, no job triggers them if empty
+	#[serde(default)]
+	pub job_names: HashSet<String>,
+	#[serde(default)]
+	pub on_completed: bool,
+	#[serde(default)]
+	pub on_failed: bool,
+	/// Show a desktop notification on the client, webhooks get the `jobFinished` events of the
+	/// library instead
+	#[serde(default)]
+	pub desktop: bool,
+}
+
+impl JobNotificationSettings {
+	fn should_notify(&self, report: &JobReport) -> bool {
+		self.job_names.contains(&report.name)
+			&& match report.status {
+				JobStatus::Completed | JobStatus::CompletedWithErrors => self.on_completed,
+				JobStatus::Failed => self.on_failed,
+				_ => false,
+			}
+	}
+}
+
+/// Sends the notifications configured for the library about a job that just finished.
+pub(super) async fn notify_job_finished(library: Arc<Library>, report: JobReport) {
+	library
+		.emit_webhook_event(WebhookEvent::job_finished(&report))
+		.await;
+
+	let config = library.config().await;
+	let settings = &config.job_notifications;
+
+	if !settings.should_notify(&report) {
+		return;
+	}
+
+	if settings.desktop {
+		let (kind, outcome) = match report.status {
+			JobStatus::Failed => (NotificationKind::Error, "failed"),
+			JobStatus::CompletedWithErrors => (NotificationKind::Warning, "completed with errors"),
+			_ => (NotificationKind::Success, "completed"),
+		};
+
+		library
+			.emit_notification(
+				NotificationData {
+					title: format!("Job {outcome}"),
+					content: format!(
+						"{} {outcome} in library '{}'",
+						report.name,
+						config.name.as_str()
+					),
+					kind,
+				},
+				None,
+			)
+			.await;
+	}
+}
@@ -0,0 +1,142 @@
+use sd_p2p::RemoteIdentity;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{JobHistoryRetention, JobResourceLimits, RetryPolicy};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq, Type)]
+pub struct JobsPreferences {
+	/// Retry policies keyed by job name, jobs without an entry here are never retried
+	#[serde(default)]
+	retry_policies: HashMap<String, RetryPolicy>,
+	#[serde(default)]
+	history_retention: JobHistoryRetention,
+	/// Resource caps keyed by job name, jobs without an entry here run unrestricted
+	#[serde(default)]
+	resource_limits: HashMap<String, JobResourceLimits>,
+	/// Maximum amount of jobs of each type running at the same time, keyed by job name
+	#[serde(default)]
+	concurrency_limits: HashMap<String, u32>,
+	#[serde(default)]
+	offload: JobOffloadPreferences,
+}
+
+/// Settings for running the heavy work of jobs on another node of the same library.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct JobOffloadPreferences {
+	/// Run work offloaded by other nodes of our libraries
+	#[serde(default)]
+	pub accept: bool,
+	/// Node generating thumbnails for us, they are generated locally while it isn't connected
+	#[serde(default)]
+	pub thumbnails_target: Option<RemoteIdentity>,
+}
+
+impl JobsPreferences {
+	pub fn retry_policy(&self, job_name: &str) -> Option<&RetryPolicy> {
+		self.retry_policies.get(job_name)
+	}
+
+	pub fn retry_policies(&self) -> &HashMap<String, RetryPolicy> {
+		&self.retry_policies
+	}
+
+	pub fn set_retry_policy(
+		&mut self,
+		job_name: impl Into<String>,
+		policy: Option<RetryPolicy>,
+	) -> &mut Self {
+		let job_name = job_name.into();
+
+		match policy {
+			Some(policy) if policy.max_attempts > 1 => {
+				self.retry_policies.insert(job_name, policy);
+			}
+			_ => {
+				self.retry_policies.remove(&job_name);
+			}
+		}
+
+		self
+	}
+
+	pub fn history_retention(&self) -> JobHistoryRetention {
+		self.history_retention
+	}
+
+	pub fn set_history_retention(&mut self, retention: JobHistoryRetention) -> &mut Self {
+		self.history_retention = retention;
+
+		self
+	}
+
+	pub fn resource_limits(&self, job_name: &str) -> JobResourceLimits {
+		self.resource_limits
+			.get(job_name)
+			.copied()
+			.unwrap_or_default()
+	}
+
+	pub fn all_resource_limits(&self) -> &HashMap<String, JobResourceLimits> {
+		&self.resource_limits
+	}
+
+	pub fn set_resource_limits(
+		&mut self,
+		job_name: impl Into<String>,
+		limits: JobResourceLimits,
+	) -> &mut Self {
+		let job_name = job_name.into();
+		let limits = limits.normalized();
+
+		if limits.is_unlimited() {
+			self.resource_limits.remove(&job_name);
+		} else {
+			self.resource_limits.insert(job_name, limits);
+		}
+
+		self
+	}
+
+	pub fn concurrency_limit(&self, job_name: &str) -> Option<u32> {
+		self.concurrency_limits.get(job_name).copied()
+	}
+
+	pub fn concurrency_limits(&self) -> &HashMap<String, u32> {
+		&self.concurrency_limits
+	}
+
+	/// A limit of 0 makes no sense, as jobs of this type would never run, so we remove the limit instead
+	pub fn set_concurrency_limit(
+		&mut self,
+		job_name: impl Into<String>,
+		limit: Option<u32>,
+	) -> &mut Self {
+		let job_name = job_name.into();
+
+		match limit {
+			Some(limit) if limit > 0 => {
+				self.concurrency_limits.insert(job_name, limit);
+			}
+			_ => {
+				self.concurrency_limits.remove(&job_name);
+			}
+		}
+
+		self
+	}
+
+	pub fn offload(&self) -> JobOffloadPreferences {
+		self.offload
+	}
+
+	pub fn set_offload(&mut self, offload: JobOffloadPreferences) -> &mut Self {
+		self.offload = offload;
+
+		self
+	}
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Set by the user on a job to reorder the queue and to change how much of the machine it
+/// gets while it runs. It is kept for the whole chain of jobs, like the indexer followed by
+/// the file identifier and the media processor.
+#[derive(
+	Debug, Clone, Copy, Default, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum JobPriority {
+	/// Runs on a single thread, and only starts once no other job is waiting
+	Low,
+	#[default]
+	Normal,
+	/// Starts before any other waiting job
+	High,
+}
+
+/// Picks the job to run next out of the queued ones that can run now: the highest priority
+/// one, the oldest between jobs of the same priority.
+pub(super) fn next_in_queue(
+	priorities: impl IntoIterator<Item = (usize, JobPriority)>,
+) -> Option<usize> {
+	priorities
+		.into_iter()
+		// `max_by_key` returns the last of the equal elements, so we reverse the order of them
+		.max_by_key(|(idx, priority)| (*priority, std::cmp::Reverse(*idx)))
+		.map(|(idx, _)| idx)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn highest_priority_first_then_oldest() {
+		assert_eq!(next_in_queue([]), None);
+
+		assert_eq!(
+			next_in_queue([
+				(0, JobPriority::Normal),
+				(1, JobPriority::Low),
+				(2, JobPriority::Normal)
+			]),
+			Some(0)
+		);
+
+		assert_eq!(
+			next_in_queue([
+				(0, JobPriority::Low),
+				(2, JobPriority::High),
+				(3, JobPriority::Normal),
+				(4, JobPriority::High)
+			]),
+			Some(2)
+		);
+	}
+}
@@ -2,7 +2,7 @@ use crate::library::Library;
 
 use sd_core_prisma_helpers::job_without_data;
 
-use sd_prisma::prisma::job;
+use sd_prisma::prisma::{job, location};
 use sd_utils::db::{maybe_missing, MissingFieldError};
 
 use std::{
@@ -16,7 +16,7 @@ use specta::Type;
 use tracing::error;
 use uuid::Uuid;
 
-use super::JobError;
+use super::{JobError, JobItemProgress, JobRetryAttempt};
 
 #[derive(Debug)]
 pub enum JobReportUpdate {
@@ -24,6 +24,8 @@ pub enum JobReportUpdate {
 	CompletedTaskCount(usize),
 	Message(String),
 	Phase(String),
+	/// Progress on the single item being processed, streamed to clients but not persisted
+	Item(JobItemProgress),
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -37,12 +39,16 @@ pub struct JobReport {
 	#[specta(type = Option<HashMap<String, serde_json::Value>>)]
 	pub metadata: Option<serde_json::Value>,
 	pub errors_text: Vec<String>,
+	/// History of failed runs that were retried according to the job's retry policy
+	pub retries: Vec<JobRetryAttempt>,
 
 	pub created_at: Option<DateTime<Utc>>,
 	pub started_at: Option<DateTime<Utc>>,
 	pub completed_at: Option<DateTime<Utc>>,
 
 	pub parent_id: Option<Uuid>,
+	/// The location this job acts upon, if any
+	pub location_id: Option<location::id::Type>,
 
 	pub status: JobStatus,
 	pub task_count: i32,
@@ -83,12 +89,14 @@ impl TryFrom<job::Data> for JobReport {
 				.errors_text
 				.map(|errors_str| errors_str.split("\n\n").map(str::to_string).collect())
 				.unwrap_or_default(),
+			retries: deserialize_retries(data.retries),
 			created_at: data.date_created.map(DateTime::into),
 			started_at: data.date_started.map(DateTime::into),
 			completed_at: data.date_completed.map(DateTime::into),
 			parent_id: data
 				.parent_id
 				.map(|id| Uuid::from_slice(&id).expect("corrupted database")),
+			location_id: data.location_id,
 			status: JobStatus::try_from(maybe_missing(data.status, "job.status")?)
 				.expect("corrupted database"),
 			task_count: data.task_count.unwrap_or(0),
@@ -124,12 +132,14 @@ impl TryFrom<job_without_data::Data> for JobReport {
 				.errors_text
 				.map(|errors_str| errors_str.split("\n\n").map(str::to_string).collect())
 				.unwrap_or_default(),
+			retries: deserialize_retries(data.retries),
 			created_at: data.date_created.map(DateTime::into),
 			started_at: data.date_started.map(DateTime::into),
 			completed_at: data.date_completed.map(DateTime::into),
 			parent_id: data
 				.parent_id
 				.map(|id| Uuid::from_slice(&id).expect("corrupted database")),
+			location_id: data.location_id,
 			status: JobStatus::try_from(maybe_missing(data.status, "job.status")?)
 				.expect("corrupted database"),
 			task_count: data.task_count.unwrap_or(0),
@@ -144,6 +154,17 @@ impl TryFrom<job_without_data::Data> for JobReport {
 	}
 }
 
+fn deserialize_retries(retries: Option<Vec<u8>>) -> Vec<JobRetryAttempt> {
+	retries
+		.map(|retries| {
+			serde_json::from_slice(&retries).unwrap_or_else(|e| {
+				error!("Failed to deserialize job retries: {}", e);
+				vec![]
+			})
+		})
+		.unwrap_or_default()
+}
+
 impl JobReport {
 	pub fn new(uuid: Uuid, name: String) -> Self {
 		Self {
@@ -155,10 +176,12 @@ impl JobReport {
 			completed_at: None,
 			status: JobStatus::Queued,
 			errors_text: vec![],
+			retries: vec![],
 			task_count: 0,
 			data: None,
 			metadata: None,
 			parent_id: None,
+			location_id: None,
 			completed_task_count: 0,
 			phase: String::new(),
 			message: String::new(),
@@ -204,6 +227,7 @@ impl JobReport {
 						job::date_started::set(self.started_at.map(|d| d.into())),
 						job::task_count::set(Some(1)),
 						job::completed_task_count::set(Some(0)),
+						job::location_id::set(self.location_id),
 					],
 					[self
 						.parent_id
@@ -232,6 +256,11 @@ impl JobReport {
 					),
 					job::data::set(self.data.clone()),
 					job::metadata::set(serde_json::to_vec(&self.metadata).ok()),
+					job::retries::set(
+						(!self.retries.is_empty())
+							.then(|| serde_json::to_vec(&self.retries).ok())
+							.flatten(),
+					),
 					job::task_count::set(Some(self.task_count)),
 					job::completed_task_count::set(Some(self.completed_task_count)),
 					job::date_started::set(self.started_at.map(Into::into)),
@@ -245,7 +274,7 @@ impl JobReport {
 }
 
 #[repr(i32)]
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, Eq, PartialEq, Hash)]
 pub enum JobStatus {
 	Queued = 0,
 	Running = 1,
@@ -292,6 +321,7 @@ pub struct JobReportBuilder {
 	pub action: Option<String>,
 	pub metadata: Option<serde_json::Value>,
 	pub parent_id: Option<Uuid>,
+	pub location_id: Option<location::id::Type>,
 }
 
 impl JobReportBuilder {
@@ -305,10 +335,12 @@ impl JobReportBuilder {
 			completed_at: None,
 			status: JobStatus::Queued,
 			errors_text: vec![],
+			retries: vec![],
 			task_count: 0,
 			data: None,
 			metadata: self.metadata,
 			parent_id: self.parent_id,
+			location_id: self.location_id,
 			completed_task_count: 0,
 			phase: String::new(),
 			message: String::new(),
@@ -323,6 +355,7 @@ impl JobReportBuilder {
 			action: None,
 			metadata: None,
 			parent_id: None,
+			location_id: None,
 		}
 	}
 
@@ -340,4 +373,9 @@ impl JobReportBuilder {
 		self.parent_id = Some(parent_id);
 		self
 	}
+
+	pub fn with_location_id(mut self, location_id: location::id::Type) -> Self {
+		self.location_id = Some(location_id);
+		self
+	}
 }
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::JobError;
+
+/// Broad classes of errors a job can fail with, used by [`RetryPolicy`] to decide
+/// if a failed job is worth running again.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum JobErrorClass {
+	/// Errors coming from the database, usually transient (locked database, busy, etc)
+	Database,
+	/// Errors reading or writing files on disk
+	FileIO,
+	/// The job didn't report any progress for too long
+	Timeout,
+	/// Errors specific to each job implementation
+	Job,
+	/// Internal errors that will most likely happen again, like corrupted job state
+	Internal,
+}
+
+impl JobError {
+	pub fn class(&self) -> JobErrorClass {
+		match self {
+			Self::Database(_) => JobErrorClass::Database,
+			Self::FileIO(_) => JobErrorClass::FileIO,
+			Self::Timeout(_) => JobErrorClass::Timeout,
+			Self::Location(_)
+			| Self::Indexer(_)
+			| Self::MediaProcessor(_)
+			| Self::FileIdentifier(_)
+			| Self::Validator(_)
+			| Self::FileSystemJobsError(_) => JobErrorClass::Job,
+			Self::JoinTask(_)
+			| Self::StateEncode(_)
+			| Self::StateDecode(_)
+			| Self::MetadataSerialization(_)
+			| Self::UnknownJobName(_, _)
+			| Self::MissingJobDataState(_, _)
+			| Self::MissingReport { .. }
+			| Self::MissingData { .. }
+			| Self::InvalidJobStatusInt(_)
+			| Self::MissingField(_)
+			| Self::MissingFromDb(_, _)
+			| Self::Critical(_)
+			| Self::EarlyFinish { .. }
+			| Self::JobDataNotFound(_)
+			| Self::Paused(_, _)
+			| Self::Canceled(_) => JobErrorClass::Internal,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Backoff {
+	/// Always wait the same amount of time between attempts
+	Fixed { delay_secs: u32 },
+	/// Double the delay after each attempt, starting at `initial_delay_secs` and never
+	/// going over `max_delay_secs`
+	Exponential {
+		initial_delay_secs: u32,
+		max_delay_secs: u32,
+	},
+}
+
+impl Default for Backoff {
+	fn default() -> Self {
+		Self::Exponential {
+			initial_delay_secs: 5,
+			max_delay_secs: 5 * 60,
+		}
+	}
+}
+
+impl Backoff {
+	/// Delay to wait before running the attempt number `attempt` (1 being the first retry)
+	pub fn delay(&self, attempt: u32) -> Duration {
+		match *self {
+			Self::Fixed { delay_secs } => Duration::from_secs(u64::from(delay_secs)),
+			Self::Exponential {
+				initial_delay_secs,
+				max_delay_secs,
+			} => Duration::from_secs(
+				u64::from(initial_delay_secs)
+					.saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1)))
+					.min(u64::from(max_delay_secs)),
+			),
+		}
+	}
+}
+
+/// How a job type should be retried when it fails.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+	/// Total number of runs a job can have, including the first one
+	pub max_attempts: u32,
+	#[serde(default)]
+	pub backoff: Backoff,
+	/// Only errors from these classes will trigger a retry
+	#[serde(default = "default_retry_on")]
+	pub retry_on: Vec<JobErrorClass>,
+}
+
+fn default_retry_on() -> Vec<JobErrorClass> {
+	vec![
+		JobErrorClass::Database,
+		JobErrorClass::FileIO,
+		JobErrorClass::Timeout,
+	]
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 1,
+			backoff: Backoff::default(),
+			retry_on: default_retry_on(),
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Returns how long to wait before retrying, or `None` if the job must be marked as failed.
+	///
+	/// `previous_retries` is the amount of retries already done for this job.
+	pub fn next_delay(&self, error: &JobError, previous_retries: usize) -> Option<Duration> {
+		let attempt = u32::try_from(previous_retries).ok()?.checked_add(1)?;
+
+		(attempt < self.max_attempts && self.retry_on.contains(&error.class()))
+			.then(|| self.backoff.delay(attempt))
+	}
+}
+
+/// A failed run of a job that was retried, kept in the job report history.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct JobRetryAttempt {
+	pub attempt: u32,
+	pub error_class: JobErrorClass,
+	pub error: String,
+	pub failed_at: DateTime<Utc>,
+	pub retry_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exponential_backoff_is_capped() {
+		let backoff = Backoff::Exponential {
+			initial_delay_secs: 2,
+			max_delay_secs: 10,
+		};
+
+		assert_eq!(backoff.delay(1), Duration::from_secs(2));
+		assert_eq!(backoff.delay(2), Duration::from_secs(4));
+		assert_eq!(backoff.delay(3), Duration::from_secs(8));
+		assert_eq!(backoff.delay(4), Duration::from_secs(10));
+		assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(10));
+	}
+
+	#[test]
+	fn retries_only_on_configured_classes() {
+		let policy = RetryPolicy {
+			max_attempts: 3,
+			backoff: Backoff::Fixed { delay_secs: 1 },
+			retry_on: vec![JobErrorClass::Timeout],
+		};
+
+		let timeout = JobError::Timeout(Duration::from_secs(600));
+		assert_eq!(policy.next_delay(&timeout, 0), Some(Duration::from_secs(1)));
+		assert_eq!(policy.next_delay(&timeout, 1), Some(Duration::from_secs(1)));
+		assert_eq!(policy.next_delay(&timeout, 2), None);
+
+		assert_eq!(
+			policy.next_delay(&JobError::Critical("corrupted state"), 0),
+			None
+		);
+	}
+}
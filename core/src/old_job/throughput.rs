@@ -0,0 +1,144 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::time::Instant;
+
+use super::JobItemProgress;
+
+/// How far back we look to compute the current rates, so they follow speed changes quickly
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(10);
+/// Minimum time between the samples we keep, as jobs can report progress thousands of times per
+/// second
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct Throughput {
+	pub(super) items_per_sec: f64,
+	pub(super) bytes_processed: u64,
+	pub(super) bytes_per_sec: f64,
+}
+
+impl Throughput {
+	/// Seconds left to complete `remaining_items` at the current rate, `None` while we don't
+	/// know the rate yet.
+	pub(super) fn estimated_remaining_secs(&self, remaining_items: u64) -> Option<u32> {
+		(self.items_per_sec > 0.0)
+			.then(|| (remaining_items as f64 / self.items_per_sec).ceil() as u32)
+	}
+}
+
+/// Keeps track of the items and bytes processed by a job to compute its throughput.
+#[derive(Debug, Default)]
+pub(super) struct ThroughputTracker {
+	/// Completed items and bytes processed at each instant, oldest first
+	samples: VecDeque<(Instant, u64, u64)>,
+	/// The item being processed and how many of its bytes were processed so far
+	current_item: Option<(String, u64)>,
+	finished_items_bytes: u64,
+}
+
+impl ThroughputTracker {
+	/// Accounts for the bytes of an item, where a new item means the previous one is done.
+	pub(super) fn record_item(&mut self, item: &JobItemProgress) {
+		let bytes_processed = item.bytes_processed.unwrap_or_default();
+
+		if let Some((current_item, current_bytes)) = &mut self.current_item {
+			if *current_item == item.item {
+				*current_bytes = bytes_processed;
+				return;
+			}
+		}
+
+		if let Some((_, bytes)) = self
+			.current_item
+			.replace((item.item.clone(), bytes_processed))
+		{
+			self.finished_items_bytes += bytes;
+		}
+	}
+
+	pub(super) fn current_item(&self) -> Option<&str> {
+		self.current_item.as_ref().map(|(item, _)| item.as_str())
+	}
+
+	/// Forgets the rates so far, as the items of a new phase usually take a different time.
+	pub(super) fn restart(&mut self) {
+		self.samples.clear();
+	}
+
+	pub(super) fn update(&mut self, now: Instant, completed_items: u64) -> Throughput {
+		let bytes_processed = self.finished_items_bytes
+			+ self
+				.current_item
+				.as_ref()
+				.map_or(0, |(_, current_bytes)| *current_bytes);
+
+		if self
+			.samples
+			.back()
+			.map_or(true, |(at, ..)| now.duration_since(*at) >= SAMPLE_INTERVAL)
+		{
+			self.samples
+				.push_back((now, completed_items, bytes_processed));
+		}
+
+		// Keeping the newest sample older than the window as the start of it
+		while self
+			.samples
+			.get(1)
+			.is_some_and(|(at, ..)| now.duration_since(*at) >= THROUGHPUT_WINDOW)
+		{
+			self.samples.pop_front();
+		}
+
+		let Some((first_at, first_items, first_bytes)) = self.samples.front() else {
+			return Throughput::default();
+		};
+
+		let elapsed = now.duration_since(*first_at).as_secs_f64();
+		if elapsed == 0.0 {
+			return Throughput {
+				bytes_processed,
+				..Default::default()
+			};
+		}
+
+		Throughput {
+			items_per_sec: completed_items.saturating_sub(*first_items) as f64 / elapsed,
+			bytes_processed,
+			bytes_per_sec: bytes_processed.saturating_sub(*first_bytes) as f64 / elapsed,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rates_over_the_last_seconds() {
+		let mut tracker = ThroughputTracker::default();
+		let start = Instant::now();
+
+		tracker.record_item(&JobItemProgress::new("a").with_bytes(100, 100));
+		assert_eq!(tracker.update(start, 0).items_per_sec, 0.0);
+
+		// A new item means the previous one is done
+		tracker.record_item(&JobItemProgress::new("b").with_bytes(50, 200));
+		tracker.record_item(&JobItemProgress::new("b").with_bytes(200, 200));
+		let throughput = tracker.update(start + Duration::from_secs(2), 4);
+		assert_eq!(throughput.bytes_processed, 300);
+		assert_eq!(throughput.items_per_sec, 2.0);
+		assert_eq!(throughput.bytes_per_sec, 100.0);
+		assert_eq!(throughput.estimated_remaining_secs(5), Some(3));
+		assert_eq!(tracker.current_item(), Some("b"));
+
+		// The first samples are out of the window now
+		let throughput = tracker.update(start + Duration::from_secs(14), 16);
+		assert_eq!(throughput.items_per_sec, 1.0);
+
+		tracker.restart();
+		let throughput = tracker.update(start + Duration::from_secs(15), 0);
+		assert_eq!(throughput.items_per_sec, 0.0);
+		assert_eq!(throughput.estimated_remaining_secs(5), None);
+	}
+}
@@ -1,7 +1,8 @@
-use crate::{api::CoreEvent, invalidate_query, library::Library, Node};
+use crate::{api::CoreEvent, invalidate_query, library::Library, metrics::METRICS, Node};
 
 use std::{
 	fmt,
+	future::Future,
 	pin::pin,
 	sync::{
 		atomic::{AtomicBool, Ordering},
@@ -16,6 +17,7 @@ use futures::stream::{self, StreamExt};
 use futures_concurrency::stream::Merge;
 use serde::Serialize;
 use serde_json::json;
+use serde_with::{serde_as, DisplayFromStr};
 use specta::Type;
 use tokio::{
 	spawn,
@@ -24,17 +26,20 @@ use tokio::{
 	time::{interval, timeout, Instant, MissedTickBehavior},
 };
 use tokio_stream::wrappers::IntervalStream;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, info_span, trace, warn, Instrument};
 use uuid::Uuid;
 
 use super::{
-	DynJob, JobError, JobIdentity, JobReport, JobReportUpdate, JobRunErrors, JobRunOutput,
-	JobStatus, OldJobs,
+	cleanup::JobCleanupHandlers, job_subsystem, notifications::notify_job_finished,
+	throughput::ThroughputTracker, DynJob, JobCleanupId, JobError, JobIdentity, JobPriority,
+	JobReport, JobReportUpdate, JobResourceLimiter, JobResourceLimits, JobRetryAttempt,
+	JobRunErrors, JobRunOutput, JobStatus, OldJobs, RetryPolicy,
 };
 
 const FIVE_SECS: Duration = Duration::from_secs(5);
 const FIVE_MINUTES: Duration = Duration::from_secs(10 * 60);
 
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Type)]
 pub struct JobProgressEvent {
 	pub id: Uuid,
@@ -44,6 +49,55 @@ pub struct JobProgressEvent {
 	pub phase: String,
 	pub message: String,
 	pub estimated_completion: DateTime<Utc>,
+	/// Tasks completed per second over the last few seconds, tasks are usually files
+	pub items_per_sec: f64,
+	/// Sum of the bytes of the items reported by the job
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub bytes_processed: u64,
+	pub bytes_per_sec: f64,
+	/// Time left at the current rate, `None` until the job completed some tasks in this phase
+	pub estimated_remaining_secs: Option<u32>,
+	/// Last item reported by the job, usually the path of a file
+	pub current_path: Option<String>,
+}
+
+/// Progress of a single item (usually a file) being processed by a job
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct JobItemProgress {
+	pub item: String,
+	#[specta(type = Option<String>)]
+	#[serde_as(as = "Option<DisplayFromStr>")]
+	pub bytes_processed: Option<u64>,
+	#[specta(type = Option<String>)]
+	#[serde_as(as = "Option<DisplayFromStr>")]
+	pub bytes_total: Option<u64>,
+}
+
+impl JobItemProgress {
+	pub fn new(item: impl Into<String>) -> Self {
+		Self {
+			item: item.into(),
+			bytes_processed: None,
+			bytes_total: None,
+		}
+	}
+
+	pub fn with_bytes(mut self, bytes_processed: u64, bytes_total: u64) -> Self {
+		self.bytes_processed = Some(bytes_processed);
+		self.bytes_total = Some(bytes_total);
+		self
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct JobItemProgressEvent {
+	pub id: Uuid,
+	pub library_id: Uuid,
+	pub phase: String,
+	#[serde(flatten)]
+	pub item: JobItemProgress,
 }
 
 // used to update the worker state from inside the worker thread
@@ -51,6 +105,7 @@ pub struct JobProgressEvent {
 pub enum WorkerEvent {
 	Progressed(Vec<JobReportUpdate>),
 	Paused,
+	Resumed,
 	Stop,
 }
 
@@ -69,6 +124,10 @@ pub struct WorkerContext {
 	pub library: Arc<Library>,
 	pub node: Arc<Node>,
 	pub(super) events_tx: chan::Sender<WorkerEvent>,
+	paused_tx: watch::Sender<bool>,
+	/// Resource caps configured for this job type
+	pub limiter: JobResourceLimiter,
+	pub(super) cleanups: JobCleanupHandlers,
 }
 
 impl fmt::Debug for WorkerContext {
@@ -87,11 +146,53 @@ impl Drop for WorkerContext {
 }
 impl WorkerContext {
 	pub fn pause(&self) {
+		self.paused_tx.send_replace(true);
 		if self.events_tx.send_blocking(WorkerEvent::Paused).is_err() {
 			error!("Error sending worker context pause event");
 		}
 	}
 
+	pub fn resume(&self) {
+		self.paused_tx.send_replace(false);
+		if self.events_tx.send_blocking(WorkerEvent::Resumed).is_err() {
+			error!("Error sending worker context resume event");
+		}
+	}
+
+	/// Waits while the job is paused. Steps that go through many files should call it between
+	/// them, otherwise a pause only takes effect once the running step is done.
+	pub async fn checkpoint(&self) {
+		if *self.paused_tx.borrow() {
+			// The sender is in this same context, so it can't be dropped while we wait
+			self.paused_tx
+				.subscribe()
+				.wait_for(|paused| !paused)
+				.await
+				.ok();
+		}
+	}
+
+	/// Changes whenever the job is paused or resumed, for jobs with work done for them
+	/// elsewhere, which must be held back while they're paused.
+	pub fn paused_rx(&self) -> watch::Receiver<bool> {
+		self.paused_tx.subscribe()
+	}
+
+	/// Registers a `cleanup` to be executed if the job gets canceled, like removing a
+	/// partially written file. Must be unregistered with [`WorkerContext::unregister_cleanup`]
+	/// once the work it protects is done.
+	pub fn register_cleanup(
+		&self,
+		description: impl Into<String>,
+		cleanup: impl Future<Output = Result<(), JobError>> + Send + 'static,
+	) -> JobCleanupId {
+		self.cleanups.register(description.into(), cleanup)
+	}
+
+	pub fn unregister_cleanup(&self, id: JobCleanupId) {
+		self.cleanups.unregister(id);
+	}
+
 	pub fn progress_msg(&self, msg: String) {
 		self.progress(vec![JobReportUpdate::Message(msg)]);
 	}
@@ -113,10 +214,12 @@ impl WorkerContext {
 // once the job is complete the worker will exit
 pub struct Worker {
 	pub(super) library_id: Uuid,
+	job_name: &'static str,
 	commands_tx: chan::Sender<WorkerCommand>,
 	report_watch_tx: Arc<watch::Sender<JobReport>>,
 	report_watch_rx: watch::Receiver<JobReport>,
 	paused: AtomicBool,
+	priority_tx: watch::Sender<JobPriority>,
 }
 
 impl Worker {
@@ -127,13 +230,33 @@ impl Worker {
 		library: Arc<Library>,
 		node: Arc<Node>,
 		job_manager: Arc<OldJobs>,
+		priority: JobPriority,
 	) -> Result<Self, JobError> {
 		let (commands_tx, commands_rx) = chan::bounded(8);
 
+		let job_name = job.name();
 		let job_hash = job.hash();
 
+		let jobs_preferences = node.config.get().await.preferences.jobs;
+
+		// Keeping a snapshot of the job state to be able to run it again in case of failure
+		let retry = jobs_preferences
+			.retry_policy(job_name)
+			.cloned()
+			.map(|policy| {
+				job.serialize_state().map(|initial_state| JobRetry {
+					policy,
+					initial_state,
+				})
+			})
+			.transpose()?;
+
+		let limits = jobs_preferences.resource_limits(job_name);
+
 		let start_time = Utc::now();
 
+		METRICS.record_job_run(job_name);
+
 		report.status = JobStatus::Running;
 		if report.started_at.is_none() {
 			report.started_at = Some(start_time);
@@ -151,10 +274,29 @@ impl Worker {
 
 		invalidate_queries(&library);
 
+		// The progress subscribers of a job that waited in the queue hear about it as soon as it
+		// starts, instead of on its first update
+		library.emit(CoreEvent::JobProgress(JobProgressEvent {
+			id: report.id,
+			library_id: library.id,
+			task_count: report.task_count,
+			completed_task_count: report.completed_task_count,
+			phase: report.phase.clone(),
+			message: report.message.clone(),
+			estimated_completion: report.estimated_completion,
+			items_per_sec: 0.0,
+			bytes_processed: 0,
+			bytes_per_sec: 0.0,
+			estimated_remaining_secs: None,
+			current_path: None,
+		}));
+
 		let (report_watch_tx, report_watch_rx) = watch::channel(report.clone());
 		let report_watch_tx = Arc::new(report_watch_tx);
 		let library_id = library.id;
 
+		let (priority_tx, priority_rx) = watch::channel(priority);
+
 		// spawn task to handle running the job
 		spawn(Self::do_work(
 			id,
@@ -163,6 +305,9 @@ impl Worker {
 				manager: job_manager,
 				hash: job_hash,
 				report,
+				retry,
+				limits,
+				priority_rx,
 			},
 			Arc::clone(&report_watch_tx),
 			start_time,
@@ -173,13 +318,19 @@ impl Worker {
 
 		Ok(Self {
 			library_id,
+			job_name,
 			commands_tx,
 			report_watch_tx,
 			report_watch_rx,
 			paused: AtomicBool::new(false),
+			priority_tx,
 		})
 	}
 
+	pub fn job_name(&self) -> &'static str {
+		self.job_name
+	}
+
 	pub async fn pause(&self) {
 		if self.report_watch_rx.borrow().status == JobStatus::Running {
 			self.paused.store(true, Ordering::Relaxed);
@@ -263,11 +414,16 @@ impl Worker {
 		self.paused.load(Ordering::Relaxed)
 	}
 
+	pub fn set_priority(&self, priority: JobPriority) {
+		self.priority_tx.send_replace(priority);
+	}
+
 	fn track_progress(
 		report: &mut JobReport,
 		last_report_watch_update: &mut Instant,
 		report_watch_tx: &watch::Sender<JobReport>,
 		start_time: DateTime<Utc>,
+		throughput_tracker: &mut ThroughputTracker,
 		updates: Vec<JobReportUpdate>,
 		library: &Library,
 	) {
@@ -296,6 +452,16 @@ impl Worker {
 						report.phase
 					);
 					report.phase = phase;
+					throughput_tracker.restart();
+				}
+				JobReportUpdate::Item(item) => {
+					throughput_tracker.record_item(&item);
+					library.emit(CoreEvent::JobItemProgress(JobItemProgressEvent {
+						id: report.id,
+						library_id: library.id,
+						phase: report.phase.clone(),
+						item,
+					}));
 				}
 			}
 		}
@@ -326,6 +492,8 @@ impl Worker {
 			*last_report_watch_update = Instant::now();
 		}
 
+		let throughput = throughput_tracker.update(Instant::now(), completed_task_count as u64);
+
 		// emit a CoreEvent
 		library.emit(CoreEvent::JobProgress(JobProgressEvent {
 			id: report.id,
@@ -335,6 +503,12 @@ impl Worker {
 			estimated_completion: report.estimated_completion,
 			phase: report.phase.clone(),
 			message: report.message.clone(),
+			items_per_sec: throughput.items_per_sec,
+			bytes_processed: throughput.bytes_processed,
+			bytes_per_sec: throughput.bytes_per_sec,
+			estimated_remaining_secs: throughput
+				.estimated_remaining_secs(remaining_task_count as u64),
+			current_path: throughput_tracker.current_item().map(str::to_string),
 		}));
 	}
 
@@ -345,6 +519,9 @@ impl Worker {
 			manager,
 			hash,
 			mut report,
+			retry,
+			limits,
+			priority_rx,
 		}: JobWorkTable,
 		report_watch_tx: Arc<watch::Sender<JobReport>>,
 		start_time: DateTime<Utc>,
@@ -360,6 +537,7 @@ impl Worker {
 		let mut last_update_received_at = Instant::now();
 
 		let mut last_reporter_watch_update = Instant::now();
+		let mut throughput_tracker = ThroughputTracker::default();
 		invalidate_query!(library, "jobs.reports");
 
 		let mut finalized_events_rx = pin!(events_rx.clone());
@@ -367,21 +545,41 @@ impl Worker {
 		let mut is_paused = false;
 
 		let mut run_task = {
+			let span = info_span!(
+				"job",
+				job.name = job.name(),
+				job.id = %report.id,
+				library.id = %library.id,
+			);
 			let library = Arc::clone(&library);
-			spawn(async move {
-				let job_result = job
-					.run(
-						WorkerContext {
-							library,
-							node,
-							events_tx,
-						},
-						commands_rx,
-					)
-					.await;
-
-				(job, job_result)
-			})
+			let node = Arc::clone(&node);
+			let limiter = JobResourceLimiter::new(limits).with_priority(priority_rx);
+			let limiter = match job_subsystem(job.name()) {
+				Some(subsystem) => {
+					limiter.with_background_policy(node.background_policy.subscribe(), subsystem)
+				}
+				None => limiter,
+			};
+			spawn(
+				async move {
+					let job_result = job
+						.run(
+							WorkerContext {
+								library,
+								node,
+								events_tx,
+								paused_tx: watch::channel(false).0,
+								limiter,
+								cleanups: JobCleanupHandlers::default(),
+							},
+							commands_rx,
+						)
+						.await;
+
+					(job, job_result)
+				}
+				.instrument(span),
+			)
 		};
 
 		type RunOutput = (Box<dyn DynJob>, Result<JobRunOutput, JobError>);
@@ -419,32 +617,41 @@ impl Worker {
 								&mut last_reporter_watch_update,
 								&report_watch_tx,
 								start_time,
+								&mut throughput_tracker,
 								updates,
 								&library,
 							);
 						}
 					}
 
-					let next_job =
-						Self::process_job_output(job, job_result, &mut report, &library).await;
+					let outcome =
+						Self::process_job_output(job, job_result, &mut report, &retry, &library)
+							.await;
 
 					report_watch_tx.send(report.clone()).ok();
 
+					METRICS.record_job_outcome(&report);
+
 					debug!(
 						"Worker<id='{worker_id}'> completed Job<id='{}', name='{}'>",
 						report.id, report.name
 					);
 
-					return manager.complete(&library, worker_id, hash, next_job).await;
+					if matches!(outcome, WorkerOutcome::Finished(_)) {
+						emit_finished(&library, &report);
+						spawn(notify_job_finished(Arc::clone(&library), report.clone()));
+					}
+
+					return outcome.finish(manager, &library, worker_id, hash).await;
 				}
 				StreamMessage::NewEvent(WorkerEvent::Progressed(updates)) => {
-					is_paused = false;
 					last_update_received_at = Instant::now();
 					Self::track_progress(
 						&mut report,
 						&mut last_reporter_watch_update,
 						&report_watch_tx,
 						start_time,
+						&mut throughput_tracker,
 						updates,
 						&library,
 					);
@@ -452,6 +659,11 @@ impl Worker {
 				StreamMessage::NewEvent(WorkerEvent::Paused) => {
 					is_paused = true;
 				}
+				StreamMessage::NewEvent(WorkerEvent::Resumed) => {
+					is_paused = false;
+					// The time spent paused doesn't count towards the timeout
+					last_update_received_at = Instant::now();
+				}
 				StreamMessage::NewEvent(WorkerEvent::Stop) => {
 					events_ended = true;
 				}
@@ -481,15 +693,31 @@ impl Worker {
 								break;
 							};
 
-							Self::process_job_output(job, job_result, &mut report, &library).await;
+							let outcome = Self::process_job_output(
+								job,
+								job_result,
+								&mut report,
+								&retry,
+								&library,
+							)
+							.await;
 
 							report_watch_tx.send(report.clone()).ok();
 
+							METRICS.record_job_outcome(&report);
+
 							error!(
 								"Worker<id='{worker_id}'> timed out Job<id='{}', name='{}'>",
 								report.id, report.name
 							);
 
+							if let WorkerOutcome::Retry { job, delay } = outcome {
+								return manager.retry(&library, worker_id, job, delay).await;
+							}
+
+							emit_finished(&library, &report);
+							spawn(notify_job_finished(Arc::clone(&library), report.clone()));
+
 							break;
 						}
 					}
@@ -504,8 +732,9 @@ impl Worker {
 		mut job: Box<dyn DynJob>,
 		job_result: Result<JobRunOutput, JobError>,
 		report: &mut JobReport,
+		retry: &Option<JobRetry>,
 		library: &Library,
-	) -> Option<Box<dyn DynJob>> {
+	) -> WorkerOutcome {
 		// Run the job and handle the result
 		match job_result {
 			// -> Job completed successfully
@@ -534,7 +763,7 @@ impl Worker {
 
 				invalidate_queries(library);
 
-				return next_job;
+				return WorkerOutcome::Finished(next_job);
 			}
 			// -> Job completed with errors
 			Ok(JobRunOutput {
@@ -567,7 +796,7 @@ impl Worker {
 
 				invalidate_queries(library);
 
-				return next_job;
+				return WorkerOutcome::Finished(next_job);
 			}
 			// -> Job paused
 			Err(JobError::Paused(state, signal_tx)) => {
@@ -621,6 +850,53 @@ impl Worker {
 			}
 			// -> Job failed
 			Err(e) => {
+				if let Some((retry, delay)) = retry.as_ref().and_then(|retry| {
+					retry
+						.policy
+						.next_delay(&e, report.retries.len())
+						.map(|delay| (retry, delay))
+				}) {
+					match job.restore_state(&retry.initial_state) {
+						Ok(()) => {
+							warn!(
+								"Job<id='{}', name='{}'> failed with error: {e:#?}; \
+								retrying in {delay:?}",
+								report.id, report.name
+							);
+
+							let now = Utc::now();
+							report.retries.push(JobRetryAttempt {
+								attempt: report.retries.len() as u32 + 1,
+								error_class: e.class(),
+								error: e.to_string(),
+								failed_at: now,
+								retry_at: chrono::Duration::from_std(delay)
+									.ok()
+									.and_then(|delay| now.checked_add_signed(delay))
+									.unwrap_or(now),
+							});
+							report.status = JobStatus::Queued;
+							// Keeping the state, so the retry survives a restart through cold resume
+							report.data = Some(retry.initial_state.clone());
+							if let Err(e) = report.update(library).await {
+								error!("failed to update job report: {:#?}", e);
+							}
+
+							invalidate_queries(library);
+
+							// Put the report back, so the job can be dispatched again
+							*job.report_mut() = Some(report.clone());
+
+							return WorkerOutcome::Retry { job, delay };
+						}
+						Err(restore_error) => error!(
+							"Failed to restore Job<id='{}', name='{}'> state for retry: \
+							{restore_error:#?}",
+							report.id, report.name
+						),
+					}
+				}
+
 				error!(
 					"Job<id='{}', name='{}'> failed with error: {e:#?};",
 					report.id, report.name
@@ -641,7 +917,7 @@ impl Worker {
 			}
 		}
 
-		None
+		WorkerOutcome::Finished(None)
 	}
 }
 
@@ -650,6 +926,53 @@ struct JobWorkTable {
 	manager: Arc<OldJobs>,
 	hash: u64,
 	report: JobReport,
+	retry: Option<JobRetry>,
+	limits: JobResourceLimits,
+	priority_rx: watch::Receiver<JobPriority>,
+}
+
+struct JobRetry {
+	policy: RetryPolicy,
+	initial_state: Vec<u8>,
+}
+
+enum WorkerOutcome {
+	Finished(Option<Box<dyn DynJob>>),
+	Retry {
+		job: Box<dyn DynJob>,
+		delay: Duration,
+	},
+}
+
+impl WorkerOutcome {
+	async fn finish(
+		self,
+		manager: Arc<OldJobs>,
+		library: &Arc<Library>,
+		worker_id: Uuid,
+		job_hash: u64,
+	) {
+		match self {
+			Self::Finished(next_job) => {
+				manager
+					.complete(library, worker_id, job_hash, next_job)
+					.await
+			}
+			Self::Retry { job, delay } => manager.retry(library, worker_id, job, delay).await,
+		}
+	}
+}
+
+/// Lets the progress subscribers of the job know it's over, paused jobs are resumed later so they
+/// aren't.
+pub(super) fn emit_finished(library: &Library, report: &JobReport) {
+	if report.status.is_finished() && report.status != JobStatus::Paused {
+		library.emit(CoreEvent::JobFinished {
+			library_id: library.id,
+			id: report.id,
+			status: report.status,
+		});
+	}
 }
 
 fn invalidate_queries(library: &Library) {
@@ -61,6 +61,19 @@ pub enum P2PEvent {
 	SpacedropRejected {
 		id: Uuid,
 	},
+	PairingRequest {
+		id: Uuid,
+		identity: RemoteIdentity,
+		peer_name: String,
+		/// To compare with the one shown on the other node
+		code: String,
+	},
+	PairingComplete {
+		id: Uuid,
+		identity: RemoteIdentity,
+		/// If both nodes accepted
+		paired: bool,
+	},
 }
 
 /// A P2P hook which listens for events and sends them over a channel which can be connected to the frontend.
@@ -1,5 +1,6 @@
 use crate::{
 	node::{
+		background_policy::Subsystem,
 		config::{self, P2PDiscoveryState, Port},
 		get_hardware_model_name, HardwareModel,
 	},
@@ -14,8 +15,8 @@ use axum::routing::IntoMakeService;
 
 use sd_p2p::{
 	flume::{bounded, Receiver},
-	HookId, Libp2pPeerId, Listener, Mdns, Peer, QuicTransport, RelayServerEntry, RemoteIdentity,
-	UnicastStream, P2P,
+	HookId, Libp2pPeerId, Listener, Mdns, Peer, QuicTransport, QuicTransportError,
+	RelayServerEntry, RemoteIdentity, UnicastStream, P2P,
 };
 use sd_p2p_tunnel::Tunnel;
 use serde::Serialize;
@@ -46,6 +47,7 @@ pub struct P2PManager {
 	pub(crate) events: P2PEvents,
 	pub(super) spacedrop_pairing_reqs: Arc<Mutex<HashMap<Uuid, oneshot::Sender<Option<String>>>>>,
 	pub(super) spacedrop_cancellations: Arc<Mutex<HashMap<Uuid, Arc<AtomicBool>>>>,
+	pub(super) pairing_reqs: Arc<Mutex<HashMap<Uuid, oneshot::Sender<bool>>>>,
 	pub(crate) node_config: Arc<config::Manager>,
 	pub libraries_hook_id: HookId,
 }
@@ -59,7 +61,7 @@ impl P2PManager {
 			Arc<P2PManager>,
 			impl FnOnce(Arc<Node>, IntoMakeService<axum::Router<()>>),
 		),
-		String,
+		QuicTransportError,
 	> {
 		let (tx, rx) = bounded(25);
 		let p2p = P2P::new(SPACEDRIVE_APP_ID, node_config.get().await.identity, tx);
@@ -73,6 +75,7 @@ impl P2PManager {
 			events: P2PEvents::spawn(p2p.clone(), libraries_hook_id),
 			spacedrop_pairing_reqs: Default::default(),
 			spacedrop_cancellations: Default::default(),
+			pairing_reqs: Default::default(),
 			node_config,
 			libraries_hook_id,
 		});
@@ -327,6 +330,10 @@ async fn start(
 
 					match msg {
 						SyncMessage::NewOperations => {
+							node.background_policy
+								.wait_until_allowed(Subsystem::Sync)
+								.await;
+
 							let Err(()) = super::sync::responder(&mut tunnel, library).await else {
 								return;
 							};
@@ -335,6 +342,23 @@ async fn start(
 						}
 					};
 				}
+				Header::Offload(library_id) => {
+					let Err(err) = operations::offload::receiver(&node, library_id, stream).await
+					else {
+						return;
+					};
+
+					error!("Failed to handle offloaded work for library '{library_id}': {err}");
+				}
+				Header::Admin => {
+					let remote = stream.remote_identity();
+					let Err(err) = operations::admin::receiver(&node, stream).await else {
+						return;
+					};
+
+					error!("Failed to handle admin request from node '{remote}': {err}");
+				}
+				Header::Pair(req) => operations::pairing::receiver(&this, req, stream).await,
 				Header::Http => {
 					let remote = stream.remote_identity();
 					let Err(err) = operations::rspc::receiver(stream, &mut service).await else {
@@ -0,0 +1,533 @@
+//! Administration of a node by another one over P2P, like a headless server managed from the
+//! desktop app.
+//!
+//! Each request opens its own stream, and is only answered if the remote node is an instance of
+//! one of our libraries and was granted a permission in our node preferences.
+
+use crate::{
+	api::search::preferences::SearchPreferences,
+	location::{find_location, scan_location, LocationError, ScanState},
+	node::{
+		background_policy::BackgroundPolicyPreferences,
+		config::{NodeConfigError, NodePreferences},
+	},
+	object::media::old_thumbnail::preferences::ThumbnailerPreferences,
+	old_job::{preferences::JobsPreferences, JobManagerError, JobReport},
+	p2p::Header,
+	Node,
+};
+
+use sd_core_prisma_helpers::{job_without_data, location_with_indexer_rules};
+
+use sd_p2p::{RemoteIdentity, UnicastStream};
+use sd_p2p_proto::{decode, encode};
+use sd_p2p_tunnel::Tunnel;
+use sd_prisma::prisma::{job, location, SortOrder};
+use sd_utils::error::FileIOError;
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use rspc::ErrorCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs::{self, File},
+	io::{self, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, SeekFrom},
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const LOGS_DIR: &str = "logs";
+const LOG_FILE_PREFIX: &str = "sd.log";
+/// Only the end of the current log file is read, the rest is too old to be interesting
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const MAX_LOG_LINES: u32 = 1000;
+/// Finished jobs sent for each library, on top of the running ones
+const RECENT_JOBS: i64 = 50;
+
+#[derive(Debug, Error)]
+pub enum AdminError {
+	#[error("node is not connected")]
+	PeerNotFound,
+	#[error("failed to connect to node: {0}")]
+	Connect(String),
+	#[error("tunnel error: {0}")]
+	Tunnel(&'static str),
+	#[error("node refused the request, is this node allowed to administrate it?")]
+	Denied,
+	#[error("request failed on the remote node: {0}")]
+	Remote(String),
+	#[error("unexpected response from the remote node")]
+	UnexpectedResponse,
+	#[error("library '{0}' not found")]
+	LibraryNotFound(Uuid),
+	#[error("invalid node name")]
+	InvalidNodeName,
+	#[error(transparent)]
+	Location(#[from] LocationError),
+	#[error(transparent)]
+	JobManager(#[from] JobManagerError),
+	#[error(transparent)]
+	NodeConfig(#[from] NodeConfigError),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error("error decoding message: {0}")]
+	Decode(#[from] decode::Error),
+	#[error("error decoding message: {0}")]
+	DecodeMessage(#[from] rmp_serde::decode::Error),
+	#[error("error encoding message: {0}")]
+	EncodeMessage(#[from] rmp_serde::encode::Error),
+}
+
+impl From<AdminError> for rspc::Error {
+	fn from(err: AdminError) -> Self {
+		match err {
+			AdminError::PeerNotFound => Self::with_cause(ErrorCode::NotFound, err.to_string(), err),
+			AdminError::Denied => Self::with_cause(ErrorCode::Forbidden, err.to_string(), err),
+			_ => Self::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoteAdminPermission {
+	/// Viewing the jobs, logs and settings of the node
+	View,
+	/// Also rescanning locations and changing the settings of the node
+	Manage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteAdminGrant {
+	pub identity: RemoteIdentity,
+	pub permission: RemoteAdminPermission,
+}
+
+/// Nodes allowed to administrate this one, they must also be instances of one of our libraries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+pub struct RemoteAdminPreferences {
+	#[serde(default)]
+	grants: Vec<RemoteAdminGrant>,
+}
+
+impl RemoteAdminPreferences {
+	pub fn grants(&self) -> &[RemoteAdminGrant] {
+		&self.grants
+	}
+
+	/// Grants the permission to the node, or revokes its access if `None`.
+	pub fn set_grant(
+		&mut self,
+		identity: RemoteIdentity,
+		permission: Option<RemoteAdminPermission>,
+	) {
+		self.grants.retain(|grant| grant.identity != identity);
+
+		if let Some(permission) = permission {
+			self.grants.push(RemoteAdminGrant {
+				identity,
+				permission,
+			});
+		}
+	}
+
+	fn permission(&self, identity: RemoteIdentity) -> Option<RemoteAdminPermission> {
+		self.grants
+			.iter()
+			.find(|grant| grant.identity == identity)
+			.map(|grant| grant.permission)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RemoteNodeSettings {
+	pub name: String,
+	pub preferences: NodePreferences,
+}
+
+/// Settings changed on the remote node, the missing ones are left untouched.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RemoteNodeSettingsUpdate {
+	#[serde(default)]
+	pub name: Option<String>,
+	#[serde(default)]
+	pub preferences: Option<RemoteNodePreferencesUpdate>,
+}
+
+/// The preferences that can be changed remotely, the missing ones are left untouched.
+///
+/// The ones granting trust or running code, like the remote administration grants, the paired
+/// nodes, the API tokens and the script hooks, can only be changed on the node itself.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct RemoteNodePreferencesUpdate {
+	#[serde(default)]
+	pub thumbnailer: Option<ThumbnailerPreferences>,
+	#[serde(default)]
+	pub jobs: Option<JobsPreferences>,
+	#[serde(default)]
+	pub background_policy: Option<BackgroundPolicyPreferences>,
+	#[serde(default)]
+	pub search: Option<SearchPreferences>,
+}
+
+impl RemoteNodePreferencesUpdate {
+	fn apply(self, preferences: &mut NodePreferences) {
+		let Self {
+			thumbnailer,
+			jobs,
+			background_policy,
+			search,
+		} = self;
+
+		if let Some(thumbnailer) = thumbnailer {
+			preferences.thumbnailer = thumbnailer;
+		}
+		if let Some(jobs) = jobs {
+			preferences.jobs = jobs;
+		}
+		if let Some(background_policy) = background_policy {
+			preferences.background_policy = background_policy;
+		}
+		if let Some(search) = search {
+			preferences
+				.search
+				.set_max_objects_take(search.max_objects_take());
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AdminRequest {
+	Jobs,
+	Logs {
+		lines: u32,
+	},
+	Settings,
+	Rescan {
+		library_id: Uuid,
+		location_id: location::id::Type,
+	},
+	UpdateSettings(RemoteNodeSettingsUpdate),
+}
+
+impl AdminRequest {
+	fn required_permission(&self) -> RemoteAdminPermission {
+		match self {
+			Self::Jobs | Self::Logs { .. } | Self::Settings => RemoteAdminPermission::View,
+			Self::Rescan { .. } | Self::UpdateSettings(_) => RemoteAdminPermission::Manage,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AdminResponse {
+	Jobs(Vec<JobReport>),
+	Logs(Vec<String>),
+	Settings(RemoteNodeSettings),
+	Done,
+	Denied,
+	Failed(String),
+}
+
+async fn read_message<T: DeserializeOwned>(
+	stream: &mut (impl AsyncRead + Unpin),
+) -> Result<T, AdminError> {
+	rmp_serde::from_slice(&decode::buf(stream).await?).map_err(Into::into)
+}
+
+async fn write_message(
+	stream: &mut (impl AsyncWrite + Unpin),
+	message: &impl Serialize,
+) -> Result<(), AdminError> {
+	let mut buf = vec![];
+	encode::buf(&mut buf, &rmp_serde::to_vec_named(message)?);
+
+	stream.write_all(&buf).await?;
+	stream.flush().await.map_err(Into::into)
+}
+
+/// Sends the request to the node `identity`, returning its response.
+pub async fn request(
+	node: &Node,
+	identity: RemoteIdentity,
+	request: AdminRequest,
+) -> Result<AdminResponse, AdminError> {
+	let peer = node
+		.p2p
+		.p2p
+		.peers()
+		.get(&identity)
+		.ok_or(AdminError::PeerNotFound)?
+		.clone();
+
+	let mut stream = peer
+		.new_stream()
+		.await
+		.map_err(|e| AdminError::Connect(e.to_string()))?;
+
+	stream.write_all(&Header::Admin.to_bytes()).await?;
+
+	let mut tunnel = Tunnel::initiator(stream)
+		.await
+		.map_err(AdminError::Tunnel)?;
+
+	write_message(&mut tunnel, &request).await?;
+
+	match read_message(&mut tunnel).await? {
+		AdminResponse::Denied => Err(AdminError::Denied),
+		AdminResponse::Failed(message) => Err(AdminError::Remote(message)),
+		response => Ok(response),
+	}
+}
+
+pub(crate) async fn receiver(node: &Arc<Node>, stream: UnicastStream) -> Result<(), AdminError> {
+	let remote = stream.remote_identity();
+
+	let mut tunnel = Tunnel::responder(stream)
+		.await
+		.map_err(AdminError::Tunnel)?;
+
+	let request = read_message::<AdminRequest>(&mut tunnel).await?;
+
+	let permission = node
+		.config
+		.get()
+		.await
+		.preferences
+		.remote_admin
+		.permission(remote);
+
+	let response = match permission {
+		Some(permission)
+			if permission >= request.required_permission()
+				&& is_library_instance(node, remote).await =>
+		{
+			debug!("Handling admin request from node '{remote}': {request:?}");
+
+			handle(node, request)
+				.await
+				.unwrap_or_else(|e| AdminResponse::Failed(e.to_string()))
+		}
+		_ => {
+			warn!("Denied admin request from node '{remote}': {request:?}");
+			AdminResponse::Denied
+		}
+	};
+
+	write_message(&mut tunnel, &response).await
+}
+
+async fn is_library_instance(node: &Node, identity: RemoteIdentity) -> bool {
+	node.libraries
+		.get_all()
+		.await
+		.iter()
+		.any(|library| node.p2p.get_instance(&library.id, identity).is_some())
+}
+
+async fn handle(node: &Arc<Node>, request: AdminRequest) -> Result<AdminResponse, AdminError> {
+	match request {
+		AdminRequest::Jobs => jobs(node).await.map(AdminResponse::Jobs),
+		AdminRequest::Logs { lines } => read_logs(&node.data_dir.join(LOGS_DIR), lines)
+			.await
+			.map(AdminResponse::Logs),
+		AdminRequest::Settings => {
+			let config = node.config.get().await;
+
+			Ok(AdminResponse::Settings(RemoteNodeSettings {
+				name: config.name,
+				preferences: config.preferences,
+			}))
+		}
+		AdminRequest::Rescan {
+			library_id,
+			location_id,
+		} => {
+			let library = node
+				.libraries
+				.get_library(&library_id)
+				.await
+				.ok_or(AdminError::LibraryNotFound(library_id))?;
+
+			let location = find_location(&library, location_id)
+				.include(location_with_indexer_rules::include())
+				.exec()
+				.await?
+				.ok_or(LocationError::IdNotFound(location_id))?;
+
+			let location_scan_state = ScanState::try_from(location.scan_state)?;
+
+			scan_location(node, &library, location, location_scan_state).await?;
+
+			Ok(AdminResponse::Done)
+		}
+		AdminRequest::UpdateSettings(RemoteNodeSettingsUpdate { name, preferences }) => {
+			if name
+				.as_ref()
+				.is_some_and(|name| name.is_empty() || name.len() > 250)
+			{
+				return Err(AdminError::InvalidNodeName);
+			}
+
+			node.config
+				.write(|config| {
+					if let Some(name) = name {
+						config.name = name;
+					}
+
+					if let Some(preferences) = preferences {
+						preferences.apply(&mut config.preferences);
+					}
+				})
+				.await?;
+
+			// This is a no-op if the name didn't change
+			node.p2p.on_node_config_change().await;
+
+			Ok(AdminResponse::Done)
+		}
+	}
+}
+
+/// The running jobs of the node, followed by the last ones of each library.
+async fn jobs(node: &Node) -> Result<Vec<JobReport>, AdminError> {
+	let mut active_reports = node.old_jobs.get_active_reports_with_id().await;
+
+	let mut reports = vec![];
+
+	for library in node.libraries.get_all().await {
+		reports.extend(
+			library
+				.db
+				.job()
+				.find_many(vec![])
+				.order_by(job::date_created::order(SortOrder::Desc))
+				.take(RECENT_JOBS)
+				.select(job_without_data::select())
+				.exec()
+				.await?
+				.into_iter()
+				.flat_map(JobReport::try_from)
+				// Running jobs have a fresher report in memory
+				.filter(|report| !active_reports.contains_key(&report.id)),
+		);
+	}
+
+	Ok(active_reports
+		.drain()
+		.map(|(_, report)| report)
+		.chain(reports)
+		.collect())
+}
+
+/// The last lines of the most recent log file, the logger starts a new one every day.
+async fn read_logs(logs_dir: &Path, lines: u32) -> Result<Vec<String>, AdminError> {
+	let mut latest = None;
+
+	let mut read_dir = fs::read_dir(logs_dir)
+		.await
+		.map_err(|e| FileIOError::from((logs_dir, e)))?;
+
+	while let Some(entry) = read_dir
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((logs_dir, e)))?
+	{
+		if !entry
+			.file_name()
+			.to_string_lossy()
+			.starts_with(LOG_FILE_PREFIX)
+		{
+			continue;
+		}
+
+		let path = entry.path();
+		let modified_at = entry
+			.metadata()
+			.await
+			.and_then(|metadata| metadata.modified())
+			.map_err(|e| FileIOError::from((&path, e)))?;
+
+		if latest.as_ref().map_or(true, |(latest_modified_at, _)| {
+			modified_at > *latest_modified_at
+		}) {
+			latest = Some((modified_at, path));
+		}
+	}
+
+	let Some((_, path)) = latest else {
+		return Ok(vec![]);
+	};
+
+	let mut file = File::open(&path)
+		.await
+		.map_err(|e| FileIOError::from((&path, e)))?;
+
+	let len = file
+		.metadata()
+		.await
+		.map_err(|e| FileIOError::from((&path, e)))?
+		.len();
+
+	file.seek(SeekFrom::Start(len.saturating_sub(MAX_LOG_BYTES)))
+		.await
+		.map_err(|e| FileIOError::from((&path, e)))?;
+
+	let mut buf = vec![];
+	file.read_to_end(&mut buf)
+		.await
+		.map_err(|e| FileIOError::from((&path, e)))?;
+
+	Ok(last_lines(&String::from_utf8_lossy(&buf), lines))
+}
+
+fn last_lines(logs: &str, lines: u32) -> Vec<String> {
+	let lines = lines.min(MAX_LOG_LINES) as usize;
+
+	let mut last = logs
+		.lines()
+		.rev()
+		.take(lines)
+		.map(str::to_string)
+		.collect::<Vec<_>>();
+	last.reverse();
+
+	last
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_last_lines() {
+		assert_eq!(last_lines("a\nb\nc\n", 2), vec!["b", "c"]);
+		assert_eq!(last_lines("a\nb", 5), vec!["a", "b"]);
+		assert_eq!(last_lines("a\nb", 0), Vec::<String>::new());
+	}
+
+	#[test]
+	fn checks_permissions() {
+		let identity = sd_p2p::Identity::new().to_remote_identity();
+		let mut preferences = RemoteAdminPreferences::default();
+
+		assert_eq!(preferences.permission(identity), None);
+
+		preferences.set_grant(identity, Some(RemoteAdminPermission::View));
+		assert_eq!(
+			preferences.permission(identity),
+			Some(RemoteAdminPermission::View)
+		);
+		assert!(preferences.permission(identity) < Some(RemoteAdminPermission::Manage));
+
+		preferences.set_grant(identity, None);
+		assert!(preferences.grants().is_empty());
+	}
+}
@@ -1,6 +1,11 @@
+pub mod admin;
+pub mod offload;
+pub mod pairing;
 pub mod ping;
 pub mod rspc;
 pub mod spacedrop;
 
+pub use offload::offload_thumbnails;
+pub use pairing::{pair, PairingError};
 pub use rspc::remote_rspc;
-pub use spacedrop::spacedrop;
+pub use spacedrop::{spacedrop, SpacedropError};
@@ -0,0 +1,401 @@
+//! Offloading the heavy work of a job to another instance of the same library.
+//!
+//! The originator sends a manifest describing the work, then streams each input file and
+//! waits for its output before sending the next one, so the remote node never holds more
+//! than a single input on disk.
+
+use crate::{
+	api::CoreEvent,
+	library::Library,
+	object::media::old_thumbnail::{
+		generate_thumbnail_at, get_indexed_thumb_key, get_indexed_thumbnail_path,
+		GenerateThumbnailArgs, ThumbnailTier, ThumbnailerError, WEBP_EXTENSION,
+	},
+	p2p::Header,
+	Node,
+};
+
+use sd_p2p::{RemoteIdentity, UnicastStream};
+use sd_p2p_proto::{decode, encode};
+use sd_p2p_tunnel::Tunnel;
+use sd_utils::error::FileIOError;
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+	fs::{self, File},
+	io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+	time::timeout,
+};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+const OFFLOAD_DIR: &str = "offload";
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(30);
+
+const REJECTED: u8 = 0;
+const ACCEPTED: u8 = 1;
+
+const ITEM_FAILED: u8 = 0;
+const ITEM_DONE: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum OffloadError {
+	#[error("node is not a connected instance of the library")]
+	PeerNotFound,
+	#[error("failed to connect to node: {0}")]
+	Connect(String),
+	#[error("tunnel error: {0}")]
+	Tunnel(&'static str),
+	#[error("node refused the offloaded work")]
+	Rejected,
+	#[error("file changed while being sent: {}", .0.display())]
+	InputChanged(Box<Path>),
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("error decoding message: {0}")]
+	Decode(#[from] decode::Error),
+	#[error("error decoding manifest: {0}")]
+	DecodeManifest(#[from] rmp_serde::decode::Error),
+	#[error("error encoding manifest: {0}")]
+	EncodeManifest(#[from] rmp_serde::encode::Error),
+}
+
+/// Describes the work sent to the remote node, inputs follow in the same order.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OffloadManifest {
+	Thumbnails(Vec<OffloadedInput>),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OffloadedInput {
+	pub extension: String,
+	pub size: u64,
+}
+
+impl OffloadManifest {
+	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, OffloadError> {
+		rmp_serde::from_slice(&decode::buf(stream).await?).map_err(Into::into)
+	}
+
+	pub fn to_bytes(&self) -> Result<Vec<u8>, OffloadError> {
+		let mut buf = vec![];
+		encode::buf(&mut buf, &rmp_serde::to_vec_named(self)?);
+		Ok(buf)
+	}
+
+	/// Extensions are used to name files on the remote node, so they can't be trusted blindly
+	fn is_valid(&self) -> bool {
+		match self {
+			Self::Thumbnails(inputs) => inputs.iter().all(|input| {
+				!input.extension.is_empty()
+					&& input.extension.chars().all(|c| c.is_ascii_alphanumeric())
+			}),
+		}
+	}
+}
+
+/// The node configured to generate our thumbnails, if it's connected and an instance of the library.
+pub async fn thumbnails_offload_target(node: &Node, library_id: Uuid) -> Option<RemoteIdentity> {
+	let identity = node
+		.config
+		.get()
+		.await
+		.preferences
+		.jobs
+		.offload()
+		.thumbnails_target?;
+
+	node.p2p
+		.get_instance(&library_id, identity)
+		.filter(|peer| peer.is_connected())
+		.map(|_| identity)
+}
+
+/// Generates the thumbnails of `batch` on the node `identity`, saving them to our thumbnails
+/// directory as they arrive.
+///
+/// Returns the errors of thumbnails that couldn't be generated, an `Err` means the work
+/// couldn't be offloaded at all.
+pub async fn offload_thumbnails(
+	node: &Arc<Node>,
+	library: &Library,
+	identity: RemoteIdentity,
+	batch: &[GenerateThumbnailArgs],
+	should_regenerate: bool,
+) -> Result<Vec<String>, OffloadError> {
+	let mut errors = vec![];
+	let mut inputs = Vec::with_capacity(batch.len());
+
+	for args in batch {
+		let output_path =
+			get_indexed_thumbnail_path(node, &args.cas_id, library.id, ThumbnailTier::Grid);
+
+		// No need to send files over the network for thumbnails we already have
+		if !should_regenerate && fs::metadata(&output_path).await.is_ok() {
+			continue;
+		}
+
+		let file = match File::open(&args.path).await {
+			Ok(file) => file,
+			Err(e) => {
+				errors.push(FileIOError::from((&args.path, e)).to_string());
+				continue;
+			}
+		};
+
+		match file.metadata().await {
+			Ok(metadata) => inputs.push((args, file, metadata.len(), output_path)),
+			Err(e) => errors.push(FileIOError::from((&args.path, e)).to_string()),
+		}
+	}
+
+	if inputs.is_empty() {
+		return Ok(errors);
+	}
+
+	let peer = node
+		.p2p
+		.get_instance(&library.id, identity)
+		.ok_or(OffloadError::PeerNotFound)?;
+
+	let mut stream = peer
+		.new_stream()
+		.await
+		.map_err(|e| OffloadError::Connect(e.to_string()))?;
+
+	stream
+		.write_all(&Header::Offload(library.id).to_bytes())
+		.await?;
+
+	let mut tunnel = Tunnel::initiator(stream)
+		.await
+		.map_err(OffloadError::Tunnel)?;
+
+	tunnel
+		.write_all(
+			&OffloadManifest::Thumbnails(
+				inputs
+					.iter()
+					.map(|(args, _, size, _)| OffloadedInput {
+						extension: args.extension.clone(),
+						size: *size,
+					})
+					.collect(),
+			)
+			.to_bytes()?,
+		)
+		.await?;
+	tunnel.flush().await?;
+
+	if tunnel.read_u8().await? != ACCEPTED {
+		return Err(OffloadError::Rejected);
+	}
+
+	debug!(
+		"Offloading {} thumbnails to node '{identity}' for library '{}'",
+		inputs.len(),
+		library.id
+	);
+
+	for (args, file, size, output_path) in inputs {
+		// The remote node reads exactly `size` bytes, anything else would break the stream
+		if io::copy(&mut BufReader::new(file).take(size), &mut tunnel).await? != size {
+			return Err(OffloadError::InputChanged(
+				args.path.clone().into_boxed_path(),
+			));
+		}
+		tunnel.flush().await?;
+
+		if tunnel.read_u8().await? != ITEM_DONE {
+			errors.push(format!(
+				"Failed to generate thumbnail for '{}' on node '{identity}': {}",
+				args.path.display(),
+				decode::string(&mut tunnel).await?
+			));
+			continue;
+		}
+
+		let webp = decode::buf(&mut tunnel).await?;
+
+		if let Some(shard_dir) = output_path.parent() {
+			fs::create_dir_all(shard_dir)
+				.await
+				.map_err(|e| FileIOError::from((shard_dir, e)))?;
+		}
+
+		fs::write(&output_path, &webp)
+			.await
+			.map_err(|e| FileIOError::from((&output_path, e)))?;
+
+		node.emit(CoreEvent::NewThumbnail {
+			thumb_key: get_indexed_thumb_key(&args.cas_id, library.id, ThumbnailTier::Grid),
+		});
+	}
+
+	Ok(errors)
+}
+
+pub(crate) async fn receiver(
+	node: &Arc<Node>,
+	library_id: Uuid,
+	stream: UnicastStream,
+) -> Result<(), OffloadError> {
+	let remote = stream.remote_identity();
+
+	let mut tunnel = Tunnel::responder(stream)
+		.await
+		.map_err(OffloadError::Tunnel)?;
+
+	let manifest = OffloadManifest::from_stream(&mut tunnel).await?;
+
+	// Only instances of the library can offload work to us
+	let accepted = node.config.get().await.preferences.jobs.offload().accept
+		&& manifest.is_valid()
+		&& node.libraries.get_library(&library_id).await.is_some()
+		&& node.p2p.get_instance(&library_id, remote).is_some();
+
+	tunnel
+		.write_all(&[if accepted { ACCEPTED } else { REJECTED }])
+		.await?;
+	tunnel.flush().await?;
+
+	if !accepted {
+		warn!("Rejected offloaded work from node '{remote}' for library '{library_id}'");
+		return Ok(());
+	}
+
+	let work_dir = node
+		.data_dir
+		.join(OFFLOAD_DIR)
+		.join(Uuid::new_v4().to_string());
+
+	fs::create_dir_all(&work_dir)
+		.await
+		.map_err(|e| FileIOError::from((&work_dir, e)))?;
+
+	let res = match manifest {
+		OffloadManifest::Thumbnails(inputs) => {
+			debug!(
+				"Generating {} thumbnails offloaded by node '{remote}'",
+				inputs.len()
+			);
+			generate_offloaded_thumbnails(&mut tunnel, &work_dir, inputs).await
+		}
+	};
+
+	if let Err(e) = fs::remove_dir_all(&work_dir).await {
+		error!(
+			"Failed to remove offloaded work directory: {:#?}",
+			FileIOError::from((work_dir, e))
+		);
+	}
+
+	res
+}
+
+async fn generate_offloaded_thumbnails(
+	tunnel: &mut Tunnel,
+	work_dir: &Path,
+	inputs: Vec<OffloadedInput>,
+) -> Result<(), OffloadError> {
+	for (idx, OffloadedInput { extension, size }) in inputs.into_iter().enumerate() {
+		// Image decoding relies on the extension, so the input keeps it
+		let input_path = work_dir.join(format!("{idx}.{extension}"));
+		let output_path = work_dir.join(format!("{idx}.{WEBP_EXTENSION}"));
+
+		receive_input(tunnel, &input_path, size).await?;
+
+		let res = match timeout(
+			THUMBNAIL_TIMEOUT,
+			generate_thumbnail_at(&input_path, &extension, &output_path, ThumbnailTier::Grid),
+		)
+		.await
+		{
+			Ok(Ok(())) => fs::read(&output_path)
+				.await
+				.map_err(|e| FileIOError::from((&output_path, e)).into()),
+			Ok(Err(e)) => Err(e),
+			Err(_) => Err(ThumbnailerError::TimedOut(
+				input_path.clone().into_boxed_path(),
+			)),
+		};
+
+		let mut buf = vec![];
+		match res {
+			Ok(webp) => {
+				buf.push(ITEM_DONE);
+				encode::buf(&mut buf, &webp);
+			}
+			Err(e) => {
+				buf.push(ITEM_FAILED);
+				encode::string(&mut buf, &e.to_string());
+			}
+		}
+
+		tunnel.write_all(&buf).await?;
+		tunnel.flush().await?;
+
+		for path in [input_path, output_path] {
+			if let Err(e) = fs::remove_file(&path).await {
+				if e.kind() != io::ErrorKind::NotFound {
+					error!(
+						"Failed to remove offloaded file: {:#?}",
+						FileIOError::from((path, e))
+					);
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+async fn receive_input(
+	tunnel: &mut Tunnel,
+	input_path: &Path,
+	size: u64,
+) -> Result<(), OffloadError> {
+	let mut file = File::create(input_path)
+		.await
+		.map_err(|e| FileIOError::from((input_path, e)))?;
+
+	if io::copy(&mut (&mut *tunnel).take(size), &mut file).await? != size {
+		return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+	}
+
+	file.flush()
+		.await
+		.map_err(|e| FileIOError::from((input_path, e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_manifest() {
+		let original = OffloadManifest::Thumbnails(vec![OffloadedInput {
+			extension: "png".to_string(),
+			size: 42,
+		}]);
+
+		let mut cursor =
+			std::io::Cursor::new(original.to_bytes().expect("manifest must be encoded"));
+		let result = OffloadManifest::from_stream(&mut cursor)
+			.await
+			.expect("manifest must be decoded");
+		assert_eq!(original, result);
+		assert!(result.is_valid());
+
+		assert!(!OffloadManifest::Thumbnails(vec![OffloadedInput {
+			extension: "../png".to_string(),
+			size: 42,
+		}])
+		.is_valid());
+	}
+}
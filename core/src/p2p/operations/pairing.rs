@@ -0,0 +1,385 @@
+//! Pairing of two nodes, so they trust each other before files can be sent between them.
+//!
+//! Both nodes show a 6 digit code derived from their identities and from a fresh nonce of each
+//! node, for the users to check they are pairing the right devices, and each of them must accept
+//! before they're trusted. The node starting the pairing commits to its nonce before seeing the
+//! one of the other node, so neither of them can choose the code.
+
+use crate::p2p::{Header, P2PEvent, P2PManager, PeerMetadata};
+
+use sd_p2p::{RemoteIdentity, UnicastStream};
+use sd_p2p_proto::{decode, encode};
+
+use std::{
+	io,
+	sync::{Arc, PoisonError},
+	time::Duration,
+};
+
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+	sync::oneshot,
+	time::timeout,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// The time given to the user to compare the codes before the pairing is rejected
+pub(crate) const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+/// The time given to the other node to send its nonce
+const NONCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const NONCE_LEN: usize = 32;
+
+type Nonce = [u8; NONCE_LEN];
+
+#[derive(Debug, Error)]
+pub enum PairingError {
+	#[error("node is not connected")]
+	PeerNotFound,
+	#[error("failed to connect to node: {0}")]
+	Connect(String),
+	#[error("node sent a nonce not matching its commitment")]
+	CommitmentMismatch,
+	#[error(transparent)]
+	Io(#[from] io::Error),
+}
+
+impl From<PairingError> for rspc::Error {
+	fn from(err: PairingError) -> Self {
+		match err {
+			PairingError::PeerNotFound => {
+				Self::with_cause(ErrorCode::NotFound, err.to_string(), err)
+			}
+			_ => Self::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
+		}
+	}
+}
+
+/// Sent by the node starting the pairing, after [`Header::Pair`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct PairingRequest {
+	/// Name of the node, shown to the user asked to accept
+	pub name: String,
+	/// Hash of the nonce of the node, which is only sent once the other node sent its own
+	pub commitment: [u8; 32],
+}
+
+impl PairingRequest {
+	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, decode::Error> {
+		let name = decode::string(stream).await?;
+
+		let mut commitment = [0; 32];
+		stream.read_exact(&mut commitment).await?;
+
+		Ok(Self { name, commitment })
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = vec![];
+		encode::string(&mut buf, &self.name);
+		buf.extend_from_slice(&self.commitment);
+		buf
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Eq)]
+pub struct PairedNode {
+	pub identity: RemoteIdentity,
+	/// As it was when paired
+	pub name: String,
+}
+
+/// The nodes this one is paired with, stored in the node preferences.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type, PartialEq, Eq)]
+pub struct PairingPreferences {
+	#[serde(default)]
+	paired: Vec<PairedNode>,
+}
+
+impl PairingPreferences {
+	pub fn paired(&self) -> &[PairedNode] {
+		&self.paired
+	}
+
+	pub fn is_paired(&self, identity: RemoteIdentity) -> bool {
+		self.paired.iter().any(|node| node.identity == identity)
+	}
+
+	fn add(&mut self, node: PairedNode) {
+		self.remove(node.identity);
+		self.paired.push(node);
+	}
+
+	pub fn remove(&mut self, identity: RemoteIdentity) {
+		self.paired.retain(|node| node.identity != identity);
+	}
+}
+
+fn commitment(nonce: &Nonce) -> [u8; 32] {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(b"sd-pairing-commitment");
+	hasher.update(nonce);
+	hasher.finalize().into()
+}
+
+/// The code shown on both nodes, from the identities and the nonces of the node that started the
+/// pairing and of the one that received it.
+pub fn pairing_code(
+	initiator: &RemoteIdentity,
+	responder: &RemoteIdentity,
+	initiator_nonce: &Nonce,
+	responder_nonce: &Nonce,
+) -> String {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(b"sd-pairing");
+	hasher.update(&initiator.get_bytes());
+	hasher.update(&responder.get_bytes());
+	hasher.update(initiator_nonce);
+	hasher.update(responder_nonce);
+	let hash = hasher.finalize();
+	let bytes = hash.as_bytes();
+
+	format!(
+		"{:06}",
+		u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) % 1_000_000
+	)
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct PairingStarted {
+	/// To confirm the pairing with, like the one of [`P2PEvent::PairingRequest`] on the other node
+	pub id: Uuid,
+	pub code: String,
+}
+
+/// Asks the node to pair with this one, the result is sent as a [`P2PEvent::PairingComplete`]
+/// once both users confirmed the pairing with [`P2PManager::confirm_pairing`].
+pub async fn pair(
+	p2p: Arc<P2PManager>,
+	identity: RemoteIdentity,
+) -> Result<PairingStarted, PairingError> {
+	let peer = p2p
+		.p2p
+		.peers()
+		.get(&identity)
+		.cloned()
+		.ok_or(PairingError::PeerNotFound)?;
+
+	let peer_name = PeerMetadata::from_hashmap(&peer.metadata())
+		.map(|metadata| metadata.name)
+		.unwrap_or_else(|_| "Unknown".to_string());
+
+	let mut stream = peer
+		.new_stream()
+		.await
+		.map_err(|e| PairingError::Connect(e.to_string()))?;
+
+	let name = p2p.node_config.get().await.name;
+	let nonce = rand::random::<Nonce>();
+	stream
+		.write_all(
+			&Header::Pair(PairingRequest {
+				name,
+				commitment: commitment(&nonce),
+			})
+			.to_bytes(),
+		)
+		.await?;
+
+	// Ours is only sent once we have the one of the other node, which can't change it anymore
+	let remote_nonce = read_nonce(&mut stream).await?;
+	stream.write_all(&nonce).await?;
+	stream.flush().await?;
+
+	let id = Uuid::new_v4();
+	let code = pairing_code(&p2p.p2p.remote_identity(), &identity, &nonce, &remote_nonce);
+	let rx = register(&p2p, id);
+
+	debug!("({id}): pairing with '{identity}'");
+	tokio::spawn(async move { exchange(&p2p, id, peer_name, stream, rx).await });
+
+	Ok(PairingStarted { id, code })
+}
+
+pub(crate) async fn receiver(
+	this: &Arc<P2PManager>,
+	req: PairingRequest,
+	mut stream: UnicastStream,
+) {
+	let id = Uuid::new_v4();
+	let identity = stream.remote_identity();
+	let nonce = rand::random::<Nonce>();
+
+	let remote_nonce = async {
+		stream.write_all(&nonce).await?;
+		stream.flush().await?;
+
+		let remote_nonce = read_nonce(&mut stream).await?;
+		if commitment(&remote_nonce) != req.commitment {
+			return Err(PairingError::CommitmentMismatch);
+		}
+
+		Ok(remote_nonce)
+	}
+	.await;
+
+	let remote_nonce = match remote_nonce {
+		Ok(remote_nonce) => remote_nonce,
+		Err(e) => {
+			warn!("({id}): failed to exchange the pairing nonces with '{identity}': {e:?}");
+			return;
+		}
+	};
+
+	let rx = register(this, id);
+
+	debug!("({id}): pairing requested by '{identity}'");
+	this.events
+		.send(P2PEvent::PairingRequest {
+			id,
+			identity,
+			peer_name: req.name.clone(),
+			code: pairing_code(
+				&identity,
+				&this.p2p.remote_identity(),
+				&remote_nonce,
+				&nonce,
+			),
+		})
+		.ok();
+
+	exchange(this, id, req.name, stream, rx).await;
+}
+
+async fn read_nonce(stream: &mut (impl AsyncRead + Unpin)) -> Result<Nonce, io::Error> {
+	let mut nonce = [0; NONCE_LEN];
+
+	timeout(NONCE_TIMEOUT, stream.read_exact(&mut nonce))
+		.await
+		.map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+	Ok(nonce)
+}
+
+fn register(this: &P2PManager, id: Uuid) -> oneshot::Receiver<bool> {
+	let (tx, rx) = oneshot::channel();
+	this.pairing_reqs
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.insert(id, tx);
+
+	rx
+}
+
+/// Sends the decision of our user and reads the one of the other node, the nodes are only paired
+/// if both accepted.
+async fn exchange(
+	this: &Arc<P2PManager>,
+	id: Uuid,
+	name: String,
+	mut stream: UnicastStream,
+	rx: oneshot::Receiver<bool>,
+) {
+	let identity = stream.remote_identity();
+
+	let accepted = matches!(timeout(PAIRING_TIMEOUT, rx).await, Ok(Ok(true)));
+	this.pairing_reqs
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.remove(&id);
+
+	let remote_accepted = async {
+		stream.write_u8(u8::from(accepted)).await?;
+		stream.flush().await?;
+
+		// The other user may not have decided yet
+		timeout(PAIRING_TIMEOUT, stream.read_u8())
+			.await
+			.map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?
+	}
+	.await;
+
+	let mut paired = match remote_accepted {
+		Ok(remote_accepted) => accepted && remote_accepted == 1,
+		Err(e) => {
+			warn!("({id}): failed to exchange the pairing decision with '{identity}': {e:?}");
+			false
+		}
+	};
+
+	if paired {
+		if let Err(e) = this
+			.node_config
+			.update_preferences(|preferences| {
+				preferences.pairing.add(PairedNode { identity, name });
+			})
+			.await
+		{
+			warn!("({id}): failed to save the pairing with '{identity}': {e:#?}");
+			paired = false;
+		}
+	}
+
+	debug!("({id}): pairing with '{identity}' complete, paired: {paired}");
+	this.events
+		.send(P2PEvent::PairingComplete {
+			id,
+			identity,
+			paired,
+		})
+		.ok();
+}
+
+impl P2PManager {
+	pub async fn confirm_pairing(&self, id: Uuid, accepted: bool) {
+		if let Some(chan) = self
+			.pairing_reqs
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&id)
+		{
+			chan.send(accepted).ok();
+		}
+	}
+
+	pub async fn is_paired(&self, identity: RemoteIdentity) -> bool {
+		self.node_config
+			.get()
+			.await
+			.preferences
+			.pairing
+			.is_paired(identity)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn same_code_on_both_nodes() {
+		let a = sd_p2p::Identity::new().to_remote_identity();
+		let b = sd_p2p::Identity::new().to_remote_identity();
+		let (a_nonce, b_nonce) = (rand::random::<Nonce>(), rand::random::<Nonce>());
+
+		let code = pairing_code(&a, &b, &a_nonce, &b_nonce);
+		assert_eq!(code, pairing_code(&a, &b, &a_nonce, &b_nonce));
+		assert_eq!(code.len(), 6);
+		assert!(code.chars().all(|c| c.is_ascii_digit()));
+	}
+
+	#[test]
+	fn commitment_binds_the_nonce() {
+		let nonce = rand::random::<Nonce>();
+		let mut other = nonce;
+		other[0] ^= 1;
+
+		assert_eq!(commitment(&nonce), commitment(&nonce));
+		assert_ne!(commitment(&nonce), commitment(&other));
+	}
+}
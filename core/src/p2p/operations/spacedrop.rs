@@ -1,20 +1,26 @@
 use std::{
 	borrow::Cow,
-	path::PathBuf,
+	ffi::OsString,
+	io,
+	path::{Path, PathBuf},
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc, PoisonError,
 	},
-	time::Duration,
+	time::{Duration, SystemTime},
 };
 
-use crate::p2p::{Header, P2PEvent, P2PManager};
+use crate::{
+	node::background_policy::{BackgroundPolicy, Subsystem, WorkMode},
+	p2p::{Header, P2PEvent, P2PManager},
+};
 use futures::future::join_all;
-use sd_p2p::{RemoteIdentity, UnicastStream};
+use sd_p2p::{NewStreamError, RemoteIdentity, UnicastStream};
 use sd_p2p_block::{BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer};
+use thiserror::Error;
 use tokio::{
-	fs::{create_dir_all, File},
-	io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+	fs::{self, create_dir_all, File, OpenOptions},
+	io::{AsyncReadExt, AsyncWriteExt, BufReader},
 	sync::oneshot,
 	time::{sleep, Instant},
 };
@@ -24,19 +30,109 @@ use uuid::Uuid;
 /// The amount of time to wait for a Spacedrop request to be accepted or rejected before it's automatically rejected
 pub(crate) const SPACEDROP_TIMEOUT: Duration = Duration::from_secs(60);
 
-// TODO: Proper error handling
+/// Manifests of Spacedrops not resumed for this long are removed, the file they were received
+/// into is still compared block by block with the one sent if it's sent again
+const MANIFEST_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const MANIFEST_EXTENSION: &str = ".sdtransfer";
+
+/// Where the blocks of a file received so far are recorded, until it's complete
+fn manifest_path(path: &Path) -> PathBuf {
+	let mut name = OsString::from(".");
+	name.push(path.file_name().unwrap_or_default());
+	name.push(MANIFEST_EXTENSION);
+
+	path.with_file_name(name)
+}
+
+/// Removes the manifests left in `dir` by Spacedrops interrupted more than [`MANIFEST_EXPIRY`]
+/// ago.
+async fn remove_expired_manifests(dir: &Path) {
+	let Ok(mut entries) = fs::read_dir(dir).await else {
+		return;
+	};
+
+	while let Ok(Some(entry)) = entries.next_entry().await {
+		let is_manifest = entry
+			.file_name()
+			.to_str()
+			.is_some_and(|name| name.starts_with('.') && name.ends_with(MANIFEST_EXTENSION));
+
+		if !is_manifest {
+			continue;
+		}
+
+		let expired = entry
+			.metadata()
+			.await
+			.and_then(|metadata| metadata.modified())
+			.ok()
+			.and_then(|modified| SystemTime::now().duration_since(modified).ok())
+			.is_some_and(|elapsed| elapsed >= MANIFEST_EXPIRY);
+
+		if expired {
+			let path = entry.path();
+			match fs::remove_file(&path).await {
+				Ok(()) => debug!("Removed expired Spacedrop manifest '{}'", path.display()),
+				Err(err) => warn!(
+					"Failed to remove expired Spacedrop manifest '{}': {err:?}",
+					path.display()
+				),
+			}
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum SpacedropError {
+	#[error("no files to send")]
+	NoFiles,
+	#[error("failed to open file '{}'", .path.display())]
+	OpenFile {
+		path: PathBuf,
+		#[source]
+		source: io::Error,
+	},
+	#[error("peer not found")]
+	PeerNotFound(RemoteIdentity),
+	#[error("failed to connect to peer")]
+	Connect {
+		identity: RemoteIdentity,
+		#[source]
+		source: NewStreamError,
+	},
+	#[error("peer is not paired with this node")]
+	NotPaired(RemoteIdentity),
+	#[error("transfers are paused by the background policy of the node")]
+	Paused,
+}
+
 pub async fn spacedrop(
 	p2p: Arc<P2PManager>,
+	background_policy: &BackgroundPolicy,
 	identity: RemoteIdentity,
 	paths: Vec<PathBuf>,
-) -> Result<Uuid, ()> {
+) -> Result<Uuid, SpacedropError> {
 	if paths.is_empty() {
-		return Err(());
+		return Err(SpacedropError::NoFiles);
+	}
+
+	if background_policy.mode(Subsystem::Transfers) == WorkMode::Paused {
+		return Err(SpacedropError::Paused);
 	}
 
 	let (files, requests): (Vec<_>, Vec<_>) = join_all(paths.into_iter().map(|path| async move {
-		let file = File::open(&path).await?;
-		let metadata = file.metadata().await?;
+		let open = async {
+			let file = File::open(&path).await?;
+			let metadata = file.metadata().await?;
+
+			Ok::<_, io::Error>((file, metadata))
+		};
+
+		let (file, metadata) = match open.await {
+			Ok(opened) => opened,
+			Err(source) => return Err(SpacedropError::OpenFile { path, source }),
+		};
+
 		let name = path
 			.file_name()
 			.map(|v| v.to_string_lossy())
@@ -54,14 +150,16 @@ pub async fn spacedrop(
 	}))
 	.await
 	.into_iter()
-	.collect::<Result<Vec<_>, std::io::Error>>()
-	.map_err(|err| {
-		warn!("error opening file: '{err:?}'");
-		// TODO: Proper error type
-	})?
+	.collect::<Result<Vec<_>, _>>()
+	.inspect_err(|err| warn!("error opening file: '{err:?}'"))?
 	.into_iter()
 	.unzip();
 
+	// The other node rejects Spacedrops from nodes it isn't paired with
+	if !p2p.is_paired(identity).await {
+		return Err(SpacedropError::NotPaired(identity));
+	}
+
 	let total_length: u64 = requests.iter().map(|req| req.size).sum();
 
 	let id = Uuid::new_v4();
@@ -72,13 +170,13 @@ pub async fn spacedrop(
 		.get(&identity)
 		.ok_or_else(|| {
 			debug!("({id}): failed to find connection method with '{identity}'");
-			// TODO: Proper error
+			SpacedropError::PeerNotFound(identity)
 		})?
 		.clone();
 
-	let mut stream = peer.new_stream().await.map_err(|err| {
-		debug!("({id}): failed to connect to '{identity}': {err:?}");
-		// TODO: Proper error
+	let mut stream = peer.new_stream().await.map_err(|source| {
+		debug!("({id}): failed to connect to '{identity}': {source:?}");
+		SpacedropError::Connect { identity, source }
 	})?;
 
 	tokio::spawn(async move {
@@ -113,9 +211,22 @@ pub async fn spacedrop(
 				p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
 				return;
 			}
-			Ok(1) => {}                     // Okay
-			Ok(_) => todo!(),               // TODO: Proper error
-			Err(err) => todo!("{:?}", err), // TODO: Proper error
+			Ok(1) => {} // Okay
+			Ok(byte) => {
+				warn!(
+					"({id}): peer '{identity}' responded with unknown byte '{byte}', treating it as a rejection"
+				);
+				p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
+				return;
+			}
+			Err(err) => {
+				// Nodes running an older version drop the stream as they can't read the header
+				warn!(
+					"({id}): peer '{identity}' closed the Spacedrop, it may run an older version: {err}"
+				);
+				p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
+				return;
+			}
 		}
 
 		let cancelled = Arc::new(AtomicBool::new(false));
@@ -206,6 +317,23 @@ pub(crate) async fn receiver(
 	mut stream: UnicastStream,
 ) -> Result<(), ()> {
 	let id = req.id;
+
+	if !this.is_paired(stream.remote_identity()).await {
+		info!(
+			"({id}): rejecting Spacedrop from unpaired peer '{}'",
+			stream.remote_identity()
+		);
+
+		stream.write_all(&[0]).await.map_err(|err| {
+			error!("({id}): error sending rejection: '{err:?}'");
+		})?;
+		stream.flush().await.map_err(|err| {
+			error!("({id}): error flushing rejection: '{err:?}'");
+		})?;
+
+		return Ok(());
+	}
+
 	let (tx, rx) = oneshot::channel();
 
 	info!(
@@ -277,14 +405,20 @@ pub(crate) async fn receiver(
 						// TODO: make sure the other peer times out or we retry???
 					})?;
 
-					let names = req.requests.iter().map(|req| req.name.clone()).collect::<Vec<_>>();
+					let files = req.requests.iter().map(|req| (req.name.clone(), req.size)).collect::<Vec<_>>();
 					let mut transfer = Transfer::new(&req, |percent| {
 						this.events.send(P2PEvent::SpacedropProgress { id, percent }).ok();
 					}, &cancelled);
 
 					let file_path = PathBuf::from(file_path);
-					let names_len = names.len();
-					for file_name in names {
+					let names_len = files.len();
+
+					let target_dir = if names_len != 1 { Some(file_path.as_path()) } else { file_path.parent() };
+					if let Some(dir) = target_dir {
+						remove_expired_manifests(dir).await;
+					}
+
+					for (file_name, size) in files {
 						 // When transferring more than 1 file we wanna join the incoming file name to the directory provided by the user
 						 let mut path = file_path.clone();
 						 if names_len != 1 {
@@ -304,21 +438,39 @@ pub(crate) async fn receiver(
 							})?;
 						}
 
-						let f = File::create(&path).await.map_err(|err| {
-							error!("({id}): error creating file at '{path:?}': '{err:?}'");
+						// An older version of the file or the part received by an interrupted
+						// Spacedrop is kept, so only the blocks missing or which changed are sent
+						let mut f = OpenOptions::new()
+							.read(true)
+							.write(true)
+							.create(true)
+							.truncate(false)
+							.open(&path)
+							.await
+							.map_err(|err| {
+								error!("({id}): error creating file at '{path:?}': '{err:?}'");
 
-							// TODO: Send error to the frontend
+								// TODO: Send error to the frontend
 
-							// TODO: Send error to remote peer
-						})?;
-						let f = BufWriter::new(f);
-						if let Err(err) = transfer.receive(&mut stream, f).await {
+								// TODO: Send error to remote peer
+							})?;
+						let manifest = manifest_path(&path);
+						if let Err(err) = transfer.receive_resumable(&mut stream, &mut f, &manifest).await {
 							error!("({id}): error receiving file '{file_name}': '{err:?}'");
 
 							// TODO: Send error to frontend
 
 							break;
 						}
+
+						// The older version of the file may have been bigger
+						if let Err(err) = f.set_len(size).await {
+							error!("({id}): error truncating file at '{path:?}': '{err:?}'");
+
+							// TODO: Send error to frontend
+
+							break;
+						}
 					}
 
 					info!("({id}): complete");
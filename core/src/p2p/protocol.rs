@@ -1,9 +1,22 @@
+use crate::p2p::operations::pairing::PairingRequest;
+
 use sd_p2p_block::{SpaceblockRequests, SpaceblockRequestsError};
 use sd_p2p_proto::{decode, encode};
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
+/// Discriminator of Spacedrop requests before the delta transfers, sent by older nodes
+const LEGACY_SPACEDROP: u8 = 0;
+/// Spacedrop requests whose files can be sent as the blocks changed from a file the receiver
+/// already has
+const SPACEDROP: u8 = 9;
+/// Discriminator of sync streams before the operations were sent as compressed frames, sent by
+/// older nodes
+const LEGACY_SYNC: u8 = 3;
+/// Sync streams whose operations are sent as compressed frames
+const SYNC: u8 = 10;
+
 /// TODO
 #[derive(Debug, PartialEq, Eq)]
 pub enum Header {
@@ -13,6 +26,12 @@ pub enum Header {
 	Sync(Uuid),
 	// A HTTP server used for rspc requests and streaming files
 	Http,
+	// Heavy work of a job offloaded by another instance of the library
+	Offload(Uuid),
+	// Administration of this node by another one
+	Admin,
+	// Request to pair with this node
+	Pair(PairingRequest),
 }
 
 #[derive(Debug, Error)]
@@ -21,10 +40,16 @@ pub enum HeaderError {
 	DiscriminatorIo(std::io::Error),
 	#[error("invalid discriminator '{0}'")]
 	DiscriminatorInvalid(u8),
+	#[error("the peer uses an older {0} protocol, both nodes must be updated to the same version")]
+	OutdatedProtocol(&'static str),
 	#[error("error reading spacedrop request: {0}")]
 	SpacedropRequest(#[from] SpaceblockRequestsError),
 	#[error("error reading sync request: {0}")]
 	SyncRequest(decode::Error),
+	#[error("error reading offload request: {0}")]
+	OffloadRequest(decode::Error),
+	#[error("error reading pairing request: {0}")]
+	PairingRequest(decode::Error),
 }
 
 impl Header {
@@ -35,16 +60,29 @@ impl Header {
 			.map_err(HeaderError::DiscriminatorIo)?;
 
 		match discriminator {
-			0 => Ok(Self::Spacedrop(
+			SPACEDROP => Ok(Self::Spacedrop(
 				SpaceblockRequests::from_stream(stream).await?,
 			)),
+			LEGACY_SPACEDROP => Err(HeaderError::OutdatedProtocol("Spacedrop")),
+			LEGACY_SYNC => Err(HeaderError::OutdatedProtocol("sync")),
 			1 => Ok(Self::Ping),
-			3 => Ok(Self::Sync(
+			SYNC => Ok(Self::Sync(
 				decode::uuid(stream)
 					.await
 					.map_err(HeaderError::SyncRequest)?,
 			)),
 			5 => Ok(Self::Http),
+			6 => Ok(Self::Offload(
+				decode::uuid(stream)
+					.await
+					.map_err(HeaderError::OffloadRequest)?,
+			)),
+			7 => Ok(Self::Admin),
+			8 => Ok(Self::Pair(
+				PairingRequest::from_stream(stream)
+					.await
+					.map_err(HeaderError::PairingRequest)?,
+			)),
 			d => Err(HeaderError::DiscriminatorInvalid(d)),
 		}
 	}
@@ -52,17 +90,28 @@ impl Header {
 	pub fn to_bytes(&self) -> Vec<u8> {
 		match self {
 			Self::Spacedrop(transfer_request) => {
-				let mut bytes = vec![0];
+				let mut bytes = vec![SPACEDROP];
 				bytes.extend_from_slice(&transfer_request.to_bytes());
 				bytes
 			}
 			Self::Ping => vec![1],
 			Self::Sync(uuid) => {
-				let mut bytes = vec![3];
+				let mut bytes = vec![SYNC];
 				encode::uuid(&mut bytes, uuid);
 				bytes
 			}
 			Self::Http => vec![5],
+			Self::Offload(library_id) => {
+				let mut bytes = vec![6];
+				encode::uuid(&mut bytes, library_id);
+				bytes
+			}
+			Self::Admin => vec![7],
+			Self::Pair(req) => {
+				let mut bytes = vec![8];
+				bytes.extend_from_slice(&req.to_bytes());
+				bytes
+			}
 		}
 	}
 }
@@ -0,0 +1,113 @@
+//! Encoding of the batches of operations sent to a syncing peer.
+//!
+//! Operations are grouped by instance, model and record like the ones sent to the cloud, and encoded
+//! as MessagePack arrays instead of maps. Frames big enough to be worth it are compressed, as the
+//! operations of a backfill repeat the same fields over and over.
+
+use sd_p2p_proto::{decode, encode};
+use sd_sync::{CRDTOperation, CompressedCRDTOperations};
+
+use std::io::{self, Read, Write};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Smaller frames are sent as is, as compressing them saves next to nothing
+const COMPRESS_FROM: usize = 1024;
+/// Frames inflating to more than this are rejected, instead of running out of memory
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum FrameKind {
+	Raw = 0,
+	Deflate = 1,
+}
+
+pub fn encode_operations(ops: &[CRDTOperation]) -> io::Result<Vec<u8>> {
+	let payload =
+		rmp_serde::to_vec(&CompressedCRDTOperations::new(ops.to_vec())).map_err(invalid_data)?;
+
+	let (kind, payload) = if payload.len() >= COMPRESS_FROM {
+		let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+		encoder.write_all(&payload)?;
+		(FrameKind::Deflate, encoder.finish()?)
+	} else {
+		(FrameKind::Raw, payload)
+	};
+
+	let mut buf = vec![kind as u8];
+	encode::buf(&mut buf, &payload);
+
+	Ok(buf)
+}
+
+pub async fn decode_operations(
+	stream: &mut (impl AsyncRead + Unpin),
+) -> io::Result<Vec<CRDTOperation>> {
+	let kind = stream.read_u8().await?;
+	let payload = decode::buf(stream).await.map_err(|e| match e {
+		decode::Error::IoError(e) => e,
+		e => invalid_data(e),
+	})?;
+
+	let payload = match kind {
+		k if k == FrameKind::Raw as u8 => payload,
+		k if k == FrameKind::Deflate as u8 => {
+			let mut decompressed = Vec::new();
+			DeflateDecoder::new(payload.as_slice())
+				.take(MAX_DECOMPRESSED_SIZE + 1)
+				.read_to_end(&mut decompressed)?;
+
+			if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+				return Err(invalid_data("sync frame is too big once decompressed"));
+			}
+
+			decompressed
+		}
+		k => return Err(invalid_data(format!("invalid sync frame kind: {k}"))),
+	};
+
+	rmp_serde::from_slice::<CompressedCRDTOperations>(&payload)
+		.map(CompressedCRDTOperations::into_ops)
+		.map_err(invalid_data)
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+	use sd_sync::{CRDTOperationData, NTP64};
+
+	use uuid::Uuid;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn compresses_big_frames() {
+		let instances = [Uuid::new_v4(), Uuid::new_v4()];
+		let ops = (0..1000)
+			.map(|i| CRDTOperation {
+				instance: instances[i / 600],
+				timestamp: NTP64(i as u64),
+				model: 1,
+				record_id: rmpv::Value::from(i as u64),
+				data: CRDTOperationData::Update {
+					field: "date_modified".to_string(),
+					value: rmpv::Value::Nil,
+				},
+			})
+			.collect::<Vec<_>>();
+
+		let bytes = encode_operations(&ops).unwrap();
+		assert_eq!(bytes[0], FrameKind::Deflate as u8);
+		assert!(bytes.len() < rmp_serde::to_vec_named(&ops).unwrap().len() / 2);
+
+		let decoded = decode_operations(&mut std::io::Cursor::new(bytes))
+			.await
+			.unwrap();
+		assert_eq!(decoded, ops);
+	}
+}
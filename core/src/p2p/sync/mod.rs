@@ -16,6 +16,7 @@ use uuid::Uuid;
 
 use super::P2PManager;
 
+mod frame;
 mod proto;
 pub use proto::*;
 
@@ -34,22 +35,17 @@ mod originator {
 		pub struct Operations(pub Vec<CRDTOperation>);
 
 		impl Operations {
-			// TODO: Per field errors for better error handling
 			pub async fn from_stream(
 				stream: &mut (impl AsyncRead + Unpin),
 			) -> std::io::Result<Self> {
-				Ok(Self(
-					rmp_serde::from_slice(&decode::buf(stream).await.unwrap()).unwrap(),
-				))
+				frame::decode_operations(stream).await.map(Self)
 			}
 
 			pub fn to_bytes(&self) -> Vec<u8> {
-				let Self(args) = self;
-				let mut buf = vec![];
+				let Self(ops) = self;
 
 				// TODO: Error handling
-				encode::buf(&mut buf, &rmp_serde::to_vec_named(&args).unwrap());
-				buf
+				frame::encode_operations(ops).unwrap()
 			}
 		}
 
@@ -0,0 +1,513 @@
+//! WASM plugins extending the media processor and search, run with [Extism](https://extism.org).
+//!
+//! A plugin is a directory in `<data_dir>/plugins` holding its WASM module and a `plugin.json`
+//! [manifest](PluginManifest). Plugins never get access to the filesystem: they are handed the
+//! bytes of the files with the extensions they declared, and can only reach the hosts their
+//! manifest allows. A plugin is only loaded once the user approved its capabilities, see
+//! [`plugin_grants`](crate::node::plugin_grants).
+//!
+//! Every export is optional, and all of them exchange JSON, with bytes encoded as base64:
+//!  - `extract_metadata`: `{ extension, data }` to an object of string keys and values, stored on
+//!    the object of the file
+//!  - `generate_thumbnail`: `{ extension, data }` to `{ data }`, an image in any common format
+//!  - `search_filter`: `{ filter, query }` to a list of search filters, for the filters declared in
+//!    the manifest
+
+use crate::{api::search::SearchFilterArgs, node::config::NodePreferences};
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	io,
+	path::{Component, Path, PathBuf},
+	sync::{Arc, Mutex, PoisonError},
+	time::Duration,
+};
+
+use base64::prelude::*;
+use extism::{Manifest, Plugin, Wasm};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs,
+	sync::{watch, RwLock},
+	task::spawn_blocking,
+};
+use tracing::{error, info, warn};
+
+pub use crate::node::plugin_grants::PluginCapabilities;
+
+const MANIFEST_FILE: &str = "plugin.json";
+
+/// WASM memory pages in a MiB
+const PAGES_PER_MIB: u32 = 16;
+
+const EXTRACT_METADATA: &str = "extract_metadata";
+const GENERATE_THUMBNAIL: &str = "generate_thumbnail";
+const SEARCH_FILTER: &str = "search_filter";
+
+#[derive(Error, Debug)]
+pub enum PluginError {
+	#[error("invalid manifest at '{}': {error}", .path.display())]
+	Manifest {
+		path: Box<Path>,
+		error: serde_json::Error,
+	},
+	#[error("invalid manifest of plugin '{plugin}': {reason}")]
+	InvalidManifest { plugin: String, reason: String },
+	#[error("failed to load plugin '{plugin}': {error}")]
+	Load { plugin: String, error: String },
+	#[error("plugin '{}' must be approved before it's loaded", .0.name)]
+	NotApproved(Box<PluginManifest>),
+	#[error("plugin '{plugin}' failed to run '{function}': {error}")]
+	Call {
+		plugin: String,
+		function: &'static str,
+		error: String,
+	},
+	#[error("plugin '{plugin}' returned invalid data from '{function}': {error}")]
+	Output {
+		plugin: String,
+		function: &'static str,
+		error: String,
+	},
+	#[error("plugin '{plugin}' doesn't have a search filter named '{filter}'")]
+	UnknownSearchFilter { plugin: String, filter: String },
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("failed to run plugin task: {0}")]
+	Task(#[from] tokio::task::JoinError),
+}
+
+/// The `plugin.json` file of a plugin.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+	/// Unique name of the plugin, used to store its metadata
+	pub name: String,
+	pub version: String,
+	#[serde(default)]
+	pub description: Option<String>,
+	/// Path of the WASM module, relative to the plugin directory
+	pub wasm: String,
+	/// Extensions of the files given to the plugin, without the dot
+	#[serde(default)]
+	pub extensions: Vec<String>,
+	#[serde(default)]
+	pub search_filters: Vec<PluginSearchFilter>,
+	#[serde(default)]
+	pub capabilities: PluginCapabilities,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginSearchFilter {
+	pub name: String,
+	#[serde(default)]
+	pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginExports {
+	pub extract_metadata: bool,
+	pub generate_thumbnail: bool,
+	pub search_filter: bool,
+}
+
+#[derive(Serialize)]
+struct FileInput<'a> {
+	extension: &'a str,
+	data: String,
+}
+
+#[derive(Deserialize)]
+struct ThumbnailOutput {
+	data: String,
+}
+
+#[derive(Serialize)]
+struct SearchFilterInput<'a> {
+	filter: &'a str,
+	query: &'a str,
+}
+
+pub struct LoadedPlugin {
+	pub manifest: PluginManifest,
+	pub exports: PluginExports,
+	// Extism plugins can only run one call at a time
+	instance: Arc<Mutex<Plugin>>,
+}
+
+impl LoadedPlugin {
+	/// Loads the plugin if the user approved its capabilities, clamped to the maximums of the
+	/// node, in `grants`.
+	async fn load(
+		dir: &Path,
+		grants: &BTreeMap<String, PluginCapabilities>,
+	) -> Result<Self, PluginError> {
+		let manifest_path = dir.join(MANIFEST_FILE);
+		let mut manifest = serde_json::from_slice::<PluginManifest>(
+			&fs::read(&manifest_path)
+				.await
+				.map_err(|e| FileIOError::from((&manifest_path, e)))?,
+		)
+		.map_err(|error| PluginError::Manifest {
+			path: manifest_path.into_boxed_path(),
+			error,
+		})?;
+
+		validate_manifest(&manifest)?;
+
+		manifest.capabilities = manifest.capabilities.clamped();
+		if grants.get(&manifest.name) != Some(&manifest.capabilities) {
+			return Err(PluginError::NotApproved(Box::new(manifest)));
+		}
+
+		let wasm_path = dir.join(&manifest.wasm);
+		let capabilities = manifest.capabilities.clone();
+		let name = manifest.name.clone();
+
+		let (instance, exports) = spawn_blocking(move || {
+			let wasm_manifest = Manifest::new([Wasm::file(wasm_path)])
+				.with_allowed_hosts(capabilities.allowed_hosts.into_iter())
+				.with_memory_max(capabilities.max_memory_mib.saturating_mul(PAGES_PER_MIB))
+				.with_timeout(Duration::from_secs(capabilities.timeout_secs.into()));
+
+			// WASI is needed by most toolchains, it has no directories or env vars given to it
+			let instance =
+				Plugin::new(&wasm_manifest, [], true).map_err(|e| PluginError::Load {
+					plugin: name,
+					error: e.to_string(),
+				})?;
+
+			let exports = PluginExports {
+				extract_metadata: instance.function_exists(EXTRACT_METADATA),
+				generate_thumbnail: instance.function_exists(GENERATE_THUMBNAIL),
+				search_filter: instance.function_exists(SEARCH_FILTER),
+			};
+
+			Ok::<_, PluginError>((instance, exports))
+		})
+		.await??;
+
+		Ok(Self {
+			manifest,
+			exports,
+			instance: Arc::new(Mutex::new(instance)),
+		})
+	}
+
+	pub fn handles_extension(&self, extension: &str) -> bool {
+		self.manifest
+			.extensions
+			.iter()
+			.any(|ext| ext.eq_ignore_ascii_case(extension))
+	}
+
+	/// Metadata of the file as string keys and values, or `None` if the file is too big for
+	/// the plugin.
+	pub async fn extract_metadata(
+		&self,
+		path: impl AsRef<Path>,
+		extension: &str,
+	) -> Result<Option<HashMap<String, String>>, PluginError> {
+		let Some(data) = self.read_file(path.as_ref()).await? else {
+			return Ok(None);
+		};
+
+		self.call(EXTRACT_METADATA, &FileInput { extension, data })
+			.await
+			.map(Some)
+	}
+
+	/// Image to generate the thumbnail of the file from, or `None` if the file is too big for
+	/// the plugin.
+	pub async fn generate_thumbnail(
+		&self,
+		path: impl AsRef<Path>,
+		extension: &str,
+	) -> Result<Option<Vec<u8>>, PluginError> {
+		let Some(data) = self.read_file(path.as_ref()).await? else {
+			return Ok(None);
+		};
+
+		let ThumbnailOutput { data } = self
+			.call(GENERATE_THUMBNAIL, &FileInput { extension, data })
+			.await?;
+
+		BASE64_STANDARD
+			.decode(data)
+			.map(Some)
+			.map_err(|e| PluginError::Output {
+				plugin: self.manifest.name.clone(),
+				function: GENERATE_THUMBNAIL,
+				error: e.to_string(),
+			})
+	}
+
+	/// Turns the query of one of the search filters declared in the manifest into filters.
+	pub async fn search_filter(
+		&self,
+		filter: &str,
+		query: &str,
+	) -> Result<Vec<SearchFilterArgs>, PluginError> {
+		if !self
+			.manifest
+			.search_filters
+			.iter()
+			.any(|declared| declared.name == filter)
+		{
+			return Err(PluginError::UnknownSearchFilter {
+				plugin: self.manifest.name.clone(),
+				filter: filter.to_string(),
+			});
+		}
+
+		self.call(SEARCH_FILTER, &SearchFilterInput { filter, query })
+			.await
+	}
+
+	async fn read_file(&self, path: &Path) -> Result<Option<String>, PluginError> {
+		let max_size = u64::from(self.manifest.capabilities.max_file_size_mib) * 1024 * 1024;
+
+		let metadata = fs::metadata(path)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))?;
+		if metadata.len() > max_size {
+			return Ok(None);
+		}
+
+		fs::read(path)
+			.await
+			.map(|data| Some(BASE64_STANDARD.encode(data)))
+			.map_err(|e| FileIOError::from((path, e)).into())
+	}
+
+	async fn call<O: DeserializeOwned + Send + 'static>(
+		&self,
+		function: &'static str,
+		input: &impl Serialize,
+	) -> Result<O, PluginError> {
+		let plugin = self.manifest.name.clone();
+		let input = serde_json::to_vec(input).map_err(|e| PluginError::Call {
+			plugin: plugin.clone(),
+			function,
+			error: e.to_string(),
+		})?;
+		let instance = Arc::clone(&self.instance);
+
+		spawn_blocking(move || {
+			let mut instance = instance.lock().unwrap_or_else(PoisonError::into_inner);
+
+			let output: &[u8] = instance
+				.call(function, input)
+				.map_err(|e| PluginError::Call {
+					plugin: plugin.clone(),
+					function,
+					error: e.to_string(),
+				})?;
+
+			serde_json::from_slice(output).map_err(|e| PluginError::Output {
+				plugin,
+				function,
+				error: e.to_string(),
+			})
+		})
+		.await?
+	}
+}
+
+fn validate_manifest(manifest: &PluginManifest) -> Result<(), PluginError> {
+	let invalid = |reason: String| {
+		Err(PluginError::InvalidManifest {
+			plugin: manifest.name.clone(),
+			reason,
+		})
+	};
+
+	if manifest.name.is_empty() {
+		return invalid("the name can't be empty".to_string());
+	}
+
+	// Only wildcards of subdomains, a plugin can't ask to reach any host
+	if let Some(host) = manifest
+		.capabilities
+		.allowed_hosts
+		.iter()
+		.find(|host| host.is_empty() || host.trim_start_matches("*.").contains('*'))
+	{
+		return invalid(format!("invalid allowed host '{host}'"));
+	}
+
+	// The module must stay in the plugin directory
+	if !Path::new(&manifest.wasm)
+		.components()
+		.all(|component| matches!(component, Component::Normal(_)))
+	{
+		return invalid(format!("invalid WASM module path '{}'", manifest.wasm));
+	}
+
+	// Extensions end up in SQL queries of the media processor
+	if let Some(extension) = manifest
+		.extensions
+		.iter()
+		.find(|ext| ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()))
+	{
+		return invalid(format!("invalid extension '{extension}'"));
+	}
+
+	Ok(())
+}
+
+/// The plugins of the node, loaded from the plugins directory.
+pub struct Plugins {
+	dir: PathBuf,
+	node_preferences: watch::Receiver<NodePreferences>,
+	plugins: RwLock<Vec<Arc<LoadedPlugin>>>,
+	/// The manifests of the plugins waiting for the user to approve them
+	pending: RwLock<Vec<PluginManifest>>,
+}
+
+impl Plugins {
+	pub async fn new(
+		dir: PathBuf,
+		node_preferences: watch::Receiver<NodePreferences>,
+	) -> Arc<Self> {
+		let this = Arc::new(Self {
+			dir,
+			node_preferences,
+			plugins: RwLock::default(),
+			pending: RwLock::default(),
+		});
+
+		if let Err(e) = this.reload().await {
+			error!("Failed to load plugins: {e:#?}");
+		}
+
+		this
+	}
+
+	/// Loads again every plugin of the plugins directory, skipping the ones failing to load.
+	pub async fn reload(&self) -> Result<Vec<PluginError>, PluginError> {
+		fs::create_dir_all(&self.dir)
+			.await
+			.map_err(|e| FileIOError::from((&self.dir, e)))?;
+
+		let mut read_dir = fs::read_dir(&self.dir)
+			.await
+			.map_err(|e| FileIOError::from((&self.dir, e)))?;
+
+		let grants = self.node_preferences.borrow().plugins.grants.clone();
+		let mut plugins = Vec::<Arc<LoadedPlugin>>::new();
+		let mut pending = vec![];
+		let mut errors = vec![];
+
+		while let Some(entry) = read_dir
+			.next_entry()
+			.await
+			.map_err(|e| FileIOError::from((&self.dir, e)))?
+		{
+			let path = entry.path();
+			if !fs::metadata(&path)
+				.await
+				.map(|metadata| metadata.is_dir())
+				.unwrap_or(false)
+			{
+				continue;
+			}
+
+			match LoadedPlugin::load(&path, &grants).await {
+				Ok(plugin)
+					if plugins
+						.iter()
+						.any(|loaded| loaded.manifest.name == plugin.manifest.name) =>
+				{
+					errors.push(PluginError::InvalidManifest {
+						plugin: plugin.manifest.name,
+						reason: "another plugin has the same name".to_string(),
+					});
+				}
+				Ok(plugin) => {
+					info!(
+						"Loaded plugin '{}' v{}",
+						plugin.manifest.name, plugin.manifest.version
+					);
+					plugins.push(Arc::new(plugin));
+				}
+				Err(PluginError::FileIO(e)) if e.source.kind() == io::ErrorKind::NotFound => {
+					// Not a plugin directory
+				}
+				Err(PluginError::NotApproved(manifest)) => {
+					warn!("Plugin '{}' is waiting to be approved", manifest.name);
+					pending.push(*manifest);
+				}
+				Err(e) => errors.push(e),
+			}
+		}
+
+		for e in &errors {
+			error!("Failed to load plugin: {e:#?}");
+		}
+
+		*self.plugins.write().await = plugins;
+		*self.pending.write().await = pending;
+
+		Ok(errors)
+	}
+
+	pub async fn list(&self) -> Vec<Arc<LoadedPlugin>> {
+		self.plugins.read().await.clone()
+	}
+
+	/// The plugins waiting for the user to approve them, with their capabilities clamped to the
+	/// maximums of the node.
+	pub async fn pending(&self) -> Vec<PluginManifest> {
+		self.pending.read().await.clone()
+	}
+
+	pub async fn get(&self, name: &str) -> Option<Arc<LoadedPlugin>> {
+		self.plugins
+			.read()
+			.await
+			.iter()
+			.find(|plugin| plugin.manifest.name == name)
+			.cloned()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn manifest(wasm: &str, extensions: &[&str]) -> PluginManifest {
+		PluginManifest {
+			name: "test".to_string(),
+			version: "0.1.0".to_string(),
+			description: None,
+			wasm: wasm.to_string(),
+			extensions: extensions.iter().map(ToString::to_string).collect(),
+			search_filters: vec![],
+			capabilities: PluginCapabilities::default(),
+		}
+	}
+
+	#[test]
+	fn validates_manifest() {
+		assert!(validate_manifest(&manifest("plugin.wasm", &["cr2", "NEF"])).is_ok());
+		assert!(validate_manifest(&manifest("target/plugin.wasm", &[])).is_ok());
+
+		assert!(validate_manifest(&manifest("../plugin.wasm", &[])).is_err());
+		assert!(validate_manifest(&manifest("/plugin.wasm", &[])).is_err());
+		assert!(validate_manifest(&manifest("plugin.wasm", &["x') OR 1=1 --"])).is_err());
+		assert!(validate_manifest(&manifest("plugin.wasm", &[""])).is_err());
+
+		let mut with_hosts = manifest("plugin.wasm", &[]);
+		with_hosts.capabilities.allowed_hosts = vec!["*.example.com".to_string()];
+		assert!(validate_manifest(&with_hosts).is_ok());
+		with_hosts.capabilities.allowed_hosts = vec!["*".to_string()];
+		assert!(validate_manifest(&with_hosts).is_err());
+	}
+}
@@ -1,4 +1,11 @@
-use sd_prisma::prisma::{preference, PrismaClient};
+use crate::sync;
+
+use sd_prisma::{
+	prisma::{preference, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::{CRDTOperation, OperationFactory};
+use sd_utils::msgpack;
 
 use std::collections::BTreeMap;
 
@@ -126,6 +133,38 @@ impl PreferenceKVs {
 			.collect()
 	}
 
+	/// Like [`Self::into_upserts`], along with the operations syncing them to the other devices of
+	/// the library.
+	pub fn into_synced_upserts(
+		self,
+		sync: &sync::Manager,
+		db: &PrismaClient,
+	) -> (Vec<CRDTOperation>, Vec<preference::UpsertQuery>) {
+		self.0
+			.into_iter()
+			.map(|(key, value)| {
+				let op = sync.shared_update(
+					prisma_sync::preference::SyncId {
+						key: key.to_string(),
+					},
+					preference::value::NAME,
+					msgpack!(&value.0),
+				);
+
+				let value = vec![preference::value::set(Some(value.0))];
+
+				(
+					op,
+					db.preference().upsert(
+						preference::key::equals(key.to_string()),
+						preference::create(key.to_string(), value.clone()),
+						value,
+					),
+				)
+			})
+			.unzip()
+	}
+
 	pub fn parse<T: Preferences>(self) -> T {
 		let entries = self
 			.0
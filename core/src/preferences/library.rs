@@ -1,4 +1,7 @@
-use crate::api::search;
+use crate::{
+	api::search::{self, file_path::FilePathOrder, SearchFilterArgs},
+	library::Library,
+};
 
 use sd_prisma::prisma::PrismaClient;
 
@@ -19,13 +22,19 @@ pub struct LibraryPreferences {
 	#[serde(default)]
 	#[specta(optional)]
 	tag: HashMap<Uuid, Settings<TagSettings>>,
+	#[serde(default)]
+	#[specta(optional)]
+	saved_search: HashMap<Uuid, Settings<SavedSearchSettings>>,
 }
 
 impl LibraryPreferences {
-	pub async fn write(self, db: &PrismaClient) -> prisma_client_rust::Result<()> {
+	/// Writes the preferences, syncing them to the other devices of the library so the views look
+	/// the same on all of them.
+	pub async fn write(self, Library { db, sync, .. }: &Library) -> prisma_client_rust::Result<()> {
 		let kvs = self.to_kvs();
 
-		db._batch(kvs.into_upserts(db)).await?;
+		sync.write_ops(db, kvs.into_synced_upserts(sync, db))
+			.await?;
 
 		Ok(())
 	}
@@ -51,6 +60,20 @@ impl LibraryPreferences {
 
 		Ok(prefs.parse())
 	}
+
+	/// The explorer settings of the location with this `pub_id`, if it has any.
+	pub fn location_explorer(&self, pub_id: &Uuid) -> Option<&ExplorerSettings<FilePathOrder>> {
+		self.location
+			.get(pub_id)
+			.map(|Settings(settings)| &settings.explorer)
+	}
+
+	/// The explorer settings of the saved search with this `pub_id`, if it has any.
+	pub fn saved_search_explorer(&self, pub_id: &Uuid) -> Option<&ExplorerSettings<FilePathOrder>> {
+		self.saved_search
+			.get(pub_id)
+			.map(|Settings(settings)| &settings.explorer)
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize, Type, Debug)]
@@ -65,6 +88,12 @@ pub struct TagSettings {
 	explorer: ExplorerSettings<search::object::ObjectOrder>,
 }
 
+#[derive(Clone, Serialize, Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchSettings {
+	explorer: ExplorerSettings<FilePathOrder>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorerSettings<TOrder> {
@@ -85,6 +114,27 @@ pub struct ExplorerSettings<TOrder> {
 	order: Option<Option<TOrder>>,
 	#[serde(default)]
 	show_hidden_files: bool,
+	/// Added to the filters of the client by `search.paths` when it asks for the defaults of the view
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[specta(optional)]
+	filters: Option<Vec<SearchFilterArgs>>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[specta(optional)]
+	group_directories: Option<bool>,
+}
+
+impl<TOrder> ExplorerSettings<TOrder> {
+	pub fn order(&self) -> Option<&TOrder> {
+		self.order.as_ref().and_then(Option::as_ref)
+	}
+
+	pub fn filters(&self) -> &[SearchFilterArgs] {
+		self.filters.as_deref().unwrap_or_default()
+	}
+
+	pub fn group_directories(&self) -> Option<bool> {
+		self.group_directories
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize, Type, Debug)]
@@ -104,12 +154,17 @@ pub enum DoubleClickAction {
 
 impl Preferences for LibraryPreferences {
 	fn to_kvs(self) -> PreferenceKVs {
-		let Self { location, tag } = self;
+		let Self {
+			location,
+			tag,
+			saved_search,
+		} = self;
 
 		let mut ret = vec![];
 
 		ret.extend(location.to_kvs().with_prefix("location"));
 		ret.extend(tag.to_kvs().with_prefix("tag"));
+		ret.extend(saved_search.to_kvs().with_prefix("savedSearch"));
 
 		PreferenceKVs::new(ret)
 	}
@@ -124,6 +179,10 @@ impl Preferences for LibraryPreferences {
 				.remove("tag")
 				.map(|value| HashMap::from_entries(value.expect_nested()))
 				.unwrap_or_default(),
+			saved_search: entries
+				.remove("savedSearch")
+				.map(|value| HashMap::from_entries(value.expect_nested()))
+				.unwrap_or_default(),
 		}
 	}
 }
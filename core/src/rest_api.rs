@@ -0,0 +1,782 @@
+//! REST endpoints mapped to the rspc procedures, so scripts and third party apps can drive a
+//! headless node without the rspc bindings.
+//!
+//! Every endpoint calls a single procedure and answers with its JSON output. Requests must carry
+//! an API token as `Authorization: Bearer <token>`, either one created with `apiTokens.create`,
+//! which is limited to its scopes and rate limit, or one of the tokens given to the router.
+//!
+//! With the `graphql` feature, a read only GraphQL schema is also served at `/graphql`.
+
+use crate::{
+	api::Router as ApiRouter,
+	location::{
+		ingest::{self, IngestError},
+		LocationError,
+	},
+	node::api_tokens::{ApiTokenRejection, ApiTokenScope},
+	Node,
+};
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use axum::{
+	body::Body,
+	extract::{BodyStream, Path, Query, State},
+	http::{header, HeaderValue, Method, Request, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	routing::{delete, get, post, put},
+	Json, Router,
+};
+use percent_encoding::percent_decode_str;
+use rspc::{ExecError, ExecKind};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+use uuid::Uuid;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+#[derive(Clone)]
+struct RestState {
+	node: Arc<Node>,
+	api: Arc<ApiRouter>,
+	/// Tokens with every scope and no rate limit. Only hashes are kept, so comparing them takes
+	/// the same time whatever the token is
+	tokens: Arc<[blake3::Hash]>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+	Query,
+	Mutation,
+}
+
+/// Builds the REST gateway, `tokens` are allowed on top of the API tokens of the node.
+pub fn router(
+	node: Arc<Node>,
+	api: Arc<ApiRouter>,
+	tokens: impl IntoIterator<Item = String>,
+) -> Router<()> {
+	let state = RestState {
+		node,
+		api,
+		tokens: tokens
+			.into_iter()
+			.map(|token| blake3::hash(token.as_bytes()))
+			.collect(),
+	};
+
+	let router = Router::new()
+		.route(
+			"/libraries",
+			get(|State(state): State<RestState>| async move {
+				call(&state, Kind::Query, "library.list", None, Value::Null).await
+			}),
+		)
+		.route(
+			"/libraries/:library_id/search/paths",
+			post(library_procedure(Kind::Query, "search.paths")),
+		)
+		.route(
+			"/libraries/:library_id/search/objects",
+			post(library_procedure(Kind::Query, "search.objects")),
+		)
+		.route(
+			"/libraries/:library_id/locations",
+			get(library_procedure(Kind::Query, "locations.list"))
+				.post(library_procedure(Kind::Mutation, "locations.create")),
+		)
+		.route(
+			"/libraries/:library_id/locations/:location_id",
+			get(library_id_procedure::<i32>(Kind::Query, "locations.get")).delete(
+				library_id_procedure::<i32>(Kind::Mutation, "locations.delete"),
+			),
+		)
+		.route(
+			"/libraries/:library_id/locations/:location_id/rescan",
+			post(
+				|State(state): State<RestState>,
+				 Path((library_id, location_id)): Path<(Uuid, i32)>,
+				 Query(RescanQuery { reidentify_objects }): Query<RescanQuery>| async move {
+					call(
+						&state,
+						Kind::Mutation,
+						"locations.fullRescan",
+						Some(library_id),
+						json!({
+							"location_id": location_id,
+							"reidentify_objects": reidentify_objects,
+						}),
+					)
+					.await
+				},
+			),
+		)
+		.route(
+			"/libraries/:library_id/locations/:location_id/jobs/:job",
+			post(
+				|State(state): State<RestState>,
+				 Path((library_id, location_id, job)): Path<(Uuid, i32, String)>,
+				 Query(LocationJobQuery { path, regenerate }): Query<LocationJobQuery>| async move {
+					let key = match job.as_str() {
+						"thumbnails" => "jobs.generateThumbsForLocation",
+						"labels" => "jobs.generateLabelsForLocation",
+						"identify" => "jobs.identifyUniqueFiles",
+						"validate" => "jobs.objectValidator",
+						_ => {
+							return error_response(
+								StatusCode::NOT_FOUND,
+								format!("Unknown job '{job}'"),
+							)
+						}
+					};
+
+					call(
+						&state,
+						Kind::Mutation,
+						key,
+						Some(library_id),
+						json!({ "id": location_id, "path": path, "regenerate": regenerate }),
+					)
+					.await
+				},
+			),
+		)
+		.route(
+			"/libraries/:library_id/locations/:location_id/ingest",
+			post(
+				|State(state): State<RestState>,
+				 Path((library_id, location_id)): Path<(Uuid, i32)>,
+				 Json(mut arg): Json<Value>| async move {
+					let Some(fields) = arg.as_object_mut() else {
+						return error_response(
+							StatusCode::BAD_REQUEST,
+							"Request body must be a JSON object".to_string(),
+						);
+					};
+					fields.insert("locationId".to_string(), location_id.into());
+
+					call(
+						&state,
+						Kind::Mutation,
+						"locations.ingest",
+						Some(library_id),
+						arg,
+					)
+					.await
+				},
+			),
+		)
+		.route(
+			"/libraries/:library_id/locations/:location_id/ingest/:name",
+			put(
+				|State(state): State<RestState>,
+				 Path((library_id, location_id, name)): Path<(Uuid, i32, String)>,
+				 body: BodyStream| async move {
+					let Some(library) = state.node.libraries.get_library(&library_id).await else {
+						return error_response(
+							StatusCode::NOT_FOUND,
+							format!("Library '{library_id}' not found"),
+						);
+					};
+
+					match ingest::ingest_stream(&state.node, &library, location_id, &name, body)
+						.await
+					{
+						Ok(file) => Json(file).into_response(),
+						Err(IngestError::Location(LocationError::IdNotFound(_))) => error_response(
+							StatusCode::NOT_FOUND,
+							format!("Location '{location_id}' not found"),
+						),
+						Err(e @ IngestError::MissingFileName) => {
+							error_response(StatusCode::BAD_REQUEST, e.to_string())
+						}
+						Err(e @ IngestError::TooLarge { .. }) => {
+							error_response(StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+						}
+						Err(e) => {
+							error!("Failed to ingest upload '{name}': {e:#?}");
+							error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+						}
+					}
+				},
+			),
+		)
+		.route(
+			"/libraries/:library_id/jobs",
+			get(library_procedure(Kind::Query, "jobs.reports")),
+		)
+		.route(
+			"/libraries/:library_id/jobs/:job_id/pause",
+			post(library_id_procedure::<Uuid>(Kind::Mutation, "jobs.pause")),
+		)
+		.route(
+			"/libraries/:library_id/jobs/:job_id/resume",
+			post(library_id_procedure::<Uuid>(Kind::Mutation, "jobs.resume")),
+		)
+		.route(
+			"/libraries/:library_id/jobs/:job_id",
+			delete(library_id_procedure::<Uuid>(Kind::Mutation, "jobs.cancel")),
+		)
+		.route(
+			"/libraries/:library_id/sync",
+			get(library_procedure(Kind::Query, "sync.status")),
+		)
+		.route(
+			"/libraries/:library_id/sync/enabled",
+			get(library_procedure(Kind::Query, "sync.enabled")),
+		)
+		.route(
+			"/libraries/:library_id/tags",
+			get(library_procedure(Kind::Query, "tags.list"))
+				.post(library_procedure(Kind::Mutation, "tags.create")),
+		)
+		.route(
+			"/libraries/:library_id/tags/:tag_id",
+			get(library_id_procedure::<i32>(Kind::Query, "tags.get"))
+				.delete(library_id_procedure::<i32>(Kind::Mutation, "tags.delete")),
+		)
+		.route(
+			"/libraries/:library_id/tags/:tag_id/assign",
+			post(
+				|State(state): State<RestState>,
+				 Path((library_id, tag_id)): Path<(Uuid, i32)>,
+				 Json(mut arg): Json<Value>| async move {
+					let Some(fields) = arg.as_object_mut() else {
+						return error_response(
+							StatusCode::BAD_REQUEST,
+							"Request body must be a JSON object".to_string(),
+						);
+					};
+					fields.insert("tag_id".to_string(), tag_id.into());
+
+					call(&state, Kind::Mutation, "tags.assign", Some(library_id), arg).await
+				},
+			),
+		);
+
+	#[cfg(feature = "graphql")]
+	let router = router.route(
+		"/graphql",
+		post({
+			let schema = crate::graphql::schema(state.node.clone());
+			move |Json(request): Json<async_graphql::Request>| async move {
+				Json(schema.execute(request).await)
+			}
+		}),
+	);
+
+	router
+		.route_layer(middleware::from_fn_with_state(state.clone(), token_auth))
+		.with_state(state)
+}
+
+#[derive(Deserialize)]
+struct RescanQuery {
+	#[serde(default)]
+	reidentify_objects: bool,
+}
+
+#[derive(Deserialize)]
+struct LocationJobQuery {
+	/// Sub path of the location to run the job on, the whole location by default
+	#[serde(default)]
+	path: String,
+	#[serde(default)]
+	regenerate: bool,
+}
+
+async fn token_auth(
+	State(state): State<RestState>,
+	request: Request<Body>,
+	next: Next<Body>,
+) -> Response {
+	let Some(token) = bearer_token(&request) else {
+		return error_response(StatusCode::UNAUTHORIZED, "Unauthorized".to_string());
+	};
+
+	if !state
+		.tokens
+		.contains(&blake3::hash(token.trim().as_bytes()))
+	{
+		// Searches are queries even if their filters are sent as a body
+		let path = request.uri().path();
+		let scope = if request.method() == Method::GET
+			|| path.ends_with("/search/paths")
+			|| path.ends_with("/search/objects")
+			|| path == "/graphql"
+		{
+			ApiTokenScope::Read
+		} else {
+			ApiTokenScope::Write
+		};
+
+		if let Err(response) = authorize(&state.node, token, scope).await {
+			return response;
+		}
+	}
+
+	next.run(request).await
+}
+
+/// The token of the `Authorization: Bearer <token>` header of the request.
+pub fn bearer_token<B>(request: &Request<B>) -> Option<&str> {
+	request
+		.headers()
+		.get(header::AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Procedures an API token with the read scope can call: the queries and subscriptions of the
+/// library data. The ones reading any path of the node, like the thumbnails of ephemeral files,
+/// are left out.
+const READ_PROCEDURES: &[&str] = &[
+	"backups.getAll",
+	"buildInfo",
+	"duplicates.list",
+	"files.get",
+	"files.getConvertibleImageExtensions",
+	"files.getMediaData",
+	"files.getPath",
+	"files.opProgress",
+	"invalidation.evictions",
+	"invalidation.listen",
+	"jobs.checksumSettings",
+	"jobs.concurrencyLimits",
+	"jobs.history",
+	"jobs.historyRetention",
+	"jobs.isActive",
+	"jobs.itemProgress",
+	"jobs.newThumbnail",
+	"jobs.offloadSettings",
+	"jobs.priorities",
+	"jobs.progress",
+	"jobs.progressSubscribe",
+	"jobs.reports",
+	"jobs.resourceLimits",
+	"jobs.retryPolicies",
+	"labels.count",
+	"labels.get",
+	"labels.getForObject",
+	"labels.getWithObjects",
+	"labels.list",
+	"labels.listWithThumbnails",
+	"library.actors",
+	"library.databaseSettings",
+	"library.kindStatistics",
+	"library.kinds",
+	"library.list",
+	"library.statistics",
+	"locations.get",
+	"locations.getWithRules",
+	"locations.indexer_rules.cacheStats",
+	"locations.indexer_rules.get",
+	"locations.indexer_rules.list",
+	"locations.indexer_rules.listForLocation",
+	"locations.ingestSettings",
+	"locations.list",
+	"locations.online",
+	"locations.usageTree",
+	"locations.validationReport",
+	"nodeState",
+	"nodes.backgroundPolicy",
+	"nodes.listLocations",
+	"notifications.get",
+	"notifications.listen",
+	"p2p.list",
+	"p2p.metrics",
+	"p2p.state",
+	"preferences.get",
+	"search.explorer.stream",
+	"search.history.list",
+	"search.history.settings",
+	"search.names",
+	"search.objects",
+	"search.objectsCount",
+	"search.paths",
+	"search.pathsCount",
+	"search.pathsLive",
+	"search.pathsStreamed",
+	"search.saved.get",
+	"search.saved.list",
+	"search.saved.subscribe",
+	"search.suggest",
+	"sync.active",
+	"sync.enabled",
+	"sync.messages",
+	"sync.newMessage",
+	"sync.status",
+	"tags.get",
+	"tags.getForObject",
+	"tags.getWithObjects",
+	"tags.list",
+	"tags.rules.actions",
+	"tags.rules.list",
+	"thumbnails.cacheStats",
+	"thumbnails.settings",
+	"trash.list",
+	"trash.settings",
+	"volumes.list",
+];
+
+/// Procedures an API token with the write scope can call on top of the [`READ_PROCEDURES`]: the
+/// mutations of the library data within its locations. The ones reaching other paths or servers,
+/// or changing the settings of the node, are left out.
+const WRITE_PROCEDURES: &[&str] = &[
+	"backups.backup",
+	"duplicates.find",
+	"files.bulkUpdate",
+	"files.convertImage",
+	"files.copyFiles",
+	"files.createFile",
+	"files.createFolder",
+	"files.cutFiles",
+	"files.deleteFiles",
+	"files.eraseFiles",
+	"files.moveToTrash",
+	"files.removeAccessTime",
+	"files.renameFile",
+	"files.setFavorite",
+	"files.setNote",
+	"files.setRating",
+	"files.updateAccessTime",
+	"jobs.cancel",
+	"jobs.clear",
+	"jobs.clearAll",
+	"jobs.exportXmp",
+	"jobs.generateLabelsForLocation",
+	"jobs.generateThumbsForLocation",
+	"jobs.identifyUniqueFiles",
+	"jobs.importMetadata",
+	"jobs.objectValidator",
+	"jobs.pause",
+	"jobs.pruneHistory",
+	"jobs.resume",
+	"jobs.setPriority",
+	"labels.delete",
+	"library.edit",
+	"locations.delete",
+	"locations.fullRescan",
+	"locations.indexer_rules.create",
+	"locations.indexer_rules.delete",
+	"locations.quickRescan",
+	"locations.setValidationSchedule",
+	"locations.subPathRescan",
+	"locations.validate",
+	"notifications.dismiss",
+	"notifications.dismissAll",
+	"preferences.update",
+	"search.history.delete",
+	"search.history.record",
+	"search.history.setSettings",
+	"search.saved.create",
+	"search.saved.delete",
+	"search.saved.update",
+	"tags.assign",
+	"tags.create",
+	"tags.delete",
+	"tags.reparent",
+	"tags.rules.create",
+	"tags.rules.delete",
+	"tags.rules.update",
+	"tags.update",
+	"trash.empty",
+	"trash.restore",
+	"trash.setSettings",
+];
+
+/// Scope an API token needs to call the procedure with this `key`, the admin scope unless it's
+/// one of the [`READ_PROCEDURES`] or [`WRITE_PROCEDURES`].
+pub fn procedure_scope(key: &str) -> ApiTokenScope {
+	if READ_PROCEDURES.contains(&key) {
+		ApiTokenScope::Read
+	} else if WRITE_PROCEDURES.contains(&key) {
+		ApiTokenScope::Write
+	} else {
+		ApiTokenScope::Admin
+	}
+}
+
+/// Scope an API token needs for a request to the gateway of `sd-server`.
+///
+/// The procedures of a rspc request are looked up by their percent-decoded keys, see
+/// [`procedure_scope`]. The rspc websocket can call any procedure, so it needs the admin scope,
+/// like anything else not known to only read the library data.
+pub fn gateway_scope<B>(request: &Request<B>) -> ApiTokenScope {
+	let Ok(path) = percent_decode_str(request.uri().path()).decode_utf8() else {
+		return ApiTokenScope::Admin;
+	};
+
+	if request.headers().contains_key(header::UPGRADE) {
+		return ApiTokenScope::Admin;
+	}
+
+	if let Some(keys) = path.strip_prefix("/rspc/") {
+		return keys
+			.split(',')
+			.map(|key| match key {
+				"ws" => ApiTokenScope::Admin,
+				key => procedure_scope(key),
+			})
+			.max()
+			.unwrap_or(ApiTokenScope::Admin);
+	}
+
+	let reads = matches!(
+		*request.method(),
+		Method::GET | Method::HEAD | Method::OPTIONS
+	) || request.method().as_str() == "PROPFIND";
+
+	// The paths of the web app, the files and thumbnails of the libraries, and the read only
+	// WebDAV server. Files by path and the files of other nodes are left to admin tokens
+	let readable = path == "/health"
+		|| path == "/metrics"
+		|| path.starts_with("/spacedrive/thumbnail/")
+		|| path.starts_with("/spacedrive/file/")
+		|| path.starts_with("/webdav")
+		|| !(path.starts_with("/spacedrive") || path.starts_with("/rspc"));
+
+	if reads && readable {
+		ApiTokenScope::Read
+	} else {
+		ApiTokenScope::Admin
+	}
+}
+
+/// Checks the API token has `scope` and takes a request from its rate limit.
+pub async fn authorize(node: &Node, token: &str, scope: ApiTokenScope) -> Result<(), Response> {
+	let result = node.config.get().await.preferences.api_tokens.authorize(
+		&node.api_token_limiter,
+		token,
+		scope,
+	);
+
+	match result {
+		Ok(_) => Ok(()),
+		Err(ApiTokenRejection::Unknown) => Err(error_response(
+			StatusCode::UNAUTHORIZED,
+			"Unauthorized".to_string(),
+		)),
+		Err(e @ ApiTokenRejection::MissingScope(_)) => {
+			Err(error_response(StatusCode::FORBIDDEN, e.to_string()))
+		}
+		Err(e @ ApiTokenRejection::RateLimited { retry_after }) => {
+			let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, e.to_string());
+			response.headers_mut().insert(
+				header::RETRY_AFTER,
+				HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+			);
+
+			Err(response)
+		}
+	}
+}
+
+/// A library procedure taking the request body as argument, or no argument if there is none.
+fn library_procedure(
+	kind: Kind,
+	key: &'static str,
+) -> impl FnOnce(State<RestState>, Path<Uuid>, Option<Json<Value>>) -> HandlerFuture
+       + Clone
+       + Send
+       + 'static {
+	move |State(state), Path(library_id), arg| {
+		Box::pin(async move {
+			call(
+				&state,
+				kind,
+				key,
+				Some(library_id),
+				arg.map_or(Value::Null, |Json(arg)| arg),
+			)
+			.await
+		})
+	}
+}
+
+/// A library procedure taking the id in the path as argument.
+fn library_id_procedure<Id>(
+	kind: Kind,
+	key: &'static str,
+) -> impl FnOnce(State<RestState>, Path<(Uuid, Id)>) -> HandlerFuture + Clone + Send + 'static
+where
+	Id: Serialize + DeserializeOwned + Send + 'static,
+{
+	move |State(state), Path((library_id, id))| {
+		Box::pin(async move { call(&state, kind, key, Some(library_id), json!(id)).await })
+	}
+}
+
+async fn call(
+	state: &RestState,
+	kind: Kind,
+	key: &'static str,
+	library_id: Option<Uuid>,
+	arg: Value,
+) -> Response {
+	let input = match library_id {
+		Some(library_id) => {
+			if state
+				.node
+				.libraries
+				.get_library(&library_id)
+				.await
+				.is_none()
+			{
+				return error_response(
+					StatusCode::NOT_FOUND,
+					format!("Library '{library_id}' not found"),
+				);
+			}
+
+			json!({ "library_id": library_id, "arg": arg })
+		}
+		None => arg,
+	};
+
+	let kind = match kind {
+		Kind::Query => ExecKind::Query,
+		Kind::Mutation => ExecKind::Mutation,
+	};
+
+	match state
+		.api
+		.exec(state.node.clone(), kind, key.to_string(), Some(input))
+		.await
+	{
+		Ok(output) => Json(output).into_response(),
+		Err(ExecError::OperationNotFound(_)) => {
+			error_response(StatusCode::NOT_FOUND, format!("Unknown procedure '{key}'"))
+		}
+		Err(ExecError::DeserializingArgErr(e)) => {
+			error_response(StatusCode::BAD_REQUEST, format!("Invalid arguments: {e}"))
+		}
+		Err(e) => {
+			error!("REST API call to procedure '{key}' failed: {e:#?}");
+			error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+		}
+	}
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+	(status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scope(method: Method, uri: &str) -> ApiTokenScope {
+		gateway_scope(
+			&Request::builder()
+				.method(method)
+				.uri(uri)
+				.body(())
+				.expect("valid request"),
+		)
+	}
+
+	#[test]
+	fn procedures_outside_the_allowlists_need_admin() {
+		assert_eq!(
+			scope(Method::GET, "/rspc/search.paths"),
+			ApiTokenScope::Read
+		);
+		assert_eq!(
+			scope(Method::POST, "/rspc/tags.create"),
+			ApiTokenScope::Write
+		);
+
+		for keys in [
+			"hooks.create",
+			"plugins.reload",
+			"webhooks.list",
+			"remoteAdmin.setScopes",
+			"mirrors.run",
+			"locations.ingest",
+			"locations.create",
+			"files.copy",
+			"thumbnails.request",
+			"library.delete",
+			"jobs.setConcurrencyLimit",
+			"tags.create,apiTokens.create",
+			"tags%2Ecreate,apiTokens%2Ecreate",
+			"api%54okens.create",
+			"ws",
+		] {
+			assert_eq!(
+				scope(Method::POST, &format!("/rspc/{keys}")),
+				ApiTokenScope::Admin,
+				"{keys}"
+			);
+		}
+
+		assert_eq!(
+			scope(Method::GET, "/rspc/search%2Epaths"),
+			ApiTokenScope::Read
+		);
+		assert_eq!(
+			scope(
+				Method::GET,
+				"/spacedrive/local-file-by-path/%2Fetc%2Fpasswd"
+			),
+			ApiTokenScope::Admin
+		);
+		assert_eq!(
+			scope(Method::GET, "/spacedrive/thumbnail/a/b.webp"),
+			ApiTokenScope::Read
+		);
+	}
+
+	#[test]
+	fn every_procedure_has_a_scope() {
+		let router = crate::api::mount();
+
+		let queries = router.queries().keys().cloned().collect::<Vec<_>>();
+		let subscriptions = router.subscriptions().keys().cloned().collect::<Vec<_>>();
+		let mutations = router.mutations().keys().cloned().collect::<Vec<_>>();
+
+		// A renamed procedure would silently need the admin scope
+		for key in READ_PROCEDURES {
+			assert!(
+				queries.iter().chain(&subscriptions).any(|k| k == key),
+				"'{key}' isn't a query or a subscription"
+			);
+		}
+
+		for key in WRITE_PROCEDURES {
+			assert!(
+				queries
+					.iter()
+					.chain(&subscriptions)
+					.chain(&mutations)
+					.any(|k| k == key),
+				"'{key}' isn't a procedure"
+			);
+		}
+
+		for key in &mutations {
+			assert_ne!(procedure_scope(key), ApiTokenScope::Read, "{key}");
+		}
+
+		// Running code, reaching other paths, servers or nodes, and changing trust stay admin only
+		for key in queries.iter().chain(&subscriptions).chain(&mutations) {
+			if [
+				"apiTokens.",
+				"ephemeralFiles.",
+				"hooks.",
+				"mirrors.",
+				"plugins.",
+				"remoteAdmin.",
+				"webhooks.",
+			]
+			.iter()
+			.any(|prefix| key.starts_with(prefix))
+			{
+				assert_eq!(procedure_scope(key), ApiTokenScope::Admin, "{key}");
+			}
+		}
+	}
+}
@@ -0,0 +1,134 @@
+//! Export of the core traces, like job runs, indexer walks, sync ingestion and P2P dials, to an
+//! OpenTelemetry collector over OTLP/HTTP.
+//!
+//! The export is only available when the core is built with the `telemetry` feature, and is
+//! turned on and off at runtime from the node preferences.
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
+pub struct TelemetryPreferences {
+	/// Base URL of the OTLP/HTTP collector, like `http://localhost:4318`, traces aren't exported
+	/// if missing
+	#[serde(default)]
+	pub otlp_endpoint: Option<String>,
+}
+
+#[cfg(feature = "telemetry")]
+pub(crate) use otlp::{layer, watch_preferences};
+
+#[cfg(feature = "telemetry")]
+mod otlp {
+	use crate::node::config::NodePreferences;
+
+	use std::sync::OnceLock;
+
+	use opentelemetry::KeyValue;
+	use opentelemetry_otlp::WithExportConfig;
+	use opentelemetry_sdk::{runtime, trace, Resource};
+	use tokio::{spawn, sync::watch, task::spawn_blocking};
+	use tracing::{error, info};
+	use tracing_opentelemetry::OpenTelemetryLayer;
+	use tracing_subscriber::{filter::Filtered, reload, EnvFilter, Layer, Registry};
+
+	/// Overrides the spans exported, with the same syntax as `RUST_LOG`
+	const FILTER_VAR: &str = "SD_OTLP_FILTER";
+	const DEFAULT_FILTER: &str = "sd_core=info,sd_core_sync=info,sd_p2p=info";
+	const SERVICE_NAME: &str = "spacedrive";
+
+	type OtlpLayer = OpenTelemetryLayer<Registry, trace::Tracer>;
+	type FilteredReloadLayer =
+		Filtered<reload::Layer<Option<OtlpLayer>, Registry>, EnvFilter, Registry>;
+
+	static HANDLE: OnceLock<reload::Handle<Option<OtlpLayer>, Registry>> = OnceLock::new();
+
+	/// Layer exporting the spans to the collector set up in the preferences, doing nothing until
+	/// [`watch_preferences`] is called.
+	pub(crate) fn layer() -> FilteredReloadLayer {
+		let (layer, handle) = reload::Layer::new(None);
+
+		// The logger is only set up once per process
+		HANDLE.set(handle).ok();
+
+		layer.with_filter(
+			EnvFilter::try_from_env(FILTER_VAR).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER)),
+		)
+	}
+
+	/// Starts or stops exporting the traces whenever the endpoint in the preferences changes.
+	pub(crate) fn watch_preferences(mut preferences_rx: watch::Receiver<NodePreferences>) {
+		spawn(async move {
+			let mut current_endpoint = None;
+
+			loop {
+				let endpoint = preferences_rx
+					.borrow_and_update()
+					.telemetry
+					.otlp_endpoint
+					.clone();
+
+				if endpoint != current_endpoint {
+					set_endpoint(endpoint.as_deref(), current_endpoint.is_some()).await;
+					current_endpoint = endpoint;
+				}
+
+				if preferences_rx.changed().await.is_err() {
+					break;
+				}
+			}
+		});
+	}
+
+	async fn set_endpoint(endpoint: Option<&str>, was_exporting: bool) {
+		let Some(handle) = HANDLE.get() else {
+			// Logger wasn't set up with `Node::init_logger`
+			return;
+		};
+
+		if was_exporting {
+			if let Err(e) = handle.modify(|layer| *layer = None) {
+				error!("Failed to stop exporting traces: {e:#?}");
+				return;
+			}
+
+			// Flushes the spans still queued for the previous collector
+			spawn_blocking(opentelemetry::global::shutdown_tracer_provider)
+				.await
+				.ok();
+
+			info!("Stopped exporting traces");
+		}
+
+		let Some(endpoint) = endpoint else {
+			return;
+		};
+
+		let tracer = match opentelemetry_otlp::new_pipeline()
+			.tracing()
+			.with_exporter(
+				opentelemetry_otlp::new_exporter()
+					.http()
+					.with_endpoint(endpoint),
+			)
+			.with_trace_config(
+				trace::config()
+					.with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+			)
+			.install_batch(runtime::Tokio)
+		{
+			Ok(tracer) => tracer,
+			Err(e) => {
+				error!("Failed to set up the OTLP exporter for '{endpoint}': {e:#?}");
+				return;
+			}
+		};
+
+		match handle
+			.modify(|layer| *layer = Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+		{
+			Ok(()) => info!("Exporting traces to '{endpoint}'"),
+			Err(e) => error!("Failed to start exporting traces: {e:#?}"),
+		}
+	}
+}
@@ -1,67 +1,163 @@
 use std::{
+	future::Future,
 	pin::Pin,
 	task::{Context, Poll},
+	time::{Duration, Instant},
 };
 
 use futures::Stream;
 use pin_project_lite::pin_project;
+use tokio::time::{sleep, Sleep};
 
-// We limit the number of polls to prevent starvation of other tasks.
-// This number is chosen arbitrarily but it is set smaller than `FuturesUnordered` or `StreamUnordered`.
-const MAX_POLLS: usize = 15;
+// Batches start this small so the first items are sent right away. It's also smaller than the
+// batches of `FuturesUnordered` or `StreamUnordered`, to prevent starvation of other tasks.
+const DEFAULT_MIN_BATCH_SIZE: usize = 15;
+const DEFAULT_MAX_BATCH_SIZE: usize = 1000;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
 
 pin_project! {
-	#[project = BatchedStreamProj]
-	pub enum BatchedStream<S> where S: Stream {
-		Active {
-			#[pin]
-			stream: S,
-			batch: Vec<S::Item>,
-		},
-		Complete
+	/// Groups the items of a stream into batches.
+	///
+	/// A batch is sent once it's full or once [`Self::with_flush_interval`] went by since its first
+	/// item. The inner stream is only polled when the consumer asks for a batch, so a slow consumer
+	/// slows down the inner stream instead of having its items pile up. When the consumer takes
+	/// longer than the flush interval to ask for the next batch, the batches double in size, up to
+	/// the maximum, so it receives fewer of them, and they shrink back once it keeps up.
+	pub struct BatchedStream<S> where S: Stream {
+		#[pin]
+		stream: S,
+		batch: Vec<S::Item>,
+		batch_size: usize,
+		min_batch_size: usize,
+		max_batch_size: usize,
+		flush_interval: Duration,
+		flush: Option<Pin<Box<Sleep>>>,
+		sent_at: Option<Instant>,
+		done: bool,
 	}
 }
 
 impl<S: Stream> BatchedStream<S> {
 	pub fn new(stream: S) -> Self {
-		Self::Active {
+		Self {
 			stream,
-			batch: Vec::with_capacity(MAX_POLLS),
+			batch: Vec::with_capacity(DEFAULT_MIN_BATCH_SIZE),
+			batch_size: DEFAULT_MIN_BATCH_SIZE,
+			min_batch_size: DEFAULT_MIN_BATCH_SIZE,
+			max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+			flush_interval: DEFAULT_FLUSH_INTERVAL,
+			flush: None,
+			sent_at: None,
+			done: false,
 		}
 	}
+
+	/// Sets the size of the batches sent to a consumer keeping up, and to the slowest consumers.
+	pub fn with_batch_size(mut self, min: usize, max: usize) -> Self {
+		self.min_batch_size = min.max(1);
+		self.max_batch_size = max.max(self.min_batch_size);
+		self.batch_size = self.min_batch_size;
+		self
+	}
+
+	pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+		self.flush_interval = flush_interval;
+		self
+	}
 }
 
 impl<S: Stream> Stream for BatchedStream<S> {
 	type Item = Vec<S::Item>;
 
-	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-		match self.as_mut().project() {
-			BatchedStreamProj::Active { mut stream, batch } => {
-				for _ in 0..MAX_POLLS {
-					match stream.as_mut().poll_next(cx) {
-						Poll::Ready(Some(item)) => batch.push(item),
-						Poll::Ready(None) => {
-							if batch.is_empty() {
-								return Poll::Ready(None);
-							} else {
-								let batch = std::mem::take(batch);
-								self.as_mut().set(BatchedStream::Complete);
-								return Poll::Ready(Some(batch));
-							}
-						}
-						Poll::Pending => break,
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let mut this = self.project();
+
+		if *this.done {
+			return Poll::Ready(None);
+		}
+
+		if let Some(sent_at) = this.sent_at.take() {
+			*this.batch_size = if sent_at.elapsed() > *this.flush_interval {
+				(*this.batch_size * 2).min(*this.max_batch_size)
+			} else {
+				(*this.batch_size / 2).max(*this.min_batch_size)
+			};
+		}
+
+		while this.batch.len() < *this.batch_size {
+			match this.stream.as_mut().poll_next(cx) {
+				Poll::Ready(Some(item)) => {
+					if this.batch.is_empty() {
+						*this.flush = Some(Box::pin(sleep(*this.flush_interval)));
 					}
+
+					this.batch.push(item);
 				}
+				Poll::Ready(None) => {
+					*this.done = true;
 
-				if batch.is_empty() {
-					cx.waker().wake_by_ref();
-					Poll::Pending
-				} else {
-					let batch = std::mem::take(batch);
-					Poll::Ready(Some(batch))
+					return if this.batch.is_empty() {
+						Poll::Ready(None)
+					} else {
+						Poll::Ready(Some(std::mem::take(this.batch)))
+					};
 				}
+				// The inner stream wakes us up once it has more items
+				Poll::Pending => break,
 			}
-			BatchedStreamProj::Complete => Poll::Ready(None),
 		}
+
+		let flush_due = this
+			.flush
+			.as_mut()
+			.is_some_and(|flush| flush.as_mut().poll(cx).is_ready());
+
+		if this.batch.len() >= *this.batch_size || flush_due {
+			*this.flush = None;
+			*this.sent_at = Some(Instant::now());
+
+			let capacity = *this.batch_size;
+			return Poll::Ready(Some(std::mem::replace(
+				this.batch,
+				Vec::with_capacity(capacity),
+			)));
+		}
+
+		Poll::Pending
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::{stream, StreamExt};
+
+	#[tokio::test]
+	async fn sends_full_batches_and_the_rest() {
+		let batches = BatchedStream::new(stream::iter(0..10))
+			.with_batch_size(4, 4)
+			.collect::<Vec<_>>()
+			.await;
+
+		assert_eq!(batches, [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+	}
+
+	#[tokio::test]
+	async fn grows_batches_for_slow_consumers() {
+		let mut batches = BatchedStream::new(stream::iter(0..100))
+			.with_batch_size(2, 8)
+			.with_flush_interval(Duration::from_millis(20));
+
+		assert_eq!(batches.next().await.unwrap().len(), 2);
+		tokio::time::sleep(Duration::from_millis(40)).await;
+		assert_eq!(batches.next().await.unwrap().len(), 4);
+		tokio::time::sleep(Duration::from_millis(40)).await;
+		assert_eq!(batches.next().await.unwrap().len(), 8);
+		tokio::time::sleep(Duration::from_millis(40)).await;
+		assert_eq!(batches.next().await.unwrap().len(), 8);
+
+		// Shrinks back once the consumer keeps up
+		assert_eq!(batches.next().await.unwrap().len(), 4);
 	}
 }
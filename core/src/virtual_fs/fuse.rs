@@ -0,0 +1,304 @@
+//! Read only FUSE mount of the virtual tree, for Linux and macOS.
+//!
+//! The kernel talks to us through inode numbers, so every path it looked up gets one for as long
+//! as the mount lives. Paths are resolved again on every request, which keeps the mount in sync
+//! with the libraries at the cost of some database queries.
+
+use crate::Node;
+
+use std::{
+	collections::HashMap,
+	ffi::OsStr,
+	fs::File,
+	io,
+	os::unix::fs::FileExt,
+	path::Path,
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use fuser::{
+	BackgroundSession, FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData,
+	ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, Request, FUSE_ROOT_ID,
+};
+use tokio::runtime::Handle;
+use tracing::{error, info};
+
+use super::{children, resolve, Props, Resource, VirtualFsError};
+
+/// How long the kernel caches entries and attributes
+const TTL: Duration = Duration::from_secs(1);
+const BLOCK_SIZE: u32 = 512;
+
+/// Mounts the virtual tree of the node at the given directory, until the session is dropped.
+pub fn mount(node: Arc<Node>, mountpoint: impl AsRef<Path>) -> io::Result<BackgroundSession> {
+	let mountpoint = mountpoint.as_ref();
+
+	let session = fuser::spawn_mount2(
+		LibraryFs {
+			node,
+			runtime: Handle::current(),
+			inodes: vec![vec![]],
+			inodes_by_path: HashMap::from([(vec![], FUSE_ROOT_ID)]),
+			open_files: HashMap::new(),
+			next_fh: 0,
+			// SAFETY: These calls can't fail
+			uid: unsafe { libc::getuid() },
+			gid: unsafe { libc::getgid() },
+		},
+		mountpoint,
+		&[
+			MountOption::RO,
+			MountOption::FSName("spacedrive".to_string()),
+			MountOption::NoExec,
+		],
+	)?;
+
+	info!("Mounted the libraries at '{}'", mountpoint.display());
+
+	Ok(session)
+}
+
+struct LibraryFs {
+	node: Arc<Node>,
+	runtime: Handle,
+	/// Path segments of every inode, the inode being the index plus one
+	inodes: Vec<Vec<String>>,
+	inodes_by_path: HashMap<Vec<String>, u64>,
+	open_files: HashMap<u64, File>,
+	next_fh: u64,
+	uid: u32,
+	gid: u32,
+}
+
+impl LibraryFs {
+	fn path(&self, ino: u64) -> Option<&[String]> {
+		self.inodes
+			.get(usize::try_from(ino).ok()?.checked_sub(1)?)
+			.map(Vec::as_slice)
+	}
+
+	fn inode(&mut self, path: Vec<String>) -> u64 {
+		if let Some(ino) = self.inodes_by_path.get(&path) {
+			return *ino;
+		}
+
+		self.inodes.push(path.clone());
+		let ino = self.inodes.len() as u64;
+		self.inodes_by_path.insert(path, ino);
+
+		ino
+	}
+
+	fn resolve(&self, ino: u64) -> Result<Resource, i32> {
+		let path = self.path(ino).ok_or(libc::ENOENT)?;
+
+		self.runtime
+			.block_on(resolve(&self.node, path))
+			.map_err(errno)
+	}
+
+	fn attr(&self, ino: u64, props: &Props) -> FileAttr {
+		let modified = props.modified.map_or(UNIX_EPOCH, SystemTime::from);
+
+		FileAttr {
+			ino,
+			size: props.size,
+			blocks: props.size.div_ceil(BLOCK_SIZE as u64),
+			atime: modified,
+			mtime: modified,
+			ctime: modified,
+			crtime: props.created.map_or(modified, SystemTime::from),
+			kind: if props.is_dir {
+				FileType::Directory
+			} else {
+				FileType::RegularFile
+			},
+			perm: if props.is_dir { 0o555 } else { 0o444 },
+			nlink: 1,
+			uid: self.uid,
+			gid: self.gid,
+			rdev: 0,
+			blksize: BLOCK_SIZE,
+			flags: 0,
+		}
+	}
+}
+
+fn errno(e: VirtualFsError) -> i32 {
+	match e {
+		VirtualFsError::NotFound => libc::ENOENT,
+		VirtualFsError::InvalidSegment(_) => libc::EINVAL,
+		VirtualFsError::OutsideOfRoot => libc::EACCES,
+		e => {
+			error!("Virtual filesystem request failed: {e:#?}");
+			libc::EIO
+		}
+	}
+}
+
+impl Filesystem for LibraryFs {
+	fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+		let (Some(parent), Some(name)) = (self.path(parent), name.to_str()) else {
+			return reply.error(libc::ENOENT);
+		};
+
+		let mut path = parent.to_vec();
+		path.push(name.to_string());
+
+		let props = match self.runtime.block_on(async {
+			resolve(&self.node, &path)
+				.await?
+				.props()
+				.await
+				.ok_or(VirtualFsError::NotFound)
+		}) {
+			Ok(props) => props,
+			Err(e) => return reply.error(errno(e)),
+		};
+
+		let ino = self.inode(path);
+		reply.entry(&TTL, &self.attr(ino, &props), 0);
+	}
+
+	fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+		let props = self
+			.resolve(ino)
+			.and_then(|resource| self.runtime.block_on(resource.props()).ok_or(libc::ENOENT));
+
+		match props {
+			Ok(props) => reply.attr(&TTL, &self.attr(ino, &props)),
+			Err(e) => reply.error(e),
+		}
+	}
+
+	fn open(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
+		let file = match self.resolve(ino) {
+			Ok(Resource::Disk(path)) => File::open(path),
+			Ok(Resource::Folder(_)) => return reply.error(libc::EISDIR),
+			Err(e) => return reply.error(e),
+		};
+
+		match file {
+			Ok(file) => {
+				self.next_fh += 1;
+				self.open_files.insert(self.next_fh, file);
+				reply.opened(self.next_fh, 0);
+			}
+			Err(e) => reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+		}
+	}
+
+	fn read(
+		&mut self,
+		_req: &Request<'_>,
+		_ino: u64,
+		fh: u64,
+		offset: i64,
+		size: u32,
+		_flags: i32,
+		_lock_owner: Option<u64>,
+		reply: ReplyData,
+	) {
+		let (Some(file), Ok(offset)) = (self.open_files.get(&fh), u64::try_from(offset)) else {
+			return reply.error(libc::EBADF);
+		};
+
+		let mut buf = vec![0; size as usize];
+		let mut read = 0;
+
+		// `read_at` may return less than asked for before the end of the file
+		while read < buf.len() {
+			match file.read_at(&mut buf[read..], offset + read as u64) {
+				Ok(0) => break,
+				Ok(n) => read += n,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+			}
+		}
+
+		reply.data(&buf[..read]);
+	}
+
+	fn release(
+		&mut self,
+		_req: &Request<'_>,
+		_ino: u64,
+		fh: u64,
+		_flags: i32,
+		_lock_owner: Option<u64>,
+		_flush: bool,
+		reply: ReplyEmpty,
+	) {
+		self.open_files.remove(&fh);
+		reply.ok();
+	}
+
+	fn readdir(
+		&mut self,
+		_req: &Request<'_>,
+		ino: u64,
+		_fh: u64,
+		offset: i64,
+		mut reply: ReplyDirectory,
+	) {
+		let Some(path) = self.path(ino).map(<[String]>::to_vec) else {
+			return reply.error(libc::ENOENT);
+		};
+
+		let entries = match self.runtime.block_on(async {
+			let resource = resolve(&self.node, &path).await?;
+
+			let mut entries = vec![];
+			for (name, child) in children(&self.node, &resource).await? {
+				if let Some(props) = child.props().await {
+					entries.push((name, props.is_dir));
+				}
+			}
+
+			Ok::<_, VirtualFsError>(entries)
+		}) {
+			Ok(entries) => entries,
+			Err(e) => return reply.error(errno(e)),
+		};
+
+		let parent = match path.split_last() {
+			Some((_, parent)) => self.inode(parent.to_vec()),
+			None => FUSE_ROOT_ID,
+		};
+
+		let entries = [
+			(ino, FileType::Directory, ".".to_string()),
+			(parent, FileType::Directory, "..".to_string()),
+		]
+		.into_iter()
+		.chain(entries.into_iter().map(|(name, is_dir)| {
+			let mut child_path = path.clone();
+			child_path.push(name.clone());
+
+			(
+				self.inode(child_path),
+				if is_dir {
+					FileType::Directory
+				} else {
+					FileType::RegularFile
+				},
+				name,
+			)
+		}))
+		.collect::<Vec<_>>();
+
+		for (i, (ino, kind, name)) in entries
+			.into_iter()
+			.enumerate()
+			.skip(usize::try_from(offset).unwrap_or_default())
+		{
+			// The offset is the one of the next entry, to resume from it if the buffer is full
+			if reply.add(ino, (i + 1) as i64, kind, name) {
+				break;
+			}
+		}
+
+		reply.ok();
+	}
+}
@@ -0,0 +1,392 @@
+//! Virtual tree of folders over the libraries of the node, browsed through WebDAV and FUSE.
+//!
+//! Every library is a folder holding:
+//!  - `Locations`: the locations of this node, served straight from the disk
+//!  - `Tags`: a folder per tag, with the files of the objects it's assigned to
+//!  - `Saved Searches`: a folder per saved search, with the files it matches
+
+use crate::{
+	api::search::SearchFilterArgs,
+	library::{Library, LibraryManagerError},
+	Node,
+};
+
+use sd_prisma::prisma::{file_path, location, object, saved_search, tag, tag_on_object};
+
+use std::{
+	collections::HashMap,
+	fs::Metadata,
+	io,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tokio::fs;
+
+#[cfg(all(feature = "fuse", unix))]
+pub mod fuse;
+
+const LOCATIONS_DIR: &str = "Locations";
+const TAGS_DIR: &str = "Tags";
+const SAVED_SEARCHES_DIR: &str = "Saved Searches";
+
+/// Files listed at most in a tag or saved search folder
+const MAX_VIRTUAL_FILES: i64 = 1000;
+
+#[derive(Error, Debug)]
+pub(crate) enum VirtualFsError {
+	#[error("no such file or directory")]
+	NotFound,
+	#[error("invalid path segment: '{0}'")]
+	InvalidSegment(String),
+	#[error("path leads out of the location it was reached from")]
+	OutsideOfRoot,
+	#[error("invalid filters of saved search: {0}")]
+	SavedSearchFilters(String),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	LibraryManager(#[from] LibraryManagerError),
+	#[error(transparent)]
+	Io(#[from] io::Error),
+}
+
+/// A folder that doesn't exist on disk.
+pub(crate) enum Folder {
+	Root,
+	Library(Arc<Library>),
+	Locations(Arc<Library>),
+	Tags(Arc<Library>),
+	Tag(Arc<Library>, tag::id::Type),
+	SavedSearches(Arc<Library>),
+	SavedSearch(Arc<Library>, saved_search::id::Type),
+}
+
+pub(crate) enum Resource {
+	Folder(Folder),
+	/// A file or directory on disk
+	Disk(PathBuf),
+}
+
+pub(crate) struct Props {
+	pub is_dir: bool,
+	pub size: u64,
+	pub created: Option<DateTime<Utc>>,
+	pub modified: Option<DateTime<Utc>>,
+}
+
+impl From<&Metadata> for Props {
+	fn from(metadata: &Metadata) -> Self {
+		Self {
+			is_dir: metadata.is_dir(),
+			size: if metadata.is_dir() { 0 } else { metadata.len() },
+			created: metadata.created().ok().map(Into::into),
+			modified: metadata.modified().ok().map(Into::into),
+		}
+	}
+}
+
+impl Resource {
+	/// The properties of the resource, if it still exists.
+	pub async fn props(&self) -> Option<Props> {
+		match self {
+			Self::Folder(_) => Some(Props {
+				is_dir: true,
+				size: 0,
+				created: None,
+				modified: None,
+			}),
+			Self::Disk(path) => fs::metadata(path)
+				.await
+				.ok()
+				.map(|metadata| Props::from(&metadata)),
+		}
+	}
+}
+
+/// Walks the tree from the root, down to the resource at the given path.
+///
+/// Paths on disk are returned canonicalized, and can't lead out of the location or file they were
+/// reached from, even through symlinks.
+pub(crate) async fn resolve(node: &Node, segments: &[String]) -> Result<Resource, VirtualFsError> {
+	let mut resource = Resource::Folder(Folder::Root);
+	// Where the walk entered the disk, like the root of a location
+	let mut disk_root = None;
+
+	for segment in segments {
+		resource = match resource {
+			Resource::Disk(path) => {
+				// Prevent directory traversal attacks (Eg. requesting `../../../etc/passwd`)
+				if segment == ".." || segment == "." || segment.contains(['/', '\\']) {
+					return Err(VirtualFsError::InvalidSegment(segment.clone()));
+				}
+
+				let child = path.join(segment);
+				disk_root.get_or_insert(path);
+
+				Resource::Disk(child)
+			}
+			folder => children(node, &folder)
+				.await?
+				.into_iter()
+				.find_map(|(name, child)| (&name == segment).then_some(child))
+				.ok_or(VirtualFsError::NotFound)?,
+		};
+	}
+
+	match (resource, disk_root) {
+		(Resource::Disk(path), Some(root)) => confine(&path, &root).await.map(Resource::Disk),
+		(resource, _) => Ok(resource),
+	}
+}
+
+/// The canonical path of `path`, which must be inside of `root` once their symlinks are followed.
+async fn confine(path: &Path, root: &Path) -> Result<PathBuf, VirtualFsError> {
+	async fn canonicalize(path: &Path) -> Result<PathBuf, VirtualFsError> {
+		fs::canonicalize(path).await.map_err(|e| match e.kind() {
+			io::ErrorKind::NotFound => VirtualFsError::NotFound,
+			_ => e.into(),
+		})
+	}
+
+	let (path, root) = (canonicalize(path).await?, canonicalize(root).await?);
+
+	if path.starts_with(&root) {
+		Ok(path)
+	} else {
+		Err(VirtualFsError::OutsideOfRoot)
+	}
+}
+
+/// The named children of a folder, or of a directory on disk.
+pub(crate) async fn children(
+	node: &Node,
+	resource: &Resource,
+) -> Result<Vec<(String, Resource)>, VirtualFsError> {
+	let folder = match resource {
+		Resource::Folder(folder) => folder,
+		Resource::Disk(path) => return read_dir(path).await,
+	};
+
+	let mut children = match folder {
+		Folder::Root => {
+			let mut children = vec![];
+			for library in node.libraries.get_all().await {
+				children.push((
+					library.config().await.name.to_string(),
+					Resource::Folder(Folder::Library(library)),
+				));
+			}
+			children
+		}
+		Folder::Library(library) => vec![
+			(
+				LOCATIONS_DIR.to_string(),
+				Resource::Folder(Folder::Locations(Arc::clone(library))),
+			),
+			(
+				TAGS_DIR.to_string(),
+				Resource::Folder(Folder::Tags(Arc::clone(library))),
+			),
+			(
+				SAVED_SEARCHES_DIR.to_string(),
+				Resource::Folder(Folder::SavedSearches(Arc::clone(library))),
+			),
+		],
+		Folder::Locations(library) => library
+			.db
+			.location()
+			.find_many(vec![location::instance_id::equals(Some(
+				library.config().await.instance_id,
+			))])
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|location| {
+				Some((
+					location.name?,
+					Resource::Disk(PathBuf::from(location.path?)),
+				))
+			})
+			.collect(),
+		Folder::Tags(library) => library
+			.db
+			.tag()
+			.find_many(vec![])
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|tag| {
+				Some((
+					tag.name?,
+					Resource::Folder(Folder::Tag(Arc::clone(library), tag.id)),
+				))
+			})
+			.collect(),
+		Folder::Tag(library, tag_id) => {
+			files(
+				library,
+				vec![file_path::object::is(vec![object::tags::some(vec![
+					tag_on_object::tag_id::equals(*tag_id),
+				])])],
+			)
+			.await?
+		}
+		Folder::SavedSearches(library) => library
+			.db
+			.saved_search()
+			.find_many(vec![])
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|search| {
+				Some((
+					search.name?,
+					Resource::Folder(Folder::SavedSearch(Arc::clone(library), search.id)),
+				))
+			})
+			.collect(),
+		Folder::SavedSearch(library, search_id) => {
+			let search = library
+				.db
+				.saved_search()
+				.find_unique(saved_search::id::equals(*search_id))
+				.exec()
+				.await?
+				.ok_or(VirtualFsError::NotFound)?;
+
+			let mut params = search
+				.search
+				.map(|search| vec![file_path::name::contains(search)])
+				.unwrap_or_default();
+
+			if let Some(filters) = search.filters {
+				for filter in serde_json::from_str::<Vec<SearchFilterArgs>>(&filters)
+					.map_err(|e| VirtualFsError::SavedSearchFilters(e.to_string()))?
+				{
+					params.extend(
+						filter
+							.into_file_path_params(&library.db)
+							.await
+							.map_err(|e| VirtualFsError::SavedSearchFilters(e.to_string()))?,
+					);
+				}
+			}
+
+			files(library, params).await?
+		}
+	};
+
+	dedup_names(&mut children);
+
+	Ok(children)
+}
+
+async fn read_dir(path: &Path) -> Result<Vec<(String, Resource)>, VirtualFsError> {
+	let mut read_dir = fs::read_dir(path).await.map_err(|e| match e.kind() {
+		io::ErrorKind::NotFound => VirtualFsError::NotFound,
+		_ => e.into(),
+	})?;
+
+	let mut children = vec![];
+	while let Some(entry) = read_dir.next_entry().await? {
+		if let Ok(name) = entry.file_name().into_string() {
+			children.push((name, Resource::Disk(entry.path())));
+		}
+	}
+
+	Ok(children)
+}
+
+/// The local files matching the filters, as virtual folders don't hold directories.
+async fn files(
+	library: &Library,
+	mut params: Vec<file_path::WhereParam>,
+) -> Result<Vec<(String, Resource)>, VirtualFsError> {
+	params.push(file_path::is_dir::equals(Some(false)));
+
+	let file_paths = library
+		.db
+		.file_path()
+		.find_many(params)
+		.take(MAX_VIRTUAL_FILES)
+		.exec()
+		.await?;
+
+	let mut full_paths = library
+		.get_file_paths(file_paths.iter().map(|file_path| file_path.id).collect())
+		.await?;
+
+	Ok(file_paths
+		.into_iter()
+		.filter_map(|file_path| {
+			let name = match (file_path.name, file_path.extension) {
+				(Some(name), Some(extension)) if !extension.is_empty() => {
+					format!("{name}.{extension}")
+				}
+				(Some(name), _) => name,
+				(None, _) => return None,
+			};
+
+			Some((
+				name,
+				Resource::Disk(full_paths.remove(&file_path.id).flatten()?),
+			))
+		})
+		.collect())
+}
+
+/// Makes the names of the children usable as path segments, numbering the ones appearing more
+/// than once like file managers do: `photo.jpg`, `photo (2).jpg`, ...
+fn dedup_names(children: &mut [(String, Resource)]) {
+	let mut seen = HashMap::<String, usize>::new();
+
+	for (name, resource) in children {
+		*name = name.replace(['/', '\\'], "-");
+
+		let count = seen.entry(name.clone()).or_default();
+		*count += 1;
+
+		if *count > 1 {
+			*name = match (name.rsplit_once('.'), resource) {
+				(Some((stem, extension)), Resource::Disk(_)) if !stem.is_empty() => {
+					format!("{stem} ({count}).{extension}")
+				}
+				_ => format!("{name} ({count})"),
+			};
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dedups_names() {
+		let mut children = vec![
+			(
+				"photo.jpg".to_string(),
+				Resource::Disk("/a/photo.jpg".into()),
+			),
+			(
+				"photo.jpg".to_string(),
+				Resource::Disk("/b/photo.jpg".into()),
+			),
+			("a/b".to_string(), Resource::Folder(Folder::Root)),
+			("a-b".to_string(), Resource::Folder(Folder::Root)),
+		];
+
+		dedup_names(&mut children);
+
+		assert_eq!(
+			children
+				.iter()
+				.map(|(name, _)| name.as_str())
+				.collect::<Vec<_>>(),
+			vec!["photo.jpg", "photo (2).jpg", "a-b", "a-b (2)"]
+		);
+	}
+}
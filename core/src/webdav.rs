@@ -0,0 +1,272 @@
+//! Read only WebDAV server over the [virtual tree](crate::virtual_fs) of the libraries, so they
+//! can be browsed from any OS file manager without the app.
+//!
+//! Only the read methods of WebDAV class 1 are implemented, so clients mount it as read only.
+
+use crate::{
+	custom_uri::{infer_the_mime_type, serve_file},
+	util::InfallibleResponse,
+	virtual_fs::{children, resolve, Props, Resource, VirtualFsError},
+	Node,
+};
+
+use std::{fmt::Write, sync::Arc};
+
+use axum::{
+	body::{self, Body, BoxBody, Full},
+	extract::{OriginalUri, State},
+	http::{header, HeaderValue, Request, Response, StatusCode},
+	routing::any,
+	Router,
+};
+use chrono::SecondsFormat;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use tokio::fs::{self, File};
+use tracing::error;
+
+const ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PROPFIND";
+
+/// Characters escaped in the name of a resource, when used as a segment of an href
+const SEGMENT: &AsciiSet = &CONTROLS
+	.add(b' ')
+	.add(b'"')
+	.add(b'#')
+	.add(b'%')
+	.add(b'/')
+	.add(b'<')
+	.add(b'>')
+	.add(b'?')
+	.add(b'[')
+	.add(b'\\')
+	.add(b']')
+	.add(b'^')
+	.add(b'`')
+	.add(b'{')
+	.add(b'|')
+	.add(b'}');
+
+/// Builds the WebDAV server, which doesn't do any authentication by itself.
+pub fn router(node: Arc<Node>) -> Router<()> {
+	Router::new()
+		.route("/", any(handle))
+		.route("/*path", any(handle))
+		.with_state(node)
+}
+
+async fn handle(
+	State(node): State<Arc<Node>>,
+	OriginalUri(original_uri): OriginalUri,
+	request: Request<Body>,
+) -> Response<BoxBody> {
+	let segments = match request
+		.uri()
+		.path()
+		.split('/')
+		.filter(|segment| !segment.is_empty())
+		.map(|segment| {
+			percent_decode_str(segment)
+				.decode_utf8()
+				.map(|segment| segment.into_owned())
+		})
+		.collect::<Result<Vec<_>, _>>()
+	{
+		Ok(segments) => segments,
+		Err(_) => return status_response(StatusCode::BAD_REQUEST),
+	};
+
+	let method = request.method().clone();
+
+	let result = match method.as_str() {
+		"OPTIONS" => Ok(InfallibleResponse::builder()
+			.header("DAV", HeaderValue::from_static("1"))
+			.header(header::ALLOW, HeaderValue::from_static(ALLOWED_METHODS))
+			.body(body::boxed(Full::from("")))),
+		"GET" | "HEAD" => get(&node, &segments, request).await,
+		"PROPFIND" => {
+			// An infinite depth is answered as a depth of 1, as listing a whole library is too much
+			let with_children = !matches!(
+				request.headers().get("Depth"),
+				Some(depth) if depth.as_bytes() == b"0"
+			);
+
+			propfind(&node, &segments, original_uri.path(), with_children).await
+		}
+		_ => Ok(InfallibleResponse::builder()
+			.status(StatusCode::METHOD_NOT_ALLOWED)
+			.header(header::ALLOW, HeaderValue::from_static(ALLOWED_METHODS))
+			.body(body::boxed(Full::from("")))),
+	};
+
+	result.unwrap_or_else(status_response)
+}
+
+async fn get(
+	node: &Node,
+	segments: &[String],
+	request: Request<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+	let Resource::Disk(path) = resolve(node, segments).await.map_err(error_status)? else {
+		return Err(StatusCode::METHOD_NOT_ALLOWED);
+	};
+
+	let metadata = fs::metadata(&path)
+		.await
+		.map_err(|_| StatusCode::NOT_FOUND)?;
+	if metadata.is_dir() {
+		return Err(StatusCode::METHOD_NOT_ALLOWED);
+	}
+
+	let mut file = File::open(&path)
+		.await
+		.map_err(|e| error_status(e.into()))?;
+
+	let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+		Some(ext) => infer_the_mime_type(ext, &mut file, &metadata)
+			.await
+			.unwrap_or_else(|_| "application/octet-stream".to_string()),
+		None => "application/octet-stream".to_string(),
+	};
+
+	let resp = InfallibleResponse::builder().header(
+		header::CONTENT_TYPE,
+		HeaderValue::from_str(&content_type).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+	);
+
+	Ok(serve_file(file, Ok(metadata), request.into_parts().0, resp)
+		.await
+		.unwrap_or_else(|response| response))
+}
+
+async fn propfind(
+	node: &Node,
+	segments: &[String],
+	href: &str,
+	with_children: bool,
+) -> Result<Response<BoxBody>, StatusCode> {
+	let resource = resolve(node, segments).await.map_err(error_status)?;
+	let props = resource.props().await.ok_or(StatusCode::NOT_FOUND)?;
+
+	let mut body =
+		String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+	write_response(
+		&mut body,
+		href,
+		segments.last().map_or("", String::as_str),
+		&props,
+	);
+
+	if with_children && props.is_dir {
+		let base = href.trim_end_matches('/');
+
+		for (name, child) in children(node, &resource).await.map_err(error_status)? {
+			let Some(props) = child.props().await else {
+				continue;
+			};
+
+			let href = format!(
+				"{base}/{}{}",
+				utf8_percent_encode(&name, SEGMENT),
+				if props.is_dir { "/" } else { "" }
+			);
+
+			write_response(&mut body, &href, &name, &props);
+		}
+	}
+
+	body.push_str("</D:multistatus>");
+
+	Ok(InfallibleResponse::builder()
+		.status(StatusCode::MULTI_STATUS)
+		.header(
+			header::CONTENT_TYPE,
+			HeaderValue::from_static("application/xml; charset=utf-8"),
+		)
+		.body(body::boxed(Full::from(body))))
+}
+
+fn write_response(body: &mut String, href: &str, name: &str, props: &Props) {
+	let _ = write!(
+		body,
+		"<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname>",
+		xml_escape(href),
+		xml_escape(name)
+	);
+
+	if props.is_dir {
+		body.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+	} else {
+		let _ = write!(
+			body,
+			"<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>",
+			props.size
+		);
+	}
+
+	if let Some(created) = props.created {
+		let _ = write!(
+			body,
+			"<D:creationdate>{}</D:creationdate>",
+			created.to_rfc3339_opts(SecondsFormat::Secs, true)
+		);
+	}
+
+	if let Some(modified) = props.modified {
+		let _ = write!(
+			body,
+			"<D:getlastmodified>{}</D:getlastmodified>",
+			modified.format("%a, %d %b %Y %H:%M:%S GMT")
+		);
+
+		if !props.is_dir {
+			// Same ETag as the one sent when serving the file
+			let _ = write!(
+				body,
+				"<D:getetag>\"{}\"</D:getetag>",
+				modified.timestamp_millis()
+			);
+		}
+	}
+
+	body.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+}
+
+fn xml_escape(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len());
+
+	for c in value.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&apos;"),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
+
+fn error_status(e: VirtualFsError) -> StatusCode {
+	match e {
+		VirtualFsError::NotFound => StatusCode::NOT_FOUND,
+		VirtualFsError::InvalidSegment(_) => StatusCode::BAD_REQUEST,
+		VirtualFsError::OutsideOfRoot => StatusCode::FORBIDDEN,
+		e => {
+			error!("WebDAV request failed: {e:#?}");
+			StatusCode::INTERNAL_SERVER_ERROR
+		}
+	}
+}
+
+fn status_response(status: StatusCode) -> Response<BoxBody> {
+	let response = InfallibleResponse::builder().status(status);
+
+	if status == StatusCode::METHOD_NOT_ALLOWED {
+		response
+			.header(header::ALLOW, HeaderValue::from_static(ALLOWED_METHODS))
+			.body(body::boxed(Full::from("")))
+	} else {
+		response.body(body::boxed(Full::from("")))
+	}
+}
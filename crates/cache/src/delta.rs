@@ -0,0 +1,274 @@
+use std::{
+	collections::{HashMap, HashSet},
+	marker::PhantomData,
+};
+
+use serde::Serialize;
+use specta::Type;
+
+use crate::{CacheNode, Model, Reference};
+
+/// The changes to the results of a query since they were last sent, so the client can patch the
+/// results it has instead of fetching them again.
+///
+/// The client applies it by removing the `removed` items, then inserting the `added` items at
+/// their `index`, in the order they come.
+#[derive(Serialize, Type, Debug)]
+pub struct NormalisedDelta<T: Model + Type> {
+	/// Set on the first delta, which holds all of the results, so the client drops the ones it had
+	pub reset: bool,
+	pub removed: Vec<Reference<T>>,
+	/// Items new to the results, or moved within them
+	pub added: Vec<DeltaItem<T>>,
+	/// Nodes of the new items and of the items whose data changed
+	pub nodes: Vec<CacheNode>,
+}
+
+#[derive(Serialize, Type, Debug)]
+pub struct DeltaItem<T: Model + Type> {
+	pub index: u32,
+	pub item: Reference<T>,
+}
+
+impl<T: Model + Type> NormalisedDelta<T> {
+	/// Only the first delta is sent when empty, so the client knows there are no results.
+	pub fn is_empty(&self) -> bool {
+		!self.reset && self.removed.is_empty() && self.added.is_empty() && self.nodes.is_empty()
+	}
+}
+
+/// Holds the results of a query last sent to a client, to compute the [`NormalisedDelta`] of its
+/// next results.
+#[derive(Debug)]
+pub struct NormalisedDiff<T> {
+	ids: Vec<String>,
+	nodes: HashMap<String, CacheNode>,
+	sent: bool,
+	ty: PhantomData<T>,
+}
+
+impl<T> Default for NormalisedDiff<T> {
+	fn default() -> Self {
+		Self {
+			ids: Vec::new(),
+			nodes: HashMap::new(),
+			sent: false,
+			ty: PhantomData,
+		}
+	}
+}
+
+impl<T: Model + Serialize + Type> NormalisedDiff<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn diff(&mut self, items: Vec<T>, id_fn: impl Fn(&T) -> String) -> NormalisedDelta<T> {
+		self.diff_refs(&items, id_fn)
+	}
+
+	/// Like [`NormalisedDiff::diff`], for results kept by the caller to update them in place.
+	pub fn diff_refs<'a>(
+		&mut self,
+		items: impl IntoIterator<Item = &'a T>,
+		id_fn: impl Fn(&T) -> String,
+	) -> NormalisedDelta<T>
+	where
+		T: 'a,
+	{
+		let items = items.into_iter();
+		let mut ids = Vec::with_capacity(items.size_hint().0);
+		let mut nodes = HashMap::with_capacity(items.size_hint().0);
+
+		for item in items {
+			let id = id_fn(item);
+			// A query shouldn't return the same item twice, but if it does only the first one counts
+			if nodes.contains_key(&id) {
+				continue;
+			}
+
+			nodes.insert(id.clone(), CacheNode::from_ref(id.clone(), item));
+			ids.push(id);
+		}
+
+		let previous_positions = self
+			.ids
+			.iter()
+			.enumerate()
+			.map(|(position, id)| (id.as_str(), position))
+			.collect::<HashMap<_, _>>();
+
+		// The kept items staying in place are the most of them which are still in the same order,
+		// the others are moved by removing and adding them again.
+		let kept = ids
+			.iter()
+			.enumerate()
+			.filter_map(|(index, id)| {
+				previous_positions
+					.get(id.as_str())
+					.map(|position| (index, *position))
+			})
+			.collect::<Vec<_>>();
+
+		let in_place = longest_increasing_subsequence(
+			&kept
+				.iter()
+				.map(|(_, position)| *position)
+				.collect::<Vec<_>>(),
+		)
+		.into_iter()
+		.map(|i| kept[i].0)
+		.collect::<HashSet<_>>();
+
+		let mut removed = self
+			.ids
+			.iter()
+			.filter(|id| !nodes.contains_key(id.as_str()))
+			.map(|id| Reference::new(id.clone()))
+			.collect::<Vec<_>>();
+
+		let mut added = Vec::new();
+		let mut changed = Vec::new();
+
+		for (index, id) in ids.iter().enumerate() {
+			let previous = self.nodes.get(id);
+
+			if previous.is_some() && !in_place.contains(&index) {
+				removed.push(Reference::new(id.clone()));
+			}
+
+			if previous.is_none() || !in_place.contains(&index) {
+				added.push(DeltaItem {
+					index: index as u32,
+					item: Reference::new(id.clone()),
+				});
+			}
+
+			if previous != nodes.get(id) {
+				changed.push(nodes[id].clone());
+			}
+		}
+
+		self.ids = ids;
+		self.nodes = nodes;
+
+		NormalisedDelta {
+			reset: !std::mem::replace(&mut self.sent, true),
+			removed,
+			added,
+			nodes: changed,
+		}
+	}
+}
+
+/// Returns the indexes of the values of one of the longest strictly increasing subsequences.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+	// The index of the smallest value ending a subsequence of each length
+	let mut tails = Vec::<usize>::new();
+	let mut predecessors = vec![None; values.len()];
+
+	for (i, value) in values.iter().enumerate() {
+		let length = tails.partition_point(|&tail| values[tail] < *value);
+
+		if length > 0 {
+			predecessors[i] = Some(tails[length - 1]);
+		}
+
+		if length == tails.len() {
+			tails.push(i);
+		} else {
+			tails[length] = i;
+		}
+	}
+
+	let mut subsequence = Vec::with_capacity(tails.len());
+	let mut next = tails.last().copied();
+
+	while let Some(i) = next {
+		subsequence.push(i);
+		next = predecessors[i];
+	}
+
+	subsequence.reverse();
+	subsequence
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Serialize, Type, Debug)]
+	struct Item(&'static str, u8);
+
+	impl Model for Item {
+		fn name() -> &'static str {
+			"Item"
+		}
+	}
+
+	fn ids(references: &[Reference<Item>]) -> Vec<&str> {
+		references.iter().map(|r| r.__id.as_str()).collect()
+	}
+
+	// Applies the delta like the clients do
+	fn apply(current: Vec<String>, delta: &NormalisedDelta<Item>) -> Vec<String> {
+		let removed = ids(&delta.removed);
+		let mut next = current
+			.into_iter()
+			.filter(|id| !removed.contains(&id.as_str()))
+			.collect::<Vec<_>>();
+
+		for added in &delta.added {
+			next.insert(added.index as usize, added.item.__id.clone());
+		}
+
+		next
+	}
+
+	#[test]
+	fn diffs_added_removed_moved_and_changed_items() {
+		let mut diff = NormalisedDiff::new();
+		let id = |item: &Item| item.0.to_string();
+
+		let first = diff.diff(vec![Item("a", 0), Item("b", 0), Item("c", 0)], id);
+		assert!(first.reset);
+		assert!(first.removed.is_empty());
+		assert_eq!(first.added.len(), 3);
+		assert_eq!(first.nodes.len(), 3);
+
+		let current = apply(Vec::new(), &first);
+		assert_eq!(current, ["a", "b", "c"]);
+
+		let unchanged = diff.diff(vec![Item("a", 0), Item("b", 0), Item("c", 0)], id);
+		assert!(unchanged.is_empty());
+
+		// "b" is removed, "d" is added, and "c" changes and moves before "a"
+		let delta = diff.diff(vec![Item("d", 0), Item("c", 1), Item("a", 0)], id);
+		assert_eq!(ids(&delta.removed), ["b", "c"]);
+		assert_eq!(
+			delta.nodes,
+			[
+				CacheNode::new("d".into(), Item("d", 0)),
+				CacheNode::new("c".into(), Item("c", 1))
+			]
+		);
+		assert_eq!(apply(current, &delta), ["d", "c", "a"]);
+	}
+
+	#[test]
+	fn sends_empty_first_results() {
+		let mut diff = NormalisedDiff::<Item>::new();
+		let id = |item: &Item| item.0.to_string();
+
+		assert!(!diff.diff(vec![], id).is_empty());
+		assert!(diff.diff(vec![], id).is_empty());
+	}
+
+	#[test]
+	fn finds_longest_increasing_subsequence() {
+		assert_eq!(longest_increasing_subsequence(&[]), Vec::<usize>::new());
+		assert_eq!(longest_increasing_subsequence(&[0, 1, 2]), [0, 1, 2]);
+		assert_eq!(longest_increasing_subsequence(&[2, 0, 1]), [1, 2]);
+		assert_eq!(longest_increasing_subsequence(&[3, 1, 4, 0, 5, 2]).len(), 3);
+	}
+}
@@ -2,11 +2,16 @@ use std::{
 	hash::{Hash, Hasher},
 	marker::PhantomData,
 	sync::Arc,
+	time::Duration,
 };
 
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use specta::{Any, DataType, NamedType, Type, TypeMap};
 
+mod delta;
+
+pub use delta::*;
+
 /// A type that can be used to return a group of `Reference<T>` and `CacheNode`'s
 ///
 /// You don't need to use this, it's just a shortcut to avoid having to write out the full type every time.
@@ -77,6 +82,22 @@ impl<T: Model> Serialize for Reference<T> {
 	}
 }
 
+/// Identifies a `CacheNode` without its data, used to evict it from the cache of the clients.
+#[derive(Serialize, Type, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CacheKey {
+	__type: &'static str,
+	__id: String,
+}
+
+impl CacheKey {
+	pub fn new<T: Model>(key: impl Into<String>) -> Self {
+		Self {
+			__type: T::name(),
+			__id: key.into(),
+		}
+	}
+}
+
 /// A node in the cache.
 /// This holds the data and is identified by it's type and id.
 #[derive(Debug, Clone)]
@@ -84,22 +105,37 @@ pub struct CacheNode(
 	&'static str,
 	serde_json::Value,
 	Result<serde_json::Value, Arc<serde_json::Error>>,
+	Option<Duration>,
 );
 
 impl CacheNode {
 	pub fn new<T: Model + Serialize + Type>(key: String, value: T) -> Self {
+		Self::from_ref(key, &value)
+	}
+
+	/// Like [`CacheNode::new`], for a value which is kept.
+	pub fn from_ref<T: Model + Serialize + Type>(key: String, value: &T) -> Self {
 		Self(
 			T::name(),
 			key.into(),
 			serde_json::to_value(value).map_err(Arc::new),
+			None,
 		)
 	}
+
+	/// Makes the clients drop the node once it's older than `ttl`, refetching the queries still
+	/// using it. Nodes without a TTL are kept until no query references them.
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.3 = Some(ttl);
+		self
+	}
 }
 
 impl PartialEq for CacheNode {
 	fn eq(&self, other: &Self) -> bool {
 		self.0 == other.0
 			&& self.1 == other.1
+			&& self.3 == other.3
 			&& match (&self.2, &other.2) {
 				(Ok(v0), Ok(v1)) => v0 == v1,
 				// Compares the values in the Arcs, not the Arc objects themselves.
@@ -129,6 +165,9 @@ impl Hash for CacheNode {
 struct CacheNodeTy {
 	__type: String,
 	__id: String,
+	/// Seconds the node can be kept for
+	#[specta(optional)]
+	__ttl: Option<u32>,
 	#[specta(rename = "#node")]
 	node: Any,
 }
@@ -137,6 +176,8 @@ struct CacheNodeTy {
 struct NodeSerdeRepr<'a> {
 	__type: &'static str,
 	__id: &'a serde_json::Value,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	__ttl: Option<u32>,
 	#[serde(flatten)]
 	v: &'a serde_json::Value,
 }
@@ -149,6 +190,9 @@ impl Serialize for CacheNode {
 		NodeSerdeRepr {
 			__type: self.0,
 			__id: &self.1,
+			__ttl: self
+				.3
+				.map(|ttl| u32::try_from(ttl.as_secs()).unwrap_or(u32::MAX)),
 			v: self.2.as_ref().map_err(|err| {
 				serde::ser::Error::custom(format!("Failed to serialize node: {}", err))
 			})?,
@@ -40,6 +40,10 @@ pub enum Error {
 	CorruptVideo,
 	#[error("Error while casting an integer to another integer type")]
 	IntCastError(#[from] TryFromIntError),
+	#[error("The video is too short to sample frames for an animated thumbnail")]
+	TooShortForAnimation,
+	#[error("Failed to encode the animated thumbnail")]
+	AnimationEncoding,
 }
 
 /// Enum to represent possible errors from `FFmpeg` library
@@ -1,9 +1,9 @@
 use crate::{film_strip_filter, Error, MovieDecoder, ThumbnailSize, VideoFrame};
 
-use std::{io, ops::Deref, path::Path};
+use std::{io, ops::Deref, path::Path, time::Duration};
 use tokio::{fs, task::spawn_blocking};
 use tracing::error;
-use webp::Encoder;
+use webp::{AnimEncoder, AnimFrame, Encoder, WebPConfig};
 
 /// `Thumbnailer` struct holds data from a `ThumbnailerBuilder`, exposing methods
 /// to generate thumbnails from video files.
@@ -90,6 +90,79 @@ impl Thumbnailer {
 		})
 		.await?
 	}
+
+	/// Processes an video input file and returns an animated webp thumbnail as bytes, failing with
+	/// [`Error::TooShortForAnimation`] for videos where less than 2 different frames could be sampled
+	pub async fn process_to_animated_webp_bytes(
+		&self,
+		video_file_path: impl AsRef<Path>,
+		frame_count: u32,
+		frame_duration: Duration,
+	) -> Result<Vec<u8>, Error> {
+		let video_file_path = video_file_path.as_ref().to_path_buf();
+		let size = self.builder.size;
+		let maintain_aspect_ratio = self.builder.maintain_aspect_ratio;
+		let quality = self.builder.quality;
+
+		spawn_blocking(move || -> Result<Vec<u8>, Error> {
+			// Embedded cover art is a single picture, so the frames are always taken from the video stream
+			let mut decoder = MovieDecoder::new(video_file_path, false)?;
+			// We actually have to decode a frame to get some metadata before we can start decoding for real
+			decoder.decode_video_frame()?;
+
+			// Sampling evenly across the video, skipping its very start and end
+			let duration = decoder.get_video_duration().as_secs();
+			let mut seconds = (1..=u64::from(frame_count))
+				.map(|index| duration * index / (u64::from(frame_count) + 1))
+				.collect::<Vec<_>>();
+			seconds.dedup();
+
+			if seconds.len() < 2 {
+				return Err(Error::TooShortForAnimation);
+			}
+
+			let frames = seconds
+				.into_iter()
+				.map(|second| {
+					decoder.seek(i64::try_from(second)?)?;
+
+					let mut video_frame = VideoFrame::default();
+					decoder.get_scaled_video_frame(
+						Some(size),
+						maintain_aspect_ratio,
+						&mut video_frame,
+					)?;
+
+					Ok(video_frame)
+				})
+				.collect::<Result<Vec<_>, Error>>()?;
+
+			let mut config = WebPConfig::new().map_err(|()| Error::AnimationEncoding)?;
+			config.quality = quality;
+
+			let mut encoder = AnimEncoder::new(frames[0].width, frames[0].height, &config);
+			encoder.set_loop_count(0);
+
+			let frame_duration = i32::try_from(frame_duration.as_millis())?;
+			let mut timestamp = 0;
+			for frame in &frames {
+				encoder.add_frame(AnimFrame::from_rgb(
+					&frame.data,
+					frame.width,
+					frame.height,
+					timestamp,
+				));
+				timestamp += frame_duration;
+			}
+
+			// Same as above, WebPMemory is !Send so we copy it out
+			encoder
+				.try_encode()
+				.map(|webp| webp.deref().to_vec())
+				.map_err(|_| Error::AnimationEncoding)
+		})
+		.await?
+	}
 }
 
 /// `ThumbnailerBuilder` struct holds data to build a `Thumbnailer` struct, exposing many methods
@@ -94,6 +94,12 @@ extension_category_enum! {
 		Nef = [0x49, 0x49, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00, 0x4E, 0x45, 0x46, 0x00],
 		Arw = [0x49, 0x49, 0x2A, 0x00, 0x08],
 		Rw2 = [0x49, 0x49, 0x2A, 0x00, 0x18],
+		Nrw = [0x49, 0x49, 0x2A, 0x00, 0x08],
+		Orf = [0x49, 0x49, 0x52, 0x4F] | [0x49, 0x49, 0x52, 0x53],
+		Raf = [0x46, 0x55, 0x4A, 0x49, 0x46, 0x49, 0x4C, 0x4D],
+		Pef = [0x49, 0x49, 0x2A, 0x00],
+		Srw = [0x49, 0x49, 0x2A, 0x00],
+		Cr3 = [0x66, 0x74, 0x79, 0x70, 0x63, 0x72, 0x78, 0x20] + 4,
 	}
 }
 
@@ -17,6 +17,10 @@ pub const GENERIC_EXTENSIONS: [&str; 17] = [
 ];
 pub const SVG_EXTENSIONS: [&str; 2] = ["svg", "svgz"];
 pub const PDF_EXTENSIONS: [&str; 1] = ["pdf"];
+/// Camera raw formats decoded with `imagepipe`, unless they embed a big enough JPEG preview
+pub const RAW_EXTENSIONS: [&str; 10] = [
+	"arw", "cr2", "dng", "nef", "nrw", "orf", "pef", "raf", "rw2", "srw",
+];
 #[cfg(feature = "heif")]
 pub const HEIF_EXTENSIONS: [&str; 8] = [
 	"hif", "heif", "heifs", "heic", "heics", "avif", "avci", "avcs",
@@ -32,6 +36,10 @@ pub const HEIF_EXTENSIONS: [&str; 8] = [
 /// It is 512x512, but if the SVG has a non-1:1 aspect ratio we need to account for that.
 pub const SVG_TARGET_PX: f32 = 262_144_f32;
 
+/// The smallest size, of the longest side, of the JPEG previews embedded in raw images that we use
+/// instead of demosaicing the sensor data.
+pub const RAW_MINIMUM_PREVIEW_SIZE: u32 = 1024;
+
 /// The size that PDF pages are rendered at.
 ///
 /// This is 96DPI at standard A4 printer paper size - the target aspect
@@ -33,8 +33,8 @@ pub enum Error {
 	Pixbuf,
 	#[error("error while loading the image (via the `image` crate): {0}")]
 	Image(#[from] image::ImageError),
-	// #[error("error while converting from raw")] // not enough rust support for it to be feasible
-	// RawConversion,
+	#[error("error while decoding the raw image: {0}")]
+	RawDecoding(String),
 	#[error("error while parsing integers")]
 	TryFromInt(#[from] TryFromIntError),
 }
@@ -29,6 +29,7 @@ mod handler;
 #[cfg(feature = "heif")]
 mod heif;
 mod pdf;
+mod raw;
 mod svg;
 
 use consts::MAXIMUM_FILE_SIZE;
@@ -38,6 +39,7 @@ pub use consts::{all_compatible_extensions, ConvertibleExtension};
 pub use error::{Error, Result};
 pub use handler::{convert_image, format_image};
 pub use image::DynamicImage;
+pub use pdf::format_pdf_pages;
 
 pub trait ImageHandler {
 	#[inline]
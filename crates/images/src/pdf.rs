@@ -11,7 +11,7 @@ use image::DynamicImage;
 use once_cell::sync::Lazy;
 use pdfium_render::{
 	color::PdfColor,
-	prelude::{PdfPageRenderRotation, PdfRenderConfig, Pdfium},
+	prelude::{PdfPage, PdfPageRenderRotation, PdfRenderConfig, Pdfium},
 };
 use tracing::error;
 
@@ -76,26 +76,44 @@ static LANDSCAPE_CONFIG: Lazy<PdfRenderConfig> = Lazy::new(|| {
 	thumbnail_config(PdfRenderConfig::new().set_target_width(PDF_LANDSCAPE_RENDER_WIDTH))
 });
 
+fn bind_pdfium() -> Result<Pdfium> {
+	Ok(Pdfium::new(
+		Pdfium::bind_to_library(PDFIUM_LIB.as_str()).or_else(|err| {
+			error!("{err:#?}");
+			Pdfium::bind_to_system_library()
+		})?,
+	))
+}
+
+fn render_page(page: &PdfPage<'_>) -> Result<DynamicImage> {
+	Ok(page
+		.render_with_config(if page.is_portrait() {
+			&PORTRAIT_CONFIG
+		} else {
+			&LANDSCAPE_CONFIG
+		})?
+		.as_image())
+}
+
 pub struct PdfHandler {}
 
 impl ImageHandler for PdfHandler {
 	fn handle_image(&self, path: &Path) -> Result<DynamicImage> {
-		let pdfium = Pdfium::new(Pdfium::bind_to_library(PDFIUM_LIB.as_str()).or_else(|err| {
-			error!("{err:#?}");
-			Pdfium::bind_to_system_library()
-		})?);
+		let pdfium = bind_pdfium()?;
 
 		let pdf = pdfium.load_pdf_from_file(path, None)?;
-		let first_page = pdf.pages().first()?;
+		render_page(&pdf.pages().first()?)
+	}
+}
 
-		let image = first_page
-			.render_with_config(if first_page.is_portrait() {
-				&PORTRAIT_CONFIG
-			} else {
-				&LANDSCAPE_CONFIG
-			})?
-			.as_image();
+/// Renders the first `max_pages` pages of the PDF at `path`, or all of them if it has fewer.
+pub fn format_pdf_pages(path: impl AsRef<Path>, max_pages: u16) -> Result<Vec<DynamicImage>> {
+	let pdfium = bind_pdfium()?;
 
-		Ok(image)
-	}
+	let pdf = pdfium.load_pdf_from_file(path.as_ref(), None)?;
+	let pages = pdf.pages();
+
+	(0..pages.len().min(max_pages))
+		.map(|index| render_page(&pages.get(index)?))
+		.collect()
 }
@@ -0,0 +1,81 @@
+pub use crate::error::{Error, Result};
+use crate::{consts::RAW_MINIMUM_PREVIEW_SIZE, ImageHandler};
+use exif::{In, Tag};
+use image::DynamicImage;
+use std::{io::Cursor, path::Path};
+
+/// The biggest size raw images are demosaiced at, as they're only used for thumbnails
+const RAW_DECODE_SIZE: usize = 2048;
+
+pub struct RawHandler {}
+
+impl ImageHandler for RawHandler {
+	fn handle_image(&self, path: &Path) -> Result<DynamicImage> {
+		let data = self.get_data(path)?; // this also makes sure the file isn't above the maximum size
+
+		// Most cameras embed a full size JPEG preview, which is much faster to decode than the sensor data
+		if let Some(preview) = embedded_preview(&data) {
+			return Ok(preview);
+		}
+
+		let image = imagepipe::simple_decode_8bit(path, RAW_DECODE_SIZE, RAW_DECODE_SIZE)
+			.map_err(Error::RawDecoding)?;
+
+		image::RgbImage::from_raw(
+			u32::try_from(image.width)?,
+			u32::try_from(image.height)?,
+			image.data,
+		)
+		.map(DynamicImage::ImageRgb8)
+		.ok_or(Error::RgbImageConversion)
+	}
+}
+
+/// Looks for the biggest JPEG preview in the TIFF directories of the raw image, rotated by its
+/// orientation, skipping the ones too small to make a good thumbnail.
+fn embedded_preview(data: &[u8]) -> Option<DynamicImage> {
+	let exif = exif::Reader::new()
+		.read_from_container(&mut Cursor::new(data))
+		.ok()?;
+
+	let uint = |tag, ifd| exif.get_field(tag, ifd)?.value.get_uint(0);
+
+	// Some cameras, like Canon's, store the preview as the JPEG compressed strip of the first directory
+	let primary_strip = matches!(uint(Tag::Compression, In::PRIMARY), Some(6 | 7))
+		.then(|| uint(Tag::StripOffsets, In::PRIMARY).zip(uint(Tag::StripByteCounts, In::PRIMARY)))
+		.flatten();
+
+	let orientation = uint(Tag::Orientation, In::PRIMARY);
+
+	[In::PRIMARY, In::THUMBNAIL]
+		.into_iter()
+		.filter_map(|ifd| {
+			uint(Tag::JPEGInterchangeFormat, ifd).zip(uint(Tag::JPEGInterchangeFormatLength, ifd))
+		})
+		.chain(primary_strip)
+		.filter_map(|(offset, length)| {
+			let start = usize::try_from(offset).ok()?;
+			let end = start.checked_add(usize::try_from(length).ok()?)?;
+
+			// Offsets are relative to the TIFF header, which raw images start with
+			data.get(start..end)
+		})
+		.max_by_key(|jpeg| jpeg.len())
+		.and_then(|jpeg| image::load_from_memory(jpeg).ok())
+		.filter(|preview| preview.width().max(preview.height()) >= RAW_MINIMUM_PREVIEW_SIZE)
+		.map(|preview| orient(preview, orientation))
+}
+
+/// Applies the EXIF orientation, as the embedded previews are stored like the sensor data
+fn orient(image: DynamicImage, orientation: Option<u32>) -> DynamicImage {
+	match orientation {
+		Some(2) => image.fliph(),
+		Some(3) => image.rotate180(),
+		Some(4) => image.flipv(),
+		Some(5) => image.rotate90().fliph(),
+		Some(6) => image.rotate90(),
+		Some(7) => image.rotate270().fliph(),
+		Some(8) => image.rotate270(),
+		_ => image,
+	}
+}
@@ -1,6 +1,6 @@
 use std::io::{self, ErrorKind};
 
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 /// TODO
 #[derive(Debug, PartialEq, Eq)]
@@ -23,6 +23,17 @@ impl<'a> Block<'a> {
 		buf
 	}
 
+	/// Writes the same bytes as [`Self::to_bytes`], without copying the data into a new buffer.
+	pub async fn write_to(&self, stream: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+		debug_assert_eq!(self.data.len(), self.size as usize);
+		let mut header = [0; 16];
+		header[..8].copy_from_slice(&self.offset.to_le_bytes());
+		header[8..].copy_from_slice(&self.size.to_le_bytes());
+
+		stream.write_all(&header).await?;
+		stream.write_all(self.data).await
+	}
+
 	pub async fn from_stream(
 		stream: &mut (impl AsyncReadExt + Unpin),
 		data_buf: &mut [u8],
@@ -79,6 +90,18 @@ mod tests {
 		assert_eq!(data, data2);
 	}
 
+	#[tokio::test]
+	async fn test_block_write_to() {
+		let block = Block {
+			offset: 420,
+			size: 10,
+			data: b"Spacedrive".as_ref(),
+		};
+		let mut bytes = Vec::new();
+		block.write_to(&mut bytes).await.unwrap();
+		assert_eq!(bytes, block.to_bytes());
+	}
+
 	#[tokio::test]
 	#[should_panic] // TODO: This currently panics but long term it should have proper error handling
 	async fn test_block_data_buf_overflow() {
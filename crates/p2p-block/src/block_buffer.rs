@@ -0,0 +1,97 @@
+use std::{
+	ops::{Deref, DerefMut},
+	sync::{Mutex, PoisonError},
+};
+
+/// Buffers kept once their transfer is done, enough for a few concurrent Spacedrops
+const MAX_POOLED_BUFFERS: usize = 8;
+
+static POOL: BufferPool = BufferPool::new();
+
+struct BufferPool(Mutex<Vec<Vec<u8>>>);
+
+impl BufferPool {
+	const fn new() -> Self {
+		Self(Mutex::new(Vec::new()))
+	}
+
+	fn take(&'static self, size: usize) -> BlockBuffer {
+		let pooled = {
+			let mut buffers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+			buffers
+				.iter()
+				.position(|buf| buf.len() == size)
+				.map(|i| buffers.swap_remove(i))
+		};
+
+		// The previous contents of a pooled buffer are never read, only the bytes written to it
+		BlockBuffer {
+			buf: pooled.unwrap_or_else(|| vec![0; size]),
+			pool: self,
+		}
+	}
+
+	fn put(&self, buf: Vec<u8>) {
+		let mut buffers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+		if buffers.len() < MAX_POOLED_BUFFERS {
+			buffers.push(buf);
+		}
+	}
+}
+
+/// A buffer the size of a block, taken from a pool shared by every transfer and returned to it
+/// when dropped.
+///
+/// A transfer reuses the same buffer for all of its files, so sending many files doesn't allocate
+/// a new block for each of them.
+pub(crate) struct BlockBuffer {
+	buf: Vec<u8>,
+	pool: &'static BufferPool,
+}
+
+impl BlockBuffer {
+	pub(crate) fn new(size: usize) -> Self {
+		POOL.take(size)
+	}
+}
+
+impl Deref for BlockBuffer {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.buf
+	}
+}
+
+impl DerefMut for BlockBuffer {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.buf
+	}
+}
+
+impl Drop for BlockBuffer {
+	fn drop(&mut self) {
+		self.pool.put(std::mem::take(&mut self.buf));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_block_buffer_reused() {
+		static POOL: BufferPool = BufferPool::new();
+
+		let buf = POOL.take(25);
+		let ptr = buf.as_ptr();
+		drop(buf);
+
+		// Only buffers of the requested size are reused
+		assert_eq!(POOL.take(50).len(), 50);
+
+		let buf = POOL.take(25);
+		assert_eq!(buf.len(), 25);
+		assert_eq!(buf.as_ptr(), ptr);
+	}
+}
@@ -0,0 +1,117 @@
+use std::io::{self, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The checksums of the blocks of the receiver's older version of a file, so the sender only sends
+/// the blocks which changed.
+///
+/// Blocks are compared at the same offsets, so edits in place are sent as a few blocks while data
+/// inserted or removed in the middle of the file changes all the blocks after it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BlockChecksums(Vec<blake3::Hash>);
+
+impl BlockChecksums {
+	/// Checksums the blocks of `file`, up to `max_blocks` of them, reading them with `buf`.
+	pub async fn from_file(
+		file: &mut (impl AsyncRead + Unpin),
+		buf: &mut [u8],
+		max_blocks: u64,
+	) -> io::Result<Self> {
+		let mut checksums = Vec::new();
+
+		while (checksums.len() as u64) < max_blocks {
+			let read = crate::read_block(file, buf).await?;
+			if read == 0 {
+				break;
+			}
+
+			checksums.push(blake3::hash(&buf[..read]));
+		}
+
+		Ok(Self(checksums))
+	}
+
+	/// Fails if the receiver sent more than `max_blocks` checksums.
+	pub async fn from_stream(
+		stream: &mut (impl AsyncRead + Unpin),
+		max_blocks: u64,
+	) -> io::Result<Self> {
+		let len = stream.read_u32_le().await?;
+		if u64::from(len) > max_blocks {
+			return Err(io::Error::new(
+				ErrorKind::Other,
+				"more block checksums than blocks",
+			));
+		}
+
+		let mut checksums = Vec::with_capacity(len as usize);
+		for _ in 0..len {
+			let mut hash = [0; blake3::OUT_LEN];
+			stream.read_exact(&mut hash).await?;
+			checksums.push(blake3::Hash::from(hash));
+		}
+
+		Ok(Self(checksums))
+	}
+
+	pub async fn write_to(&self, stream: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+		let mut buf = Vec::with_capacity(4 + self.0.len() * blake3::OUT_LEN);
+		#[allow(clippy::cast_possible_truncation)] // There are never more than `u32::MAX` blocks
+		buf.extend_from_slice(&(self.0.len() as u32).to_le_bytes());
+		for hash in &self.0 {
+			buf.extend_from_slice(hash.as_bytes());
+		}
+
+		stream.write_all(&buf).await
+	}
+
+	pub(crate) fn get(&self, index: usize) -> Option<&blake3::Hash> {
+		self.0.get(index)
+	}
+
+	/// Whether the receiver already has `data` as its block number `index`.
+	#[must_use]
+	pub fn matches(&self, index: usize, data: &[u8]) -> bool {
+		self.0
+			.get(index)
+			.is_some_and(|hash| *hash == blake3::hash(data))
+	}
+}
+
+impl From<Vec<blake3::Hash>> for BlockChecksums {
+	fn from(checksums: Vec<blake3::Hash>) -> Self {
+		Self(checksums)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_block_checksums() {
+		let mut buf = [0; 4];
+		let checksums =
+			BlockChecksums::from_file(&mut Cursor::new(b"Spacedrive".to_vec()), &mut buf, 10)
+				.await
+				.unwrap();
+		assert!(checksums.matches(0, b"Spac"));
+		assert!(checksums.matches(2, b"ve"));
+		assert!(!checksums.matches(1, b"ECDR"));
+		assert!(!checksums.matches(3, b""));
+
+		let mut bytes = Vec::new();
+		checksums.write_to(&mut bytes).await.unwrap();
+		assert_eq!(
+			BlockChecksums::from_stream(&mut Cursor::new(&bytes), 3)
+				.await
+				.unwrap(),
+			checksums
+		);
+		assert!(BlockChecksums::from_stream(&mut Cursor::new(&bytes), 2)
+			.await
+			.is_err());
+	}
+}
@@ -7,7 +7,7 @@
 #![allow(unused)] // TODO: This module is still in heavy development!
 
 use std::{
-	io,
+	io::{self, SeekFrom},
 	marker::PhantomData,
 	path::{Path, PathBuf},
 	string::FromUtf8Error,
@@ -15,12 +15,16 @@ use std::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
 	},
+	time::{Duration, Instant},
 };
 
 use thiserror::Error;
 use tokio::{
 	fs::File,
-	io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+	io::{
+		AsyncBufRead, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+		BufReader,
+	},
 };
 use tracing::debug;
 
@@ -28,13 +32,20 @@ use sd_p2p::UnicastStream;
 use sd_p2p_proto::{decode, encode};
 
 mod block;
+mod block_buffer;
+mod block_checksums;
 mod block_size;
+mod manifest;
 mod sb_request;
 
 pub use block::*;
+pub use block_checksums::*;
 pub use block_size::*;
+pub use manifest::*;
 pub use sb_request::*;
 
+use block_buffer::BlockBuffer;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Msg<'a> {
 	Block(Block<'a>),
@@ -69,8 +80,22 @@ impl<'a> Msg<'a> {
 			Msg::Cancelled => vec![1],
 		}
 	}
+
+	/// Writes the same bytes as [`Self::to_bytes`], without copying the data of a block.
+	pub async fn write_to(&self, stream: &mut (impl AsyncWrite + Unpin)) -> Result<(), io::Error> {
+		match self {
+			Msg::Block(block) => {
+				stream.write_u8(0).await?;
+				block.write_to(stream).await
+			}
+			Msg::Cancelled => stream.write_u8(1).await,
+		}
+	}
 }
 
+/// How often the [`TransferManifest`] of a file being received is saved
+const MANIFEST_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
 /// TODO
 pub struct Transfer<'a, F> {
 	reqs: &'a SpaceblockRequests,
@@ -80,6 +105,8 @@ pub struct Transfer<'a, F> {
 	// TODO: Remove `i` plz
 	i: usize,
 	cancelled: &'a AtomicBool,
+	/// Shared by all the files of the transfer
+	buf: BlockBuffer,
 }
 
 impl<'a, F> Transfer<'a, F>
@@ -96,6 +123,7 @@ where
 			total_bytes: req.requests.iter().map(|req| req.size).sum(),
 			i: 0,
 			cancelled,
+			buf: BlockBuffer::new(req.block_size.size() as usize),
 		}
 	}
 
@@ -105,18 +133,26 @@ where
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
 		mut file: (impl AsyncBufRead + Unpin),
 	) -> Result<(), io::Error> {
+		let size = self.current_size()?;
+		if size == 0 {
+			self.i += 1;
+			return Ok(());
+		}
+
+		let checksums = BlockChecksums::from_stream(stream, self.blocks_of(size)).await?;
+
 		// We manually implement what is basically a `BufReader` so we have more control
-		let mut buf = vec![0u8; self.reqs.block_size.size() as usize];
 		let mut offset: u64 = 0;
+		let mut index = 0;
 
 		loop {
 			if self.cancelled.load(Ordering::Relaxed) {
-				stream.write_all(&Msg::Cancelled.to_bytes()).await?;
+				Msg::Cancelled.write_to(stream).await?;
 				stream.flush().await?;
 				return Ok(());
 			}
 
-			let read = file.read(&mut buf[..]).await?;
+			let read = read_block(&mut file, &mut self.buf).await?;
 			self.total_offset += read as u64;
 			(self.on_progress)(
 				((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
@@ -127,25 +163,37 @@ where
 						// The file may have been modified during sender on the sender and we don't account for that.
 						// TODO: Error handling + send error to remote
 				assert!(
-					(offset + read as u64) == self.reqs.requests[self.i].size,
+					(offset + read as u64) == size,
 					"File sending has stopped but it doesn't match the expected length!"
 				);
 
+				self.i += 1;
 				return Ok(());
 			}
 
+			// The last block is always sent, as it's how the receiver knows the file is complete
+			if offset + (read as u64) < size && checksums.matches(index, &self.buf[..read]) {
+				debug!("Skipping unchanged block at offset {offset} of size {read}");
+				offset += read as u64;
+				index += 1;
+				continue;
+			}
+
 			let block = Block {
 				offset,
 				size: read as u64,
-				data: &buf[..read],
+				data: &self.buf[..read],
 			};
 			debug!(
 				"Sending block at offset {} of size {}",
 				block.offset, block.size
 			);
 			offset += read as u64;
+			index += 1;
 
-			stream.write_all(&Msg::Block(block).to_bytes()).await?;
+			// QUIC encrypts the stream in userspace, so the kernel can't `sendfile` the file into it,
+			// but the block is at least written straight from the buffer it was read into
+			Msg::Block(block).write_to(stream).await?;
 			stream.flush().await?;
 
 			match stream.read_u8().await? {
@@ -157,7 +205,10 @@ where
 					return Ok(());
 				}
 				// Transfer complete
-				2 => return Ok(()),
+				2 => {
+					self.i += 1;
+					return Ok(());
+				}
 				_ => todo!(),
 			}
 		}
@@ -167,31 +218,112 @@ where
 	pub async fn receive(
 		&mut self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
-		mut file: (impl AsyncWrite + Unpin),
+		file: (impl AsyncWrite + Unpin),
 		// TODO: Proper error type
 	) -> Result<(), io::Error> {
-		// We manually implement what is basically a `BufReader` so we have more control
-		let mut data_buf = vec![0u8; self.reqs.block_size.size() as usize];
+		self.receive_blocks(stream, Appended(file), BlockChecksums::default(), None)
+			.await
+	}
+
+	/// Receives a file over the receiver's older version of it, only the blocks which changed are
+	/// sent.
+	///
+	/// The file isn't truncated, so it's up to the caller to shrink it to the size of the request.
+	pub async fn receive_delta(
+		&mut self,
+		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+		mut file: (impl AsyncRead + AsyncWrite + AsyncSeek + Unpin),
+	) -> Result<(), io::Error> {
+		let max_blocks = self.blocks_of(self.current_size()?);
+		let checksums = BlockChecksums::from_file(&mut file, &mut self.buf, max_blocks).await?;
+
+		self.receive_blocks(stream, Patched(file), checksums, None)
+			.await
+	}
+
+	/// Like [`Self::receive_delta`], but the blocks received are recorded in a
+	/// [`TransferManifest`] at `manifest_path` until the file is complete, so if the transfer is
+	/// interrupted the next one resumes from the blocks already received and verified.
+	pub async fn receive_resumable(
+		&mut self,
+		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+		mut file: (impl AsyncRead + AsyncWrite + AsyncSeek + Unpin),
+		manifest_path: &Path,
+	) -> Result<(), io::Error> {
+		let size = self.current_size()?;
+
+		let (mut manifest, checksums) = match TransferManifest::load(manifest_path).await? {
+			Some(mut manifest) if manifest.is_for(size, &self.reqs.block_size) => {
+				let checksums = manifest.verify(&mut file, &mut self.buf).await?;
+				debug!(
+					"Resuming transfer with the ranges {:?} already received",
+					manifest.completed_ranges()
+				);
+				(manifest, checksums)
+			}
+			// Nothing to resume, but the file may be an older version of the one received
+			_ => {
+				file.seek(SeekFrom::Start(0)).await?;
+				let checksums =
+					BlockChecksums::from_file(&mut file, &mut self.buf, self.blocks_of(size))
+						.await?;
+				(
+					TransferManifest::new(size, &self.reqs.block_size),
+					checksums,
+				)
+			}
+		};
+
+		self.receive_blocks(
+			stream,
+			Patched(file),
+			checksums,
+			Some((&mut manifest, manifest_path)),
+		)
+		.await
+	}
+
+	async fn receive_blocks(
+		&mut self,
+		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+		mut file: impl BlockWriter,
+		checksums: BlockChecksums,
+		mut manifest: Option<(&mut TransferManifest, &Path)>,
+	) -> Result<(), io::Error> {
+		// The end of the blocks received so far, or skipped as unchanged
 		let mut offset: u64 = 0;
+		let mut saved_at = Instant::now();
 
-		if self.reqs.requests[self.i].size == 0 {
+		let size = self.current_size()?;
+		if size == 0 {
 			self.i += 1;
+			if let Some((_, path)) = manifest {
+				TransferManifest::remove(path).await?;
+			}
 			return Ok(());
 		}
 
+		checksums.write_to(stream).await?;
+		stream.flush().await?;
+
 		// TODO: Prevent loop being a DOS vector
 		loop {
 			if self.cancelled.load(Ordering::Relaxed) {
 				stream.write_u8(1).await?;
 				stream.flush().await?;
+				if let Some((manifest, path)) = &manifest {
+					file.flush().await?;
+					manifest.save(path).await?;
+				}
 				return Ok(());
 			}
 
 			// TODO: Timeout if nothing is being received
-			let msg = Msg::from_stream(stream, &mut data_buf).await?;
+			let msg = Msg::from_stream(stream, &mut self.buf).await?;
 			match msg {
 				Msg::Block(block) => {
-					self.total_offset += block.size;
+					let end = block.offset + block.size;
+					self.total_offset += end.saturating_sub(offset);
 					(self.on_progress)(
 						((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
 					); // SAFETY: Percent must be between 0 and 100
@@ -200,16 +332,29 @@ where
 						"Received block at offset {} of size {}",
 						block.offset, block.size
 					);
-					offset += block.size;
+					let skipped_from = offset;
+					offset = end;
 
-					file.write_all(&data_buf[..block.size as usize]).await?;
+					file.write_block(block.offset, &self.buf[..block.size as usize])
+						.await?;
+
+					if let Some((manifest, path)) = &mut manifest {
+						manifest.received(
+							skipped_from,
+							block.offset,
+							&self.buf[..block.size as usize],
+							&checksums,
+						);
+
+						if saved_at.elapsed() >= MANIFEST_SAVE_INTERVAL {
+							file.flush().await?;
+							manifest.save(path).await?;
+							saved_at = Instant::now();
+						}
+					}
 
-					let req = self.reqs.requests.get(self.i).ok_or_else(|| {
-						debug!("Vector read out of bounds!");
-						io::ErrorKind::Other
-					})?;
 					// TODO: Should this be `read == 0`
-					if offset == req.size {
+					if offset >= size {
 						break;
 					}
 
@@ -220,6 +365,10 @@ where
 				}
 				Msg::Cancelled => {
 					debug!("Sender cancelled Spacedrop transfer!");
+					if let Some((manifest, path)) = &manifest {
+						file.flush().await?;
+						manifest.save(path).await?;
+					}
 					return Ok(());
 				}
 			}
@@ -230,8 +379,76 @@ where
 		file.flush().await?;
 		self.i += 1;
 
+		if let Some((_, path)) = manifest {
+			TransferManifest::remove(path).await?;
+		}
+
 		Ok(())
 	}
+
+	fn current_size(&self) -> Result<u64, io::Error> {
+		self.reqs
+			.requests
+			.get(self.i)
+			.map(|req| req.size)
+			.ok_or_else(|| {
+				debug!("Vector read out of bounds!");
+				io::ErrorKind::Other.into()
+			})
+	}
+
+	fn blocks_of(&self, size: u64) -> u64 {
+		size.div_ceil(u64::from(self.reqs.block_size.size()))
+	}
+}
+
+/// Where [`Transfer`] writes the blocks it receives
+trait BlockWriter {
+	async fn write_block(&mut self, offset: u64, data: &[u8]) -> io::Result<()>;
+
+	async fn flush(&mut self) -> io::Result<()>;
+}
+
+/// A new file, every block is received in order
+struct Appended<W>(W);
+
+impl<W: AsyncWrite + Unpin> BlockWriter for Appended<W> {
+	async fn write_block(&mut self, _offset: u64, data: &[u8]) -> io::Result<()> {
+		self.0.write_all(data).await
+	}
+
+	async fn flush(&mut self) -> io::Result<()> {
+		self.0.flush().await
+	}
+}
+
+/// An older version of the file, only the blocks which changed are received
+struct Patched<W>(W);
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> BlockWriter for Patched<W> {
+	async fn write_block(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+		self.0.seek(SeekFrom::Start(offset)).await?;
+		self.0.write_all(data).await
+	}
+
+	async fn flush(&mut self) -> io::Result<()> {
+		self.0.flush().await
+	}
+}
+
+/// Fills `buf` unless the file ends first, so every block but the last one is full and the file
+/// is read at offsets aligned to the block size.
+async fn read_block(file: &mut (impl AsyncRead + Unpin), buf: &mut [u8]) -> io::Result<usize> {
+	let mut read = 0;
+
+	while read < buf.len() {
+		match file.read(&mut buf[read..]).await? {
+			0 => break,
+			n => read += n,
+		}
+	}
+
+	Ok(read)
 }
 
 #[cfg(test)]
@@ -323,6 +540,103 @@ mod tests {
 		assert_eq!(result, data);
 	}
 
+	#[tokio::test]
+	async fn test_spaceblock_delta() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let block_size = 25u32;
+		let data = (0..block_size * 4).map(|i| i as u8).collect::<Vec<_>>();
+		let block_size = BlockSize::dangerously_new(block_size);
+
+		// The receiver's version of the file has a different second block
+		let mut existing = data.clone();
+		existing[30] = 0;
+
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size,
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Full,
+			}],
+		};
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				tx.send(()).unwrap();
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await;
+			}
+		});
+
+		rx.await.unwrap();
+
+		let mut result = Cursor::new(existing);
+		Transfer::new(&req, |_| {}, &Default::default())
+			.receive_delta(&mut server, &mut result)
+			.await
+			.unwrap();
+		assert_eq!(result.into_inner(), data);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_resume() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let block_size = 25u32;
+		let data = (0..block_size * 4).map(|i| i as u8).collect::<Vec<_>>();
+		let block_size = BlockSize::dangerously_new(block_size);
+
+		// The first two blocks were received before the transfer was interrupted
+		let existing = data[..50].to_vec();
+		let mut manifest = TransferManifest::new(data.len() as u64, &block_size);
+		manifest.complete(0, blake3::hash(&data[..25]));
+		manifest.complete(1, blake3::hash(&data[25..50]));
+		let manifest_path = std::env::temp_dir().join(format!("{}.sdtransfer", Uuid::new_v4()));
+		manifest.save(&manifest_path).await.unwrap();
+
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size,
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Full,
+			}],
+		};
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				tx.send(()).unwrap();
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await;
+			}
+		});
+
+		rx.await.unwrap();
+
+		let mut result = Cursor::new(existing);
+		Transfer::new(&req, |_| {}, &Default::default())
+			.receive_resumable(&mut server, &mut result, &manifest_path)
+			.await
+			.unwrap();
+		assert_eq!(result.into_inner(), data);
+		assert_eq!(TransferManifest::load(&manifest_path).await.unwrap(), None);
+	}
+
 	#[tokio::test]
 	async fn test_transfer_receiver_cancelled() {
 		let (mut client, mut server) = tokio::io::duplex(64);
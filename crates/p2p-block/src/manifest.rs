@@ -0,0 +1,243 @@
+use std::{
+	collections::BTreeMap,
+	io::{self, ErrorKind, SeekFrom},
+	ops::Range,
+	path::Path,
+};
+
+use tokio::{
+	fs,
+	io::{AsyncRead, AsyncSeek, AsyncSeekExt},
+};
+
+use crate::{BlockChecksums, BlockSize};
+
+/// The blocks of a file received so far, saved next to it while it's received so an interrupted
+/// transfer resumes from them instead of restarting.
+///
+/// The checksum of each block is kept, so blocks changed on disk since are received again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferManifest {
+	/// Of the whole file, to not resume the transfer of another version of it
+	size: u64,
+	block_size: u32,
+	blocks: BTreeMap<u64, blake3::Hash>,
+}
+
+impl TransferManifest {
+	#[must_use]
+	pub fn new(size: u64, block_size: &BlockSize) -> Self {
+		Self {
+			size,
+			block_size: block_size.size(),
+			blocks: BTreeMap::new(),
+		}
+	}
+
+	/// Reads the manifest at `path`, `None` if there is none.
+	pub async fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+		match fs::read(path).await {
+			Ok(bytes) => Self::from_bytes(&bytes).map(Some),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	pub async fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		fs::write(path, self.to_bytes()).await
+	}
+
+	/// Removes the manifest at `path`, once the file is complete.
+	pub async fn remove(path: impl AsRef<Path>) -> io::Result<()> {
+		match fs::remove_file(path).await {
+			Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+			_ => Ok(()),
+		}
+	}
+
+	/// Whether the manifest is of the transfer of a file of `size` bytes in blocks of `block_size`.
+	#[must_use]
+	pub fn is_for(&self, size: u64, block_size: &BlockSize) -> bool {
+		self.size == size && self.block_size == block_size.size()
+	}
+
+	/// The ranges of bytes received, merged when contiguous.
+	#[must_use]
+	pub fn completed_ranges(&self) -> Vec<Range<u64>> {
+		let mut ranges: Vec<Range<u64>> = Vec::new();
+
+		for index in self.blocks.keys() {
+			let range = self.block_range(*index);
+			match ranges.last_mut() {
+				Some(last) if last.end == range.start => last.end = range.end,
+				_ => ranges.push(range),
+			}
+		}
+
+		ranges
+	}
+
+	pub(crate) fn complete(&mut self, index: u64, checksum: blake3::Hash) {
+		self.blocks.insert(index, checksum);
+	}
+
+	/// Records the block received at `offset`, and the ones from `skipped_from` to it the sender
+	/// skipped as they matched the `checksums` of the receiver.
+	pub(crate) fn received(
+		&mut self,
+		skipped_from: u64,
+		offset: u64,
+		data: &[u8],
+		checksums: &BlockChecksums,
+	) {
+		let block_size = u64::from(self.block_size);
+
+		for index in skipped_from / block_size..offset / block_size {
+			if let Some(checksum) = usize::try_from(index).ok().and_then(|i| checksums.get(i)) {
+				self.complete(index, *checksum);
+			}
+		}
+
+		self.complete(offset / block_size, blake3::hash(data));
+	}
+
+	/// Checksums the blocks of `file` the manifest has, forgetting the ones which don't match
+	/// anymore, and returns the checksums to send to the sender so it skips the others.
+	pub async fn verify(
+		&mut self,
+		file: &mut (impl AsyncRead + AsyncSeek + Unpin),
+		buf: &mut [u8],
+	) -> io::Result<BlockChecksums> {
+		let mut checksums = Vec::new();
+		let mut verified = BTreeMap::new();
+
+		for (index, checksum) in &self.blocks {
+			let range = self.block_range(*index);
+			#[allow(clippy::cast_possible_truncation)] // Blocks are never bigger than `u32::MAX`
+			let len = (range.end - range.start) as usize;
+
+			file.seek(SeekFrom::Start(range.start)).await?;
+			if crate::read_block(file, &mut buf[..len]).await? != len
+				|| blake3::hash(&buf[..len]) != *checksum
+			{
+				continue;
+			}
+
+			// The blocks missing in between are sent by the sender, as nothing matches this.
+			// There are never more than `u32::MAX` blocks.
+			#[allow(clippy::cast_possible_truncation)]
+			checksums.resize(*index as usize, blake3::Hash::from([0; blake3::OUT_LEN]));
+			checksums.push(*checksum);
+			verified.insert(*index, *checksum);
+		}
+
+		self.blocks = verified;
+
+		Ok(BlockChecksums::from(checksums))
+	}
+
+	fn block_range(&self, index: u64) -> Range<u64> {
+		let start = index * u64::from(self.block_size);
+		start..(start + u64::from(self.block_size)).min(self.size)
+	}
+
+	fn blocks_count(&self) -> u64 {
+		self.size.div_ceil(u64::from(self.block_size))
+	}
+
+	#[must_use]
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(16 + self.blocks.len() * (8 + blake3::OUT_LEN));
+		buf.extend_from_slice(&self.size.to_le_bytes());
+		buf.extend_from_slice(&self.block_size.to_le_bytes());
+		#[allow(clippy::cast_possible_truncation)] // There are never more than `u32::MAX` blocks
+		buf.extend_from_slice(&(self.blocks.len() as u32).to_le_bytes());
+		for (index, checksum) in &self.blocks {
+			buf.extend_from_slice(&index.to_le_bytes());
+			buf.extend_from_slice(checksum.as_bytes());
+		}
+
+		buf
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+		fn invalid() -> io::Error {
+			io::Error::new(ErrorKind::InvalidData, "invalid transfer manifest")
+		}
+
+		fn take<'a>(bytes: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+			if bytes.len() < len {
+				return Err(invalid());
+			}
+
+			let (taken, rest) = bytes.split_at(len);
+			*bytes = rest;
+			Ok(taken)
+		}
+
+		let mut bytes = bytes;
+		let size = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().map_err(|_| invalid())?);
+		let block_size =
+			u32::from_le_bytes(take(&mut bytes, 4)?.try_into().map_err(|_| invalid())?);
+		let len = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().map_err(|_| invalid())?);
+
+		if block_size == 0 {
+			return Err(invalid());
+		}
+
+		let mut manifest = Self {
+			size,
+			block_size,
+			blocks: BTreeMap::new(),
+		};
+
+		if u64::from(len) > manifest.blocks_count() {
+			return Err(invalid());
+		}
+
+		for _ in 0..len {
+			let index = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().map_err(|_| invalid())?);
+			let checksum: [u8; blake3::OUT_LEN] = take(&mut bytes, blake3::OUT_LEN)?
+				.try_into()
+				.map_err(|_| invalid())?;
+
+			if index >= manifest.blocks_count() {
+				return Err(invalid());
+			}
+
+			manifest.blocks.insert(index, blake3::Hash::from(checksum));
+		}
+
+		Ok(manifest)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_transfer_manifest() {
+		let data = b"Spacedrive".to_vec();
+		let mut manifest = TransferManifest::new(data.len() as u64, &BlockSize::dangerously_new(4));
+		manifest.complete(0, blake3::hash(b"Spac"));
+		manifest.complete(1, blake3::hash(b"edri"));
+		manifest.complete(2, blake3::hash(b"ve"));
+		assert_eq!(manifest.completed_ranges(), vec![0..10]);
+
+		assert_eq!(
+			TransferManifest::from_bytes(&manifest.to_bytes()).unwrap(),
+			manifest
+		);
+
+		// The second block changed on disk since it was received
+		let mut file = Cursor::new(b"SpacEDRIve".to_vec());
+		let checksums = manifest.verify(&mut file, &mut [0; 4]).await.unwrap();
+		assert!(checksums.matches(0, b"Spac"));
+		assert!(!checksums.matches(1, b"edri"));
+		assert!(checksums.matches(2, b"ve"));
+		assert_eq!(manifest.completed_ranges(), vec![0..4, 8..10]);
+	}
+}
@@ -4,6 +4,7 @@
 pub(crate) mod hooks;
 mod identity;
 mod mdns;
+mod metrics;
 mod p2p;
 mod peer;
 mod quic;
@@ -13,9 +14,10 @@ mod stream;
 pub use hooks::{HookEvent, HookId, ListenerId, ShutdownGuard};
 pub use identity::{Identity, IdentityErr, RemoteIdentity};
 pub use mdns::Mdns;
+pub use metrics::{Metrics, PeerStats};
 pub use p2p::{Listener, P2P};
-pub use peer::{ConnectionRequest, Peer, PeerConnectionCandidate};
-pub use quic::{Libp2pPeerId, QuicTransport, RelayServerEntry};
+pub use peer::{ConnectionRequest, NewStreamError, Peer, PeerConnectionCandidate};
+pub use quic::{Libp2pPeerId, QuicTransport, QuicTransportError, RelayServerEntry};
 pub use smart_guards::SmartWriteGuard;
 pub use stream::UnicastStream;
 
@@ -0,0 +1,140 @@
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicU32, AtomicU64, Ordering},
+		Arc, PoisonError, RwLock,
+	},
+	time::Duration,
+};
+
+use crate::RemoteIdentity;
+
+/// The most peers we keep statistics for, so a node seeing many identities doesn't grow the map
+/// forever
+const MAX_TRACKED_PEERS: usize = 256;
+
+/// Transfer statistics of the peers this node exchanged streams with, since it started.
+///
+/// Once [`MAX_TRACKED_PEERS`] are tracked, the idle peer that transferred the least is forgotten
+/// to make room for a new one. If every tracked peer has streams open, the new peer is counted
+/// without being tracked.
+#[derive(Debug, Default)]
+pub struct Metrics {
+	peers: RwLock<HashMap<RemoteIdentity, Arc<PeerMetrics>>>,
+}
+
+/// Counters of a single peer, updated by its [`crate::UnicastStream`]s.
+#[derive(Debug, Default)]
+pub struct PeerMetrics {
+	bytes_sent: AtomicU64,
+	bytes_received: AtomicU64,
+	active_streams: AtomicU32,
+	/// Round trip time in microseconds of the last ping, `0` if the peer was never pinged
+	rtt_micros: AtomicU64,
+}
+
+/// A snapshot of the [`PeerMetrics`] of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerStats {
+	pub identity: RemoteIdentity,
+	pub bytes_sent: u64,
+	pub bytes_received: u64,
+	pub active_streams: u32,
+	pub rtt: Option<Duration>,
+}
+
+impl Metrics {
+	pub(crate) fn peer(&self, identity: RemoteIdentity) -> Arc<PeerMetrics> {
+		if let Some(metrics) = self
+			.peers
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(&identity)
+		{
+			return metrics.clone();
+		}
+
+		let mut peers = self.peers.write().unwrap_or_else(PoisonError::into_inner);
+		if let Some(metrics) = peers.get(&identity) {
+			return metrics.clone();
+		}
+
+		if peers.len() >= MAX_TRACKED_PEERS {
+			// Only the map holds idle peers, the streams of a peer hold it too
+			let least_active = peers
+				.iter()
+				.filter(|(_, metrics)| Arc::strong_count(metrics) == 1)
+				.min_by_key(|(_, metrics)| metrics.bytes_transferred())
+				.map(|(identity, _)| *identity);
+
+			match least_active {
+				Some(least_active) => {
+					peers.remove(&least_active);
+				}
+				None => return Arc::default(),
+			}
+		}
+
+		peers.entry(identity).or_default().clone()
+	}
+
+	pub(crate) fn set_rtt(&self, identity: RemoteIdentity, rtt: Duration) {
+		self.peer(identity).rtt_micros.store(
+			u64::try_from(rtt.as_micros()).unwrap_or(u64::MAX).max(1),
+			Ordering::Relaxed,
+		);
+	}
+
+	/// The statistics of every peer, in no particular order.
+	pub fn stats(&self) -> Vec<PeerStats> {
+		self.peers
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.map(|(identity, metrics)| PeerStats {
+				identity: *identity,
+				bytes_sent: metrics.bytes_sent.load(Ordering::Relaxed),
+				bytes_received: metrics.bytes_received.load(Ordering::Relaxed),
+				active_streams: metrics.active_streams.load(Ordering::Relaxed),
+				rtt: match metrics.rtt_micros.load(Ordering::Relaxed) {
+					0 => None,
+					micros => Some(Duration::from_micros(micros)),
+				},
+			})
+			.collect()
+	}
+}
+
+impl PeerMetrics {
+	fn bytes_transferred(&self) -> u64 {
+		self.bytes_sent
+			.load(Ordering::Relaxed)
+			.saturating_add(self.bytes_received.load(Ordering::Relaxed))
+	}
+
+	pub(crate) fn sent(&self, bytes: usize) {
+		self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	pub(crate) fn received(&self, bytes: usize) {
+		self.bytes_received
+			.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+}
+
+/// Counts a stream as active until dropped.
+#[derive(Debug)]
+pub(crate) struct StreamMetrics(pub(crate) Arc<PeerMetrics>);
+
+impl StreamMetrics {
+	pub(crate) fn new(metrics: Arc<PeerMetrics>) -> Self {
+		metrics.active_streams.fetch_add(1, Ordering::Relaxed);
+		Self(metrics)
+	}
+}
+
+impl Drop for StreamMetrics {
+	fn drop(&mut self) {
+		self.0.active_streams.fetch_sub(1, Ordering::Relaxed);
+	}
+}
@@ -15,7 +15,7 @@ use tracing::info;
 use crate::{
 	hooks::{HandlerFn, Hook, HookEvent, ListenerData, ListenerId, ShutdownGuard},
 	smart_guards::SmartWriteGuard,
-	HookId, Identity, Peer, PeerConnectionCandidate, RemoteIdentity, UnicastStream,
+	HookId, Identity, Metrics, Peer, PeerConnectionCandidate, RemoteIdentity, UnicastStream,
 };
 
 /// Manager for the entire P2P system.
@@ -37,6 +37,8 @@ pub struct P2P {
 	pub(crate) peers: RwLock<HashMap<RemoteIdentity, Arc<Peer>>>,
 	/// Hooks can be registered to react to state changes in the P2P system.
 	pub(crate) hooks: RwLock<StableVec<Hook>>,
+	/// Transfer statistics of the peers, updated by the transports.
+	metrics: Metrics,
 }
 
 impl P2P {
@@ -63,6 +65,7 @@ impl P2P {
 			peers: Default::default(),
 			handler_tx,
 			hooks: Default::default(),
+			metrics: Default::default(),
 		})
 	}
 
@@ -71,6 +74,11 @@ impl P2P {
 		self.app_name
 	}
 
+	/// Bytes sent and received, active streams and round trip time of each peer.
+	pub fn metrics(&self) -> &Metrics {
+		&self.metrics
+	}
+
 	/// The identifier of this node that can *MUST* be kept secret.
 	/// This is a private key in crypto terms.
 	pub fn identity(&self) -> &Identity {
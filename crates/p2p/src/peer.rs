@@ -6,9 +6,11 @@ use std::{
 
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
-use tracing::warn;
+use tracing::{instrument, warn};
 
-use crate::{HookEvent, HookId, ListenerId, RemoteIdentity, UnicastStream, P2P};
+use crate::{
+	HookEvent, HookId, ListenerId, QuicTransportError, RemoteIdentity, UnicastStream, P2P,
+};
 
 #[derive(Debug)]
 pub struct Peer {
@@ -49,7 +51,7 @@ pub(crate) struct State {
 pub struct ConnectionRequest {
 	pub to: RemoteIdentity,
 	pub addrs: BTreeSet<PeerConnectionCandidate>,
-	pub tx: oneshot::Sender<Result<UnicastStream, String>>,
+	pub tx: oneshot::Sender<Result<UnicastStream, QuicTransportError>>,
 }
 
 // TODO: Maybe use this?
@@ -180,6 +182,7 @@ impl Peer {
 	}
 
 	/// Construct a new Quic stream to the peer.
+	#[instrument(name = "p2p_dial", skip(self), fields(peer = %self.identity))]
 	pub async fn new_stream(&self) -> Result<UnicastStream, NewStreamError> {
 		let (addrs, connect_tx) = {
 			let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
@@ -308,5 +311,5 @@ pub enum NewStreamError {
 	#[error("Failed to establish the connection w/ error: {0}")]
 	ConnectionNeverEstablished(oneshot::error::RecvError),
 	#[error("error connecting to peer: {0}")]
-	Connecting(String),
+	Connecting(QuicTransportError),
 }
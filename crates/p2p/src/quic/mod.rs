@@ -1,4 +1,4 @@
 pub(super) mod transport;
 pub(super) mod utils;
 
-pub use transport::{Libp2pPeerId, QuicTransport, RelayServerEntry};
+pub use transport::{Libp2pPeerId, QuicTransport, QuicTransportError, RelayServerEntry};
@@ -1,5 +1,7 @@
 use std::{
 	collections::HashMap,
+	error::Error,
+	io,
 	net::{Ipv4Addr, Ipv6Addr, SocketAddr},
 	str::FromStr,
 	sync::{Arc, Mutex, PoisonError, RwLock},
@@ -10,12 +12,15 @@ use flume::{bounded, Receiver, Sender};
 use libp2p::{
 	autonat, dcutr,
 	futures::{AsyncReadExt, AsyncWriteExt, StreamExt},
+	identity::DecodingError,
 	multiaddr::Protocol,
-	noise, relay,
+	noise, ping, relay,
 	swarm::{NetworkBehaviour, SwarmEvent},
-	yamux, Multiaddr, PeerId, StreamProtocol, Swarm, SwarmBuilder,
+	yamux, Multiaddr, PeerId, StreamProtocol, Swarm, SwarmBuilder, TransportError,
 };
+use libp2p_stream::OpenStreamError;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio::{
 	net::TcpListener,
 	sync::{mpsc, oneshot},
@@ -36,6 +41,42 @@ use crate::{
 
 const PROTOCOL: StreamProtocol = StreamProtocol::new("/sdp2p/1");
 
+#[derive(Debug, Error)]
+pub enum QuicTransportError {
+	#[error("failed to derive the libp2p keypair from the node identity: {0}")]
+	Keypair(#[from] DecodingError),
+	#[error("failed to build the libp2p swarm: {0}")]
+	Swarm(Box<dyn Error + Send + Sync>),
+	#[error("failed to bind to '{addr}': {source}")]
+	Bind {
+		addr: SocketAddr,
+		#[source]
+		source: io::Error,
+	},
+	#[error("'{0}' is already in use by another listener")]
+	AddrInUse(SocketAddr),
+	#[error("failed to listen on '{addr}': {source}")]
+	Listen {
+		addr: SocketAddr,
+		#[source]
+		source: TransportError<io::Error>,
+	},
+	#[error("failed to dial peer '{peer}': {source}")]
+	Dial {
+		peer: RemoteIdentity,
+		#[source]
+		source: OpenStreamError,
+	},
+	#[error("failed to send our identity to peer '{peer}': {source}")]
+	Handshake {
+		peer: RemoteIdentity,
+		#[source]
+		source: io::Error,
+	},
+	#[error("the event loop of the transport is offline")]
+	EventLoopOffline,
+}
+
 /// [libp2p::PeerId] for debugging purposes only.
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -47,16 +88,16 @@ enum InternalEvent {
 		id: ListenerId,
 		ipv4: bool,
 		addr: SocketAddr,
-		result: oneshot::Sender<Result<(), String>>,
+		result: oneshot::Sender<Result<(), QuicTransportError>>,
 	},
 	UnregisterListener {
 		id: ListenerId,
 		ipv4: bool,
-		result: oneshot::Sender<Result<(), String>>,
+		result: oneshot::Sender<Result<(), QuicTransportError>>,
 	},
 	RegisterRelays {
 		relays: Vec<RelayServerEntry>,
-		result: oneshot::Sender<Result<(), String>>,
+		result: oneshot::Sender<Result<(), QuicTransportError>>,
 	},
 }
 
@@ -76,6 +117,8 @@ struct MyBehaviour {
 	autonat: autonat::Behaviour,
 	// TODO: Can this be optional?
 	dcutr: dcutr::Behaviour,
+	/// Measures the round trip time of the connections for [`crate::Metrics`]
+	ping: ping::Behaviour,
 }
 
 /// Transport using Quic to establish a connection between peers.
@@ -91,9 +134,8 @@ pub struct QuicTransport {
 impl QuicTransport {
 	/// Spawn the `QuicTransport` and register it with the P2P system.
 	/// Be aware spawning this does nothing unless you call `Self::set_ipv4_enabled`/`Self::set_ipv6_enabled` to enable the listeners.
-	// TODO: Error type here
-	pub fn spawn(p2p: Arc<P2P>) -> Result<(Self, Libp2pPeerId), String> {
-		let keypair = identity_to_libp2p_keypair(p2p.identity());
+	pub fn spawn(p2p: Arc<P2P>) -> Result<(Self, Libp2pPeerId), QuicTransportError> {
+		let keypair = identity_to_libp2p_keypair(p2p.identity())?;
 		let libp2p_peer_id = Libp2pPeerId(keypair.public().to_peer_id());
 
 		let (tx, rx) = bounded(15);
@@ -108,14 +150,15 @@ impl QuicTransport {
 			.with_tokio()
 			.with_quic()
 			.with_relay_client(noise::Config::new, yamux::Config::default)
-			.map_err(|err| err.to_string())?
+			.map_err(|err| QuicTransportError::Swarm(err.into()))?
 			.with_behaviour(|keypair, relay_behaviour| MyBehaviour {
 				stream: libp2p_stream::Behaviour::new(),
 				relay: relay_behaviour,
 				autonat: autonat::Behaviour::new(keypair.public().to_peer_id(), Default::default()),
 				dcutr: dcutr::Behaviour::new(keypair.public().to_peer_id()),
+				ping: ping::Behaviour::default(),
 			})
-			.map_err(|err| err.to_string())?
+			.map_err(|err| QuicTransportError::Swarm(err.into()))?
 			.with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
 			.build();
 
@@ -163,7 +206,7 @@ impl QuicTransport {
 	}
 
 	// `None` on the port means disabled. Use `0` for random port.
-	pub async fn set_ipv4_enabled(&self, port: Option<u16>) -> Result<(), String> {
+	pub async fn set_ipv4_enabled(&self, port: Option<u16>) -> Result<(), QuicTransportError> {
 		self.setup_listener(
 			port.map(|p| SocketAddr::from((Ipv4Addr::UNSPECIFIED, p))),
 			true,
@@ -171,7 +214,7 @@ impl QuicTransport {
 		.await
 	}
 
-	pub async fn set_ipv6_enabled(&self, port: Option<u16>) -> Result<(), String> {
+	pub async fn set_ipv6_enabled(&self, port: Option<u16>) -> Result<(), QuicTransportError> {
 		self.setup_listener(
 			port.map(|p| SocketAddr::from((Ipv6Addr::UNSPECIFIED, p))),
 			false,
@@ -179,20 +222,21 @@ impl QuicTransport {
 		.await
 	}
 
-	// TODO: Proper error type
-	async fn setup_listener(&self, addr: Option<SocketAddr>, ipv4: bool) -> Result<(), String> {
+	async fn setup_listener(
+		&self,
+		addr: Option<SocketAddr>,
+		ipv4: bool,
+	) -> Result<(), QuicTransportError> {
 		let (tx, rx) = oneshot::channel();
 		let event = if let Some(mut addr) = addr {
 			if addr.port() == 0 {
-				#[allow(clippy::unwrap_used)] // TODO: Error handling
-				addr.set_port(
-					TcpListener::bind(addr)
-						.await
-						.unwrap()
-						.local_addr()
-						.unwrap()
-						.port(),
-				);
+				let port = TcpListener::bind(addr)
+					.await
+					.and_then(|listener| listener.local_addr())
+					.map_err(|source| QuicTransportError::Bind { addr, source })?
+					.port();
+
+				addr.set_port(port);
 			}
 
 			InternalEvent::RegisterListener {
@@ -210,10 +254,10 @@ impl QuicTransport {
 		};
 
 		let Ok(_) = self.internal_tx.send(event) else {
-			return Err("internal channel closed".to_string());
+			return Err(QuicTransportError::EventLoopOffline);
 		};
 		rx.await
-			.map_err(|_| "internal response channel closed".to_string())
+			.map_err(|_| QuicTransportError::EventLoopOffline)
 			.and_then(|r| r)
 	}
 
@@ -238,6 +282,8 @@ async fn start(
 	let mut incoming = control.accept(PROTOCOL).unwrap();
 	let map = Arc::new(RwLock::new(HashMap::new()));
 	let mut relay_config = Vec::new();
+	// The circuit listeners on each relay server, so they are removed when it's no longer configured
+	let mut relay_listeners = HashMap::new();
 
 	loop {
 		tokio::select! {
@@ -319,7 +365,8 @@ async fn start(
 					// TODO: Sync metadata
 					let metadata = HashMap::new();
 
-					let stream = UnicastStream::new(identity, stream.compat());
+					let stream = UnicastStream::new(identity, stream.compat())
+						.with_metrics(p2p.metrics().peer(identity));
 					let (shutdown_tx, shutdown_rx) = oneshot::channel();
 					p2p.connected_to(
 						id,
@@ -333,7 +380,8 @@ async fn start(
 					let _todo = shutdown_rx; // TODO: Handle `shutdown_rx`
 				});
 			},
-			event = swarm.select_next_some() => if let SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } = event {
+			event = swarm.select_next_some() => match event {
+				SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
 					let Some(identity) = map.write().unwrap_or_else(PoisonError::into_inner).remove(&peer_id) else {
 						warn!("Tried to remove a peer that wasn't in the map.");
 						continue;
@@ -346,25 +394,45 @@ async fn start(
 					};
 
 					peer.disconnected_from(id);
+				},
+				SwarmEvent::Behaviour(MyBehaviourEvent::Ping(ping::Event { peer, result: Ok(rtt), .. })) => {
+					// Only the peers we exchanged streams with are known by their identity
+					let identity = map.read().unwrap_or_else(PoisonError::into_inner).get(&peer).copied();
+					if let Some(identity) = identity {
+						p2p.metrics().set_rtt(identity, rtt);
+					}
+				},
+				_ => {},
 			},
 			Ok(event) = internal_rx.recv_async() => match event {
 				InternalEvent::RegisterListener { id, ipv4, addr, result } => {
+					let this = match ipv4 {
+						true => &mut ipv4_listener,
+						false => &mut ipv6_listener,
+					};
+
+					// Already listening on this address, so there is nothing to change
+					if matches!(this, Some((_, current_addr)) if *current_addr == addr) {
+						let _ = result.send(Ok(()));
+						continue;
+					}
+
 					match swarm.listen_on(socketaddr_to_quic_multiaddr(&addr)) {
 						Ok(libp2p_listener_id) => {
-							let this = match ipv4 {
-								true => &mut ipv4_listener,
-								false => &mut ipv6_listener,
-							};
-							// TODO: Diff the `addr` & if it's changed actually update it
-							if this.is_none() {
-								*this =  Some((libp2p_listener_id, addr));
-								p2p.register_listener_addr(id, addr);
+							// The listener of the previous address is only removed once the new one is up
+							if let Some((old_id, old_addr)) = this.replace((libp2p_listener_id, addr)) {
+								swarm.remove_listener(old_id);
+								p2p.unregister_listener_addr(id, old_addr);
 							}
+							p2p.register_listener_addr(id, addr);
 
 							let _ = result.send(Ok(()));
 						},
-						Err(e) => {
-							let _ = result.send(Err(e.to_string()));
+						Err(TransportError::Other(e)) if e.kind() == io::ErrorKind::AddrInUse => {
+							let _ = result.send(Err(QuicTransportError::AddrInUse(addr)));
+						},
+						Err(source) => {
+							let _ = result.send(Err(QuicTransportError::Listen { addr, source }));
 						},
 					}
 				},
@@ -381,17 +449,34 @@ async fn start(
 					let _ = result.send(Ok(()));
 				},
 				InternalEvent::RegisterRelays { relays, result } => {
-					// TODO: Replace any existing relays
 					// TODO: Only add some of the relays???
 
-					for relay in &relays {
-						let peer_id = match PeerId::from_str(&relay.peer_id) {
-							Ok(peer_id) => peer_id,
+					let relay_peer_ids = relays
+						.iter()
+						.filter_map(|relay| match PeerId::from_str(&relay.peer_id) {
+							Ok(peer_id) => Some((peer_id, relay)),
 							Err(err) => {
 								error!("Failed to parse Relay peer ID '{}': {err:?}", relay.peer_id);
-								continue;
+								None
 							},
-						};
+						})
+						.collect::<HashMap<_, _>>();
+
+					relay_listeners.retain(|peer_id, listener_id| {
+						if relay_peer_ids.contains_key(peer_id) {
+							return true;
+						}
+
+						swarm.behaviour_mut().autonat.remove_server(peer_id);
+						swarm.remove_listener(*listener_id);
+						false
+					});
+
+					for (peer_id, relay) in relay_peer_ids {
+						if relay_listeners.contains_key(&peer_id) {
+							continue;
+						}
+
 						let addrs = relay
 							.addrs
 							.iter()
@@ -413,7 +498,9 @@ async fn start(
 								.with(Protocol::P2p(peer_id))
 								.with(Protocol::P2pCircuit)
 						) {
-							Ok(_) => {},
+							Ok(listener_id) => {
+								relay_listeners.insert(peer_id, listener_id);
+							},
 							Err(e) => {
 								error!("Failed to listen on relay server '{}': {e}", relay.id);
 
@@ -431,6 +518,7 @@ async fn start(
 			Some(req) = connect_rx.recv() => {
 				let mut control = control.clone();
 				let self_remote_identity = p2p.identity().to_remote_identity();
+				let metrics = p2p.metrics().peer(req.to);
 				let map = map.clone();
 				let peer_id = remote_identity_to_libp2p_peerid(&req.to);
 				let addrs = get_addrs(peer_id, &relay_config, req.addrs.iter());
@@ -447,15 +535,21 @@ async fn start(
 							match stream.write_all(&self_remote_identity.get_bytes()).await {
 								Ok(_) => {
 									debug!("Established outbound stream with '{}'", req.to);
-									let _ = req.tx.send(Ok(UnicastStream::new(req.to, stream.compat())));
+									let _ = req.tx.send(Ok(UnicastStream::new(req.to, stream.compat()).with_metrics(metrics)));
 								},
-								Err(e) => {
-									let _ = req.tx.send(Err(e.to_string()));
+								Err(source) => {
+									let _ = req.tx.send(Err(QuicTransportError::Handshake {
+										peer: req.to,
+										source,
+									}));
 								},
 							}
 						},
-						Err(e) => {
-							let _ = req.tx.send(Err(e.to_string()));
+						Err(source) => {
+							let _ = req.tx.send(Err(QuicTransportError::Dial {
+								peer: req.to,
+								source,
+							}));
 						},
 					}
 				});
@@ -2,7 +2,11 @@
 
 use std::net::SocketAddr;
 
-use libp2p::{identity::Keypair, multiaddr::Protocol, Multiaddr, PeerId};
+use libp2p::{
+	identity::{DecodingError, Keypair},
+	multiaddr::Protocol,
+	Multiaddr, PeerId,
+};
 
 use crate::{Identity, RemoteIdentity};
 
@@ -31,8 +35,36 @@ pub fn remote_identity_to_libp2p_peerid(identity: &RemoteIdentity) -> PeerId {
 // This is sketchy, but it makes the whole system a lot easier to work with
 // We are assuming the libp2p `Keypair` is the same format as our `Identity` type.
 // This is *acktually* true but they reserve the right to change it at any point.
-#[must_use]
-pub fn identity_to_libp2p_keypair(identity: &Identity) -> Keypair {
+pub fn identity_to_libp2p_keypair(identity: &Identity) -> Result<Keypair, DecodingError> {
 	libp2p::identity::Keypair::ed25519_from_bytes(identity.to_bytes())
-		.expect("should be the same format")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Guards the assumption above, as the peer would get a new `PeerId` on every restart and
+	// couldn't be re-identified if the layouts diverged
+	#[test]
+	fn test_identity_to_libp2p_keypair_is_deterministic() {
+		let identity = Identity::new();
+		let restored = Identity::from_bytes(&identity.to_bytes()).unwrap();
+
+		let peer_id = identity_to_libp2p_keypair(&identity)
+			.unwrap()
+			.public()
+			.to_peer_id();
+
+		assert_eq!(
+			peer_id,
+			identity_to_libp2p_keypair(&restored)
+				.unwrap()
+				.public()
+				.to_peer_id()
+		);
+		assert_eq!(
+			peer_id,
+			remote_identity_to_libp2p_peerid(&identity.to_remote_identity())
+		);
+	}
 }
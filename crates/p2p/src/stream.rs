@@ -1,13 +1,17 @@
 use std::{
 	fmt, io,
 	pin::Pin,
+	sync::Arc,
 	task::{Context, Poll},
 };
 
 use sync_wrapper::SyncWrapper;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 
-use crate::RemoteIdentity;
+use crate::{
+	metrics::{PeerMetrics, StreamMetrics},
+	RemoteIdentity,
+};
 
 trait IoStream: AsyncRead + AsyncWrite {}
 impl<S: AsyncRead + AsyncWrite> IoStream for S {}
@@ -16,6 +20,7 @@ impl<S: AsyncRead + AsyncWrite> IoStream for S {}
 pub struct UnicastStream {
 	io: SyncWrapper<Pin<Box<dyn IoStream + Send>>>,
 	remote: RemoteIdentity,
+	metrics: Option<StreamMetrics>,
 }
 
 impl fmt::Debug for UnicastStream {
@@ -31,9 +36,17 @@ impl UnicastStream {
 		Self {
 			io: SyncWrapper::new(Box::pin(io)),
 			remote,
+			metrics: None,
 		}
 	}
 
+	/// Counts the bytes going through the stream in the [`crate::Metrics`] of its peer.
+	#[must_use]
+	pub(crate) fn with_metrics(mut self, metrics: Arc<PeerMetrics>) -> Self {
+		self.metrics = Some(StreamMetrics::new(metrics));
+		self
+	}
+
 	#[must_use]
 	pub fn remote_identity(&self) -> RemoteIdentity {
 		self.remote
@@ -50,9 +63,16 @@ impl AsyncRead for UnicastStream {
 		cx: &mut Context<'_>,
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().io)
-			.get_pin_mut()
-			.poll_read(cx, buf)
+		let this = self.get_mut();
+		let filled = buf.filled().len();
+
+		let poll = Pin::new(&mut this.io).get_pin_mut().poll_read(cx, buf);
+
+		if let (Poll::Ready(Ok(())), Some(metrics)) = (&poll, &this.metrics) {
+			metrics.0.received(buf.filled().len() - filled);
+		}
+
+		poll
 	}
 }
 
@@ -62,9 +82,15 @@ impl AsyncWrite for UnicastStream {
 		cx: &mut Context<'_>,
 		buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		Pin::new(&mut self.get_mut().io)
-			.get_pin_mut()
-			.poll_write(cx, buf)
+		let this = self.get_mut();
+
+		let poll = Pin::new(&mut this.io).get_pin_mut().poll_write(cx, buf);
+
+		if let (Poll::Ready(Ok(written)), Some(metrics)) = (&poll, &this.metrics) {
+			metrics.0.sent(*written);
+		}
+
+		poll
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -1,21 +1,27 @@
 use std::{
 	future::ready,
 	io::{self, ErrorKind},
-	path::PathBuf,
+	path::{Path, PathBuf},
 };
 
 use chrono::{DateTime, Utc};
 use futures_util::{Stream, StreamExt, TryFutureExt};
-use opendal::{Operator, Scheme};
+use opendal::{Metadata, Metakey, Operator, Scheme};
 use sd_core_file_path_helper::path_is_hidden;
-use sd_core_indexer_rules::{IndexerRule, RuleKind};
+use sd_core_indexer_rules::{rejected_by_entry_rules, IndexerRule, RuleKind};
 use sd_file_ext::{extensions::Extension, kind::ObjectKind};
 use serde::Serialize;
 use specta::Type;
+use tokio::sync::oneshot;
 
 use crate::stream::TaskStream;
 
-#[derive(Serialize, Type, Debug)]
+/// Entries processed at once, their metadata and kind are read while the listing goes on
+const LOCAL_CONCURRENCY: usize = 16;
+/// Backends like S3 or WebDAV take a round trip per request, so many more are run at once
+const REMOTE_CONCURRENCY: usize = 64;
+
+#[derive(Serialize, Type, Debug, Clone)]
 pub struct NonIndexedPathItem {
 	pub path: String,
 	pub name: String,
@@ -27,6 +33,18 @@ pub struct NonIndexedPathItem {
 	pub date_modified: DateTime<Utc>,
 	pub size_in_bytes_bytes: Vec<u8>,
 	pub hidden: bool,
+	/// Version of the contents of remote files given by the server, which changes with them
+	#[serde(skip)]
+	pub etag: Option<String>,
+}
+
+/// A page of a listing, for the servers like S3 whose folders can hold too many entries to list
+/// them all at once.
+#[derive(Debug, Clone)]
+pub struct ListingPage {
+	/// The entry the page starts after, from the end of the previous page
+	pub start_after: Option<String>,
+	pub max_entries: usize,
 }
 
 pub async fn ephemeral(
@@ -34,167 +52,75 @@ pub async fn ephemeral(
 	rules: Vec<IndexerRule>,
 	path: &str,
 ) -> opendal::Result<impl Stream<Item = io::Result<NonIndexedPathItem>>> {
+	ephemeral_page(opendal, rules, path, None)
+		.await
+		.map(|(stream, _)| stream)
+}
+
+/// Like [`ephemeral`], but only lists the entries of `page` if there is one, along with the entry
+/// the next page starts after, sent once the page is listed if there may be more entries.
+pub async fn ephemeral_page(
+	opendal: Operator,
+	rules: Vec<IndexerRule>,
+	path: &str,
+	page: Option<ListingPage>,
+) -> opendal::Result<(
+	impl Stream<Item = io::Result<NonIndexedPathItem>>,
+	oneshot::Receiver<Option<String>>,
+)> {
 	let is_fs = opendal.info().scheme() == Scheme::Fs;
 	let base_path = PathBuf::from(opendal.info().root());
-	let mut lister = opendal.lister(path).await?;
+	let mut lister = opendal.lister_with(path);
+	if !is_fs {
+		// Local files are read one by one instead. Only S3 lists the etags, the other servers would
+		// be asked for each file's
+		let metakey = Metakey::Mode | Metakey::ContentLength | Metakey::LastModified;
+		lister = lister.metakey(if opendal.info().scheme() == Scheme::S3 {
+			metakey | Metakey::Etag
+		} else {
+			metakey
+		});
+	}
+	if let Some(start_after) = page.as_ref().and_then(|page| page.start_after.as_deref()) {
+		lister = lister.start_after(start_after);
+	}
+	let lister = lister.await?;
+	let max_entries = page.map_or(usize::MAX, |page| page.max_entries);
+	let concurrency = if is_fs {
+		LOCAL_CONCURRENCY
+	} else {
+		REMOTE_CONCURRENCY
+	};
+	let (next_page_tx, next_page_rx) = oneshot::channel();
 
-	Ok(TaskStream::new(move |tx| async move {
+	let stream = TaskStream::new(move |tx| async move {
 		let rules = &*rules;
-		while let Some(entry) = lister.next().await {
-			let base_path = base_path.clone();
-			let result = ready(entry)
-				.map_err(|err| io::Error::new(ErrorKind::Other, format!("OpenDAL: {err:?}")))
-				.and_then(|entry| async move {
-					let path = base_path.join(entry.path());
-
-					let extension = (!path.is_dir())
-						.then(|| {
-							path.extension()
-								.and_then(|s| s.to_str().map(str::to_string))
-								.unwrap_or_default()
-						})
-						.unwrap_or_default();
-
-					// Only Windows supports normalised files without FS access.
-					// For now we only do normalisation for local files.
-					let (relative_path, name) = if is_fs {
-						crate::path::normalize_path(&path).map_err(|err| {
-							io::Error::new(
-								ErrorKind::Other,
-								format!("Error normalising path '{path:?}': {err:?}"),
-							)
-						})?
-					} else {
-						unreachable!();
-						// (
-						// 	path.file_stem()
-						// 		.and_then(|s| s.to_str().map(str::to_string))
-						// 		.ok_or_else(|| {
-						// 			io::Error::new(
-						// 				ErrorKind::Other,
-						// 				"error on file '{path:?}: non UTF-8",
-						// 			)
-						// 		})?
-						// 		.to_string(),
-						// 	path.to_str()
-						// 		.expect("non UTF-8 path - is unreachable")
-						// 		.to_string(),
-						// )
-					};
-
-					let kind = if entry.metadata().is_dir() {
-						ObjectKind::Folder
-					} else if is_fs {
-						Extension::resolve_conflicting(&path, false)
-							.await
-							.map(Into::into)
-							.unwrap_or(ObjectKind::Unknown)
-					} else {
-						// TODO: Determine kind of remote files - https://linear.app/spacedriveapp/issue/ENG-1718/fix-objectkind-of-remote-files
-						ObjectKind::Unknown
-					};
-
-					let name = (kind != ObjectKind::Folder)
-						.then(|| {
-							path.file_stem()
-								.and_then(|s| s.to_str().map(str::to_string))
-						})
-						.flatten()
-						.unwrap_or(name);
-
-					let mut path = path
-						.to_str()
-						.expect("comes from string so this is impossible")
-						.to_string();
-
-					// OpenDAL will *always* end in a `/` for directories, we strip it here so we can give the path to Tokio.
-					if path.ends_with('/') && path.len() > 1 {
-						path.pop();
-					}
-
-					let result = IndexerRule::apply_all(rules, &path).await.map_err(|err| {
-						io::Error::new(
-							ErrorKind::Other,
-							format!("Error running indexer rules on file '{path:?}': {err:?}"),
-						)
-					})?;
-
-					// No OS Protected and No Hidden rules, must always be from this kind, should panic otherwise
-					if result[&RuleKind::RejectFilesByGlob]
-						.iter()
-						.any(|reject| !reject)
-					{
-						return Ok(None); // Skip this file
-					};
-
-					// TODO: OpenDAL last modified time - https://linear.app/spacedriveapp/issue/ENG-1717/fix-modified-time
-					// TODO: OpenDAL hidden files - https://linear.app/spacedriveapp/issue/ENG-1720/fix-hidden-files
-					let (hidden, date_created, date_modified, size) = if is_fs {
-						let metadata = tokio::fs::metadata(&path).await.map_err(|err| {
-							io::Error::new(
-								ErrorKind::Other,
-								format!("Error getting metadata for '{path:?}': {err:?}"),
-							)
-						})?;
-
-						(
-							path_is_hidden(&path, &metadata),
-							metadata
-								.created()
-								.map_err(|err| {
-									io::Error::new(
-									ErrorKind::Other,
-									format!("Error determining created time for '{path:?}': {err:?}"),
-								)
-								})?
-								.into(),
-							metadata
-								.modified()
-								.map_err(|err| {
-									io::Error::new(
-									ErrorKind::Other,
-									format!("Error determining modified time for '{path:?}': {err:?}"),
-								)
-								})?
-								.into(),
-							metadata.len(),
-						)
-					} else {
-						(false, Default::default(), Default::default(), 0)
-					};
-
-					// TODO: Fix this - https://linear.app/spacedriveapp/issue/ENG-1725/fix-last-modified
-					#[allow(clippy::redundant_locals)]
-					let date_modified = date_modified;
-					// entry.metadata().last_modified().ok_or_else(|| {
-					// 	io::Error::new(
-					// 		ErrorKind::Other,
-					// 		format!("Error getting modified time for '{path:?}'"),
-					// 	)
-					// })?;
-
-					#[allow(clippy::redundant_locals)]
-					// TODO: Fix this - https://linear.app/spacedriveapp/issue/ENG-1726/fix-file-size
-					let size = size;
-
-					Ok(Some(NonIndexedPathItem {
-						path: relative_path,
-						name,
-						extension,
-						kind: kind as i32,
-						is_dir: kind == ObjectKind::Folder,
-						date_created,
-						date_modified,
-						// TODO
-						// entry
-						// 	.metadata()
-						// 	.content_length()
-						size_in_bytes_bytes: size.to_be_bytes().to_vec(),
-						hidden,
-					}))
-				})
-				.await;
+		let mut listed = 0;
+		let mut last_listed = None;
+
+		// The entries are sent as soon as they are ready, so not in the order they are listed
+		let mut results = lister
+			.take(max_entries)
+			.inspect(|entry| {
+				listed += 1;
+				if let Ok(entry) = entry {
+					last_listed = Some(entry.path().to_string());
+				}
+			})
+			.map(|entry| {
+				let base_path = base_path.clone();
+				ready(entry)
+					.map_err(|err| io::Error::new(ErrorKind::Other, format!("OpenDAL: {err:?}")))
+					.and_then(|entry| async move {
+						let path = base_path.join(entry.path());
 
+						// Local entries are read from the device, the metadata of remote ones was listed
+						entry_item(rules, path, (!is_fs).then(|| entry.metadata())).await
+					})
+			})
+			.buffer_unordered(concurrency);
+
+		while let Some(result) = results.next().await {
 			if tx
 				.send(match result {
 					Ok(Some(item)) => Ok(item),
@@ -208,5 +134,173 @@ pub async fn ephemeral(
 				continue;
 			}
 		}
+
+		drop(results);
+		next_page_tx
+			.send((listed == max_entries).then_some(last_listed).flatten())
+			.ok();
+	});
+
+	Ok((stream, next_page_rx))
+}
+
+/// Reads the entry of the local file or directory at `path` like [`ephemeral`] lists them, `None`
+/// if it's rejected by the `rules`. Used to update a listing with only the entries that changed.
+pub async fn ephemeral_entry(
+	rules: &[IndexerRule],
+	path: &Path,
+) -> io::Result<Option<NonIndexedPathItem>> {
+	entry_item(rules, path.to_path_buf(), None).await
+}
+
+/// The item of the entry at `path`, read from this device unless the `remote` server listed its
+/// metadata.
+async fn entry_item(
+	rules: &[IndexerRule],
+	path: PathBuf,
+	remote: Option<&Metadata>,
+) -> io::Result<Option<NonIndexedPathItem>> {
+	let is_fs = remote.is_none();
+
+	// Remote paths can't be checked on this device
+	let is_dir = remote.map_or_else(|| path.is_dir(), Metadata::is_dir);
+
+	let extension = (!is_dir)
+		.then(|| {
+			path.extension()
+				.and_then(|s| s.to_str().map(str::to_string))
+				.unwrap_or_default()
+		})
+		.unwrap_or_default();
+
+	// Only Windows supports normalised files without FS access.
+	// For now we only do normalisation for local files.
+	let (relative_path, name) = if is_fs {
+		crate::path::normalize_path(&path).map_err(|err| {
+			io::Error::new(
+				ErrorKind::Other,
+				format!("Error normalising path '{path:?}': {err:?}"),
+			)
+		})?
+	} else {
+		// The server already gives normalised paths, with a `/` after the ones
+		// of directories
+		let relative_path = path
+			.to_str()
+			.map(|path| path.trim_end_matches('/').to_string())
+			.ok_or_else(|| {
+				io::Error::new(
+					ErrorKind::InvalidInput,
+					format!("Found non-UTF-8 path '{path:?}'"),
+				)
+			})?;
+
+		let name = path
+			.file_name()
+			.and_then(|s| s.to_str().map(str::to_string))
+			.unwrap_or_default();
+
+		(relative_path, name)
+	};
+
+	let kind = if is_dir {
+		ObjectKind::Folder
+	} else if is_fs {
+		Extension::resolve_conflicting(&path, false)
+			.await
+			.map(Into::into)
+			.unwrap_or(ObjectKind::Unknown)
+	} else {
+		// TODO: Determine kind of remote files - https://linear.app/spacedriveapp/issue/ENG-1718/fix-objectkind-of-remote-files
+		ObjectKind::Unknown
+	};
+
+	let name = (kind != ObjectKind::Folder)
+		.then(|| {
+			path.file_stem()
+				.and_then(|s| s.to_str().map(str::to_string))
+		})
+		.flatten()
+		.unwrap_or(name);
+
+	let mut path = path
+		.to_str()
+		.expect("comes from string so this is impossible")
+		.to_string();
+
+	// OpenDAL will *always* end in a `/` for directories, we strip it here so we can give the path to Tokio.
+	if path.ends_with('/') && path.len() > 1 {
+		path.pop();
+	}
+
+	let result = IndexerRule::apply_all(rules, &path).await.map_err(|err| {
+		io::Error::new(
+			ErrorKind::Other,
+			format!("Error running indexer rules on file '{path:?}': {err:?}"),
+		)
+	})?;
+
+	// No OS Protected and No Hidden rules, must always be from this kind, should panic otherwise
+	if result[&RuleKind::RejectFilesByGlob]
+		.iter()
+		.any(|reject| !reject)
+		|| rejected_by_entry_rules(&result)
+	{
+		return Ok(None); // Skip this file
+	};
+
+	// TODO: OpenDAL hidden files - https://linear.app/spacedriveapp/issue/ENG-1720/fix-hidden-files
+	let (hidden, date_created, date_modified, size, etag) = if let Some(metadata) = remote {
+		(
+			name.starts_with('.'),
+			Default::default(),
+			metadata.last_modified().unwrap_or_default(),
+			metadata.content_length(),
+			metadata.etag().map(str::to_string),
+		)
+	} else {
+		let metadata = tokio::fs::metadata(&path).await.map_err(|err| {
+			io::Error::new(
+				ErrorKind::Other,
+				format!("Error getting metadata for '{path:?}': {err:?}"),
+			)
+		})?;
+
+		(
+			path_is_hidden(&path, &metadata),
+			metadata
+				.created()
+				.map_err(|err| {
+					io::Error::new(
+						ErrorKind::Other,
+						format!("Error determining created time for '{path:?}': {err:?}"),
+					)
+				})?
+				.into(),
+			metadata
+				.modified()
+				.map_err(|err| {
+					io::Error::new(
+						ErrorKind::Other,
+						format!("Error determining modified time for '{path:?}': {err:?}"),
+					)
+				})?
+				.into(),
+			metadata.len(),
+			None,
+		)
+	};
+
+	Ok(Some(NonIndexedPathItem {
+		path: relative_path,
+		name,
+		extension,
+		kind: kind as i32,
+		is_dir: kind == ObjectKind::Folder,
+		date_created,
+		date_modified,
+		size_in_bytes_bytes: size.to_be_bytes().to_vec(),
+		hidden,
+		etag,
 	}))
 }
@@ -237,8 +237,15 @@ export const SingleItemMetadata = ({ item }: { item: ExplorerItem }) => {
 		enabled: filePathData != null && readyToFetch
 	});
 
+	// Read along with the item when its search asked for it
+	const itemMediaData =
+		item.type === 'Path' || item.type === 'Object' ? item.media_data : undefined;
+
 	const filesMediaData = useLibraryQuery(['files.getMediaData', objectData?.id ?? -1], {
-		enabled: objectData?.kind === ObjectKindEnum.Image && readyToFetch
+		enabled:
+			itemMediaData === undefined &&
+			objectData?.kind === ObjectKindEnum.Image &&
+			readyToFetch
 	});
 
 	const ephemeralLocationMediaData = useBridgeQuery(
@@ -248,7 +255,10 @@ export const SingleItemMetadata = ({ item }: { item: ExplorerItem }) => {
 		}
 	);
 
-	const mediaData = filesMediaData ?? ephemeralLocationMediaData ?? null;
+	const mediaData =
+		itemMediaData !== undefined
+			? { data: itemMediaData }
+			: filesMediaData ?? ephemeralLocationMediaData ?? null;
 
 	const fullPath = queriedFullPath.data ?? ephemeralPathData?.path;
 
@@ -173,7 +173,7 @@ function LabelItemCount({ data }: { data: Extract<ExplorerItem, { type: 'Label'
 
 	return (
 		<div className="truncate rounded-md px-1.5 py-px text-center text-tiny text-ink-dull">
-			{t('item_with_count', { count: count.data })}
+			{t('item_with_count', { count: count.data.count })}
 		</div>
 	);
 }
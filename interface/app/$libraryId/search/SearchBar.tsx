@@ -2,7 +2,7 @@ import { useCallback, useEffect, useRef, useState } from 'react';
 import { useNavigate } from 'react-router';
 import { createSearchParams } from 'react-router-dom';
 import { useDebouncedCallback } from 'use-debounce';
-import { SearchFilterArgs } from '@sd/client';
+import { SearchFilterArgs, useLibraryMutation } from '@sd/client';
 import { Input, ModifierKeys, Shortcut } from '@sd/ui';
 import { useOperatingSystem } from '~/hooks';
 import { keybindForOs } from '~/util/keybinds';
@@ -22,6 +22,7 @@ export default ({ redirectToSearch, defaultFilters, defaultTarget }: Props) => {
 	const searchRef = useRef<HTMLInputElement>(null);
 	const navigate = useNavigate();
 	const searchStore = useSearchStore();
+	const recordSearch = useLibraryMutation('search.history.record');
 
 	const os = useOperatingSystem(true);
 	const keybind = keybindForOs(os);
@@ -99,6 +100,10 @@ export default ({ redirectToSearch, defaultFilters, defaultTarget }: Props) => {
 			onChange={(e) => {
 				updateValue(e.target.value);
 			}}
+			onKeyDown={(e) => {
+				// Only submitted searches go in the history, not each text typed on the way
+				if (e.key === 'Enter' && value) recordSearch.mutate(value);
+			}}
 			onBlur={() => {
 				if (search.rawSearch === '' && !searchStore.interactingWithSearchOptions) {
 					clearValue();
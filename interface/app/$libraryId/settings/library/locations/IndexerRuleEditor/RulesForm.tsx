@@ -21,7 +21,15 @@ const ruleKinds: UnionToTuple<RuleKind> = [
 	'AcceptFilesByGlob',
 	'RejectFilesByGlob',
 	'AcceptIfChildrenDirectoriesArePresent',
-	'RejectIfChildrenDirectoriesArePresent'
+	'RejectIfChildrenDirectoriesArePresent',
+	'AcceptFilesByExtension',
+	'RejectFilesByExtension',
+	'AcceptFilesBySize',
+	'RejectFilesBySize',
+	'AcceptFilesByAge',
+	'RejectFilesByAge',
+	'RejectIgnoredByGitIgnore',
+	'RejectIgnoredBySdIgnore'
 ];
 const ruleKindEnum = z.enum(ruleKinds);
 
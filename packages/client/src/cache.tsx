@@ -1,4 +1,4 @@
-import { useQueryClient } from '@tanstack/react-query';
+import { QueryClient, useQueryClient } from '@tanstack/react-query';
 import {
 	createContext,
 	PropsWithChildren,
@@ -11,7 +11,8 @@ import {
 } from 'react';
 import { proxy, snapshot, subscribe } from 'valtio';
 
-import { type CacheNode } from './core';
+import { type CacheKey, type CacheNode, type NormalisedDelta, type Reference } from './core';
+import { useBridgeSubscription } from './rspc';
 import { getPermits } from './rspc-cursed';
 
 declare global {
@@ -21,6 +22,8 @@ declare global {
 }
 
 type Store = ReturnType<typeof defaultStore>;
+// When the nodes with a TTL expire, by the key of their type and id
+type Expiries = Map<string, number>;
 type Context = ReturnType<typeof createCache>;
 export type NormalisedCache = ReturnType<typeof createCache>;
 
@@ -32,10 +35,13 @@ const Context = createContext<Context>(undefined!);
 
 export function createCache() {
 	const cache = proxy(defaultStore());
+	// Kept outside of the proxy so tracking them doesn't rerender every `useCache`
+	const expiries: Expiries = new Map();
 	return {
 		cache,
+		expiries,
 		withNodes(data: CacheNode[] | undefined, suffix?: string) {
-			updateNodes(cache, data, suffix);
+			updateNodes(cache, expiries, data, suffix);
 		},
 		withCache<T>(data: T | undefined, suffix?: string): UseCacheResult<T> {
 			return restore(cache, new Map(), data, suffix) as any;
@@ -64,6 +70,12 @@ export function CacheProvider({ cache, children }: PropsWithChildren<{ cache: No
 	}, []);
 
 	const queryClient = useQueryClient();
+
+	// Nodes the backend knows are gone, like deleted files, are dropped without refetching
+	useBridgeSubscription(['invalidation.evictions'], {
+		onData: (keys) => evict(cache, queryClient, keys)
+	});
+
 	useEffect(() => {
 		const interval = setInterval(() => {
 			const permits = getPermits();
@@ -72,9 +84,24 @@ export function CacheProvider({ cache, children }: PropsWithChildren<{ cache: No
 				return;
 			}
 
+			// Queries using expired nodes are refetched, which replaces the nodes.
+			// Until then the nodes are kept so the queries can still be restored.
+			const now = Date.now();
+			const expiredKeys = new StableSet<[string, string]>();
+			for (const [key, expiry] of cache.expiries) {
+				if (expiry <= now) expiredKeys.set.add(key);
+			}
+
 			const requiredKeys = new StableSet<[string, string]>();
 			for (const query of queryClient.getQueryCache().getAll()) {
-				if (query.state.data) scanDataForKeys(cache.cache, requiredKeys, query.state.data);
+				if (!query.state.data) continue;
+
+				const queryKeys = new StableSet<[string, string]>();
+				scanDataForKeys(cache.cache, queryKeys, query.state.data);
+				for (const key of queryKeys.set) requiredKeys.set.add(key);
+
+				if (expiredKeys.size !== 0 && [...queryKeys.set].some((k) => expiredKeys.set.has(k)))
+					queryClient.invalidateQueries({ queryKey: query.queryKey, exact: true });
 			}
 
 			const existingKeys = new StableSet<[string, string]>();
@@ -87,6 +114,7 @@ export function CacheProvider({ cache, children }: PropsWithChildren<{ cache: No
 				if (!requiredKeys.has([type, id])) {
 					// Yeet the imposter
 					delete cache.cache.nodes?.[type]?.[id];
+					cache.expiries.delete(JSON.stringify([type, id]));
 				}
 			}
 		}, 60 * 1000);
@@ -102,6 +130,99 @@ export function useCacheContext() {
 	return context;
 }
 
+function evict(cache: NormalisedCache, queryClient: QueryClient, keys: CacheKey[]) {
+	const evicted = new StableSet<[string, string]>();
+	for (const key of keys) evicted.add([key.__type, key.__id]);
+
+	// References within arrays are removed, like an item of a list. The others can't be without
+	// breaking the shape of the data, so their query is refetched and the node kept until then.
+	const kept = new StableSet<[string, string]>();
+	for (const query of queryClient.getQueryCache().getAll()) {
+		if (!query.state.data) continue;
+
+		const stuck = new StableSet<[string, string]>();
+		const data = withoutReferences(query.state.data, evicted, stuck);
+		if (data !== query.state.data) queryClient.setQueryData(query.queryKey, data);
+
+		if (stuck.size !== 0) {
+			for (const key of stuck.set) kept.set.add(key);
+			queryClient.invalidateQueries({ queryKey: query.queryKey, exact: true });
+		}
+	}
+
+	for (const [type, nodes] of Object.entries(cache.cache.nodes)) {
+		for (const [id, node] of Object.entries(nodes)) {
+			if (evicted.has([type, id])) continue;
+
+			const updated = withoutReferences(node, evicted, kept);
+			if (updated !== node) nodes[id] = updated as Record<string, unknown>;
+		}
+	}
+
+	// Streamed queries hold their data outside of React Query, so their references can't be checked
+	if (getPermits() !== 0) return;
+
+	for (const [type, id] of evicted.entries()) {
+		if (kept.has([type, id])) continue;
+
+		delete cache.cache.nodes?.[type]?.[id];
+		cache.expiries.delete(JSON.stringify([type, id]));
+	}
+}
+
+// Returns `item` without the references to `evicted` nodes within arrays, or `item` itself if it
+// had none. The references which couldn't be removed are added to `stuck`.
+function withoutReferences(
+	item: unknown,
+	evicted: StableSet<[string, string]>,
+	stuck: StableSet<[string, string]>
+): unknown {
+	if (Array.isArray(item)) {
+		let changed = false;
+		const result = [];
+		for (const v of item) {
+			if (isReference(v) && evicted.has([v.__type, v.__id])) {
+				changed = true;
+				continue;
+			}
+
+			const updated = withoutReferences(v, evicted, stuck);
+			if (updated !== v) changed = true;
+			result.push(updated);
+		}
+
+		return changed ? result : item;
+	} else if (item !== null && typeof item === 'object') {
+		if (isReference(item)) {
+			if (evicted.has([item.__type, item.__id])) stuck.add([item.__type, item.__id]);
+			return item;
+		}
+
+		let changed = false;
+		const result: Record<string, unknown> = {};
+		for (const [key, value] of Object.entries(item)) {
+			const updated = withoutReferences(value, evicted, stuck);
+			if (updated !== value) changed = true;
+			result[key] = updated;
+		}
+
+		return changed ? result : item;
+	}
+
+	return item;
+}
+
+function isReference(item: unknown): item is { __type: string; __id: string } {
+	return (
+		item !== null &&
+		typeof item === 'object' &&
+		'__type' in item &&
+		'__id' in item &&
+		typeof item.__type === 'string' &&
+		typeof item.__id === 'string'
+	);
+}
+
 function scanDataForKeys(cache: Store, keys: StableSet<[string, string]>, item: unknown) {
 	if (item === undefined || item === null) return;
 	if (Array.isArray(item)) {
@@ -171,7 +292,7 @@ export function useNodes(data: CacheNode[] | undefined) {
 
 	// `useMemo` instead of `useEffect` here is cursed but it needs to run before the `useMemo` in the `useCache` hook.
 	useMemo(() => {
-		updateNodes(cache.cache, data);
+		updateNodes(cache.cache, cache.expiries, data);
 	}, [cache, data]);
 }
 
@@ -186,7 +307,12 @@ export function useNormalisedCache() {
 	};
 }
 
-function updateNodes(cache: Store, data: CacheNode[] | undefined, suffix?: string) {
+function updateNodes(
+	cache: Store,
+	expiries: Expiries,
+	data: CacheNode[] | undefined,
+	suffix?: string
+) {
 	if (!data) return;
 
 	for (const item of data) {
@@ -198,6 +324,14 @@ function updateNodes(cache: Store, data: CacheNode[] | undefined, suffix?: strin
 		const copy = { ...item } as any;
 		delete copy.__type;
 		delete copy.__id;
+		delete copy.__ttl;
+
+		const expiryKey = JSON.stringify([ty, item.__id]);
+		if (typeof item.__ttl === 'number') {
+			expiries.set(expiryKey, Date.now() + item.__ttl * 1000);
+		} else {
+			expiries.delete(expiryKey);
+		}
 
 		const original = cache.nodes?.[ty]?.[item.__id];
 		specialMerge(copy, original);
@@ -233,6 +367,17 @@ function specialMerge(copy: Record<any, any>, original: unknown) {
 	}
 }
 
+// Applies a delta of a live query to the references of the results it had.
+export function applyDelta<T>(items: Reference<T>[], delta: NormalisedDelta<T>): Reference<T>[] {
+	const removed = new StableSet<[string, string]>();
+	for (const item of delta.removed) removed.add([item.__type, item.__id]);
+
+	const result = delta.reset ? [] : items.filter((item) => !removed.has([item.__type, item.__id]));
+	for (const { index, item } of delta.added) result.splice(index, 0, item);
+
+	return result;
+}
+
 export type UseCacheResult<T> = T extends (infer A)[]
 	? UseCacheResult<A>[]
 	: T extends object
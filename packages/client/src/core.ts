@@ -3,6 +3,7 @@
 
 export type Procedures = {
     queries: 
+        { key: "apiTokens.list", input: never, result: ApiToken[] } | 
         { key: "auth.me", input: never, result: { id: string; email: string } } | 
         { key: "backups.getAll", input: never, result: GetAll } | 
         { key: "buildInfo", input: never, result: BuildInfo } | 
@@ -10,53 +11,99 @@ export type Procedures = {
         { key: "cloud.library.get", input: LibraryArgs<null>, result: { id: string; uuid: string; name: string; instances: CloudInstance[]; ownerId: string } | null } | 
         { key: "cloud.library.list", input: never, result: CloudLibrary[] } | 
         { key: "cloud.locations.list", input: never, result: CloudLocation[] } | 
+        { key: "duplicates.list", input: LibraryArgs<ListDuplicatesArgs>, result: Duplicates } | 
         { key: "ephemeralFiles.getMediaData", input: string, result: ({ type: "Image" } & ImageMetadata) | ({ type: "Video" } & VideoMetadata) | ({ type: "Audio" } & AudioMetadata) | null } | 
         { key: "files.get", input: LibraryArgs<number>, result: { item: Reference<ObjectWithFilePaths2>; nodes: CacheNode[] } | null } | 
         { key: "files.getConvertibleImageExtensions", input: never, result: string[] } | 
         { key: "files.getMediaData", input: LibraryArgs<number>, result: MediaMetadata } | 
         { key: "files.getPath", input: LibraryArgs<number>, result: string | null } | 
+        { key: "hooks.invocations", input: LibraryArgs<string | null>, result: HookInvocation[] } | 
+        { key: "hooks.list", input: LibraryArgs<null>, result: ScriptHook[] } | 
         { key: "invalidation.test-invalidate", input: never, result: number } | 
+        { key: "jobs.checksumSettings", input: LibraryArgs<null>, result: ChecksumSettings } | 
+        { key: "jobs.concurrencyLimits", input: never, result: { [key in string]: number } } | 
+        { key: "jobs.history", input: LibraryArgs<JobHistoryArgs>, result: JobHistoryPage } | 
+        { key: "jobs.historyRetention", input: never, result: JobHistoryRetention } | 
         { key: "jobs.isActive", input: LibraryArgs<null>, result: boolean } | 
+        { key: "jobs.notificationSettings", input: LibraryArgs<null>, result: JobNotificationSettings } | 
+        { key: "jobs.offloadSettings", input: never, result: JobOffloadPreferences } | 
+        { key: "jobs.priorities", input: never, result: { [key in string]: JobPriority } } | 
         { key: "jobs.reports", input: LibraryArgs<null>, result: JobGroup[] } | 
+        { key: "jobs.resourceLimits", input: never, result: { [key in string]: JobResourceLimits } } | 
+        { key: "jobs.retryPolicies", input: never, result: { [key in string]: RetryPolicy } } | 
         { key: "labels.count", input: LibraryArgs<null>, result: number } | 
         { key: "labels.get", input: LibraryArgs<number>, result: { id: number; name: string; date_created: string | null; date_modified: string | null } | null } | 
         { key: "labels.getForObject", input: LibraryArgs<number>, result: Label[] } | 
         { key: "labels.getWithObjects", input: LibraryArgs<number[]>, result: { [key in number]: { date_created: string; object: { id: number } }[] } } | 
         { key: "labels.list", input: LibraryArgs<null>, result: Label[] } | 
         { key: "labels.listWithThumbnails", input: LibraryArgs<string>, result: ExplorerItem[] } | 
+        { key: "library.databaseSettings", input: LibraryArgs<null>, result: DatabaseSettingsProfiles } | 
         { key: "library.kindStatistics", input: LibraryArgs<null>, result: KindStatistics } | 
         { key: "library.list", input: never, result: NormalisedResults<LibraryConfigWrapped> } | 
         { key: "library.statistics", input: LibraryArgs<null>, result: StatisticsResponse } | 
+        { key: "library.statistics.kinds", input: LibraryArgs<KindStatisticsArgs>, result: KindStatistics } | 
         { key: "locations.get", input: LibraryArgs<number>, result: { item: Reference<Location>; nodes: CacheNode[] } | null } | 
         { key: "locations.getWithRules", input: LibraryArgs<number>, result: { item: Reference<LocationWithIndexerRule>; nodes: CacheNode[] } | null } | 
+        { key: "locations.indexer_rules.cacheStats", input: never, result: IndexerRulesCacheStats } | 
         { key: "locations.indexer_rules.get", input: LibraryArgs<number>, result: NormalisedResult<IndexerRule> } | 
         { key: "locations.indexer_rules.list", input: LibraryArgs<null>, result: NormalisedResults<IndexerRule> } | 
         { key: "locations.indexer_rules.listForLocation", input: LibraryArgs<number>, result: NormalisedResults<IndexerRule> } | 
-        { key: "locations.list", input: LibraryArgs<null>, result: NormalisedResults<Location> } | 
+        { key: "locations.ingestInbox", input: LibraryArgs<null>, result: IngestInbox | null } | 
+        { key: "locations.ingestSettings", input: LibraryArgs<null>, result: IngestSettings } | 
+        { key: "locations.list", input: LibraryArgs<CursorArgs<number> | null>, result: NormalisedPage<Location, number> } | 
         { key: "locations.systemLocations", input: never, result: SystemLocations } | 
+        { key: "locations.usageTree", input: LibraryArgs<UsageTreeArgs>, result: UsageTreeNode } | 
+        { key: "locations.validationReport", input: LibraryArgs<number>, result: ValidationReport } | 
+        { key: "mirrors.list", input: LibraryArgs<null>, result: LocationMirror[] } | 
         { key: "models.image_detection.list", input: never, result: string[] } | 
         { key: "nodeState", input: never, result: NodeState } | 
+        { key: "nodes.backgroundPolicy", input: never, result: BackgroundPolicyState } | 
         { key: "nodes.listLocations", input: LibraryArgs<string | null>, result: ExplorerItem[] } | 
         { key: "notifications.dismiss", input: NotificationId, result: null } | 
         { key: "notifications.dismissAll", input: never, result: null } | 
         { key: "notifications.get", input: never, result: Notification[] } | 
+        { key: "p2p.metrics", input: never, result: PeerMetrics[] } | 
+        { key: "p2p.pair.list", input: never, result: PairedNode[] } | 
         { key: "p2p.state", input: never, result: JsonValue } | 
+        { key: "plugins.list", input: never, result: PluginInfo[] } | 
+        { key: "plugins.pending", input: never, result: PluginManifest[] } | 
+        { key: "plugins.searchFilter", input: PluginSearchFilterArgs, result: SearchFilterArgs[] } | 
         { key: "preferences.get", input: LibraryArgs<null>, result: LibraryPreferences } | 
-        { key: "search.objects", input: LibraryArgs<ObjectSearchArgs>, result: SearchData<ExplorerItem> } | 
-        { key: "search.objectsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
-        { key: "search.paths", input: LibraryArgs<FilePathSearchArgs>, result: SearchData<ExplorerItem> } | 
-        { key: "search.pathsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
-        { key: "search.saved.get", input: LibraryArgs<number>, result: { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null } | null } | 
+        { key: "previews.pages", input: LibraryArgs<ThumbnailSource>, result: string[][] } | 
+        { key: "remoteAdmin.grants", input: never, result: RemoteAdminGrant[] } | 
+        { key: "remoteAdmin.jobs", input: RemoteIdentity, result: JobReport[] } | 
+        { key: "remoteAdmin.logs", input: LogsArgs, result: string[] } | 
+        { key: "remoteAdmin.settings", input: RemoteIdentity, result: RemoteNodeSettings } | 
+        { key: "search.history.list", input: LibraryArgs<number | null>, result: SearchHistory[] } | 
+        { key: "search.history.settings", input: LibraryArgs<null>, result: SearchHistorySettings } | 
+        { key: "search.names", input: LibraryArgs<NameSearchArgs>, result: SearchData<ExplorerItem, null> } | 
+        { key: "search.objects", input: LibraryArgs<ObjectSearchArgs>, result: SearchData<ExplorerItem, OrderAndPagination<number, ObjectOrder, ObjectCursor>> } | 
+        { key: "search.objectsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: CachedCount } | 
+        { key: "search.paths", input: LibraryArgs<FilePathSearchArgs>, result: SearchData<ExplorerItem, FilePathOrderAndPagination> } | 
+        { key: "search.pathsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: CachedCount } | 
+        { key: "search.saved.get", input: LibraryArgs<number>, result: { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null; is_live: boolean | null } | null } | 
         { key: "search.saved.list", input: LibraryArgs<null>, result: SavedSearch[] } | 
+        { key: "search.suggest", input: LibraryArgs<SuggestArgs>, result: SearchSuggestion[] } | 
         { key: "sync.enabled", input: LibraryArgs<null>, result: boolean } | 
         { key: "sync.messages", input: LibraryArgs<null>, result: CRDTOperation[] } | 
+        { key: "sync.status", input: LibraryArgs<null>, result: SyncStatus } | 
         { key: "tags.get", input: LibraryArgs<number>, result: { item: Reference<Tag>; nodes: CacheNode[] } | null } | 
         { key: "tags.getForObject", input: LibraryArgs<number>, result: NormalisedResults<Tag> } | 
         { key: "tags.getWithObjects", input: LibraryArgs<number[]>, result: { [key in number]: ({ date_created: string | null; object: { id: number } })[] } } | 
-        { key: "tags.list", input: LibraryArgs<null>, result: NormalisedResults<Tag> } | 
-        { key: "volumes.list", input: never, result: NormalisedResults<Volume> },
+        { key: "tags.list", input: LibraryArgs<CursorArgs<number> | null>, result: NormalisedPage<Tag, number> } | 
+        { key: "tags.rules.actions", input: LibraryArgs<TagRuleActionsArgs>, result: TagRuleActions } | 
+        { key: "tags.rules.list", input: LibraryArgs<number | null>, result: TagRule[] } | 
+        { key: "thumbnails.cacheStats", input: never, result: ThumbnailCacheStats } | 
+        { key: "thumbnails.request", input: LibraryArgs<RequestThumbnailArgs>, result: string[] } | 
+        { key: "thumbnails.settings", input: LibraryArgs<null>, result: ThumbnailSettings } | 
+        { key: "trash.list", input: LibraryArgs<null>, result: TrashedFile[] } | 
+        { key: "trash.settings", input: LibraryArgs<null>, result: TrashSettings } | 
+        { key: "volumes.list", input: never, result: NormalisedResults<Volume> }, | 
+        { key: "webhooks.list", input: LibraryArgs<null>, result: WebhookInfo[] }
     mutations: 
         { key: "api.sendFeedback", input: Feedback, result: null } | 
+        { key: "apiTokens.create", input: CreateApiTokenArgs, result: CreatedApiToken } | 
+        { key: "apiTokens.revoke", input: string, result: null } | 
         { key: "auth.logout", input: never, result: null } | 
         { key: "backups.backup", input: LibraryArgs<null>, result: string } | 
         { key: "backups.delete", input: string, result: null } | 
@@ -68,6 +115,7 @@ export type Procedures = {
         { key: "cloud.locations.remove", input: string, result: CloudLocation } | 
         { key: "cloud.locations.testing", input: TestingParams, result: null } | 
         { key: "cloud.setApiOrigin", input: string, result: null } | 
+        { key: "duplicates.find", input: LibraryArgs<OldDuplicateFinderJobInit>, result: null } | 
         { key: "ephemeralFiles.copyFiles", input: LibraryArgs<EphemeralFileSystemOps>, result: null } | 
         { key: "ephemeralFiles.createFile", input: LibraryArgs<CreateEphemeralFileArgs>, result: string } | 
         { key: "ephemeralFiles.createFolder", input: LibraryArgs<CreateEphemeralFolderArgs>, result: string } | 
@@ -75,77 +123,179 @@ export type Procedures = {
         { key: "ephemeralFiles.deleteFiles", input: LibraryArgs<string[]>, result: null } | 
         { key: "ephemeralFiles.moveToTrash", input: LibraryArgs<string[]>, result: null } | 
         { key: "ephemeralFiles.renameFile", input: LibraryArgs<EphemeralRenameFileArgs>, result: null } | 
+        { key: "files.bulkUpdate", input: LibraryArgs<BulkUpdateArgs>, result: number } | 
         { key: "files.convertImage", input: LibraryArgs<ConvertImageArgs>, result: null } | 
+        { key: "files.copy", input: LibraryArgs<FileOpArgs>, result: string } | 
         { key: "files.copyFiles", input: LibraryArgs<OldFileCopierJobInit>, result: null } | 
         { key: "files.createFile", input: LibraryArgs<CreateFileArgs>, result: string } | 
         { key: "files.createFolder", input: LibraryArgs<CreateFolderArgs>, result: string } | 
         { key: "files.cutFiles", input: LibraryArgs<OldFileCutterJobInit>, result: null } | 
+        { key: "files.delete", input: LibraryArgs<FileOpSource>, result: string } | 
         { key: "files.deleteFiles", input: LibraryArgs<OldFileDeleterJobInit>, result: null } | 
         { key: "files.eraseFiles", input: LibraryArgs<OldFileEraserJobInit>, result: null } | 
+        { key: "files.move", input: LibraryArgs<FileOpArgs>, result: string } | 
         { key: "files.moveToTrash", input: LibraryArgs<OldFileDeleterJobInit>, result: null } | 
         { key: "files.removeAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "files.renameFile", input: LibraryArgs<RenameFileArgs>, result: null } | 
         { key: "files.setFavorite", input: LibraryArgs<SetFavoriteArgs>, result: null } | 
         { key: "files.setNote", input: LibraryArgs<SetNoteArgs>, result: null } | 
+        { key: "files.setRating", input: LibraryArgs<SetRatingArgs>, result: null } | 
         { key: "files.updateAccessTime", input: LibraryArgs<number[]>, result: null } | 
+        { key: "hooks.create", input: LibraryArgs<CreateHookArgs>, result: string } | 
+        { key: "hooks.delete", input: LibraryArgs<string>, result: null } | 
+        { key: "hooks.setEnabled", input: boolean, result: null } | 
+        { key: "hooks.update", input: LibraryArgs<ScriptHook>, result: null } | 
         { key: "invalidation.test-invalidate-mutation", input: LibraryArgs<null>, result: null } | 
         { key: "jobs.cancel", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.clear", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.clearAll", input: LibraryArgs<null>, result: null } | 
+        { key: "jobs.exportXmp", input: LibraryArgs<ExportXmpArgs>, result: null } | 
         { key: "jobs.generateLabelsForLocation", input: LibraryArgs<GenerateLabelsForLocationArgs>, result: null } | 
         { key: "jobs.generateThumbsForLocation", input: LibraryArgs<GenerateThumbsForLocationArgs>, result: null } | 
         { key: "jobs.identifyUniqueFiles", input: LibraryArgs<IdentifyUniqueFilesArgs>, result: null } | 
+        { key: "jobs.importMetadata", input: LibraryArgs<ImportMetadataArgs>, result: null } | 
         { key: "jobs.objectValidator", input: LibraryArgs<ObjectValidatorArgs>, result: null } | 
         { key: "jobs.pause", input: LibraryArgs<string>, result: null } | 
+        { key: "jobs.pruneHistory", input: LibraryArgs<PruneHistoryArgs>, result: number } | 
         { key: "jobs.resume", input: LibraryArgs<string>, result: null } | 
+        { key: "jobs.setChecksumSettings", input: LibraryArgs<ChecksumSettings>, result: null } | 
+        { key: "jobs.setConcurrencyLimit", input: SetConcurrencyLimitArgs, result: null } | 
+        { key: "jobs.setHistoryRetention", input: JobHistoryRetention, result: null } | 
+        { key: "jobs.setNotificationSettings", input: LibraryArgs<JobNotificationSettings>, result: null } | 
+        { key: "jobs.setOffloadSettings", input: JobOffloadPreferences, result: null } | 
+        { key: "jobs.setPriority", input: SetPriorityArgs, result: null } | 
+        { key: "jobs.setResourceLimits", input: SetResourceLimitsArgs, result: null } | 
+        { key: "jobs.setRetryPolicy", input: SetRetryPolicyArgs, result: null } | 
         { key: "labels.delete", input: LibraryArgs<number>, result: null } | 
         { key: "library.create", input: CreateLibraryArgs, result: NormalisedResult<LibraryConfigWrapped> } | 
         { key: "library.delete", input: string, result: null } | 
         { key: "library.edit", input: EditLibraryArgs, result: null } | 
+        { key: "library.setDatabaseSettings", input: LibraryArgs<DatabaseSettings>, result: null } | 
         { key: "library.startActor", input: LibraryArgs<string>, result: null } | 
         { key: "library.stopActor", input: LibraryArgs<string>, result: null } | 
         { key: "library.vaccumDb", input: LibraryArgs<null>, result: null } | 
         { key: "locations.addLibrary", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
         { key: "locations.create", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
+        { key: "locations.createRemote", input: LibraryArgs<RemoteLocationCreateArgs>, result: number } | 
         { key: "locations.delete", input: LibraryArgs<number>, result: null } | 
         { key: "locations.fullRescan", input: LibraryArgs<FullRescanArgs>, result: null } | 
         { key: "locations.indexer_rules.create", input: LibraryArgs<IndexerRuleCreateArgs>, result: null } | 
         { key: "locations.indexer_rules.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "locations.indexer_rules.update", input: LibraryArgs<IndexerRuleUpdateArgs>, result: null } | 
+        { key: "locations.ingest", input: LibraryArgs<IngestArgs>, result: IngestedFile } | 
         { key: "locations.relink", input: LibraryArgs<string>, result: number } | 
+        { key: "locations.setIngestInbox", input: LibraryArgs<IngestInbox | null>, result: null } | 
+        { key: "locations.setIngestSettings", input: LibraryArgs<IngestSettings>, result: null } | 
+        { key: "locations.setValidationSchedule", input: LibraryArgs<SetValidationScheduleArgs>, result: null } | 
         { key: "locations.subPathRescan", input: LibraryArgs<RescanArgs>, result: null } | 
         { key: "locations.update", input: LibraryArgs<LocationUpdateArgs>, result: null } | 
+        { key: "locations.validate", input: LibraryArgs<OldLocationValidatorJobInit>, result: null } | 
+        { key: "mirrors.create", input: LibraryArgs<CreateMirrorArgs>, result: string } | 
+        { key: "mirrors.delete", input: LibraryArgs<string>, result: null } | 
+        { key: "mirrors.run", input: LibraryArgs<string>, result: null } | 
+        { key: "mirrors.update", input: LibraryArgs<LocationMirror>, result: null } | 
         { key: "nodes.edit", input: ChangeNodeNameArgs, result: null } | 
+        { key: "nodes.updateBackgroundPolicy", input: BackgroundPolicyPreferences, result: null } | 
+        { key: "nodes.updateSearchPreferences", input: UpdateSearchPreferences, result: null } | 
+        { key: "nodes.updateTelemetryPreferences", input: TelemetryPreferences, result: null } | 
         { key: "nodes.updateThumbnailerPreferences", input: UpdateThumbnailerPreferences, result: null } | 
         { key: "p2p.acceptSpacedrop", input: [string, string | null], result: null } | 
         { key: "p2p.cancelSpacedrop", input: string, result: null } | 
         { key: "p2p.debugConnect", input: RemoteIdentity, result: string } | 
+        { key: "p2p.pair.confirm", input: ConfirmPairingArgs, result: null } | 
+        { key: "p2p.pair.remove", input: RemoteIdentity, result: null } | 
+        { key: "p2p.pair.start", input: RemoteIdentity, result: PairingStarted } | 
         { key: "p2p.spacedrop", input: SpacedropArgs, result: string } | 
+        { key: "plugins.approve", input: ApprovePluginArgs, result: string[] } | 
+        { key: "plugins.reload", input: never, result: string[] } | 
+        { key: "plugins.revoke", input: string, result: string[] } | 
         { key: "preferences.update", input: LibraryArgs<LibraryPreferences>, result: null } | 
-        { key: "search.saved.create", input: LibraryArgs<{ name: string; target?: SearchTarget; search?: string | null; filters?: string | null; description?: string | null; icon?: string | null }>, result: null } | 
+        { key: "remoteAdmin.rescan", input: RemoteRescanArgs, result: null } | 
+        { key: "remoteAdmin.setGrant", input: SetGrantArgs, result: null } | 
+        { key: "remoteAdmin.updateSettings", input: UpdateSettingsArgs, result: null } | 
+        { key: "search.history.delete", input: LibraryArgs<number[] | null>, result: null } | 
+        { key: "search.history.record", input: LibraryArgs<string>, result: null } | 
+        { key: "search.history.setSettings", input: LibraryArgs<SearchHistorySettings>, result: null } | 
+        { key: "search.saved.create", input: LibraryArgs<{ name: string; target?: SearchTarget; search?: string | null; filters?: string | null; description?: string | null; icon?: string | null; 
+/**
+ * Re-evaluated as the file paths and objects change, see `search.saved.subscribe`
+ */
+is_live?: boolean }>, result: null } | 
         { key: "search.saved.delete", input: LibraryArgs<number>, result: null } | 
         { key: "search.saved.update", input: LibraryArgs<[number, Args]>, result: null } | 
         { key: "sync.enable", input: LibraryArgs<null>, result: null } | 
         { key: "tags.assign", input: LibraryArgs<{ targets: Target[]; tag_id: number; unassign: boolean }>, result: null } | 
         { key: "tags.create", input: LibraryArgs<TagCreateArgs>, result: Tag } | 
         { key: "tags.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "tags.reparent", input: LibraryArgs<TagReparentArgs>, result: null } | 
+        { key: "tags.rules.create", input: LibraryArgs<TagRuleCreateArgs>, result: TagRule } | 
+        { key: "tags.rules.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "tags.rules.update", input: LibraryArgs<TagRuleUpdateArgs>, result: null } | 
         { key: "tags.update", input: LibraryArgs<TagUpdateArgs>, result: null } | 
-        { key: "toggleFeatureFlag", input: BackendFeature, result: null },
+        { key: "thumbnails.clear", input: never, result: null } | 
+        { key: "thumbnails.setCacheBudget", input: number | null, result: null } | 
+        { key: "thumbnails.setSettings", input: LibraryArgs<ThumbnailSettings>, result: null } | 
+        { key: "toggleFeatureFlag", input: BackendFeature, result: null } | 
+        { key: "trash.empty", input: LibraryArgs<number[] | null>, result: null } | 
+        { key: "trash.restore", input: LibraryArgs<number[]>, result: null } | 
+        { key: "trash.setSettings", input: LibraryArgs<TrashSettings>, result: null }, | 
+        { key: "webhooks.create", input: LibraryArgs<CreateWebhookArgs>, result: string } | 
+        { key: "webhooks.delete", input: LibraryArgs<string>, result: null } | 
+        { key: "webhooks.update", input: LibraryArgs<UpdateWebhookArgs>, result: null }
     subscriptions: 
         { key: "auth.loginSession", input: never, result: Response } | 
+        { key: "files.opProgress", input: LibraryArgs<null>, result: FileOpProgressEvent } | 
+        { key: "invalidation.evictions", input: never, result: CacheKey[] } | 
         { key: "invalidation.listen", input: never, result: InvalidateOperationEvent[] } | 
+        { key: "jobs.itemProgress", input: LibraryArgs<string | null>, result: JobItemProgressEvent } | 
         { key: "jobs.newThumbnail", input: LibraryArgs<null>, result: string[] } | 
         { key: "jobs.progress", input: LibraryArgs<null>, result: JobProgressEvent } | 
+        { key: "jobs.progressSubscribe", input: LibraryArgs<string>, result: JobProgressEvent } | 
         { key: "library.actors", input: LibraryArgs<null>, result: { [key in string]: boolean } } | 
         { key: "locations.online", input: never, result: number[][] } | 
         { key: "locations.quickRescan", input: LibraryArgs<LightScanArgs>, result: null } | 
         { key: "notifications.listen", input: never, result: Notification } | 
         { key: "p2p.events", input: never, result: P2PEvent } | 
         { key: "search.ephemeralPaths", input: LibraryArgs<EphemeralPathSearchArgs>, result: EphemeralPathsResultItem } | 
+        { key: "search.ephemeralPathsLive", input: LibraryArgs<LiveEphemeralPathsArgs>, result: NormalisedDelta<ExplorerItem> } | 
+        { key: "search.explorer.stream", input: LibraryArgs<ExplorerStreamArgs>, result: ExplorerBatch } | 
+        { key: "search.pathsLive", input: LibraryArgs<LivePathsArgs>, result: NormalisedDelta<ExplorerItem> } | 
+        { key: "search.saved.subscribe", input: LibraryArgs<LiveSavedSearchArgs>, result: NormalisedDelta<ExplorerItem> } | 
+        { key: "search.pathsStreamed", input: LibraryArgs<StreamedPathsArgs>, result: StreamedPathsBatch } | 
         { key: "sync.active", input: LibraryArgs<null>, result: SyncStatus } | 
         { key: "sync.newMessage", input: LibraryArgs<null>, result: null }
 };
 
-export type Args = { search?: string | null; filters?: string | null; name?: string | null; icon?: string | null; description?: string | null }
+export type ApiToken = { id: string; name: string; scopes: ApiTokenScope[]; requestsPerMinute: number; createdAt: string; 
+/**
+ * Hex encoded blake3 hash of the secret
+ */
+hash: string }
+
+export type ApiTokenPreferences = { tokens?: ApiToken[] }
+
+export type ApiTokenScope = 
+/**
+ * Queries, and downloading files and thumbnails
+ */
+"read" | 
+/**
+ * Mutations, and anything else changing the node or its libraries
+ */
+"write" | 
+/**
+ * Managing the API tokens, and the rspc websocket which can call any procedure
+ */
+"admin"
+
+export type ApprovePluginArgs = { name: string; 
+/**
+ * The capabilities shown to the user by `plugins.pending`, so a plugin asking
+ * for more in the meantime isn't approved
+ */
+capabilities: PluginCapabilities }
+
+export type Args = { search?: string | null; filters?: string | null; name?: string | null; icon?: string | null; description?: string | null; is_live?: boolean | null }
 
 export type AudioMetadata = { duration: number | null; audio_codec: string | null }
 
@@ -156,20 +306,108 @@ export type AudioMetadata = { duration: number | null; audio_codec: string | nul
  */
 export type BackendFeature = "filesOverP2P" | "cloudSync"
 
+/**
+ * User settings deciding when heavy background work is held back.
+ */
+export type BackgroundPolicyPreferences = { 
+/**
+ * Pause every subsystem while running on battery below this percentage, `None` never pauses
+ */
+pauseBelowBatteryPercent?: number | null; 
+/**
+ * Run indexing and thumbnailing with a single thread while running on battery
+ */
+throttleOnBattery?: boolean; 
+/**
+ * Pause sync and transfers while connected to a metered network
+ */
+pauseOnMeteredNetwork?: boolean; meteredNetwork?: MeteredNetwork; 
+/**
+ * Pause every subsystem during these hours
+ */
+quietHours?: QuietHours | null }
+
+/**
+ * The outcome of the policy for each subsystem.
+ */
+export type BackgroundPolicyState = { indexing: WorkMode; thumbnailing: WorkMode; sync: WorkMode; transfers: WorkMode; 
+/**
+ * Why some subsystems aren't running normally, empty if all of them are
+ */
+reasons: PolicyReason[]; conditions: SystemConditions }
+
+export type Backoff = 
+/**
+ * Always wait the same amount of time between attempts
+ */
+{ type: "fixed"; delay_secs: number } | 
+/**
+ * Double the delay after each attempt, starting at `initial_delay_secs` and never
+ * going over `max_delay_secs`
+ */
+{ type: "exponential"; initial_delay_secs: number; max_delay_secs: number }
+
 export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string }) & { path: string }
 
+export type BatteryStatus = { percent: number; 
+/**
+ * The machine isn't plugged in
+ */
+discharging: boolean }
+
 export type BuildInfo = { version: string; commit: string }
 
+export type BulkUpdateArgs = { objectIds: number[]; assignTags?: number[]; unassignTags?: number[]; 
+/**
+ * From 1 to 5 stars, `null` removes the rating and a missing field leaves it untouched
+ */
+rating?: MaybeUndefined<number>; 
+/**
+ * `null` removes the note and a missing field leaves it untouched
+ */
+note?: MaybeUndefined<string>; favorite?: boolean | null }
+
 export type CRDTOperation = { instance: string; timestamp: number; model: number; record_id: JsonValue; data: CRDTOperationData }
 
 export type CRDTOperationData = "c" | { u: { field: string; value: JsonValue } } | "d"
 
-export type CacheNode = { __type: string; __id: string; "#node": any }
+/**
+ * Identifies a `CacheNode` without its data, used to evict it from the cache of the clients.
+ */
+export type CacheKey = { __type: string; __id: string }
+
+export type CacheNode = { __type: string; __id: string; 
+/**
+ * Seconds the node can be kept for
+ */
+__ttl?: number; "#node": any }
+
+export type CachedCount = { count: number; 
+/**
+ * The count may be outdated, the query is invalidated once it's counted again
+ */
+stale: boolean; 
+/**
+ * The count was estimated from the statistics of the database instead of counted
+ */
+approximate: boolean }
 
 export type CameraData = { device_make: string | null; device_model: string | null; color_space: string | null; color_profile: ColorProfile | null; focal_length: number | null; shutter_speed: number | null; flash: Flash | null; orientation: Orientation; lens_make: string | null; lens_model: string | null; bit_depth: number | null; red_eye: boolean | null; zoom: number | null; iso: number | null; software: string | null; serial_number: string | null; lens_serial_number: string | null; contrast: number | null; saturation: number | null; sharpness: number | null; composite: Composite | null }
 
 export type ChangeNodeNameArgs = { name: string | null; p2p_ipv4_port: Port | null; p2p_ipv6_port: Port | null; p2p_discovery: P2PDiscoveryState | null; image_labeler_version: string | null }
 
+/**
+ * How the full checksums of the files of a library are computed, stored in the library config.
+ */
+export type ChecksumSettings = { 
+/**
+ * Checksum the whole contents of the files after each scan of their location, so duplicates
+ * and synced file paths can be compared by it instead of only by their sampled cas id.
+ * 
+ * Off by default, as it reads every file of the location in full
+ */
+inBackground: boolean }
+
 export type CloudInstance = { id: string; uuid: string; identity: RemoteIdentity; nodeId: string; metadata: { [key in string]: string } }
 
 export type CloudLibrary = { id: string; uuid: string; name: string; instances: CloudInstance[]; ownerId: string }
@@ -200,12 +438,33 @@ export type Composite =
  * The method used for the connection with this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
  */
+/**
+ * What to do with a file whose target already exists.
+ */
+export type ConfirmPairingArgs = { id: string; accepted: boolean }
+
+export type ConflictResolution = 
+/**
+ * Leave both files as they are
+ */
+"skip" | "overwrite" | 
+/**
+ * Name the new file like `photo (1).jpg`
+ */
+"keepBoth" | 
+/**
+ * Stop the operation, leaving the files already processed
+ */
+"fail"
+
 export type ConnectionMethod = "Relay" | "Local" | "Disconnected"
 
 export type ConvertImageArgs = { location_id: number; file_path_id: number; delete_src: boolean; desired_extension: ConvertibleExtension; quality_percentage: number | null }
 
 export type ConvertibleExtension = "bmp" | "dib" | "ff" | "gif" | "ico" | "jpg" | "jpeg" | "png" | "pnm" | "qoi" | "tga" | "icb" | "vda" | "vst" | "tiff" | "tif" | "hif" | "heif" | "heifs" | "heic" | "heics" | "avif" | "avci" | "avcs" | "svg" | "svgz" | "pdf" | "webp"
 
+export type CreateApiTokenArgs = { name: string; scopes: ApiTokenScope[]; requestsPerMinute?: number | null }
+
 export type CreateEphemeralFileArgs = { path: string; context: EphemeralFileCreateContextTypes; name: string | null }
 
 export type CreateEphemeralFolderArgs = { path: string; name: string | null }
@@ -214,31 +473,129 @@ export type CreateFileArgs = { location_id: number; sub_path: string | null; nam
 
 export type CreateFolderArgs = { location_id: number; sub_path: string | null; name: string | null }
 
+export type CreateHookArgs = { name: string; command: string; events: WebhookEventFilter[]; timeoutSecs?: number | null }
+
 export type CreateLibraryArgs = { name: LibraryName; default_locations: DefaultLocations | null }
 
+export type CreateMirrorArgs = { locationId: number; remote: string; direction: MirrorDirection; bandwidthLimitKib?: number | null }
+
+export type CreateWebhookArgs = { url: string; secret?: string | null; events: WebhookEventFilter[]; maxRetries?: number | null }
+
+export type CreatedApiToken = { token: ApiToken; 
+/**
+ * Only returned here, the node keeps a hash of it
+ */
+secret: string }
+
+/**
+ * Arguments of the list procedures supporting cursor pagination.
+ */
+export type CursorArgs<C> = { 
+/**
+ * Amount of items in the page, up to [`MAX_TAKE`] which is also the default
+ */
+take?: number | null; 
+/**
+ * The `cursor` returned with the previous page, missing for the first page
+ */
+cursor?: C | null }
+
 export type CursorOrderItem<T> = { order: SortOrder; data: T }
 
+export type DatabaseSettings = { 
+/**
+ * Write-ahead logging, so the explorer can read while the indexer writes
+ */
+wal: boolean; 
+/**
+ * Pages written to the WAL before it's checkpointed back into the database, 0 to only
+ * checkpoint when the library is closed
+ */
+walAutocheckpoint: number; 
+/**
+ * Memory for the page cache of SQLite
+ */
+cacheSizeMib: number; 
+/**
+ * Part of the database file mapped into memory, 0 to read it with regular I/O
+ */
+mmapSizeMib: number; 
+/**
+ * How long a query waits for the database to be unlocked before failing
+ */
+busyTimeoutMs: number }
+
+export type DatabaseSettingsProfiles = { current: DatabaseSettings; default: DatabaseSettings; largeLibrary: DatabaseSettings }
+
 export type DefaultLocations = { desktop: boolean; documents: boolean; downloads: boolean; pictures: boolean; music: boolean; videos: boolean }
 
 /**
  * The method used for the discovery of this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
  */
+export type DeltaItem<T> = { index: number; item: Reference<T> }
+
 export type DiscoveryMethod = "Relay" | "Local"
 
 export type DiskType = "SSD" | "HDD" | "Removable"
 
 export type DoubleClickAction = "openFile" | "quickPreview"
 
+/**
+ * File paths which are copies of each other.
+ */
+export type DuplicateCluster = { casId: string; 
+/**
+ * Of each of the file paths
+ */
+sizeInBytes: string; 
+/**
+ * Freed by keeping a single file path of the cluster
+ */
+reclaimableBytes: string; 
+/**
+ * The full contents of the file paths were compared through their checksum, otherwise only
+ * the samples the cas id is made of were
+ */
+verified: boolean; filePaths: FilePath[] }
+
+export type Duplicates = { 
+/**
+ * The biggest savings first
+ */
+clusters: DuplicateCluster[]; 
+/**
+ * Of all the clusters, not only the ones in this page
+ */
+reclaimableBytes: string; 
+/**
+ * `None` on the last page
+ */
+cursor: DuplicatesCursor | null }
+
+/**
+ * The last cluster of a page, to list the ones after it
+ */
+export type DuplicatesCursor = { reclaimableBytes: string; casId: string; filePathId: number }
+
 export type EditLibraryArgs = { id: string; name: LibraryName | null; description: MaybeUndefined<string> }
 
 export type EphemeralFileCreateContextTypes = "empty" | "text"
 
 export type EphemeralFileSystemOps = { sources: string[]; target_dir: string }
 
-export type EphemeralPathSearchArgs = { from: PathFrom; path: string; withHiddenFiles: boolean }
+export type EphemeralPathSearchArgs = { from: PathFrom; path: string; withHiddenFiles: boolean; credentials?: RemoteCredentials | null; 
+/**
+ * To list the next page of a S3 bucket, from the `next_page` of the previous one
+ */
+startAfter?: string | null }
 
-export type EphemeralPathsResultItem = { entries: Reference<ExplorerItem>[]; errors: string[]; nodes: CacheNode[] }
+export type EphemeralPathsResultItem = { entries: Reference<ExplorerItem>[]; errors: string[]; nodes: CacheNode[]; 
+/**
+ * Only in the last item of a page of a S3 bucket with more entries, to give as the
+ * `startAfter` of the search listing the next page
+ */
+next_page: string | null }
 
 export type EphemeralRenameFileArgs = { kind: EphemeralRenameKind }
 
@@ -248,31 +605,162 @@ export type EphemeralRenameMany = { from_pattern: FromPattern; to_pattern: strin
 
 export type EphemeralRenameOne = { from_path: string; to: string }
 
-export type ExplorerItem = { type: "Path"; thumbnail: string[] | null; item: FilePathWithObject } | { type: "Object"; thumbnail: string[] | null; item: ObjectWithFilePaths } | { type: "Location"; item: Location } | { type: "NonIndexedPath"; thumbnail: string[] | null; item: NonIndexedPathItem } | { type: "SpacedropPeer"; item: PeerMetadata } | { type: "Label"; thumbnails: string[][]; item: LabelWithObjects }
+export type ExplorerBatch = { items: Reference<ExplorerItem>[]; nodes: CacheNode[]; errors: string[]; 
+/**
+ * Resumes the stream after this batch, missing on the last batch and for non indexed paths,
+ * which are always listed in full
+ */
+cursor: number | null }
+
+export type ExplorerItem = { type: "Path"; thumbnail: string[] | null; item: FilePathWithObject; 
+/**
+ * The media data of the object, only read when the search asks for it
+ */
+media_data?: MediaMetadata } | { type: "Object"; thumbnail: string[] | null; item: ObjectWithFilePaths; 
+/**
+ * The media data of the object, only read when the search asks for it
+ */
+media_data?: MediaMetadata } | { type: "Location"; item: Location } | { type: "NonIndexedPath"; thumbnail: string[] | null; item: NonIndexedPathItem } | { type: "SpacedropPeer"; item: PeerMetadata } | { type: "Label"; thumbnails: string[][]; item: LabelWithObjects }
 
 export type ExplorerLayout = "grid" | "list" | "media"
 
-export type ExplorerSettings<TOrder> = { layoutMode: ExplorerLayout | null; gridItemSize: number | null; gridGap: number | null; mediaColumns: number | null; mediaAspectSquare: boolean | null; mediaViewWithDescendants: boolean | null; openOnDoubleClick: DoubleClickAction | null; showBytesInGridView: boolean | null; colVisibility: { [key in string]: boolean } | null; colSizes: { [key in string]: number } | null; listViewIconSize: string | null; listViewTextSize: string | null; order?: TOrder | null; showHiddenFiles?: boolean }
+export type ExplorerSource = 
+/**
+ * The file paths matching the filters, like `search.paths`
+ */
+{ type: "paths"; order?: FilePathOrder | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean } | 
+/**
+ * The objects matching the filters, like `search.objects`
+ */
+{ type: "objects"; order?: ObjectOrder | null; filters?: SearchFilterArgs[] } | 
+/**
+ * The entries of a path outside of the locations, like `search.ephemeralPaths`
+ */
+{ type: "ephemeral"; path: string; withHiddenFiles: boolean } | 
+/**
+ * The results of the saved search with this id
+ */
+{ type: "savedSearch"; id: number }
+
+export type ExplorerStreamArgs = { source: ExplorerSource; 
+/**
+ * The `cursor` of the last batch received, to resume the stream after it
+ */
+cursor?: number | null }
+
+export type ExplorerSettings<TOrder> = { layoutMode: ExplorerLayout | null; gridItemSize: number | null; gridGap: number | null; mediaColumns: number | null; mediaAspectSquare: boolean | null; mediaViewWithDescendants: boolean | null; openOnDoubleClick: DoubleClickAction | null; showBytesInGridView: boolean | null; colVisibility: { [key in string]: boolean } | null; colSizes: { [key in string]: number } | null; listViewIconSize: string | null; listViewTextSize: string | null; order?: TOrder | null; showHiddenFiles?: boolean; 
+/**
+ * Added to the filters of the client by `search.paths` when it asks for the defaults of the view
+ */
+filters?: SearchFilterArgs[] | null; groupDirectories?: boolean | null }
+
+export type ExportXmpArgs = { id: number; path: string; naming?: XmpSidecarNaming }
 
 export type Feedback = { message: string; emoji: number }
 
 export type FileCreateContextTypes = "empty" | "text"
 
+export type FileOpArgs = { source: FileOpSource; target: FileOpTarget; conflict?: ConflictResolution }
+
+export type FileOpKind = "copy" | "move" | "delete"
+
+export type FileOpProgressEvent = { id: string; libraryId: string; kind: FileOpKind; completedFiles: number; totalFiles: number; 
+/**
+ * Of the files copied or moved so far
+ */
+completedBytes: string; 
+/**
+ * The path being processed
+ */
+current: string | null; errors: string[]; 
+/**
+ * Sent once, after every other event of the operation
+ */
+done: boolean }
+
+/**
+ * Where the files of `files.copy`, `files.move` and `files.delete` are.
+ */
+export type FileOpSource = 
+/**
+ * File paths of a location
+ */
+{ type: "indexed"; locationId: number; filePathIds: number[] } | 
+/**
+ * Paths outside of the locations, like the ones listed by `search.ephemeralPaths`
+ */
+{ type: "ephemeral"; from: PathFrom; credentials?: RemoteCredentials | null; paths: string[] }
+
+/**
+ * The folder the files of `files.copy` and `files.move` go into.
+ */
+export type FileOpTarget = 
+/**
+ * A folder of a location, by its path from the root of the location
+ */
+{ type: "location"; locationId: number; path: string } | { type: "ephemeral"; from: PathFrom; credentials?: RemoteCredentials | null; path: string }
+
 export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null }
 
 export type FilePathCursor = { isDir: boolean; variant: FilePathCursorVariant }
 
-export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string> } | { sizeInBytes: SortOrder } | { dateCreated: CursorOrderItem<string> } | { dateModified: CursorOrderItem<string> } | { dateIndexed: CursorOrderItem<string> } | { object: FilePathObjectCursor }
+export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string | null> } | 
+/**
+ * Only orders the file paths, as the database can't compare their sizes to go past the cursor,
+ * so they're read in a single page
+ */
+{ sizeInBytes: SortOrder } | { dateCreated: CursorOrderItem<string | null> } | { dateModified: CursorOrderItem<string | null> } | { dateIndexed: CursorOrderItem<string | null> } | { object: FilePathObjectCursor }
 
-export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean }
+export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | 
+/**
+ * A case insensitive regex matched against the names with their extension, like `^IMG_\\d{4}`
+ */
+{ nameRegex: string } | 
+/**
+ * A case insensitive glob matched against the whole names with their extension, like `*.torrent`
+ */
+{ nameGlob: string } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean }
 
-export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
+export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string | null> } | { kind: CursorOrderItem<number | null> }
 
 export type FilePathOrder = { field: "name"; value: SortOrder } | { field: "sizeInBytes"; value: SortOrder } | { field: "dateCreated"; value: SortOrder } | { field: "dateModified"; value: SortOrder } | { field: "dateIndexed"; value: SortOrder } | { field: "object"; value: ObjectOrder }
 
-export type FilePathSearchArgs = { take?: number | null; orderAndPagination?: OrderAndPagination<number, FilePathOrder, FilePathCursor> | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean }
+/**
+ * The orders and pagination shared with the objects, along with `Relevance` which puts the best
+ * matches first, ranked from the text of the `name` filter, how recently the file paths were
+ * modified and how often they're opened. Only the
+ * [`MAX_RANKED`](super::relevance::MAX_RANKED) most recently modified matches are ranked, along
+ * with the ones whose name starts with the text.
+ */
+export type FilePathOrderAndPagination = { orderOnly: FilePathOrder } | { offset: { offset: number; order: FilePathOrder | null } } | { cursor: { id: number; cursor: FilePathCursor } } | { relevance: { offset: number } }
+
+export type FilePathSearchArgs = { 
+/**
+ * Up to [`MAX_TAKE`], which is also the default
+ */
+take?: number | null; orderAndPagination?: FilePathOrderAndPagination | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean; 
+/**
+ * Also count the file paths matching the filters, like `search.pathsCount`
+ */
+withCount?: boolean; 
+/**
+ * Don't look for the thumbnails of the file paths, for views not showing them
+ */
+skipThumbnails?: boolean; 
+/**
+ * Also read the media data of the file paths, for views showing it like the inspector
+ */
+withMediaData?: boolean; 
+/**
+ * Read the next page in the background, for offset pages
+ */
+prefetch?: boolean; 
+/**
+ * Apply the default filters, order and grouping stored in the preferences of this view
+ */
+useDefaults?: ViewDefaults | null }
 
-export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null } | null }
+export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; rating: number | null; note: string | null; date_created: string | null; date_accessed: string | null; access_count: number | null } | null }
 
 export type Flash = { 
 /**
@@ -321,6 +809,12 @@ export type FlashMode =
 
 export type FromPattern = { pattern: string; replace_all: boolean }
 
+export type FtpCredentials = { 
+/**
+ * Like `ftp://example.com:21`
+ */
+endpoint: string; user?: string | null; password?: string | null }
+
 export type FullRescanArgs = { location_id: number; reidentify_objects: boolean }
 
 export type GenerateLabelsForLocationArgs = { id: number; path: string; regenerate?: boolean }
@@ -329,12 +823,62 @@ export type GenerateThumbsForLocationArgs = { id: number; path: string; regenera
 
 export type GetAll = { backups: Backup[]; directory: string }
 
+/**
+ * The area between two latitudes and two longitudes, in degrees. It crosses the antimeridian when
+ * `west` is greater than `east`.
+ */
+export type GpsBoundingBox = { north: number; south: number; east: number; west: number }
+
 export type HardwareModel = "Other" | "MacStudio" | "MacBookAir" | "MacBookPro" | "MacBook" | "MacMini" | "MacPro" | "IMac" | "IMacPro" | "IPad" | "IPhone" | "Simulator" | "Android"
 
+/**
+ * Log of a single run of a hook.
+ */
+export type HookInvocation = { id: string; hookId: string; event: string; startedAt: string; durationMs: number; 
+/**
+ * Missing if the hook didn't start, timed out or was killed by a signal
+ */
+exitCode: number | null; timedOut: boolean; 
+/**
+ * Why the hook couldn't be started
+ */
+error: string | null; stdout: string; stderr: string }
+
 export type IdentifyUniqueFilesArgs = { id: number; path: string }
 
 export type ImageMetadata = { resolution: Resolution; date_taken: MediaDate | null; location: MediaLocation | null; camera_data: CameraData; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null }
 
+export type ImportMetadataArgs = { id: number; source: ImportSource; source_root: string | null }
+
+/**
+ * Where the metadata is imported from.
+ */
+export type ImportSource = 
+/**
+ * The `digikam4.db` database of a digiKam collection
+ */
+{ type: "digiKam"; database: string } | 
+/**
+ * The `.ts` sidecar folders and file name tags TagSpaces left in the location
+ */
+{ type: "tagSpaces" } | 
+/**
+ * A file list or the run history exported as CSV by Everything
+ */
+{ type: "everything"; file: string; 
+/**
+ * Tag given to the files of a file list, defaulting to the name of the list
+ */
+tag?: string | null } | 
+/**
+ * An `mlocate.db` database or a list of paths given by `locate`
+ */
+{ type: "locate"; file: string; 
+/**
+ * Tag given to the files, defaulting to the name of the database or list
+ */
+tag?: string | null }
+
 export type InOrNotIn<T> = { in: T[] } | { notIn: T[] }
 
 export type IndexerRule = { id: number; pub_id: number[]; name: string | null; default: boolean | null; rules_per_kind: number[] | null; date_created: string | null; date_modified: string | null }
@@ -348,55 +892,347 @@ export type IndexerRule = { id: number; pub_id: number[]; name: string | null; d
  * 
  * In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
  * `parameters` field must be a vector of strings containing the names of the directories.
+ * 
+ * In case of `RuleKind::AcceptFilesByExtension` or `RuleKind::RejectFilesByExtension`, it will be a
+ * vector of extensions, with or without the leading dot.
+ * 
+ * In case of `RuleKind::AcceptFilesBySize` or `RuleKind::RejectFilesBySize`, it will be the minimum
+ * and maximum sizes in bytes, and for `RuleKind::AcceptFilesByAge` or `RuleKind::RejectFilesByAge`
+ * the minimum and maximum ages in seconds since the files were modified. An empty string leaves
+ * that end of the range open.
+ * 
+ * In case of `RuleKind::RejectIgnoredByGitIgnore` or `RuleKind::RejectIgnoredBySdIgnore` the
+ * `parameters` field is ignored.
  */
 export type IndexerRuleCreateArgs = { name: string; dry_run: boolean; rules: ([RuleKind, string[]])[] }
 
-export type InvalidateOperationEvent = { type: "single"; data: SingleInvalidateOperationEvent } | { type: "all" }
-
-export type JobGroup = { id: string; action: string | null; status: JobStatus; created_at: string; jobs: JobReport[] }
-
-export type JobProgressEvent = { id: string; library_id: string; task_count: number; completed_task_count: number; phase: string; message: string; estimated_completion: string }
-
-export type JobReport = { id: string; name: string; action: string | null; data: number[] | null; metadata: { [key in string]: JsonValue } | null; errors_text: string[]; created_at: string | null; started_at: string | null; completed_at: string | null; parent_id: string | null; status: JobStatus; task_count: number; completed_task_count: number; phase: string; message: string; estimated_completion: string }
-
-export type JobStatus = "Queued" | "Running" | "Completed" | "Canceled" | "Failed" | "Paused" | "CompletedWithErrors"
-
-export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
-
-export type KindStatistic = { kind: number; name: string; count: number; total_bytes: string }
-
-export type KindStatistics = { statistics: KindStatistic[] }
-
-export type Label = { id: number; name: string; date_created: string | null; date_modified: string | null }
+/**
+ * `IndexerRuleUpdateArgs` is the argument received from the client using rspc to change an
+ * existing indexer rule, with the same `rules` as [`IndexerRuleCreateArgs`] replacing all the
+ * current ones.
+ */
+export type IndexerRuleUpdateArgs = { id: number; name: string | null; rules: ([RuleKind, string[]])[] | null }
 
-export type LabelWithObjects = { id: number; name: string; date_created: string | null; date_modified: string | null; label_objects: { object: { id: number; file_paths: FilePath[] } }[] }
+export type IndexerRulesCacheStats = { 
+/**
+ * Rule sets reused without compiling them
+ */
+hits: number; 
+/**
+ * Rule sets compiled, as they weren't cached yet or their rules changed
+ */
+misses: number; cached_rule_sets: number; 
+/**
+ * Time spent compiling rule sets since the node started
+ */
+compile_time_ms: number }
 
+export type IngestArgs = { locationId: number; source: IngestSource; 
 /**
- * Can wrap a query argument to require it to contain a `library_id` and provide helpers for working with libraries.
+ * Name of the stored file, taken from the source if missing
  */
-export type LibraryArgs<T> = { library_id: string; arg: T }
+name?: string | null }
 
 /**
- * LibraryConfig holds the configuration for a specific library. This is stored as a '{uuid}.sdlibrary' file.
+ * A folder watched for new files, stored in the library config.
  */
-export type LibraryConfig = { 
+export type IngestInbox = { path: string; 
 /**
- * name is the display name of the library. This is used in the UI and is set by the user.
+ * Location the files are moved into
  */
-name: LibraryName; 
+locationId: number }
+
 /**
- * description is a user set description of the library. This is used in the UI and is set by the user.
+ * Limits of the ingested files, stored in the library config.
  */
-description: string | null; 
+export type IngestSettings = { 
 /**
- * id of the current instance so we know who this `.db` is. This can be looked up within the `Instance` table.
+ * Biggest file that can be copied, downloaded or uploaded, there's no limit if `None`
  */
-instance_id: number; 
+maxSizeMib: number | null }
+
+export type IngestSource = 
 /**
- * cloud_id is the ID of the cloud library this library is linked to.
- * If this is set we can assume the library is synced with the Cloud.
+ * A file on this node, which is copied and left untouched
+ */
+{ path: string } | 
+/**
+ * An `http` or `https` URL, which is downloaded
  */
-cloud_id?: string | null; generate_sync_operations?: boolean; version: LibraryConfigVersion }
+{ url: string }
+
+export type IngestedFile = { filePathId: number; objectId: number; objectPubId: string; 
+/**
+ * Where the file was stored, its name is changed if the location already had one with it
+ */
+path: string }
+
+export type InvalidateOperationEvent = { type: "single"; data: SingleInvalidateOperationEvent } | { type: "all" }
+
+/**
+ * Broad classes of errors a job can fail with, used by [`RetryPolicy`] to decide
+ * if a failed job is worth running again.
+ */
+export type JobErrorClass = 
+/**
+ * Errors coming from the database, usually transient (locked database, busy, etc)
+ */
+"database" | 
+/**
+ * Errors reading or writing files on disk
+ */
+"fileIO" | 
+/**
+ * The job didn't report any progress for too long
+ */
+"timeout" | 
+/**
+ * Errors specific to each job implementation
+ */
+"job" | 
+/**
+ * Internal errors that will most likely happen again, like corrupted job state
+ */
+"internal"
+
+export type JobGroup = { id: string; action: string | null; status: JobStatus; created_at: string; jobs: JobReport[] }
+
+export type JobHistoryArgs = { take?: number | null; cursor?: string | null; filters?: JobHistoryFilterArgs[] }
+
+/**
+ * A finished or running job as shown in the history, with its error details.
+ */
+export type JobHistoryEntry = ({ id: string; name: string; action: string | null; data: number[] | null; metadata: { [key in string]: JsonValue } | null; errors_text: string[]; 
+/**
+ * History of failed runs that were retried according to the job's retry policy
+ */
+retries: JobRetryAttempt[]; created_at: string | null; started_at: string | null; completed_at: string | null; parent_id: string | null; 
+/**
+ * The location this job acts upon, if any
+ */
+location_id: number | null; status: JobStatus; task_count: number; completed_task_count: number; phase: string; message: string; estimated_completion: string }) & { 
+/**
+ * Time between the job start and its completion, in seconds
+ */
+duration_secs: number | null; 
+/**
+ * The last error reported by the job, falling back to the error of its last retry
+ */
+last_error: string | null }
+
+export type JobHistoryFilterArgs = { name: InOrNotIn<string> } | { status: InOrNotIn<JobStatus> } | { location: InOrNotIn<number> } | { createdAt: Range<string> }
+
+export type JobHistoryPage = { items: JobHistoryEntry[]; cursor: string | null }
+
+/**
+ * How long finished jobs are kept in the history.
+ */
+export type JobHistoryRetention = { 
+/**
+ * Finished jobs older than this are removed, `None` keeps them forever
+ */
+maxAgeDays: number | null; 
+/**
+ * Only the most recent finished jobs are kept, `None` keeps all of them
+ */
+maxEntries: number | null }
+
+export type JobItemProgressEvent = ({ item: string; bytes_processed: string | null; bytes_total: string | null }) & { id: string; library_id: string; phase: string }
+
+/**
+ * Per library settings for notifying users when jobs are done, stored in the library config.
+ */
+export type JobNotificationSettings = { 
+/**
+ * Names of the jobs that trigger notifications, no job triggers them if empty
+ */
+jobNames?: string[]; onCompleted?: boolean; onFailed?: boolean; 
+/**
+ * Show a desktop notification on the client, webhooks get the `jobFinished` events of the
+ * library instead
+ */
+desktop?: boolean }
+
+/**
+ * Settings for running the heavy work of jobs on another node of the same library.
+ */
+export type JobOffloadPreferences = { 
+/**
+ * Run work offloaded by other nodes of our libraries
+ */
+accept?: boolean; 
+/**
+ * Node generating thumbnails for us, they are generated locally while it isn't connected
+ */
+thumbnailsTarget?: RemoteIdentity | null }
+
+/**
+ * Set by the user on a job to reorder the queue and to change how much of the machine it
+ * gets while it runs. It is kept for the whole chain of jobs, like the indexer followed by
+ * the file identifier and the media processor.
+ */
+export type JobPriority = 
+/**
+ * Runs on a single thread, and only starts once no other job is waiting
+ */
+"low" | "normal" | 
+/**
+ * Starts before any other waiting job
+ */
+"high"
+
+export type JobProgressEvent = { id: string; library_id: string; task_count: number; completed_task_count: number; phase: string; message: string; estimated_completion: string; 
+/**
+ * Tasks completed per second over the last few seconds, tasks are usually files
+ */
+items_per_sec: number; 
+/**
+ * Sum of the bytes of the items reported by the job
+ */
+bytes_processed: string; bytes_per_sec: number; 
+/**
+ * Time left at the current rate, `None` until the job completed some tasks in this phase
+ */
+estimated_remaining_secs: number | null; 
+/**
+ * Last item reported by the job, usually the path of a file
+ */
+current_path: string | null }
+
+export type JobReport = { id: string; name: string; action: string | null; data: number[] | null; metadata: { [key in string]: JsonValue } | null; errors_text: string[]; 
+/**
+ * History of failed runs that were retried according to the job's retry policy
+ */
+retries: JobRetryAttempt[]; created_at: string | null; started_at: string | null; completed_at: string | null; parent_id: string | null; 
+/**
+ * The location this job acts upon, if any
+ */
+location_id: number | null; status: JobStatus; task_count: number; completed_task_count: number; phase: string; message: string; estimated_completion: string }
+
+/**
+ * Caps on the resources a job type can use, to keep the machine responsive while it runs.
+ */
+export type JobResourceLimits = { 
+/**
+ * Maximum amount of files processed at the same time, `None` lets the job decide
+ */
+maxThreads: number | null; 
+/**
+ * Maximum disk read rate in KiB per second, `None` means unlimited
+ */
+maxIoKibPerSec: number | null }
+
+/**
+ * A failed run of a job that was retried, kept in the job report history.
+ */
+export type JobRetryAttempt = { attempt: number; error_class: JobErrorClass; error: string; failed_at: string; retry_at: string }
+
+export type JobStatus = "Queued" | "Running" | "Completed" | "Canceled" | "Failed" | "Paused" | "CompletedWithErrors"
+
+export type JobsPreferences = { 
+/**
+ * Retry policies keyed by job name, jobs without an entry here are never retried
+ */
+retry_policies?: { [key in string]: RetryPolicy }; history_retention?: JobHistoryRetention; 
+/**
+ * Resource caps keyed by job name, jobs without an entry here run unrestricted
+ */
+resource_limits?: { [key in string]: JobResourceLimits }; 
+/**
+ * Maximum amount of jobs of each type running at the same time, keyed by job name
+ */
+concurrency_limits?: { [key in string]: number }; offload?: JobOffloadPreferences }
+
+export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
+
+export type KindStatistic = { kind: number; name: string; 
+/**
+ * Number of objects of the kind
+ */
+count: number; 
+/**
+ * Size of every file of the kind, so the copies of an object are all counted
+ */
+total_bytes: string }
+
+export type KindStatistics = { statistics: KindStatistic[] }
+
+export type KindStatisticsArgs = { locationId?: number | null; filters?: SearchFilterArgs[] }
+
+export type Label = { id: number; name: string; date_created: string | null; date_modified: string | null }
+
+export type LabelWithObjects = { id: number; name: string; date_created: string | null; date_modified: string | null; label_objects: { object: { id: number; file_paths: FilePath[] } }[] }
+
+/**
+ * Can wrap a query argument to require it to contain a `library_id` and provide helpers for working with libraries.
+ */
+export type LibraryArgs<T> = { library_id: string; arg: T }
+
+/**
+ * LibraryConfig holds the configuration for a specific library. This is stored as a '{uuid}.sdlibrary' file.
+ */
+export type LibraryConfig = { 
+/**
+ * name is the display name of the library. This is used in the UI and is set by the user.
+ */
+name: LibraryName; 
+/**
+ * description is a user set description of the library. This is used in the UI and is set by the user.
+ */
+description: string | null; 
+/**
+ * id of the current instance so we know who this `.db` is. This can be looked up within the `Instance` table.
+ */
+instance_id: number; 
+/**
+ * cloud_id is the ID of the cloud library this library is linked to.
+ * If this is set we can assume the library is synced with the Cloud.
+ */
+cloud_id?: string | null; generate_sync_operations?: boolean; 
+/**
+ * Which finished jobs should notify the user, and how
+ */
+job_notifications?: JobNotificationSettings; 
+/**
+ * Webhooks notified about events of the library
+ */
+webhooks?: Webhook[]; 
+/**
+ * Shell commands run by the node on events of the library
+ */
+hooks?: ScriptHook[]; 
+/**
+ * Locations mirrored to or from rclone remotes
+ */
+mirrors?: LocationMirror[]; 
+/**
+ * Folder whose files are moved into a location and indexed as soon as they appear
+ */
+ingest_inbox?: IngestInbox | null; 
+/**
+ * How big the files ingested into the locations can be
+ */
+ingest?: IngestSettings; 
+/**
+ * How long the files deleted from the locations stay in their trash
+ */
+trash?: TrashSettings; 
+/**
+ * SQLite settings of the database of the library
+ */
+database?: DatabaseSettings; 
+/**
+ * Whether the searches run in the library are recorded to suggest them again
+ */
+search_history?: SearchHistorySettings; 
+/**
+ * How the thumbnails of the library's files are generated
+ */
+thumbnails?: ThumbnailSettings; 
+/**
+ * Whether the files are checksummed in the background, after being identified by their cas id
+ */
+checksums?: ChecksumSettings; version: LibraryConfigVersion }
 
 export type LibraryConfigVersion = "V0" | "V1" | "V2" | "V3" | "V4" | "V5" | "V6" | "V7" | "V8" | "V9" | "V10"
 
@@ -404,13 +1240,33 @@ export type LibraryConfigWrapped = { uuid: string; instance_id: string; instance
 
 export type LibraryName = string
 
-export type LibraryPreferences = { location?: { [key in string]: LocationSettings }; tag?: { [key in string]: TagSettings } }
+export type LibraryPreferences = { location?: { [key in string]: LocationSettings }; tag?: { [key in string]: TagSettings }; savedSearch?: { [key in string]: SavedSearchSettings } }
 
 export type LightScanArgs = { location_id: number; sub_path: string }
 
+export type ListDuplicatesArgs = { 
+/**
+ * Only the clusters with a file path in this location
+ */
+locationId?: number | null; 
+/**
+ * Only the clusters whose contents were compared by `duplicates.find`
+ */
+verifiedOnly?: boolean; 
+/**
+ * Up to [`MAX_TAKE`], which is also the default
+ */
+take?: number | null; cursor?: DuplicatesCursor | null }
+
 export type Listener2 = { id: string; name: string; addrs: string[] }
 
-export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; scan_state: number; instance_id: number | null }
+export type LiveEphemeralPathsArgs = { path: string; withHiddenFiles: boolean }
+
+export type LivePathsArgs = { take?: number | null; order?: FilePathOrder | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean }
+
+export type LiveSavedSearchArgs = { id: number; take?: number | null }
+
+export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; scan_state: number; instance_id: number | null; remote: string | null; validation_interval_days: number | null; date_validated: string | null }
 
 /**
  * `LocationCreateArgs` is the argument received from the client using `rspc` to create a new location.
@@ -419,6 +1275,19 @@ export type Location = { id: number; pub_id: number[]; name: string | null; path
  */
 export type LocationCreateArgs = { path: string; dry_run: boolean; indexer_rules_ids: number[] }
 
+/**
+ * A mirror of a location, stored in the library config.
+ */
+export type LocationMirror = { id: string; locationId: number; 
+/**
+ * rclone remote and path, like `gdrive:Photos`, set up with `rclone config`
+ */
+remote: string; direction: MirrorDirection; 
+/**
+ * In KiB/s, unlimited if missing
+ */
+bandwidthLimitKib?: number | null }
+
 export type LocationSettings = { explorer: ExplorerSettings<FilePathOrder> }
 
 /**
@@ -433,8 +1302,17 @@ export type LocationUpdateArgs = { id: number; name: string | null; generate_pre
 
 export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: Reference<IndexerRule>[] }
 
+export type LogsArgs = { identity: RemoteIdentity; lines?: number | null }
+
 export type MaybeUndefined<T> = null | T
 
+export type MediaDataFilterArgs = { capturedAt: Range<string> } | { cameraModel: TextMatch } | { location: GpsBoundingBox } | 
+/**
+ * Taken at most `meters` away from the point at `lat` and `lon`, in degrees, as well as some
+ * points just outside of the circle
+ */
+{ withinRadius: { lat: number; lon: number; meters: number } }
+
 export type MediaDataOrder = { field: "epochTime"; value: SortOrder }
 
 /**
@@ -447,7 +1325,30 @@ export type MediaLocation = { latitude: number; longitude: number; pluscode: Plu
 
 export type MediaMetadata = ({ type: "Image" } & ImageMetadata) | ({ type: "Video" } & VideoMetadata) | ({ type: "Audio" } & AudioMetadata)
 
-export type NodePreferences = { thumbnailer: ThumbnailerPreferences }
+/**
+ * Whether the current network is metered, detection is only available on Linux through
+ * NetworkManager, so other platforms must set it by hand.
+ */
+export type MeteredNetwork = "detect" | "metered" | "unmetered"
+
+export type MirrorDirection = 
+/**
+ * Makes the remote a copy of the location
+ */
+"push" | 
+/**
+ * Makes the location a copy of the remote, indexing it afterwards
+ */
+"pull"
+
+/**
+ * Why the contents of a file didn't match the ones it was indexed with, when validating its location
+ */
+export type MismatchKind = "corrupted" | "modified" | "missing"
+
+export type NameSearchArgs = { query: string; take?: number | null }
+
+export type NodePreferences = { thumbnailer: ThumbnailerPreferences; jobs?: JobsPreferences; telemetry?: TelemetryPreferences; remote_admin?: RemoteAdminPreferences; background_policy?: BackgroundPolicyPreferences; api_tokens?: ApiTokenPreferences; search?: SearchPreferences; pairing?: PairingPreferences; script_hooks?: ScriptHookPreferences; plugins?: PluginPreferences }
 
 export type NodeState = ({ 
 /**
@@ -461,6 +1362,36 @@ name: string; identity: RemoteIdentity; p2p_ipv4_port: Port; p2p_ipv6_port: Port
 
 export type NonIndexedPathItem = { path: string; name: string; extension: string; kind: number; is_dir: boolean; date_created: string; date_modified: string; size_in_bytes_bytes: number[]; hidden: boolean }
 
+/**
+ * The changes to the results of a query since they were last sent, so the client can patch the
+ * results it has instead of fetching them again.
+ * 
+ * The client applies it by removing the `removed` items, then inserting the `added` items at
+ * their `index`, in the order they come.
+ */
+export type NormalisedDelta<T> = { 
+/**
+ * Set on the first delta, which holds all of the results, so the client drops the ones it had
+ */
+reset: boolean; removed: Reference<T>[]; 
+/**
+ * Items new to the results, or moved within them
+ */
+added: DeltaItem<T>[]; 
+/**
+ * Nodes of the new items and of the items whose data changed
+ */
+nodes: CacheNode[] }
+
+/**
+ * A page of normalised items, with the cursor of the next page.
+ */
+export type NormalisedPage<T, C> = { items: Reference<T>[]; nodes: CacheNode[]; 
+/**
+ * Missing on the last page
+ */
+cursor: C | null }
+
 /**
  * A type that can be used to return a group of `Reference<T>` and `CacheNode`'s
  * 
@@ -490,32 +1421,69 @@ export type NotificationId = { type: "library"; id: [string, number] } | { type:
 
 export type NotificationKind = "info" | "success" | "error" | "warning"
 
-export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null }
+export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; rating: number | null; note: string | null; date_created: string | null; date_accessed: string | null; access_count: number | null }
 
-export type ObjectCursor = "none" | { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
+export type ObjectCursor = "none" | { dateAccessed: CursorOrderItem<string | null> } | { kind: CursorOrderItem<number | null> }
 
-export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: InOrNotIn<number> } | { labels: InOrNotIn<number> } | { dateAccessed: Range<string> }
+export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: InOrNotIn<number> } | 
+/**
+ * Like `Tags`, also matching the tags nested under them
+ */
+{ tagAnyDescendant: InOrNotIn<number> } | { labels: InOrNotIn<number> } | { dateAccessed: Range<string> } | { pluginMetadata: PluginMetadataFilter } | { mediaData: MediaDataFilterArgs }
 
 export type ObjectHiddenFilter = "exclude" | "include"
 
 export type ObjectOrder = { field: "dateAccessed"; value: SortOrder } | { field: "kind"; value: SortOrder } | { field: "mediaData"; value: MediaDataOrder }
 
-export type ObjectSearchArgs = { take: number; orderAndPagination?: OrderAndPagination<number, ObjectOrder, ObjectCursor> | null; filters?: SearchFilterArgs[] }
+export type ObjectSearchArgs = { 
+/**
+ * Up to the `max_objects_take` search preference of the node, which is also the default
+ */
+take?: number | null; orderAndPagination?: OrderAndPagination<number, ObjectOrder, ObjectCursor> | null; filters?: SearchFilterArgs[]; 
+/**
+ * Also count the objects matching the filters, like `search.objectsCount`
+ */
+withCount?: boolean; 
+/**
+ * Don't look for the thumbnails of the objects, for views not showing them
+ */
+skipThumbnails?: boolean; 
+/**
+ * Also read the media data of the objects, for views showing it like the inspector
+ */
+withMediaData?: boolean; 
+/**
+ * Read the next page in the background, for offset pages
+ */
+prefetch?: boolean }
 
 export type ObjectValidatorArgs = { id: number; path: string }
 
-export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: FilePath[] }
+export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; rating: number | null; note: string | null; date_created: string | null; date_accessed: string | null; access_count: number | null; file_paths: FilePath[] }
 
-export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: Reference<FilePath>[] }
+export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; rating: number | null; note: string | null; date_created: string | null; date_accessed: string | null; access_count: number | null; file_paths: Reference<FilePath>[] }
+
+export type OldDuplicateFinderJobInit = { locationId: number; 
+/**
+ * Checksum the whole contents of the file paths of the location which have duplicates, as
+ * their cas id only hashes samples of the big files
+ */
+verify: boolean }
 
 export type OldFileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
 
 export type OldFileCutterJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
 
-export type OldFileDeleterJobInit = { location_id: number; file_path_ids: number[] }
+export type OldFileDeleterJobInit = { location_id: number; file_path_ids: number[]; 
+/**
+ * Remove the files right away, instead of moving them into the trash of the location
+ */
+permanent?: boolean }
 
 export type OldFileEraserJobInit = { location_id: number; file_path_ids: number[]; passes: string }
 
+export type OldLocationValidatorJobInit = { locationId: number }
+
 /**
  * Represents the operating system which the remote peer is running.
  * This is not used internally and predominantly is designed to be used for display purposes by the embedding application.
@@ -528,37 +1496,249 @@ export type Orientation = "Normal" | "CW90" | "CW180" | "CW270" | "MirroredVerti
 
 export type P2PDiscoveryState = "Everyone" | "ContactsOnly" | "Disabled"
 
-export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string }
-
-export type PathFrom = "path"
-
-export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; device_model: HardwareModel | null; version: string | null }
-
-export type PlusCode = string
-
-export type Port = null | number
-
-export type Range<T> = { from: T } | { to: T }
-
+export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string } | { type: "PairingRequest"; id: string; identity: RemoteIdentity; peer_name: string; 
 /**
- * A reference to a `CacheNode`.
- * 
- * This does not contain the actual data, but instead a reference to it.
- * This allows the CacheNode's to be switched out and the query recomputed without any backend communication.
- * 
- * If you use a `Reference` in a query, you *must* ensure the corresponding `CacheNode` is also in the query.
+ * To compare with the one shown on the other node
  */
-export type Reference<T> = { __type: string; __id: string; "#type": T }
-
-export type RemoteIdentity = string
-
-export type RenameFileArgs = { location_id: number; kind: RenameKind }
+code: string } | { type: "PairingComplete"; id: string; identity: RemoteIdentity; 
+/**
+ * If both nodes accepted
+ */
+paired: boolean }
 
-export type RenameKind = { One: RenameOne } | { Many: RenameMany }
+export type PairedNode = { identity: RemoteIdentity; 
+/**
+ * As it was when paired
+ */
+name: string }
 
-export type RenameMany = { from_pattern: FromPattern; to_pattern: string; from_file_path_ids: number[] }
+/**
+ * The nodes this one is paired with, stored in the node preferences.
+ */
+export type PairingPreferences = { paired?: PairedNode[] }
 
-export type RenameOne = { from_file_path_id: number; to: string }
+export type PairingStarted = { 
+/**
+ * To confirm the pairing with, like the one of [`P2PEvent::PairingRequest`] on the other node
+ */
+id: string; code: string }
+
+export type PathFrom = "path" | 
+/**
+ * A FTP server, connected to with the FTP credentials of the search
+ */
+"ftp" | 
+/**
+ * A S3 bucket, or one of a service compatible with S3 like MinIO
+ */
+{ s3: { bucket: string; 
+/**
+ * `us-east-1` if missing, which the services compatible with S3 usually accept
+ */
+region?: string | null; 
+/**
+ * The URL of a service compatible with S3, like `http://localhost:9000` for MinIO
+ */
+endpoint?: string | null } } | 
+/**
+ * The SSH server of a remote location, whose files are read over SFTP with the connection
+ * stored for the location
+ */
+{ sftp: { locationId: number } } | 
+/**
+ * A WebDAV server, like the ones of Nextcloud and ownCloud
+ */
+{ webdav: { 
+/**
+ * Like `https://cloud.example.com/remote.php/dav/files/user` for Nextcloud
+ */
+endpoint: string; 
+/**
+ * The server is read anonymously if missing
+ */
+user?: string | null; 
+/**
+ * Not kept anywhere, unlike the passwords of the remote locations
+ */
+password?: string | null } }
+
+export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; device_model: HardwareModel | null; version: string | null }
+
+export type PeerMetrics = { identity: RemoteIdentity; 
+/**
+ * Since the node started, so transfer speeds are computed between two calls
+ */
+bytesSent: string; bytesReceived: string; activeStreams: number; 
+/**
+ * Of the last ping of the peer
+ */
+rttMs: number | null }
+
+/**
+ * What the plugin is allowed to use, anything not listed here is denied.
+ */
+export type PluginCapabilities = { 
+/**
+ * Hosts the plugin can send HTTP requests to, wildcards like `*.example.com` are supported
+ */
+allowedHosts?: string[]; maxMemoryMib?: number; 
+/**
+ * Time a single call can take before the plugin is interrupted
+ */
+timeoutSecs?: number; 
+/**
+ * Files bigger than this are skipped instead of being given to the plugin
+ */
+maxFileSizeMib?: number }
+
+export type PluginExports = { extractMetadata: boolean; generateThumbnail: boolean; searchFilter: boolean }
+
+export type PluginInfo = { manifest: PluginManifest; exports: PluginExports }
+
+/**
+ * The `plugin.json` file of a plugin.
+ */
+export type PluginManifest = { 
+/**
+ * Unique name of the plugin, used to store its metadata
+ */
+name: string; version: string; description?: string | null; 
+/**
+ * Path of the WASM module, relative to the plugin directory
+ */
+wasm: string; 
+/**
+ * Extensions of the files given to the plugin, without the dot
+ */
+extensions?: string[]; searchFilters?: PluginSearchFilter[]; capabilities?: PluginCapabilities }
+
+/**
+ * Matches objects with a metadata extracted by a plugin, with any value if none is given.
+ */
+export type PluginMetadataFilter = { plugin: string; key: string; value?: TextMatch | null }
+
+export type PluginPreferences = { 
+/**
+ * The capabilities approved by the user, by plugin name
+ */
+grants?: { [key in string]: PluginCapabilities } }
+
+export type PluginSearchFilter = { name: string; description?: string | null }
+
+export type PluginSearchFilterArgs = { plugin: string; filter: string; query: string }
+
+export type PlusCode = string
+
+export type PolicyReason = "lowBattery" | "onBattery" | "meteredNetwork" | "quietHours"
+
+export type Port = null | number
+
+export type PruneHistoryArgs = { 
+/**
+ * Only count the jobs that would be removed
+ */
+dry_run?: boolean }
+
+/**
+ * A daily time range in local time, wrapping around midnight if it ends before it starts.
+ */
+export type QuietHours = { 
+/**
+ * Minutes after midnight
+ */
+startMinute: number; 
+/**
+ * Minutes after midnight, excluded from the range
+ */
+endMinute: number }
+
+export type Range<T> = { from: T } | { to: T }
+
+/**
+ * A reference to a `CacheNode`.
+ * 
+ * This does not contain the actual data, but instead a reference to it.
+ * This allows the CacheNode's to be switched out and the query recomputed without any backend communication.
+ * 
+ * If you use a `Reference` in a query, you *must* ensure the corresponding `CacheNode` is also in the query.
+ */
+export type Reference<T> = { __type: string; __id: string; "#type": T }
+
+export type RemoteAdminGrant = { identity: RemoteIdentity; permission: RemoteAdminPermission }
+
+export type RemoteAdminPermission = 
+/**
+ * Viewing the jobs, logs and settings of the node
+ */
+"view" | 
+/**
+ * Also rescanning locations and changing the settings of the node
+ */
+"manage"
+
+/**
+ * Nodes allowed to administrate this one, they must also be instances of one of our libraries.
+ */
+export type RemoteAdminPreferences = { grants?: RemoteAdminGrant[] }
+
+/**
+ * The credentials of a FTP server or S3 bucket given along with the [`PathFrom`] of a search, as
+ * they're not kept anywhere.
+ */
+export type RemoteCredentials = { ftp: FtpCredentials } | { s3: S3Credentials }
+
+export type RemoteIdentity = string
+
+/**
+ * `RemoteLocationCreateArgs` is the argument received from the client using `rspc` to create a
+ * location for a directory of a remote server. Unlike the locations on this node, nothing is
+ * written to the directory, and the connection is checked before creating it.
+ */
+export type RemoteLocationCreateArgs = { 
+/**
+ * The name of the directory if missing
+ */
+name?: string | null; source: RemoteSource; 
+/**
+ * The absolute path of the directory on the server
+ */
+path: string; 
+/**
+ * Kept in the keyring of this node, for the sources logging in with one
+ */
+password?: string | null; indexer_rules_ids: number[] }
+
+/**
+ * The preferences that can be changed remotely, the missing ones are left untouched.
+ * 
+ * The ones granting trust or running code, like the remote administration grants, the paired
+ * nodes, the API tokens and the script hooks, can only be changed on the node itself.
+ */
+export type RemoteNodePreferencesUpdate = { thumbnailer?: ThumbnailerPreferences | null; jobs?: JobsPreferences | null; background_policy?: BackgroundPolicyPreferences | null; search?: SearchPreferences | null }
+
+export type RemoteNodeSettings = { name: string; preferences: NodePreferences }
+
+/**
+ * Settings changed on the remote node, the missing ones are left untouched.
+ */
+export type RemoteNodeSettingsUpdate = { name?: string | null; preferences?: RemoteNodePreferencesUpdate | null }
+
+export type RemoteRescanArgs = { identity: RemoteIdentity; libraryId: string; locationId: number }
+
+/**
+ * The server the files of a remote location are read from, kept as JSON in its `remote` column.
+ */
+export type RemoteSource = ({ type: "sftp" } & SftpConnection) | ({ type: "webdav" } & WebdavConnection)
+
+export type RenameFileArgs = { location_id: number; kind: RenameKind }
+
+export type RenameKind = { One: RenameOne } | { Many: RenameMany }
+
+export type RenameMany = { from_pattern: FromPattern; to_pattern: string; from_file_path_ids: number[] }
+
+export type RenameOne = { from_file_path_id: number; to: string }
+
+export type RequestThumbnailArgs = { source: ThumbnailSource; tier: ThumbnailTier }
 
 export type RescanArgs = { location_id: number; sub_path: string }
 
@@ -566,20 +1746,147 @@ export type Resolution = { width: number; height: number }
 
 export type Response = { Start: { user_code: string; verification_url: string; verification_url_complete: string } } | "Complete" | { Error: string }
 
-export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent"
+/**
+ * How a job type should be retried when it fails.
+ */
+export type RetryPolicy = { 
+/**
+ * Total number of runs a job can have, including the first one
+ */
+maxAttempts: number; backoff?: Backoff; 
+/**
+ * Only errors from these classes will trigger a retry
+ */
+retryOn?: JobErrorClass[] }
+
+export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent" | "AcceptFilesByExtension" | "RejectFilesByExtension" | "AcceptFilesBySize" | "RejectFilesBySize" | "AcceptFilesByAge" | "RejectFilesByAge" | "RejectIgnoredByGitIgnore" | "RejectIgnoredBySdIgnore"
+
+export type S3Credentials = { accessKeyId: string; secretAccessKey: string }
+
+export type SavedSearch = { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null; is_live: boolean | null }
+
+export type SavedSearchSettings = { explorer: ExplorerSettings<FilePathOrder> }
+
+/**
+ * A shell command run by the node when events of the library happen, stored in the library
+ * config.
+ */
+export type ScriptHook = { id: string; name: string; 
+/**
+ * Run with `sh -c`, or `cmd /C` on Windows, receiving the same JSON body as webhooks on stdin
+ */
+command: string; 
+/**
+ * Events running this hook, matched like the ones of webhooks
+ */
+events: WebhookEventFilter[]; 
+/**
+ * The hook is killed if it runs for longer
+ */
+timeoutSecs?: number }
 
-export type SavedSearch = { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null }
+/**
+ * Hooks run arbitrary commands as the user running the node, so they have to be turned on for
+ * the node before any of them can be created or run.
+ */
+export type ScriptHookPreferences = { enabled?: boolean }
 
-export type SearchData<T> = { cursor: number[] | null; items: Reference<T>[]; nodes: CacheNode[] }
+export type SearchData<T, C> = { 
+/**
+ * The `orderAndPagination` of the next page, missing once there are no more
+ */
+cursor: C | null; items: Reference<T>[]; nodes: CacheNode[]; 
+/**
+ * Amount of items matching the filters, counted in the same round trip as the page when
+ * `withCount` is set
+ */
+count?: number }
 
 export type SearchFilterArgs = { filePath: FilePathFilterArgs } | { object: ObjectFilterArgs }
 
+export type SearchHistory = { id: number; query: string; count: number; date_searched: string }
+
+/**
+ * Whether the searches are recorded, stored in the library config.
+ */
+export type SearchHistorySettings = { enabled: boolean }
+
+export type SearchPreferences = { 
+/**
+ * Most objects returned in a single page of `search.objects`
+ */
+max_objects_take: number }
+
+export type SearchSuggestion = 
+/**
+ * A search run before
+ */
+{ kind: "history"; text: string } | { kind: "tag"; text: string } | 
+/**
+ * The name of a file path, without its extension
+ */
+{ kind: "fileName"; text: string }
+
 export type SearchTarget = "paths" | "objects"
 
+export type SetConcurrencyLimitArgs = { job_name: string; limit: number | null }
+
 export type SetFavoriteArgs = { id: number; favorite: boolean }
 
+export type SetGrantArgs = { identity: RemoteIdentity; 
+/**
+ * Revokes the access of the node if missing
+ */
+permission: RemoteAdminPermission | null }
+
 export type SetNoteArgs = { id: number; note: string | null }
 
+export type SetPriorityArgs = { id: string; priority: JobPriority }
+
+export type SetRatingArgs = { id: number; 
+/**
+ * From 1 to 5 stars, or `None` to remove the rating
+ */
+rating: number | null }
+
+export type SetResourceLimitsArgs = { job_name: string; limits: JobResourceLimits }
+
+export type SetRetryPolicyArgs = { job_name: string; policy: RetryPolicy | null }
+
+export type SetValidationScheduleArgs = { locationId: number; 
+/**
+ * Days between the validations, like 30 to validate monthly, or `None` to only
+ * validate on demand
+ */
+intervalDays: number | null }
+
+/**
+ * How to log into the SSH server. Passwords aren't supported, as OpenDAL connects through the
+ * `ssh` binary of this node, which can't be given one.
+ */
+export type SftpAuth = 
+/**
+ * The keys of the SSH agent and of the `ssh` configuration of this node
+ */
+{ type: "agent" } | 
+/**
+ * A private key file on this node
+ */
+{ type: "key"; path: string }
+
+/**
+ * A SSH server whose files are read over SFTP.
+ */
+export type SftpConnection = { host: string; 
+/**
+ * 22 if missing
+ */
+port?: number | null; 
+/**
+ * The user of the `ssh` configuration of this node if missing
+ */
+user?: string | null; auth: SftpAuth }
+
 export type SingleInvalidateOperationEvent = { 
 /**
  * This fields are intentionally private.
@@ -594,13 +1901,71 @@ export type Statistics = { id: number; date_captured: string; total_object_count
 
 export type StatisticsResponse = { statistics: Statistics | null }
 
+export type StreamedPathsArgs = { order?: FilePathOrder | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean }
+
+export type StreamedPathsBatch = { items: Reference<ExplorerItem>[]; errors: string[]; nodes: CacheNode[] }
+
+export type SuggestArgs = { query: string; take?: number | null }
+
 export type SyncStatus = { ingest: boolean; cloud_send: boolean; cloud_receive: boolean; cloud_ingest: boolean }
 
+/**
+ * What the machine is going through, as far as we can tell.
+ */
+export type SystemConditions = { 
+/**
+ * Missing on machines without a battery, or where we can't read it
+ */
+battery: BatteryStatus | null; meteredNetworkDetected: boolean }
+
 export type SystemLocations = { desktop: string | null; documents: string | null; downloads: string | null; pictures: string | null; music: string | null; videos: string | null }
 
-export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null }
+export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null; parent_id: number | null }
+
+export type TagCreateArgs = { name: string; color: string; 
+/**
+ * Nests the new tag under this one
+ */
+parent_id?: number | null }
+
+export type TagReparentArgs = { id: number; 
+/**
+ * Makes it a top level tag if `None`
+ */
+parent_id: number | null }
+
+export type TagRule = { id: number; filters: string; remove_unmatched: boolean; enabled: boolean; date_created: string | null; date_modified: string | null; tag_id: number }
+
+export type TagRuleAction = { id: number; applied: boolean; date: string; rule_id: number; object_id: number }
+
+export type TagRuleActions = { 
+/**
+ * The latest first
+ */
+items: TagRuleAction[]; 
+/**
+ * Missing on the last page
+ */
+cursor: number | null }
+
+export type TagRuleActionsArgs = { ruleId: number; 
+/**
+ * Amount of actions in the page, up to 100 which is also the default
+ */
+take?: number | null; 
+/**
+ * The `cursor` returned with the previous page, missing for the first page
+ */
+cursor?: number | null }
+
+export type TagRuleCreateArgs = { tagId: number; filters: SearchFilterArgs[]; 
+/**
+ * Also removes the tag from the objects the rule applied it to once they stop
+ * matching, `true` by default
+ */
+removeUnmatched?: boolean | null }
 
-export type TagCreateArgs = { name: string; color: string }
+export type TagRuleUpdateArgs = { id: number; filters?: SearchFilterArgs[] | null; removeUnmatched?: boolean | null; enabled?: boolean | null }
 
 export type TagSettings = { explorer: ExplorerSettings<ObjectOrder> }
 
@@ -608,14 +1973,214 @@ export type TagUpdateArgs = { id: number; name: string | null; color: string | n
 
 export type Target = { Object: number } | { FilePath: number }
 
+export type TelemetryPreferences = { 
+/**
+ * Base URL of the OTLP/HTTP collector, like `http://localhost:4318`, traces aren't exported
+ * if missing
+ */
+otlp_endpoint?: string | null }
+
 export type TestingParams = { id: string; path: string }
 
 export type TextMatch = { contains: string } | { startsWith: string } | { endsWith: string } | { equals: string }
 
-export type ThumbnailerPreferences = { background_processing_percentage: number }
+/**
+ * How much space the thumbnails take on disk, for all libraries
+ */
+export type ThumbnailCacheStats = { sizeInBytes: string; count: number; 
+/**
+ * In MiB, thumbnails are never evicted if `None`
+ */
+budgetMb: number | null }
+
+/**
+ * How the thumbnails of a library are generated, stored in the library config.
+ */
+export type ThumbnailSettings = { 
+/**
+ * Videos get a short looping thumbnail of frames sampled across them, instead of a single frame
+ */
+animatedVideos: boolean }
+
+export type ThumbnailSource = 
+/**
+ * A file indexed in one of the library's locations
+ */
+{ type: "indexed"; id: number } | 
+/**
+ * A file outside of the library's locations, shown through an ephemeral path
+ */
+{ type: "ephemeral"; path: string }
+
+/**
+ * The sizes thumbnails are generated at. Every thumbnailable file gets a [`ThumbnailTier::Grid`]
+ * one, while the bigger ones are only generated when requested, as they take much more time and space.
+ */
+export type ThumbnailTier = 
+/**
+ * Shown on the explorer's grid and list views
+ */
+"grid" | 
+/**
+ * Shown on the inspector and on big grid items
+ */
+"detail" | 
+/**
+ * Shown on the quick preview, in place of the original file when it can't be rendered
+ */
+"preview"
+
+export type ThumbnailerPreferences = { background_processing_percentage: number; cache_budget_mb: number | null }
+
+/**
+ * How long deleted files are kept, stored in the library config.
+ */
+export type TrashSettings = { 
+/**
+ * Days after which trashed files are purged, they're kept until the trash is emptied if `None`
+ */
+retentionDays: number | null }
+
+export type TrashedFile = { id: number; pub_id: number[]; original_path: string; is_dir: boolean; size_in_bytes_bytes: number[] | null; date_deleted: string; location_id: number }
+
+export type UpdateSearchPreferences = { 
+/**
+ * Clamped between 1 and 10000
+ */
+max_objects_take: number }
+
+export type UpdateSettingsArgs = { identity: RemoteIdentity; update: RemoteNodeSettingsUpdate }
 
 export type UpdateThumbnailerPreferences = { background_processing_percentage: number }
 
+export type UpdateWebhookArgs = { id: string; url: string; 
+/**
+ * `null` removes the secret and a missing field leaves it untouched
+ */
+secret?: MaybeUndefined<string>; events: WebhookEventFilter[]; maxRetries: number }
+
+export type UsageTreeArgs = { locationId: number; 
+/**
+ * Directory at the root of the tree, the whole location if missing
+ */
+path?: string | null; 
+/**
+ * Levels of sub directories in the tree, at most 8
+ */
+depth: number }
+
+/**
+ * A directory with the size and count of every file under it, for treemaps of the storage used by
+ * a location.
+ */
+export type UsageTreeNode = { name: string; 
+/**
+ * Relative to the location, like `/photos/2024/`
+ */
+materializedPath: string; sizeInBytes: string; fileCount: number; 
+/**
+ * Biggest first, missing beyond the depth of the tree, where the whole directory is counted
+ * in its deepest ancestor
+ */
+children: UsageTreeNode[] }
+
+/**
+ * A view whose defaults are stored in the preferences of the library.
+ */
+export type ValidationMismatch = { kind: MismatchKind; filePathId: number; path: string; expectedCasId: string; 
+/**
+ * Missing if the file couldn't be found
+ */
+actualCasId: string | null; dateDetected: string }
+
+export type ValidationReport = { 
+/**
+ * Days between the scheduled validations, they're only run on demand if missing
+ */
+intervalDays: number | null; dateValidated: string | null; mismatches: ValidationMismatch[] }
+
+export type ViewDefaults = { type: "location"; id: number } | { type: "savedSearch"; id: number }
+
 export type VideoMetadata = { duration: number | null; video_codec: string | null; audio_codec: string | null }
 
 export type Volume = { name: string; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean }
+
+/**
+ * A WebDAV server, like the ones of Nextcloud and ownCloud.
+ */
+export type WebdavConnection = { 
+/**
+ * Like `https://cloud.example.com/remote.php/dav/files/user` for Nextcloud
+ */
+endpoint: string; 
+/**
+ * The server is read anonymously if missing
+ */
+user?: string | null }
+
+/**
+ * A webhook of the library, stored in the library config.
+ */
+export type Webhook = { id: string; 
+/**
+ * URL receiving a HTTP POST request with a [`WebhookPayload`] as JSON body
+ */
+url: string; 
+/**
+ * Signs the body with HMAC-SHA256, sent hex encoded in the `X-Spacedrive-Signature` header
+ */
+secret?: string | null; 
+/**
+ * Events sending a request to this webhook
+ */
+events: WebhookEventFilter[]; 
+/**
+ * Times a failed request is retried, with an exponential backoff
+ */
+maxRetries?: number }
+
+export type WebhookEventFilter = 
+/**
+ * Files indexed in the library, any file matches the empty filters
+ */
+{ type: "filesAdded"; locationIds?: number[]; 
+/**
+ * Lowercase extensions, without the dot
+ */
+extensions?: string[] } | 
+/**
+ * Jobs that finished, every job and status match the empty filters
+ */
+{ type: "jobFinished"; jobNames?: string[]; statuses?: JobStatus[] } | 
+/**
+ * A new device joined the library
+ */
+{ type: "devicePaired" }
+
+/**
+ * A webhook as listed to the clients, which never get its secret back.
+ */
+export type WebhookInfo = { id: string; url: string; hasSecret: boolean; events: WebhookEventFilter[]; maxRetries: number }
+
+export type WorkMode = "normal" | 
+/**
+ * Keep going, using as few resources as possible
+ */
+"throttled" | 
+/**
+ * Wait until the policy allows work again
+ */
+"paused"
+
+/**
+ * How sidecars are named, as apps look for them in different places.
+ */
+export type XmpSidecarNaming = 
+/**
+ * `photo.jpg.xmp`, used by darktable and digiKam
+ */
+"appendExtension" | 
+/**
+ * `photo.xmp`, used by Lightroom and Capture One
+ */
+"replaceExtension"
@@ -4,6 +4,8 @@ export * from './useExplorerInfiniteQuery';
 export * from './usePathsInfiniteQuery';
 export * from './usePathsOffsetInfiniteQuery';
 export * from './usePathsExplorerQuery';
+export * from './usePathsLiveQuery';
+export * from './usePathsStreamedQuery';
 export * from './useObjectsInfiniteQuery';
 export * from './useObjectsOffsetInfiniteQuery';
 export * from './useObjectsExplorerQuery';
@@ -7,4 +7,4 @@ export type UseExplorerInfiniteQueryArgs<TArg, TOrder extends Ordering> = {
 	arg: TArg;
 	order: TOrder | null;
 	onSuccess?: () => void;
-} & Pick<UseInfiniteQueryOptions<SearchData<ExplorerItem>>, 'enabled' | 'suspense'>;
+} & Pick<UseInfiniteQueryOptions<SearchData<ExplorerItem, unknown>>, 'enabled' | 'suspense'>;
@@ -1,13 +1,10 @@
-import { UseInfiniteQueryResult, UseQueryResult } from '@tanstack/react-query';
+import { UseInfiniteQueryResult } from '@tanstack/react-query';
 import { useCallback, useMemo } from 'react';
 
 import { useCache } from '../cache';
 import { SearchData } from '../core';
 
-export function useExplorerQuery<Q>(
-	query: UseInfiniteQueryResult<SearchData<Q>>,
-	count: UseQueryResult<number>
-) {
+export function useExplorerQuery<Q>(query: UseInfiniteQueryResult<SearchData<Q, unknown>>) {
 	const items = useMemo(() => query.data?.pages.flatMap((d) => d.items) ?? null, [query.data]);
 
 	const loadMore = useCallback(() => {
@@ -16,7 +13,7 @@ export function useExplorerQuery<Q>(
 		}
 	}, [query.hasNextPage, query.isFetchingNextPage, query.fetchNextPage]);
 
-	return { query, items: useCache(items), loadMore, count: count.data };
+	return { query, items: useCache(items), loadMore, count: query.data?.pages[0]?.count };
 }
 
 export type UseExplorerQuery<Q> = ReturnType<typeof useExplorerQuery<Q>>;
@@ -1,5 +1,4 @@
 import { ObjectOrder, ObjectSearchArgs } from '../core';
-import { useLibraryQuery } from '../rspc';
 import { UseExplorerInfiniteQueryArgs } from './useExplorerInfiniteQuery';
 import { useExplorerQuery } from './useExplorerQuery';
 import { useObjectsOffsetInfiniteQuery } from './useObjectsOffsetInfiniteQuery';
@@ -9,9 +8,5 @@ export function useObjectsExplorerQuery(
 ) {
 	const query = useObjectsOffsetInfiniteQuery(props);
 
-	const count = useLibraryQuery(['search.objectsCount', { filters: props.arg.filters }], {
-		enabled: query.isSuccess
-	});
-
-	return useExplorerQuery(query, count);
+	return useExplorerQuery(query);
 }
@@ -2,7 +2,7 @@ import { useInfiniteQuery } from '@tanstack/react-query';
 import { useMemo } from 'react';
 
 import { useNodes } from '../cache';
-import { ExplorerItem, ObjectCursor, ObjectOrder, ObjectSearchArgs } from '../core';
+import { ObjectOrder, ObjectSearchArgs } from '../core';
 import { useLibraryContext } from '../hooks';
 import { useRspcLibraryContext } from '../rspc';
 import { UseExplorerInfiniteQueryArgs } from './useExplorerInfiniteQuery';
@@ -22,43 +22,13 @@ export function useObjectsInfiniteQuery({
 	const query = useInfiniteQuery({
 		queryKey: ['search.objects', { library_id: library.uuid, arg }] as const,
 		queryFn: ({ pageParam, queryKey: [_, { arg }] }) => {
-			const cItem: Extract<ExplorerItem, { type: 'Object' }> = pageParam;
+			// The server returns the `orderAndPagination` of the next page along with each page
+			const orderAndPagination: (typeof arg)['orderAndPagination'] =
+				pageParam ?? (order ? { orderOnly: order } : undefined);
 
-			let orderAndPagination: (typeof arg)['orderAndPagination'];
-
-			if (!cItem) {
-				if (order) orderAndPagination = { orderOnly: order };
-			} else {
-				let cursor: ObjectCursor | undefined;
-
-				if (!order) cursor = 'none';
-				else if (cItem) {
-					switch (order.field) {
-						case 'kind': {
-							const data = cItem.item.kind;
-							if (data !== null) cursor = { kind: { order: order.value, data } };
-							break;
-						}
-						case 'dateAccessed': {
-							const data = cItem.item.date_accessed;
-							if (data !== null)
-								cursor = { dateAccessed: { order: order.value, data } };
-							break;
-						}
-					}
-				}
-
-				if (cursor) orderAndPagination = { cursor: { cursor, id: cItem.item.id } };
-			}
-
-			arg.orderAndPagination = orderAndPagination;
-
-			return ctx.client.query(['search.objects', arg]);
-		},
-		getNextPageParam: (lastPage) => {
-			if (lastPage.items.length < arg.take) return undefined;
-			else return lastPage.nodes[arg.take - 1];
+			return ctx.client.query(['search.objects', { ...arg, orderAndPagination }]);
 		},
+		getNextPageParam: (lastPage) => lastPage.cursor ?? undefined,
 		...args
 	});
 
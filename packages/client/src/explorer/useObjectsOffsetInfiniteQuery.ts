@@ -12,6 +12,8 @@ export function useObjectsOffsetInfiniteQuery({
 	order,
 	...args
 }: UseExplorerInfiniteQueryArgs<ObjectSearchArgs, ObjectOrder>) {
+	const take = arg.take ?? 100;
+
 	const { library } = useLibraryContext();
 	const ctx = useRspcLibraryContext();
 	const cache = useNormalisedCache();
@@ -21,7 +23,13 @@ export function useObjectsOffsetInfiniteQuery({
 	}
 
 	const query = useInfiniteQuery({
-		queryKey: ['search.objects', { library_id: library.uuid, arg }] as const,
+		queryKey: [
+			'search.objects',
+			{
+				library_id: library.uuid,
+				arg: { ...arg, take }
+			}
+		] satisfies [any, any],
 		queryFn: async ({ pageParam, queryKey: [_, { arg }] }) => {
 			let orderAndPagination: (typeof arg)['orderAndPagination'];
 
@@ -38,7 +46,11 @@ export function useObjectsOffsetInfiniteQuery({
 
 			arg.orderAndPagination = orderAndPagination;
 
-			const result = await ctx.client.query(['search.objects', arg]);
+			// The first page is counted along with it, instead of with another query
+			const result = await ctx.client.query([
+				'search.objects',
+				{ ...arg, withCount: !pageParam, prefetch: true }
+			]);
 			cache.withNodes(result.nodes);
 
 			return { ...result, offset: pageParam, arg };
@@ -1,5 +1,4 @@
 import { FilePathOrder, FilePathSearchArgs } from '../core';
-import { useLibraryQuery } from '../rspc';
 import { useExplorerQuery } from './useExplorerQuery';
 import { usePathsOffsetInfiniteQuery } from './usePathsOffsetInfiniteQuery';
 
@@ -11,9 +10,5 @@ export function usePathsExplorerQuery(props: {
 }) {
 	const query = usePathsOffsetInfiniteQuery(props);
 
-	const count = useLibraryQuery(['search.pathsCount', { filters: props.arg.filters }], {
-		enabled: query.isSuccess
-	});
-
-	return useExplorerQuery(query, count);
+	return useExplorerQuery(query);
 }
@@ -0,0 +1,61 @@
+import { useQuery, useQueryClient } from '@tanstack/react-query';
+import { useMemo } from 'react';
+
+import { applyDelta, useCache, useNormalisedCache } from '../cache';
+import { ExplorerItem, FilePathOrder, FilePathSearchArgs, LivePathsArgs, Reference } from '../core';
+import { useLibraryContext } from '../hooks';
+import { useLibrarySubscription } from '../rspc';
+
+type LivePaths = { items: Reference<ExplorerItem>[] };
+
+// The first page of `search.paths`, which the core keeps up to date as the file paths change
+// instead of the search being run again.
+export function usePathsLiveQuery({
+	arg,
+	order,
+	enabled = true
+}: {
+	arg: FilePathSearchArgs;
+	order: FilePathOrder | null;
+	enabled?: boolean;
+}) {
+	const { library } = useLibraryContext();
+	const queryClient = useQueryClient();
+	const cache = useNormalisedCache();
+
+	const liveArg = useMemo<LivePathsArgs>(
+		() => ({
+			take: arg.take,
+			order,
+			filters: arg.filters,
+			groupDirectories: arg.groupDirectories
+		}),
+		[arg.take, arg.filters, arg.groupDirectories, order]
+	);
+
+	const queryKey = useMemo(
+		() => ['search.pathsLive', { library_id: library.uuid, arg: liveArg }] as const,
+		[library.uuid, liveArg]
+	);
+
+	// The results are kept in React Query, so the cleanup of the normalised cache keeps their nodes.
+	// They only come from the subscription, the query is never fetched.
+	const query = useQuery<LivePaths>({
+		queryKey,
+		queryFn: () => queryClient.getQueryData<LivePaths>(queryKey) ?? { items: [] },
+		enabled: false,
+		staleTime: Infinity
+	});
+
+	useLibrarySubscription(['search.pathsLive', liveArg], {
+		enabled,
+		onData(delta) {
+			cache.withNodes(delta.nodes);
+			queryClient.setQueryData<LivePaths>(queryKey, (data) => ({
+				items: applyDelta(data?.items ?? [], delta)
+			}));
+		}
+	});
+
+	return { items: useCache(query.data?.items ?? null), isLoading: query.data === undefined };
+}
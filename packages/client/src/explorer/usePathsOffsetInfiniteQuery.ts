@@ -21,7 +21,6 @@ export function usePathsOffsetInfiniteQuery({
 
 	if (order) {
 		arg.orderAndPagination = { orderOnly: order };
-		if (arg.orderAndPagination.orderOnly.field === 'sizeInBytes') delete arg.take;
 	}
 
 	const query = useInfiniteQuery({
@@ -48,7 +47,11 @@ export function usePathsOffsetInfiniteQuery({
 
 			arg.orderAndPagination = orderAndPagination;
 
-			const result = await ctx.client.query(['search.paths', arg]);
+			// The first page is counted along with it, instead of with another query
+			const result = await ctx.client.query([
+				'search.paths',
+				{ ...arg, withCount: !pageParam, prefetch: true }
+			]);
 			cache.withNodes(result.nodes);
 
 			return { ...result, offset: pageParam, arg };
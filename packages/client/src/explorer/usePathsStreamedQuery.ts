@@ -0,0 +1,52 @@
+import { useMemo } from 'react';
+
+import { useCache, useNormalisedCache } from '../cache';
+import { FilePathOrder, FilePathSearchArgs, StreamedPathsArgs } from '../core';
+import { useLibraryContext } from '../hooks';
+import { useUnsafeStreamedQuery } from '../rspc-cursed';
+
+// All of the results of `search.paths`, rendered as the batches are read instead of once they
+// all are, for directories too big to page through.
+export function usePathsStreamedQuery({
+	arg,
+	order,
+	enabled = true
+}: {
+	arg: FilePathSearchArgs;
+	order: FilePathOrder | null;
+	enabled?: boolean;
+}) {
+	const { library } = useLibraryContext();
+	const cache = useNormalisedCache();
+
+	const streamedArg = useMemo<StreamedPathsArgs>(
+		() => ({ order, filters: arg.filters, groupDirectories: arg.groupDirectories }),
+		[arg.filters, arg.groupDirectories, order]
+	);
+
+	const query = useUnsafeStreamedQuery(
+		['search.pathsStreamed', { library_id: library.uuid, arg: streamedArg }],
+		{
+			enabled,
+			onBatch: (batch) => {
+				cache.withNodes(batch.nodes);
+			}
+		}
+	);
+
+	// The streamed batches are pushed into the same array, so its length tells when one arrives
+	const batches = query.data ?? query.streaming;
+	const batchCount = batches.length;
+
+	const items = useCache(
+		// eslint-disable-next-line react-hooks/exhaustive-deps
+		useMemo(() => batches.flatMap((batch) => batch.items), [batches, batchCount])
+	);
+	const errors = useMemo(
+		() => batches.flatMap((batch) => batch.errors),
+		// eslint-disable-next-line react-hooks/exhaustive-deps
+		[batches, batchCount]
+	);
+
+	return { query, items, errors, isStreaming: query.isFetching };
+}
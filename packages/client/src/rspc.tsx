@@ -131,3 +131,27 @@ export function extractInfoRSPCError(error: unknown) {
 	if (!(error instanceof AlphaRSPCError)) return null;
 	return error;
 }
+
+/**
+ * Structured error sent by the core as the message of an rspc error,
+ * built from `ApiError` in `core/src/api/error.rs`.
+ */
+export interface ApiErrorInfo {
+	code: string;
+	message: string;
+	context: Record<string, unknown>;
+}
+
+export function extractApiError(error: unknown): ApiErrorInfo | null {
+	const rspcError = extractInfoRSPCError(error);
+	if (!rspcError) return null;
+
+	try {
+		const payload = JSON.parse(rspcError.message);
+		if (typeof payload?.code === 'string') return payload as ApiErrorInfo;
+	} catch {
+		// Errors without a code are sent as plain messages
+	}
+
+	return null;
+}